@@ -0,0 +1,69 @@
+//! secp256k1 signing and recovery as used by transactions and `ecrecover`.
+
+use ethereum_types::{Address, H256, U256};
+use keccak_hash::keccak;
+use secp256k1::{
+    ecdsa::{RecoverableSignature, RecoveryId},
+    Message, PublicKey, SecretKey, SECP256K1,
+};
+
+/// Half the order of the secp256k1 curve, the highest `s` value a transaction signature
+/// may have (EIP-2).
+pub const SECP256K1N_HALF: U256 = U256([
+    0xdfe92f46681b20a0,
+    0x5d576e7357a4501d,
+    0xffffffffffffffff,
+    0x7fffffffffffffff,
+]);
+
+/// Recovers the address whose key produced the signature `(y_parity, r, s)` over `hash`.
+/// Returns `None` for signatures that do not recover to a valid public key.
+pub fn recover_address(hash: H256, y_parity: bool, r: U256, s: U256) -> Option<Address> {
+    let mut compact = [0; 64];
+    r.to_big_endian(&mut compact[..32]);
+    s.to_big_endian(&mut compact[32..]);
+    let id = RecoveryId::from_i32(y_parity as i32).ok()?;
+    let signature = RecoverableSignature::from_compact(&compact, id).ok()?;
+    let public_key = SECP256K1
+        .recover_ecdsa(&Message::from_digest(hash.0), &signature)
+        .ok()?;
+    Some(public_key_to_address(&public_key))
+}
+
+/// Signs `hash` with `secret_key`, returning the signature as `(y_parity, r, s)`.
+pub fn sign(hash: H256, secret_key: &[u8; 32]) -> Option<(bool, U256, U256)> {
+    let secret_key = SecretKey::from_slice(secret_key).ok()?;
+    let signature = SECP256K1.sign_ecdsa_recoverable(&Message::from_digest(hash.0), &secret_key);
+    let (id, compact) = signature.serialize_compact();
+    Some((
+        id.to_i32() == 1,
+        U256::from_big_endian(&compact[..32]),
+        U256::from_big_endian(&compact[32..]),
+    ))
+}
+
+/// Address controlled by `secret_key`.
+pub fn secret_key_to_address(secret_key: &[u8; 32]) -> Option<Address> {
+    let secret_key = SecretKey::from_slice(secret_key).ok()?;
+    Some(public_key_to_address(&secret_key.public_key(SECP256K1)))
+}
+
+fn public_key_to_address(public_key: &PublicKey) -> Address {
+    Address::from_slice(&keccak(&public_key.serialize_uncompressed()[1..]).0[12..])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recovers_signer() {
+        let secret_key = [0x46; 32];
+        let hash = keccak(b"reec");
+        let (y_parity, r, s) = sign(hash, &secret_key).unwrap();
+        let signer = secret_key_to_address(&secret_key).unwrap();
+        assert_eq!(recover_address(hash, y_parity, r, s), Some(signer));
+        assert_ne!(recover_address(hash, !y_parity, r, s), Some(signer));
+        assert_eq!(recover_address(hash, y_parity, U256::zero(), s), None);
+    }
+}
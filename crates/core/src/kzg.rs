@@ -0,0 +1,206 @@
+//! KZG commitments to blobs (EIP-4844), computed and checked with the mainnet trusted
+//! setup.
+
+use std::fmt;
+
+use c_kzg::{Blob, Bytes32, Bytes48, KzgSettings};
+use ethereum_types::H256;
+use sha2::{Digest, Sha256};
+
+pub const BYTES_PER_BLOB: usize = c_kzg::BYTES_PER_BLOB;
+pub const FIELD_ELEMENTS_PER_BLOB: usize = c_kzg::FIELD_ELEMENTS_PER_BLOB;
+/// Order of the BLS12-381 scalar field, big-endian. Blob field elements are below it.
+pub const BLS_MODULUS: [u8; 32] =
+    hex_literal::hex!("73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001");
+/// First byte of the versioned hash of a KZG commitment.
+pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+
+pub type Commitment = [u8; 48];
+pub type Proof = [u8; 48];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KzgError {
+    /// A blob, commitment, proof or field element does not decode.
+    Malformed,
+    /// The blob, commitment and proof lists of a batch differ in length.
+    LengthMismatch {
+        blobs: usize,
+        commitments: usize,
+        proofs: usize,
+    },
+}
+
+impl fmt::Display for KzgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KzgError::Malformed => {
+                f.write_str("malformed blob, commitment, proof or field element")
+            }
+            KzgError::LengthMismatch {
+                blobs,
+                commitments,
+                proofs,
+            } => {
+                write!(
+                    f,
+                    "{blobs} blobs, {commitments} commitments and {proofs} proofs"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for KzgError {}
+
+/// The mainnet trusted setup, loaded on first use.
+fn settings() -> &'static KzgSettings {
+    c_kzg::ethereum_kzg_settings(0)
+}
+
+/// Copies blobs to the heap, as they are too large to be moved around on the stack.
+fn to_blobs(blobs: &[&[u8]]) -> Result<Vec<Blob>, KzgError> {
+    let mut copies = Vec::with_capacity(blobs.len());
+    copies.resize_with(blobs.len(), Blob::default);
+    for (copy, bytes) in copies.iter_mut().zip(blobs) {
+        if bytes.len() != BYTES_PER_BLOB {
+            return Err(KzgError::Malformed);
+        }
+        copy.copy_from_slice(bytes);
+    }
+    Ok(copies)
+}
+
+/// What blob transactions commit to in place of a commitment: its SHA-256 hash with the
+/// version as the first byte.
+pub fn versioned_hash(commitment: &Commitment) -> H256 {
+    let mut hash = H256::from_slice(&Sha256::digest(commitment));
+    hash.0[0] = VERSIONED_HASH_VERSION_KZG;
+    hash
+}
+
+pub fn blob_to_commitment(blob_bytes: &[u8]) -> Result<Commitment, KzgError> {
+    let blob = &to_blobs(&[blob_bytes])?[0];
+    let commitment = settings()
+        .blob_to_kzg_commitment(blob)
+        .map_err(|_| KzgError::Malformed)?;
+    Ok(commitment.to_bytes().into_inner())
+}
+
+/// Proof that `blob` is what `commitment` commits to, as sent alongside blob transactions.
+pub fn compute_blob_proof(blob_bytes: &[u8], commitment: &Commitment) -> Result<Proof, KzgError> {
+    let blob = &to_blobs(&[blob_bytes])?[0];
+    let proof = settings()
+        .compute_blob_kzg_proof(blob, &Bytes48::new(*commitment))
+        .map_err(|_| KzgError::Malformed)?;
+    Ok(proof.to_bytes().into_inner())
+}
+
+/// Checks that the polynomial behind `commitment` evaluates to `y` at `z`.
+pub fn verify_proof(
+    commitment: &Commitment,
+    z: &[u8; 32],
+    y: &[u8; 32],
+    proof: &Proof,
+) -> Result<bool, KzgError> {
+    settings()
+        .verify_kzg_proof(
+            &Bytes48::new(*commitment),
+            &Bytes32::new(*z),
+            &Bytes32::new(*y),
+            &Bytes48::new(*proof),
+        )
+        .map_err(|_| KzgError::Malformed)
+}
+
+/// Checks that each blob matches its commitment, all at once. Holds for no blobs.
+pub fn verify_blob_proofs(
+    blobs: &[&[u8]],
+    commitments: &[Commitment],
+    proofs: &[Proof],
+) -> Result<bool, KzgError> {
+    if blobs.len() != commitments.len() || blobs.len() != proofs.len() {
+        return Err(KzgError::LengthMismatch {
+            blobs: blobs.len(),
+            commitments: commitments.len(),
+            proofs: proofs.len(),
+        });
+    }
+    let blobs = to_blobs(blobs)?;
+    let commitments: Vec<_> = commitments
+        .iter()
+        .map(|commitment| Bytes48::new(*commitment))
+        .collect();
+    let proofs: Vec<_> = proofs.iter().map(|proof| Bytes48::new(*proof)).collect();
+    settings()
+        .verify_blob_kzg_proof_batch(&blobs, &commitments, &proofs)
+        .map_err(|_| KzgError::Malformed)
+}
+
+#[cfg(test)]
+mod test {
+    use hex_literal::hex;
+
+    use super::*;
+
+    /// The commitment to, and proof for, the zero polynomial: the point at infinity.
+    const INFINITY: [u8; 48] = {
+        let mut point = [0; 48];
+        point[0] = 0xc0;
+        point
+    };
+
+    #[test]
+    fn commits_to_blobs() {
+        let zero = vec![0; BYTES_PER_BLOB];
+        assert_eq!(blob_to_commitment(&zero), Ok(INFINITY));
+
+        let mut blob = zero.clone();
+        for (i, element) in blob.chunks_exact_mut(32).enumerate() {
+            element[24..].copy_from_slice(&(i as u64).to_be_bytes());
+        }
+        let commitment = blob_to_commitment(&blob).unwrap();
+        let proof = compute_blob_proof(&blob, &commitment).unwrap();
+        assert_eq!(
+            verify_blob_proofs(&[&blob, &zero], &[commitment, INFINITY], &[proof, INFINITY]),
+            Ok(true)
+        );
+        assert_eq!(
+            verify_blob_proofs(&[&zero], &[commitment], &[proof]),
+            Ok(false)
+        );
+        assert_eq!(verify_blob_proofs(&[], &[], &[]), Ok(true));
+        assert_eq!(
+            verify_blob_proofs(&[&blob], &[], &[proof]),
+            Err(KzgError::LengthMismatch {
+                blobs: 1,
+                commitments: 0,
+                proofs: 1
+            })
+        );
+
+        // Field elements must be below the modulus
+        blob[..32].copy_from_slice(&BLS_MODULUS);
+        assert_eq!(blob_to_commitment(&blob), Err(KzgError::Malformed));
+    }
+
+    #[test]
+    fn verifies_point_evaluations() {
+        // verify_kzg_proof_case_correct_proof_4_4 from the c-kzg-4844 reference tests
+        let commitment = hex!("8f59a8d2a1a625a17f3fea0fe5eb8c896db3764f3185481bc22f91b4aaffcca25f26936857bc3a7c2539ea8ec3a952b7");
+        let z = hex!("73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000000");
+        let y = hex!("1522a4a7f34e1ea350ae07c29c96c7e79655aa926122e95fe69fcbd932ca49e9");
+        let proof = hex!("a62ad71d14c5719385c0686f1871430475bf3a00f0aa3f7b8dd99a9abc2160744faf0070725e00b60ad9a026a15b1a8c");
+        assert_eq!(verify_proof(&commitment, &z, &y, &proof), Ok(true));
+        assert_eq!(verify_proof(&commitment, &z, &[0; 32], &proof), Ok(false));
+        assert_eq!(
+            verify_proof(&[0; 48], &z, &y, &proof),
+            Err(KzgError::Malformed)
+        );
+        assert_eq!(
+            versioned_hash(&INFINITY),
+            H256(hex!(
+                "010657f37554c781402a22917dee2f75def7ab966d7b770905398eba3c444014"
+            ))
+        );
+    }
+}
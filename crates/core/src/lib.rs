@@ -1 +1,5 @@
-mod rlp;
\ No newline at end of file
+pub mod crypto;
+pub mod kzg;
+pub mod rlp;
+pub mod trie;
+pub mod types;
@@ -1 +1,4 @@
-mod encode;
\ No newline at end of file
+pub mod decode;
+pub mod encode;
+pub mod error;
+pub mod structs;
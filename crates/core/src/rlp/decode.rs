@@ -0,0 +1,343 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use bytes::Bytes;
+use ethereum_types::U256;
+
+use super::error::RLPDecodeError;
+
+pub trait RLPDecode: Sized {
+    /// Decodes a single item from the front of `rlp`, returning it together with the
+    /// bytes that follow it.
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError>;
+
+    fn decode(rlp: &[u8]) -> Result<Self, RLPDecodeError> {
+        let (decoded, remaining) = Self::decode_unfinished(rlp)?;
+        if !remaining.is_empty() {
+            return Err(RLPDecodeError::InvalidLength);
+        }
+        Ok(decoded)
+    }
+}
+
+impl RLPDecode for bool {
+    #[inline(always)]
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let first = *rlp.first().ok_or(RLPDecodeError::InvalidLength)?;
+        let value = match first {
+            0x80 => false,
+            0x01 => true,
+            _ => return Err(RLPDecodeError::MalformedBoolean),
+        };
+        Ok((value, &rlp[1..]))
+    }
+}
+
+macro_rules! impl_decode_uint {
+    ($($t:ty),*) => {
+        $(
+            impl RLPDecode for $t {
+                fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+                    let (bytes, rest) = decode_bytes(rlp)?;
+                    let padded = static_left_pad::<{ std::mem::size_of::<$t>() }>(bytes)?;
+                    Ok((<$t>::from_be_bytes(padded), rest))
+                }
+            }
+        )*
+    };
+}
+
+impl_decode_uint!(u8, u16, u32, u64, usize, u128);
+
+impl RLPDecode for U256 {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let (bytes, rest) = decode_bytes(rlp)?;
+        let padded = static_left_pad::<32>(bytes)?;
+        Ok((U256::from_big_endian(&padded), rest))
+    }
+}
+
+impl<const N: usize> RLPDecode for [u8; N] {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let (bytes, rest) = decode_bytes(rlp)?;
+        let value = bytes
+            .try_into()
+            .map_err(|_| RLPDecodeError::InvalidLength)?;
+        Ok((value, rest))
+    }
+}
+
+impl RLPDecode for Bytes {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let (bytes, rest) = decode_bytes(rlp)?;
+        Ok((Bytes::copy_from_slice(bytes), rest))
+    }
+}
+
+impl RLPDecode for String {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let (bytes, rest) = decode_bytes(rlp)?;
+        let value = String::from_utf8(bytes.to_vec()).map_err(|_| RLPDecodeError::MalformedData)?;
+        Ok((value, rest))
+    }
+}
+
+macro_rules! impl_decode_hash {
+    ($($t:ty),*) => {
+        $(
+            impl RLPDecode for $t {
+                fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+                    let (bytes, rest) = decode_bytes(rlp)?;
+                    if bytes.len() != <$t>::len_bytes() {
+                        return Err(RLPDecodeError::InvalidLength);
+                    }
+                    Ok((<$t>::from_slice(bytes), rest))
+                }
+            }
+        )*
+    };
+}
+
+impl_decode_hash!(
+    ethereum_types::H32,
+    ethereum_types::H64,
+    ethereum_types::H128,
+    ethereum_types::H256,
+    ethereum_types::H512,
+    ethereum_types::Address,
+    ethereum_types::Signature,
+    ethereum_types::Bloom
+);
+
+impl RLPDecode for Ipv4Addr {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let (octets, rest) = <[u8; 4]>::decode_unfinished(rlp)?;
+        Ok((Ipv4Addr::from(octets), rest))
+    }
+}
+
+impl RLPDecode for Ipv6Addr {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let (octets, rest) = <[u8; 16]>::decode_unfinished(rlp)?;
+        Ok((Ipv6Addr::from(octets), rest))
+    }
+}
+
+impl RLPDecode for IpAddr {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let (bytes, rest) = decode_bytes(rlp)?;
+        let ip = match bytes.len() {
+            4 => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(bytes).unwrap())),
+            16 => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(bytes).unwrap())),
+            _ => return Err(RLPDecodeError::InvalidLength),
+        };
+        Ok((ip, rest))
+    }
+}
+
+impl<T: RLPDecode> RLPDecode for Vec<T> {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let (is_list, mut payload, rest) = decode_rlp_item(rlp)?;
+        if !is_list {
+            return Err(RLPDecodeError::UnexpectedString);
+        }
+        let mut items = Vec::new();
+        while !payload.is_empty() {
+            let (item, remaining) = T::decode_unfinished(payload)?;
+            items.push(item);
+            payload = remaining;
+        }
+        Ok((items, rest))
+    }
+}
+
+impl<S: RLPDecode, T: RLPDecode> RLPDecode for (S, T) {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let (is_list, payload, rest) = decode_rlp_item(rlp)?;
+        if !is_list {
+            return Err(RLPDecodeError::UnexpectedString);
+        }
+        let (first, payload) = S::decode_unfinished(payload)?;
+        let second = T::decode(payload)?;
+        Ok(((first, second), rest))
+    }
+}
+
+/// Splits the first item off `data`, returning whether it is a list, its payload and
+/// the bytes that follow it.
+pub fn decode_rlp_item(data: &[u8]) -> Result<(bool, &[u8], &[u8]), RLPDecodeError> {
+    let first = *data.first().ok_or(RLPDecodeError::InvalidLength)?;
+    match first {
+        0x00..=0x7f => Ok((false, &data[..1], &data[1..])),
+        0x80..=0xb7 => {
+            let len = (first - 0x80) as usize;
+            let (payload, rest) = split_payload(&data[1..], len)?;
+            if len == 1 && payload[0] < 0x80 {
+                return Err(RLPDecodeError::MalformedData);
+            }
+            Ok((false, payload, rest))
+        }
+        0xb8..=0xbf => {
+            let (len, data) = decode_long_length(data, first - 0xb7)?;
+            let (payload, rest) = split_payload(data, len)?;
+            Ok((false, payload, rest))
+        }
+        0xc0..=0xf7 => {
+            let len = (first - 0xc0) as usize;
+            let (payload, rest) = split_payload(&data[1..], len)?;
+            Ok((true, payload, rest))
+        }
+        0xf8..=0xff => {
+            let (len, data) = decode_long_length(data, first - 0xf7)?;
+            let (payload, rest) = split_payload(data, len)?;
+            Ok((true, payload, rest))
+        }
+    }
+}
+
+/// Splits the first item off `data` keeping its prefix, which is what hashing and
+/// re-encoding already encoded items need.
+pub fn get_item_with_prefix(data: &[u8]) -> Result<(&[u8], &[u8]), RLPDecodeError> {
+    let (_, _, rest) = decode_rlp_item(data)?;
+    let item_len = data.len() - rest.len();
+    Ok((&data[..item_len], rest))
+}
+
+/// Decodes a byte string, failing if the item is a list.
+pub fn decode_bytes(data: &[u8]) -> Result<(&[u8], &[u8]), RLPDecodeError> {
+    let (is_list, payload, rest) = decode_rlp_item(data)?;
+    if is_list {
+        return Err(RLPDecodeError::UnexpectedList);
+    }
+    Ok((payload, rest))
+}
+
+/// Left-pads a big-endian integer to `N` bytes, rejecting leading zeroes as the
+/// encoding is required to be canonical.
+pub fn static_left_pad<const N: usize>(data: &[u8]) -> Result<[u8; N], RLPDecodeError> {
+    let mut result = [0; N];
+    if data.is_empty() {
+        return Ok(result);
+    }
+    if data[0] == 0 {
+        return Err(RLPDecodeError::MalformedData);
+    }
+    if data.len() > N {
+        return Err(RLPDecodeError::InvalidLength);
+    }
+    result[N - data.len()..].copy_from_slice(data);
+    Ok(result)
+}
+
+fn decode_long_length(data: &[u8], len_of_len: u8) -> Result<(usize, &[u8]), RLPDecodeError> {
+    let len_of_len = len_of_len as usize;
+    if data.len() < 1 + len_of_len {
+        return Err(RLPDecodeError::InvalidLength);
+    }
+    let len_bytes = &data[1..1 + len_of_len];
+    let len = usize::from_be_bytes(static_left_pad(len_bytes)?);
+    if len < 56 {
+        return Err(RLPDecodeError::MalformedData);
+    }
+    Ok((len, &data[1 + len_of_len..]))
+}
+
+fn split_payload(data: &[u8], len: usize) -> Result<(&[u8], &[u8]), RLPDecodeError> {
+    if data.len() < len {
+        return Err(RLPDecodeError::InvalidLength);
+    }
+    Ok(data.split_at(len))
+}
+
+#[cfg(test)]
+mod test {
+    use ethereum_types::{Address, U256};
+    use hex_literal::hex;
+
+    use super::RLPDecode;
+    use crate::rlp::{encode::RLPEncode, error::RLPDecodeError};
+
+    #[test]
+    fn can_decode_booleans() {
+        assert!(bool::decode(&[0x01]).unwrap());
+        assert!(!bool::decode(&[0x80]).unwrap());
+        assert!(bool::decode(&[0x02]).is_err());
+    }
+
+    #[test]
+    fn can_decode_integers() {
+        assert_eq!(u8::decode(&[0x80]).unwrap(), 0);
+        assert_eq!(u8::decode(&[0x7f]).unwrap(), 0x7f);
+        assert_eq!(u8::decode(&[0x81, 0x80]).unwrap(), 0x80);
+        assert_eq!(u16::decode(&[0x82, 0x04, 0x00]).unwrap(), 0x0400);
+        assert_eq!(u64::decode(&[0x83, 0x01, 0x00, 0x00]).unwrap(), 0x010000);
+        assert_eq!(
+            U256::decode(&hex!("8412345678")).unwrap(),
+            U256::from(0x12345678u64)
+        );
+    }
+
+    #[test]
+    fn rejects_non_canonical_integers() {
+        assert_eq!(
+            u64::decode(&[0x82, 0x00, 0x01]).unwrap_err(),
+            RLPDecodeError::MalformedData
+        );
+        assert_eq!(
+            u64::decode(&[0x81, 0x05]).unwrap_err(),
+            RLPDecodeError::MalformedData
+        );
+        assert_eq!(
+            u8::decode(&[0x82, 0x01, 0x00]).unwrap_err(),
+            RLPDecodeError::InvalidLength
+        );
+    }
+
+    #[test]
+    fn can_decode_strings() {
+        let encoded = [0x83, b'c', b'a', b't'];
+        assert_eq!(String::decode(&encoded).unwrap(), "cat");
+    }
+
+    #[test]
+    fn can_decode_lists() {
+        let encoded = [0xc8, 0x83, b'd', b'o', b'g', 0x83, b'c', b'a', b't'];
+        assert_eq!(
+            Vec::<String>::decode(&encoded).unwrap(),
+            vec!["dog".to_string(), "cat".to_string()]
+        );
+        assert_eq!(
+            Vec::<String>::decode(&[0xc0]).unwrap(),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn can_decode_addresses() {
+        let encoded = hex!("94ef2d6d194084c2de36e0dabfce45d046b37d1106");
+        assert_eq!(
+            Address::decode(&encoded).unwrap(),
+            Address::from(hex!("ef2d6d194084c2de36e0dabfce45d046b37d1106"))
+        );
+    }
+
+    #[test]
+    fn long_items_roundtrip() {
+        let data = bytes::Bytes::from(vec![0xab; 1024]);
+        let encoded = data.encode_to_vec();
+        assert_eq!(&encoded[..3], &[0xb9, 0x04, 0x00]);
+        assert_eq!(bytes::Bytes::decode(&encoded).unwrap(), data);
+
+        let list: Vec<u64> = (0..100).collect();
+        let encoded = list.encode_to_vec();
+        assert_eq!(encoded[0], 0xf8);
+        assert_eq!(Vec::<u64>::decode(&encoded).unwrap(), list);
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        assert_eq!(
+            u8::decode(&[0x01, 0x02]).unwrap_err(),
+            RLPDecodeError::InvalidLength
+        );
+    }
+}
@@ -5,12 +5,32 @@ use tinyvec::ArrayVec;
 
 pub trait RLPEncode {
     fn encode(&self, buf: &mut dyn BufMut);
-    
+
     fn length(&self) -> usize {
         let mut buf = Vec::new();
         self.encode(&mut buf);
         buf.len()
     }
+
+    fn encode_to_vec(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode(&mut buf);
+        buf
+    }
+}
+
+/// Writes the prefix of a list whose encoded items take `total_len` bytes.
+pub fn encode_length(total_len: usize, buf: &mut dyn BufMut) {
+    if total_len < 56 {
+        buf.put_u8(0xc0 + total_len as u8);
+    } else {
+        let mut bytes = ArrayVec::<[u8; 8]>::new();
+        bytes.extend_from_slice(&total_len.to_be_bytes());
+        let start = bytes.iter().position(|&x| x != 0).unwrap();
+        let len = bytes.len() - start;
+        buf.put_u8(0xf7 + len as u8);
+        buf.put_slice(&bytes[start..]);
+    }
 }
 
 impl RLPEncode for bool {
@@ -50,7 +70,7 @@ impl RLPEncode for u16 {
     fn encode(&self, buf: &mut dyn BufMut) {
         match *self {
             0 => buf.put_u8(0x80),
-            n@ 1..=0x7f => buf.put_u8(n as u8),
+            n @ 1..=0x7f => buf.put_u8(n as u8),
             n => {
                 let mut bytes = ArrayVec::<[u8; 8]>::new();
                 bytes.extend_from_slice(&n.to_be_bytes());
@@ -67,7 +87,7 @@ impl RLPEncode for u32 {
     fn encode(&self, buf: &mut dyn BufMut) {
         match *self {
             0 => buf.put_u8(0x80),
-            n @ 1..0x7f => buf.put_u8(n as u8),
+            n @ 1..=0x7f => buf.put_u8(n as u8),
             n => {
                 let mut bytes = ArrayVec::<[u8; 8]>::new();
                 bytes.extend_from_slice(&n.to_be_bytes());
@@ -84,7 +104,7 @@ impl RLPEncode for u64 {
     fn encode(&self, buf: &mut dyn BufMut) {
         match *self {
             0 => buf.put_u8(0x80),
-            n @ 1..0x7f => buf.put_u8(n as u8),
+            n @ 1..=0x7f => buf.put_u8(n as u8),
             n => {
                 let mut bytes = ArrayVec::<[u8; 8]>::new();
                 bytes.extend_from_slice(&n.to_be_bytes());
@@ -105,7 +125,7 @@ impl RLPEncode for usize {
             n => {
                 let mut bytes = ArrayVec::<[u8; 8]>::new();
                 bytes.extend_from_slice(&n.to_be_bytes());
-                let start = bytes.iter().position(|&x|x != 0).unwrap();
+                let start = bytes.iter().position(|&x| x != 0).unwrap();
                 let len = bytes.len() - start;
                 buf.put_u8(0x80 + len as u8);
                 buf.put_slice(&bytes[start..]);
@@ -114,6 +134,29 @@ impl RLPEncode for usize {
     }
 }
 
+impl RLPEncode for u128 {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        match *self {
+            0 => buf.put_u8(0x80),
+            n @ 1..=0x7f => buf.put_u8(n as u8),
+            n => {
+                let bytes = n.to_be_bytes();
+                let start = bytes.iter().position(|&x| x != 0).unwrap();
+                bytes[start..].encode(buf);
+            }
+        }
+    }
+}
+
+impl RLPEncode for ethereum_types::U256 {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        let mut bytes = [0u8; 32];
+        self.to_big_endian(&mut bytes);
+        let start = bytes.iter().position(|&x| x != 0).unwrap_or(bytes.len());
+        bytes[start..].encode(buf);
+    }
+}
+
 impl RLPEncode for () {
     fn encode(&self, buf: &mut dyn BufMut) {
         buf.put_u8(0x80);
@@ -132,9 +175,9 @@ impl RLPEncode for [u8] {
             } else {
                 let mut bytes = ArrayVec::<[u8; 8]>::new();
                 bytes.extend_from_slice(&len.to_be_bytes());
-                let start = bytes.iter().position(|&x|x != 0).unwrap();
+                let start = bytes.iter().position(|&x| x != 0).unwrap();
                 let len = bytes.len() - start;
-                buf.put_u8(0x7f + len as u8);
+                buf.put_u8(0xb7 + len as u8);
                 buf.put_slice(&bytes[start..]);
             }
             buf.put_slice(self);
@@ -142,7 +185,7 @@ impl RLPEncode for [u8] {
     }
 }
 
-impl <const N: usize> RLPEncode for [u8; N] {
+impl<const N: usize> RLPEncode for [u8; N] {
     fn encode(&self, buf: &mut dyn BufMut) {
         self.as_ref().encode(buf);
     }
@@ -166,7 +209,13 @@ impl RLPEncode for String {
     }
 }
 
-impl <T: RLPEncode> RLPEncode for Vec<T> {
+impl RLPEncode for bytes::Bytes {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        self.as_ref().encode(buf);
+    }
+}
+
+impl<T: RLPEncode> RLPEncode for Vec<T> {
     fn encode(&self, buf: &mut dyn BufMut) {
         if self.is_empty() {
             buf.put_u8(0xc0);
@@ -175,16 +224,7 @@ impl <T: RLPEncode> RLPEncode for Vec<T> {
             for item in self {
                 total_len += item.length();
             }
-            if total_len < 56 {
-                buf.put_u8(0xc0 + total_len as u8);
-            } else {
-                let mut bytes = ArrayVec::<[u8; 8]>::new();
-                bytes.extend_from_slice(&total_len.to_be_bytes());
-                let start = bytes.iter().position(|&x| x!= 0).unwrap();
-                let len = bytes.len() - start;
-                buf.put_u8(0x7f + len as u8);
-                buf.put_slice(&bytes[start..]);
-            }
+            encode_length(total_len, buf);
             for item in self {
                 item.encode(buf);
             }
@@ -192,6 +232,15 @@ impl <T: RLPEncode> RLPEncode for Vec<T> {
     }
 }
 
+impl<S: RLPEncode, T: RLPEncode> RLPEncode for (S, T) {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        let total_len = self.0.length() + self.1.length();
+        encode_length(total_len, buf);
+        self.0.encode(buf);
+        self.1.encode(buf);
+    }
+}
+
 impl RLPEncode for Ipv4Addr {
     fn encode(&self, buf: &mut dyn BufMut) {
         self.octets().encode(buf);
@@ -249,19 +298,24 @@ impl RLPEncode for ethereum_types::Address {
     }
 }
 
-impl RLPEncode for ethereum_types::Signature {
+impl RLPEncode for ethereum_types::Bloom {
     fn encode(&self, buf: &mut dyn BufMut) {
         self.as_bytes().encode(buf);
     }
 }
 
+impl RLPEncode for ethereum_types::Signature {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        self.as_bytes().encode(buf);
+    }
+}
 
 #[cfg(test)]
 mod test {
-    use std::net::IpAddr;
+    use super::RLPEncode;
     use ethereum_types::Address;
     use hex_literal::hex;
-    use super::RLPEncode;
+    use std::net::IpAddr;
 
     #[test]
     fn can_encode_booleans() {
@@ -424,7 +478,7 @@ mod test {
             message.encode(&mut buf);
             buf
         };
-        let expected:[u8; 4] = [0x83, b'c', b'a', b't']; 
+        let expected: [u8; 4] = [0x83, b'c', b'a', b't'];
         assert_eq!(encoded, expected);
     }
 
@@ -437,7 +491,7 @@ mod test {
             buf
         };
 
-        let expected:[u8; 9] = [0xc8, 0x83, b'd', b'o', b'g', 0x83, b'c', b'a', b't'];
+        let expected: [u8; 9] = [0xc8, 0x83, b'd', b'o', b'g', 0x83, b'c', b'a', b't'];
         assert_eq!(encoded, expected);
 
         let message: Vec<&str> = vec![];
@@ -446,7 +500,7 @@ mod test {
             message.encode(&mut buf);
             buf
         };
-        let expected: [u8; 1] = [0xc0]; 
+        let expected: [u8; 1] = [0xc0];
         assert_eq!(encoded, expected);
     }
 
@@ -460,7 +514,7 @@ mod test {
             buf
         };
 
-        let expected:[u8; 5] = [0x84, 192, 168, 0, 1];
+        let expected: [u8; 5] = [0x84, 192, 168, 0, 1];
         assert_eq!(encoded, expected);
 
         let message = "2001:0000:130F:0000:0000:09C0:876A:130B";
@@ -470,8 +524,10 @@ mod test {
             ip.encode(&mut buf);
             buf
         };
-        let expected: [u8; 17] = [0x90, 0x20, 0x01, 0x00, 0x00, 0x13, 0x0f, 0x00, 0x00, 0x00, 0x00, 0x09, 0xc0, 0x87,
-        0x6a, 0x13, 0x0b];
+        let expected: [u8; 17] = [
+            0x90, 0x20, 0x01, 0x00, 0x00, 0x13, 0x0f, 0x00, 0x00, 0x00, 0x00, 0x09, 0xc0, 0x87,
+            0x6a, 0x13, 0x0b,
+        ];
         assert_eq!(encoded, expected)
     }
 
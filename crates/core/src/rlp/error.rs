@@ -0,0 +1,27 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RLPDecodeError {
+    InvalidLength,
+    MalformedData,
+    MalformedBoolean,
+    UnexpectedList,
+    UnexpectedString,
+    InvalidTransactionType,
+}
+
+impl fmt::Display for RLPDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            RLPDecodeError::InvalidLength => "invalid length",
+            RLPDecodeError::MalformedData => "malformed data",
+            RLPDecodeError::MalformedBoolean => "malformed boolean",
+            RLPDecodeError::UnexpectedList => "expected a string, found a list",
+            RLPDecodeError::UnexpectedString => "expected a list, found a string",
+            RLPDecodeError::InvalidTransactionType => "invalid transaction type",
+        };
+        write!(f, "RLP decode error: {msg}")
+    }
+}
+
+impl std::error::Error for RLPDecodeError {}
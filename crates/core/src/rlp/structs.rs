@@ -0,0 +1,140 @@
+use bytes::BufMut;
+
+use super::{
+    decode::{decode_rlp_item, RLPDecode},
+    encode::{encode_length, RLPEncode},
+    error::RLPDecodeError,
+};
+
+/// Decodes the fields of an RLP list one at a time, in declaration order.
+#[must_use = "`Decoder` must be consumed with `finish` to perform decoding checks"]
+pub struct Decoder<'a> {
+    payload: &'a [u8],
+    remaining: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(buf: &'a [u8]) -> Result<Self, RLPDecodeError> {
+        match decode_rlp_item(buf)? {
+            (true, payload, remaining) => Ok(Self { payload, remaining }),
+            (false, _, _) => Err(RLPDecodeError::UnexpectedString),
+        }
+    }
+
+    pub fn decode_field<T: RLPDecode>(self, _name: &str) -> Result<(T, Self), RLPDecodeError> {
+        let (field, rest) = T::decode_unfinished(self.payload)?;
+        let updated_self = Self {
+            payload: rest,
+            ..self
+        };
+        Ok((field, updated_self))
+    }
+
+    /// Decodes a trailing field that may be absent, as fork-dependent header fields are.
+    pub fn decode_optional_field<T: RLPDecode>(self) -> (Option<T>, Self) {
+        match T::decode_unfinished(self.payload) {
+            Ok((field, rest)) => (
+                Some(field),
+                Self {
+                    payload: rest,
+                    ..self
+                },
+            ),
+            Err(_) => (None, self),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.payload.is_empty()
+    }
+
+    /// Checks every field was consumed and returns the bytes after the list.
+    pub fn finish(self) -> Result<&'a [u8], RLPDecodeError> {
+        if self.is_done() {
+            Ok(self.remaining)
+        } else {
+            Err(RLPDecodeError::MalformedData)
+        }
+    }
+}
+
+/// Encodes the fields of a struct as an RLP list.
+#[must_use = "`Encoder` must be consumed with `finish` to perform the encoding"]
+pub struct Encoder<'a> {
+    buf: &'a mut dyn BufMut,
+    temp_buf: Vec<u8>,
+}
+
+impl<'a> Encoder<'a> {
+    pub fn new(buf: &'a mut dyn BufMut) -> Self {
+        Self {
+            buf,
+            temp_buf: Vec::new(),
+        }
+    }
+
+    pub fn encode_field<T: RLPEncode + ?Sized>(mut self, value: &T) -> Self {
+        value.encode(&mut self.temp_buf);
+        self
+    }
+
+    /// Encodes the value only when present; optional fields must come last.
+    pub fn encode_optional_field<T: RLPEncode>(mut self, value: &Option<T>) -> Self {
+        if let Some(value) = value {
+            value.encode(&mut self.temp_buf);
+        }
+        self
+    }
+
+    pub fn finish(self) {
+        encode_length(self.temp_buf.len(), self.buf);
+        self.buf.put_slice(&self.temp_buf);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Decoder, Encoder};
+
+    #[derive(Debug, PartialEq)]
+    struct Simple {
+        a: u8,
+        b: u16,
+        c: Option<u64>,
+    }
+
+    #[test]
+    fn encodes_and_decodes_structs() {
+        let input = Simple {
+            a: 61,
+            b: 75,
+            c: Some(0x1234),
+        };
+        let mut buf = Vec::new();
+        Encoder::new(&mut buf)
+            .encode_field(&input.a)
+            .encode_field(&input.b)
+            .encode_optional_field(&input.c)
+            .finish();
+        assert_eq!(buf, vec![0xc5, 61, 75, 0x82, 0x12, 0x34]);
+
+        let decoder = Decoder::new(&buf).unwrap();
+        let (a, decoder) = decoder.decode_field("a").unwrap();
+        let (b, decoder) = decoder.decode_field("b").unwrap();
+        let (c, decoder) = decoder.decode_optional_field();
+        let rest = decoder.finish().unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(Simple { a, b, c }, input);
+    }
+
+    #[test]
+    fn missing_optional_fields_decode_as_none() {
+        let buf = vec![0xc2, 61, 75];
+        let decoder = Decoder::new(&buf).unwrap();
+        let (_a, decoder): (u8, _) = decoder.decode_field("a").unwrap();
+        let (_b, decoder): (u16, _) = decoder.decode_field("b").unwrap();
+        let (c, decoder): (Option<u64>, _) = decoder.decode_optional_field();
+        decoder.finish().unwrap();
+        assert_eq!(c, None);
+    }
+}
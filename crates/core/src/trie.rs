@@ -0,0 +1,212 @@
+//! Merkle Patricia Trie root computation over in-memory key/value sets, as used for
+//! the transaction, receipt and withdrawal roots of a block, and the node encoding
+//! shared with the state trie kept by storage.
+
+use std::collections::BTreeMap;
+
+use bytes::BufMut;
+use ethereum_types::H256;
+use keccak_hash::keccak;
+
+use crate::rlp::encode::{encode_length, RLPEncode};
+
+/// Root of the empty trie, `keccak(rlp(""))`.
+pub const EMPTY_TRIE_ROOT: H256 = H256([
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+]);
+
+/// Root of the trie holding `entries`. Later entries replace earlier ones with the
+/// same key.
+pub fn trie_root<K, V>(entries: impl IntoIterator<Item = (K, V)>) -> H256
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    let entries: BTreeMap<Vec<u8>, V> = entries
+        .into_iter()
+        .map(|(key, value)| (nibbles(key.as_ref()), value))
+        .collect();
+    if entries.is_empty() {
+        return EMPTY_TRIE_ROOT;
+    }
+    let entries: Vec<(&[u8], &[u8])> = entries
+        .iter()
+        .map(|(path, value)| (path.as_slice(), value.as_ref()))
+        .collect();
+    keccak(encode_node(&entries, 0))
+}
+
+/// Root of the trie mapping the RLP encoded index of each item to the item, the
+/// layout of the transactions, receipts and withdrawals tries.
+pub fn ordered_trie_root<V: AsRef<[u8]>>(items: impl IntoIterator<Item = V>) -> H256 {
+    trie_root(
+        items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| (index.encode_to_vec(), item)),
+    )
+}
+
+/// The nibbles of `key`, one per byte, as trie paths are walked.
+pub fn nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0f])
+        .collect()
+}
+
+/// Encodes the node holding the sorted `entries`, whose paths all share their first
+/// `depth` nibbles.
+fn encode_node(entries: &[(&[u8], &[u8])], depth: usize) -> Vec<u8> {
+    if let [(path, value)] = entries {
+        return encode_list(&[
+            compact_path(&path[depth..], true)
+                .as_slice()
+                .encode_to_vec(),
+            value.encode_to_vec(),
+        ]);
+    }
+
+    let (first, last) = (entries[0].0, entries[entries.len() - 1].0);
+    let shared = first[depth..]
+        .iter()
+        .zip(&last[depth..])
+        .take_while(|(a, b)| a == b)
+        .count();
+    if shared > 0 {
+        let child = encode_node(entries, depth + shared);
+        return encode_list(&[
+            compact_path(&first[depth..depth + shared], false)
+                .as_slice()
+                .encode_to_vec(),
+            reference(child),
+        ]);
+    }
+
+    let mut items = Vec::with_capacity(17);
+    let mut value = None;
+    let mut rest = entries;
+    if rest[0].0.len() == depth {
+        value = Some(rest[0].1);
+        rest = &rest[1..];
+    }
+    for nibble in 0..16 {
+        let count = rest
+            .iter()
+            .take_while(|(path, _)| path[depth] == nibble)
+            .count();
+        let (children, remaining) = rest.split_at(count);
+        rest = remaining;
+        items.push(match children {
+            [] => [0x80].to_vec(),
+            children => reference(encode_node(children, depth + 1)),
+        });
+    }
+    items.push(value.unwrap_or_default().encode_to_vec());
+    encode_list(&items)
+}
+
+/// How a parent refers to an encoded child: nodes shorter than a hash are embedded.
+fn reference(node: Vec<u8>) -> Vec<u8> {
+    if node.len() < 32 {
+        node
+    } else {
+        keccak(node).encode_to_vec()
+    }
+}
+
+/// Hex-prefix encoding of a nibble path.
+pub fn compact_path(path: &[u8], leaf: bool) -> Vec<u8> {
+    let flag = if leaf { 2 } else { 0 };
+    let mut compact = Vec::with_capacity(path.len() / 2 + 1);
+    let rest = if path.len() % 2 == 1 {
+        compact.push(((flag + 1) << 4) | path[0]);
+        &path[1..]
+    } else {
+        compact.push(flag << 4);
+        path
+    };
+    compact.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+    compact
+}
+
+/// RLP list of already encoded `items`.
+pub fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    encode_length(items.iter().map(Vec::len).sum(), &mut buf);
+    for item in items {
+        buf.put_slice(item);
+    }
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use hex_literal::hex;
+
+    use super::*;
+
+    #[test]
+    fn empty_trie_root() {
+        assert_eq!(EMPTY_TRIE_ROOT, keccak([0x80]));
+        assert_eq!(trie_root(Vec::<(Vec<u8>, Vec<u8>)>::new()), EMPTY_TRIE_ROOT);
+    }
+
+    #[test]
+    fn known_trie_roots() {
+        // From ethereum/tests TrieTests/trietest.json
+        let root = trie_root([
+            (b"do".as_slice(), b"verb".as_slice()),
+            (b"dog", b"puppy"),
+            (b"doge", b"coin"),
+            (b"horse", b"stallion"),
+        ]);
+        assert_eq!(
+            root,
+            H256(hex!(
+                "5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84"
+            ))
+        );
+
+        let root = trie_root([(b"A".as_slice(), [b'a'; 50].as_slice())]);
+        assert_eq!(
+            root,
+            H256(hex!(
+                "d23786fb4a010da3ce639d66d5e904a11dbc02746d1ce25029e53290cabf28ab"
+            ))
+        );
+    }
+
+    #[test]
+    fn ordered_root_of_transactions() {
+        use crate::types::{LegacyTransaction, Transaction, TxKind};
+        use ethereum_types::{Address, U256};
+
+        // Mainnet block 46147, whose single transaction is also used in the
+        // transaction tests
+        let tx = Transaction::LegacyTransaction(LegacyTransaction {
+            nonce: 0,
+            gas_price: U256::from(50_000_000_000_000u64),
+            gas: 21_000,
+            to: TxKind::Call(Address::from(hex!(
+                "5df9b87991262f6ba471f09758cde1c0fc1de734"
+            ))),
+            value: U256::from(31337),
+            data: Default::default(),
+            v: U256::from(28),
+            r: U256::from_big_endian(&hex!(
+                "88ff6cf0fefd94db46111149ae4bfc179e9b94721fffd821d38d16464b3f71d0"
+            )),
+            s: U256::from_big_endian(&hex!(
+                "45e0aff800961cfce805daef7016b9b675c137a6a41a548f7b60a3484c06a33a"
+            )),
+        });
+        let root = ordered_trie_root([tx.encode_canonical_to_vec()]);
+        assert_eq!(
+            root,
+            H256(hex!(
+                "4513310fcb9f6f616972a3b948dc5d547f280849a87ebb5af0191f98b87be598"
+            ))
+        );
+    }
+}
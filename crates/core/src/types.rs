@@ -0,0 +1,9 @@
+mod account;
+mod block;
+mod receipt;
+mod transaction;
+
+pub use account::*;
+pub use block::*;
+pub use receipt::*;
+pub use transaction::*;
@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+
+use bytes::{BufMut, Bytes};
+use ethereum_types::{Address, H256, U256};
+use hex_literal::hex;
+use keccak_hash::keccak;
+
+use crate::{
+    rlp::{
+        decode::RLPDecode,
+        encode::RLPEncode,
+        error::RLPDecodeError,
+        structs::{Decoder, Encoder},
+    },
+    trie::trie_root,
+};
+
+/// keccak256 of the empty byte string.
+pub const EMPTY_CODE_HASH: H256 = H256(hex!(
+    "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+));
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountInfo {
+    pub nonce: u64,
+    pub balance: U256,
+    pub code_hash: H256,
+}
+
+impl Default for AccountInfo {
+    fn default() -> Self {
+        Self {
+            nonce: 0,
+            balance: U256::zero(),
+            code_hash: EMPTY_CODE_HASH,
+        }
+    }
+}
+
+impl AccountInfo {
+    /// Empty as defined by EIP-161: no nonce, no balance and no code.
+    pub fn is_empty(&self) -> bool {
+        self.nonce == 0 && self.balance.is_zero() && self.code_hash == EMPTY_CODE_HASH
+    }
+}
+
+impl RLPEncode for AccountInfo {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.nonce)
+            .encode_field(&self.balance)
+            .encode_field(&self.code_hash)
+            .finish();
+    }
+}
+
+impl RLPDecode for AccountInfo {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let decoder = Decoder::new(rlp)?;
+        let (nonce, decoder) = decoder.decode_field("nonce")?;
+        let (balance, decoder) = decoder.decode_field("balance")?;
+        let (code_hash, decoder) = decoder.decode_field("code_hash")?;
+        let rest = decoder.finish()?;
+        Ok((
+            AccountInfo {
+                nonce,
+                balance,
+                code_hash,
+            },
+            rest,
+        ))
+    }
+}
+
+/// Changes made to a single account by a block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountDiff {
+    /// Account after the block, `None` if it was deleted.
+    pub info: Option<AccountInfo>,
+    /// Set if all storage held before the block was wiped, as self-destruct does.
+    pub storage_cleared: bool,
+    /// Updated storage slots; a zero value deletes the slot.
+    pub storage: BTreeMap<H256, U256>,
+    /// Code deployed to the account, keyed in storage by its hash.
+    pub code: Option<Bytes>,
+}
+
+/// Every account change made by a block, which is what storage needs to persist the
+/// new state.
+pub type StateDiff = BTreeMap<Address, AccountDiff>;
+
+/// Every account of a state along with all of its storage, the input of a state root
+/// computed from scratch.
+pub type FullState = BTreeMap<Address, (AccountInfo, BTreeMap<H256, U256>)>;
+
+/// Root of the state trie holding `state` with `diff` applied on top.
+pub fn compute_state_root(mut state: FullState, diff: &StateDiff) -> H256 {
+    for (address, diff) in diff {
+        let Some(info) = &diff.info else {
+            state.remove(address);
+            continue;
+        };
+        let (account, storage) = state.entry(*address).or_default();
+        *account = info.clone();
+        if diff.storage_cleared {
+            storage.clear();
+        }
+        for (slot, value) in &diff.storage {
+            storage.insert(*slot, *value);
+        }
+    }
+    trie_root(state.iter().map(|(address, (info, storage))| {
+        let storage_root = trie_root(
+            storage
+                .iter()
+                .filter(|(_, value)| !value.is_zero())
+                .map(|(slot, value)| (keccak(slot), value.encode_to_vec())),
+        );
+        let mut buf = Vec::new();
+        Encoder::new(&mut buf)
+            .encode_field(&info.nonce)
+            .encode_field(&info.balance)
+            .encode_field(&storage_root)
+            .encode_field(&info.code_hash)
+            .finish();
+        (keccak(address), buf)
+    }))
+}
+
+/// Address of a contract created with `CREATE` (or a creation transaction) by
+/// `sender` at `nonce`.
+pub fn contract_address(sender: Address, nonce: u64) -> Address {
+    let mut buf = Vec::new();
+    Encoder::new(&mut buf)
+        .encode_field(&sender)
+        .encode_field(&nonce)
+        .finish();
+    Address::from_slice(&keccak(buf).0[12..])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn account_roundtrip() {
+        let account = AccountInfo {
+            nonce: 5,
+            balance: U256::from(1_000_000),
+            code_hash: H256::repeat_byte(7),
+        };
+        assert_eq!(
+            AccountInfo::decode(&account.encode_to_vec()).unwrap(),
+            account
+        );
+        assert!(AccountInfo::default().is_empty());
+    }
+
+    #[test]
+    fn computes_state_root() {
+        let address = Address::repeat_byte(1);
+        let info = AccountInfo {
+            nonce: 1,
+            ..Default::default()
+        };
+        let storage = BTreeMap::from([(H256::zero(), U256::one())]);
+        let state = FullState::from([(address, (info.clone(), storage.clone()))]);
+        let diff = StateDiff::from([(
+            address,
+            AccountDiff {
+                info: Some(info.clone()),
+                storage: BTreeMap::from([(H256::zero(), U256::zero())]),
+                ..Default::default()
+            },
+        )]);
+        // A slot set to zero leaves the trie
+        let cleared = FullState::from([(address, (info, BTreeMap::new()))]);
+        assert_eq!(
+            compute_state_root(state.clone(), &diff),
+            compute_state_root(cleared, &StateDiff::new())
+        );
+        let deleted = StateDiff::from([(address, AccountDiff::default())]);
+        assert_eq!(
+            compute_state_root(state, &deleted),
+            crate::trie::EMPTY_TRIE_ROOT
+        );
+    }
+
+    #[test]
+    fn computes_contract_address() {
+        let sender = Address::from(hex!("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0"));
+        assert_eq!(
+            contract_address(sender, 0),
+            Address::from(hex!("cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"))
+        );
+        assert_eq!(
+            contract_address(sender, 1),
+            Address::from(hex!("343c43a37d37dff08ae8c4a11544c718abb4fcf8"))
+        );
+    }
+}
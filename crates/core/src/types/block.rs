@@ -0,0 +1,486 @@
+use std::fmt;
+
+use bytes::{BufMut, Bytes};
+use ethereum_types::{Address, Bloom, H256, U256};
+use keccak_hash::keccak;
+
+use super::Transaction;
+use crate::{
+    rlp::{
+        decode::RLPDecode,
+        encode::RLPEncode,
+        error::RLPDecodeError,
+        structs::{Decoder, Encoder},
+    },
+    trie::ordered_trie_root,
+};
+
+/// Lowest gas limit a block may have.
+pub const MIN_GAS_LIMIT: u64 = 5000;
+/// A block's gas limit may differ from its parent's by less than 1/1024th.
+const GAS_LIMIT_BOUND_DIVISOR: u64 = 1024;
+
+pub type BlockNumber = u64;
+pub type BlockHash = H256;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub parent_hash: H256,
+    pub ommers_hash: H256,
+    pub coinbase: Address,
+    pub state_root: H256,
+    pub transactions_root: H256,
+    pub receipts_root: H256,
+    pub logs_bloom: Bloom,
+    pub difficulty: U256,
+    pub number: BlockNumber,
+    pub gas_limit: u64,
+    pub gas_used: u64,
+    pub timestamp: u64,
+    pub extra_data: Bytes,
+    pub prev_randao: H256,
+    pub nonce: u64,
+    pub base_fee_per_gas: Option<u64>,
+    pub withdrawals_root: Option<H256>,
+    pub blob_gas_used: Option<u64>,
+    pub excess_blob_gas: Option<u64>,
+    pub parent_beacon_block_root: Option<H256>,
+    pub requests_hash: Option<H256>,
+}
+
+impl BlockHeader {
+    pub fn compute_block_hash(&self) -> BlockHash {
+        keccak(self.encode_to_vec())
+    }
+
+    /// Checks the fields that follow from the parent block alone: number, parent hash,
+    /// timestamp and gas limit, and that gas used fits the limit.
+    pub fn validate_against_parent(&self, parent: &BlockHeader) -> Result<(), InvalidBlockError> {
+        if self.number != parent.number + 1 {
+            return Err(InvalidBlockError::InvalidNumber);
+        }
+        if self.parent_hash != parent.compute_block_hash() {
+            return Err(InvalidBlockError::ParentHashMismatch);
+        }
+        if self.timestamp <= parent.timestamp {
+            return Err(InvalidBlockError::InvalidTimestamp);
+        }
+        if self.gas_limit < MIN_GAS_LIMIT
+            || self.gas_limit.abs_diff(parent.gas_limit)
+                >= parent.gas_limit / GAS_LIMIT_BOUND_DIVISOR
+        {
+            return Err(InvalidBlockError::InvalidGasLimit);
+        }
+        if self.gas_used > self.gas_limit {
+            return Err(InvalidBlockError::GasUsedExceedsLimit);
+        }
+        Ok(())
+    }
+}
+
+impl RLPEncode for BlockHeader {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.parent_hash)
+            .encode_field(&self.ommers_hash)
+            .encode_field(&self.coinbase)
+            .encode_field(&self.state_root)
+            .encode_field(&self.transactions_root)
+            .encode_field(&self.receipts_root)
+            .encode_field(&self.logs_bloom)
+            .encode_field(&self.difficulty)
+            .encode_field(&self.number)
+            .encode_field(&self.gas_limit)
+            .encode_field(&self.gas_used)
+            .encode_field(&self.timestamp)
+            .encode_field(&self.extra_data)
+            .encode_field(&self.prev_randao)
+            .encode_field(&self.nonce.to_be_bytes())
+            .encode_optional_field(&self.base_fee_per_gas)
+            .encode_optional_field(&self.withdrawals_root)
+            .encode_optional_field(&self.blob_gas_used)
+            .encode_optional_field(&self.excess_blob_gas)
+            .encode_optional_field(&self.parent_beacon_block_root)
+            .encode_optional_field(&self.requests_hash)
+            .finish();
+    }
+}
+
+impl RLPDecode for BlockHeader {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let decoder = Decoder::new(rlp)?;
+        let (parent_hash, decoder) = decoder.decode_field("parent_hash")?;
+        let (ommers_hash, decoder) = decoder.decode_field("ommers_hash")?;
+        let (coinbase, decoder) = decoder.decode_field("coinbase")?;
+        let (state_root, decoder) = decoder.decode_field("state_root")?;
+        let (transactions_root, decoder) = decoder.decode_field("transactions_root")?;
+        let (receipts_root, decoder) = decoder.decode_field("receipts_root")?;
+        let (logs_bloom, decoder) = decoder.decode_field("logs_bloom")?;
+        let (difficulty, decoder) = decoder.decode_field("difficulty")?;
+        let (number, decoder) = decoder.decode_field("number")?;
+        let (gas_limit, decoder) = decoder.decode_field("gas_limit")?;
+        let (gas_used, decoder) = decoder.decode_field("gas_used")?;
+        let (timestamp, decoder) = decoder.decode_field("timestamp")?;
+        let (extra_data, decoder) = decoder.decode_field("extra_data")?;
+        let (prev_randao, decoder) = decoder.decode_field("prev_randao")?;
+        let (nonce, decoder): ([u8; 8], _) = decoder.decode_field("nonce")?;
+        let (base_fee_per_gas, decoder) = decoder.decode_optional_field();
+        let (withdrawals_root, decoder) = decoder.decode_optional_field();
+        let (blob_gas_used, decoder) = decoder.decode_optional_field();
+        let (excess_blob_gas, decoder) = decoder.decode_optional_field();
+        let (parent_beacon_block_root, decoder) = decoder.decode_optional_field();
+        let (requests_hash, decoder) = decoder.decode_optional_field();
+        let rest = decoder.finish()?;
+        let header = BlockHeader {
+            parent_hash,
+            ommers_hash,
+            coinbase,
+            state_root,
+            transactions_root,
+            receipts_root,
+            logs_bloom,
+            difficulty,
+            number,
+            gas_limit,
+            gas_used,
+            timestamp,
+            extra_data,
+            prev_randao,
+            nonce: u64::from_be_bytes(nonce),
+            base_fee_per_gas,
+            withdrawals_root,
+            blob_gas_used,
+            excess_blob_gas,
+            parent_beacon_block_root,
+            requests_hash,
+        };
+        Ok((header, rest))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Withdrawal {
+    pub index: u64,
+    pub validator_index: u64,
+    pub address: Address,
+    /// Amount in Gwei.
+    pub amount: u64,
+}
+
+impl RLPEncode for Withdrawal {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.index)
+            .encode_field(&self.validator_index)
+            .encode_field(&self.address)
+            .encode_field(&self.amount)
+            .finish();
+    }
+}
+
+impl RLPDecode for Withdrawal {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let decoder = Decoder::new(rlp)?;
+        let (index, decoder) = decoder.decode_field("index")?;
+        let (validator_index, decoder) = decoder.decode_field("validator_index")?;
+        let (address, decoder) = decoder.decode_field("address")?;
+        let (amount, decoder) = decoder.decode_field("amount")?;
+        let rest = decoder.finish()?;
+        Ok((
+            Withdrawal {
+                index,
+                validator_index,
+                address,
+                amount,
+            },
+            rest,
+        ))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockBody {
+    pub transactions: Vec<Transaction>,
+    pub ommers: Vec<BlockHeader>,
+    pub withdrawals: Option<Vec<Withdrawal>>,
+}
+
+impl BlockBody {
+    pub fn compute_transactions_root(&self) -> H256 {
+        ordered_trie_root(
+            self.transactions
+                .iter()
+                .map(Transaction::encode_canonical_to_vec),
+        )
+    }
+
+    pub fn compute_ommers_hash(&self) -> H256 {
+        keccak(self.ommers.encode_to_vec())
+    }
+
+    /// `None` before Shanghai, when bodies carry no withdrawals.
+    pub fn compute_withdrawals_root(&self) -> Option<H256> {
+        self.withdrawals
+            .as_ref()
+            .map(|withdrawals| ordered_trie_root(withdrawals.iter().map(RLPEncode::encode_to_vec)))
+    }
+}
+
+impl RLPEncode for BlockBody {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.transactions)
+            .encode_field(&self.ommers)
+            .encode_optional_field(&self.withdrawals)
+            .finish();
+    }
+}
+
+impl RLPDecode for BlockBody {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let decoder = Decoder::new(rlp)?;
+        let (transactions, decoder) = decoder.decode_field("transactions")?;
+        let (ommers, decoder) = decoder.decode_field("ommers")?;
+        let (withdrawals, decoder) = decoder.decode_optional_field();
+        let rest = decoder.finish()?;
+        Ok((
+            BlockBody {
+                transactions,
+                ommers,
+                withdrawals,
+            },
+            rest,
+        ))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Block {
+    pub header: BlockHeader,
+    pub body: BlockBody,
+}
+
+impl Block {
+    pub fn hash(&self) -> BlockHash {
+        self.header.compute_block_hash()
+    }
+}
+
+impl Block {
+    /// Checks that the body is the one the header commits to.
+    pub fn validate_body(&self) -> Result<(), InvalidBlockError> {
+        if self.body.compute_transactions_root() != self.header.transactions_root {
+            return Err(InvalidBlockError::TransactionsRootMismatch);
+        }
+        if self.body.compute_ommers_hash() != self.header.ommers_hash {
+            return Err(InvalidBlockError::OmmersHashMismatch);
+        }
+        if self.body.compute_withdrawals_root() != self.header.withdrawals_root {
+            return Err(InvalidBlockError::WithdrawalsRootMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Reasons a block is rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidBlockError {
+    InvalidNumber,
+    ParentHashMismatch,
+    InvalidTimestamp,
+    InvalidGasLimit,
+    GasUsedExceedsLimit,
+    TransactionsRootMismatch,
+    OmmersHashMismatch,
+    WithdrawalsRootMismatch,
+    /// A field the block's fork requires is missing.
+    MissingField(&'static str),
+    /// A field from a later fork is present.
+    UnexpectedField(&'static str),
+    InvalidBaseFee,
+    InvalidExcessBlobGas,
+    /// Difficulty, nonce or ommers set after the merge.
+    ProofOfWorkAfterMerge,
+    GasUsedMismatch,
+    ReceiptsRootMismatch,
+    LogsBloomMismatch,
+    BlobGasUsedMismatch,
+    RequestsHashMismatch,
+    StateRootMismatch,
+}
+
+impl fmt::Display for InvalidBlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            InvalidBlockError::InvalidNumber => "block number does not follow its parent",
+            InvalidBlockError::ParentHashMismatch => "parent hash does not match the parent block",
+            InvalidBlockError::InvalidTimestamp => "timestamp is not after the parent's",
+            InvalidBlockError::InvalidGasLimit => "gas limit is out of bounds",
+            InvalidBlockError::GasUsedExceedsLimit => "gas used exceeds the gas limit",
+            InvalidBlockError::TransactionsRootMismatch => "transactions root mismatch",
+            InvalidBlockError::OmmersHashMismatch => "ommers hash mismatch",
+            InvalidBlockError::WithdrawalsRootMismatch => "withdrawals root mismatch",
+            InvalidBlockError::MissingField(field) => {
+                return write!(f, "invalid block: missing {field}")
+            }
+            InvalidBlockError::UnexpectedField(field) => {
+                return write!(f, "invalid block: unexpected {field}")
+            }
+            InvalidBlockError::InvalidBaseFee => "base fee does not follow from the parent",
+            InvalidBlockError::InvalidExcessBlobGas => {
+                "excess blob gas does not follow from the parent"
+            }
+            InvalidBlockError::ProofOfWorkAfterMerge => "proof of work fields set after the merge",
+            InvalidBlockError::GasUsedMismatch => "gas used mismatch",
+            InvalidBlockError::ReceiptsRootMismatch => "receipts root mismatch",
+            InvalidBlockError::LogsBloomMismatch => "logs bloom mismatch",
+            InvalidBlockError::BlobGasUsedMismatch => "blob gas used mismatch",
+            InvalidBlockError::RequestsHashMismatch => "requests hash mismatch",
+            InvalidBlockError::StateRootMismatch => "state root mismatch",
+        };
+        write!(f, "invalid block: {msg}")
+    }
+}
+
+impl std::error::Error for InvalidBlockError {}
+
+impl RLPEncode for Block {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.header)
+            .encode_field(&self.body.transactions)
+            .encode_field(&self.body.ommers)
+            .encode_optional_field(&self.body.withdrawals)
+            .finish();
+    }
+}
+
+impl RLPDecode for Block {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let decoder = Decoder::new(rlp)?;
+        let (header, decoder) = decoder.decode_field("header")?;
+        let (transactions, decoder) = decoder.decode_field("transactions")?;
+        let (ommers, decoder) = decoder.decode_field("ommers")?;
+        let (withdrawals, decoder) = decoder.decode_optional_field();
+        let rest = decoder.finish()?;
+        let body = BlockBody {
+            transactions,
+            ommers,
+            withdrawals,
+        };
+        Ok((Block { header, body }, rest))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hex_literal::hex;
+
+    use super::*;
+
+    fn mainnet_block_1() -> BlockHeader {
+        BlockHeader {
+            parent_hash: H256(hex!(
+                "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+            )),
+            ommers_hash: H256(hex!(
+                "1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347"
+            )),
+            coinbase: Address::from(hex!("05a56e2d52c817161883f50c441c3228cfe54d9f")),
+            state_root: H256(hex!(
+                "d67e4d450343046425ae4271474353857ab860dbc0a1dde64b41b5cd3a532bf3"
+            )),
+            transactions_root: H256(hex!(
+                "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+            )),
+            receipts_root: H256(hex!(
+                "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+            )),
+            logs_bloom: Bloom::zero(),
+            difficulty: U256::from(0x3ff800000u64),
+            number: 1,
+            gas_limit: 5000,
+            gas_used: 0,
+            timestamp: 0x55ba4224,
+            extra_data: Bytes::from_static(b"Geth/v1.0.0/linux/go1.4.2"),
+            prev_randao: H256(hex!(
+                "969b900de27b6ac6a67742365dd65f55a0526c41fd18e1b16f1a1215c2e66f59"
+            )),
+            nonce: 0x539bd4979fef1ec4,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn computes_mainnet_block_hash() {
+        let header = mainnet_block_1();
+        assert_eq!(
+            header.compute_block_hash(),
+            H256(hex!(
+                "88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6"
+            ))
+        );
+    }
+
+    #[test]
+    fn block_roundtrip() {
+        let block = Block {
+            header: mainnet_block_1(),
+            body: BlockBody::default(),
+        };
+        let encoded = block.encode_to_vec();
+        assert_eq!(Block::decode(&encoded).unwrap(), block);
+    }
+
+    #[test]
+    fn validates_block_against_parent_and_body() {
+        let parent = mainnet_block_1();
+        let mut header = BlockHeader {
+            parent_hash: parent.compute_block_hash(),
+            ommers_hash: parent.ommers_hash,
+            transactions_root: parent.transactions_root,
+            number: 2,
+            gas_limit: 5000,
+            timestamp: parent.timestamp + 13,
+            ..Default::default()
+        };
+        let mut block = Block {
+            header: header.clone(),
+            body: BlockBody::default(),
+        };
+        assert_eq!(header.validate_against_parent(&parent), Ok(()));
+        assert_eq!(block.validate_body(), Ok(()));
+
+        header.timestamp = parent.timestamp;
+        assert_eq!(
+            header.validate_against_parent(&parent),
+            Err(InvalidBlockError::InvalidTimestamp)
+        );
+        block.body.withdrawals = Some(Vec::new());
+        assert_eq!(
+            block.validate_body(),
+            Err(InvalidBlockError::WithdrawalsRootMismatch)
+        );
+        block.body.ommers.push(parent);
+        assert_eq!(
+            block.validate_body(),
+            Err(InvalidBlockError::OmmersHashMismatch)
+        );
+    }
+
+    #[test]
+    fn post_cancun_header_roundtrip() {
+        let header = BlockHeader {
+            number: 20_000_000,
+            gas_limit: 30_000_000,
+            base_fee_per_gas: Some(7),
+            withdrawals_root: Some(H256::repeat_byte(1)),
+            blob_gas_used: Some(0),
+            excess_blob_gas: Some(0x20000),
+            parent_beacon_block_root: Some(H256::repeat_byte(2)),
+            ..Default::default()
+        };
+        let encoded = header.encode_to_vec();
+        assert_eq!(BlockHeader::decode(&encoded).unwrap(), header);
+    }
+}
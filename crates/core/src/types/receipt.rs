@@ -0,0 +1,252 @@
+use bytes::{BufMut, Bytes};
+use ethereum_types::{Address, Bloom, BloomInput, H256};
+
+use super::TxType;
+use crate::{
+    rlp::{
+        decode::{decode_rlp_item, RLPDecode},
+        encode::RLPEncode,
+        error::RLPDecodeError,
+        structs::{Decoder, Encoder},
+    },
+    trie::ordered_trie_root,
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Receipt {
+    pub tx_type: TxType,
+    pub outcome: ReceiptOutcome,
+    pub cumulative_gas_used: u64,
+    pub bloom: Bloom,
+    pub logs: Vec<Log>,
+}
+
+/// The first field of a receipt: the state root after the transaction before
+/// Byzantium, whether the transaction succeeded since (EIP-658).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReceiptOutcome {
+    StateRoot(H256),
+    Status(bool),
+}
+
+impl Default for ReceiptOutcome {
+    fn default() -> Self {
+        ReceiptOutcome::Status(false)
+    }
+}
+
+impl Receipt {
+    pub fn new(tx_type: TxType, succeeded: bool, cumulative_gas_used: u64, logs: Vec<Log>) -> Self {
+        Self::with_outcome(
+            tx_type,
+            ReceiptOutcome::Status(succeeded),
+            cumulative_gas_used,
+            logs,
+        )
+    }
+
+    pub fn with_outcome(
+        tx_type: TxType,
+        outcome: ReceiptOutcome,
+        cumulative_gas_used: u64,
+        logs: Vec<Log>,
+    ) -> Self {
+        Self {
+            tx_type,
+            outcome,
+            cumulative_gas_used,
+            bloom: bloom_from_logs(&logs),
+            logs,
+        }
+    }
+
+    /// Whether the transaction succeeded, which receipts from before Byzantium do not
+    /// record.
+    pub fn succeeded(&self) -> Option<bool> {
+        match self.outcome {
+            ReceiptOutcome::StateRoot(_) => None,
+            ReceiptOutcome::Status(succeeded) => Some(succeeded),
+        }
+    }
+
+    /// Encodes the receipt as it goes into the receipts trie: plain RLP for legacy
+    /// receipts, `type || rlp(payload)` for typed ones.
+    pub fn encode_inner(&self, buf: &mut dyn BufMut) {
+        if self.tx_type != TxType::Legacy {
+            buf.put_u8(self.tx_type as u8);
+        }
+        self.encode_payload(buf);
+    }
+
+    pub fn encode_inner_to_vec(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_inner(&mut buf);
+        buf
+    }
+
+    fn encode_payload(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.outcome)
+            .encode_field(&self.cumulative_gas_used)
+            .encode_field(&self.bloom)
+            .encode_field(&self.logs)
+            .finish();
+    }
+
+    pub fn decode_inner(bytes: &[u8]) -> Result<Self, RLPDecodeError> {
+        let first = *bytes.first().ok_or(RLPDecodeError::InvalidLength)?;
+        let (tx_type, payload) = if first >= 0xc0 {
+            (TxType::Legacy, bytes)
+        } else {
+            (TxType::try_from(first)?, &bytes[1..])
+        };
+        let decoder = Decoder::new(payload)?;
+        let (outcome, decoder) = decoder.decode_field("outcome")?;
+        let (cumulative_gas_used, decoder) = decoder.decode_field("cumulative_gas_used")?;
+        let (bloom, decoder) = decoder.decode_field("bloom")?;
+        let (logs, decoder) = decoder.decode_field("logs")?;
+        if !decoder.finish()?.is_empty() {
+            return Err(RLPDecodeError::InvalidLength);
+        }
+        Ok(Receipt {
+            tx_type,
+            outcome,
+            cumulative_gas_used,
+            bloom,
+            logs,
+        })
+    }
+}
+
+/// Like transactions, typed receipts are wrapped in an RLP string inside lists.
+impl RLPEncode for Receipt {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        match self.tx_type {
+            TxType::Legacy => self.encode_payload(buf),
+            _ => self.encode_inner_to_vec().as_slice().encode(buf),
+        }
+    }
+}
+
+impl RLPDecode for Receipt {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let (is_list, payload, rest) = decode_rlp_item(rlp)?;
+        let receipt = if is_list {
+            Receipt::decode_inner(&rlp[..rlp.len() - rest.len()])?
+        } else {
+            Receipt::decode_inner(payload)?
+        };
+        Ok((receipt, rest))
+    }
+}
+
+impl RLPEncode for ReceiptOutcome {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        match self {
+            ReceiptOutcome::StateRoot(root) => root.encode(buf),
+            ReceiptOutcome::Status(succeeded) => succeeded.encode(buf),
+        }
+    }
+}
+
+/// A 32-byte string is a state root, anything else must be a status.
+impl RLPDecode for ReceiptOutcome {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let (is_list, payload, _) = decode_rlp_item(rlp)?;
+        if !is_list && payload.len() == 32 {
+            let (root, rest) = H256::decode_unfinished(rlp)?;
+            Ok((ReceiptOutcome::StateRoot(root), rest))
+        } else {
+            let (succeeded, rest) = bool::decode_unfinished(rlp)?;
+            Ok((ReceiptOutcome::Status(succeeded), rest))
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Log {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Bytes,
+}
+
+impl RLPEncode for Log {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.address)
+            .encode_field(&self.topics)
+            .encode_field(&self.data)
+            .finish();
+    }
+}
+
+impl RLPDecode for Log {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let decoder = Decoder::new(rlp)?;
+        let (address, decoder) = decoder.decode_field("address")?;
+        let (topics, decoder) = decoder.decode_field("topics")?;
+        let (data, decoder) = decoder.decode_field("data")?;
+        let rest = decoder.finish()?;
+        Ok((
+            Log {
+                address,
+                topics,
+                data,
+            },
+            rest,
+        ))
+    }
+}
+
+pub fn compute_receipts_root(receipts: &[Receipt]) -> H256 {
+    ordered_trie_root(receipts.iter().map(Receipt::encode_inner_to_vec))
+}
+
+pub fn bloom_from_logs(logs: &[Log]) -> Bloom {
+    let mut bloom = Bloom::zero();
+    for log in logs {
+        bloom.accrue(BloomInput::Raw(log.address.as_bytes()));
+        for topic in &log.topics {
+            bloom.accrue(BloomInput::Raw(topic.as_bytes()));
+        }
+    }
+    bloom
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample_log() -> Log {
+        Log {
+            address: Address::repeat_byte(0xaa),
+            topics: vec![H256::repeat_byte(0x01), H256::repeat_byte(0x02)],
+            data: Bytes::from_static(&[1, 2, 3]),
+        }
+    }
+
+    #[test]
+    fn receipts_roundtrip_inside_lists() {
+        let receipts = vec![
+            Receipt::new(TxType::Legacy, true, 21000, vec![]),
+            Receipt::new(TxType::EIP1559, false, 42000, vec![sample_log()]),
+            Receipt::with_outcome(
+                TxType::Legacy,
+                ReceiptOutcome::StateRoot(H256::repeat_byte(0x5a)),
+                63000,
+                vec![sample_log()],
+            ),
+        ];
+        let encoded = receipts.encode_to_vec();
+        assert_eq!(Vec::<Receipt>::decode(&encoded).unwrap(), receipts);
+    }
+
+    #[test]
+    fn bloom_contains_log_address_and_topics() {
+        let log = sample_log();
+        let bloom = bloom_from_logs(std::slice::from_ref(&log));
+        assert!(bloom.contains_input(BloomInput::Raw(log.address.as_bytes())));
+        assert!(bloom.contains_input(BloomInput::Raw(log.topics[1].as_bytes())));
+        assert!(!bloom.contains_input(BloomInput::Raw(Address::repeat_byte(0xbb).as_bytes())));
+    }
+}
@@ -0,0 +1,1075 @@
+use bytes::{BufMut, Bytes};
+use ethereum_types::{Address, H256, U256};
+use keccak_hash::keccak;
+
+use crate::{
+    crypto,
+    rlp::{
+        decode::{decode_rlp_item, RLPDecode},
+        encode::RLPEncode,
+        error::RLPDecodeError,
+        structs::{Decoder, Encoder},
+    },
+};
+
+pub type AccessList = Vec<(Address, Vec<H256>)>;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TxType {
+    #[default]
+    Legacy = 0x00,
+    EIP2930 = 0x01,
+    EIP1559 = 0x02,
+    EIP4844 = 0x03,
+    EIP7702 = 0x04,
+}
+
+impl TryFrom<u8> for TxType {
+    type Error = RLPDecodeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(TxType::Legacy),
+            0x01 => Ok(TxType::EIP2930),
+            0x02 => Ok(TxType::EIP1559),
+            0x03 => Ok(TxType::EIP4844),
+            0x04 => Ok(TxType::EIP7702),
+            _ => Err(RLPDecodeError::InvalidTransactionType),
+        }
+    }
+}
+
+/// Destination of a transaction: a call to an address or a contract creation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TxKind {
+    Call(Address),
+    #[default]
+    Create,
+}
+
+impl RLPEncode for TxKind {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        match self {
+            TxKind::Call(address) => address.encode(buf),
+            TxKind::Create => buf.put_u8(0x80),
+        }
+    }
+}
+
+impl RLPDecode for TxKind {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        if rlp.first() == Some(&0x80) {
+            return Ok((TxKind::Create, &rlp[1..]));
+        }
+        let (address, rest) = Address::decode_unfinished(rlp)?;
+        Ok((TxKind::Call(address), rest))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Transaction {
+    LegacyTransaction(LegacyTransaction),
+    EIP2930Transaction(EIP2930Transaction),
+    EIP1559Transaction(EIP1559Transaction),
+    EIP4844Transaction(EIP4844Transaction),
+    EIP7702Transaction(EIP7702Transaction),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LegacyTransaction {
+    pub nonce: u64,
+    pub gas_price: U256,
+    pub gas: u64,
+    pub to: TxKind,
+    pub value: U256,
+    pub data: Bytes,
+    pub v: U256,
+    pub r: U256,
+    pub s: U256,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EIP2930Transaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub gas_price: U256,
+    pub gas_limit: u64,
+    pub to: TxKind,
+    pub value: U256,
+    pub data: Bytes,
+    pub access_list: AccessList,
+    pub signature_y_parity: bool,
+    pub signature_r: U256,
+    pub signature_s: U256,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EIP1559Transaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: u64,
+    pub to: TxKind,
+    pub value: U256,
+    pub data: Bytes,
+    pub access_list: AccessList,
+    pub signature_y_parity: bool,
+    pub signature_r: U256,
+    pub signature_s: U256,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EIP4844Transaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas: u64,
+    pub to: Address,
+    pub value: U256,
+    pub data: Bytes,
+    pub access_list: AccessList,
+    pub max_fee_per_blob_gas: U256,
+    pub blob_versioned_hashes: Vec<H256>,
+    pub signature_y_parity: bool,
+    pub signature_r: U256,
+    pub signature_s: U256,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EIP7702Transaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: u64,
+    pub to: Address,
+    pub value: U256,
+    pub data: Bytes,
+    pub access_list: AccessList,
+    pub authorization_list: Vec<AuthorizationTuple>,
+    pub signature_y_parity: bool,
+    pub signature_r: U256,
+    pub signature_s: U256,
+}
+
+/// Prefix of the hash signed by an EIP-7702 authorization, which keeps it from being a
+/// valid hash of anything else.
+pub const AUTHORIZATION_MAGIC: u8 = 0x05;
+
+/// Signed EIP-7702 authorization to install a delegation designator on `authority`'s
+/// account.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuthorizationTuple {
+    pub chain_id: U256,
+    pub address: Address,
+    pub nonce: u64,
+    pub y_parity: U256,
+    pub r_signature: U256,
+    pub s_signature: U256,
+}
+
+impl Transaction {
+    pub fn tx_type(&self) -> TxType {
+        match self {
+            Transaction::LegacyTransaction(_) => TxType::Legacy,
+            Transaction::EIP2930Transaction(_) => TxType::EIP2930,
+            Transaction::EIP1559Transaction(_) => TxType::EIP1559,
+            Transaction::EIP4844Transaction(_) => TxType::EIP4844,
+            Transaction::EIP7702Transaction(_) => TxType::EIP7702,
+        }
+    }
+
+    pub fn nonce(&self) -> u64 {
+        match self {
+            Transaction::LegacyTransaction(tx) => tx.nonce,
+            Transaction::EIP2930Transaction(tx) => tx.nonce,
+            Transaction::EIP1559Transaction(tx) => tx.nonce,
+            Transaction::EIP4844Transaction(tx) => tx.nonce,
+            Transaction::EIP7702Transaction(tx) => tx.nonce,
+        }
+    }
+
+    pub fn gas_limit(&self) -> u64 {
+        match self {
+            Transaction::LegacyTransaction(tx) => tx.gas,
+            Transaction::EIP2930Transaction(tx) => tx.gas_limit,
+            Transaction::EIP1559Transaction(tx) => tx.gas_limit,
+            Transaction::EIP4844Transaction(tx) => tx.gas,
+            Transaction::EIP7702Transaction(tx) => tx.gas_limit,
+        }
+    }
+
+    /// Gas price for pre-London transactions, max fee per gas for the rest.
+    pub fn gas_price(&self) -> U256 {
+        match self {
+            Transaction::LegacyTransaction(tx) => tx.gas_price,
+            Transaction::EIP2930Transaction(tx) => tx.gas_price,
+            Transaction::EIP1559Transaction(tx) => tx.max_fee_per_gas,
+            Transaction::EIP4844Transaction(tx) => tx.max_fee_per_gas,
+            Transaction::EIP7702Transaction(tx) => tx.max_fee_per_gas,
+        }
+    }
+
+    pub fn max_priority_fee(&self) -> Option<U256> {
+        match self {
+            Transaction::LegacyTransaction(_) | Transaction::EIP2930Transaction(_) => None,
+            Transaction::EIP1559Transaction(tx) => Some(tx.max_priority_fee_per_gas),
+            Transaction::EIP4844Transaction(tx) => Some(tx.max_priority_fee_per_gas),
+            Transaction::EIP7702Transaction(tx) => Some(tx.max_priority_fee_per_gas),
+        }
+    }
+
+    pub fn to(&self) -> TxKind {
+        match self {
+            Transaction::LegacyTransaction(tx) => tx.to,
+            Transaction::EIP2930Transaction(tx) => tx.to,
+            Transaction::EIP1559Transaction(tx) => tx.to,
+            Transaction::EIP4844Transaction(tx) => TxKind::Call(tx.to),
+            Transaction::EIP7702Transaction(tx) => TxKind::Call(tx.to),
+        }
+    }
+
+    pub fn value(&self) -> U256 {
+        match self {
+            Transaction::LegacyTransaction(tx) => tx.value,
+            Transaction::EIP2930Transaction(tx) => tx.value,
+            Transaction::EIP1559Transaction(tx) => tx.value,
+            Transaction::EIP4844Transaction(tx) => tx.value,
+            Transaction::EIP7702Transaction(tx) => tx.value,
+        }
+    }
+
+    pub fn data(&self) -> &Bytes {
+        match self {
+            Transaction::LegacyTransaction(tx) => &tx.data,
+            Transaction::EIP2930Transaction(tx) => &tx.data,
+            Transaction::EIP1559Transaction(tx) => &tx.data,
+            Transaction::EIP4844Transaction(tx) => &tx.data,
+            Transaction::EIP7702Transaction(tx) => &tx.data,
+        }
+    }
+
+    pub fn access_list(&self) -> &[(Address, Vec<H256>)] {
+        match self {
+            Transaction::LegacyTransaction(_) => &[],
+            Transaction::EIP2930Transaction(tx) => &tx.access_list,
+            Transaction::EIP1559Transaction(tx) => &tx.access_list,
+            Transaction::EIP4844Transaction(tx) => &tx.access_list,
+            Transaction::EIP7702Transaction(tx) => &tx.access_list,
+        }
+    }
+
+    pub fn blob_versioned_hashes(&self) -> &[H256] {
+        match self {
+            Transaction::EIP4844Transaction(tx) => &tx.blob_versioned_hashes,
+            _ => &[],
+        }
+    }
+
+    pub fn authorization_list(&self) -> Option<&[AuthorizationTuple]> {
+        match self {
+            Transaction::EIP7702Transaction(tx) => Some(&tx.authorization_list),
+            _ => None,
+        }
+    }
+
+    pub fn max_fee_per_blob_gas(&self) -> Option<U256> {
+        match self {
+            Transaction::EIP4844Transaction(tx) => Some(tx.max_fee_per_blob_gas),
+            _ => None,
+        }
+    }
+
+    /// Price paid per unit of gas in a block with the given base fee.
+    pub fn effective_gas_price(&self, base_fee_per_gas: Option<u64>) -> U256 {
+        match (self.max_priority_fee(), base_fee_per_gas) {
+            (Some(priority_fee), Some(base_fee)) => self
+                .gas_price()
+                .min(priority_fee.saturating_add(U256::from(base_fee))),
+            _ => self.gas_price(),
+        }
+    }
+
+    /// Chain the transaction is valid on, `None` for legacy transactions signed
+    /// without EIP-155 replay protection.
+    pub fn chain_id(&self) -> Option<u64> {
+        match self {
+            Transaction::LegacyTransaction(tx) if tx.v >= U256::from(35) => {
+                Some(((tx.v - 35) / 2).low_u64())
+            }
+            Transaction::LegacyTransaction(_) => None,
+            Transaction::EIP2930Transaction(tx) => Some(tx.chain_id),
+            Transaction::EIP1559Transaction(tx) => Some(tx.chain_id),
+            Transaction::EIP4844Transaction(tx) => Some(tx.chain_id),
+            Transaction::EIP7702Transaction(tx) => Some(tx.chain_id),
+        }
+    }
+
+    /// Signature as `(y_parity, r, s)`.
+    pub fn signature(&self) -> (bool, U256, U256) {
+        match self {
+            Transaction::LegacyTransaction(tx) => {
+                let offset = if tx.v >= U256::from(35) { 35 } else { 27 };
+                (
+                    ((tx.v.saturating_sub(U256::from(offset))) % 2).low_u64() == 1,
+                    tx.r,
+                    tx.s,
+                )
+            }
+            Transaction::EIP2930Transaction(tx) => {
+                (tx.signature_y_parity, tx.signature_r, tx.signature_s)
+            }
+            Transaction::EIP1559Transaction(tx) => {
+                (tx.signature_y_parity, tx.signature_r, tx.signature_s)
+            }
+            Transaction::EIP4844Transaction(tx) => {
+                (tx.signature_y_parity, tx.signature_r, tx.signature_s)
+            }
+            Transaction::EIP7702Transaction(tx) => {
+                (tx.signature_y_parity, tx.signature_r, tx.signature_s)
+            }
+        }
+    }
+
+    /// Hash the sender signs: the transaction without its signature, plus the chain id
+    /// for EIP-155 legacy transactions.
+    pub fn signing_hash(&self) -> H256 {
+        let mut buf = Vec::new();
+        match self {
+            Transaction::LegacyTransaction(tx) => tx.encode_unsigned(self.chain_id(), &mut buf),
+            Transaction::EIP2930Transaction(tx) => {
+                buf.put_u8(TxType::EIP2930 as u8);
+                tx.encode_unsigned(&mut buf);
+            }
+            Transaction::EIP1559Transaction(tx) => {
+                buf.put_u8(TxType::EIP1559 as u8);
+                tx.encode_unsigned(&mut buf);
+            }
+            Transaction::EIP4844Transaction(tx) => {
+                buf.put_u8(TxType::EIP4844 as u8);
+                tx.encode_unsigned(&mut buf);
+            }
+            Transaction::EIP7702Transaction(tx) => {
+                buf.put_u8(TxType::EIP7702 as u8);
+                tx.encode_unsigned(&mut buf);
+            }
+        }
+        keccak(buf)
+    }
+
+    /// Recovers the sender from the signature, `None` if it is invalid.
+    pub fn sender(&self) -> Option<Address> {
+        let (y_parity, r, s) = self.signature();
+        if let Transaction::LegacyTransaction(tx) = self {
+            if tx.v < U256::from(27) || (tx.v > U256::from(28) && tx.v < U256::from(35)) {
+                return None;
+            }
+        }
+        crypto::recover_address(self.signing_hash(), y_parity, r, s)
+    }
+
+    /// Signs the transaction. `chain_id` replaces the chain id of typed transactions;
+    /// legacy transactions are signed with EIP-155 replay protection when it is given
+    /// and without it otherwise.
+    pub fn sign(&mut self, secret_key: &[u8; 32], chain_id: Option<u64>) -> Option<()> {
+        match (&mut *self, chain_id) {
+            (Transaction::LegacyTransaction(tx), chain_id) => {
+                tx.v = chain_id.map_or(U256::from(27), |id| U256::from(id) * 2 + 35);
+            }
+            (Transaction::EIP2930Transaction(tx), Some(id)) => tx.chain_id = id,
+            (Transaction::EIP1559Transaction(tx), Some(id)) => tx.chain_id = id,
+            (Transaction::EIP4844Transaction(tx), Some(id)) => tx.chain_id = id,
+            (Transaction::EIP7702Transaction(tx), Some(id)) => tx.chain_id = id,
+            (_, None) => {}
+        }
+        let (y_parity, r, s) = crypto::sign(self.signing_hash(), secret_key)?;
+        match self {
+            Transaction::LegacyTransaction(tx) => {
+                tx.v += U256::from(y_parity as u8);
+                (tx.r, tx.s) = (r, s);
+            }
+            Transaction::EIP2930Transaction(tx) => {
+                (tx.signature_y_parity, tx.signature_r, tx.signature_s) = (y_parity, r, s)
+            }
+            Transaction::EIP1559Transaction(tx) => {
+                (tx.signature_y_parity, tx.signature_r, tx.signature_s) = (y_parity, r, s)
+            }
+            Transaction::EIP4844Transaction(tx) => {
+                (tx.signature_y_parity, tx.signature_r, tx.signature_s) = (y_parity, r, s)
+            }
+            Transaction::EIP7702Transaction(tx) => {
+                (tx.signature_y_parity, tx.signature_r, tx.signature_s) = (y_parity, r, s)
+            }
+        }
+        Some(())
+    }
+
+    /// Encodes the transaction as it is hashed and gossiped: plain RLP for legacy
+    /// transactions, `type || rlp(payload)` for typed ones.
+    pub fn encode_canonical(&self, buf: &mut dyn BufMut) {
+        match self {
+            Transaction::LegacyTransaction(tx) => tx.encode(buf),
+            Transaction::EIP2930Transaction(tx) => {
+                buf.put_u8(TxType::EIP2930 as u8);
+                tx.encode(buf);
+            }
+            Transaction::EIP1559Transaction(tx) => {
+                buf.put_u8(TxType::EIP1559 as u8);
+                tx.encode(buf);
+            }
+            Transaction::EIP4844Transaction(tx) => {
+                buf.put_u8(TxType::EIP4844 as u8);
+                tx.encode(buf);
+            }
+            Transaction::EIP7702Transaction(tx) => {
+                buf.put_u8(TxType::EIP7702 as u8);
+                tx.encode(buf);
+            }
+        }
+    }
+
+    pub fn encode_canonical_to_vec(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_canonical(&mut buf);
+        buf
+    }
+
+    pub fn decode_canonical(bytes: &[u8]) -> Result<Self, RLPDecodeError> {
+        let first = *bytes.first().ok_or(RLPDecodeError::InvalidLength)?;
+        if first >= 0xc0 {
+            return LegacyTransaction::decode(bytes).map(Transaction::LegacyTransaction);
+        }
+        let payload = &bytes[1..];
+        let tx = match TxType::try_from(first)? {
+            TxType::Legacy => return Err(RLPDecodeError::InvalidTransactionType),
+            TxType::EIP2930 => Transaction::EIP2930Transaction(RLPDecode::decode(payload)?),
+            TxType::EIP1559 => Transaction::EIP1559Transaction(RLPDecode::decode(payload)?),
+            TxType::EIP4844 => Transaction::EIP4844Transaction(RLPDecode::decode(payload)?),
+            TxType::EIP7702 => Transaction::EIP7702Transaction(RLPDecode::decode(payload)?),
+        };
+        Ok(tx)
+    }
+
+    pub fn compute_hash(&self) -> H256 {
+        keccak(self.encode_canonical_to_vec())
+    }
+}
+
+/// Inside lists (block bodies, network messages) typed transactions are wrapped in
+/// an RLP string while legacy ones are embedded as-is.
+impl RLPEncode for Transaction {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        match self {
+            Transaction::LegacyTransaction(tx) => tx.encode(buf),
+            _ => self.encode_canonical_to_vec().as_slice().encode(buf),
+        }
+    }
+}
+
+impl RLPDecode for Transaction {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let (is_list, payload, rest) = decode_rlp_item(rlp)?;
+        if is_list {
+            let (tx, rest) = LegacyTransaction::decode_unfinished(rlp)?;
+            return Ok((Transaction::LegacyTransaction(tx), rest));
+        }
+        Ok((Transaction::decode_canonical(payload)?, rest))
+    }
+}
+
+impl LegacyTransaction {
+    fn encode_unsigned(&self, chain_id: Option<u64>, buf: &mut dyn BufMut) {
+        let encoder = Encoder::new(buf)
+            .encode_field(&self.nonce)
+            .encode_field(&self.gas_price)
+            .encode_field(&self.gas)
+            .encode_field(&self.to)
+            .encode_field(&self.value)
+            .encode_field(&self.data);
+        match chain_id {
+            Some(chain_id) => encoder
+                .encode_field(&chain_id)
+                .encode_field(&0u8)
+                .encode_field(&0u8)
+                .finish(),
+            None => encoder.finish(),
+        }
+    }
+}
+
+impl RLPEncode for LegacyTransaction {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.nonce)
+            .encode_field(&self.gas_price)
+            .encode_field(&self.gas)
+            .encode_field(&self.to)
+            .encode_field(&self.value)
+            .encode_field(&self.data)
+            .encode_field(&self.v)
+            .encode_field(&self.r)
+            .encode_field(&self.s)
+            .finish();
+    }
+}
+
+impl RLPDecode for LegacyTransaction {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let decoder = Decoder::new(rlp)?;
+        let (nonce, decoder) = decoder.decode_field("nonce")?;
+        let (gas_price, decoder) = decoder.decode_field("gas_price")?;
+        let (gas, decoder) = decoder.decode_field("gas")?;
+        let (to, decoder) = decoder.decode_field("to")?;
+        let (value, decoder) = decoder.decode_field("value")?;
+        let (data, decoder) = decoder.decode_field("data")?;
+        let (v, decoder) = decoder.decode_field("v")?;
+        let (r, decoder) = decoder.decode_field("r")?;
+        let (s, decoder) = decoder.decode_field("s")?;
+        let rest = decoder.finish()?;
+        let tx = LegacyTransaction {
+            nonce,
+            gas_price,
+            gas,
+            to,
+            value,
+            data,
+            v,
+            r,
+            s,
+        };
+        Ok((tx, rest))
+    }
+}
+
+impl EIP2930Transaction {
+    fn encode_unsigned(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.chain_id)
+            .encode_field(&self.nonce)
+            .encode_field(&self.gas_price)
+            .encode_field(&self.gas_limit)
+            .encode_field(&self.to)
+            .encode_field(&self.value)
+            .encode_field(&self.data)
+            .encode_field(&self.access_list)
+            .finish();
+    }
+}
+
+impl RLPEncode for EIP2930Transaction {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.chain_id)
+            .encode_field(&self.nonce)
+            .encode_field(&self.gas_price)
+            .encode_field(&self.gas_limit)
+            .encode_field(&self.to)
+            .encode_field(&self.value)
+            .encode_field(&self.data)
+            .encode_field(&self.access_list)
+            .encode_field(&self.signature_y_parity)
+            .encode_field(&self.signature_r)
+            .encode_field(&self.signature_s)
+            .finish();
+    }
+}
+
+impl RLPDecode for EIP2930Transaction {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let decoder = Decoder::new(rlp)?;
+        let (chain_id, decoder) = decoder.decode_field("chain_id")?;
+        let (nonce, decoder) = decoder.decode_field("nonce")?;
+        let (gas_price, decoder) = decoder.decode_field("gas_price")?;
+        let (gas_limit, decoder) = decoder.decode_field("gas_limit")?;
+        let (to, decoder) = decoder.decode_field("to")?;
+        let (value, decoder) = decoder.decode_field("value")?;
+        let (data, decoder) = decoder.decode_field("data")?;
+        let (access_list, decoder) = decoder.decode_field("access_list")?;
+        let (signature_y_parity, decoder) = decoder.decode_field("signature_y_parity")?;
+        let (signature_r, decoder) = decoder.decode_field("signature_r")?;
+        let (signature_s, decoder) = decoder.decode_field("signature_s")?;
+        let rest = decoder.finish()?;
+        let tx = EIP2930Transaction {
+            chain_id,
+            nonce,
+            gas_price,
+            gas_limit,
+            to,
+            value,
+            data,
+            access_list,
+            signature_y_parity,
+            signature_r,
+            signature_s,
+        };
+        Ok((tx, rest))
+    }
+}
+
+impl EIP1559Transaction {
+    fn encode_unsigned(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.chain_id)
+            .encode_field(&self.nonce)
+            .encode_field(&self.max_priority_fee_per_gas)
+            .encode_field(&self.max_fee_per_gas)
+            .encode_field(&self.gas_limit)
+            .encode_field(&self.to)
+            .encode_field(&self.value)
+            .encode_field(&self.data)
+            .encode_field(&self.access_list)
+            .finish();
+    }
+}
+
+impl RLPEncode for EIP1559Transaction {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.chain_id)
+            .encode_field(&self.nonce)
+            .encode_field(&self.max_priority_fee_per_gas)
+            .encode_field(&self.max_fee_per_gas)
+            .encode_field(&self.gas_limit)
+            .encode_field(&self.to)
+            .encode_field(&self.value)
+            .encode_field(&self.data)
+            .encode_field(&self.access_list)
+            .encode_field(&self.signature_y_parity)
+            .encode_field(&self.signature_r)
+            .encode_field(&self.signature_s)
+            .finish();
+    }
+}
+
+impl RLPDecode for EIP1559Transaction {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let decoder = Decoder::new(rlp)?;
+        let (chain_id, decoder) = decoder.decode_field("chain_id")?;
+        let (nonce, decoder) = decoder.decode_field("nonce")?;
+        let (max_priority_fee_per_gas, decoder) =
+            decoder.decode_field("max_priority_fee_per_gas")?;
+        let (max_fee_per_gas, decoder) = decoder.decode_field("max_fee_per_gas")?;
+        let (gas_limit, decoder) = decoder.decode_field("gas_limit")?;
+        let (to, decoder) = decoder.decode_field("to")?;
+        let (value, decoder) = decoder.decode_field("value")?;
+        let (data, decoder) = decoder.decode_field("data")?;
+        let (access_list, decoder) = decoder.decode_field("access_list")?;
+        let (signature_y_parity, decoder) = decoder.decode_field("signature_y_parity")?;
+        let (signature_r, decoder) = decoder.decode_field("signature_r")?;
+        let (signature_s, decoder) = decoder.decode_field("signature_s")?;
+        let rest = decoder.finish()?;
+        let tx = EIP1559Transaction {
+            chain_id,
+            nonce,
+            max_priority_fee_per_gas,
+            max_fee_per_gas,
+            gas_limit,
+            to,
+            value,
+            data,
+            access_list,
+            signature_y_parity,
+            signature_r,
+            signature_s,
+        };
+        Ok((tx, rest))
+    }
+}
+
+impl EIP4844Transaction {
+    fn encode_unsigned(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.chain_id)
+            .encode_field(&self.nonce)
+            .encode_field(&self.max_priority_fee_per_gas)
+            .encode_field(&self.max_fee_per_gas)
+            .encode_field(&self.gas)
+            .encode_field(&self.to)
+            .encode_field(&self.value)
+            .encode_field(&self.data)
+            .encode_field(&self.access_list)
+            .encode_field(&self.max_fee_per_blob_gas)
+            .encode_field(&self.blob_versioned_hashes)
+            .finish();
+    }
+}
+
+impl RLPEncode for EIP4844Transaction {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.chain_id)
+            .encode_field(&self.nonce)
+            .encode_field(&self.max_priority_fee_per_gas)
+            .encode_field(&self.max_fee_per_gas)
+            .encode_field(&self.gas)
+            .encode_field(&self.to)
+            .encode_field(&self.value)
+            .encode_field(&self.data)
+            .encode_field(&self.access_list)
+            .encode_field(&self.max_fee_per_blob_gas)
+            .encode_field(&self.blob_versioned_hashes)
+            .encode_field(&self.signature_y_parity)
+            .encode_field(&self.signature_r)
+            .encode_field(&self.signature_s)
+            .finish();
+    }
+}
+
+impl RLPDecode for EIP4844Transaction {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let decoder = Decoder::new(rlp)?;
+        let (chain_id, decoder) = decoder.decode_field("chain_id")?;
+        let (nonce, decoder) = decoder.decode_field("nonce")?;
+        let (max_priority_fee_per_gas, decoder) =
+            decoder.decode_field("max_priority_fee_per_gas")?;
+        let (max_fee_per_gas, decoder) = decoder.decode_field("max_fee_per_gas")?;
+        let (gas, decoder) = decoder.decode_field("gas")?;
+        let (to, decoder) = decoder.decode_field("to")?;
+        let (value, decoder) = decoder.decode_field("value")?;
+        let (data, decoder) = decoder.decode_field("data")?;
+        let (access_list, decoder) = decoder.decode_field("access_list")?;
+        let (max_fee_per_blob_gas, decoder) = decoder.decode_field("max_fee_per_blob_gas")?;
+        let (blob_versioned_hashes, decoder) = decoder.decode_field("blob_versioned_hashes")?;
+        let (signature_y_parity, decoder) = decoder.decode_field("signature_y_parity")?;
+        let (signature_r, decoder) = decoder.decode_field("signature_r")?;
+        let (signature_s, decoder) = decoder.decode_field("signature_s")?;
+        let rest = decoder.finish()?;
+        let tx = EIP4844Transaction {
+            chain_id,
+            nonce,
+            max_priority_fee_per_gas,
+            max_fee_per_gas,
+            gas,
+            to,
+            value,
+            data,
+            access_list,
+            max_fee_per_blob_gas,
+            blob_versioned_hashes,
+            signature_y_parity,
+            signature_r,
+            signature_s,
+        };
+        Ok((tx, rest))
+    }
+}
+
+impl EIP7702Transaction {
+    fn encode_unsigned(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.chain_id)
+            .encode_field(&self.nonce)
+            .encode_field(&self.max_priority_fee_per_gas)
+            .encode_field(&self.max_fee_per_gas)
+            .encode_field(&self.gas_limit)
+            .encode_field(&self.to)
+            .encode_field(&self.value)
+            .encode_field(&self.data)
+            .encode_field(&self.access_list)
+            .encode_field(&self.authorization_list)
+            .finish();
+    }
+}
+
+impl RLPEncode for EIP7702Transaction {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.chain_id)
+            .encode_field(&self.nonce)
+            .encode_field(&self.max_priority_fee_per_gas)
+            .encode_field(&self.max_fee_per_gas)
+            .encode_field(&self.gas_limit)
+            .encode_field(&self.to)
+            .encode_field(&self.value)
+            .encode_field(&self.data)
+            .encode_field(&self.access_list)
+            .encode_field(&self.authorization_list)
+            .encode_field(&self.signature_y_parity)
+            .encode_field(&self.signature_r)
+            .encode_field(&self.signature_s)
+            .finish();
+    }
+}
+
+impl RLPDecode for EIP7702Transaction {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let decoder = Decoder::new(rlp)?;
+        let (chain_id, decoder) = decoder.decode_field("chain_id")?;
+        let (nonce, decoder) = decoder.decode_field("nonce")?;
+        let (max_priority_fee_per_gas, decoder) =
+            decoder.decode_field("max_priority_fee_per_gas")?;
+        let (max_fee_per_gas, decoder) = decoder.decode_field("max_fee_per_gas")?;
+        let (gas_limit, decoder) = decoder.decode_field("gas_limit")?;
+        let (to, decoder) = decoder.decode_field("to")?;
+        let (value, decoder) = decoder.decode_field("value")?;
+        let (data, decoder) = decoder.decode_field("data")?;
+        let (access_list, decoder) = decoder.decode_field("access_list")?;
+        let (authorization_list, decoder) = decoder.decode_field("authorization_list")?;
+        let (signature_y_parity, decoder) = decoder.decode_field("signature_y_parity")?;
+        let (signature_r, decoder) = decoder.decode_field("signature_r")?;
+        let (signature_s, decoder) = decoder.decode_field("signature_s")?;
+        let rest = decoder.finish()?;
+        let tx = EIP7702Transaction {
+            chain_id,
+            nonce,
+            max_priority_fee_per_gas,
+            max_fee_per_gas,
+            gas_limit,
+            to,
+            value,
+            data,
+            access_list,
+            authorization_list,
+            signature_y_parity,
+            signature_r,
+            signature_s,
+        };
+        Ok((tx, rest))
+    }
+}
+
+impl AuthorizationTuple {
+    /// Hash the authority signs: the tuple without its signature, behind the EIP-7702
+    /// magic byte.
+    pub fn signing_hash(&self) -> H256 {
+        let mut buf = vec![AUTHORIZATION_MAGIC];
+        Encoder::new(&mut buf)
+            .encode_field(&self.chain_id)
+            .encode_field(&self.address)
+            .encode_field(&self.nonce)
+            .finish();
+        keccak(buf)
+    }
+
+    /// Recovers the account that signed the authorization, `None` if the signature is
+    /// invalid, including one with a high `s` value (EIP-2).
+    pub fn authority(&self) -> Option<Address> {
+        if self.y_parity > U256::one() || self.s_signature > crypto::SECP256K1N_HALF {
+            return None;
+        }
+        crypto::recover_address(
+            self.signing_hash(),
+            self.y_parity == U256::one(),
+            self.r_signature,
+            self.s_signature,
+        )
+    }
+
+    pub fn sign(&mut self, secret_key: &[u8; 32]) -> Option<()> {
+        let (y_parity, r, s) = crypto::sign(self.signing_hash(), secret_key)?;
+        (self.y_parity, self.r_signature, self.s_signature) = (U256::from(y_parity as u8), r, s);
+        Some(())
+    }
+}
+
+impl RLPEncode for AuthorizationTuple {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.chain_id)
+            .encode_field(&self.address)
+            .encode_field(&self.nonce)
+            .encode_field(&self.y_parity)
+            .encode_field(&self.r_signature)
+            .encode_field(&self.s_signature)
+            .finish();
+    }
+}
+
+impl RLPDecode for AuthorizationTuple {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let decoder = Decoder::new(rlp)?;
+        let (chain_id, decoder) = decoder.decode_field("chain_id")?;
+        let (address, decoder) = decoder.decode_field("address")?;
+        let (nonce, decoder) = decoder.decode_field("nonce")?;
+        let (y_parity, decoder) = decoder.decode_field("y_parity")?;
+        let (r_signature, decoder) = decoder.decode_field("r_signature")?;
+        let (s_signature, decoder) = decoder.decode_field("s_signature")?;
+        let rest = decoder.finish()?;
+        let tuple = AuthorizationTuple {
+            chain_id,
+            address,
+            nonce,
+            y_parity,
+            r_signature,
+            s_signature,
+        };
+        Ok((tuple, rest))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hex_literal::hex;
+
+    use super::*;
+
+    #[test]
+    fn legacy_transaction_hash() {
+        // First transaction on mainnet, block 46147.
+        let tx = Transaction::LegacyTransaction(LegacyTransaction {
+            nonce: 0,
+            gas_price: U256::from(50_000_000_000_000u64),
+            gas: 21000,
+            to: TxKind::Call(Address::from(hex!(
+                "5df9b87991262f6ba471f09758cde1c0fc1de734"
+            ))),
+            value: U256::from(31337),
+            data: Bytes::new(),
+            v: U256::from(28),
+            r: U256::from_big_endian(&hex!(
+                "88ff6cf0fefd94db46111149ae4bfc179e9b94721fffd821d38d16464b3f71d0"
+            )),
+            s: U256::from_big_endian(&hex!(
+                "45e0aff800961cfce805daef7016b9b675c137a6a41a548f7b60a3484c06a33a"
+            )),
+        });
+        assert_eq!(
+            tx.compute_hash(),
+            H256(hex!(
+                "5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060"
+            ))
+        );
+    }
+
+    #[test]
+    fn recovers_legacy_sender() {
+        let tx = Transaction::LegacyTransaction(LegacyTransaction {
+            gas_price: U256::from(50_000_000_000_000u64),
+            gas: 21000,
+            to: TxKind::Call(Address::from(hex!(
+                "5df9b87991262f6ba471f09758cde1c0fc1de734"
+            ))),
+            value: U256::from(31337),
+            v: U256::from(28),
+            r: U256::from_big_endian(&hex!(
+                "88ff6cf0fefd94db46111149ae4bfc179e9b94721fffd821d38d16464b3f71d0"
+            )),
+            s: U256::from_big_endian(&hex!(
+                "45e0aff800961cfce805daef7016b9b675c137a6a41a548f7b60a3484c06a33a"
+            )),
+            ..Default::default()
+        });
+        assert_eq!(tx.chain_id(), None);
+        assert_eq!(
+            tx.sender(),
+            Some(Address::from(hex!(
+                "a1e4380a3b1f749673e270229993ee55f35663b4"
+            )))
+        );
+    }
+
+    #[test]
+    fn eip155_signing_hash() {
+        // Example from EIP-155.
+        let mut tx = Transaction::LegacyTransaction(LegacyTransaction {
+            nonce: 9,
+            gas_price: U256::from(20_000_000_000u64),
+            gas: 21000,
+            to: TxKind::Call(Address::from(hex!(
+                "3535353535353535353535353535353535353535"
+            ))),
+            value: U256::from(1_000_000_000_000_000_000u64),
+            ..Default::default()
+        });
+        tx.sign(&[0x46; 32], Some(1)).unwrap();
+        assert_eq!(tx.chain_id(), Some(1));
+        assert_eq!(
+            tx.signing_hash(),
+            H256(hex!(
+                "daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53"
+            ))
+        );
+        let Transaction::LegacyTransaction(legacy) = &tx else {
+            unreachable!()
+        };
+        assert_eq!(legacy.v, U256::from(37));
+        assert_eq!(
+            tx.sender(),
+            Some(Address::from(hex!(
+                "9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f"
+            )))
+        );
+    }
+
+    #[test]
+    fn signs_typed_transactions() {
+        let secret_key = [0x11; 32];
+        let mut tx = Transaction::EIP1559Transaction(EIP1559Transaction {
+            nonce: 3,
+            max_priority_fee_per_gas: U256::from(2),
+            max_fee_per_gas: U256::from(10),
+            gas_limit: 21000,
+            ..Default::default()
+        });
+        tx.sign(&secret_key, Some(5)).unwrap();
+        assert_eq!(tx.chain_id(), Some(5));
+        assert_eq!(tx.sender(), crypto::secret_key_to_address(&secret_key));
+        assert_eq!(tx.effective_gas_price(Some(7)), U256::from(9));
+        assert_eq!(tx.effective_gas_price(Some(9)), U256::from(10));
+    }
+
+    #[test]
+    fn recovers_authority() {
+        let secret_key = [0x22; 32];
+        let mut tuple = AuthorizationTuple {
+            chain_id: U256::one(),
+            address: Address::repeat_byte(0x33),
+            nonce: 4,
+            ..Default::default()
+        };
+        tuple.sign(&secret_key).unwrap();
+        assert_eq!(
+            tuple.authority(),
+            crypto::secret_key_to_address(&secret_key)
+        );
+        let mut other_nonce = tuple.clone();
+        other_nonce.nonce = 5;
+        assert_ne!(other_nonce.authority(), tuple.authority());
+        // The same signature with the other s value does not count
+        let mut high_s = tuple.clone();
+        high_s.s_signature = crypto::SECP256K1N_HALF * 2 + 1 - tuple.s_signature;
+        high_s.y_parity = U256::one() - tuple.y_parity;
+        assert_eq!(high_s.authority(), None);
+        tuple.y_parity = U256::from(2);
+        assert_eq!(tuple.authority(), None);
+    }
+
+    #[test]
+    fn typed_transactions_roundtrip_inside_lists() {
+        let txs = vec![
+            Transaction::EIP1559Transaction(EIP1559Transaction {
+                chain_id: 1,
+                nonce: 7,
+                max_fee_per_gas: U256::from(100),
+                gas_limit: 21000,
+                to: TxKind::Call(Address::repeat_byte(0x11)),
+                access_list: vec![(Address::repeat_byte(0x22), vec![H256::repeat_byte(3)])],
+                ..Default::default()
+            }),
+            Transaction::EIP4844Transaction(EIP4844Transaction {
+                chain_id: 1,
+                blob_versioned_hashes: vec![H256::repeat_byte(1)],
+                ..Default::default()
+            }),
+            Transaction::EIP7702Transaction(EIP7702Transaction {
+                chain_id: 1,
+                authorization_list: vec![AuthorizationTuple {
+                    chain_id: U256::one(),
+                    address: Address::repeat_byte(0x33),
+                    nonce: 1,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+        ];
+        let encoded = txs.encode_to_vec();
+        assert_eq!(Vec::<Transaction>::decode(&encoded).unwrap(), txs);
+        for tx in &txs {
+            let canonical = tx.encode_canonical_to_vec();
+            assert_eq!(canonical[0], tx.tx_type() as u8);
+            assert_eq!(&Transaction::decode_canonical(&canonical).unwrap(), tx);
+        }
+    }
+}
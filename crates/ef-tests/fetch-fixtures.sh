@@ -0,0 +1,78 @@
+#!/usr/bin/env bash
+# Fetches the pinned subset of the official test fixtures into fixtures/, where the
+# ignored corpus tests of this crate look for them:
+#
+#     crates/ef-tests/fetch-fixtures.sh
+#     cargo test -p ef-tests -- --ignored --nocapture
+#
+# which prints the passed and failed cases of each fork. Each release archive must
+# match its line in fixtures.sha256, and the script stops at any archive without one.
+# After moving a pin to another release, run it with --pin once to record the new
+# archive's checksum, and commit it along with the pin.
+set -euo pipefail
+
+pin=false
+if [[ "${1:-}" == --pin ]]; then
+    pin=true
+fi
+
+# ethereum/tests, for the GeneralStateTests. The corpus test leaves out the slow
+# directories listed in `EXCLUDED` in src/state_test.rs.
+TESTS_TAG=v13.3
+TESTS_SUBSET=(
+    GeneralStateTests
+)
+# execution-spec-tests, whose blockchain tests name the exception each invalid block
+# must be rejected for
+EEST_TAG=v4.1.0
+EEST_SUBSET=(
+    blockchain_tests/frontier/opcodes
+    blockchain_tests/berlin/eip2930_access_list
+    blockchain_tests/shanghai/eip3860_initcode
+    blockchain_tests/shanghai/eip4895_withdrawals
+    blockchain_tests/cancun/eip1153_tstore
+    blockchain_tests/cancun/eip4844_blobs
+)
+# the EOF validation tests are only in the fixtures of forks under development
+EEST_DEVELOP_SUBSET=(
+    eof_tests/osaka/eip7692_eof_v1
+)
+
+dir="$(cd "$(dirname "$0")" && pwd)"
+archive="$(mktemp)"
+trap 'rm -f "$archive"' EXIT
+
+# Downloads `url` as `name`, checks it and extracts the subset given after the
+# archive's top-level directory.
+fetch() {
+    local name="$1" url="$2" top="$3"
+    shift 3
+    curl -fsSL "$url" -o "$archive"
+    local checksum
+    checksum="$(sha256sum "$archive" | cut -d ' ' -f 1)  $name"
+    if grep -q "  $name\$" "$dir/fixtures.sha256" 2>/dev/null; then
+        if ! grep -qx "$checksum" "$dir/fixtures.sha256"; then
+            echo "$name does not match fixtures.sha256: $checksum" >&2
+            exit 1
+        fi
+    elif $pin; then
+        echo "$checksum" >>"$dir/fixtures.sha256"
+        echo "recorded the checksum of $name in fixtures.sha256, commit it" >&2
+    else
+        echo "$name has no checksum in fixtures.sha256, run with --pin to record it" >&2
+        exit 1
+    fi
+    tar -xzf "$archive" -C "$dir/fixtures" --strip-components 1 "${@/#/$top/}"
+}
+
+rm -rf "$dir/fixtures"
+mkdir "$dir/fixtures"
+fetch "tests-$TESTS_TAG.tar.gz" \
+    "https://github.com/ethereum/tests/archive/refs/tags/$TESTS_TAG.tar.gz" \
+    "tests-${TESTS_TAG#v}" "${TESTS_SUBSET[@]}"
+fetch "execution-spec-tests-$EEST_TAG-fixtures_stable.tar.gz" \
+    "https://github.com/ethereum/execution-spec-tests/releases/download/$EEST_TAG/fixtures_stable.tar.gz" \
+    fixtures "${EEST_SUBSET[@]}"
+fetch "execution-spec-tests-$EEST_TAG-fixtures_develop.tar.gz" \
+    "https://github.com/ethereum/execution-spec-tests/releases/download/$EEST_TAG/fixtures_develop.tar.gz" \
+    fixtures "${EEST_DEVELOP_SUBSET[@]}"
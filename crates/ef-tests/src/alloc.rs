@@ -0,0 +1,210 @@
+//! Accounts as the fixtures list them before and after a test, and the state root
+//! they hash to.
+
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use evm::MemoryDb;
+use keccak_hash::keccak;
+use reec_core::{
+    rlp::{encode::RLPEncode, structs::Encoder},
+    trie::trie_root,
+    types::{AccountDiff, AccountInfo, StateDiff, EMPTY_CODE_HASH},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::serde_hex;
+
+/// An account, written with its empty fields left out.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Account {
+    #[serde(
+        default,
+        deserialize_with = "serde_hex::u64",
+        serialize_with = "serde_hex::serialize_u64",
+        skip_serializing_if = "is_zero"
+    )]
+    pub nonce: u64,
+    #[serde(default, deserialize_with = "serde_hex::u256")]
+    pub balance: U256,
+    #[serde(
+        default,
+        deserialize_with = "serde_hex::bytes",
+        serialize_with = "serde_hex::serialize_bytes",
+        skip_serializing_if = "Bytes::is_empty"
+    )]
+    pub code: Bytes,
+    #[serde(
+        default,
+        deserialize_with = "serde_hex::storage",
+        serialize_with = "serde_hex::serialize_storage",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub storage: BTreeMap<H256, U256>,
+}
+
+impl Account {
+    pub fn code_hash(&self) -> H256 {
+        if self.code.is_empty() {
+            EMPTY_CODE_HASH
+        } else {
+            keccak(&self.code)
+        }
+    }
+
+    pub fn storage_root(&self) -> H256 {
+        trie_root(
+            self.storage
+                .iter()
+                .filter(|(_, value)| !value.is_zero())
+                .map(|(slot, value)| (keccak(slot), value.encode_to_vec())),
+        )
+    }
+
+    /// The account as the state trie holds it.
+    fn trie_value(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        Encoder::new(&mut buf)
+            .encode_field(&self.nonce)
+            .encode_field(&self.balance)
+            .encode_field(&self.storage_root())
+            .encode_field(&self.code_hash())
+            .finish();
+        buf
+    }
+}
+
+/// Every account of a state, by address.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Alloc(pub BTreeMap<Address, Account>);
+
+impl Alloc {
+    pub fn to_db(&self) -> MemoryDb {
+        let mut db = MemoryDb::default();
+        for (address, account) in &self.0 {
+            db.insert_account(
+                *address,
+                account.nonce,
+                account.balance,
+                account.code.clone(),
+            );
+            for (slot, value) in &account.storage {
+                db.insert_storage(*address, *slot, *value);
+            }
+        }
+        db
+    }
+
+    /// The state as a diff against an empty one, to be written to a store.
+    pub fn to_state_diff(&self) -> StateDiff {
+        self.0
+            .iter()
+            .map(|(address, account)| {
+                let info = AccountInfo {
+                    nonce: account.nonce,
+                    balance: account.balance,
+                    code_hash: account.code_hash(),
+                };
+                let diff = AccountDiff {
+                    info: Some(info),
+                    storage_cleared: false,
+                    storage: account.storage.clone(),
+                    code: (!account.code.is_empty()).then(|| account.code.clone()),
+                };
+                (*address, diff)
+            })
+            .collect()
+    }
+
+    /// Applies the changes made by executing transactions or blocks on top of this state.
+    pub fn apply(&mut self, diff: &StateDiff) {
+        for (address, diff) in diff {
+            let Some(info) = &diff.info else {
+                self.0.remove(address);
+                continue;
+            };
+            let account = self.0.entry(*address).or_default();
+            if diff.storage_cleared {
+                account.storage.clear();
+            }
+            for (slot, value) in &diff.storage {
+                if value.is_zero() {
+                    account.storage.remove(slot);
+                } else {
+                    account.storage.insert(*slot, *value);
+                }
+            }
+            account.nonce = info.nonce;
+            account.balance = info.balance;
+            if let Some(code) = &diff.code {
+                account.code = code.clone();
+            }
+            if info.code_hash == EMPTY_CODE_HASH {
+                account.code = Bytes::new();
+            }
+        }
+    }
+
+    pub fn state_root(&self) -> H256 {
+        trie_root(
+            self.0
+                .iter()
+                .map(|(address, account)| (keccak(address), account.trie_value())),
+        )
+    }
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+#[cfg(test)]
+mod test {
+    use hex_literal::hex;
+    use reec_core::trie::EMPTY_TRIE_ROOT;
+
+    use super::*;
+
+    #[test]
+    fn applies_diffs() {
+        let address = Address::repeat_byte(1);
+        let slot = H256::from_low_u64_be(1);
+        let mut alloc = Alloc::default();
+        alloc.0.insert(
+            address,
+            Account {
+                nonce: 1,
+                storage: [(slot, U256::one())].into(),
+                ..Default::default()
+            },
+        );
+        assert_ne!(alloc.0[&address].storage_root(), EMPTY_TRIE_ROOT);
+
+        let code = Bytes::from_static(&hex!("6000"));
+        let info = reec_core::types::AccountInfo {
+            nonce: 2,
+            balance: U256::from(5),
+            code_hash: keccak(&code),
+        };
+        let diff = AccountDiff {
+            info: Some(info),
+            storage_cleared: false,
+            storage: [(slot, U256::zero())].into(),
+            code: Some(code.clone()),
+        };
+        alloc.apply(&[(address, diff)].into());
+        let expected = Account {
+            nonce: 2,
+            balance: U256::from(5),
+            code,
+            storage: BTreeMap::new(),
+        };
+        assert_eq!(alloc.0[&address], expected);
+        assert_eq!(expected.storage_root(), EMPTY_TRIE_ROOT);
+
+        alloc.apply(&[(address, AccountDiff::default())].into());
+        assert_eq!(alloc.state_root(), EMPTY_TRIE_ROOT);
+    }
+}
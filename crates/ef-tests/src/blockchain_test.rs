@@ -0,0 +1,640 @@
+//! BlockchainTests: a chain imported block by block from its genesis into a fresh store,
+//! with every block validated and executed as a synced block would be. Blocks may
+//! branch off anywhere below the head, the store switching to whichever branch the fork
+//! choice picks.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Context};
+use bytes::Bytes;
+use ethereum_types::{H256, U256};
+use evm::{
+    block::validate_header,
+    config::{Activation, ChainConfig},
+    execute_block, execute_block_parallel, BlockResult, Fork, InvalidTransaction,
+};
+use reec_core::{
+    rlp::{decode::RLPDecode, error::RLPDecodeError},
+    types::{Block, InvalidBlockError, Receipt, StateDiff},
+};
+use serde::Deserialize;
+use storage::Store;
+
+use crate::{serde_hex, Alloc, CaseResult};
+
+/// Thread counts the parallel executor runs on, each checked against sequential
+/// execution.
+const PARALLEL_THREADS: [usize; 4] = [1, 2, 4, 8];
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockchainTest {
+    /// Fork, or transition between two forks, the chain runs.
+    pub network: String,
+    #[serde(rename = "genesisRLP", deserialize_with = "serde_hex::bytes")]
+    pub genesis_rlp: Bytes,
+    pub pre: Alloc,
+    pub blocks: Vec<TestBlock>,
+    pub lastblockhash: H256,
+    /// Accounts after the last block. Fixtures with too many give only their state
+    /// root in `post_state_hash`.
+    pub post_state: Option<Alloc>,
+    pub post_state_hash: Option<H256>,
+    pub config: Option<crate::state_test::TestConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestBlock {
+    #[serde(deserialize_with = "serde_hex::bytes")]
+    pub rlp: Bytes,
+    /// Set if the block must be rejected, with the reasons it may be rejected for.
+    pub expect_exception: Option<String>,
+}
+
+/// A valid block, on the canonical chain or a side chain, with what it takes to switch
+/// the store to a branch through it.
+struct ImportedBlock {
+    number: u64,
+    parent_hash: H256,
+    total_difficulty: U256,
+    state_diff: StateDiff,
+    /// Accounts after the block, from which the state root is computed.
+    state: Alloc,
+}
+
+impl BlockchainTest {
+    pub fn run(&self, name: &str) -> CaseResult {
+        CaseResult {
+            name: name.to_string(),
+            fork: self.network.clone(),
+            error: self.run_chain().err().map(|err| format!("{err:#}")),
+        }
+    }
+
+    fn run_chain(&self) -> anyhow::Result<()> {
+        let chain_id = self.config.as_ref().map_or(1, |config| config.chainid);
+        let config = chain_config(&self.network, chain_id)?;
+        let datadir = tempfile::tempdir()?;
+        let store = Store::new(datadir.path())?;
+
+        let genesis = Block::decode(&self.genesis_rlp).context("undecodable genesis")?;
+        if self.pre.state_root() != genesis.header.state_root {
+            bail!("pre-state does not match the genesis state root");
+        }
+        let genesis_hash = genesis.hash();
+        store.write_state(0, &self.pre.to_state_diff())?;
+        store.add_block(genesis.header.clone(), genesis.body)?;
+        store.set_canonical_head(genesis_hash)?;
+
+        let mut blocks = HashMap::from([(
+            genesis_hash,
+            ImportedBlock {
+                number: 0,
+                parent_hash: genesis.header.parent_hash,
+                total_difficulty: genesis.header.difficulty,
+                state_diff: self.pre.to_state_diff(),
+                state: self.pre.clone(),
+            },
+        )]);
+        for (index, block) in self.blocks.iter().enumerate() {
+            match (
+                import_block(&store, &config, &mut blocks, &block.rlp),
+                &block.expect_exception,
+            ) {
+                (Ok(()), None) => {}
+                (Ok(()), Some(expected)) => {
+                    bail!("block {index} was imported, expected {expected}")
+                }
+                (Err(err), Some(expected)) if !is_expected(&err, expected) => {
+                    bail!("block {index} was rejected for {err:#}, expected {expected}")
+                }
+                (Err(_), Some(_)) => {}
+                (Err(err), None) => return Err(err.context(format!("block {index}"))),
+            }
+        }
+
+        let head = head_hash(&store)?;
+        if head != self.lastblockhash {
+            bail!(
+                "head mismatch: expected {:#x}, got {head:#x}",
+                self.lastblockhash
+            );
+        }
+        if let Some(post_state) = &self.post_state {
+            check_post_state(&store, post_state)?;
+        }
+        let root = blocks[&head].state.state_root();
+        let expected_root = self
+            .post_state
+            .as_ref()
+            .map(Alloc::state_root)
+            .or(self.post_state_hash);
+        if expected_root.is_some_and(|expected| expected != root) {
+            bail!("post-state root mismatch: got {root:#x}");
+        }
+        Ok(())
+    }
+}
+
+/// Decodes, validates and executes a block on top of its parent, then stores it and
+/// makes it the head if the fork choice picks it. A block whose parent is not the head
+/// is executed after switching the store to the parent's branch, and the store switches
+/// back unless the block becomes the head.
+fn import_block(
+    store: &Store,
+    config: &ChainConfig,
+    blocks: &mut HashMap<H256, ImportedBlock>,
+    rlp: &[u8],
+) -> anyhow::Result<()> {
+    let block = Block::decode(rlp)?;
+    let hash = block.hash();
+    if blocks.contains_key(&hash) {
+        return Ok(());
+    }
+    let head = head_hash(store)?;
+    let parent_hash = block.header.parent_hash;
+    let parent = blocks
+        .get(&parent_hash)
+        .ok_or_else(|| anyhow!("unknown parent {parent_hash:#x}"))?;
+    if parent_hash != head {
+        switch_branch(store, blocks, parent_hash)?;
+    }
+    let (imported, receipts) = match execute_on_parent(store, config, &block, parent) {
+        Ok(result) => result,
+        Err(err) => {
+            switch_branch(store, blocks, head)?;
+            return Err(err);
+        }
+    };
+
+    let fork = config.fork(block.header.number, block.header.timestamp);
+    store.add_head_block(block, receipts, &imported.state_diff)?;
+    let new_head = is_new_head(fork, &imported, &blocks[&head]);
+    blocks.insert(hash, imported);
+    if !new_head {
+        switch_branch(store, blocks, head)?;
+    }
+    Ok(())
+}
+
+/// Validates `block` and executes it on the state of `parent`, which the store must
+/// have as its head.
+fn execute_on_parent(
+    store: &Store,
+    config: &ChainConfig,
+    block: &Block,
+    parent: &ImportedBlock,
+) -> anyhow::Result<(ImportedBlock, Vec<Receipt>)> {
+    let header = &block.header;
+    let parent_header = store
+        .get_block_header_by_hash(header.parent_hash)?
+        .ok_or_else(|| anyhow!("unknown parent {:#x}", header.parent_hash))?;
+    let fork = config.fork(header.number, header.timestamp);
+    header.validate_against_parent(&parent_header)?;
+    validate_header(header, &parent_header, fork)?;
+    block.validate_body()?;
+
+    let result = execute_both_ways(store, config, block)?;
+    result.validate(header)?;
+    let mut state = parent.state.clone();
+    state.apply(&result.state_diff);
+    if state.state_root() != header.state_root {
+        return Err(InvalidBlockError::StateRootMismatch.into());
+    }
+    let imported = ImportedBlock {
+        number: header.number,
+        parent_hash: header.parent_hash,
+        total_difficulty: parent.total_difficulty + header.difficulty,
+        state_diff: result.state_diff,
+        state,
+    };
+    Ok((imported, result.receipts))
+}
+
+/// Whether `new` replaces `head` as the head. From the merge on, the last valid block
+/// does, as a consensus client would have it. Before, the heavier chain does, and on
+/// equal total difficulty the shorter one, as in geth.
+fn is_new_head(fork: Fork, new: &ImportedBlock, head: &ImportedBlock) -> bool {
+    fork >= Fork::Paris
+        || new.total_difficulty > head.total_difficulty
+        || (new.total_difficulty == head.total_difficulty && new.number < head.number)
+}
+
+/// Makes the branch ending at `target` canonical, unwinding the state of the canonical
+/// blocks it replaces and writing that of its own blocks.
+fn switch_branch(
+    store: &Store,
+    blocks: &HashMap<H256, ImportedBlock>,
+    target: H256,
+) -> anyhow::Result<()> {
+    let mut branch = Vec::new();
+    let mut hash = target;
+    let fork_point = loop {
+        let block = blocks
+            .get(&hash)
+            .ok_or_else(|| anyhow!("unknown block {hash:#x}"))?;
+        if store.get_canonical_block_hash(block.number)? == Some(hash) {
+            break block.number;
+        }
+        branch.push((block.number, hash));
+        hash = block.parent_hash;
+    };
+    let latest = store
+        .get_latest_block_number()?
+        .ok_or_else(|| anyhow!("empty store"))?;
+    for number in (fork_point + 1..=latest).rev() {
+        store.unwind_state(number)?;
+    }
+    store.set_canonical_head(hash)?;
+    for (number, hash) in branch.into_iter().rev() {
+        store.write_state(number, &blocks[&hash].state_diff)?;
+        store.set_canonical_head(hash)?;
+    }
+    Ok(())
+}
+
+/// Executes the block sequentially and with the parallel executor on each of
+/// [`PARALLEL_THREADS`], which must all agree on the result or on the error.
+fn execute_both_ways(
+    store: &Store,
+    config: &ChainConfig,
+    block: &Block,
+) -> anyhow::Result<BlockResult> {
+    let sequential = execute_block(store, config, block);
+    for threads in PARALLEL_THREADS {
+        let parallel = execute_block_parallel(store, config, block, threads);
+        match (&sequential, &parallel) {
+            (Ok(sequential), Ok(parallel)) if sequential != parallel => {
+                bail!(
+                    "parallel execution on {threads} threads diverged: {parallel:?}, \
+                     sequential gave {sequential:?}"
+                )
+            }
+            (Err(sequential), Err(parallel))
+                if format!("{sequential:#}") != format!("{parallel:#}") =>
+            {
+                bail!(
+                    "parallel execution on {threads} threads diverged: {parallel:#}, \
+                     sequential gave {sequential:#}"
+                )
+            }
+            (Ok(_), Err(parallel)) => {
+                bail!("parallel execution on {threads} threads failed: {parallel:#}")
+            }
+            (Err(sequential), Ok(_)) => bail!(
+                "parallel execution on {threads} threads passed a block that failed with \
+                 {sequential:#}"
+            ),
+            _ => {}
+        }
+    }
+    sequential
+}
+
+fn head_hash(store: &Store) -> anyhow::Result<H256> {
+    let number = store
+        .get_latest_block_number()?
+        .ok_or_else(|| anyhow!("empty store"))?;
+    store
+        .get_canonical_block_hash(number)?
+        .ok_or_else(|| anyhow!("missing canonical block {number}"))
+}
+
+/// Compares the accounts of `expected` with those in the store.
+fn check_post_state(store: &Store, expected: &Alloc) -> anyhow::Result<()> {
+    for (address, account) in &expected.0 {
+        let info = store
+            .get_account_info(*address)?
+            .ok_or_else(|| anyhow!("missing account {address:#x}"))?;
+        if info.nonce != account.nonce
+            || info.balance != account.balance
+            || info.code_hash != account.code_hash()
+        {
+            bail!("account {address:#x} mismatch: expected {account:?}, got {info:?}");
+        }
+        for (slot, value) in &account.storage {
+            let stored = store.get_storage_at(*address, *slot)?;
+            if stored != *value {
+                bail!("storage {address:#x} {slot:#x} mismatch: expected {value}, got {stored}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The chain config of a fixture network: a fork, active from genesis, or a
+/// transition such as `ShanghaiToCancunAtTime15k`.
+fn chain_config(network: &str, chain_id: u64) -> anyhow::Result<ChainConfig> {
+    let Some((from, to)) = network.split_once("To") else {
+        return Ok(ChainConfig::from_genesis(chain_id, network.parse()?));
+    };
+    let (to, at) = to
+        .split_once("At")
+        .ok_or_else(|| anyhow!("unknown network {network}"))?;
+    let parse = |at: &str| -> anyhow::Result<u64> {
+        let (digits, scale) = at
+            .strip_suffix('k')
+            .map_or((at, 1), |digits| (digits, 1000));
+        Ok(digits
+            .parse::<u64>()
+            .with_context(|| format!("unknown network {network}"))?
+            * scale)
+    };
+    let activation = match at.strip_prefix("Time") {
+        Some(time) => Activation::Timestamp(parse(time)?),
+        None => Activation::Block(parse(at)?),
+    };
+    let mut config = ChainConfig::from_genesis(chain_id, from.parse()?);
+    config.forks.push((to.parse::<Fork>()?, activation));
+    Ok(config)
+}
+
+/// Whether a block rejected with `err` was rejected for one of the reasons in
+/// `expected`, which lists exception names of execution-spec-tests separated by `|`.
+/// The older names of ethereum/tests are not mapped, so they never match.
+fn is_expected(err: &anyhow::Error, expected: &str) -> bool {
+    let names: Vec<&str> = expected
+        .split('|')
+        .filter_map(|name| {
+            name.strip_prefix("TransactionException.")
+                .or_else(|| name.strip_prefix("BlockException."))
+        })
+        .collect();
+    let reasons = exception_names(err);
+    names.iter().any(|name| {
+        reasons.contains(name)
+            || (err.downcast_ref::<RLPDecodeError>().is_some() && name.starts_with("RLP_"))
+    })
+}
+
+/// Names execution-spec-tests gives the reason for `err`.
+fn exception_names(err: &anyhow::Error) -> &'static [&'static str] {
+    if let Some(err) = err.downcast_ref::<InvalidTransaction>() {
+        return match err {
+            InvalidTransaction::TypeNotSupported(_) => &["TYPE_NOT_SUPPORTED"],
+            InvalidTransaction::ChainIdMismatch => &["INVALID_CHAINID"],
+            InvalidTransaction::NonceTooLow { .. } => &["NONCE_MISMATCH_TOO_LOW"],
+            InvalidTransaction::NonceTooHigh { .. } => &["NONCE_MISMATCH_TOO_HIGH"],
+            InvalidTransaction::NonceMax => &["NONCE_IS_MAX"],
+            InvalidTransaction::SenderNotEoa => &["SENDER_NOT_EOA"],
+            InvalidTransaction::GasLimitExceedsBlock { .. } => &["GAS_ALLOWANCE_EXCEEDED"],
+            InvalidTransaction::FeeCapBelowBaseFee => &["INSUFFICIENT_MAX_FEE_PER_GAS"],
+            InvalidTransaction::TipAboveFeeCap => &["PRIORITY_GREATER_THAN_MAX_FEE_PER_GAS"],
+            InvalidTransaction::IntrinsicGasTooLow { .. } => &[
+                "INTRINSIC_GAS_TOO_LOW",
+                "INTRINSIC_GAS_BELOW_FLOOR_GAS_COST",
+            ],
+            InvalidTransaction::InitCodeSizeExceeded(_) => &["INITCODE_SIZE_EXCEEDED"],
+            InvalidTransaction::InsufficientFunds { .. } => &["INSUFFICIENT_ACCOUNT_FUNDS"],
+            InvalidTransaction::NoBlobs => &["TYPE_3_TX_ZERO_BLOBS"],
+            InvalidTransaction::TooManyBlobs(_) => &[
+                "TYPE_3_TX_MAX_BLOB_GAS_ALLOWANCE_EXCEEDED",
+                "TYPE_3_TX_BLOB_COUNT_EXCEEDED",
+            ],
+            InvalidTransaction::InvalidBlobVersion => &["TYPE_3_TX_INVALID_BLOB_VERSIONED_HASH"],
+            InvalidTransaction::BlobFeeCapBelowBlobBaseFee => {
+                &["INSUFFICIENT_MAX_FEE_PER_BLOB_GAS"]
+            }
+            InvalidTransaction::EmptyAuthorizationList => &["TYPE_4_EMPTY_AUTHORIZATION_LIST"],
+        };
+    }
+    if let Some(err) = err.downcast_ref::<InvalidBlockError>() {
+        return match err {
+            InvalidBlockError::InvalidNumber => &["INVALID_BLOCK_NUMBER"],
+            InvalidBlockError::ParentHashMismatch => &["UNKNOWN_PARENT"],
+            InvalidBlockError::InvalidTimestamp => &["INVALID_BLOCK_TIMESTAMP_OLDER_THAN_PARENT"],
+            InvalidBlockError::InvalidGasLimit => &["INVALID_GASLIMIT"],
+            InvalidBlockError::GasUsedExceedsLimit => &["GAS_USED_OVERFLOW"],
+            InvalidBlockError::TransactionsRootMismatch => &["INVALID_TRANSACTIONS_ROOT"],
+            InvalidBlockError::OmmersHashMismatch => &["INVALID_UNCLES_HASH"],
+            InvalidBlockError::WithdrawalsRootMismatch => &["INVALID_WITHDRAWALS_ROOT"],
+            InvalidBlockError::MissingField(_) | InvalidBlockError::UnexpectedField(_) => {
+                &["INCORRECT_BLOCK_FORMAT"]
+            }
+            InvalidBlockError::InvalidBaseFee => &["INVALID_BASEFEE_PER_GAS"],
+            InvalidBlockError::InvalidExcessBlobGas => &["INCORRECT_EXCESS_BLOB_GAS"],
+            InvalidBlockError::ProofOfWorkAfterMerge => &[
+                "IMPORT_IMPOSSIBLE_DIFFICULTY_OVER_PARIS",
+                "IMPORT_IMPOSSIBLE_UNCLES_OVER_PARIS",
+            ],
+            InvalidBlockError::GasUsedMismatch => &["INVALID_GAS_USED"],
+            InvalidBlockError::ReceiptsRootMismatch => &["INVALID_RECEIPTS_ROOT"],
+            InvalidBlockError::LogsBloomMismatch => &["INVALID_LOG_BLOOM"],
+            InvalidBlockError::BlobGasUsedMismatch => &["INCORRECT_BLOB_GAS_USED"],
+            InvalidBlockError::RequestsHashMismatch => &["INVALID_REQUESTS"],
+            InvalidBlockError::StateRootMismatch => &["INVALID_STATE_ROOT"],
+        };
+    }
+    if err.downcast_ref::<RLPDecodeError>().is_some() {
+        return &[
+            "INCORRECT_BLOCK_FORMAT",
+            "TYPE_3_TX_CONTRACT_CREATION",
+            "TYPE_4_TX_CONTRACT_CREATION",
+        ];
+    }
+    &[]
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeMap, path::Path};
+
+    use reec_core::{rlp::encode::RLPEncode, trie::EMPTY_TRIE_ROOT};
+
+    use super::*;
+    use crate::{fixture_files, official_fixtures_dir, run_blockchain_tests, Report};
+
+    fn fixture() -> BlockchainTest {
+        let path = Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/testdata/blockchain/simple.json"
+        ));
+        let file = std::fs::read_to_string(path).unwrap();
+        let mut tests: BTreeMap<String, BlockchainTest> = serde_json::from_str(&file).unwrap();
+        tests.remove("shanghaiToCancun").unwrap()
+    }
+
+    /// A smoke test of the runner, the client is checked against the official fixtures.
+    #[test]
+    fn passes_fixtures() {
+        // State roots computed with revm
+        let path = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/blockchain"));
+        let results = run_blockchain_tests(&path.join("simple.json"), |_| true).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].error, None);
+        assert!(
+            run_blockchain_tests(&path.join("simple.json"), |network| network == "Cancun")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn reports_failures() {
+        let mut test = fixture();
+        test.blocks[2].expect_exception =
+            Some("TransactionException.INTRINSIC_GAS_TOO_LOW".to_string());
+        let error = test.run("shanghaiToCancun").error.unwrap();
+        assert!(
+            error.starts_with("block 2 was rejected for transaction 0"),
+            "{error}"
+        );
+        assert!(
+            error.ends_with("expected TransactionException.INTRINSIC_GAS_TOO_LOW"),
+            "{error}"
+        );
+
+        let mut test = fixture();
+        test.blocks[3].expect_exception = None;
+        assert_eq!(
+            test.run("shanghaiToCancun").error.unwrap(),
+            "block 3: invalid block: state root mismatch"
+        );
+
+        let mut test = fixture();
+        test.blocks.truncate(1);
+        assert!(test
+            .run("shanghaiToCancun")
+            .error
+            .unwrap()
+            .starts_with("head mismatch"));
+
+        let mut test = fixture();
+        test.post_state
+            .as_mut()
+            .unwrap()
+            .0
+            .values_mut()
+            .next()
+            .unwrap()
+            .nonce = 7;
+        assert!(test
+            .run("shanghaiToCancun")
+            .error
+            .unwrap()
+            .starts_with("account 0x0000000000000000000000000000000000001000 mismatch"));
+    }
+
+    #[test]
+    fn switches_to_side_chains() {
+        let mut test = fixture();
+        let genesis = Block::decode(&test.genesis_rlp).unwrap();
+        // Block 1 without its transaction and withdrawal, leaving the genesis state
+        // unchanged
+        let mut side = Block::decode(&test.blocks[0].rlp).unwrap();
+        side.body.transactions.clear();
+        side.body.withdrawals = Some(Vec::new());
+        let header = &mut side.header;
+        header.transactions_root = side.body.compute_transactions_root();
+        header.withdrawals_root = Some(EMPTY_TRIE_ROOT);
+        header.receipts_root = EMPTY_TRIE_ROOT;
+        header.logs_bloom = Default::default();
+        header.gas_used = 0;
+        header.state_root = genesis.header.state_root;
+        let mut invalid = side.clone();
+        invalid.header.extra_data = Bytes::from_static(b"invalid");
+        invalid.header.state_root = H256::zero();
+        // Each sibling of block 1 becomes the head in turn, block 2 then switches back
+        // to the branch of the first
+        test.blocks.splice(
+            1..1,
+            [
+                TestBlock {
+                    rlp: side.encode_to_vec().into(),
+                    expect_exception: None,
+                },
+                TestBlock {
+                    rlp: invalid.encode_to_vec().into(),
+                    expect_exception: Some("BlockException.INVALID_STATE_ROOT".to_string()),
+                },
+            ],
+        );
+        assert_eq!(test.run("shanghaiToCancun").error, None);
+
+        test.blocks.truncate(3);
+        test.lastblockhash = side.hash();
+        test.post_state = Some(test.pre.clone());
+        assert_eq!(test.run("shanghaiToCancun").error, None);
+    }
+
+    #[test]
+    fn picks_the_heavier_chain_before_the_merge() {
+        let block = |number, total_difficulty: u64| ImportedBlock {
+            number,
+            parent_hash: H256::zero(),
+            total_difficulty: total_difficulty.into(),
+            state_diff: StateDiff::new(),
+            state: Alloc::default(),
+        };
+        assert!(is_new_head(Fork::London, &block(5, 11), &block(5, 10)));
+        assert!(!is_new_head(Fork::London, &block(6, 10), &block(5, 11)));
+        assert!(is_new_head(Fork::London, &block(4, 10), &block(5, 10)));
+        assert!(!is_new_head(Fork::London, &block(5, 10), &block(5, 10)));
+        assert!(is_new_head(Fork::Paris, &block(5, 0), &block(5, 10)));
+    }
+
+    /// Runs the pinned subset on every network the client can configure, printing the
+    /// passed and failed cases of each with `--nocapture`. Tests of other networks,
+    /// such as transitions at a total difficulty, are counted as skipped.
+    #[test]
+    #[ignore = "needs the official fixtures, see fetch-fixtures.sh"]
+    fn passes_official_fixtures() {
+        // Every block also runs on the parallel executor, see `execute_both_ways`
+        let root = official_fixtures_dir("blockchain_tests");
+        let (mut invalid_blocks, mut skipped) = (0, 0);
+        let mut report = Report::default();
+        for file in fixture_files(&root).unwrap() {
+            let tests: BTreeMap<String, BlockchainTest> =
+                serde_json::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+            let mut results = Vec::new();
+            for (name, test) in tests {
+                if chain_config(&test.network, 1).is_err() {
+                    skipped += 1;
+                    continue;
+                }
+                invalid_blocks += test
+                    .blocks
+                    .iter()
+                    .filter(|block| block.expect_exception.is_some())
+                    .count();
+                results.push(test.run(&name));
+            }
+            report.add(file.strip_prefix(&root).unwrap(), results);
+        }
+        println!("{report}{skipped} skipped");
+        assert!(
+            report.cases() > 0 && invalid_blocks > 0,
+            "the subset has no invalid blocks"
+        );
+        assert!(
+            report.failures().is_empty(),
+            "{}\n{report}",
+            report.failures().join("\n")
+        );
+    }
+
+    #[test]
+    fn rejects_for_the_expected_exception_only() {
+        let err = anyhow::Error::from(InvalidBlockError::StateRootMismatch);
+        assert!(is_expected(&err, "BlockException.INVALID_STATE_ROOT"));
+        assert!(is_expected(
+            &err,
+            "BlockException.INVALID_GAS_USED|BlockException.INVALID_STATE_ROOT"
+        ));
+        assert!(!is_expected(&err, "BlockException.INVALID_GAS_USED"));
+        assert!(!is_expected(&err, "InvalidStateRoot"));
+    }
+
+    #[test]
+    fn parses_networks() {
+        let config = chain_config("ShanghaiToCancunAtTime15k", 1).unwrap();
+        assert_eq!(config.fork(1, 14_999), Fork::Shanghai);
+        assert_eq!(config.fork(1, 15_000), Fork::Cancun);
+        let config = chain_config("BerlinToLondonAt5", 1).unwrap();
+        assert_eq!(config.fork(4, 0), Fork::Berlin);
+        assert_eq!(config.fork(5, 0), Fork::London);
+        assert_eq!(chain_config("Cancun", 1).unwrap().fork(0, 0), Fork::Cancun);
+        assert!(chain_config("ShanghaiToCancunAtNoon", 1).is_err());
+    }
+}
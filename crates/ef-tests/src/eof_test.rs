@@ -0,0 +1,298 @@
+//! EOFTests: containers and whether they are valid EOF, checked by validation alone.
+
+use std::collections::BTreeMap;
+
+use anyhow::bail;
+use bytes::Bytes;
+use evm::eof::{ContainerKind, Eof, EofError};
+use serde::Deserialize;
+
+use crate::{serde_hex, CaseResult};
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EofTest {
+    pub vectors: BTreeMap<String, EofVector>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EofVector {
+    #[serde(deserialize_with = "serde_hex::bytes")]
+    pub code: Bytes,
+    /// `INITCODE` for init code, runtime code if absent.
+    pub container_kind: Option<String>,
+    /// Expected validity by fork name.
+    pub results: BTreeMap<String, EofResult>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EofResult {
+    pub result: bool,
+    /// `EOFException` names, separated by `|`, any of which an invalid container may be
+    /// rejected for.
+    pub exception: Option<String>,
+}
+
+impl EofTest {
+    /// Validates every vector once for each fork `filter` accepts. The rules are the
+    /// same whichever fork the fixture names, so unknown forks are checked too.
+    pub fn run(&self, name: &str, filter: impl Fn(&str) -> bool) -> Vec<CaseResult> {
+        let mut results = Vec::new();
+        for (vector_name, vector) in &self.vectors {
+            let validity = vector.validate();
+            for (fork, expected) in vector.results.iter().filter(|(fork, _)| filter(fork)) {
+                let error = match (&validity, expected.result) {
+                    (Ok(()), false) => Some(format!(
+                        "valid, expected {}",
+                        expected
+                            .exception
+                            .as_deref()
+                            .unwrap_or("an invalid container")
+                    )),
+                    (Err(err), true) => Some(format!("{err:#}")),
+                    (Err(err), false) => match &expected.exception {
+                        Some(exception) if !is_expected(err, exception) => {
+                            Some(format!("{err:#}, expected {exception}"))
+                        }
+                        _ => None,
+                    },
+                    (Ok(()), true) => None,
+                };
+                results.push(CaseResult {
+                    name: format!("{name} {vector_name}"),
+                    fork: fork.clone(),
+                    error,
+                });
+            }
+        }
+        results
+    }
+}
+
+impl EofVector {
+    fn validate(&self) -> anyhow::Result<()> {
+        let kind = match self.container_kind.as_deref() {
+            None | Some("RUNTIME") => ContainerKind::Runtime,
+            Some("INITCODE") => ContainerKind::Initcode,
+            Some(kind) => bail!("unknown container kind {kind}"),
+        };
+        Ok(Eof::decode(self.code.clone())?.validate(kind)?)
+    }
+}
+
+/// Whether `err` is one of the `|` separated exceptions `expected` names.
+fn is_expected(err: &anyhow::Error, expected: &str) -> bool {
+    let reasons = err.downcast_ref().map_or(&[][..], exception_names);
+    expected
+        .split('|')
+        .filter_map(|name| name.strip_prefix("EOFException."))
+        .any(|name| reasons.contains(&name))
+}
+
+/// Names execution-spec-tests gives the reason for `err`. Where the fixtures tell
+/// apart cases a single error covers, all of them are accepted.
+fn exception_names(err: &EofError) -> &'static [&'static str] {
+    match err {
+        EofError::InvalidMagic => &["INVALID_MAGIC", "INCOMPLETE_MAGIC"],
+        EofError::InvalidVersion => &["INVALID_VERSION", "UNKNOWN_VERSION"],
+        EofError::InvalidHeader("missing type section") => &["MISSING_TYPE_HEADER"],
+        EofError::InvalidHeader("missing code sections") => &["MISSING_CODE_HEADER"],
+        EofError::InvalidHeader("invalid number of code sections") => {
+            &["TOO_MANY_CODE_SECTIONS", "ZERO_SECTION_SIZE"]
+        }
+        EofError::InvalidHeader("type section size does not match the code sections") => {
+            &["INVALID_TYPE_SECTION_SIZE"]
+        }
+        EofError::InvalidHeader("invalid number of container sections") => {
+            &["TOO_MANY_CONTAINERS", "ZERO_SECTION_SIZE"]
+        }
+        EofError::InvalidHeader("missing data section") => {
+            &["MISSING_DATA_SECTION", "UNEXPECTED_HEADER_KIND"]
+        }
+        EofError::InvalidHeader("missing terminator") => {
+            &["MISSING_TERMINATOR", "MISSING_HEADERS_TERMINATOR"]
+        }
+        EofError::InvalidHeader("empty section") => &["ZERO_SECTION_SIZE"],
+        EofError::InvalidHeader(_) => &[
+            "INCOMPLETE_SECTION_NUMBER",
+            "INCOMPLETE_SECTION_SIZE",
+            "INCOMPLETE_CODE_HEADER",
+            "INCOMPLETE_DATA_HEADER",
+            "MISSING_HEADERS_TERMINATOR",
+            "MISSING_TYPE_HEADER",
+            "MISSING_CODE_HEADER",
+            "MISSING_DATA_SECTION",
+        ],
+        EofError::TruncatedBody => &["INVALID_SECTION_BODIES_SIZE"],
+        EofError::TrailingBytes => &["INVALID_SECTION_BODIES_SIZE", "TRAILING_BYTES"],
+        EofError::TruncatedData => &[
+            "TOPLEVEL_CONTAINER_TRUNCATED",
+            "EOFCREATE_WITH_TRUNCATED_CONTAINER",
+            "INVALID_SECTION_BODIES_SIZE",
+        ],
+        EofError::InvalidType { .. } => &[
+            "INVALID_FIRST_SECTION_TYPE",
+            "INVALID_TYPE_BODY",
+            "INPUTS_OUTPUTS_NUM_ABOVE_LIMIT",
+            "MAX_STACK_INCREASE_ABOVE_LIMIT",
+        ],
+        EofError::UndefinedInstruction { .. } => &["UNDEFINED_INSTRUCTION"],
+        EofError::TruncatedImmediate { .. } => &["TRUNCATED_INSTRUCTION"],
+        EofError::InvalidJumpTarget { .. } => &["INVALID_RJUMP_DESTINATION"],
+        EofError::InvalidSectionIndex { .. } => &["INVALID_CODE_SECTION_INDEX"],
+        EofError::CallfToNonReturning { .. } => &["CALLF_TO_NON_RETURNING"],
+        EofError::InvalidDataLoadN { .. } => &["INVALID_DATALOADN_INDEX"],
+        EofError::InvalidContainerIndex { .. } => &["INVALID_CONTAINER_SECTION_INDEX"],
+        EofError::InvalidReturning { .. } => &["INVALID_NON_RETURNING_FLAG"],
+        EofError::IncompatibleContainerKind { .. } => {
+            &["INCOMPATIBLE_CONTAINER_KIND", "AMBIGUOUS_CONTAINER_KIND"]
+        }
+        EofError::UnreferencedContainer(_) => &["ORPHAN_SUBCONTAINER"],
+        EofError::UnreachableSection(_) => &["UNREACHABLE_CODE_SECTIONS"],
+        EofError::UnreachableCode { .. } => &["UNREACHABLE_INSTRUCTIONS"],
+        EofError::NoTerminatingInstruction { .. } => &["MISSING_STOP_OPCODE"],
+        EofError::StackUnderflow { .. } => &["STACK_UNDERFLOW"],
+        EofError::StackOverflow { .. } => &["STACK_OVERFLOW", "MAX_STACK_INCREASE_ABOVE_LIMIT"],
+        EofError::StackHeightMismatch { .. } => &[
+            "STACK_HEIGHT_MISMATCH",
+            "STACK_HIGHER_THAN_OUTPUTS",
+            "JUMPF_DESTINATION_INCOMPATIBLE_OUTPUTS",
+        ],
+        EofError::InvalidMaxStackIncrease { .. } => &["INVALID_MAX_STACK_INCREASE"],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{fixture_files, official_fixtures_dir, run_eof_tests, Report};
+
+    fn fixture() -> &'static Path {
+        Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/testdata/eof/simple.json"
+        ))
+    }
+
+    /// A smoke test of the runner, validation is checked against the official fixtures.
+    #[test]
+    fn passes_fixtures() {
+        let results = run_eof_tests(fixture(), |_| true).unwrap();
+        assert_eq!(results.len(), 9);
+        for result in &results {
+            assert_eq!(result.error, None, "{} [{}]", result.name, result.fork);
+        }
+        assert_eq!(
+            run_eof_tests(fixture(), |fork| fork == "Osaka")
+                .unwrap()
+                .len(),
+            8
+        );
+    }
+
+    #[test]
+    fn reports_failures() {
+        let file = std::fs::read_to_string(fixture()).unwrap();
+        let mut tests: BTreeMap<String, EofTest> = serde_json::from_str(&file).unwrap();
+        let test = tests.get_mut("validation").unwrap();
+        test.vectors
+            .get_mut("stop")
+            .unwrap()
+            .results
+            .get_mut("Osaka")
+            .unwrap()
+            .result = false;
+        test.vectors
+            .get_mut("undefined_instruction")
+            .unwrap()
+            .results
+            .get_mut("Osaka")
+            .unwrap()
+            .result = true;
+        test.vectors
+            .get_mut("truncated_push")
+            .unwrap()
+            .results
+            .get_mut("Osaka")
+            .unwrap()
+            .exception = Some("EOFException.UNDEFINED_INSTRUCTION".to_string());
+        let failed: Vec<_> = test
+            .run("validation", |_| true)
+            .into_iter()
+            .filter_map(|result| result.error.map(|error| (result.name, error)))
+            .collect();
+        assert_eq!(
+            failed,
+            [
+                (
+                    "validation stop".to_string(),
+                    "valid, expected an invalid container".to_string()
+                ),
+                (
+                    "validation truncated_push".to_string(),
+                    "truncated immediate at 0 in code section 0, \
+                     expected EOFException.UNDEFINED_INSTRUCTION"
+                        .to_string()
+                ),
+                (
+                    "validation undefined_instruction".to_string(),
+                    "undefined instruction 0x56 at 1 in code section 0".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_any_of_the_expected_exceptions() {
+        let err = anyhow::Error::new(EofError::TrailingBytes);
+        assert!(is_expected(
+            &err,
+            "EOFException.INVALID_SECTION_BODIES_SIZE"
+        ));
+        assert!(is_expected(
+            &err,
+            "EOFException.MISSING_TERMINATOR|EOFException.TRAILING_BYTES"
+        ));
+        assert!(!is_expected(&err, "EOFException.MISSING_TERMINATOR"));
+        assert!(!is_expected(&err, "TRAILING_BYTES"));
+        assert!(!is_expected(
+            &anyhow::anyhow!("unknown container kind"),
+            "EOFException.TRAILING_BYTES"
+        ));
+    }
+
+    /// Validates every vector of the pinned fixtures, printing the passed and failed
+    /// cases of each fork with `--nocapture`.
+    #[test]
+    #[ignore = "needs the official fixtures, see fetch-fixtures.sh"]
+    fn passes_official_fixtures() {
+        let root = official_fixtures_dir("eof_tests");
+        let mut invalid = 0;
+        let mut report = Report::default();
+        for file in fixture_files(&root).unwrap() {
+            let tests: BTreeMap<String, EofTest> =
+                serde_json::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+            invalid += tests
+                .values()
+                .flat_map(|test| test.vectors.values())
+                .flat_map(|vector| vector.results.values())
+                .filter(|expected| !expected.result)
+                .count();
+            let results = run_eof_tests(&file, |_| true).unwrap();
+            report.add(file.strip_prefix(&root).unwrap(), results);
+        }
+        println!("{report}");
+        assert!(report.cases() > 0, "no cases in the official fixtures");
+        assert!(
+            invalid > 0,
+            "no invalid containers in the official fixtures"
+        );
+        assert!(
+            report.failures().is_empty(),
+            "{}\n{report}",
+            report.failures().join("\n")
+        );
+    }
+}
@@ -0,0 +1,179 @@
+//! Genesis files in the format of geth's `init`: the fork schedule of a chain, the
+//! fields of its genesis block and the accounts it starts with.
+
+use anyhow::{bail, Context};
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use evm::{
+    block::compute_requests_hash,
+    config::{Activation, ChainConfig},
+    Fork,
+};
+use reec_core::{
+    trie::EMPTY_TRIE_ROOT,
+    types::{Block, BlockBody, BlockHeader},
+};
+use serde::Deserialize;
+
+use crate::{serde_hex, Alloc};
+
+/// Gas limit of genesis blocks that do not set one, as geth has it.
+const DEFAULT_GAS_LIMIT: u64 = 4_712_388;
+/// Difficulty of genesis blocks that do not set one, as geth has it.
+const DEFAULT_DIFFICULTY: u64 = 131_072;
+/// Base fee of genesis blocks that start on London or later without setting one.
+const INITIAL_BASE_FEE: u64 = 1_000_000_000;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Genesis {
+    pub config: GenesisConfig,
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub nonce: Option<u64>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub timestamp: Option<u64>,
+    #[serde(default, deserialize_with = "serde_hex::opt_bytes")]
+    pub extra_data: Option<Bytes>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub gas_limit: Option<u64>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub difficulty: Option<U256>,
+    #[serde(default)]
+    pub mix_hash: H256,
+    #[serde(default)]
+    pub coinbase: Address,
+    #[serde(default)]
+    pub parent_hash: H256,
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub base_fee_per_gas: Option<u64>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub excess_blob_gas: Option<u64>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub blob_gas_used: Option<u64>,
+    pub alloc: Alloc,
+}
+
+/// The fork schedule, with the names geth gives each activation.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenesisConfig {
+    pub chain_id: u64,
+    pub homestead_block: Option<u64>,
+    pub eip150_block: Option<u64>,
+    pub eip158_block: Option<u64>,
+    pub byzantium_block: Option<u64>,
+    pub constantinople_block: Option<u64>,
+    pub petersburg_block: Option<u64>,
+    pub istanbul_block: Option<u64>,
+    pub berlin_block: Option<u64>,
+    pub london_block: Option<u64>,
+    /// Block the merge took effect at, which is all the client can activate Paris
+    /// by.
+    pub merge_netsplit_block: Option<u64>,
+    pub terminal_total_difficulty: Option<serde_json::Number>,
+    pub shanghai_time: Option<u64>,
+    pub cancun_time: Option<u64>,
+    pub prague_time: Option<u64>,
+    pub osaka_time: Option<u64>,
+    pub deposit_contract_address: Option<Address>,
+}
+
+impl Genesis {
+    pub fn from_file(path: &std::path::Path) -> anyhow::Result<Self> {
+        let file = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&file).with_context(|| format!("invalid genesis {}", path.display()))
+    }
+
+    pub fn chain_config(&self) -> anyhow::Result<ChainConfig> {
+        let config = &self.config;
+        if config.osaka_time.is_some() {
+            bail!("Osaka is not supported");
+        }
+        let paris = match (
+            config.merge_netsplit_block,
+            &config.terminal_total_difficulty,
+        ) {
+            (Some(block), _) => Some(block),
+            (None, Some(difficulty)) if difficulty.as_u64() == Some(0) => Some(0),
+            (None, Some(_)) => bail!(
+                "the merge is only scheduled by terminal total difficulty, set \
+                 mergeNetsplitBlock to the block it took effect at"
+            ),
+            (None, None) => None,
+        };
+        let blocks = [
+            (Fork::Homestead, config.homestead_block),
+            (Fork::Tangerine, config.eip150_block),
+            (Fork::SpuriousDragon, config.eip158_block),
+            (Fork::Byzantium, config.byzantium_block),
+            (Fork::Constantinople, config.constantinople_block),
+            (Fork::Petersburg, config.petersburg_block),
+            (Fork::Istanbul, config.istanbul_block),
+            (Fork::Berlin, config.berlin_block),
+            (Fork::London, config.london_block),
+            (Fork::Paris, paris),
+        ];
+        let times = [
+            (Fork::Shanghai, config.shanghai_time),
+            (Fork::Cancun, config.cancun_time),
+            (Fork::Prague, config.prague_time),
+        ];
+        let forks = blocks
+            .into_iter()
+            .filter_map(|(fork, block)| Some((fork, Activation::Block(block?))))
+            .chain(
+                times
+                    .into_iter()
+                    .filter_map(|(fork, time)| Some((fork, Activation::Timestamp(time?)))),
+            )
+            .collect();
+        let mainnet = ChainConfig::mainnet();
+        Ok(ChainConfig {
+            chain_id: config.chain_id,
+            forks,
+            deposit_contract: config
+                .deposit_contract_address
+                .unwrap_or(mainnet.deposit_contract),
+        })
+    }
+
+    /// The genesis block, with the fields of the forks active at genesis filled in as
+    /// geth does.
+    pub fn block(&self) -> anyhow::Result<Block> {
+        let timestamp = self.timestamp.unwrap_or_default();
+        let fork = self.chain_config()?.fork(0, timestamp);
+        let body = BlockBody {
+            withdrawals: (fork >= Fork::Shanghai).then(Vec::new),
+            ..Default::default()
+        };
+        let header = BlockHeader {
+            parent_hash: self.parent_hash,
+            ommers_hash: body.compute_ommers_hash(),
+            coinbase: self.coinbase,
+            state_root: self.alloc.state_root(),
+            transactions_root: EMPTY_TRIE_ROOT,
+            receipts_root: EMPTY_TRIE_ROOT,
+            logs_bloom: Default::default(),
+            difficulty: self
+                .difficulty
+                .unwrap_or_else(|| U256::from(DEFAULT_DIFFICULTY)),
+            number: 0,
+            gas_limit: self.gas_limit.unwrap_or(DEFAULT_GAS_LIMIT),
+            gas_used: 0,
+            timestamp,
+            extra_data: self.extra_data.clone().unwrap_or_default(),
+            prev_randao: self.mix_hash,
+            nonce: self.nonce.unwrap_or_default(),
+            base_fee_per_gas: (fork >= Fork::London)
+                .then(|| self.base_fee_per_gas.unwrap_or(INITIAL_BASE_FEE)),
+            withdrawals_root: (fork >= Fork::Shanghai).then_some(EMPTY_TRIE_ROOT),
+            blob_gas_used: (fork >= Fork::Cancun).then(|| self.blob_gas_used.unwrap_or_default()),
+            excess_blob_gas: (fork >= Fork::Cancun)
+                .then(|| self.excess_blob_gas.unwrap_or_default()),
+            parent_beacon_block_root: (fork >= Fork::Cancun).then(H256::zero),
+            requests_hash: (fork >= Fork::Prague).then(|| compute_requests_hash(&[])),
+        };
+        Ok(Block { header, body })
+    }
+}
@@ -0,0 +1,164 @@
+//! Runners for the JSON test fixtures of ethereum/tests and execution-spec-tests, which
+//! every execution client is checked against.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+pub mod alloc;
+pub mod blockchain_test;
+pub mod eof_test;
+pub mod genesis;
+mod serde_hex;
+pub mod state_test;
+pub mod t8n;
+
+pub use alloc::{Account, Alloc};
+pub use blockchain_test::BlockchainTest;
+pub use eof_test::EofTest;
+pub use genesis::Genesis;
+pub use state_test::StateTest;
+
+/// Outcome of one case of a test: one fork (or fork transition for blockchain tests)
+/// and, for state tests, one combination of transaction data, gas limit and value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaseResult {
+    pub name: String,
+    pub fork: String,
+    /// Why the case failed, `None` if it passed.
+    pub error: Option<String>,
+}
+
+/// Passed and failed cases by fork, over any number of fixture files.
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    /// Passed and failed cases of each fork.
+    forks: BTreeMap<String, (usize, usize)>,
+    failures: Vec<String>,
+}
+
+impl Report {
+    /// Tallies the results of the fixture file at `path`.
+    pub fn add(&mut self, path: &Path, results: Vec<CaseResult>) {
+        for CaseResult { name, fork, error } in results {
+            let (passed, failed) = self.forks.entry(fork.clone()).or_default();
+            match error {
+                None => *passed += 1,
+                Some(error) => {
+                    *failed += 1;
+                    self.failures
+                        .push(format!("{} {name} [{fork}]: {error}", path.display()));
+                }
+            }
+        }
+    }
+
+    pub fn cases(&self) -> usize {
+        self.forks
+            .values()
+            .map(|(passed, failed)| passed + failed)
+            .sum()
+    }
+
+    /// Every failed case, with its file, fork and error.
+    pub fn failures(&self) -> &[String] {
+        &self.failures
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (fork, (passed, failed)) in &self.forks {
+            writeln!(f, "{fork}: {passed} passed, {failed} failed")?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs every state test in the fixture file at `path` on the forks `filter` accepts.
+pub fn run_state_tests(
+    path: &Path,
+    filter: impl Fn(&str) -> bool,
+) -> anyhow::Result<Vec<CaseResult>> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let tests: BTreeMap<String, StateTest> = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    Ok(tests
+        .iter()
+        .flat_map(|(name, test)| test.run(name, &filter))
+        .collect())
+}
+
+/// Runs every blockchain test in the fixture file at `path` whose network `filter`
+/// accepts.
+pub fn run_blockchain_tests(
+    path: &Path,
+    filter: impl Fn(&str) -> bool,
+) -> anyhow::Result<Vec<CaseResult>> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let tests: BTreeMap<String, BlockchainTest> = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    Ok(tests
+        .iter()
+        .filter(|(_, test)| filter(&test.network))
+        .map(|(name, test)| test.run(name))
+        .collect())
+}
+
+/// Runs every EOF validation test in the fixture file at `path` on the forks `filter`
+/// accepts.
+pub fn run_eof_tests(
+    path: &Path,
+    filter: impl Fn(&str) -> bool,
+) -> anyhow::Result<Vec<CaseResult>> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let tests: BTreeMap<String, EofTest> = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    Ok(tests
+        .iter()
+        .flat_map(|(name, test)| test.run(name, &filter))
+        .collect())
+}
+
+/// The JSON files at `path`, which is either one of them or a directory searched
+/// recursively, in a stable order.
+pub fn fixture_files(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path).with_context(|| format!("failed to read {}", path.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(fixture_files(&path)?);
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The directory of `suite` in the pinned official fixtures, which `fetch-fixtures.sh`
+/// downloads.
+#[cfg(test)]
+pub(crate) fn official_fixtures_dir(suite: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(suite);
+    assert!(
+        dir.is_dir(),
+        "{} is missing, run fetch-fixtures.sh",
+        dir.display()
+    );
+    dir
+}
@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use clap::{Arg, ArgAction, Command};
+use ef_tests::{fixture_files, run_blockchain_tests, run_eof_tests, run_state_tests, Report};
+
+fn cli() -> Command {
+    Command::new("reec-ef-tests")
+        .about("Run the Ethereum JSON test fixtures against the REEC evm")
+        .subcommand_required(true)
+        .arg(
+            Arg::new("fork")
+                .long("fork")
+                .value_name("FORK")
+                .help("Only run the cases of this fork, by its fixture name")
+                .global(true)
+                .action(ArgAction::Set),
+        )
+        .subcommand(
+            Command::new("state")
+                .about("Run GeneralStateTests fixtures")
+                .arg(
+                    Arg::new("paths")
+                        .required(true)
+                        .num_args(1..)
+                        .value_name("PATH")
+                        .help("Fixture files, or directories searched for them"),
+                ),
+        )
+        .subcommand(
+            Command::new("blockchain")
+                .about("Run BlockchainTests fixtures")
+                .arg(
+                    Arg::new("paths")
+                        .required(true)
+                        .num_args(1..)
+                        .value_name("PATH")
+                        .help("Fixture files, or directories searched for them"),
+                ),
+        )
+        .subcommand(
+            Command::new("eof").about("Run EOFTests fixtures").arg(
+                Arg::new("paths")
+                    .required(true)
+                    .num_args(1..)
+                    .value_name("PATH")
+                    .help("Fixture files, or directories searched for them"),
+            ),
+        )
+}
+
+fn main() {
+    let matches = cli().get_matches();
+    let fork = matches.get_one::<String>("fork").cloned();
+    let filter = |name: &str| fork.as_ref().is_none_or(|fork| fork == name);
+
+    let Some((kind, args)) = matches.subcommand() else {
+        unreachable!("subcommands are required");
+    };
+    let mut report = Report::default();
+    let mut broken_files = 0;
+    for path in args
+        .get_many::<String>("paths")
+        .expect("paths are required")
+    {
+        let files = fixture_files(Path::new(path)).unwrap_or_else(|err| {
+            eprintln!("{err:#}");
+            std::process::exit(1);
+        });
+        for file in files {
+            let results = match kind {
+                "blockchain" => run_blockchain_tests(&file, filter),
+                "eof" => run_eof_tests(&file, filter),
+                _ => run_state_tests(&file, filter),
+            };
+            let results = match results {
+                Ok(results) => results,
+                Err(err) => {
+                    println!("ERROR {err:#}");
+                    broken_files += 1;
+                    continue;
+                }
+            };
+            report.add(&file, results);
+        }
+    }
+
+    for failure in report.failures() {
+        println!("FAIL {failure}");
+    }
+    print!("{report}");
+    if broken_files > 0 {
+        println!("{broken_files} fixture files could not be read");
+    }
+    if broken_files > 0 || !report.failures().is_empty() {
+        std::process::exit(1);
+    }
+}
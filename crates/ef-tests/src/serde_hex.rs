@@ -0,0 +1,155 @@
+//! Deserializers for the numbers and byte strings of the fixtures, which are hex
+//! strings that may carry leading zeros, unlike the canonical encoding of the rpc. The
+//! serializers write the canonical encoding, which the tools reading the t8n output
+//! expect.
+
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use ethereum_types::{H256, U256};
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+fn parse_u256(value: &str) -> Result<U256, String> {
+    match value.strip_prefix("0x") {
+        Some("") => Ok(U256::zero()),
+        Some(digits) => {
+            U256::from_str_radix(digits, 16).map_err(|err| format!("invalid number {value}: {err}"))
+        }
+        None => U256::from_dec_str(value).map_err(|err| format!("invalid number {value}: {err}")),
+    }
+}
+
+fn parse_u64(value: &str) -> Result<u64, String> {
+    let number = parse_u256(value)?;
+    if number > U256::from(u64::MAX) {
+        return Err(format!("number {value} does not fit 64 bits"));
+    }
+    Ok(number.low_u64())
+}
+
+fn parse_bytes(value: &str) -> Result<Bytes, String> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    if digits.len() % 2 == 1 {
+        return Err(format!("odd number of digits in {value}"));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map(Bytes::from)
+        .map_err(|err| format!("invalid bytes {value}: {err}"))
+}
+
+pub fn u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    parse_u64(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+pub fn u256<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+    parse_u256(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+pub fn bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+    parse_bytes(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+pub fn opt_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse_u64(&value))
+        .transpose()
+        .map_err(D::Error::custom)
+}
+
+pub fn opt_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Bytes>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse_bytes(&value))
+        .transpose()
+        .map_err(D::Error::custom)
+}
+
+pub fn opt_u256<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<U256>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse_u256(&value))
+        .transpose()
+        .map_err(D::Error::custom)
+}
+
+pub fn u64_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u64>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|value| parse_u64(value))
+        .collect::<Result<_, _>>()
+        .map_err(D::Error::custom)
+}
+
+pub fn u256_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<U256>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|value| parse_u256(value))
+        .collect::<Result<_, _>>()
+        .map_err(D::Error::custom)
+}
+
+pub fn bytes_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Bytes>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|value| parse_bytes(value))
+        .collect::<Result<_, _>>()
+        .map_err(D::Error::custom)
+}
+
+/// Storage slots and values, both written as numbers.
+pub fn storage<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<H256, U256>, D::Error> {
+    let mut storage = BTreeMap::new();
+    for (slot, value) in BTreeMap::<String, String>::deserialize(deserializer)? {
+        let mut key = [0; 32];
+        parse_u256(&slot)
+            .map_err(D::Error::custom)?
+            .to_big_endian(&mut key);
+        storage.insert(H256(key), parse_u256(&value).map_err(D::Error::custom)?);
+    }
+    Ok(storage)
+}
+
+/// Maps keyed by numbers, such as block hashes by block number.
+pub fn u64_map<'de, D: Deserializer<'de>, V: Deserialize<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<u64, V>, D::Error> {
+    BTreeMap::<String, V>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, value)| Ok((parse_u64(&key)?, value)))
+        .collect::<Result<_, String>>()
+        .map_err(D::Error::custom)
+}
+
+pub fn serialize_u64<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{value:#x}"))
+}
+
+pub fn serialize_opt_u64<S: Serializer>(
+    value: &Option<u64>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serialize_u64(value, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn serialize_bytes<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    let digits: String = value.iter().map(|byte| format!("{byte:02x}")).collect();
+    serializer.serialize_str(&format!("0x{digits}"))
+}
+
+/// Storage slots and values, both written as 32-byte words as geth writes them.
+pub fn serialize_storage<S: Serializer>(
+    storage: &BTreeMap<H256, U256>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(storage.iter().map(|(slot, value)| {
+        let mut word = [0; 32];
+        value.to_big_endian(&mut word);
+        (slot, H256(word))
+    }))
+}
@@ -0,0 +1,491 @@
+//! GeneralStateTests: a single transaction run on top of a pre-state, once for each
+//! combination of its data, gas limit and value, and on each fork the fixture covers.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Context};
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use evm::{
+    block::blob_base_fee, execute_transaction, gas, BlockEnv, Env, ExecutionResult, Fork,
+    InvalidTransaction, JournaledState, TxEnv,
+};
+use keccak_hash::keccak;
+use reec_core::{
+    rlp::encode::RLPEncode,
+    types::{
+        AccessList, AuthorizationTuple, EIP1559Transaction, EIP2930Transaction, EIP4844Transaction,
+        EIP7702Transaction, LegacyTransaction, Transaction, TxKind, TxType,
+    },
+};
+use serde::Deserialize;
+
+use crate::{serde_hex, Alloc, CaseResult};
+
+/// Base fee assumed from London when the environment gives none, as geth does.
+const DEFAULT_BASE_FEE: u64 = 10;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StateTest {
+    pub env: TestEnv,
+    pub pre: Alloc,
+    pub transaction: TestTransaction,
+    /// Expected outcomes by fork name.
+    pub post: BTreeMap<String, Vec<PostState>>,
+    #[serde(default)]
+    pub config: TestConfig,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestEnv {
+    pub current_coinbase: Address,
+    #[serde(deserialize_with = "serde_hex::u64")]
+    pub current_gas_limit: u64,
+    #[serde(deserialize_with = "serde_hex::u64")]
+    pub current_number: u64,
+    #[serde(deserialize_with = "serde_hex::u64")]
+    pub current_timestamp: u64,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub current_difficulty: Option<U256>,
+    pub current_random: Option<H256>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub current_base_fee: Option<U256>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub current_excess_blob_gas: Option<u64>,
+}
+
+/// The transaction to run, with lists of data, gas limits and values to pick from.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestTransaction {
+    #[serde(deserialize_with = "serde_hex::bytes_list")]
+    pub data: Vec<Bytes>,
+    #[serde(deserialize_with = "serde_hex::u64_list")]
+    pub gas_limit: Vec<u64>,
+    #[serde(deserialize_with = "serde_hex::u256_list")]
+    pub value: Vec<U256>,
+    #[serde(deserialize_with = "serde_hex::u64")]
+    pub nonce: u64,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub gas_price: Option<U256>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub max_fee_per_gas: Option<U256>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub max_priority_fee_per_gas: Option<U256>,
+    /// Empty for contract creations.
+    pub to: String,
+    pub secret_key: H256,
+    /// Access lists matching the entries of `data`, for transactions that have them.
+    pub access_lists: Option<Vec<Option<Vec<AccessListItem>>>>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub max_fee_per_blob_gas: Option<U256>,
+    pub blob_versioned_hashes: Option<Vec<H256>>,
+    pub authorization_list: Option<Vec<TestAuthorization>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListItem {
+    pub address: Address,
+    pub storage_keys: Vec<H256>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestAuthorization {
+    #[serde(deserialize_with = "serde_hex::u256")]
+    pub chain_id: U256,
+    pub address: Address,
+    #[serde(deserialize_with = "serde_hex::u64")]
+    pub nonce: u64,
+    #[serde(deserialize_with = "serde_hex::u256")]
+    pub v: U256,
+    #[serde(deserialize_with = "serde_hex::u256")]
+    pub r: U256,
+    #[serde(deserialize_with = "serde_hex::u256")]
+    pub s: U256,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostState {
+    /// State root after the transaction.
+    pub hash: H256,
+    /// Hash of the RLP encoded list of the transaction's logs.
+    pub logs: H256,
+    pub indexes: Indexes,
+    /// Set if the transaction is invalid, and so leaves the state as it was.
+    pub expect_exception: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Indexes {
+    pub data: usize,
+    pub gas: usize,
+    pub value: usize,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TestConfig {
+    #[serde(deserialize_with = "serde_hex::u64")]
+    pub chainid: u64,
+}
+
+impl Default for TestConfig {
+    fn default() -> Self {
+        TestConfig { chainid: 1 }
+    }
+}
+
+impl StateTest {
+    /// Runs every case of the test on the forks `filter` accepts. Cases on forks this
+    /// client does not know fail.
+    pub fn run(&self, name: &str, filter: impl Fn(&str) -> bool) -> Vec<CaseResult> {
+        let mut results = Vec::new();
+        for (fork_name, posts) in self.post.iter().filter(|(fork, _)| filter(fork)) {
+            for post in posts {
+                let Indexes { data, gas, value } = post.indexes;
+                let outcome = match fork_name.parse() {
+                    Ok(fork) => self.run_case(fork, post),
+                    Err(err) => Err(err),
+                };
+                results.push(CaseResult {
+                    name: format!("{name} d{data}g{gas}v{value}"),
+                    fork: fork_name.clone(),
+                    error: outcome.err().map(|err| format!("{err:#}")),
+                });
+            }
+        }
+        results
+    }
+
+    fn run_case(&self, fork: Fork, post: &PostState) -> anyhow::Result<()> {
+        let tx = self.transaction.build(post.indexes, self.config.chainid)?;
+        let sender = tx.sender().ok_or_else(|| anyhow!("invalid secret key"))?;
+        let mut db = self.pre.to_db();
+        // BLOCKHASH of the fixtures, which have no chain: geth's stand-in hashes
+        let number = self.env.current_number;
+        for ancestor in number.saturating_sub(256)..number {
+            db.insert_block_hash(ancestor, keccak(ancestor.to_string()));
+        }
+        let mut state = JournaledState::new(db, self.env(fork, self.config.chainid));
+
+        let logs = match (
+            self.execute(&mut state, &tx, sender, fork),
+            &post.expect_exception,
+        ) {
+            (Ok(_), Some(exception)) => bail!("expected exception {exception}"),
+            (Ok(result), None) => result.logs,
+            (Err(err), expected) if err.downcast_ref::<InvalidTransaction>().is_some() => {
+                if expected.is_none() {
+                    bail!("unexpected exception: {err}");
+                }
+                Vec::new()
+            }
+            (Err(err), _) => return Err(err),
+        };
+
+        let mut post_state = self.pre.clone();
+        post_state.apply(&state.state_diff());
+        // geth touches the coinbase even when the transaction is invalid or pays it nothing,
+        // which creates it as an empty account before EIP-161 cleared those away
+        if fork < Fork::SpuriousDragon {
+            post_state.0.entry(self.env.current_coinbase).or_default();
+        }
+        let root = post_state.state_root();
+        if root != post.hash {
+            bail!(
+                "state root mismatch: expected {:#x}, got {root:#x}",
+                post.hash
+            );
+        }
+        let logs_hash = keccak(logs.encode_to_vec());
+        if logs_hash != post.logs {
+            bail!(
+                "logs hash mismatch: expected {:#x}, got {logs_hash:#x}",
+                post.logs
+            );
+        }
+        Ok(())
+    }
+
+    /// Executes the transaction after the checks a block would make of it.
+    fn execute(
+        &self,
+        state: &mut JournaledState<evm::MemoryDb>,
+        tx: &Transaction,
+        sender: Address,
+        fork: Fork,
+    ) -> anyhow::Result<ExecutionResult> {
+        if tx.gas_limit() > self.env.current_gas_limit {
+            let available = self.env.current_gas_limit;
+            bail!(InvalidTransaction::GasLimitExceedsBlock {
+                gas_limit: tx.gas_limit(),
+                available
+            });
+        }
+        let blobs = tx.blob_versioned_hashes().len();
+        if gas::PER_BLOB * blobs as u64 > gas::max_blob_gas_per_block(fork) {
+            bail!(InvalidTransaction::TooManyBlobs(blobs));
+        }
+        execute_transaction(state, tx, sender)
+    }
+
+    fn env(&self, fork: Fork, chain_id: u64) -> Env {
+        let env = &self.env;
+        let base_fee = match env.current_base_fee {
+            _ if fork < Fork::London => U256::zero(),
+            Some(base_fee) => base_fee,
+            None => U256::from(DEFAULT_BASE_FEE),
+        };
+        let blob_base_fee = match fork {
+            fork if fork >= Fork::Cancun => {
+                blob_base_fee(env.current_excess_blob_gas.unwrap_or_default(), fork)
+            }
+            _ => U256::zero(),
+        };
+        Env {
+            fork,
+            chain_id,
+            block: BlockEnv {
+                number: env.current_number,
+                coinbase: env.current_coinbase,
+                timestamp: env.current_timestamp,
+                gas_limit: env.current_gas_limit,
+                base_fee,
+                difficulty: env.current_difficulty.unwrap_or_default(),
+                prev_randao: env.current_random.unwrap_or_default(),
+                blob_base_fee,
+            },
+            tx: TxEnv::default(),
+        }
+    }
+}
+
+impl TestTransaction {
+    /// The signed transaction for one combination of data, gas limit and value. Its
+    /// type follows from the fields the fixture gives.
+    fn build(&self, indexes: Indexes, chain_id: u64) -> anyhow::Result<Transaction> {
+        let data = pick(&self.data, indexes.data, "data")?;
+        let gas_limit = pick(&self.gas_limit, indexes.gas, "gas")?;
+        let value = pick(&self.value, indexes.value, "value")?;
+        let to = match self.to.as_str() {
+            "" => TxKind::Create,
+            to => TxKind::Call(to.parse().map_err(|_| anyhow!("invalid recipient {to}"))?),
+        };
+        let call_to = || match to {
+            TxKind::Call(to) => Ok(to),
+            TxKind::Create => Err(anyhow!("transaction type cannot create contracts")),
+        };
+        let access_list: Option<AccessList> = self
+            .access_lists
+            .as_ref()
+            .and_then(|lists| lists.get(indexes.data).cloned().flatten())
+            .map(|items| {
+                items
+                    .into_iter()
+                    .map(|item| (item.address, item.storage_keys))
+                    .collect()
+            });
+        let fee_cap = || {
+            self.max_fee_per_gas
+                .ok_or_else(|| anyhow!("missing max fee per gas"))
+        };
+        let tip_cap = self.max_priority_fee_per_gas.unwrap_or_default();
+
+        let nonce = self.nonce;
+        let mut tx = if let Some(authorizations) = &self.authorization_list {
+            Transaction::EIP7702Transaction(EIP7702Transaction {
+                nonce,
+                max_priority_fee_per_gas: tip_cap,
+                max_fee_per_gas: fee_cap()?,
+                gas_limit,
+                to: call_to()?,
+                value,
+                data,
+                access_list: access_list.unwrap_or_default(),
+                authorization_list: authorizations
+                    .iter()
+                    .map(|auth| AuthorizationTuple {
+                        chain_id: auth.chain_id,
+                        address: auth.address,
+                        nonce: auth.nonce,
+                        y_parity: auth.v,
+                        r_signature: auth.r,
+                        s_signature: auth.s,
+                    })
+                    .collect(),
+                ..Default::default()
+            })
+        } else if let Some(blob_hashes) = &self.blob_versioned_hashes {
+            Transaction::EIP4844Transaction(EIP4844Transaction {
+                nonce,
+                max_priority_fee_per_gas: tip_cap,
+                max_fee_per_gas: fee_cap()?,
+                gas: gas_limit,
+                to: call_to()?,
+                value,
+                data,
+                access_list: access_list.unwrap_or_default(),
+                max_fee_per_blob_gas: self.max_fee_per_blob_gas.unwrap_or_default(),
+                blob_versioned_hashes: blob_hashes.clone(),
+                ..Default::default()
+            })
+        } else if self.max_fee_per_gas.is_some() {
+            Transaction::EIP1559Transaction(EIP1559Transaction {
+                nonce,
+                max_priority_fee_per_gas: tip_cap,
+                max_fee_per_gas: fee_cap()?,
+                gas_limit,
+                to,
+                value,
+                data,
+                access_list: access_list.unwrap_or_default(),
+                ..Default::default()
+            })
+        } else {
+            let gas_price = self.gas_price.ok_or_else(|| anyhow!("missing gas price"))?;
+            match access_list {
+                Some(access_list) => Transaction::EIP2930Transaction(EIP2930Transaction {
+                    nonce,
+                    gas_price,
+                    gas_limit,
+                    to,
+                    value,
+                    data,
+                    access_list,
+                    ..Default::default()
+                }),
+                None => Transaction::LegacyTransaction(LegacyTransaction {
+                    nonce,
+                    gas_price,
+                    gas: gas_limit,
+                    to,
+                    value,
+                    data,
+                    ..Default::default()
+                }),
+            }
+        };
+        // Legacy transactions of the fixtures predate EIP-155 replay protection
+        let chain_id = (tx.tx_type() != TxType::Legacy).then_some(chain_id);
+        tx.sign(&self.secret_key.0, chain_id)
+            .ok_or_else(|| anyhow!("invalid secret key"))?;
+        Ok(tx)
+    }
+}
+
+fn pick<T: Clone>(list: &[T], index: usize, name: &str) -> anyhow::Result<T> {
+    list.get(index)
+        .cloned()
+        .with_context(|| format!("{name} index {index} out of range"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{fixture_files, official_fixtures_dir, run_state_tests, Report};
+
+    fn fixture() -> &'static Path {
+        Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/testdata/state/simple.json"
+        ))
+    }
+
+    /// A smoke test of the runner, the EVM is checked against the official fixtures.
+    #[test]
+    fn passes_fixtures() {
+        // Expected roots and logs hashes computed with revm
+        let results = run_state_tests(fixture(), |_| true).unwrap();
+        assert_eq!(results.len(), 16);
+        for result in &results {
+            assert_eq!(result.error, None, "{} [{}]", result.name, result.fork);
+        }
+        assert_eq!(
+            run_state_tests(fixture(), |fork| fork == "London")
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn reports_failures() {
+        let file = std::fs::read_to_string(fixture()).unwrap();
+        let mut tests: BTreeMap<String, StateTest> = serde_json::from_str(&file).unwrap();
+        let test = tests.get_mut("storeAndLog").unwrap();
+        let mut posts = test.post.remove("Cancun").unwrap();
+        posts[0].logs = H256::zero();
+        // The invalid transaction is run as if it were valid
+        posts[1].expect_exception = None;
+        test.post.insert("Cancun".to_string(), posts.clone());
+        test.post.insert("Osaka".to_string(), posts);
+
+        let errors: Vec<_> = test
+            .run("storeAndLog", |fork| fork != "Berlin" && fork != "Shanghai")
+            .into_iter()
+            .map(|result| (result.fork, result.error))
+            .collect();
+        assert_eq!(errors.len(), 8);
+        assert!(errors[0]
+            .1
+            .as_ref()
+            .unwrap()
+            .starts_with("logs hash mismatch"));
+        assert!(errors[1]
+            .1
+            .as_ref()
+            .unwrap()
+            .starts_with("unexpected exception: intrinsic gas too low"));
+        assert_eq!(errors[2].1, None);
+        assert_eq!(
+            errors[4],
+            ("Osaka".to_string(), Some("unknown fork Osaka".to_string()))
+        );
+    }
+
+    /// GeneralStateTests directories the corpus test leaves out, the ones geth's test
+    /// suite also marks as too slow to run on every change.
+    const EXCLUDED: [(&str, &str); 3] = [
+        (
+            "stTimeConsumingTests",
+            "each case runs for minutes in a debug build",
+        ),
+        (
+            "stQuadraticComplexityTest",
+            "tens of thousands of calls per case, minutes each in a debug build",
+        ),
+        (
+            "VMTests/vmPerformance",
+            "loops to the gas limit, minutes per case in a debug build",
+        ),
+    ];
+
+    /// Runs all of GeneralStateTests but [`EXCLUDED`] on the forks the client knows,
+    /// printing the passed and failed cases of each with `--nocapture`.
+    #[test]
+    #[ignore = "needs the official fixtures, see fetch-fixtures.sh"]
+    fn passes_official_fixtures() {
+        let root = official_fixtures_dir("GeneralStateTests");
+        let mut report = Report::default();
+        for file in fixture_files(&root).unwrap() {
+            let relative = file.strip_prefix(&root).unwrap();
+            if EXCLUDED.iter().any(|(dir, _)| relative.starts_with(dir)) {
+                continue;
+            }
+            let results = run_state_tests(&file, |fork| fork.parse::<Fork>().is_ok()).unwrap();
+            report.add(relative, results);
+        }
+        println!("{report}");
+        assert!(report.cases() > 0, "no cases in the official fixtures");
+        assert!(
+            report.failures().is_empty(),
+            "{}\n{report}",
+            report.failures().join("\n")
+        );
+    }
+}
@@ -0,0 +1,765 @@
+//! The transition tool of execution-spec-tests, which fills its fixtures by having a
+//! client run a block: the state before it, its environment and its transactions go in,
+//! and the state after it and what its header commits to come out. The formats are
+//! those of geth's `evm t8n`.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Context};
+use bytes::Bytes;
+use ethereum_types::{Address, Bloom, H256, U256};
+use evm::{
+    begin_block,
+    block::{
+        calculate_base_fee, calculate_excess_blob_gas, check_block_room, compute_requests_hash,
+        finish_block, transaction_receipt,
+    },
+    execute_transaction, ChainConfig, Fork, InvalidTransaction,
+};
+use keccak_hash::keccak;
+use reec_core::{
+    rlp::{decode::RLPDecode, encode::RLPEncode},
+    types::{
+        compute_receipts_root, AccessList, AuthorizationTuple, Block, BlockBody, BlockHeader,
+        EIP1559Transaction, EIP2930Transaction, EIP4844Transaction, EIP7702Transaction,
+        LegacyTransaction, Log, ReceiptOutcome, Transaction, TxKind, TxType, Withdrawal,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{serde_hex, state_test::AccessListItem, Alloc};
+
+/// Everything the tool reads, as one object when it all comes from stdin.
+#[derive(Clone, Debug, Deserialize)]
+pub struct T8nInput {
+    pub alloc: Alloc,
+    pub env: T8nEnv,
+    #[serde(default)]
+    pub txs: T8nTxs,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct T8nEnv {
+    pub current_coinbase: Address,
+    #[serde(deserialize_with = "serde_hex::u64")]
+    pub current_gas_limit: u64,
+    #[serde(deserialize_with = "serde_hex::u64")]
+    pub current_number: u64,
+    #[serde(deserialize_with = "serde_hex::u64")]
+    pub current_timestamp: u64,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub current_difficulty: Option<U256>,
+    pub current_random: Option<H256>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub current_base_fee: Option<u64>,
+    /// With the parent's gas used and gas limit, gives the base fee when none is given.
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub parent_base_fee: Option<u64>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub parent_gas_used: Option<u64>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub parent_gas_limit: Option<u64>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub current_excess_blob_gas: Option<u64>,
+    /// With the parent's blob gas used, gives the excess blob gas when none is given.
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub parent_excess_blob_gas: Option<u64>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub parent_blob_gas_used: Option<u64>,
+    pub parent_beacon_block_root: Option<H256>,
+    /// Hashes of earlier blocks by number, for `BLOCKHASH` and the parent hash.
+    #[serde(default, deserialize_with = "serde_hex::u64_map")]
+    pub block_hashes: BTreeMap<u64, H256>,
+    #[serde(default)]
+    pub ommers: Vec<T8nOmmer>,
+    pub withdrawals: Option<Vec<T8nWithdrawal>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct T8nOmmer {
+    /// How many blocks before the current one the ommer is.
+    pub delta: u64,
+    pub address: Address,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct T8nWithdrawal {
+    #[serde(deserialize_with = "serde_hex::u64")]
+    pub index: u64,
+    #[serde(deserialize_with = "serde_hex::u64")]
+    pub validator_index: u64,
+    pub address: Address,
+    #[serde(deserialize_with = "serde_hex::u64")]
+    pub amount: u64,
+}
+
+/// The transactions, as JSON objects or as the hex of their RLP list.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum T8nTxs {
+    Json(Vec<T8nTransaction>),
+    Rlp(#[serde(deserialize_with = "serde_hex::bytes")] Bytes),
+}
+
+impl Default for T8nTxs {
+    fn default() -> Self {
+        T8nTxs::Json(Vec::new())
+    }
+}
+
+/// A transaction as geth writes it. It is signed with `secretKey` if given, and carries
+/// its `v`, `r` and `s` otherwise.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct T8nTransaction {
+    #[serde(rename = "type", default, deserialize_with = "serde_hex::opt_u64")]
+    pub tx_type: Option<u64>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u64")]
+    pub chain_id: Option<u64>,
+    #[serde(deserialize_with = "serde_hex::u64")]
+    pub nonce: u64,
+    #[serde(alias = "gasLimit", deserialize_with = "serde_hex::u64")]
+    pub gas: u64,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub gas_price: Option<U256>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub max_fee_per_gas: Option<U256>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub max_priority_fee_per_gas: Option<U256>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub max_fee_per_blob_gas: Option<U256>,
+    /// `None` for a creation.
+    pub to: Option<Address>,
+    #[serde(default, deserialize_with = "serde_hex::u256")]
+    pub value: U256,
+    #[serde(default, alias = "data", deserialize_with = "serde_hex::bytes")]
+    pub input: Bytes,
+    #[serde(default)]
+    pub access_list: Vec<AccessListItem>,
+    #[serde(default)]
+    pub blob_versioned_hashes: Vec<H256>,
+    #[serde(default)]
+    pub authorization_list: Vec<T8nAuthorization>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub v: Option<U256>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub r: Option<U256>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub s: Option<U256>,
+    pub secret_key: Option<H256>,
+    /// Whether a legacy transaction signed here gets EIP-155 replay protection.
+    #[serde(default = "protected_by_default")]
+    pub protected: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct T8nAuthorization {
+    #[serde(deserialize_with = "serde_hex::u256")]
+    pub chain_id: U256,
+    pub address: Address,
+    #[serde(deserialize_with = "serde_hex::u64")]
+    pub nonce: u64,
+    /// Written as `v` by some tools.
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub y_parity: Option<U256>,
+    #[serde(default, deserialize_with = "serde_hex::opt_u256")]
+    pub v: Option<U256>,
+    #[serde(deserialize_with = "serde_hex::u256")]
+    pub r: U256,
+    #[serde(deserialize_with = "serde_hex::u256")]
+    pub s: U256,
+}
+
+/// What the header of the block commits to, and what happened to each transaction.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct T8nResult {
+    pub state_root: H256,
+    pub tx_root: H256,
+    pub receipts_root: H256,
+    pub logs_hash: H256,
+    pub logs_bloom: Bloom,
+    pub receipts: Vec<T8nReceipt>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rejected: Vec<RejectedTransaction>,
+    pub current_difficulty: Option<U256>,
+    #[serde(serialize_with = "serde_hex::serialize_u64")]
+    pub gas_used: u64,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serde_hex::serialize_opt_u64"
+    )]
+    pub current_base_fee: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub withdrawals_root: Option<H256>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serde_hex::serialize_opt_u64"
+    )]
+    pub current_excess_blob_gas: Option<u64>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serde_hex::serialize_opt_u64"
+    )]
+    pub blob_gas_used: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests_hash: Option<H256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests: Option<Vec<HexBytes>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct T8nReceipt {
+    #[serde(rename = "type", serialize_with = "serde_hex::serialize_u64")]
+    pub tx_type: u64,
+    /// The intermediate state root of receipts before Byzantium, empty since.
+    pub root: HexBytes,
+    #[serde(serialize_with = "serde_hex::serialize_u64")]
+    pub status: u64,
+    #[serde(serialize_with = "serde_hex::serialize_u64")]
+    pub cumulative_gas_used: u64,
+    pub logs_bloom: Bloom,
+    pub logs: Option<Vec<T8nLog>>,
+    pub transaction_hash: H256,
+    /// The zero address if the transaction created no contract.
+    pub contract_address: Address,
+    #[serde(serialize_with = "serde_hex::serialize_u64")]
+    pub gas_used: u64,
+    /// Zero, as the tool does not build the whole header.
+    pub block_hash: H256,
+    #[serde(serialize_with = "serde_hex::serialize_u64")]
+    pub transaction_index: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct T8nLog {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: HexBytes,
+    #[serde(serialize_with = "serde_hex::serialize_u64")]
+    pub block_number: u64,
+    pub transaction_hash: H256,
+    #[serde(serialize_with = "serde_hex::serialize_u64")]
+    pub transaction_index: u64,
+    pub block_hash: H256,
+    #[serde(serialize_with = "serde_hex::serialize_u64")]
+    pub log_index: u64,
+    pub removed: bool,
+}
+
+/// A transaction the block could not include, by its index among the input ones.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RejectedTransaction {
+    pub index: usize,
+    pub error: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct HexBytes(#[serde(serialize_with = "serde_hex::serialize_bytes")] pub Bytes);
+
+/// What the tool writes: the result, the state after the block and the RLP list of the
+/// transactions the block included.
+#[derive(Clone, Debug)]
+pub struct T8nOutput {
+    pub result: T8nResult,
+    pub alloc: Alloc,
+    pub body: Bytes,
+}
+
+/// Runs the block `input` describes on `fork`. Transactions the block cannot include are
+/// rejected rather than failing it. `reward` is the miner's, `None` to leave the miner
+/// and ommers unpaid, see [`evm::block::block_reward`].
+pub fn transition(
+    input: &T8nInput,
+    fork: Fork,
+    chain_id: u64,
+    reward: Option<U256>,
+) -> anyhow::Result<T8nOutput> {
+    let env = &input.env;
+    let config = ChainConfig::from_genesis(chain_id, fork);
+    let header = env.header(fork)?;
+    let mut db = input.alloc.to_db();
+    for (number, hash) in &env.block_hashes {
+        db.insert_block_hash(*number, *hash);
+    }
+    let mut state = begin_block(db, &config, &header)?;
+
+    let mut transactions = Vec::new();
+    let mut results = Vec::new();
+    let mut receipts = Vec::new();
+    let mut rejected = Vec::new();
+    let (mut gas_used, mut blob_gas_used) = (0, 0);
+    for (index, tx) in input.txs.decode(chain_id)?.into_iter().enumerate() {
+        let Some(sender) = tx.sender() else {
+            rejected.push(RejectedTransaction {
+                index,
+                error: "invalid signature".to_string(),
+            });
+            continue;
+        };
+        let outcome = check_block_room(&header, fork, &tx, gas_used, blob_gas_used)
+            .map_err(anyhow::Error::from)
+            .and_then(|blob_gas| Ok((blob_gas, execute_transaction(&mut state, &tx, sender)?)));
+        let (blob_gas, result) = match outcome {
+            Ok(outcome) => outcome,
+            Err(err) if err.downcast_ref::<InvalidTransaction>().is_some() => {
+                rejected.push(RejectedTransaction {
+                    index,
+                    error: format!("{err:#}"),
+                });
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        gas_used += result.gas_used;
+        blob_gas_used += blob_gas;
+        receipts.push(transaction_receipt(&state, &tx, &result, gas_used)?);
+        results.push(result);
+        transactions.push(tx);
+    }
+
+    let number = header.number;
+    let ommers = env
+        .ommers
+        .iter()
+        .map(|ommer| ommer.header(number))
+        .collect();
+    let withdrawals = (fork >= Fork::Shanghai).then(|| env.withdrawals());
+    let block = Block {
+        header,
+        body: BlockBody {
+            transactions,
+            ommers,
+            withdrawals,
+        },
+    };
+    let block_result = finish_block(
+        state,
+        &config,
+        &block,
+        receipts,
+        gas_used,
+        blob_gas_used,
+        reward,
+    )?;
+    let mut alloc = input.alloc.clone();
+    alloc.apply(&block_result.state_diff);
+
+    let mut logs = Vec::new();
+    let mut logs_bloom = Bloom::zero();
+    let mut t8n_receipts = Vec::new();
+    let included = block
+        .body
+        .transactions
+        .iter()
+        .zip(&results)
+        .zip(&block_result.receipts);
+    for (index, ((tx, result), receipt)) in included.enumerate() {
+        let transaction_hash = tx.compute_hash();
+        let transaction_index = index as u64;
+        logs_bloom.accrue_bloom(&receipt.bloom);
+        let receipt_logs = result
+            .logs
+            .iter()
+            .enumerate()
+            .map(|(offset, log)| T8nLog {
+                address: log.address,
+                topics: log.topics.clone(),
+                data: HexBytes(log.data.clone()),
+                block_number: number,
+                transaction_hash,
+                transaction_index,
+                block_hash: H256::zero(),
+                log_index: (logs.len() + offset) as u64,
+                removed: false,
+            })
+            .collect::<Vec<_>>();
+        logs.extend(result.logs.iter().cloned());
+        t8n_receipts.push(T8nReceipt {
+            tx_type: tx.tx_type() as u64,
+            root: match receipt.outcome {
+                ReceiptOutcome::StateRoot(root) => HexBytes(root.as_bytes().to_vec().into()),
+                ReceiptOutcome::Status(_) => HexBytes::default(),
+            },
+            status: result.is_success().into(),
+            cumulative_gas_used: receipt.cumulative_gas_used,
+            logs_bloom: receipt.bloom,
+            logs: (!receipt_logs.is_empty()).then_some(receipt_logs),
+            transaction_hash,
+            contract_address: result.contract_address.unwrap_or_default(),
+            gas_used: result.gas_used,
+            block_hash: H256::zero(),
+            transaction_index,
+        });
+    }
+
+    let prague = fork >= Fork::Prague;
+    let result = T8nResult {
+        state_root: alloc.state_root(),
+        tx_root: block.body.compute_transactions_root(),
+        receipts_root: compute_receipts_root(&block_result.receipts),
+        logs_hash: keccak(Vec::<Log>::encode_to_vec(&logs)),
+        logs_bloom,
+        receipts: t8n_receipts,
+        rejected,
+        current_difficulty: env.current_difficulty,
+        gas_used,
+        current_base_fee: block.header.base_fee_per_gas,
+        withdrawals_root: block.body.compute_withdrawals_root(),
+        current_excess_blob_gas: block.header.excess_blob_gas,
+        blob_gas_used: (fork >= Fork::Cancun).then_some(blob_gas_used),
+        requests_hash: prague.then(|| compute_requests_hash(&block_result.requests)),
+        requests: prague.then(|| {
+            block_result
+                .requests
+                .iter()
+                .cloned()
+                .map(HexBytes)
+                .collect()
+        }),
+    };
+    let body = Bytes::from(block.body.transactions.encode_to_vec());
+    Ok(T8nOutput {
+        result,
+        alloc,
+        body,
+    })
+}
+
+impl T8nEnv {
+    /// The header of the block, with the fields the environment leaves out derived from
+    /// the parent's as the header checks would expect them.
+    fn header(&self, fork: Fork) -> anyhow::Result<BlockHeader> {
+        if fork < Fork::Paris && self.current_difficulty.is_none() {
+            bail!("currentDifficulty is required before the merge");
+        }
+        if fork >= Fork::Paris && self.current_random.is_none() {
+            bail!("currentRandom is required from the merge");
+        }
+        let parent = BlockHeader {
+            gas_limit: self.parent_gas_limit.unwrap_or_default(),
+            gas_used: self.parent_gas_used.unwrap_or_default(),
+            base_fee_per_gas: self.parent_base_fee,
+            blob_gas_used: self.parent_blob_gas_used,
+            excess_blob_gas: self.parent_excess_blob_gas,
+            ..Default::default()
+        };
+        let base_fee_per_gas = match (self.current_base_fee, self.parent_base_fee) {
+            _ if fork < Fork::London => None,
+            (Some(base_fee), _) => Some(base_fee),
+            (None, Some(_)) => Some(calculate_base_fee(&parent)),
+            (None, None) => bail!("currentBaseFee or parentBaseFee is required from London"),
+        };
+        let excess_blob_gas = (fork >= Fork::Cancun).then(|| {
+            self.current_excess_blob_gas
+                .unwrap_or_else(|| calculate_excess_blob_gas(&parent, fork))
+        });
+        let parent_hash = self
+            .current_number
+            .checked_sub(1)
+            .and_then(|number| self.block_hashes.get(&number));
+        Ok(BlockHeader {
+            parent_hash: parent_hash.copied().unwrap_or_default(),
+            coinbase: self.current_coinbase,
+            difficulty: self.current_difficulty.unwrap_or_default(),
+            number: self.current_number,
+            gas_limit: self.current_gas_limit,
+            timestamp: self.current_timestamp,
+            prev_randao: self.current_random.unwrap_or_default(),
+            base_fee_per_gas,
+            excess_blob_gas,
+            parent_beacon_block_root: self.parent_beacon_block_root,
+            ..Default::default()
+        })
+    }
+
+    fn withdrawals(&self) -> Vec<Withdrawal> {
+        self.withdrawals
+            .iter()
+            .flatten()
+            .map(|withdrawal| Withdrawal {
+                index: withdrawal.index,
+                validator_index: withdrawal.validator_index,
+                address: withdrawal.address,
+                amount: withdrawal.amount,
+            })
+            .collect()
+    }
+}
+
+impl T8nOmmer {
+    /// A stand-in header of the ommer, of which the rewards only read the number and
+    /// coinbase.
+    fn header(&self, number: u64) -> BlockHeader {
+        BlockHeader {
+            number: number.saturating_sub(self.delta),
+            coinbase: self.address,
+            ..Default::default()
+        }
+    }
+}
+
+impl T8nTxs {
+    fn decode(&self, chain_id: u64) -> anyhow::Result<Vec<Transaction>> {
+        match self {
+            T8nTxs::Json(txs) => txs
+                .iter()
+                .enumerate()
+                .map(|(index, tx)| {
+                    tx.build(chain_id)
+                        .with_context(|| format!("transaction {index}"))
+                })
+                .collect(),
+            T8nTxs::Rlp(rlp) => Vec::<Transaction>::decode(rlp)
+                .map_err(|err| anyhow!("invalid transactions rlp: {err}")),
+        }
+    }
+}
+
+impl T8nTransaction {
+    /// The transaction of the type it gives, on `chain_id` if it gives no chain id.
+    fn build(&self, chain_id: u64) -> anyhow::Result<Transaction> {
+        let chain_id = self.chain_id.unwrap_or(chain_id);
+        let to = self.to.map_or(TxKind::Create, TxKind::Call);
+        let call_to = || {
+            self.to
+                .ok_or_else(|| anyhow!("transaction type cannot create contracts"))
+        };
+        let gas_price = || self.gas_price.ok_or_else(|| anyhow!("missing gasPrice"));
+        let fee_cap = || {
+            self.max_fee_per_gas
+                .ok_or_else(|| anyhow!("missing maxFeePerGas"))
+        };
+        let tip_cap = self.max_priority_fee_per_gas.unwrap_or_default();
+        let access_list: AccessList = self
+            .access_list
+            .iter()
+            .map(|item| (item.address, item.storage_keys.clone()))
+            .collect();
+        let (nonce, gas, value, data) = (self.nonce, self.gas, self.value, self.input.clone());
+
+        let mut tx = match self.tx_type.unwrap_or_default() {
+            0 => Transaction::LegacyTransaction(LegacyTransaction {
+                nonce,
+                gas_price: gas_price()?,
+                gas,
+                to,
+                value,
+                data,
+                ..Default::default()
+            }),
+            1 => Transaction::EIP2930Transaction(EIP2930Transaction {
+                chain_id,
+                nonce,
+                gas_price: gas_price()?,
+                gas_limit: gas,
+                to,
+                value,
+                data,
+                access_list,
+                ..Default::default()
+            }),
+            2 => Transaction::EIP1559Transaction(EIP1559Transaction {
+                chain_id,
+                nonce,
+                max_priority_fee_per_gas: tip_cap,
+                max_fee_per_gas: fee_cap()?,
+                gas_limit: gas,
+                to,
+                value,
+                data,
+                access_list,
+                ..Default::default()
+            }),
+            3 => Transaction::EIP4844Transaction(EIP4844Transaction {
+                chain_id,
+                nonce,
+                max_priority_fee_per_gas: tip_cap,
+                max_fee_per_gas: fee_cap()?,
+                gas,
+                to: call_to()?,
+                value,
+                data,
+                access_list,
+                max_fee_per_blob_gas: self.max_fee_per_blob_gas.unwrap_or_default(),
+                blob_versioned_hashes: self.blob_versioned_hashes.clone(),
+                ..Default::default()
+            }),
+            4 => Transaction::EIP7702Transaction(EIP7702Transaction {
+                chain_id,
+                nonce,
+                max_priority_fee_per_gas: tip_cap,
+                max_fee_per_gas: fee_cap()?,
+                gas_limit: gas,
+                to: call_to()?,
+                value,
+                data,
+                access_list,
+                authorization_list: self
+                    .authorization_list
+                    .iter()
+                    .map(T8nAuthorization::to_tuple)
+                    .collect(),
+                ..Default::default()
+            }),
+            tx_type => bail!("unknown transaction type {tx_type}"),
+        };
+
+        if let Some(secret_key) = &self.secret_key {
+            let chain_id = (tx.tx_type() != TxType::Legacy || self.protected).then_some(chain_id);
+            tx.sign(&secret_key.0, chain_id)
+                .ok_or_else(|| anyhow!("invalid secret key"))?;
+            return Ok(tx);
+        }
+        let (v, r, s) = (
+            self.v.unwrap_or_default(),
+            self.r.unwrap_or_default(),
+            self.s.unwrap_or_default(),
+        );
+        let y_parity = !v.is_zero();
+        match &mut tx {
+            Transaction::LegacyTransaction(tx) => (tx.v, tx.r, tx.s) = (v, r, s),
+            Transaction::EIP2930Transaction(tx) => {
+                (tx.signature_y_parity, tx.signature_r, tx.signature_s) = (y_parity, r, s)
+            }
+            Transaction::EIP1559Transaction(tx) => {
+                (tx.signature_y_parity, tx.signature_r, tx.signature_s) = (y_parity, r, s)
+            }
+            Transaction::EIP4844Transaction(tx) => {
+                (tx.signature_y_parity, tx.signature_r, tx.signature_s) = (y_parity, r, s)
+            }
+            Transaction::EIP7702Transaction(tx) => {
+                (tx.signature_y_parity, tx.signature_r, tx.signature_s) = (y_parity, r, s)
+            }
+        }
+        Ok(tx)
+    }
+}
+
+impl T8nAuthorization {
+    fn to_tuple(&self) -> AuthorizationTuple {
+        AuthorizationTuple {
+            chain_id: self.chain_id,
+            address: self.address,
+            nonce: self.nonce,
+            y_parity: self.y_parity.or(self.v).unwrap_or_default(),
+            r_signature: self.r,
+            s_signature: self.s,
+        }
+    }
+}
+
+fn protected_by_default() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    /// The pre-state and environment of the `storeAndLog` state test fixture.
+    fn input(txs: serde_json::Value) -> T8nInput {
+        serde_json::from_value(json!({
+            "alloc": {
+                "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b": { "balance": "0x0ba1a9ce0ba1a9ce" },
+                "0x0000000000000000000000000000000000001000": {
+                    "code": "0x600035600155600060005560aa60005260a060206000a100",
+                    "storage": { "0x00": "0x01" },
+                },
+            },
+            "env": {
+                "currentCoinbase": "0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba",
+                "currentGasLimit": "0x01c9c380",
+                "currentNumber": "0x01",
+                "currentTimestamp": "0x03e8",
+                "currentBaseFee": "0x07",
+                "currentRandom": "0x0000000000000000000000000000000000000000000000000000000000020000",
+                "currentExcessBlobGas": "0x00",
+                "parentBeaconBlockRoot": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "withdrawals": [],
+            },
+            "txs": txs,
+        }))
+        .unwrap()
+    }
+
+    fn transaction(nonce: u64, gas: u64) -> serde_json::Value {
+        json!({
+            "type": "0x0",
+            "nonce": format!("{nonce:#x}"),
+            "gas": format!("{gas:#x}"),
+            "gasPrice": "0x0a",
+            "to": "0x0000000000000000000000000000000000001000",
+            "value": "0x0",
+            "input": "0x0000000000000000000000000000000000000000000000000000000000000042",
+            "secretKey": "0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8",
+            "protected": false,
+        })
+    }
+
+    #[test]
+    fn matches_state_test_fixture() {
+        // The second transaction is short of its intrinsic gas
+        let txs = json!([transaction(0, 400_000), transaction(1, 21_000)]);
+        let output = transition(&input(txs), Fork::Cancun, 1, None).unwrap();
+        let result = serde_json::to_value(&output.result).unwrap();
+        // The fixture's root and logs hash for the first transaction alone
+        assert_eq!(
+            result["stateRoot"],
+            "0x5df9a5b6bae41601df7f6bb1798437d4d48b4a322edb894bb4fd76607619dc09"
+        );
+        assert_eq!(
+            result["logsHash"],
+            "0xb2be8c1d2e09a2d2d4ffa68c8c05b62ebc6cd0acb4058ce7656023353a99ed83"
+        );
+        assert_eq!(result["receipts"].as_array().unwrap().len(), 1);
+        assert_eq!(result["receipts"][0]["status"], "0x1");
+        assert_eq!(result["receipts"][0]["logs"][0]["logIndex"], "0x0");
+        assert_eq!(result["rejected"][0]["index"], 1);
+        assert!(result["rejected"][0]["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("intrinsic gas too low"));
+        assert_eq!(result["currentExcessBlobGas"], "0x0");
+        assert_eq!(
+            result["gasUsed"],
+            result["receipts"][0]["cumulativeGasUsed"]
+        );
+        assert!(result.get("requestsHash").is_none());
+        let storage = &output.alloc.0[&Address::from_low_u64_be(0x1000)].storage;
+        assert_eq!(
+            storage.clone().into_iter().collect::<Vec<_>>(),
+            [(H256::from_low_u64_be(1), U256::from(0x42))]
+        );
+
+        // The body holds the included transaction, which runs the same given as rlp
+        let body = serde_json::to_value(HexBytes(output.body)).unwrap();
+        let again = transition(&input(body), Fork::Cancun, 1, None).unwrap();
+        assert_eq!(again.result.state_root, output.result.state_root);
+        assert_eq!(again.result.tx_root, output.result.tx_root);
+        assert!(again.result.rejected.is_empty());
+    }
+
+    #[test]
+    fn requires_a_base_fee_from_london() {
+        let mut input = input(json!([]));
+        input.env.current_base_fee = None;
+        let err = transition(&input, Fork::Cancun, 1, None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "currentBaseFee or parentBaseFee is required from London"
+        );
+        input.env.parent_base_fee = Some(8);
+        input.env.parent_gas_limit = Some(30_000_000);
+        let output = transition(&input, Fork::Cancun, 1, None).unwrap();
+        assert_eq!(output.result.current_base_fee, Some(7));
+    }
+}
@@ -0,0 +1,202 @@
+//! Code analysis on mainnet contracts, and on contracts shaped like them: a selector
+//! dispatcher in front of function bodies, filled out to the contract size limit with
+//! push data.
+
+use std::{num::NonZeroUsize, sync::Arc};
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use ethereum_types::{Address, H160, U256};
+use evm::{
+    execute_transaction, BlockEnv, Bytecode, BytecodeCache, Env, Fork, JournaledState, MemoryDb,
+};
+use hex_literal::hex;
+use keccak_hash::keccak;
+use reec_core::types::{LegacyTransaction, Transaction, TxKind};
+
+const FUNCTIONS: u32 = 64;
+const CONTRACTS: u8 = 8;
+const CALLS_PER_BLOCK: u64 = 100;
+
+/// Runtime code of mainnet contracts as `cast code` gives it, each with a call that
+/// succeeds on no state: the `totalSupply()` of WETH and of USDC, whose proxy delegates
+/// to an implementation that is not there, the Uniswap V3 router's `WETH9()` and
+/// Seaport 1.1's `name()`.
+const MAINNET: [(&str, H160, &str, [u8; 4]); 4] = [
+    (
+        "WETH",
+        H160(hex!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2")),
+        include_str!("mainnet/weth.hex"),
+        hex!("18160ddd"),
+    ),
+    (
+        "USDC",
+        H160(hex!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48")),
+        include_str!("mainnet/usdc.hex"),
+        hex!("18160ddd"),
+    ),
+    (
+        "Uniswap V3 router",
+        H160(hex!("e592427a0aece92de3edee1f18e0157c05861564")),
+        include_str!("mainnet/uniswap_v3_router.hex"),
+        hex!("4aa4a4fc"),
+    ),
+    (
+        "Seaport",
+        H160(hex!("00000000006c3852cbef3e08e8df289169ede581")),
+        include_str!("mainnet/seaport.hex"),
+        hex!("06fdde03"),
+    ),
+];
+
+/// A contract of about 24KB dispatching on the calldata's selector to one of
+/// [`FUNCTIONS`] bodies, each of which pushes and drops some constants and stops.
+fn dispatcher(seed: u8) -> Bytes {
+    // PUSH1 0 CALLDATALOAD PUSH1 0xe0 SHR
+    let mut code = vec![0x60, 0x00, 0x35, 0x60, 0xe0, 0x1c];
+    let table_len = 6 + FUNCTIONS as usize * 11 + 1;
+    let body_len = (24_000 - table_len) / FUNCTIONS as usize;
+    for function in 0..FUNCTIONS {
+        let dest = (table_len + function as usize * body_len) as u16;
+        // DUP1 PUSH4 selector EQ PUSH2 dest JUMPI
+        code.extend([0x80, 0x63]);
+        code.extend(function.to_be_bytes());
+        code.extend([0x14, 0x61]);
+        code.extend(dest.to_be_bytes());
+        code.push(0x57);
+    }
+    code.push(0x00);
+    for _ in 0..FUNCTIONS {
+        let start = code.len();
+        code.push(0x5b);
+        // PUSH32 data POP
+        while code.len() + 34 < start + body_len {
+            code.push(0x7f);
+            code.extend([seed; 32]);
+            code.push(0x50);
+        }
+        code.resize(start + body_len - 1, 0x5b);
+        code.push(0x00);
+    }
+    Bytes::from(code)
+}
+
+fn mainnet_code(code: &str) -> Bytes {
+    hex::decode(code.trim().trim_start_matches("0x"))
+        .unwrap()
+        .into()
+}
+
+fn contract_address(index: u8) -> Address {
+    Address::repeat_byte(0xc0 + index)
+}
+
+fn sender() -> Address {
+    Address::repeat_byte(0xaa)
+}
+
+fn database() -> MemoryDb {
+    let mut db = MemoryDb::default();
+    db.insert_account(sender(), 0, U256::exp10(18), Bytes::new());
+    for index in 0..CONTRACTS {
+        db.insert_account(contract_address(index), 1, U256::zero(), dispatcher(index));
+    }
+    for (_, address, code, _) in MAINNET {
+        db.insert_account(address, 1, U256::zero(), mainnet_code(code));
+    }
+    db
+}
+
+/// Calls to the last function of each dispatcher, so the whole dispatcher runs.
+fn dispatcher_calls() -> Vec<(Address, Bytes)> {
+    let data = Bytes::copy_from_slice(&(FUNCTIONS - 1).to_be_bytes());
+    (0..CONTRACTS)
+        .map(|index| (contract_address(index), data.clone()))
+        .collect()
+}
+
+fn mainnet_calls() -> Vec<(Address, Bytes)> {
+    MAINNET
+        .iter()
+        .map(|(_, address, _, selector)| (*address, Bytes::copy_from_slice(selector)))
+        .collect()
+}
+
+/// Runs a block's worth of `calls`, taking them in turn.
+fn run_block(db: &MemoryDb, cache: Arc<BytecodeCache>, calls: &[(Address, Bytes)]) {
+    let env = Env {
+        fork: Fork::Cancun,
+        chain_id: 1,
+        block: BlockEnv {
+            gas_limit: 30_000_000,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut state = JournaledState::new(db, env).with_bytecode_cache(cache);
+    for nonce in 0..CALLS_PER_BLOCK {
+        let (to, data) = &calls[nonce as usize % calls.len()];
+        let tx = Transaction::LegacyTransaction(LegacyTransaction {
+            nonce,
+            gas: 100_000,
+            to: TxKind::Call(*to),
+            data: data.clone(),
+            ..Default::default()
+        });
+        let result = execute_transaction(&mut state, &tx, sender()).unwrap();
+        assert!(result.is_success());
+    }
+}
+
+fn analysis(c: &mut Criterion) {
+    let mut group = c.benchmark_group("analysis");
+    let contracts = MAINNET
+        .iter()
+        .map(|(name, _, code, _)| (*name, mainnet_code(code)))
+        .chain([("dispatcher", dispatcher(0))]);
+    for (name, code) in contracts {
+        let code_hash = keccak(&code);
+        group.bench_function(format!("analyze {name}"), |b| {
+            b.iter(|| Bytecode::new(code.clone()))
+        });
+        let cache = BytecodeCache::new(NonZeroUsize::new(16).unwrap());
+        cache.get_or_analyze(code_hash, code.clone());
+        group.bench_function(format!("cache hit {name}"), |b| {
+            b.iter(|| cache.get_or_analyze(code_hash, code.clone()))
+        });
+    }
+    group.finish();
+}
+
+fn block(c: &mut Criterion) {
+    let db = database();
+    for (workload, calls) in [
+        ("dispatchers", dispatcher_calls()),
+        ("mainnet", mainnet_calls()),
+    ] {
+        let mut group = c.benchmark_group(format!("block of {workload}"));
+        // With room for a single contract every call misses, as when nothing was cached
+        group.bench_function("uncached", |b| {
+            b.iter_batched(
+                || BytecodeCache::new(NonZeroUsize::MIN).into(),
+                |cache| run_block(&db, cache, &calls),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function("cold cache", |b| {
+            b.iter_batched(
+                || BytecodeCache::new(NonZeroUsize::new(64).unwrap()).into(),
+                |cache| run_block(&db, cache, &calls),
+                BatchSize::SmallInput,
+            )
+        });
+        let warm = Arc::new(BytecodeCache::new(NonZeroUsize::new(64).unwrap()));
+        group.bench_function("warm cache", |b| {
+            b.iter(|| run_block(&db, warm.clone(), &calls))
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, analysis, block);
+criterion_main!(benches);
@@ -0,0 +1,758 @@
+//! Execution of a whole block: the system calls around its transactions, the
+//! transactions themselves, rewards and withdrawals, and the requests it produces.
+
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, bail, Context};
+use bytes::Bytes;
+use ethereum_types::{Address, Bloom, H160, H256, U256};
+use hex_literal::hex;
+use reec_core::types::{
+    compute_receipts_root, Block, BlockBody, BlockHeader, InvalidBlockError, Log, Receipt,
+    ReceiptOutcome, StateDiff, Transaction,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::ChainConfig,
+    frame, gas,
+    interpreter::{CallInputs, CallScheme},
+    transaction::{execute_transaction, ExecutionResult, InvalidTransaction},
+    Action, BlockEnv, Database, Env, Fork, FrameResult, Host, JournaledState, NoopTracer, TxEnv,
+};
+
+/// Sender of the system calls, which are not transactions and pay nothing.
+pub const SYSTEM_ADDRESS: Address = H160(hex!("fffffffffffffffffffffffffffffffffffffffe"));
+/// EIP-4788 contract keeping recent beacon block roots.
+pub const BEACON_ROOTS_ADDRESS: Address = H160(hex!("000f3df6d732807ef1319fb7b8bb8522d0beac02"));
+/// EIP-2935 contract keeping recent block hashes.
+pub const HISTORY_STORAGE_ADDRESS: Address = H160(hex!("0000f90827f1c53a10cb7a02335b175320002935"));
+/// EIP-7002 queue of withdrawals requested from the execution layer.
+pub const WITHDRAWAL_REQUEST_ADDRESS: Address =
+    H160(hex!("00000961ef480eb55e80d19ad83579a64c007002"));
+/// EIP-7251 queue of validator consolidations.
+pub const CONSOLIDATION_REQUEST_ADDRESS: Address =
+    H160(hex!("0000bbddc7ce488642fb579f8b00f3a590007251"));
+
+const SYSTEM_CALL_GAS_LIMIT: u64 = 30_000_000;
+/// `DepositEvent(bytes,bytes,bytes,bytes,bytes)` of the deposit contract.
+const DEPOSIT_EVENT_TOPIC: H256 = H256(hex!(
+    "649bbc62d0e31342afea4e5cd82d4049e7e1ee912fc0889aa790803be39038c5"
+));
+const DEPOSIT_REQUEST_TYPE: u8 = 0x00;
+const WITHDRAWAL_REQUEST_TYPE: u8 = 0x01;
+const CONSOLIDATION_REQUEST_TYPE: u8 = 0x02;
+const GWEI: u64 = 1_000_000_000;
+const MIN_BLOB_BASE_FEE: u64 = 1;
+/// Base fee of the first block after London.
+const INITIAL_BASE_FEE: u64 = 1_000_000_000;
+const ELASTICITY_MULTIPLIER: u64 = 2;
+const BASE_FEE_MAX_CHANGE_DENOMINATOR: u128 = 8;
+
+/// What executing a block produced, to be checked against its header.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockResult {
+    pub receipts: Vec<Receipt>,
+    pub gas_used: u64,
+    pub blob_gas_used: u64,
+    /// EIP-7685 requests from Prague, each its type byte followed by its data. Types
+    /// without any request are left out.
+    pub requests: Vec<Bytes>,
+    pub state_diff: StateDiff,
+}
+
+impl BlockResult {
+    /// Checks everything the header commits to about execution except the state root.
+    pub fn validate(&self, header: &BlockHeader) -> Result<(), InvalidBlockError> {
+        if self.gas_used != header.gas_used {
+            return Err(InvalidBlockError::GasUsedMismatch);
+        }
+        if compute_receipts_root(&self.receipts) != header.receipts_root {
+            return Err(InvalidBlockError::ReceiptsRootMismatch);
+        }
+        let mut bloom = Bloom::zero();
+        for receipt in &self.receipts {
+            bloom.accrue_bloom(&receipt.bloom);
+        }
+        if bloom != header.logs_bloom {
+            return Err(InvalidBlockError::LogsBloomMismatch);
+        }
+        if header
+            .blob_gas_used
+            .is_some_and(|used| used != self.blob_gas_used)
+        {
+            return Err(InvalidBlockError::BlobGasUsedMismatch);
+        }
+        if header
+            .requests_hash
+            .is_some_and(|hash| hash != compute_requests_hash(&self.requests))
+        {
+            return Err(InvalidBlockError::RequestsHashMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// EIP-7685 commitment to a block's requests.
+pub fn compute_requests_hash(requests: &[Bytes]) -> H256 {
+    let mut hasher = Sha256::new();
+    for request in requests {
+        hasher.update(Sha256::digest(request));
+    }
+    H256::from_slice(&hasher.finalize())
+}
+
+/// Checks the header fields that depend on the fork: those each fork adds, the base fee
+/// (EIP-1559), the excess blob gas (EIP-4844) and the proof of work fields the merge
+/// zeroed (EIP-3675). With [`BlockHeader::validate_against_parent`] this covers the
+/// whole header except the difficulty and seal of pre-merge blocks.
+pub fn validate_header(
+    header: &BlockHeader,
+    parent: &BlockHeader,
+    fork: Fork,
+) -> Result<(), InvalidBlockError> {
+    let fields = [
+        (header.base_fee_per_gas.is_some(), Fork::London, "base fee"),
+        (
+            header.withdrawals_root.is_some(),
+            Fork::Shanghai,
+            "withdrawals root",
+        ),
+        (
+            header.blob_gas_used.is_some(),
+            Fork::Cancun,
+            "blob gas used",
+        ),
+        (
+            header.excess_blob_gas.is_some(),
+            Fork::Cancun,
+            "excess blob gas",
+        ),
+        (
+            header.parent_beacon_block_root.is_some(),
+            Fork::Cancun,
+            "parent beacon block root",
+        ),
+        (
+            header.requests_hash.is_some(),
+            Fork::Prague,
+            "requests hash",
+        ),
+    ];
+    for (present, since, field) in fields {
+        match (present, fork >= since) {
+            (false, true) => return Err(InvalidBlockError::MissingField(field)),
+            (true, false) => return Err(InvalidBlockError::UnexpectedField(field)),
+            _ => {}
+        }
+    }
+    if header
+        .base_fee_per_gas
+        .is_some_and(|base_fee| base_fee != calculate_base_fee(parent))
+    {
+        return Err(InvalidBlockError::InvalidBaseFee);
+    }
+    if header
+        .excess_blob_gas
+        .is_some_and(|excess| excess != calculate_excess_blob_gas(parent, fork))
+    {
+        return Err(InvalidBlockError::InvalidExcessBlobGas);
+    }
+    if fork >= Fork::Paris
+        && (!header.difficulty.is_zero()
+            || header.nonce != 0
+            || header.ommers_hash != BlockBody::default().compute_ommers_hash())
+    {
+        return Err(InvalidBlockError::ProofOfWorkAfterMerge);
+    }
+    Ok(())
+}
+
+/// EIP-1559 base fee of the child of `parent`, which moves by up to an eighth towards
+/// keeping blocks half full.
+pub fn calculate_base_fee(parent: &BlockHeader) -> u64 {
+    let Some(parent_base_fee) = parent.base_fee_per_gas else {
+        return INITIAL_BASE_FEE;
+    };
+    let target = parent.gas_limit / ELASTICITY_MULTIPLIER;
+    let change = |delta: u64| {
+        let change = parent_base_fee as u128 * delta as u128
+            / target.max(1) as u128
+            / BASE_FEE_MAX_CHANGE_DENOMINATOR;
+        change as u64
+    };
+    match parent.gas_used.cmp(&target) {
+        Ordering::Equal => parent_base_fee,
+        Ordering::Greater => {
+            parent_base_fee.saturating_add(change(parent.gas_used - target).max(1))
+        }
+        Ordering::Less => parent_base_fee - change(target - parent.gas_used),
+    }
+}
+
+/// EIP-4844 excess blob gas of the child of `parent`: the blob gas used above the
+/// target, accumulated over the blocks since Cancun.
+pub fn calculate_excess_blob_gas(parent: &BlockHeader, fork: Fork) -> u64 {
+    let excess =
+        parent.excess_blob_gas.unwrap_or_default() + parent.blob_gas_used.unwrap_or_default();
+    excess.saturating_sub(gas::target_blob_gas_per_block(fork))
+}
+
+/// EIP-4844 price of blob gas, which rises exponentially with the excess blob gas.
+pub fn blob_base_fee(excess_blob_gas: u64, fork: Fork) -> U256 {
+    let update_fraction = if fork >= Fork::Prague {
+        5_007_716
+    } else {
+        3_338_477
+    };
+    fake_exponential(
+        U256::from(MIN_BLOB_BASE_FEE),
+        U256::from(excess_blob_gas),
+        U256::from(update_fraction),
+    )
+}
+
+/// Approximates `factor * e ** (numerator / denominator)` with integers.
+fn fake_exponential(factor: U256, numerator: U256, denominator: U256) -> U256 {
+    let mut output = U256::zero();
+    let mut accumulator = factor * denominator;
+    let mut i = U256::one();
+    while !accumulator.is_zero() {
+        output += accumulator;
+        accumulator = accumulator * numerator / (denominator * i);
+        i += U256::one();
+    }
+    output / denominator
+}
+
+pub fn block_env(header: &BlockHeader, fork: Fork) -> BlockEnv {
+    BlockEnv {
+        number: header.number,
+        coinbase: header.coinbase,
+        timestamp: header.timestamp,
+        gas_limit: header.gas_limit,
+        base_fee: header.base_fee_per_gas.unwrap_or_default().into(),
+        difficulty: header.difficulty,
+        prev_randao: header.prev_randao,
+        blob_base_fee: match header.excess_blob_gas {
+            Some(excess_blob_gas) if fork >= Fork::Cancun => blob_base_fee(excess_blob_gas, fork),
+            _ => U256::zero(),
+        },
+    }
+}
+
+/// Executes `block` on top of the state in `db`, which must be its parent's.
+///
+/// Fails if the block is invalid, with an [`InvalidTransaction`] in the error chain if
+/// one of its transactions is. The result still has to be checked against the header,
+/// see [`BlockResult::validate`].
+pub fn execute_block<DB: Database>(
+    db: DB,
+    config: &ChainConfig,
+    block: &Block,
+) -> anyhow::Result<BlockResult> {
+    let header = &block.header;
+    let fork = config.fork(header.number, header.timestamp);
+    let mut state = begin_block(db, config, header)?;
+
+    let mut receipts = Vec::with_capacity(block.body.transactions.len());
+    let (mut gas_used, mut blob_gas_used) = (0, 0);
+    for (index, tx) in block.body.transactions.iter().enumerate() {
+        let context = || transaction_context(index, tx);
+        let blob_gas =
+            check_block_room(header, fork, tx, gas_used, blob_gas_used).with_context(context)?;
+        let sender = tx
+            .sender()
+            .ok_or_else(|| anyhow!("invalid signature"))
+            .with_context(context)?;
+        let result = execute_transaction(&mut state, tx, sender).with_context(context)?;
+        gas_used += result.gas_used;
+        blob_gas_used += blob_gas;
+        receipts.push(transaction_receipt(&state, tx, &result, gas_used)?);
+    }
+    finish_block(
+        state,
+        config,
+        block,
+        receipts,
+        gas_used,
+        blob_gas_used,
+        block_reward(fork),
+    )
+}
+
+/// Describes a transaction of the block in errors.
+pub(crate) fn transaction_context(index: usize, tx: &Transaction) -> String {
+    format!("transaction {index} ({:#x})", tx.compute_hash())
+}
+
+/// The receipt of `tx`, which left `state` with `cumulative_gas_used` gas used in the
+/// block. Before Byzantium it holds the state root `tx` left instead of its status
+/// (EIP-658).
+pub fn transaction_receipt<DB: Database>(
+    state: &JournaledState<DB>,
+    tx: &Transaction,
+    result: &ExecutionResult,
+    cumulative_gas_used: u64,
+) -> anyhow::Result<Receipt> {
+    if state.env().fork >= Fork::Byzantium {
+        return Ok(result.receipt(tx.tx_type(), cumulative_gas_used));
+    }
+    let root = state.db().state_root(&state.state_diff())?;
+    Ok(Receipt::with_outcome(
+        tx.tx_type(),
+        ReceiptOutcome::StateRoot(root),
+        cumulative_gas_used,
+        result.logs.clone(),
+    ))
+}
+
+/// Checks that the gas and blob gas left in the block, after the transactions before
+/// `tx` used theirs, cover `tx`. Returns its blob gas.
+pub fn check_block_room(
+    header: &BlockHeader,
+    fork: Fork,
+    tx: &Transaction,
+    gas_used: u64,
+    blob_gas_used: u64,
+) -> Result<u64, InvalidTransaction> {
+    let available = header.gas_limit.saturating_sub(gas_used);
+    if tx.gas_limit() > available {
+        return Err(InvalidTransaction::GasLimitExceedsBlock {
+            gas_limit: tx.gas_limit(),
+            available,
+        });
+    }
+    let blobs = tx.blob_versioned_hashes().len();
+    let blob_gas = gas::PER_BLOB * blobs as u64;
+    if blob_gas_used + blob_gas > gas::max_blob_gas_per_block(fork) {
+        return Err(InvalidTransaction::TooManyBlobs(blobs));
+    }
+    Ok(blob_gas)
+}
+
+/// Applies what follows the transactions of `block`, which left `state`: the request
+/// system calls, the rewards and the withdrawals. `reward` is the miner's for the block,
+/// see [`block_reward`], and `None` leaves the miner and ommers unpaid and untouched.
+pub fn finish_block<DB: Database>(
+    mut state: JournaledState<DB>,
+    config: &ChainConfig,
+    block: &Block,
+    receipts: Vec<Receipt>,
+    gas_used: u64,
+    blob_gas_used: u64,
+    reward: Option<U256>,
+) -> anyhow::Result<BlockResult> {
+    let fork = config.fork(block.header.number, block.header.timestamp);
+    let mut requests = Vec::new();
+    if fork >= Fork::Prague {
+        let logs = receipts.iter().flat_map(|receipt| &receipt.logs);
+        let mut deposits = vec![DEPOSIT_REQUEST_TYPE];
+        for log in logs.filter(|log| log.address == config.deposit_contract) {
+            if log.topics.first() == Some(&DEPOSIT_EVENT_TOPIC) {
+                deposits.extend_from_slice(&parse_deposit(log)?);
+            }
+        }
+        let queues = [
+            (
+                WITHDRAWAL_REQUEST_ADDRESS,
+                WITHDRAWAL_REQUEST_TYPE,
+                "withdrawal",
+            ),
+            (
+                CONSOLIDATION_REQUEST_ADDRESS,
+                CONSOLIDATION_REQUEST_TYPE,
+                "consolidation",
+            ),
+        ];
+        requests.push(deposits);
+        for (address, request_type, name) in queues {
+            let result = system_call(&mut state, address, Bytes::new())?
+                .ok_or_else(|| anyhow!("{name} request contract has no code"))?;
+            if !result.status.is_success() {
+                bail!("{name} request system call failed: {}", result.status);
+            }
+            requests.push([&[request_type], &result.output[..]].concat());
+        }
+        requests.retain(|request| request.len() > 1);
+    }
+
+    if let Some(reward) = reward {
+        apply_rewards(&mut state, block, reward)?;
+    }
+    for withdrawal in block.body.withdrawals.iter().flatten() {
+        state.add_balance(withdrawal.address, U256::from(withdrawal.amount) * GWEI)?;
+    }
+    state.finalize_transaction();
+
+    Ok(BlockResult {
+        receipts,
+        gas_used,
+        blob_gas_used,
+        requests: requests.into_iter().map(Bytes::from).collect(),
+        state_diff: state.state_diff(),
+    })
+}
+
+/// The state the transactions of the block with `header` run on: `db`, which must hold
+/// its parent's state, in the block's environment and with the system calls that come
+/// before the transactions applied.
+pub fn begin_block<DB: Database>(
+    db: DB,
+    config: &ChainConfig,
+    header: &BlockHeader,
+) -> anyhow::Result<JournaledState<DB>> {
+    let fork = config.fork(header.number, header.timestamp);
+    let env = Env {
+        fork,
+        chain_id: config.chain_id,
+        block: block_env(header, fork),
+        tx: TxEnv::default(),
+    };
+    let mut state = JournaledState::new(db, env);
+
+    if fork >= Fork::Cancun {
+        let root = header
+            .parent_beacon_block_root
+            .ok_or_else(|| anyhow!("missing parent beacon block root"))?;
+        system_call(
+            &mut state,
+            BEACON_ROOTS_ADDRESS,
+            Bytes::copy_from_slice(root.as_bytes()),
+        )?;
+    }
+    if fork >= Fork::Prague {
+        system_call(
+            &mut state,
+            HISTORY_STORAGE_ADDRESS,
+            Bytes::copy_from_slice(header.parent_hash.as_bytes()),
+        )?;
+    }
+    Ok(state)
+}
+
+/// Calls a system contract with the whole system call gas limit and outside of any
+/// transaction. Returns `None` if the contract has no code.
+fn system_call<DB: Database>(
+    state: &mut JournaledState<DB>,
+    address: Address,
+    input: Bytes,
+) -> anyhow::Result<Option<FrameResult>> {
+    if state.account_code(address)?.is_empty() {
+        return Ok(None);
+    }
+    state.env_mut().tx = TxEnv {
+        origin: SYSTEM_ADDRESS,
+        ..Default::default()
+    };
+    state.warm_address(address);
+    let call = CallInputs {
+        scheme: CallScheme::Call,
+        target: address,
+        code_address: address,
+        caller: SYSTEM_ADDRESS,
+        value: U256::zero(),
+        transfer: false,
+        input,
+        gas_limit: SYSTEM_CALL_GAS_LIMIT,
+        is_static: false,
+        depth: 0,
+    };
+    let (result, _) = frame::execute(state, Action::Call(Box::new(call)), &mut NoopTracer)?;
+    state.finalize_transaction();
+    Ok(Some(result))
+}
+
+/// The miner's reward for a block, `None` from the merge.
+pub fn block_reward(fork: Fork) -> Option<U256> {
+    let ether = U256::exp10(18);
+    match fork {
+        f if f >= Fork::Paris => None,
+        f if f >= Fork::Constantinople => Some(ether * 2),
+        f if f >= Fork::Byzantium => Some(ether * 3),
+        _ => Some(ether * 5),
+    }
+}
+
+/// Pays `reward` to the miner and shares of it to the miners of included ommers.
+fn apply_rewards<DB: Database>(
+    state: &mut JournaledState<DB>,
+    block: &Block,
+    reward: U256,
+) -> anyhow::Result<()> {
+    let number = block.header.number;
+    for ommer in &block.body.ommers {
+        let distance = (ommer.number + 8).saturating_sub(number);
+        state.add_balance(ommer.coinbase, reward * distance / 8)?;
+    }
+    let inclusion_reward = reward / 32 * block.body.ommers.len();
+    state.add_balance(block.header.coinbase, reward + inclusion_reward)
+}
+
+/// Flattens an EIP-6110 deposit log into the request data: public key, withdrawal
+/// credentials, amount, signature and index.
+fn parse_deposit(log: &Log) -> anyhow::Result<[u8; 192]> {
+    // Five ABI encoded byte strings: their offsets, then each length and padded content
+    const LAYOUT: [(usize, usize); 5] = [(160, 48), (256, 32), (320, 8), (384, 96), (512, 8)];
+    let data = &log.data;
+    if data.len() != 576 {
+        bail!("invalid deposit log length {}", data.len());
+    }
+    let word = |offset: usize| U256::from_big_endian(&data[offset..offset + 32]);
+    let mut request = [0; 192];
+    let mut written = 0;
+    for (field, (offset, len)) in LAYOUT.into_iter().enumerate() {
+        if word(field * 32) != U256::from(offset) || word(offset) != U256::from(len) {
+            bail!("invalid deposit log layout");
+        }
+        request[written..written + len].copy_from_slice(&data[offset + 32..offset + 32 + len]);
+        written += len;
+    }
+    Ok(request)
+}
+
+#[cfg(test)]
+mod test {
+    use reec_core::{
+        crypto,
+        types::{LegacyTransaction, TxKind, Withdrawal},
+    };
+
+    use super::*;
+    use crate::db::MemoryDb;
+
+    #[test]
+    fn blob_base_fee_grows_with_excess() {
+        assert_eq!(blob_base_fee(0, Fork::Cancun), U256::one());
+        assert_eq!(blob_base_fee(10_000_000, Fork::Cancun), 19.into());
+        assert_eq!(blob_base_fee(10_000_000, Fork::Prague), 7.into());
+    }
+
+    #[test]
+    fn base_fee_follows_gas_used() {
+        let parent = BlockHeader {
+            gas_limit: 30_000_000,
+            gas_used: 15_000_000,
+            base_fee_per_gas: Some(1_000_000_000),
+            ..Default::default()
+        };
+        assert_eq!(calculate_base_fee(&parent), 1_000_000_000);
+        assert_eq!(
+            calculate_base_fee(&BlockHeader {
+                gas_used: 30_000_000,
+                ..parent.clone()
+            }),
+            1_125_000_000
+        );
+        assert_eq!(
+            calculate_base_fee(&BlockHeader {
+                gas_used: 0,
+                ..parent.clone()
+            }),
+            875_000_000
+        );
+        assert_eq!(
+            calculate_base_fee(&BlockHeader {
+                base_fee_per_gas: None,
+                ..parent
+            }),
+            INITIAL_BASE_FEE
+        );
+    }
+
+    #[test]
+    fn validates_fork_fields() {
+        let parent = BlockHeader {
+            gas_limit: 30_000_000,
+            base_fee_per_gas: Some(7),
+            withdrawals_root: Some(H256::zero()),
+            ommers_hash: BlockBody::default().compute_ommers_hash(),
+            ..Default::default()
+        };
+        let header = BlockHeader {
+            base_fee_per_gas: Some(calculate_base_fee(&parent)),
+            ..parent.clone()
+        };
+        assert_eq!(validate_header(&header, &parent, Fork::Shanghai), Ok(()));
+        assert_eq!(
+            validate_header(&header, &parent, Fork::London),
+            Err(InvalidBlockError::UnexpectedField("withdrawals root"))
+        );
+        assert_eq!(
+            validate_header(&header, &parent, Fork::Cancun),
+            Err(InvalidBlockError::MissingField("blob gas used"))
+        );
+        let header = BlockHeader {
+            base_fee_per_gas: Some(8),
+            ..header
+        };
+        assert_eq!(
+            validate_header(&header, &parent, Fork::Shanghai),
+            Err(InvalidBlockError::InvalidBaseFee)
+        );
+        let header = BlockHeader {
+            base_fee_per_gas: parent.base_fee_per_gas.map(|_| calculate_base_fee(&parent)),
+            difficulty: 1.into(),
+            ..header
+        };
+        assert_eq!(
+            validate_header(&header, &parent, Fork::Shanghai),
+            Err(InvalidBlockError::ProofOfWorkAfterMerge)
+        );
+    }
+
+    #[test]
+    fn parses_deposit_log() {
+        let mut data = vec![0; 576];
+        for (field, (offset, len)) in [(160, 48), (256, 32), (320, 8), (384, 96), (512, 8)]
+            .into_iter()
+            .enumerate()
+        {
+            data[field * 32 + 31] = (offset & 0xff) as u8;
+            data[field * 32 + 30] = (offset >> 8) as u8;
+            data[offset + 31] = len as u8;
+            data[offset + 32..offset + 32 + len].fill(field as u8 + 1);
+        }
+        let log = Log {
+            data: data.into(),
+            ..Default::default()
+        };
+        let request = parse_deposit(&log).unwrap();
+        assert_eq!(request[..48], [1; 48]);
+        assert_eq!(request[80..88], [3; 8]);
+        assert_eq!(request[184..], [5; 8]);
+        let truncated = Log {
+            data: log.data.slice(..544),
+            ..Default::default()
+        };
+        assert!(parse_deposit(&truncated).is_err());
+    }
+
+    #[test]
+    fn pays_rewards_and_withdrawals() {
+        let miner = Address::repeat_byte(1);
+        let ommer = BlockHeader {
+            number: 9,
+            coinbase: Address::repeat_byte(2),
+            ..Default::default()
+        };
+        let block = Block {
+            header: BlockHeader {
+                number: 10,
+                coinbase: miner,
+                ..Default::default()
+            },
+            body: BlockBody {
+                ommers: vec![ommer],
+                ..Default::default()
+            },
+        };
+        let result = execute_block(
+            MemoryDb::default(),
+            &ChainConfig::from_genesis(1, Fork::Byzantium),
+            &block,
+        )
+        .unwrap();
+        let ether = U256::exp10(18);
+        assert_eq!(
+            result.state_diff[&miner].info.as_ref().unwrap().balance,
+            ether * 3 + ether * 3 / 32
+        );
+        let ommer_balance = result.state_diff[&Address::repeat_byte(2)]
+            .info
+            .as_ref()
+            .unwrap()
+            .balance;
+        assert_eq!(ommer_balance, ether * 3 * 7 / 8);
+
+        let withdrawal = Withdrawal {
+            address: miner,
+            amount: 2,
+            ..Default::default()
+        };
+        let header = BlockHeader {
+            number: 10,
+            receipts_root: compute_receipts_root(&[]),
+            ..Default::default()
+        };
+        let block = Block {
+            header,
+            body: BlockBody {
+                withdrawals: Some(vec![withdrawal]),
+                ..Default::default()
+            },
+        };
+        let result = execute_block(
+            MemoryDb::default(),
+            &ChainConfig::from_genesis(1, Fork::Shanghai),
+            &block,
+        )
+        .unwrap();
+        assert_eq!(
+            result.state_diff[&miner].info.as_ref().unwrap().balance,
+            U256::from(2 * GWEI)
+        );
+        assert!(result.validate(&block.header).is_ok());
+    }
+
+    #[test]
+    fn records_state_roots_before_byzantium() {
+        let key = [1; 32];
+        let sender = crypto::secret_key_to_address(&key).unwrap();
+        let (recipient, coinbase) = (Address::repeat_byte(0xee), Address::repeat_byte(0xcb));
+        let balance = U256::exp10(18);
+        let mut db = MemoryDb::default();
+        db.insert_account(sender, 0, balance, Bytes::new());
+        let transactions = (0..2)
+            .map(|nonce| {
+                let mut tx = Transaction::LegacyTransaction(LegacyTransaction {
+                    nonce,
+                    gas_price: U256::one(),
+                    gas: 21_000,
+                    to: TxKind::Call(recipient),
+                    value: U256::from(100),
+                    ..Default::default()
+                });
+                tx.sign(&key, None).unwrap();
+                tx
+            })
+            .collect();
+        let mut block = Block {
+            header: BlockHeader {
+                number: 1,
+                coinbase,
+                gas_limit: 30_000_000,
+                gas_used: 42_000,
+                ..Default::default()
+            },
+            body: BlockBody {
+                transactions,
+                ..Default::default()
+            },
+        };
+        let config = ChainConfig::from_genesis(1, Fork::Homestead);
+        let result = execute_block(&db, &config, &block).unwrap();
+
+        // The state each transaction left, without the block reward paid after them
+        let roots: Vec<_> = (1..=2)
+            .map(|sent: u64| {
+                let mut after = MemoryDb::default();
+                let spent = U256::from(sent * (21_000 + 100));
+                after.insert_account(sender, sent, balance - spent, Bytes::new());
+                after.insert_account(recipient, 0, U256::from(sent * 100), Bytes::new());
+                after.insert_account(coinbase, 0, U256::from(sent * 21_000), Bytes::new());
+                ReceiptOutcome::StateRoot(after.state_root(&StateDiff::new()).unwrap())
+            })
+            .collect();
+        let outcomes: Vec<_> = result.receipts.iter().map(|r| r.outcome).collect();
+        assert_eq!(outcomes, roots);
+
+        assert_eq!(
+            result.validate(&block.header),
+            Err(InvalidBlockError::ReceiptsRootMismatch)
+        );
+        block.header.receipts_root = compute_receipts_root(&result.receipts);
+        block.header.logs_bloom = Bloom::zero();
+        assert_eq!(result.validate(&block.header), Ok(()));
+    }
+}
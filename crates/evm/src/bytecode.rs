@@ -0,0 +1,214 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use bytes::Bytes;
+use ethereum_types::{Address, H256};
+use lru::LruCache;
+
+use crate::{
+    eof::{self, ContainerKind, Eof},
+    opcode,
+};
+
+/// Zeroes appended to the code so that a trailing `PUSH32` reads its missing immediate
+/// bytes as zeroes and execution then runs into `STOP`.
+const PADDING: usize = 33;
+
+/// Contracts the shared [`BytecodeCache`] holds. Most calls on mainnet go to a few
+/// thousand contracts.
+const SHARED_CACHE_CAPACITY: NonZeroUsize = NonZeroUsize::new(4096).unwrap();
+
+static SHARED_CACHE: LazyLock<Arc<BytecodeCache>> =
+    LazyLock::new(|| Arc::new(BytecodeCache::new(SHARED_CACHE_CAPACITY)));
+
+/// Start of an EIP-7702 delegation designator, the code of an account that runs the
+/// code of the address after it instead.
+pub const DELEGATION_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
+
+/// The code that delegates to `address`.
+pub fn delegation_designator(address: Address) -> Bytes {
+    [&DELEGATION_PREFIX[..], address.as_bytes()].concat().into()
+}
+
+/// The address `code` delegates to, if it is a delegation designator.
+pub fn delegated_address(code: &[u8]) -> Option<Address> {
+    let address = code.strip_prefix(&DELEGATION_PREFIX)?;
+    (address.len() == 20).then(|| Address::from_slice(address))
+}
+
+/// Contract code along with the positions of its valid jump destinations, and its EOF
+/// container if it is one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bytecode {
+    code: Bytes,
+    padded: Vec<u8>,
+    /// Bitmap of the `JUMPDEST`s that are opcodes rather than push data.
+    jumpdests: Vec<u64>,
+    eof: Option<Eof>,
+}
+
+impl Bytecode {
+    /// Analyzes deployed code, which runs as EOF if it is a valid runtime container and
+    /// the fork has EOF. It runs as legacy code otherwise.
+    pub fn new(code: Bytes) -> Self {
+        let eof = code
+            .starts_with(&eof::MAGIC)
+            .then(|| Eof::decode(code.clone()).ok())
+            .flatten()
+            .filter(|eof| eof.validate(ContainerKind::Runtime).is_ok());
+        Bytecode {
+            eof,
+            ..Bytecode::legacy(code)
+        }
+    }
+
+    /// Code of a container that was validated already, such as init code.
+    pub fn from_eof(eof: Eof) -> Self {
+        Bytecode {
+            eof: Some(eof.clone()),
+            ..Bytecode::legacy(eof.raw().clone())
+        }
+    }
+
+    fn legacy(code: Bytes) -> Self {
+        let mut jumpdests = vec![0; code.len().div_ceil(64)];
+        let mut pc = 0;
+        while pc < code.len() {
+            let op = code[pc];
+            if op == opcode::JUMPDEST {
+                jumpdests[pc / 64] |= 1 << (pc % 64);
+            }
+            pc += 1 + opcode::immediate_size(op);
+        }
+        let mut padded = Vec::with_capacity(code.len() + PADDING);
+        padded.extend_from_slice(&code);
+        padded.resize(code.len() + PADDING, 0);
+        Bytecode {
+            code,
+            padded,
+            jumpdests,
+            eof: None,
+        }
+    }
+
+    /// The code as deployed.
+    pub fn original(&self) -> &Bytes {
+        &self.code
+    }
+
+    pub fn len(&self) -> usize {
+        self.code.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.code.is_empty()
+    }
+
+    /// The code followed by enough zeroes to read any opcode or immediate at a
+    /// position up to its length.
+    pub fn padded(&self) -> &[u8] {
+        &self.padded
+    }
+
+    pub fn eof(&self) -> Option<&Eof> {
+        self.eof.as_ref()
+    }
+
+    pub fn is_jumpdest(&self, pc: usize) -> bool {
+        pc < self.code.len() && self.jumpdests[pc / 64] & (1 << (pc % 64)) != 0
+    }
+}
+
+/// Analyzed code by code hash, for the contracts called most recently. Deployed code
+/// never changes under its hash, so entries stay valid across blocks and threads.
+#[derive(Debug)]
+pub struct BytecodeCache {
+    entries: Mutex<LruCache<H256, Arc<Bytecode>>>,
+}
+
+impl BytecodeCache {
+    /// A cache that keeps the analysis of up to `capacity` contracts.
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        BytecodeCache {
+            entries: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// The cache block executions share unless given another one.
+    pub fn shared() -> Arc<BytecodeCache> {
+        SHARED_CACHE.clone()
+    }
+
+    /// The analysis of `code`, whose hash is `code_hash`, from the cache or done now.
+    pub fn get_or_analyze(&self, code_hash: H256, code: Bytes) -> Arc<Bytecode> {
+        if let Some(bytecode) = self.lock().get(&code_hash) {
+            return bytecode.clone();
+        }
+        // Analyzed outside the lock, so that other threads are not held up
+        let bytecode = Arc::new(Bytecode::new(code));
+        self.lock().put(code_hash, bytecode.clone());
+        bytecode
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache<H256, Arc<Bytecode>>> {
+        // Entries are inserted whole, so a panic elsewhere cannot leave one half-written
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_push_data() {
+        // JUMPDEST PUSH2 0x5b5b JUMPDEST PUSH1 (truncated)
+        let code = Bytecode::new(Bytes::from_static(&[0x5b, 0x61, 0x5b, 0x5b, 0x5b, 0x60]));
+        let jumpdests: Vec<usize> = (0..8).filter(|pc| code.is_jumpdest(*pc)).collect();
+        assert_eq!(jumpdests, vec![0, 4]);
+        assert_eq!(code.padded().len(), 6 + PADDING);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = BytecodeCache::new(NonZeroUsize::new(2).unwrap());
+        let hashes: Vec<_> = (1..=3).map(H256::from_low_u64_be).collect();
+        let first = cache.get_or_analyze(hashes[0], Bytes::from_static(&[0x5b]));
+        cache.get_or_analyze(hashes[1], Bytes::from_static(&[0x00]));
+        // Using the first makes the second the one to go
+        assert!(Arc::ptr_eq(
+            &first,
+            &cache.get_or_analyze(hashes[0], Bytes::new())
+        ));
+        cache.get_or_analyze(hashes[2], Bytes::from_static(&[0x01]));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get_or_analyze(hashes[0], Bytes::new()).is_jumpdest(0));
+        assert!(cache.get_or_analyze(hashes[1], Bytes::new()).is_empty());
+    }
+
+    #[test]
+    fn parses_delegation_designators() {
+        let address = Address::repeat_byte(0x42);
+        let designator = delegation_designator(address);
+        assert_eq!(designator.len(), 23);
+        assert_eq!(delegated_address(&designator), Some(address));
+        assert_eq!(delegated_address(&designator[..22]), None);
+        assert_eq!(delegated_address(&[0xef, 0x01, 0x01]), None);
+    }
+}
@@ -0,0 +1,109 @@
+//! Which fork applies to a block of a given chain.
+
+use ethereum_types::Address;
+use hex_literal::hex;
+use reec_core::types::BlockNumber;
+
+use crate::Fork;
+
+/// When a fork activates: by block number before the merge, by timestamp after it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Activation {
+    Block(BlockNumber),
+    Timestamp(u64),
+}
+
+/// Fork schedule and consensus constants of a chain. Irregular state changes, such as
+/// the DAO fork's, are not applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    /// Forks after Frontier in activation order.
+    pub forks: Vec<(Fork, Activation)>,
+    /// Contract whose deposit logs become EIP-6110 requests.
+    pub deposit_contract: Address,
+}
+
+impl ChainConfig {
+    pub fn mainnet() -> Self {
+        ChainConfig {
+            chain_id: 1,
+            forks: vec![
+                (Fork::Homestead, Activation::Block(1_150_000)),
+                (Fork::Tangerine, Activation::Block(2_463_000)),
+                (Fork::SpuriousDragon, Activation::Block(2_675_000)),
+                (Fork::Byzantium, Activation::Block(4_370_000)),
+                (Fork::Constantinople, Activation::Block(7_280_000)),
+                (Fork::Petersburg, Activation::Block(7_280_000)),
+                (Fork::Istanbul, Activation::Block(9_069_000)),
+                (Fork::Berlin, Activation::Block(12_244_000)),
+                (Fork::London, Activation::Block(12_965_000)),
+                (Fork::Paris, Activation::Block(15_537_394)),
+                (Fork::Shanghai, Activation::Timestamp(1_681_338_455)),
+                (Fork::Cancun, Activation::Timestamp(1_710_338_135)),
+                (Fork::Prague, Activation::Timestamp(1_746_612_311)),
+            ],
+            deposit_contract: Address::from(hex!("00000000219ab540356cbb839cbe05303d7705fa")),
+        }
+    }
+
+    /// A chain running `fork` and everything before it from genesis, as test fixtures
+    /// assume.
+    pub fn from_genesis(chain_id: u64, fork: Fork) -> Self {
+        ChainConfig {
+            chain_id,
+            forks: Fork::ALL
+                .into_iter()
+                .filter(|f| *f > Fork::Frontier && *f <= fork)
+                .map(|f| (f, Activation::Block(0)))
+                .collect(),
+            ..ChainConfig::mainnet()
+        }
+    }
+
+    /// The fork that applies to the block with the given number and timestamp.
+    pub fn fork(&self, number: BlockNumber, timestamp: u64) -> Fork {
+        self.forks
+            .iter()
+            .take_while(|(_, activation)| match activation {
+                Activation::Block(block) => number >= *block,
+                Activation::Timestamp(time) => timestamp >= *time,
+            })
+            .last()
+            .map_or(Fork::Frontier, |(fork, _)| *fork)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn picks_fork_by_block_then_timestamp() {
+        let mainnet = ChainConfig::mainnet();
+        assert_eq!(mainnet.fork(0, 0), Fork::Frontier);
+        assert_eq!(mainnet.fork(1_150_000, 0), Fork::Homestead);
+        assert_eq!(mainnet.fork(7_280_000, 0), Fork::Petersburg);
+        assert_eq!(mainnet.fork(15_537_394, 1_681_338_454), Fork::Paris);
+        assert_eq!(mainnet.fork(17_034_870, 1_681_338_455), Fork::Shanghai);
+        assert_eq!(mainnet.fork(22_431_084, 1_746_612_311), Fork::Prague);
+        assert_eq!(
+            ChainConfig::from_genesis(1, Fork::Berlin).fork(0, 0),
+            Fork::Berlin
+        );
+    }
+
+    #[test]
+    fn eof_only_runs_where_scheduled() {
+        assert_eq!(
+            ChainConfig::mainnet().fork(u64::MAX, u64::MAX),
+            Fork::Prague
+        );
+        let devnet = ChainConfig::from_genesis(1, Fork::Eof);
+        assert_eq!(devnet.fork(0, 0), Fork::Eof);
+        assert_eq!(
+            devnet.forks.last(),
+            Some(&(Fork::Eof, Activation::Block(0)))
+        );
+    }
+}
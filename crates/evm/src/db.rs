@@ -0,0 +1,86 @@
+//! A [`Database`] held in memory, for state that comes from test fixtures or has to be
+//! built up by hand.
+
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use keccak_hash::keccak;
+use reec_core::types::{
+    compute_state_root, AccountInfo, BlockNumber, FullState, StateDiff, EMPTY_CODE_HASH,
+};
+
+use crate::Database;
+
+#[derive(Clone, Debug, Default)]
+pub struct MemoryDb {
+    accounts: HashMap<Address, AccountInfo>,
+    storage: HashMap<(Address, H256), U256>,
+    codes: HashMap<H256, Bytes>,
+    block_hashes: HashMap<BlockNumber, H256>,
+}
+
+impl MemoryDb {
+    pub fn insert_account(&mut self, address: Address, nonce: u64, balance: U256, code: Bytes) {
+        let code_hash = if code.is_empty() {
+            EMPTY_CODE_HASH
+        } else {
+            keccak(&code)
+        };
+        if !code.is_empty() {
+            self.codes.insert(code_hash, code);
+        }
+        self.accounts.insert(
+            address,
+            AccountInfo {
+                nonce,
+                balance,
+                code_hash,
+            },
+        );
+    }
+
+    pub fn insert_storage(&mut self, address: Address, slot: H256, value: U256) {
+        self.storage.insert((address, slot), value);
+    }
+
+    pub fn insert_block_hash(&mut self, number: BlockNumber, hash: H256) {
+        self.block_hashes.insert(number, hash);
+    }
+}
+
+impl Database for MemoryDb {
+    fn account(&self, address: Address) -> anyhow::Result<Option<AccountInfo>> {
+        Ok(self.accounts.get(&address).cloned())
+    }
+
+    fn storage(&self, address: Address, slot: H256) -> anyhow::Result<U256> {
+        Ok(self
+            .storage
+            .get(&(address, slot))
+            .copied()
+            .unwrap_or_default())
+    }
+
+    fn code(&self, code_hash: H256) -> anyhow::Result<Bytes> {
+        Ok(self.codes.get(&code_hash).cloned().unwrap_or_default())
+    }
+
+    fn block_hash(&self, number: BlockNumber) -> anyhow::Result<Option<H256>> {
+        Ok(self.block_hashes.get(&number).copied())
+    }
+
+    fn state_root(&self, diff: &StateDiff) -> anyhow::Result<H256> {
+        let mut state: FullState = self
+            .accounts
+            .iter()
+            .map(|(address, info)| (*address, (info.clone(), Default::default())))
+            .collect();
+        for ((address, slot), value) in &self.storage {
+            if let Some((_, storage)) = state.get_mut(address) {
+                storage.insert(*slot, *value);
+            }
+        }
+        Ok(compute_state_root(state, diff))
+    }
+}
@@ -0,0 +1,35 @@
+use ethereum_types::{Address, H256, U256};
+
+use crate::Fork;
+
+/// Everything a transaction can observe about the block and itself besides state.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Env {
+    pub fork: Fork,
+    pub chain_id: u64,
+    pub block: BlockEnv,
+    pub tx: TxEnv,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockEnv {
+    pub number: u64,
+    pub coinbase: Address,
+    pub timestamp: u64,
+    pub gas_limit: u64,
+    /// Zero before London.
+    pub base_fee: U256,
+    /// Only meaningful before the merge, replaced by `prev_randao` after it.
+    pub difficulty: U256,
+    pub prev_randao: H256,
+    /// Zero before Cancun.
+    pub blob_base_fee: U256,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TxEnv {
+    pub origin: Address,
+    /// Effective gas price paid per unit of gas.
+    pub gas_price: U256,
+    pub blob_hashes: Vec<H256>,
+}
@@ -0,0 +1,1421 @@
+//! The EVM Object Format (EIP-3540 and the rest of EIP-7692): code in a versioned
+//! container that splits it into functions and keeps it apart from its data. Containers
+//! are validated once, before they are deployed or run as init code, so that execution
+//! needs neither jump destination analysis nor stack checks on every instruction.
+
+use std::{fmt, ops::Range};
+
+use bytes::Bytes;
+
+use crate::{opcode::*, stack::STACK_LIMIT};
+
+pub const MAGIC: [u8; 2] = [0xef, 0x00];
+pub const VERSION: u8 = 1;
+
+const KIND_TYPES: u8 = 0x01;
+const KIND_CODE: u8 = 0x02;
+const KIND_CONTAINER: u8 = 0x03;
+const KIND_DATA: u8 = 0xff;
+const TERMINATOR: u8 = 0x00;
+
+const MAX_CODE_SECTIONS: usize = 1024;
+const MAX_CONTAINER_SECTIONS: usize = 256;
+const MAX_INPUTS_OUTPUTS: u8 = 0x7f;
+/// Highest the operand stack may grow within a code section, its inputs included.
+const MAX_STACK_HEIGHT: usize = 0x3ff;
+/// `outputs` of a code section that never returns to its caller.
+pub const NON_RETURNING: u8 = 0x80;
+/// Depth limit of the `CALLF` return stack.
+pub const RETURN_STACK_LIMIT: usize = 1024;
+
+/// Operand stack items taken and left by each instruction EOF code may use, `None` for
+/// the opcodes it may not. Those of `CALLF`, `RETF`, `JUMPF`, `DUPN`, `SWAPN` and
+/// `EXCHANGE` depend on their immediates and are worked out during validation.
+const STACK_IO: [Option<(u8, u8)>; 256] = {
+    let mut io = [None; 256];
+    set_io(
+        &mut io,
+        &[
+            STOP, JUMPDEST, INVALID, RJUMP, CALLF, RETF, JUMPF, DUPN, SWAPN, EXCHANGE,
+        ],
+        (0, 0),
+    );
+    set_io(
+        &mut io,
+        &[
+            ADD, MUL, SUB, DIV, SDIV, MOD, SMOD, EXP, SIGNEXTEND, LT, GT, SLT, SGT, EQ, AND, OR,
+            XOR, BYTE, SHL, SHR, SAR, KECCAK256,
+        ],
+        (2, 1),
+    );
+    set_io(&mut io, &[ADDMOD, MULMOD], (3, 1));
+    set_io(
+        &mut io,
+        &[
+            ISZERO,
+            NOT,
+            BALANCE,
+            CALLDATALOAD,
+            BLOCKHASH,
+            BLOBHASH,
+            MLOAD,
+            SLOAD,
+            TLOAD,
+            DATALOAD,
+            RETURNDATALOAD,
+        ],
+        (1, 1),
+    );
+    set_io(
+        &mut io,
+        &[
+            ADDRESS,
+            ORIGIN,
+            CALLER,
+            CALLVALUE,
+            CALLDATASIZE,
+            GASPRICE,
+            RETURNDATASIZE,
+            COINBASE,
+            TIMESTAMP,
+            NUMBER,
+            PREVRANDAO,
+            GASLIMIT,
+            CHAINID,
+            SELFBALANCE,
+            BASEFEE,
+            BLOBBASEFEE,
+            MSIZE,
+            DATALOADN,
+            DATASIZE,
+        ],
+        (0, 1),
+    );
+    set_io(
+        &mut io,
+        &[CALLDATACOPY, RETURNDATACOPY, MCOPY, DATACOPY],
+        (3, 0),
+    );
+    set_io(&mut io, &[POP, RJUMPI, RJUMPV], (1, 0));
+    set_io(
+        &mut io,
+        &[
+            MSTORE,
+            MSTORE8,
+            SSTORE,
+            TSTORE,
+            RETURN,
+            REVERT,
+            RETURNCONTRACT,
+        ],
+        (2, 0),
+    );
+    set_io(&mut io, &[EOFCREATE, EXTCALL], (4, 1));
+    set_io(&mut io, &[EXTDELEGATECALL, EXTSTATICCALL], (3, 1));
+    let mut n = 0;
+    while n < 33 {
+        io[(PUSH0 + n) as usize] = Some((0, 1));
+        n += 1;
+    }
+    let mut n = 0;
+    while n < 16 {
+        io[(DUP1 + n) as usize] = Some((n + 1, n + 2));
+        io[(SWAP1 + n) as usize] = Some((n + 2, n + 2));
+        n += 1;
+    }
+    let mut n = 0;
+    while n < 5 {
+        io[(LOG0 + n) as usize] = Some((n + 2, 0));
+        n += 1;
+    }
+    io
+};
+
+const fn set_io(io: &mut [Option<(u8, u8)>; 256], ops: &[u8], stack: (u8, u8)) {
+    let mut i = 0;
+    while i < ops.len() {
+        io[ops[i] as usize] = Some(stack);
+        i += 1;
+    }
+}
+
+/// Whether execution never continues after `op` in the same section.
+fn is_terminating(op: u8) -> bool {
+    matches!(
+        op,
+        STOP | RETURN | REVERT | INVALID | RETF | JUMPF | RETURNCONTRACT
+    )
+}
+
+/// Number of immediate bytes following the instruction at `pc` of EOF code, which for
+/// `RJUMPV` depends on the first of them.
+pub fn immediate_size(code: &[u8], pc: usize) -> usize {
+    match code[pc] {
+        op @ PUSH1..=PUSH32 => (op - PUSH0) as usize,
+        RJUMP | RJUMPI | CALLF | JUMPF | DATALOADN => 2,
+        DUPN | SWAPN | EXCHANGE | EOFCREATE | RETURNCONTRACT => 1,
+        RJUMPV => {
+            1 + code
+                .get(pc + 1)
+                .map_or(0, |&max_index| 2 * (max_index as usize + 1))
+        }
+        _ => 0,
+    }
+}
+
+/// The operands of `EXCHANGE`, from its immediate.
+pub fn exchange_operands(immediate: u8) -> (usize, usize) {
+    (
+        (immediate >> 4) as usize + 1,
+        (immediate & 0x0f) as usize + 1,
+    )
+}
+
+/// What a container is validated for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContainerKind {
+    /// Code deployed to an account.
+    Runtime,
+    /// Code run by `EOFCREATE` or a creation transaction, which ends with
+    /// `RETURNCONTRACT` rather than `RETURN` or `STOP`.
+    Initcode,
+}
+
+/// Why a container is not valid EOF.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EofError {
+    InvalidMagic,
+    InvalidVersion,
+    /// The header is malformed, for the given reason.
+    InvalidHeader(&'static str),
+    /// The body is shorter than the sections before the data that the header declares.
+    TruncatedBody,
+    /// Bytes follow the data section.
+    TrailingBytes,
+    /// The data section is shorter than declared, which only a container deployed by
+    /// `RETURNCONTRACT` may be before its aux data is appended.
+    TruncatedData,
+    /// A code section's inputs, outputs or stack increase are out of range, or the first
+    /// section takes inputs or returns.
+    InvalidType {
+        section: usize,
+    },
+    UndefinedInstruction {
+        section: usize,
+        pc: usize,
+        opcode: u8,
+    },
+    TruncatedImmediate {
+        section: usize,
+        pc: usize,
+    },
+    InvalidJumpTarget {
+        section: usize,
+        pc: usize,
+    },
+    InvalidSectionIndex {
+        section: usize,
+        pc: usize,
+    },
+    CallfToNonReturning {
+        section: usize,
+        pc: usize,
+    },
+    /// `DATALOADN` reads past the declared data section.
+    InvalidDataLoadN {
+        section: usize,
+        pc: usize,
+    },
+    InvalidContainerIndex {
+        section: usize,
+        pc: usize,
+    },
+    /// The section is declared returning but has neither `RETF` nor a `JUMPF` to a
+    /// returning section, or the other way around.
+    InvalidReturning {
+        section: usize,
+    },
+    /// An instruction the kind of container may not use, or a subcontainer used both as
+    /// init code and as code to deploy.
+    IncompatibleContainerKind {
+        section: usize,
+        pc: usize,
+    },
+    UnreferencedContainer(usize),
+    UnreachableSection(usize),
+    UnreachableCode {
+        section: usize,
+        pc: usize,
+    },
+    /// Execution can run past the end of the section.
+    NoTerminatingInstruction {
+        section: usize,
+    },
+    StackUnderflow {
+        section: usize,
+        pc: usize,
+    },
+    StackOverflow {
+        section: usize,
+    },
+    /// The stack height differs between paths into an instruction in a way only forward
+    /// jumps allow, or is not what `RETF` or `JUMPF` need.
+    StackHeightMismatch {
+        section: usize,
+        pc: usize,
+    },
+    InvalidMaxStackIncrease {
+        section: usize,
+    },
+}
+
+impl fmt::Display for EofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EofError::InvalidMagic => f.write_str("invalid magic"),
+            EofError::InvalidVersion => f.write_str("invalid version"),
+            EofError::InvalidHeader(reason) => write!(f, "invalid header: {reason}"),
+            EofError::TruncatedBody => f.write_str("container shorter than its sections"),
+            EofError::TrailingBytes => f.write_str("bytes after the data section"),
+            EofError::TruncatedData => f.write_str("data section shorter than declared"),
+            EofError::InvalidType { section } => {
+                write!(f, "invalid type of code section {section}")
+            }
+            EofError::UndefinedInstruction {
+                section,
+                pc,
+                opcode,
+            } => {
+                write!(
+                    f,
+                    "undefined instruction {opcode:#04x} at {pc} in code section {section}"
+                )
+            }
+            EofError::TruncatedImmediate { section, pc } => {
+                write!(f, "truncated immediate at {pc} in code section {section}")
+            }
+            EofError::InvalidJumpTarget { section, pc } => {
+                write!(f, "invalid jump target at {pc} in code section {section}")
+            }
+            EofError::InvalidSectionIndex { section, pc } => {
+                write!(
+                    f,
+                    "invalid code section index at {pc} in code section {section}"
+                )
+            }
+            EofError::CallfToNonReturning { section, pc } => {
+                write!(
+                    f,
+                    "CALLF to a non-returning section at {pc} in code section {section}"
+                )
+            }
+            EofError::InvalidDataLoadN { section, pc } => {
+                write!(
+                    f,
+                    "DATALOADN past the data section at {pc} in code section {section}"
+                )
+            }
+            EofError::InvalidContainerIndex { section, pc } => {
+                write!(
+                    f,
+                    "invalid container index at {pc} in code section {section}"
+                )
+            }
+            EofError::InvalidReturning { section } => {
+                write!(
+                    f,
+                    "returning flag of code section {section} does not match its code"
+                )
+            }
+            EofError::IncompatibleContainerKind { section, pc } => {
+                write!(f, "instruction at {pc} in code section {section} is not allowed in this kind of container")
+            }
+            EofError::UnreferencedContainer(index) => {
+                write!(f, "container section {index} is never used")
+            }
+            EofError::UnreachableSection(section) => {
+                write!(f, "code section {section} is unreachable")
+            }
+            EofError::UnreachableCode { section, pc } => {
+                write!(f, "unreachable code at {pc} in code section {section}")
+            }
+            EofError::NoTerminatingInstruction { section } => {
+                write!(
+                    f,
+                    "code section {section} does not end with a terminating instruction"
+                )
+            }
+            EofError::StackUnderflow { section, pc } => {
+                write!(f, "stack underflow at {pc} in code section {section}")
+            }
+            EofError::StackOverflow { section } => {
+                write!(f, "stack overflow in code section {section}")
+            }
+            EofError::StackHeightMismatch { section, pc } => {
+                write!(f, "stack height mismatch at {pc} in code section {section}")
+            }
+            EofError::InvalidMaxStackIncrease { section } => {
+                write!(f, "wrong max stack increase of code section {section}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EofError {}
+
+/// How a code section is called: the stack items it takes and leaves, and how far it
+/// grows the stack above its inputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CodeType {
+    pub inputs: u8,
+    /// [`NON_RETURNING`] for sections that do not return.
+    pub outputs: u8,
+    pub max_stack_increase: u16,
+}
+
+impl CodeType {
+    pub fn is_returning(&self) -> bool {
+        self.outputs != NON_RETURNING
+    }
+}
+
+/// A parsed EOF container. Sections are kept as ranges of the raw container, in which
+/// code sections are also addressed, so that relative jumps and the program counter work
+/// on the container as a whole.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Eof {
+    raw: Bytes,
+    types: Vec<CodeType>,
+    code: Vec<Range<usize>>,
+    containers: Vec<Range<usize>>,
+    /// The data section as present, which may fall short of `data_size`.
+    data: Range<usize>,
+    data_size: usize,
+    /// Where in the header `data_size` is, to be updated as aux data is appended.
+    data_size_offset: usize,
+}
+
+impl Eof {
+    /// Parses a container, which is not validated. Its data section may be shorter than
+    /// the header declares.
+    pub fn decode(raw: Bytes) -> Result<Eof, EofError> {
+        let eof = Eof::parse(raw)?;
+        if eof.data.end < eof.raw.len() {
+            return Err(EofError::TrailingBytes);
+        }
+        Ok(eof)
+    }
+
+    /// Parses the container at the start of a creation transaction's data, returning it
+    /// and the call data that follows it (EIP-7698).
+    pub fn decode_with_calldata(data: &Bytes) -> Result<(Eof, Bytes), EofError> {
+        let mut eof = Eof::parse(data.clone())?;
+        if !eof.is_data_complete() {
+            return Err(EofError::TruncatedData);
+        }
+        let calldata = data.slice(eof.data.end..);
+        eof.raw = data.slice(..eof.data.end);
+        Ok((eof, calldata))
+    }
+
+    fn parse(raw: Bytes) -> Result<Eof, EofError> {
+        let mut header = Reader {
+            bytes: &raw,
+            pos: 0,
+        };
+        if header.u8()? != MAGIC[0] || header.u8()? != MAGIC[1] {
+            return Err(EofError::InvalidMagic);
+        }
+        if header.u8()? != VERSION {
+            return Err(EofError::InvalidVersion);
+        }
+        header.kind(KIND_TYPES, "missing type section")?;
+        let types_size = header.u16()?;
+        header.kind(KIND_CODE, "missing code sections")?;
+        let code_sections = header.u16()?;
+        if code_sections == 0 || code_sections > MAX_CODE_SECTIONS {
+            return Err(EofError::InvalidHeader("invalid number of code sections"));
+        }
+        if types_size != code_sections * 4 {
+            return Err(EofError::InvalidHeader(
+                "type section size does not match the code sections",
+            ));
+        }
+        let code_sizes = (0..code_sections)
+            .map(|_| header.u16())
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut container_sizes = Vec::new();
+        if header.peek()? == KIND_CONTAINER {
+            header.pos += 1;
+            let count = header.u16()?;
+            if count == 0 || count > MAX_CONTAINER_SECTIONS {
+                return Err(EofError::InvalidHeader(
+                    "invalid number of container sections",
+                ));
+            }
+            container_sizes = (0..count)
+                .map(|_| header.u32())
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+        header.kind(KIND_DATA, "missing data section")?;
+        let data_size_offset = header.pos;
+        let data_size = header.u16()?;
+        if header.u8()? != TERMINATOR {
+            return Err(EofError::InvalidHeader("missing terminator"));
+        }
+        if code_sizes.contains(&0) || container_sizes.contains(&0) {
+            return Err(EofError::InvalidHeader("empty section"));
+        }
+
+        let mut pos = header.pos;
+        let mut sections = |sizes: &[usize]| {
+            sizes
+                .iter()
+                .map(|&size| {
+                    pos += size;
+                    pos - size..pos
+                })
+                .collect::<Vec<_>>()
+        };
+        let types = sections(&[types_size]).remove(0);
+        let code = sections(&code_sizes);
+        let containers = sections(&container_sizes);
+        if pos > raw.len() {
+            return Err(EofError::TruncatedBody);
+        }
+        let types = raw[types]
+            .chunks(4)
+            .map(|ty| CodeType {
+                inputs: ty[0],
+                outputs: ty[1],
+                max_stack_increase: u16::from_be_bytes([ty[2], ty[3]]),
+            })
+            .collect();
+        let data = pos..raw.len().min(pos + data_size);
+        Ok(Eof {
+            raw,
+            types,
+            code,
+            containers,
+            data,
+            data_size,
+            data_size_offset,
+        })
+    }
+
+    pub fn raw(&self) -> &Bytes {
+        &self.raw
+    }
+
+    pub fn types(&self) -> &[CodeType] {
+        &self.types
+    }
+
+    pub fn code_section_count(&self) -> usize {
+        self.code.len()
+    }
+
+    /// Where code section `index` starts within the container.
+    pub fn code_start(&self, index: usize) -> usize {
+        self.code[index].start
+    }
+
+    pub fn code_section(&self, index: usize) -> &[u8] {
+        &self.raw[self.code[index].clone()]
+    }
+
+    pub fn container_count(&self) -> usize {
+        self.containers.len()
+    }
+
+    pub fn container(&self, index: usize) -> Bytes {
+        self.raw.slice(self.containers[index].clone())
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.raw[self.data.clone()]
+    }
+
+    /// Size of the data section as declared.
+    pub fn data_size(&self) -> usize {
+        self.data_size
+    }
+
+    fn is_data_complete(&self) -> bool {
+        self.data.len() == self.data_size
+    }
+
+    /// The container with `aux_data` appended to its data section, as `RETURNCONTRACT`
+    /// deploys it. `None` if the data section is still shorter than declared, or too
+    /// long for the header.
+    pub fn with_aux_data(&self, aux_data: &[u8]) -> Option<Bytes> {
+        let data_size = self.data.len() + aux_data.len();
+        if data_size < self.data_size || data_size > u16::MAX as usize {
+            return None;
+        }
+        let mut raw = Vec::with_capacity(self.raw.len() + aux_data.len());
+        raw.extend_from_slice(&self.raw);
+        raw.extend_from_slice(aux_data);
+        raw[self.data_size_offset..self.data_size_offset + 2]
+            .copy_from_slice(&(data_size as u16).to_be_bytes());
+        Some(raw.into())
+    }
+
+    /// Checks that the container is valid as `kind`, and so are the containers nested in
+    /// it as whatever its code uses them for.
+    pub fn validate(&self, kind: ContainerKind) -> Result<(), EofError> {
+        if !self.is_data_complete() {
+            return Err(EofError::TruncatedData);
+        }
+        let mut pending = Vec::new();
+        self.validate_one(kind, &mut pending)?;
+        while let Some((container, kind)) = pending.pop() {
+            // Only code yet to be deployed may lack the aux data that completes it
+            if kind == ContainerKind::Initcode && !container.is_data_complete() {
+                return Err(EofError::TruncatedData);
+            }
+            container.validate_one(kind, &mut pending)?;
+        }
+        Ok(())
+    }
+
+    /// Validates this container's code, adding its subcontainers to `pending`.
+    fn validate_one(
+        &self,
+        kind: ContainerKind,
+        pending: &mut Vec<(Eof, ContainerKind)>,
+    ) -> Result<(), EofError> {
+        for (section, ty) in self.types.iter().enumerate() {
+            let first_ok = section != 0 || (ty.inputs == 0 && !ty.is_returning());
+            if !first_ok
+                || ty.inputs > MAX_INPUTS_OUTPUTS
+                || (ty.outputs > MAX_INPUTS_OUTPUTS && ty.is_returning())
+                || ty.max_stack_increase as usize > MAX_STACK_HEIGHT
+            {
+                return Err(EofError::InvalidType { section });
+            }
+        }
+
+        let mut container_kinds = vec![None; self.containers.len()];
+        let mut reached = vec![false; self.code.len()];
+        reached[0] = true;
+        let mut sections = vec![0];
+        while let Some(section) = sections.pop() {
+            for callee in self.validate_section(section, kind, &mut container_kinds)? {
+                if !reached[callee] {
+                    reached[callee] = true;
+                    sections.push(callee);
+                }
+            }
+        }
+        if let Some(section) = reached.iter().position(|reached| !reached) {
+            return Err(EofError::UnreachableSection(section));
+        }
+        for (index, kind) in container_kinds.into_iter().enumerate() {
+            let kind = kind.ok_or(EofError::UnreferencedContainer(index))?;
+            pending.push((Eof::decode(self.container(index))?, kind));
+        }
+        Ok(())
+    }
+
+    /// Validates the instructions of a code section and the stack heights they run at
+    /// (EIP-3670, EIP-4200 and EIP-5450), recording what the section uses subcontainers
+    /// for. Returns the sections it calls or jumps to.
+    fn validate_section(
+        &self,
+        section: usize,
+        kind: ContainerKind,
+        container_kinds: &mut [Option<ContainerKind>],
+    ) -> Result<Vec<usize>, EofError> {
+        let code = self.code_section(section);
+        let ty = self.types[section];
+        let mut is_start = vec![false; code.len()];
+        let mut jump_targets = Vec::new();
+        let mut callees = Vec::new();
+        let mut returns = false;
+        let mut pc = 0;
+        while pc < code.len() {
+            let op = code[pc];
+            is_start[pc] = true;
+            if STACK_IO[op as usize].is_none() {
+                return Err(EofError::UndefinedInstruction {
+                    section,
+                    pc,
+                    opcode: op,
+                });
+            }
+            let next = pc + 1 + immediate_size(code, pc);
+            if next > code.len() {
+                return Err(EofError::TruncatedImmediate { section, pc });
+            }
+            match op {
+                RJUMP | RJUMPI | RJUMPV => {
+                    for target in relative_targets(code, pc) {
+                        let target = usize::try_from(target)
+                            .ok()
+                            .filter(|&target| target < code.len())
+                            .ok_or(EofError::InvalidJumpTarget { section, pc })?;
+                        jump_targets.push((pc, target));
+                    }
+                }
+                CALLF | JUMPF => {
+                    let callee = read_u16(code, pc + 1);
+                    let callee_type = self
+                        .types
+                        .get(callee)
+                        .ok_or(EofError::InvalidSectionIndex { section, pc })?;
+                    if op == CALLF && !callee_type.is_returning() {
+                        return Err(EofError::CallfToNonReturning { section, pc });
+                    }
+                    returns |= op == JUMPF && callee_type.is_returning();
+                    callees.push(callee);
+                }
+                RETF => returns = true,
+                DATALOADN if read_u16(code, pc + 1) + 32 > self.data_size => {
+                    return Err(EofError::InvalidDataLoadN { section, pc });
+                }
+                EOFCREATE | RETURNCONTRACT => {
+                    let index = code[pc + 1] as usize;
+                    let used = container_kinds
+                        .get_mut(index)
+                        .ok_or(EofError::InvalidContainerIndex { section, pc })?;
+                    let used_as = if op == EOFCREATE {
+                        ContainerKind::Initcode
+                    } else {
+                        ContainerKind::Runtime
+                    };
+                    if (op == RETURNCONTRACT && kind == ContainerKind::Runtime)
+                        || used.is_some_and(|used| used != used_as)
+                    {
+                        return Err(EofError::IncompatibleContainerKind { section, pc });
+                    }
+                    *used = Some(used_as);
+                }
+                RETURN | STOP if kind == ContainerKind::Initcode => {
+                    return Err(EofError::IncompatibleContainerKind { section, pc });
+                }
+                _ => {}
+            }
+            pc = next;
+        }
+        for (pc, target) in jump_targets {
+            if !is_start[target] {
+                return Err(EofError::InvalidJumpTarget { section, pc });
+            }
+        }
+        if returns != ty.is_returning() {
+            return Err(EofError::InvalidReturning { section });
+        }
+        self.validate_stack(section, &is_start)?;
+        Ok(callees)
+    }
+
+    /// Works out the lowest and highest stack height each instruction can run at, in one
+    /// pass in code order, as the only backward jumps allowed are those that arrive at
+    /// the height the target was first reached with.
+    fn validate_stack(&self, section: usize, is_start: &[bool]) -> Result<(), EofError> {
+        let code = self.code_section(section);
+        let ty = self.types[section];
+        let mut heights: Vec<Option<(usize, usize)>> = vec![None; code.len()];
+        heights[0] = Some((ty.inputs as usize, ty.inputs as usize));
+        let mut max_height = ty.inputs as usize;
+        let mut pc = 0;
+        while pc < code.len() {
+            debug_assert!(is_start[pc]);
+            let Some((min, max)) = heights[pc] else {
+                return Err(EofError::UnreachableCode { section, pc });
+            };
+            let op = code[pc];
+            let (inputs, outputs) = match op {
+                CALLF | JUMPF => {
+                    let callee = self.types[read_u16(code, pc + 1)];
+                    if max + callee.max_stack_increase as usize > STACK_LIMIT {
+                        return Err(EofError::StackOverflow { section });
+                    }
+                    if op == JUMPF && callee.is_returning() {
+                        // Returns straight to the caller, so it must leave what this
+                        // section would
+                        let expected = (ty.outputs as usize + callee.inputs as usize)
+                            .checked_sub(callee.outputs as usize);
+                        if ty.outputs < callee.outputs || min != max || Some(max) != expected {
+                            return Err(EofError::StackHeightMismatch { section, pc });
+                        }
+                    }
+                    let outputs = if callee.is_returning() {
+                        callee.outputs
+                    } else {
+                        0
+                    };
+                    (callee.inputs as usize, outputs as usize)
+                }
+                RETF => {
+                    if min != max || max != ty.outputs as usize {
+                        return Err(EofError::StackHeightMismatch { section, pc });
+                    }
+                    (0, 0)
+                }
+                DUPN => {
+                    let n = code[pc + 1] as usize + 1;
+                    (n, n + 1)
+                }
+                SWAPN => {
+                    let n = code[pc + 1] as usize + 2;
+                    (n, n)
+                }
+                EXCHANGE => {
+                    let (n, m) = exchange_operands(code[pc + 1]);
+                    (n + m + 1, n + m + 1)
+                }
+                op => {
+                    let (inputs, outputs) =
+                        STACK_IO[op as usize].expect("instructions were checked");
+                    (inputs as usize, outputs as usize)
+                }
+            };
+            if min < inputs {
+                return Err(EofError::StackUnderflow { section, pc });
+            }
+            let after = (min - inputs + outputs, max - inputs + outputs);
+            max_height = max_height.max(after.1);
+
+            let next = pc + 1 + immediate_size(code, pc);
+            let mut successors = Vec::new();
+            if op != RJUMP && !is_terminating(op) {
+                if next >= code.len() {
+                    return Err(EofError::NoTerminatingInstruction { section });
+                }
+                successors.push(next);
+            }
+            if matches!(op, RJUMP | RJUMPI | RJUMPV) {
+                successors.extend(relative_targets(code, pc).map(|target| target as usize));
+            }
+            for successor in successors {
+                if successor > pc {
+                    let merged = match heights[successor] {
+                        Some((min, max)) => (min.min(after.0), max.max(after.1)),
+                        None => after,
+                    };
+                    heights[successor] = Some(merged);
+                } else if heights[successor] != Some(after) {
+                    return Err(EofError::StackHeightMismatch { section, pc });
+                }
+            }
+            pc = next;
+        }
+        if max_height > MAX_STACK_HEIGHT {
+            return Err(EofError::StackOverflow { section });
+        }
+        if max_height - ty.inputs as usize != ty.max_stack_increase as usize {
+            return Err(EofError::InvalidMaxStackIncrease { section });
+        }
+        Ok(())
+    }
+}
+
+/// Where the relative jump at `pc` can go, as offsets into the section that may be out
+/// of range. Offsets count from the end of the instruction.
+fn relative_targets(code: &[u8], pc: usize) -> impl Iterator<Item = isize> + '_ {
+    let end = (pc + 1 + immediate_size(code, pc)) as isize;
+    let offsets = match code[pc] {
+        RJUMPV => pc + 2..end as usize,
+        _ => pc + 1..end as usize,
+    };
+    code[offsets]
+        .chunks(2)
+        .map(move |offset| end + i16::from_be_bytes([offset[0], offset[1]]) as isize)
+}
+
+fn read_u16(code: &[u8], pos: usize) -> usize {
+    u16::from_be_bytes([code[pos], code[pos + 1]]) as usize
+}
+
+/// Reads the header, failing on anything cut short.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], EofError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + N)
+            .ok_or(EofError::InvalidHeader("truncated header"))?;
+        self.pos += N;
+        Ok(bytes.try_into().expect("N bytes were taken"))
+    }
+
+    fn peek(&self) -> Result<u8, EofError> {
+        self.bytes
+            .get(self.pos)
+            .copied()
+            .ok_or(EofError::InvalidHeader("truncated header"))
+    }
+
+    fn u8(&mut self) -> Result<u8, EofError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<usize, EofError> {
+        Ok(u16::from_be_bytes(self.take()?) as usize)
+    }
+
+    fn u32(&mut self) -> Result<usize, EofError> {
+        Ok(u32::from_be_bytes(self.take()?) as usize)
+    }
+
+    fn kind(&mut self, kind: u8, missing: &'static str) -> Result<(), EofError> {
+        if self.u8()? != kind {
+            return Err(EofError::InvalidHeader(missing));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethereum_types::{Address, H256, U256};
+    use reec_core::types::{EIP1559Transaction, Transaction, TxKind};
+
+    use super::*;
+    use crate::{
+        db::MemoryDb, eofcreate_address, execute_transaction, BlockEnv, Env, ExitStatus, Fork,
+        JournaledState,
+    };
+
+    const SENDER: Address = Address::repeat_byte(0xaa);
+
+    /// A container of code sections given as inputs, outputs, max stack increase and
+    /// code, with `data_size` declared for `data`.
+    fn container_with(
+        sections: &[(u8, u8, u16, &[u8])],
+        containers: &[&[u8]],
+        data: &[u8],
+        data_size: u16,
+    ) -> Vec<u8> {
+        let mut raw = vec![0xef, 0x00, VERSION, KIND_TYPES];
+        raw.extend((sections.len() as u16 * 4).to_be_bytes());
+        raw.push(KIND_CODE);
+        raw.extend((sections.len() as u16).to_be_bytes());
+        for (_, _, _, code) in sections {
+            raw.extend((code.len() as u16).to_be_bytes());
+        }
+        if !containers.is_empty() {
+            raw.push(KIND_CONTAINER);
+            raw.extend((containers.len() as u16).to_be_bytes());
+            for container in containers {
+                raw.extend((container.len() as u32).to_be_bytes());
+            }
+        }
+        raw.push(KIND_DATA);
+        raw.extend(data_size.to_be_bytes());
+        raw.push(TERMINATOR);
+        for (inputs, outputs, max_stack_increase, _) in sections {
+            raw.extend([*inputs, *outputs]);
+            raw.extend(max_stack_increase.to_be_bytes());
+        }
+        for (_, _, _, code) in sections {
+            raw.extend_from_slice(code);
+        }
+        for container in containers {
+            raw.extend_from_slice(container);
+        }
+        raw.extend_from_slice(data);
+        raw
+    }
+
+    fn container(sections: &[(u8, u8, u16, &[u8])], containers: &[&[u8]], data: &[u8]) -> Vec<u8> {
+        container_with(sections, containers, data, data.len() as u16)
+    }
+
+    fn validate(raw: &[u8], kind: ContainerKind) -> Result<(), EofError> {
+        Eof::decode(Bytes::copy_from_slice(raw))?.validate(kind)
+    }
+
+    fn validate_code(code: &[u8], max_stack_increase: u16) -> Result<(), EofError> {
+        validate(
+            &container(&[(0, NON_RETURNING, max_stack_increase, code)], &[], &[]),
+            ContainerKind::Runtime,
+        )
+    }
+
+    #[test]
+    fn decodes_sections() {
+        let raw = container(
+            &[(0, NON_RETURNING, 0, &[STOP]), (1, 0, 0, &[POP, RETF])],
+            &[],
+            &[1, 2, 3],
+        );
+        let eof = Eof::decode(Bytes::from(raw.clone())).unwrap();
+        assert_eq!(eof.code_section_count(), 2);
+        assert_eq!(
+            eof.types()[1],
+            CodeType {
+                inputs: 1,
+                outputs: 0,
+                max_stack_increase: 0
+            }
+        );
+        assert_eq!(eof.code_section(1), [POP, RETF]);
+        assert_eq!(eof.code_start(1), raw.len() - 5);
+        assert_eq!(eof.data(), [1, 2, 3]);
+
+        let mut bad_version = raw.clone();
+        bad_version[2] = 2;
+        assert_eq!(
+            Eof::decode(bad_version.into()),
+            Err(EofError::InvalidVersion)
+        );
+        assert_eq!(
+            Eof::decode(Bytes::from_static(&[0xef, 0x01, 0x01])),
+            Err(EofError::InvalidMagic)
+        );
+        assert_eq!(
+            Eof::decode(raw[..12].to_vec().into()),
+            Err(EofError::InvalidHeader("truncated header"))
+        );
+        assert_eq!(
+            Eof::decode(raw[..raw.len() - 6].to_vec().into()),
+            Err(EofError::TruncatedBody)
+        );
+        let mut trailing = raw.clone();
+        trailing.push(0);
+        assert_eq!(Eof::decode(trailing.into()), Err(EofError::TrailingBytes));
+        let no_sections = [0xef, 0x00, 0x01, 0x01, 0x00, 0x00, 0x02, 0x00, 0x00];
+        assert!(matches!(
+            Eof::decode(Bytes::copy_from_slice(&no_sections)),
+            Err(EofError::InvalidHeader(_))
+        ));
+    }
+
+    #[test]
+    fn validates_instructions() {
+        assert_eq!(validate_code(&[STOP], 0), Ok(()));
+        assert_eq!(validate_code(&[PUSH0, PUSH0, ADD, POP, STOP], 2), Ok(()));
+        assert_eq!(
+            validate_code(&[PUSH0, JUMP], 1),
+            Err(EofError::UndefinedInstruction {
+                section: 0,
+                pc: 1,
+                opcode: JUMP
+            })
+        );
+        assert_eq!(
+            validate_code(&[PUSH2, 0x00], 1),
+            Err(EofError::TruncatedImmediate { section: 0, pc: 0 })
+        );
+        assert_eq!(
+            validate_code(&[PUSH0, POP], 1),
+            Err(EofError::NoTerminatingInstruction { section: 0 })
+        );
+        assert_eq!(
+            validate_code(&[STOP, STOP], 0),
+            Err(EofError::UnreachableCode { section: 0, pc: 1 })
+        );
+        assert_eq!(
+            validate_code(&[PUSH0, ADD, STOP], 1),
+            Err(EofError::StackUnderflow { section: 0, pc: 1 })
+        );
+        assert_eq!(
+            validate_code(&[PUSH0, POP, STOP], 2),
+            Err(EofError::InvalidMaxStackIncrease { section: 0 })
+        );
+        // Into the immediate of the PUSH1
+        assert_eq!(
+            validate_code(&[RJUMP, 0x00, 0x01, PUSH1, STOP, STOP], 0),
+            Err(EofError::InvalidJumpTarget { section: 0, pc: 0 })
+        );
+        assert_eq!(
+            validate_code(&[DATALOADN, 0x00, 0x00, POP, STOP], 1),
+            Err(EofError::InvalidDataLoadN { section: 0, pc: 0 })
+        );
+    }
+
+    #[test]
+    fn validates_stack_heights_across_jumps() {
+        // A loop that counts down from 3, its backward jump at the height it began with
+        let count_down = [
+            PUSH1, 3, JUMPDEST, PUSH1, 1, SWAP1, SUB, DUP1, RJUMPI, 0xff, 0xf7, POP, STOP,
+        ];
+        assert_eq!(validate_code(&count_down, 2), Ok(()));
+        // The same loop pushing an item each time round
+        let growing = [
+            PUSH1, 3, JUMPDEST, PUSH1, 1, DUP2, SUB, DUP1, RJUMPI, 0xff, 0xf7, POP, STOP,
+        ];
+        assert_eq!(
+            validate_code(&growing, 3),
+            Err(EofError::StackHeightMismatch { section: 0, pc: 8 })
+        );
+        // Forward jumps may join paths at different heights
+        let forward = [PUSH0, PUSH0, RJUMPI, 0x00, 0x01, PUSH0, STOP];
+        assert_eq!(validate_code(&forward, 2), Ok(()));
+        let table = [PUSH0, RJUMPV, 0x01, 0x00, 0x00, 0x00, 0x01, STOP, STOP];
+        assert_eq!(validate_code(&table, 1), Ok(()));
+    }
+
+    #[test]
+    fn validates_sections() {
+        let callf = |outputs| {
+            container(
+                &[
+                    (0, NON_RETURNING, outputs as u16, &[CALLF, 0x00, 0x01, STOP]),
+                    (0, outputs, 0, &[RETF]),
+                ],
+                &[],
+                &[],
+            )
+        };
+        assert_eq!(validate(&callf(0), ContainerKind::Runtime), Ok(()));
+        let stop = container(
+            &[
+                (0, NON_RETURNING, 0, &[CALLF, 0x00, 0x01, STOP]),
+                (0, NON_RETURNING, 0, &[STOP]),
+            ],
+            &[],
+            &[],
+        );
+        assert_eq!(
+            validate(&stop, ContainerKind::Runtime),
+            Err(EofError::CallfToNonReturning { section: 0, pc: 0 })
+        );
+        assert_eq!(
+            validate(&callf(1), ContainerKind::Runtime),
+            Err(EofError::StackHeightMismatch { section: 1, pc: 0 })
+        );
+        let unreachable = container(
+            &[(0, NON_RETURNING, 0, &[STOP]), (0, 0, 0, &[RETF])],
+            &[],
+            &[],
+        );
+        assert_eq!(
+            validate(&unreachable, ContainerKind::Runtime),
+            Err(EofError::UnreachableSection(1))
+        );
+        let first_returns = container(&[(0, 0, 0, &[RETF])], &[], &[]);
+        assert_eq!(
+            validate(&first_returns, ContainerKind::Runtime),
+            Err(EofError::InvalidType { section: 0 })
+        );
+        let jumpf_returning = container(
+            &[
+                (0, NON_RETURNING, 0, &[JUMPF, 0x00, 0x01]),
+                (0, 0, 0, &[RETF]),
+            ],
+            &[],
+            &[],
+        );
+        assert_eq!(
+            validate(&jumpf_returning, ContainerKind::Runtime),
+            Err(EofError::InvalidReturning { section: 0 })
+        );
+    }
+
+    #[test]
+    fn validates_subcontainers_by_use() {
+        let runtime = container(&[(0, NON_RETURNING, 0, &[STOP])], &[], &[]);
+        let deploys = [PUSH0, PUSH0, RETURNCONTRACT, 0x00];
+        let initcode = container(&[(0, NON_RETURNING, 2, &deploys)], &[&runtime], &[]);
+        assert_eq!(validate(&initcode, ContainerKind::Initcode), Ok(()));
+        assert_eq!(
+            validate(&initcode, ContainerKind::Runtime),
+            Err(EofError::IncompatibleContainerKind { section: 0, pc: 2 })
+        );
+        assert_eq!(
+            validate(&runtime, ContainerKind::Initcode),
+            Err(EofError::IncompatibleContainerKind { section: 0, pc: 0 })
+        );
+
+        let creates = [PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, POP, STOP];
+        let factory = container(&[(0, NON_RETURNING, 4, &creates)], &[&initcode], &[]);
+        assert_eq!(validate(&factory, ContainerKind::Runtime), Ok(()));
+        let unused = container(&[(0, NON_RETURNING, 0, &[STOP])], &[&initcode], &[]);
+        assert_eq!(
+            validate(&unused, ContainerKind::Runtime),
+            Err(EofError::UnreferencedContainer(0))
+        );
+        // Invalid code nested two levels down
+        let invalid = container(&[(0, NON_RETURNING, 0, &[JUMPDEST])], &[], &[]);
+        let initcode = container(&[(0, NON_RETURNING, 2, &deploys)], &[&invalid], &[]);
+        let factory = container(&[(0, NON_RETURNING, 4, &creates)], &[&initcode], &[]);
+        assert_eq!(
+            validate(&factory, ContainerKind::Runtime),
+            Err(EofError::NoTerminatingInstruction { section: 0 })
+        );
+    }
+
+    #[test]
+    fn only_deployed_code_may_lack_aux_data() {
+        let runtime = container_with(&[(0, NON_RETURNING, 0, &[STOP])], &[], &[1], 3);
+        assert_eq!(
+            validate(&runtime, ContainerKind::Runtime),
+            Err(EofError::TruncatedData)
+        );
+        let initcode = container(
+            &[(0, NON_RETURNING, 2, &[PUSH0, PUSH0, RETURNCONTRACT, 0x00])],
+            &[&runtime],
+            &[],
+        );
+        assert_eq!(validate(&initcode, ContainerKind::Initcode), Ok(()));
+        let factory = container(
+            &[(
+                0,
+                NON_RETURNING,
+                4,
+                &[PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, POP, STOP],
+            )],
+            &[&runtime],
+            &[],
+        );
+        assert_eq!(
+            validate(&factory, ContainerKind::Runtime),
+            Err(EofError::TruncatedData)
+        );
+
+        let eof = Eof::decode(runtime.into()).unwrap();
+        assert_eq!(eof.with_aux_data(&[2]), None);
+        let deployed = Eof::decode(eof.with_aux_data(&[2, 3]).unwrap()).unwrap();
+        assert_eq!((deployed.data(), deployed.data_size()), (&[1, 2, 3][..], 3));
+        assert_eq!(deployed.validate(ContainerKind::Runtime), Ok(()));
+    }
+
+    fn state(db: &MemoryDb, fork: Fork) -> JournaledState<&MemoryDb> {
+        let block = BlockEnv {
+            gas_limit: 30_000_000,
+            ..Default::default()
+        };
+        JournaledState::new(
+            db,
+            Env {
+                fork,
+                chain_id: 1,
+                block,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn db_with(contracts: &[(Address, &[u8])]) -> MemoryDb {
+        let mut db = MemoryDb::default();
+        db.insert_account(SENDER, 0, U256::exp10(18), Bytes::new());
+        for (address, code) in contracts {
+            if code.starts_with(&MAGIC) {
+                assert_eq!(
+                    validate(code, ContainerKind::Runtime),
+                    Ok(()),
+                    "{address:?}"
+                );
+            }
+            db.insert_account(*address, 1, U256::zero(), Bytes::copy_from_slice(code));
+        }
+        db
+    }
+
+    fn tx(nonce: u64, to: TxKind, data: Vec<u8>) -> Transaction {
+        Transaction::EIP1559Transaction(EIP1559Transaction {
+            chain_id: 1,
+            nonce,
+            max_fee_per_gas: 1.into(),
+            gas_limit: 1_000_000,
+            to,
+            data: data.into(),
+            ..Default::default()
+        })
+    }
+
+    fn slot(state: &mut JournaledState<&MemoryDb>, address: Address, slot: u64) -> U256 {
+        state.storage(address, H256::from_low_u64_be(slot)).unwrap()
+    }
+
+    #[test]
+    fn runs_functions_and_relative_jumps() {
+        let contract = Address::repeat_byte(0xc0);
+        // SSTORE(0, double(3)), double returning its input if zero and twice it otherwise
+        let main = [PUSH1, 3, CALLF, 0x00, 0x01, PUSH0, SSTORE, STOP];
+        let double = [DUP1, RJUMPI, 0x00, 0x01, RETF, PUSH1, 2, MUL, RETF];
+        let code = container(
+            &[(0, NON_RETURNING, 2, &main), (1, 1, 1, &double)],
+            &[],
+            &[],
+        );
+        let db = db_with(&[(contract, &code)]);
+        let mut state = state(&db, Fork::Eof);
+        let result = execute_transaction(
+            &mut state,
+            &tx(0, TxKind::Call(contract), Vec::new()),
+            SENDER,
+        )
+        .unwrap();
+        assert!(result.is_success(), "{}", result.status);
+        assert_eq!(slot(&mut state, contract, 0), U256::from(6));
+
+        // Before the fork the container runs as legacy code, starting at its magic
+        let mut state = self::state(&db, Fork::Cancun);
+        let result = execute_transaction(
+            &mut state,
+            &tx(0, TxKind::Call(contract), Vec::new()),
+            SENDER,
+        )
+        .unwrap();
+        assert_eq!(result.status, ExitStatus::InvalidOpcode(0xef));
+    }
+
+    #[test]
+    fn ext_calls_push_their_status() {
+        let (caller, callee, legacy) = (
+            Address::repeat_byte(0xc0),
+            Address::repeat_byte(0xc1),
+            Address::repeat_byte(0xc2),
+        );
+        // SSTORE(1, EXTCALL(callee)), SSTORE(2, RETURNDATALOAD(0)),
+        // SSTORE(3, EXTDELEGATECALL(legacy))
+        let mut main = vec![PUSH0, PUSH0, PUSH0, PUSH20];
+        main.extend_from_slice(callee.as_bytes());
+        main.extend([
+            EXTCALL,
+            PUSH1,
+            1,
+            SSTORE,
+            PUSH0,
+            RETURNDATALOAD,
+            PUSH1,
+            2,
+            SSTORE,
+            PUSH0,
+            PUSH0,
+            PUSH20,
+        ]);
+        main.extend_from_slice(legacy.as_bytes());
+        main.extend([EXTDELEGATECALL, PUSH1, 3, SSTORE, STOP]);
+        let caller_code = container(&[(0, NON_RETURNING, 4, &main)], &[], &[]);
+        // RETURN of the word 42
+        let returns = [PUSH1, 42, PUSH0, MSTORE, PUSH1, 32, PUSH0, RETURN];
+        let callee_code = container(&[(0, NON_RETURNING, 2, &returns)], &[], &[]);
+        let db = db_with(&[
+            (caller, &caller_code),
+            (callee, &callee_code),
+            (legacy, &[STOP]),
+        ]);
+        let mut state = state(&db, Fork::Eof);
+        let result =
+            execute_transaction(&mut state, &tx(0, TxKind::Call(caller), Vec::new()), SENDER)
+                .unwrap();
+        assert!(result.is_success(), "{}", result.status);
+        assert_eq!(slot(&mut state, caller, 1), U256::zero());
+        assert_eq!(slot(&mut state, caller, 2), U256::from(42));
+        assert_eq!(slot(&mut state, caller, 3), U256::one());
+    }
+
+    /// Init code deploying a contract that stores the first word of its data, which
+    /// the init code appends from the first word of its call data.
+    fn initcode() -> Vec<u8> {
+        let stores_data = [DATALOADN, 0x00, 0x00, PUSH0, SSTORE, STOP];
+        let runtime = container_with(&[(0, NON_RETURNING, 2, &stores_data)], &[], &[], 32);
+        let deploys = [
+            PUSH0,
+            CALLDATALOAD,
+            PUSH0,
+            MSTORE,
+            PUSH1,
+            32,
+            PUSH0,
+            RETURNCONTRACT,
+            0x00,
+        ];
+        container(&[(0, NON_RETURNING, 2, &deploys)], &[&runtime], &[])
+    }
+
+    #[test]
+    fn creation_transactions_pass_call_data_after_the_container() {
+        let db = db_with(&[]);
+        let mut state = state(&db, Fork::Eof);
+        let mut data = initcode();
+        data.extend(H256::from_low_u64_be(42).as_bytes());
+        let result =
+            execute_transaction(&mut state, &tx(0, TxKind::Create, data.clone()), SENDER).unwrap();
+        assert!(result.is_success(), "{}", result.status);
+        let deployed = result.contract_address.unwrap();
+        let result = execute_transaction(
+            &mut state,
+            &tx(1, TxKind::Call(deployed), Vec::new()),
+            SENDER,
+        )
+        .unwrap();
+        assert!(result.is_success(), "{}", result.status);
+        assert_eq!(slot(&mut state, deployed, 0), U256::from(42));
+
+        // Invalid init code fails the transaction, spending all its gas
+        data[3] = 0x00;
+        let result = execute_transaction(&mut state, &tx(2, TxKind::Create, data), SENDER).unwrap();
+        assert_eq!(result.status, ExitStatus::InvalidEofInitcode);
+        assert_eq!(result.gas_used, 1_000_000);
+    }
+
+    #[test]
+    fn eofcreate_deploys_a_subcontainer() {
+        let factory = Address::repeat_byte(0xf0);
+        // MSTORE(0, 43), SSTORE(0, EOFCREATE[0](value 0, salt 0, input from memory))
+        let creates = [
+            PUSH1, 43, PUSH0, MSTORE, PUSH1, 32, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, PUSH0,
+            SSTORE, STOP,
+        ];
+        let code = container(&[(0, NON_RETURNING, 4, &creates)], &[&initcode()], &[]);
+        let db = db_with(&[(factory, &code)]);
+        let mut state = state(&db, Fork::Eof);
+        let result = execute_transaction(
+            &mut state,
+            &tx(0, TxKind::Call(factory), Vec::new()),
+            SENDER,
+        )
+        .unwrap();
+        assert!(result.is_success(), "{}", result.status);
+        let deployed = eofcreate_address(factory, U256::zero());
+        assert_eq!(
+            slot(&mut state, factory, 0),
+            U256::from_big_endian(deployed.as_bytes())
+        );
+        let deployed_code = state.account_code(deployed).unwrap();
+        assert_eq!(
+            Eof::decode(deployed_code).unwrap().data(),
+            H256::from_low_u64_be(43).as_bytes()
+        );
+
+        let result = execute_transaction(
+            &mut state,
+            &tx(1, TxKind::Call(deployed), Vec::new()),
+            SENDER,
+        )
+        .unwrap();
+        assert!(result.is_success(), "{}", result.status);
+        assert_eq!(slot(&mut state, deployed, 0), U256::from(43));
+    }
+
+    #[test]
+    fn legacy_creation_rejects_eof_initcode() {
+        let factory = Address::repeat_byte(0xf0);
+        // CREATE of the call data, storing the address
+        let creates = [
+            CALLDATASIZE,
+            PUSH0,
+            PUSH0,
+            CALLDATACOPY,
+            CALLDATASIZE,
+            PUSH0,
+            PUSH0,
+            CREATE,
+            PUSH0,
+            SSTORE,
+            STOP,
+        ];
+        let db = db_with(&[(factory, &creates)]);
+        let mut state = state(&db, Fork::Eof);
+        let result = execute_transaction(
+            &mut state,
+            &tx(0, TxKind::Call(factory), initcode()),
+            SENDER,
+        )
+        .unwrap();
+        assert!(result.is_success(), "{}", result.status);
+        assert_eq!(slot(&mut state, factory, 0), U256::zero());
+        assert_eq!(state.account_info(factory).unwrap().unwrap().nonce, 2);
+    }
+}
@@ -0,0 +1,95 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::bail;
+
+/// Protocol upgrades that change how transactions execute, in activation order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Fork {
+    Frontier,
+    Homestead,
+    /// EIP-150 gas repricing.
+    Tangerine,
+    /// EIP-155, EIP-160, EIP-161 and EIP-170.
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    /// Constantinople without EIP-1283.
+    Petersburg,
+    Istanbul,
+    Berlin,
+    London,
+    /// The merge.
+    Paris,
+    Shanghai,
+    #[default]
+    Cancun,
+    Prague,
+    /// The EVM Object Format (EIP-7692). No network has scheduled it, devnets that run
+    /// it list it in the fork schedule of their chain config.
+    Eof,
+}
+
+impl Fork {
+    pub const ALL: [Fork; 15] = [
+        Fork::Frontier,
+        Fork::Homestead,
+        Fork::Tangerine,
+        Fork::SpuriousDragon,
+        Fork::Byzantium,
+        Fork::Constantinople,
+        Fork::Petersburg,
+        Fork::Istanbul,
+        Fork::Berlin,
+        Fork::London,
+        Fork::Paris,
+        Fork::Shanghai,
+        Fork::Cancun,
+        Fork::Prague,
+        Fork::Eof,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Fork::Frontier => "Frontier",
+            Fork::Homestead => "Homestead",
+            Fork::Tangerine => "Tangerine",
+            Fork::SpuriousDragon => "SpuriousDragon",
+            Fork::Byzantium => "Byzantium",
+            Fork::Constantinople => "Constantinople",
+            Fork::Petersburg => "Petersburg",
+            Fork::Istanbul => "Istanbul",
+            Fork::Berlin => "Berlin",
+            Fork::London => "London",
+            Fork::Paris => "Paris",
+            Fork::Shanghai => "Shanghai",
+            Fork::Cancun => "Cancun",
+            Fork::Prague => "Prague",
+            // As execution-spec-tests names the unscheduled fork
+            Fork::Eof => "EOFv1",
+        }
+    }
+}
+
+impl fmt::Display for Fork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Accepts the names above as well as the ones used by the Ethereum test fixtures.
+impl FromStr for Fork {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "EIP150" | "TangerineWhistle" => Fork::Tangerine,
+            "EIP158" => Fork::SpuriousDragon,
+            "ConstantinopleFix" => Fork::Petersburg,
+            "Merge" => Fork::Paris,
+            s => match Fork::ALL.into_iter().find(|fork| fork.name() == s) {
+                Some(fork) => fork,
+                None => bail!("unknown fork {s}"),
+            },
+        })
+    }
+}
@@ -0,0 +1,331 @@
+//! Runs a call or contract creation along with every frame it spawns. Frames are kept on
+//! an explicit stack, each with the checkpoint its changes revert to if it fails.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use ethereum_types::{Address, U256};
+use keccak_hash::keccak;
+use reec_core::types::contract_address;
+
+use crate::{
+    bytecode::delegated_address,
+    eof::{self, ContainerKind, Eof},
+    gas::{self, Gas},
+    interpreter::{CallInputs, CallScheme, Contract, CreateInputs, CreateScheme, MAX_CODE_SIZE},
+    precompiles::{self, PrecompileError, PrecompileOutput},
+    state::{Checkpoint, JournaledState},
+    tracer::Tracer,
+    Action, Bytecode, Database, ExitStatus, Fork, FrameResult, Host, Interpreter,
+};
+
+struct Frame {
+    interpreter: Interpreter,
+    checkpoint: Checkpoint,
+    /// Address of the contract being created, for creation frames.
+    created: Option<Address>,
+}
+
+/// Result of a finished frame, in the form its parent resumes with.
+enum Outcome {
+    Call(FrameResult),
+    Create(FrameResult, Option<Address>),
+}
+
+enum Step {
+    Run(Box<Frame>),
+    Done(Outcome),
+}
+
+/// Executes a call or creation to completion. Returns its result and, for creations,
+/// the address of the new contract if it was deployed.
+pub(crate) fn execute<DB: Database, T: Tracer + ?Sized>(
+    state: &mut JournaledState<DB>,
+    action: Action,
+    tracer: &mut T,
+) -> anyhow::Result<(FrameResult, Option<Address>)> {
+    let mut frames: Vec<Box<Frame>> = Vec::new();
+    let mut action = action;
+    loop {
+        let step = match action {
+            Action::Call(inputs) => {
+                tracer.call_start(&inputs);
+                start_call(state, *inputs)?
+            }
+            Action::Create(inputs) => start_create(state, *inputs, tracer)?,
+            Action::Return(result) => {
+                let frame = frames.pop().expect("only running frames return");
+                Step::Done(finish(state, frame, result)?)
+            }
+        };
+        let outcome = match step {
+            Step::Run(frame) => {
+                frames.push(frame);
+                let frame = frames.last_mut().expect("just pushed");
+                action = frame.interpreter.run(state, tracer)?;
+                continue;
+            }
+            Step::Done(outcome) => outcome,
+        };
+        let (Outcome::Call(result) | Outcome::Create(result, _)) = &outcome;
+        tracer.frame_end(result);
+        let Some(parent) = frames.last_mut() else {
+            return Ok(match outcome {
+                Outcome::Call(result) => (result, None),
+                Outcome::Create(result, address) => (result, address),
+            });
+        };
+        match outcome {
+            Outcome::Call(result) => parent.interpreter.resume_call(result),
+            Outcome::Create(result, address) => parent.interpreter.resume_create(result, address),
+        }
+        action = parent.interpreter.run(state, tracer)?;
+    }
+}
+
+/// Moves the value and sets up the callee's frame, or finishes right away if there is
+/// no code to run. The caller has already checked the depth and its balance.
+fn start_call<DB: Database>(
+    state: &mut JournaledState<DB>,
+    inputs: CallInputs,
+) -> anyhow::Result<Step> {
+    let fork = state.env().fork;
+    let checkpoint = state.checkpoint();
+    let done = |gas_limit| {
+        Ok(Step::Done(Outcome::Call(FrameResult {
+            status: ExitStatus::Stop,
+            gas: Gas::new(gas_limit),
+            output: Bytes::new(),
+        })))
+    };
+    match inputs.scheme {
+        CallScheme::Call | CallScheme::ExtCall => {
+            // Calls without value do not create accounts from Spurious Dragon (EIP-161),
+            // except to precompiles
+            if fork >= Fork::SpuriousDragon
+                && inputs.value.is_zero()
+                && precompiles::get(inputs.target, fork).is_none()
+                && state.account_info(inputs.target)?.is_none()
+            {
+                return done(inputs.gas_limit);
+            }
+            state.transfer(inputs.caller, inputs.target, inputs.value)?;
+        }
+        // Touches the callee like a transfer of nothing would
+        CallScheme::StaticCall | CallScheme::ExtStaticCall => state.touch(inputs.target)?,
+        CallScheme::CallCode | CallScheme::DelegateCall | CallScheme::ExtDelegateCall => {}
+    }
+
+    if let Some(precompile) = precompiles::get(inputs.code_address, fork) {
+        let mut gas = Gas::new(inputs.gas_limit);
+        let result = match precompile(&inputs.input, inputs.gas_limit) {
+            Ok(PrecompileOutput { gas_used, output }) => {
+                let charged = gas.record_cost(gas_used);
+                debug_assert!(charged, "precompiles charge at most their gas limit");
+                FrameResult {
+                    status: ExitStatus::Return,
+                    gas,
+                    output,
+                }
+            }
+            Err(err) => {
+                state.revert(checkpoint);
+                gas.spend_all();
+                let status = match err {
+                    PrecompileError::OutOfGas => ExitStatus::OutOfGas,
+                    PrecompileError::Failure(reason) => ExitStatus::PrecompileFailure(reason),
+                };
+                FrameResult {
+                    status,
+                    gas,
+                    output: Bytes::new(),
+                }
+            }
+        };
+        return Ok(Step::Done(Outcome::Call(result)));
+    }
+
+    let mut code = state.analyzed_code(inputs.code_address)?;
+    // EIP-7702: accounts with a delegation designator run the delegate's code, which is
+    // not followed any further and counts as empty for a precompile
+    if fork >= Fork::Prague {
+        if let Some(delegate) = delegated_address(code.original()) {
+            code = state.analyzed_code(delegate)?;
+        }
+    }
+    if code.is_empty() {
+        return done(inputs.gas_limit);
+    }
+    let contract = Contract {
+        address: inputs.target,
+        caller: inputs.caller,
+        value: inputs.value,
+        input: inputs.input,
+        code,
+    };
+    let interpreter = Interpreter::new(
+        contract,
+        inputs.gas_limit,
+        inputs.is_static,
+        inputs.depth,
+        fork,
+    );
+    Ok(Step::Run(Box::new(Frame {
+        interpreter,
+        checkpoint,
+        created: None,
+    })))
+}
+
+/// Bumps the creator's nonce and sets up the init code frame, unless the address is
+/// taken. The caller has already checked the depth and its balance.
+fn start_create<DB: Database, T: Tracer + ?Sized>(
+    state: &mut JournaledState<DB>,
+    inputs: CreateInputs,
+    tracer: &mut T,
+) -> anyhow::Result<Step> {
+    let fork = state.env().fork;
+    let failed = |status, mut gas: Gas| {
+        if matches!(
+            status,
+            ExitStatus::CreateCollision | ExitStatus::InvalidEofInitcode
+        ) {
+            gas.spend_all();
+        }
+        Ok(Step::Done(Outcome::Create(
+            FrameResult {
+                status,
+                gas,
+                output: Bytes::new(),
+            },
+            None,
+        )))
+    };
+    let gas = Gas::new(inputs.gas_limit);
+    let nonce = state.account_info(inputs.caller)?.unwrap_or_default().nonce;
+    let address = match inputs.scheme {
+        CreateScheme::Create => contract_address(inputs.caller, nonce),
+        CreateScheme::Create2 { salt } => create2_address(inputs.caller, salt, &inputs.init_code),
+        CreateScheme::EofCreate { salt } => eofcreate_address(inputs.caller, salt),
+    };
+    tracer.create_start(&inputs, address);
+    if nonce == u64::MAX {
+        return failed(ExitStatus::NonceOverflow, gas);
+    }
+    state.increment_nonce(inputs.caller)?;
+    if fork >= Fork::Berlin {
+        state.warm_address(address);
+    }
+    if state.has_code_or_nonce(address)? {
+        return failed(ExitStatus::CreateCollision, gas);
+    }
+
+    // From the EOF fork, init code that is a container is only run by `EOFCREATE`, which
+    // validated it with its parent, and by creation transactions, which follow it with
+    // the call data (EIP-7698)
+    let (code, input) = if fork >= Fork::Eof && inputs.init_code.starts_with(&eof::MAGIC) {
+        match inputs.scheme {
+            CreateScheme::EofCreate { .. } => {
+                let eof = Eof::decode(inputs.init_code).expect("subcontainers were validated");
+                (Bytecode::from_eof(eof), inputs.input)
+            }
+            _ if inputs.depth == 0 => {
+                let decoded =
+                    Eof::decode_with_calldata(&inputs.init_code).and_then(|(eof, input)| {
+                        eof.validate(ContainerKind::Initcode).map(|()| (eof, input))
+                    });
+                match decoded {
+                    Ok((eof, input)) => (Bytecode::from_eof(eof), input),
+                    Err(_) => return failed(ExitStatus::InvalidEofInitcode, gas),
+                }
+            }
+            _ => return failed(ExitStatus::InvalidEofInitcode, gas),
+        }
+    } else {
+        (Bytecode::new(inputs.init_code), inputs.input)
+    };
+
+    let checkpoint = state.checkpoint();
+    state.create_account(address)?;
+    state.transfer(inputs.caller, address, inputs.value)?;
+    let contract = Contract {
+        address,
+        caller: inputs.caller,
+        value: inputs.value,
+        input,
+        code: Arc::new(code),
+    };
+    let interpreter = Interpreter::new(contract, inputs.gas_limit, false, inputs.depth, fork);
+    Ok(Step::Run(Box::new(Frame {
+        interpreter,
+        checkpoint,
+        created: Some(address),
+    })))
+}
+
+/// Keeps or reverts the frame's changes and, for a creation, deploys the returned code.
+fn finish<DB: Database>(
+    state: &mut JournaledState<DB>,
+    frame: Box<Frame>,
+    mut result: FrameResult,
+) -> anyhow::Result<Outcome> {
+    let Some(address) = frame.created else {
+        if !result.status.is_success() {
+            state.revert(frame.checkpoint);
+        }
+        return Ok(Outcome::Call(result));
+    };
+    if !result.status.is_success() {
+        state.revert(frame.checkpoint);
+        return Ok(Outcome::Create(result, None));
+    }
+
+    let fork = state.env().fork;
+    let code = &result.output;
+    let failure = if fork >= Fork::SpuriousDragon && code.len() > MAX_CODE_SIZE {
+        Some(ExitStatus::CodeSizeExceeded)
+    } else if fork >= Fork::London && code.first() == Some(&0xef) && !frame.interpreter.is_eof() {
+        // EOF init code returns a container that `RETURNCONTRACT` has checked
+        Some(ExitStatus::InvalidCode)
+    } else if !result
+        .gas
+        .record_cost(gas::CODE_DEPOSIT_BYTE * code.len() as u64)
+    {
+        // Frontier deploys nothing rather than failing
+        if fork < Fork::Homestead {
+            return Ok(Outcome::Create(result, Some(address)));
+        }
+        Some(ExitStatus::CodeStoreOutOfGas)
+    } else {
+        None
+    };
+    if let Some(status) = failure {
+        state.revert(frame.checkpoint);
+        result.status = status;
+        result.gas.spend_all();
+        result.output = Bytes::new();
+        return Ok(Outcome::Create(result, None));
+    }
+    state.set_code(address, result.output.clone())?;
+    Ok(Outcome::Create(result, Some(address)))
+}
+
+/// Address of a contract created with `CREATE2` (EIP-1014).
+pub fn create2_address(caller: Address, salt: U256, init_code: &[u8]) -> Address {
+    let mut buf = [0; 85];
+    buf[0] = 0xff;
+    buf[1..21].copy_from_slice(caller.as_bytes());
+    salt.to_big_endian(&mut buf[21..53]);
+    buf[53..].copy_from_slice(keccak(init_code).as_bytes());
+    Address::from_slice(&keccak(buf).as_bytes()[12..])
+}
+
+/// Address of a contract created with `EOFCREATE` (EIP-7620), which unlike `CREATE2`'s
+/// does not depend on the init code.
+pub fn eofcreate_address(caller: Address, salt: U256) -> Address {
+    let mut buf = [0; 65];
+    buf[0] = 0xff;
+    buf[13..33].copy_from_slice(caller.as_bytes());
+    salt.to_big_endian(&mut buf[33..]);
+    Address::from_slice(&keccak(buf).as_bytes()[12..])
+}
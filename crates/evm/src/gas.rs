@@ -0,0 +1,346 @@
+//! Gas costs of the operations whose price is not a per-opcode constant, by fork.
+
+use crate::{host::SStoreResult, Fork};
+
+pub const ZERO: u64 = 0;
+pub const BASE: u64 = 2;
+pub const VERY_LOW: u64 = 3;
+pub const LOW: u64 = 5;
+pub const MID: u64 = 8;
+pub const HIGH: u64 = 10;
+pub const JUMPDEST: u64 = 1;
+pub const BLOCKHASH: u64 = 20;
+pub const EXP: u64 = 10;
+pub const KECCAK256: u64 = 30;
+pub const KECCAK256_WORD: u64 = 6;
+pub const COPY_WORD: u64 = 3;
+pub const MEMORY_WORD: u64 = 3;
+pub const MEMORY_QUAD_DIVISOR: u64 = 512;
+pub const LOG: u64 = 375;
+pub const LOG_TOPIC: u64 = 375;
+pub const LOG_DATA: u64 = 8;
+pub const CREATE: u64 = 32000;
+pub const CODE_DEPOSIT_BYTE: u64 = 200;
+/// EIP-3860 cost per word of init code.
+pub const INITCODE_WORD: u64 = 2;
+pub const CALL_VALUE: u64 = 9000;
+pub const CALL_STIPEND: u64 = 2300;
+pub const NEW_ACCOUNT: u64 = 25000;
+pub const SELFDESTRUCT: u64 = 5000;
+pub const SELFDESTRUCT_REFUND: i64 = 24000;
+pub const SSTORE_SET: u64 = 20000;
+pub const SSTORE_RESET: u64 = 5000;
+/// EIP-2929 costs of touching an account or storage slot for the first time in a
+/// transaction, and of touching it again.
+pub const COLD_ACCOUNT_ACCESS: u64 = 2600;
+pub const COLD_SLOAD: u64 = 2100;
+pub const WARM_STORAGE_READ: u64 = 100;
+/// Costs of the EOF instructions that are not a tier of their own (EIP-4200, EIP-4750
+/// and EIP-7480).
+pub const RJUMPI: u64 = 4;
+pub const CALLF: u64 = 5;
+pub const RETF: u64 = 3;
+pub const DATALOAD: u64 = 4;
+/// EIP-7069: the gas the `EXTCALL` family leaves to the caller at least, and the least
+/// it must be able to give the callee.
+pub const MIN_RETAINED_GAS: u64 = 5000;
+pub const MIN_CALLEE_GAS: u64 = 2300;
+
+/// Intrinsic costs every transaction pays before any code runs.
+pub const TX: u64 = 21000;
+pub const TX_DATA_ZERO: u64 = 4;
+/// Cost of a non-zero calldata byte, lowered from 68 by EIP-2028.
+pub const TX_DATA_NON_ZERO: u64 = 16;
+pub const TX_DATA_NON_ZERO_FRONTIER: u64 = 68;
+pub const ACCESS_LIST_ADDRESS: u64 = 2400;
+pub const ACCESS_LIST_STORAGE_KEY: u64 = 1900;
+/// EIP-7702 cost of each authorization, priced as if its authority were a new account,
+/// and the part of it that is kept when the authority already exists.
+pub const PER_EMPTY_ACCOUNT: u64 = 25000;
+pub const PER_AUTH_BASE: u64 = 12500;
+/// EIP-7623 minimum cost per calldata token, a zero byte being one token and any other
+/// byte four.
+pub const TOTAL_COST_FLOOR_PER_TOKEN: u64 = 10;
+/// EIP-4844 blob gas per blob.
+pub const PER_BLOB: u64 = 1 << 17;
+
+/// Gas available to a frame and the refund it has accumulated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Gas {
+    limit: u64,
+    remaining: u64,
+    refunded: i64,
+}
+
+impl Gas {
+    pub fn new(limit: u64) -> Self {
+        Gas {
+            limit,
+            remaining: limit,
+            refunded: 0,
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    pub fn used(&self) -> u64 {
+        self.limit - self.remaining
+    }
+
+    /// Refund accumulated so far. It can be negative within a frame, as EIP-2200 takes
+    /// back refunds for slots that are set again.
+    pub fn refunded(&self) -> i64 {
+        self.refunded
+    }
+
+    /// Spends `cost`, failing without spending anything if not enough gas is left.
+    #[must_use]
+    pub fn record_cost(&mut self, cost: u64) -> bool {
+        match self.remaining.checked_sub(cost) {
+            Some(remaining) => {
+                self.remaining = remaining;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Gives back gas that was not used, such as the unspent gas of a child frame.
+    pub fn erase_cost(&mut self, returned: u64) {
+        self.remaining += returned;
+    }
+
+    pub fn record_refund(&mut self, refund: i64) {
+        self.refunded += refund;
+    }
+
+    pub fn spend_all(&mut self) {
+        self.remaining = 0;
+    }
+}
+
+pub fn words(len: u64) -> u64 {
+    len.div_ceil(32)
+}
+
+/// Total cost of a memory of `words` words.
+pub fn memory_cost(words: u64) -> u64 {
+    MEMORY_WORD
+        .saturating_mul(words)
+        .saturating_add(words.saturating_mul(words) / MEMORY_QUAD_DIVISOR)
+}
+
+pub fn exp_byte(fork: Fork) -> u64 {
+    if fork >= Fork::SpuriousDragon {
+        50
+    } else {
+        10
+    }
+}
+
+/// Cost of reaching an account from Berlin (EIP-2929), `legacy` before.
+pub fn account_access(fork: Fork, is_cold: bool, legacy: u64) -> u64 {
+    match (fork >= Fork::Berlin, is_cold) {
+        (false, _) => legacy,
+        (true, true) => COLD_ACCOUNT_ACCESS,
+        (true, false) => WARM_STORAGE_READ,
+    }
+}
+
+/// Pre-Berlin cost of `BALANCE`.
+pub fn balance(fork: Fork) -> u64 {
+    match fork {
+        f if f >= Fork::Istanbul => 700,
+        f if f >= Fork::Tangerine => 400,
+        _ => 20,
+    }
+}
+
+/// Pre-Berlin cost of `EXTCODESIZE` and `EXTCODECOPY`.
+pub fn extcode(fork: Fork) -> u64 {
+    if fork >= Fork::Tangerine {
+        700
+    } else {
+        20
+    }
+}
+
+/// Pre-Berlin cost of `EXTCODEHASH`.
+pub fn extcodehash(fork: Fork) -> u64 {
+    if fork >= Fork::Istanbul {
+        700
+    } else {
+        400
+    }
+}
+
+/// Pre-Berlin cost of the `CALL` family.
+pub fn call(fork: Fork) -> u64 {
+    if fork >= Fork::Tangerine {
+        700
+    } else {
+        40
+    }
+}
+
+pub fn sload(fork: Fork, is_cold: bool) -> u64 {
+    match fork {
+        f if f >= Fork::Berlin => {
+            if is_cold {
+                COLD_SLOAD
+            } else {
+                WARM_STORAGE_READ
+            }
+        }
+        f if f >= Fork::Istanbul => 800,
+        f if f >= Fork::Tangerine => 200,
+        _ => 50,
+    }
+}
+
+/// Refund for clearing a storage slot, lowered by EIP-3529.
+pub fn sstore_clears_refund(fork: Fork) -> i64 {
+    if fork >= Fork::London {
+        4800
+    } else {
+        15000
+    }
+}
+
+/// Largest share of the gas used that refunds can give back, lowered by EIP-3529.
+pub fn max_refund_quotient(fork: Fork) -> u64 {
+    if fork >= Fork::London {
+        5
+    } else {
+        2
+    }
+}
+
+/// Most blob gas a block can use.
+pub fn max_blob_gas_per_block(fork: Fork) -> u64 {
+    if fork >= Fork::Prague {
+        9 * PER_BLOB
+    } else {
+        6 * PER_BLOB
+    }
+}
+
+/// Blob gas per block the blob base fee steers towards.
+pub fn target_blob_gas_per_block(fork: Fork) -> u64 {
+    if fork >= Fork::Prague {
+        6 * PER_BLOB
+    } else {
+        3 * PER_BLOB
+    }
+}
+
+/// Cost and refund change of an `SSTORE`: net metering from EIP-1283 (Constantinople
+/// only) and EIP-2200, with the EIP-2929 access costs from Berlin.
+pub fn sstore(fork: Fork, slot: &SStoreResult, is_cold: bool) -> (u64, i64) {
+    let SStoreResult {
+        original,
+        present,
+        new,
+    } = *slot;
+    if fork < Fork::Constantinople || fork == Fork::Petersburg {
+        let cost = if present.is_zero() && !new.is_zero() {
+            SSTORE_SET
+        } else {
+            SSTORE_RESET
+        };
+        let refund = if !present.is_zero() && new.is_zero() {
+            sstore_clears_refund(fork)
+        } else {
+            0
+        };
+        return (cost, refund);
+    }
+
+    let (noop, reset) = match fork {
+        Fork::Constantinople => (200, SSTORE_RESET),
+        Fork::Istanbul => (800, SSTORE_RESET),
+        _ => (WARM_STORAGE_READ, SSTORE_RESET - COLD_SLOAD),
+    };
+    let clears = sstore_clears_refund(fork);
+    let mut refund = 0;
+    let cost = if new == present {
+        noop
+    } else if original == present {
+        if original.is_zero() {
+            SSTORE_SET
+        } else {
+            if new.is_zero() {
+                refund += clears;
+            }
+            reset
+        }
+    } else {
+        if !original.is_zero() {
+            if present.is_zero() {
+                refund -= clears;
+            } else if new.is_zero() {
+                refund += clears;
+            }
+        }
+        if original == new {
+            refund += if original.is_zero() {
+                SSTORE_SET - noop
+            } else {
+                reset - noop
+            } as i64;
+        }
+        noop
+    };
+    let access = if fork >= Fork::Berlin && is_cold {
+        COLD_SLOAD
+    } else {
+        0
+    };
+    (cost + access, refund)
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum_types::U256;
+
+    use super::*;
+
+    #[test]
+    fn memory_expansion() {
+        assert_eq!(memory_cost(0), 0);
+        assert_eq!(memory_cost(1), 3);
+        assert_eq!(memory_cost(32), 98);
+        assert_eq!(memory_cost(1024), 5120);
+        assert_eq!(memory_cost(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn sstore_net_metering() {
+        // Cases from the EIP-2200 and EIP-3529 tables, as (original, present, new)
+        let slot = |original: u64, present: u64, new: u64| SStoreResult {
+            original: U256::from(original),
+            present: U256::from(present),
+            new: U256::from(new),
+        };
+        assert_eq!(sstore(Fork::Istanbul, &slot(0, 0, 0), false), (800, 0));
+        assert_eq!(sstore(Fork::Istanbul, &slot(0, 0, 1), false), (20000, 0));
+        assert_eq!(sstore(Fork::Istanbul, &slot(1, 1, 0), false), (5000, 15000));
+        assert_eq!(
+            sstore(Fork::Istanbul, &slot(1, 0, 1), false),
+            (800, -15000 + 4200)
+        );
+        assert_eq!(sstore(Fork::Istanbul, &slot(0, 1, 0), false), (800, 19200));
+        assert_eq!(sstore(Fork::Berlin, &slot(1, 1, 0), true), (5000, 15000));
+        assert_eq!(sstore(Fork::London, &slot(1, 1, 0), false), (2900, 4800));
+        assert_eq!(sstore(Fork::London, &slot(1, 2, 0), false), (100, 4800));
+        assert_eq!(sstore(Fork::London, &slot(0, 1, 0), true), (2200, 19900));
+        assert_eq!(sstore(Fork::Petersburg, &slot(1, 1, 1), false), (5000, 0));
+        assert_eq!(sstore(Fork::Frontier, &slot(0, 1, 0), false), (5000, 15000));
+    }
+}
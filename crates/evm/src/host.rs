@@ -0,0 +1,104 @@
+//! The interpreter's view of the world. [`Host`] is what running code can observe and
+//! change, [`Database`] is the read-only state backend a host is built upon.
+
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use reec_core::types::{AccountInfo, BlockNumber, Log, StateDiff};
+
+use crate::Env;
+
+/// State read from a backend, such as the chain database or a test fixture.
+pub trait Database {
+    /// The account at `address`, `None` if it does not exist.
+    fn account(&self, address: Address) -> anyhow::Result<Option<AccountInfo>>;
+    /// The value of a storage slot, zero if it was never set.
+    fn storage(&self, address: Address, slot: H256) -> anyhow::Result<U256>;
+    fn code(&self, code_hash: H256) -> anyhow::Result<Bytes>;
+    /// Hash of a canonical block, `None` if it is not known.
+    fn block_hash(&self, number: BlockNumber) -> anyhow::Result<Option<H256>>;
+    /// Root of the state trie after applying `diff` to this state. Only executing blocks
+    /// before Byzantium needs it, for the state root each receipt holds.
+    fn state_root(&self, _diff: &StateDiff) -> anyhow::Result<H256> {
+        anyhow::bail!("this database cannot compute state roots")
+    }
+}
+
+/// A value read from state along with whether this was its first access in the
+/// transaction, which makes it cost more from Berlin (EIP-2929).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StateLoad<T> {
+    pub data: T,
+    pub is_cold: bool,
+}
+
+impl<T> StateLoad<T> {
+    pub fn new(data: T, is_cold: bool) -> Self {
+        StateLoad { data, is_cold }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AccountLoad {
+    /// Whether the account is in the state at all.
+    pub exists: bool,
+    /// Whether it is empty as defined by EIP-161, which includes not existing.
+    pub is_empty: bool,
+    /// From Prague, the address whose code the account runs instead of its own
+    /// (EIP-7702), along with whether loading that one was its first access.
+    pub delegation: Option<StateLoad<Address>>,
+}
+
+/// Values of a storage slot around an `SSTORE`, which is what its gas cost and refund
+/// depend on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SStoreResult {
+    /// Value at the start of the transaction.
+    pub original: U256,
+    /// Value before this store.
+    pub present: U256,
+    pub new: U256,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SelfDestructResult {
+    /// Balance of the destroyed account, which went to the beneficiary.
+    pub balance: U256,
+    /// Whether the beneficiary existed, or was non-empty from Spurious Dragon.
+    pub target_exists: bool,
+    /// Whether the account was already destroyed earlier in the transaction.
+    pub previously_destroyed: bool,
+}
+
+/// State access for the running code. Loading an account or slot marks it warm for the
+/// rest of the transaction; hosts for forks before Berlin may report everything warm.
+///
+/// Errors are fatal: they come from the backend rather than from the executed code, and
+/// abort the whole execution.
+pub trait Host {
+    fn env(&self) -> &Env;
+    fn load_account(&mut self, address: Address) -> anyhow::Result<StateLoad<AccountLoad>>;
+    fn balance(&mut self, address: Address) -> anyhow::Result<StateLoad<U256>>;
+    fn code(&mut self, address: Address) -> anyhow::Result<StateLoad<Bytes>>;
+    /// Hash of the account's code, zero for accounts that are empty or do not exist.
+    fn code_hash(&mut self, address: Address) -> anyhow::Result<StateLoad<H256>>;
+    fn sload(&mut self, address: Address, slot: H256) -> anyhow::Result<StateLoad<U256>>;
+    fn sstore(
+        &mut self,
+        address: Address,
+        slot: H256,
+        value: U256,
+    ) -> anyhow::Result<StateLoad<SStoreResult>>;
+    /// Transient storage (EIP-1153), cleared at the end of every transaction.
+    fn tload(&mut self, address: Address, slot: H256) -> U256;
+    fn tstore(&mut self, address: Address, slot: H256, value: U256);
+    /// Hash of one of the 256 most recent blocks, zero for any other block.
+    fn block_hash(&mut self, number: BlockNumber) -> anyhow::Result<H256>;
+    fn log(&mut self, log: Log);
+    /// Moves the balance of `address` to `target` and schedules its destruction, which
+    /// from Cancun (EIP-6780) only happens if it was created in the same transaction.
+    fn selfdestruct(
+        &mut self,
+        address: Address,
+        target: Address,
+    ) -> anyhow::Result<StateLoad<SelfDestructResult>>;
+}
@@ -0,0 +1,806 @@
+//! Execution of a single call frame. Calls and contract creations made by the running
+//! code are handed back to the caller of [`Interpreter::run`] as an [`Action`], so
+//! nesting is bounded by the call depth limit rather than by the native stack.
+
+use std::{fmt, ops::Range, sync::Arc};
+
+use bytes::Bytes;
+use ethereum_types::{Address, U256};
+
+use crate::{
+    bytecode::Bytecode,
+    gas::{self, Gas},
+    memory::Memory,
+    opcode,
+    stack::Stack,
+    tracer::{TracedHost, Tracer},
+    Fork, Host,
+};
+
+mod instructions;
+
+/// Frames deeper than this cannot make calls or create contracts.
+pub const CALL_DEPTH_LIMIT: usize = 1024;
+/// EIP-170 limit on deployed code.
+pub const MAX_CODE_SIZE: usize = 0x6000;
+/// EIP-3860 limit on init code.
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+/// How a frame ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ExitStatus {
+    Stop,
+    Return,
+    SelfDestruct,
+    Revert,
+    OutOfGas,
+    InvalidOpcode(u8),
+    StackUnderflow,
+    StackOverflow,
+    InvalidJump,
+    /// State modification within a static call.
+    StaticStateChange,
+    ReturnDataOutOfBounds,
+    InitCodeSizeExceeded,
+    CodeSizeExceeded,
+    /// EIP-3541: deployed code may not start with 0xef.
+    InvalidCode,
+    /// Not enough gas left to pay for storing the deployed code.
+    CodeStoreOutOfGas,
+    CreateCollision,
+    NonceOverflow,
+    CallDepthExceeded,
+    InsufficientBalance,
+    /// A precompile rejected its input, for the given reason.
+    PrecompileFailure(&'static str),
+    /// Init code that is not a valid EOF container, or one run by a legacy creation.
+    InvalidEofInitcode,
+    /// An `EXTCALL` to a word with bits set above those of an address.
+    AddressOutOfRange,
+    /// `RETURNCONTRACT` aux data that leaves the data section short of its declared size
+    /// or longer than it can be.
+    InvalidAuxData,
+}
+
+impl ExitStatus {
+    pub fn is_success(self) -> bool {
+        matches!(
+            self,
+            ExitStatus::Stop | ExitStatus::Return | ExitStatus::SelfDestruct
+        )
+    }
+
+    pub fn is_revert(self) -> bool {
+        self == ExitStatus::Revert
+    }
+
+    /// Whether the frame failed in a way that consumes all of its gas.
+    pub fn is_error(self) -> bool {
+        !self.is_success() && !self.is_revert()
+    }
+}
+
+/// The messages geth uses for the same errors, which traces are compared against.
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitStatus::Stop => f.write_str("stopped"),
+            ExitStatus::Return => f.write_str("returned"),
+            ExitStatus::SelfDestruct => f.write_str("self destructed"),
+            ExitStatus::Revert => f.write_str("execution reverted"),
+            ExitStatus::OutOfGas => f.write_str("out of gas"),
+            ExitStatus::InvalidOpcode(op) => write!(f, "invalid opcode: {}", opcode::name(*op)),
+            ExitStatus::StackUnderflow => f.write_str("stack underflow"),
+            ExitStatus::StackOverflow => f.write_str("stack limit reached"),
+            ExitStatus::InvalidJump => f.write_str("invalid jump destination"),
+            ExitStatus::StaticStateChange => f.write_str("write protection"),
+            ExitStatus::ReturnDataOutOfBounds => f.write_str("return data out of bounds"),
+            ExitStatus::InitCodeSizeExceeded => f.write_str("max initcode size exceeded"),
+            ExitStatus::CodeSizeExceeded => f.write_str("max code size exceeded"),
+            ExitStatus::InvalidCode => f.write_str("invalid code: must not begin with 0xef"),
+            ExitStatus::CodeStoreOutOfGas => {
+                f.write_str("contract creation code storage out of gas")
+            }
+            ExitStatus::CreateCollision => f.write_str("contract address collision"),
+            ExitStatus::NonceOverflow => f.write_str("nonce uint64 overflow"),
+            ExitStatus::CallDepthExceeded => f.write_str("max call depth exceeded"),
+            ExitStatus::InsufficientBalance => f.write_str("insufficient balance for transfer"),
+            ExitStatus::PrecompileFailure(reason) => f.write_str(reason),
+            ExitStatus::InvalidEofInitcode => f.write_str("invalid eof initcode"),
+            ExitStatus::AddressOutOfRange => f.write_str("address out of range"),
+            ExitStatus::InvalidAuxData => f.write_str("invalid aux data size"),
+        }
+    }
+}
+
+/// Outcome of a frame: its status, the gas it left and its return or revert data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameResult {
+    pub status: ExitStatus,
+    pub gas: Gas,
+    pub output: Bytes,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallScheme {
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+    /// The calls EOF code makes (EIP-7069), which work like the legacy ones of the same
+    /// name.
+    ExtCall,
+    ExtDelegateCall,
+    ExtStaticCall,
+}
+
+/// A message call made by running code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallInputs {
+    pub scheme: CallScheme,
+    /// Account whose storage and balance the callee runs with.
+    pub target: Address,
+    /// Account whose code runs, which differs from `target` for `CALLCODE` and
+    /// `DELEGATECALL`.
+    pub code_address: Address,
+    pub caller: Address,
+    /// Value seen by the callee.
+    pub value: U256,
+    /// Whether `value` moves from `caller` to `target`, as it does for `CALL` and
+    /// `CALLCODE`.
+    pub transfer: bool,
+    pub input: Bytes,
+    pub gas_limit: u64,
+    pub is_static: bool,
+    /// Depth of the callee.
+    pub depth: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CreateScheme {
+    Create,
+    Create2 {
+        salt: U256,
+    },
+    /// EIP-7620 creation from one of the running container's subcontainers.
+    EofCreate {
+        salt: U256,
+    },
+}
+
+/// A contract creation made by running code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CreateInputs {
+    pub scheme: CreateScheme,
+    pub caller: Address,
+    pub value: U256,
+    pub init_code: Bytes,
+    /// Call data of the init code, which only EOF init code can read.
+    pub input: Bytes,
+    pub gas_limit: u64,
+    /// Depth of the init code frame.
+    pub depth: usize,
+}
+
+/// What the caller of [`Interpreter::run`] has to do next.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Run the call and pass its result to [`Interpreter::resume_call`].
+    Call(Box<CallInputs>),
+    /// Run the creation and pass its result to [`Interpreter::resume_create`].
+    Create(Box<CreateInputs>),
+    /// The frame is done.
+    Return(FrameResult),
+}
+
+/// The account a frame runs for and the code it runs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Contract {
+    pub address: Address,
+    pub caller: Address,
+    pub value: U256,
+    pub input: Bytes,
+    pub code: Arc<Bytecode>,
+}
+
+/// Why execution of the current frame stopped, internal to the instructions.
+enum Halt {
+    Exit(ExitStatus),
+    Call(Box<CallInputs>),
+    Create(Box<CreateInputs>),
+    Fatal(anyhow::Error),
+}
+
+impl From<ExitStatus> for Halt {
+    fn from(status: ExitStatus) -> Self {
+        Halt::Exit(status)
+    }
+}
+
+impl From<anyhow::Error> for Halt {
+    fn from(err: anyhow::Error) -> Self {
+        Halt::Fatal(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct Interpreter {
+    pub contract: Contract,
+    pub pc: usize,
+    pub stack: Stack,
+    pub memory: Memory,
+    pub gas: Gas,
+    /// Output of the most recent call or creation made by this frame.
+    pub return_data: Bytes,
+    pub is_static: bool,
+    pub depth: usize,
+    fork: Fork,
+    /// Whether the code runs as EOF, which it does from the EOF fork if it is a container.
+    is_eof: bool,
+    /// Where each `CALLF` still running returns to.
+    return_stack: Vec<usize>,
+    output: Bytes,
+    /// Where the output of the pending call goes.
+    return_range: Range<usize>,
+}
+
+impl Interpreter {
+    pub fn new(
+        contract: Contract,
+        gas_limit: u64,
+        is_static: bool,
+        depth: usize,
+        fork: Fork,
+    ) -> Self {
+        let eof = contract.code.eof().filter(|_| fork >= Fork::Eof);
+        let pc = eof.map_or(0, |eof| eof.code_start(0));
+        let is_eof = eof.is_some();
+        Interpreter {
+            contract,
+            pc,
+            stack: Stack::default(),
+            memory: Memory::default(),
+            gas: Gas::new(gas_limit),
+            return_data: Bytes::new(),
+            is_static,
+            depth,
+            fork,
+            is_eof,
+            return_stack: Vec::new(),
+            output: Bytes::new(),
+            return_range: 0..0,
+        }
+    }
+
+    pub fn fork(&self) -> Fork {
+        self.fork
+    }
+
+    pub fn is_eof(&self) -> bool {
+        self.is_eof
+    }
+
+    /// The opcode at the program counter, which runs next.
+    pub fn current_opcode(&self) -> u8 {
+        self.contract.code.padded()[self.pc]
+    }
+
+    /// Runs until the frame ends or makes a call or creation, showing each instruction
+    /// to `tracer`. Errors come from the host and abort the whole execution.
+    pub fn run<H: Host + ?Sized, T: Tracer + ?Sized>(
+        &mut self,
+        host: &mut H,
+        tracer: &mut T,
+    ) -> anyhow::Result<Action> {
+        loop {
+            tracer.step(self);
+            let result = self.step(&mut TracedHost {
+                host: &mut *host,
+                tracer: &mut *tracer,
+            });
+            match result {
+                Ok(()) => tracer.step_end(self, None),
+                Err(Halt::Exit(status)) => {
+                    tracer.step_end(self, Some(status));
+                    return Ok(Action::Return(self.finish(status)));
+                }
+                Err(Halt::Call(inputs)) => {
+                    tracer.step_end(self, None);
+                    return Ok(Action::Call(inputs));
+                }
+                Err(Halt::Create(inputs)) => {
+                    tracer.step_end(self, None);
+                    return Ok(Action::Create(inputs));
+                }
+                Err(Halt::Fatal(err)) => return Err(err),
+            }
+        }
+    }
+
+    /// Continues after a call: pushes whether it succeeded, copies its output to the
+    /// memory the caller reserved for it and takes back the gas it left. EOF code is
+    /// pushed 0 for success, 1 for a revert and 2 for a failure instead, and reads the
+    /// output from the return data.
+    pub fn resume_call(&mut self, result: FrameResult) {
+        let range = std::mem::replace(&mut self.return_range, 0..0);
+        let copied = range.len().min(result.output.len());
+        self.memory.set(range.start, &result.output[..copied]);
+        let pushed = match result.status {
+            status if !self.is_eof => status.is_success() as u8,
+            status if status.is_success() => 0,
+            status if status.is_revert() => 1,
+            _ => 2,
+        };
+        self.resume(&result, U256::from(pushed));
+        self.return_data = result.output;
+    }
+
+    /// Continues after a contract creation: pushes the new address, zero if it failed,
+    /// and takes back the gas it left. Only a reverting init code leaves return data.
+    pub fn resume_create(&mut self, result: FrameResult, address: Option<Address>) {
+        let address = match address {
+            Some(address) if result.status.is_success() => address_to_u256(address),
+            _ => U256::zero(),
+        };
+        self.resume(&result, address);
+        self.return_data = if result.status.is_revert() {
+            result.output
+        } else {
+            Bytes::new()
+        };
+    }
+
+    fn resume(&mut self, result: &FrameResult, pushed: U256) {
+        self.gas.erase_cost(result.gas.remaining());
+        if result.status.is_success() {
+            self.gas.record_refund(result.gas.refunded());
+        }
+        // Room for the result was left when the call was made
+        let _ = self.stack.push(pushed);
+    }
+
+    fn finish(&mut self, status: ExitStatus) -> FrameResult {
+        if status.is_error() {
+            self.gas.spend_all();
+            self.output = Bytes::new();
+        }
+        FrameResult {
+            status,
+            gas: self.gas,
+            output: std::mem::take(&mut self.output),
+        }
+    }
+
+    fn charge(&mut self, cost: u64) -> Result<(), ExitStatus> {
+        if self.gas.record_cost(cost) {
+            Ok(())
+        } else {
+            Err(ExitStatus::OutOfGas)
+        }
+    }
+
+    /// Fails with an invalid opcode error in legacy code, for the opcodes EOF introduced.
+    fn require_eof(&self, op: u8) -> Result<(), ExitStatus> {
+        if self.is_eof {
+            Ok(())
+        } else {
+            Err(ExitStatus::InvalidOpcode(op))
+        }
+    }
+
+    /// Fails with an invalid opcode error before `fork`, for opcodes it introduced.
+    fn require(&self, fork: Fork, op: u8) -> Result<(), ExitStatus> {
+        if self.fork >= fork {
+            Ok(())
+        } else {
+            Err(ExitStatus::InvalidOpcode(op))
+        }
+    }
+
+    fn require_non_static(&self) -> Result<(), ExitStatus> {
+        if self.is_static {
+            Err(ExitStatus::StaticStateChange)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Validates a memory region given as stack operands and pays for growing the
+    /// memory to cover it. Empty regions are always valid, wherever they start.
+    fn memory_range(&mut self, offset: U256, len: U256) -> Result<Range<usize>, ExitStatus> {
+        if len.is_zero() {
+            return Ok(0..0);
+        }
+        // Anything past 4 GiB costs far more gas than a block holds
+        let limit = U256::from(u32::MAX);
+        if offset > limit || len > limit {
+            return Err(ExitStatus::OutOfGas);
+        }
+        let (offset, len) = (offset.as_usize(), len.as_usize());
+        self.expand_memory(offset + len)?;
+        Ok(offset..offset + len)
+    }
+
+    fn expand_memory(&mut self, end: usize) -> Result<(), ExitStatus> {
+        let words = gas::words(end as u64);
+        let current = self.memory.words() as u64;
+        if words > current {
+            self.charge(gas::memory_cost(words) - gas::memory_cost(current))?;
+            self.memory.resize(words as usize);
+        }
+        Ok(())
+    }
+}
+
+pub fn address_to_u256(address: Address) -> U256 {
+    U256::from_big_endian(address.as_bytes())
+}
+
+pub fn u256_to_address(value: U256) -> Address {
+    let mut bytes = [0; 32];
+    value.to_big_endian(&mut bytes);
+    Address::from_slice(&bytes[12..])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use ethereum_types::H256;
+    use reec_core::types::{BlockNumber, Log};
+
+    use super::*;
+    use crate::{
+        host::{AccountLoad, SStoreResult, SelfDestructResult, StateLoad},
+        opcode::*,
+        tracer::NoopTracer,
+        Env,
+    };
+
+    const ADDRESS: Address = Address::repeat_byte(0xaa);
+
+    /// A host over plain maps, warming everything it touches.
+    #[derive(Default)]
+    struct TestHost {
+        env: Env,
+        balances: HashMap<Address, U256>,
+        /// Original and present value of each slot.
+        storage: HashMap<H256, (U256, U256)>,
+        transient: HashMap<H256, U256>,
+        warm: HashSet<Address>,
+        logs: Vec<Log>,
+    }
+
+    impl TestHost {
+        fn warm(&mut self, address: Address) -> bool {
+            !self.warm.insert(address)
+        }
+    }
+
+    impl Host for TestHost {
+        fn env(&self) -> &Env {
+            &self.env
+        }
+
+        fn load_account(&mut self, address: Address) -> anyhow::Result<StateLoad<AccountLoad>> {
+            let exists = self.balances.contains_key(&address);
+            let load = AccountLoad {
+                exists,
+                is_empty: !exists,
+                delegation: None,
+            };
+            Ok(StateLoad::new(load, !self.warm(address)))
+        }
+
+        fn balance(&mut self, address: Address) -> anyhow::Result<StateLoad<U256>> {
+            let balance = self.balances.get(&address).copied().unwrap_or_default();
+            Ok(StateLoad::new(balance, !self.warm(address)))
+        }
+
+        fn code(&mut self, address: Address) -> anyhow::Result<StateLoad<Bytes>> {
+            Ok(StateLoad::new(Bytes::new(), !self.warm(address)))
+        }
+
+        fn code_hash(&mut self, address: Address) -> anyhow::Result<StateLoad<H256>> {
+            Ok(StateLoad::new(H256::zero(), !self.warm(address)))
+        }
+
+        fn sload(&mut self, _: Address, slot: H256) -> anyhow::Result<StateLoad<U256>> {
+            let is_cold = !self.storage.contains_key(&slot);
+            Ok(StateLoad::new(
+                self.storage.entry(slot).or_default().1,
+                is_cold,
+            ))
+        }
+
+        fn sstore(
+            &mut self,
+            _: Address,
+            slot: H256,
+            new: U256,
+        ) -> anyhow::Result<StateLoad<SStoreResult>> {
+            let is_cold = !self.storage.contains_key(&slot);
+            let (original, present) = self.storage.entry(slot).or_default();
+            let result = SStoreResult {
+                original: *original,
+                present: *present,
+                new,
+            };
+            *present = new;
+            Ok(StateLoad::new(result, is_cold))
+        }
+
+        fn tload(&mut self, _: Address, slot: H256) -> U256 {
+            self.transient.get(&slot).copied().unwrap_or_default()
+        }
+
+        fn tstore(&mut self, _: Address, slot: H256, value: U256) {
+            self.transient.insert(slot, value);
+        }
+
+        fn block_hash(&mut self, number: BlockNumber) -> anyhow::Result<H256> {
+            Ok(H256::from_low_u64_be(number))
+        }
+
+        fn log(&mut self, log: Log) {
+            self.logs.push(log);
+        }
+
+        fn selfdestruct(
+            &mut self,
+            _: Address,
+            target: Address,
+        ) -> anyhow::Result<StateLoad<SelfDestructResult>> {
+            let result = SelfDestructResult {
+                target_exists: true,
+                ..Default::default()
+            };
+            Ok(StateLoad::new(result, !self.warm(target)))
+        }
+    }
+
+    fn interpreter(code: &[u8], fork: Fork) -> Interpreter {
+        let contract = Contract {
+            address: ADDRESS,
+            code: Arc::new(Bytecode::new(Bytes::copy_from_slice(code))),
+            ..Default::default()
+        };
+        Interpreter::new(contract, 100_000, false, 0, fork)
+    }
+
+    fn run(code: &[u8], fork: Fork, host: &mut TestHost) -> FrameResult {
+        match interpreter(code, fork).run(host, &mut NoopTracer).unwrap() {
+            Action::Return(result) => result,
+            action => panic!("unexpected {action:?}"),
+        }
+    }
+
+    /// Code that runs `ops` and returns the word on top of the stack.
+    fn returning(ops: &[u8]) -> Vec<u8> {
+        [ops, &[PUSH0, MSTORE, PUSH1, 32, PUSH0, RETURN]].concat()
+    }
+
+    fn push32(value: U256) -> Vec<u8> {
+        let mut code = vec![PUSH32; 33];
+        value.to_big_endian(&mut code[1..]);
+        code
+    }
+
+    fn result_word(ops: &[u8]) -> U256 {
+        let result = run(&returning(ops), Fork::Cancun, &mut TestHost::default());
+        assert_eq!(result.status, ExitStatus::Return);
+        U256::from_big_endian(&result.output)
+    }
+
+    #[test]
+    fn arithmetic() {
+        let minus = |value: u64| (!U256::from(value)).overflowing_add(U256::one()).0;
+        assert_eq!(
+            result_word(&[PUSH1, 3, PUSH1, 4, ADD, PUSH1, 5, MUL]),
+            U256::from(35)
+        );
+        assert_eq!(result_word(&[PUSH1, 0, PUSH1, 7, DIV]), U256::zero());
+        assert_eq!(result_word(&[PUSH1, 3, PUSH1, 10, SUB]), U256::from(7));
+        // -8 / 3 and -8 % 3
+        let minus_eight = push32(minus(8));
+        assert_eq!(
+            result_word(&[&[PUSH1, 3][..], &minus_eight, &[SDIV]].concat()),
+            minus(2)
+        );
+        assert_eq!(
+            result_word(&[&[PUSH1, 3][..], &minus_eight, &[SMOD]].concat()),
+            minus(2)
+        );
+        assert_eq!(
+            result_word(&[&[PUSH1, 1][..], &minus_eight, &[SLT]].concat()),
+            U256::one()
+        );
+        // Sign extension of 0xff from one byte, and an arithmetic shift of -8
+        assert_eq!(result_word(&[PUSH1, 0xff, PUSH1, 0, SIGNEXTEND]), U256::MAX);
+        assert_eq!(
+            result_word(&[&minus_eight[..], &[PUSH1, 2, SAR]].concat()),
+            minus(2)
+        );
+        assert_eq!(
+            result_word(&[PUSH1, 1, PUSH1, 255, SHL, PUSH1, 255, SHR]),
+            U256::one()
+        );
+        // (MAX + MAX) % 10, which overflows 256 bits
+        let max = push32(U256::MAX);
+        assert_eq!(
+            result_word(&[&[PUSH1, 10][..], &max, &max, &[ADDMOD]].concat()),
+            U256::from(0)
+        );
+        assert_eq!(
+            result_word(&[PUSH1, 0xff, PUSH1, 31, BYTE, PUSH1, 10, PUSH1, 2, EXP, ADD]),
+            U256::from(1279)
+        );
+    }
+
+    #[test]
+    fn charges_gas_and_memory() {
+        let mut host = TestHost::default();
+        // PUSH1 PUSH1 MSTORE at 64 expands memory to three words: 3 + 3 + 3 + 9
+        let result = run(&[PUSH1, 1, PUSH1, 64, MSTORE], Fork::Cancun, &mut host);
+        assert_eq!(result.status, ExitStatus::Stop);
+        assert_eq!(result.gas.used(), 18);
+
+        // Memory far beyond what the gas pays for
+        let result = run(
+            &[PUSH1, 1, PUSH4, 0xff, 0xff, 0xff, 0xff, MSTORE],
+            Fork::Cancun,
+            &mut host,
+        );
+        assert_eq!(result.status, ExitStatus::OutOfGas);
+        assert_eq!(result.gas.remaining(), 0);
+
+        // EXP pays per byte of exponent, more from Spurious Dragon
+        let code = [PUSH2, 1, 0, PUSH1, 2, EXP];
+        assert_eq!(
+            run(&code, Fork::Cancun, &mut host).gas.used(),
+            6 + 10 + 2 * 50
+        );
+        assert_eq!(
+            run(&code, Fork::Homestead, &mut host).gas.used(),
+            6 + 10 + 2 * 10
+        );
+    }
+
+    #[test]
+    fn validates_jumps() {
+        let mut host = TestHost::default();
+        // Jumps over an invalid opcode to the JUMPDEST at 4
+        let result = run(
+            &[PUSH1, 4, JUMP, INVALID, JUMPDEST, STOP],
+            Fork::Cancun,
+            &mut host,
+        );
+        assert_eq!(result.status, ExitStatus::Stop);
+        // The only 0x5b is push data
+        let result = run(&[PUSH1, 3, JUMP, PUSH1, JUMPDEST], Fork::Cancun, &mut host);
+        assert_eq!(result.status, ExitStatus::InvalidJump);
+        let result = run(&[PUSH1, 0, PUSH1, 3, JUMPI, STOP], Fork::Cancun, &mut host);
+        assert_eq!(result.status, ExitStatus::Stop);
+        let result = run(&[INVALID], Fork::Cancun, &mut host);
+        assert_eq!(result.status, ExitStatus::InvalidOpcode(INVALID));
+        assert_eq!(result.status.to_string(), "invalid opcode: INVALID");
+    }
+
+    #[test]
+    fn gates_opcodes_by_fork() {
+        let mut host = TestHost::default();
+        assert_eq!(
+            run(&[PUSH0], Fork::Paris, &mut host).status,
+            ExitStatus::InvalidOpcode(PUSH0)
+        );
+        assert_eq!(
+            run(&[PUSH0], Fork::Shanghai, &mut host).status,
+            ExitStatus::Stop
+        );
+        assert_eq!(
+            run(&[PUSH1, 0, PUSH1, 0, SHL], Fork::Byzantium, &mut host).status,
+            ExitStatus::InvalidOpcode(SHL)
+        );
+        assert_eq!(
+            run(&[PUSH0, TLOAD], Fork::Shanghai, &mut host).status,
+            ExitStatus::InvalidOpcode(TLOAD)
+        );
+
+        // Transient storage and memory copies from Cancun
+        let result = run(
+            &returning(&[
+                PUSH1, 42, PUSH1, 1, TSTORE, PUSH1, 1, TLOAD, PUSH0, MSTORE, PUSH1, 32, PUSH0,
+                PUSH1, 1, MCOPY, PUSH1, 1, MLOAD,
+            ]),
+            Fork::Cancun,
+            &mut host,
+        );
+        assert_eq!(U256::from_big_endian(&result.output), U256::from(42));
+
+        host.env.tx.blob_hashes = vec![H256::repeat_byte(1)];
+        host.env.block.blob_base_fee = U256::from(7);
+        assert_eq!(
+            U256::from_big_endian(
+                &run(&returning(&[PUSH0, BLOBHASH]), Fork::Cancun, &mut host).output
+            ),
+            U256::from_big_endian(&[1; 32])
+        );
+        assert_eq!(
+            U256::from_big_endian(&run(&returning(&[BLOBBASEFEE]), Fork::Cancun, &mut host).output),
+            U256::from(7)
+        );
+    }
+
+    #[test]
+    fn storage_costs_and_refunds() {
+        let mut host = TestHost::default();
+        host.storage
+            .insert(H256::zero(), (U256::one(), U256::one()));
+        // Clears slot 0, then sets cold slot 1
+        let code = [PUSH0, PUSH0, SSTORE, PUSH1, 1, PUSH1, 1, SSTORE];
+        let result = run(&code, Fork::Cancun, &mut host);
+        assert_eq!(result.gas.used(), 2 + 2 + 2900 + 3 + 3 + 20000 + 2100);
+        assert_eq!(result.gas.refunded(), 4800);
+
+        let mut host = TestHost::default();
+        let result = run(&code, Fork::Istanbul, &mut host);
+        assert_eq!(result.status, ExitStatus::InvalidOpcode(PUSH0));
+
+        let mut interpreter = interpreter(&[PUSH1, 1, PUSH0, SSTORE], Fork::Cancun);
+        interpreter.is_static = true;
+        let Action::Return(result) = interpreter.run(&mut host, &mut NoopTracer).unwrap() else {
+            panic!()
+        };
+        assert_eq!(result.status, ExitStatus::StaticStateChange);
+
+        let result = run(
+            &[PUSH1, 0xab, PUSH0, MSTORE, PUSH1, 7, PUSH1, 1, PUSH0, LOG1],
+            Fork::Cancun,
+            &mut host,
+        );
+        assert_eq!(result.status, ExitStatus::Stop);
+        assert_eq!(host.logs[0].topics, vec![H256::from_low_u64_be(7)]);
+        assert_eq!(host.logs[0].data.as_ref(), &[0]);
+    }
+
+    #[test]
+    fn hands_out_calls() {
+        let mut host = TestHost::default();
+        // CALL(gas = 0xffff, to = 0xbb, value = 0, input = memory[0..4], output = memory[0..32])
+        let code = [
+            PUSH1, 32, PUSH0, PUSH1, 4, PUSH0, PUSH0, PUSH1, 0xbb, PUSH2, 0xff, 0xff, CALL,
+        ];
+        let mut frame = interpreter(&code, Fork::Cancun);
+        let Action::Call(call) = frame.run(&mut host, &mut NoopTracer).unwrap() else {
+            panic!("expected a call")
+        };
+        assert_eq!(call.target, Address::from_low_u64_be(0xbb));
+        assert_eq!((call.caller, call.depth, call.input.len()), (ADDRESS, 1, 4));
+        assert_eq!(call.gas_limit, 0xffff);
+        let charged = frame.gas.used();
+
+        let mut child_gas = Gas::new(call.gas_limit);
+        assert!(child_gas.record_cost(100));
+        frame.resume_call(FrameResult {
+            status: ExitStatus::Return,
+            gas: child_gas,
+            output: Bytes::from_static(&[1, 2, 3]),
+        });
+        assert_eq!(frame.stack.data(), &[U256::one()]);
+        assert_eq!(&frame.memory.data()[..4], &[1, 2, 3, 0]);
+        assert_eq!(frame.gas.used(), charged - 0xffff + 100);
+        let Action::Return(result) = frame.run(&mut host, &mut NoopTracer).unwrap() else {
+            panic!()
+        };
+        assert_eq!(result.status, ExitStatus::Stop);
+
+        // A frame at the depth limit cannot call, and keeps the gas it would have given
+        let mut frame = interpreter(&code, Fork::Cancun);
+        frame.depth = CALL_DEPTH_LIMIT;
+        let Action::Return(result) = frame.run(&mut host, &mut NoopTracer).unwrap() else {
+            panic!("expected no call")
+        };
+        assert_eq!(frame.stack.data(), &[U256::zero()]);
+        assert!(result.gas.used() < 3000);
+    }
+}
@@ -0,0 +1,973 @@
+use bytes::Bytes;
+use ethereum_types::{H256, U256, U512};
+use keccak_hash::keccak;
+use reec_core::types::Log;
+
+use super::{
+    address_to_u256, u256_to_address, CallInputs, CallScheme, CreateInputs, CreateScheme,
+    ExitStatus, Halt, Interpreter, CALL_DEPTH_LIMIT, MAX_INITCODE_SIZE,
+};
+use crate::{
+    eof::{self, Eof, RETURN_STACK_LIMIT},
+    gas,
+    opcode::*,
+    stack::STACK_LIMIT,
+    Fork, Host,
+};
+
+impl Interpreter {
+    /// Executes the instruction at the program counter.
+    pub(super) fn step<H: Host + ?Sized>(&mut self, host: &mut H) -> Result<(), Halt> {
+        let op = self.current_opcode();
+        self.pc += 1;
+        match op {
+            STOP => return Err(ExitStatus::Stop.into()),
+
+            ADD => self.binary(gas::VERY_LOW, |a, b| a.overflowing_add(b).0)?,
+            MUL => self.binary(gas::LOW, |a, b| a.overflowing_mul(b).0)?,
+            SUB => self.binary(gas::VERY_LOW, |a, b| a.overflowing_sub(b).0)?,
+            DIV => self.binary(gas::LOW, |a, b| if b.is_zero() { b } else { a / b })?,
+            SDIV => self.binary(gas::LOW, signed_div)?,
+            MOD => self.binary(gas::LOW, |a, b| if b.is_zero() { b } else { a % b })?,
+            SMOD => self.binary(gas::LOW, signed_mod)?,
+            ADDMOD => {
+                self.charge(gas::MID)?;
+                let [a, b, n] = self.stack.pop_n()?;
+                let result = if n.is_zero() {
+                    n
+                } else {
+                    let sum = (U512::from(a) + U512::from(b)) % U512::from(n);
+                    U256::try_from(sum).expect("remainder is below a U256")
+                };
+                self.stack.push(result)?;
+            }
+            MULMOD => {
+                self.charge(gas::MID)?;
+                let [a, b, n] = self.stack.pop_n()?;
+                let result = if n.is_zero() {
+                    n
+                } else {
+                    U256::try_from(a.full_mul(b) % U512::from(n))
+                        .expect("remainder is below a U256")
+                };
+                self.stack.push(result)?;
+            }
+            EXP => {
+                let [base, exponent] = self.stack.pop_n()?;
+                let exponent_bytes = exponent.bits().div_ceil(8) as u64;
+                self.charge(gas::EXP + gas::exp_byte(self.fork) * exponent_bytes)?;
+                self.stack.push(base.overflowing_pow(exponent).0)?;
+            }
+            SIGNEXTEND => self.binary(gas::LOW, sign_extend)?,
+
+            LT => self.binary(gas::VERY_LOW, |a, b| bool_to_u256(a < b))?,
+            GT => self.binary(gas::VERY_LOW, |a, b| bool_to_u256(a > b))?,
+            SLT => self.binary(gas::VERY_LOW, |a, b| bool_to_u256(signed_lt(a, b)))?,
+            SGT => self.binary(gas::VERY_LOW, |a, b| bool_to_u256(signed_lt(b, a)))?,
+            EQ => self.binary(gas::VERY_LOW, |a, b| bool_to_u256(a == b))?,
+            ISZERO => {
+                self.charge(gas::VERY_LOW)?;
+                let a = self.stack.pop()?;
+                self.stack.push(bool_to_u256(a.is_zero()))?;
+            }
+            AND => self.binary(gas::VERY_LOW, |a, b| a & b)?,
+            OR => self.binary(gas::VERY_LOW, |a, b| a | b)?,
+            XOR => self.binary(gas::VERY_LOW, |a, b| a ^ b)?,
+            NOT => {
+                self.charge(gas::VERY_LOW)?;
+                let a = self.stack.pop()?;
+                self.stack.push(!a)?;
+            }
+            BYTE => self.binary(gas::VERY_LOW, |i, x| {
+                if i < U256::from(32) {
+                    U256::from(x.byte(31 - i.as_usize()))
+                } else {
+                    U256::zero()
+                }
+            })?,
+            SHL => {
+                self.require(Fork::Constantinople, op)?;
+                self.binary(gas::VERY_LOW, |shift, value| {
+                    if shift < U256::from(256) {
+                        value << shift.as_usize()
+                    } else {
+                        U256::zero()
+                    }
+                })?
+            }
+            SHR => {
+                self.require(Fork::Constantinople, op)?;
+                self.binary(gas::VERY_LOW, |shift, value| {
+                    if shift < U256::from(256) {
+                        value >> shift.as_usize()
+                    } else {
+                        U256::zero()
+                    }
+                })?
+            }
+            SAR => {
+                self.require(Fork::Constantinople, op)?;
+                self.binary(gas::VERY_LOW, |shift, value| {
+                    let shift = if shift < U256::from(256) {
+                        shift.as_usize()
+                    } else {
+                        256
+                    };
+                    // Shifting the complement in zeroes shifts the value in ones
+                    match (is_negative(value), shift) {
+                        (false, 256) => U256::zero(),
+                        (false, shift) => value >> shift,
+                        (true, 256) => U256::MAX,
+                        (true, shift) => !(!value >> shift),
+                    }
+                })?
+            }
+
+            KECCAK256 => {
+                let [offset, len] = self.stack.pop_n()?;
+                self.charge(gas::KECCAK256 + gas::KECCAK256_WORD * words(len))?;
+                let range = self.memory_range(offset, len)?;
+                let hash = keccak(self.memory.get(range));
+                self.stack.push(U256::from_big_endian(hash.as_bytes()))?;
+            }
+
+            ADDRESS => self.push_value(gas::BASE, address_to_u256(self.contract.address))?,
+            BALANCE => {
+                let address = u256_to_address(self.stack.pop()?);
+                let balance = host.balance(address)?;
+                self.charge(gas::account_access(
+                    self.fork,
+                    balance.is_cold,
+                    gas::balance(self.fork),
+                ))?;
+                self.stack.push(balance.data)?;
+            }
+            ORIGIN => self.push_value(gas::BASE, address_to_u256(host.env().tx.origin))?,
+            CALLER => self.push_value(gas::BASE, address_to_u256(self.contract.caller))?,
+            CALLVALUE => self.push_value(gas::BASE, self.contract.value)?,
+            CALLDATALOAD => {
+                self.charge(gas::VERY_LOW)?;
+                let offset = self.stack.pop()?;
+                let word = load_word(&self.contract.input, offset);
+                self.stack.push(word)?;
+            }
+            CALLDATASIZE => self.push_value(gas::BASE, U256::from(self.contract.input.len()))?,
+            CALLDATACOPY => {
+                let input = self.contract.input.clone();
+                self.copy_to_memory(&input)?;
+            }
+            CODESIZE => self.push_value(gas::BASE, U256::from(self.contract.code.len()))?,
+            CODECOPY => {
+                let code = self.contract.code.original().clone();
+                self.copy_to_memory(&code)?;
+            }
+            GASPRICE => self.push_value(gas::BASE, host.env().tx.gas_price)?,
+            EXTCODESIZE => {
+                let address = u256_to_address(self.stack.pop()?);
+                let code = host.code(address)?;
+                self.charge(gas::account_access(
+                    self.fork,
+                    code.is_cold,
+                    gas::extcode(self.fork),
+                ))?;
+                self.stack.push(U256::from(code.data.len()))?;
+            }
+            EXTCODECOPY => {
+                let [address, memory_offset, code_offset, len] = self.stack.pop_n()?;
+                let code = host.code(u256_to_address(address))?;
+                let access = gas::account_access(self.fork, code.is_cold, gas::extcode(self.fork));
+                self.charge(access + gas::COPY_WORD * words(len))?;
+                let range = self.memory_range(memory_offset, len)?;
+                self.memory.set_data(range, &code.data, code_offset);
+            }
+            RETURNDATASIZE => {
+                self.require(Fork::Byzantium, op)?;
+                self.push_value(gas::BASE, U256::from(self.return_data.len()))?
+            }
+            RETURNDATACOPY => {
+                self.require(Fork::Byzantium, op)?;
+                let [memory_offset, data_offset, len] = self.stack.pop_n()?;
+                self.charge(gas::VERY_LOW + gas::COPY_WORD * words(len))?;
+                // EOF code reads zeroes past the end instead (EIP-7069)
+                let end = data_offset.checked_add(len);
+                if !self.is_eof && end.is_none_or(|end| end > U256::from(self.return_data.len())) {
+                    return Err(ExitStatus::ReturnDataOutOfBounds.into());
+                }
+                let range = self.memory_range(memory_offset, len)?;
+                let data = self.return_data.clone();
+                self.memory.set_data(range, &data, data_offset);
+            }
+            EXTCODEHASH => {
+                self.require(Fork::Constantinople, op)?;
+                let address = u256_to_address(self.stack.pop()?);
+                let hash = host.code_hash(address)?;
+                self.charge(gas::account_access(
+                    self.fork,
+                    hash.is_cold,
+                    gas::extcodehash(self.fork),
+                ))?;
+                self.stack
+                    .push(U256::from_big_endian(hash.data.as_bytes()))?;
+            }
+
+            BLOCKHASH => {
+                self.charge(gas::BLOCKHASH)?;
+                let number = self.stack.pop()?;
+                let current = host.env().block.number;
+                let hash = match u64::try_from(number) {
+                    Ok(number) if number < current && current - number <= 256 => {
+                        host.block_hash(number)?
+                    }
+                    _ => H256::zero(),
+                };
+                self.stack.push(U256::from_big_endian(hash.as_bytes()))?;
+            }
+            COINBASE => self.push_value(gas::BASE, address_to_u256(host.env().block.coinbase))?,
+            TIMESTAMP => self.push_value(gas::BASE, U256::from(host.env().block.timestamp))?,
+            NUMBER => self.push_value(gas::BASE, U256::from(host.env().block.number))?,
+            PREVRANDAO => {
+                let block = &host.env().block;
+                let value = if self.fork >= Fork::Paris {
+                    U256::from_big_endian(block.prev_randao.as_bytes())
+                } else {
+                    block.difficulty
+                };
+                self.push_value(gas::BASE, value)?
+            }
+            GASLIMIT => self.push_value(gas::BASE, U256::from(host.env().block.gas_limit))?,
+            CHAINID => {
+                self.require(Fork::Istanbul, op)?;
+                self.push_value(gas::BASE, U256::from(host.env().chain_id))?
+            }
+            SELFBALANCE => {
+                self.require(Fork::Istanbul, op)?;
+                self.charge(gas::LOW)?;
+                let balance = host.balance(self.contract.address)?;
+                self.stack.push(balance.data)?;
+            }
+            BASEFEE => {
+                self.require(Fork::London, op)?;
+                self.push_value(gas::BASE, host.env().block.base_fee)?
+            }
+            BLOBHASH => {
+                self.require(Fork::Cancun, op)?;
+                self.charge(gas::VERY_LOW)?;
+                let index = self.stack.pop()?;
+                let hashes = &host.env().tx.blob_hashes;
+                let hash = match usize::try_from(index) {
+                    Ok(index) if index < hashes.len() => {
+                        U256::from_big_endian(hashes[index].as_bytes())
+                    }
+                    _ => U256::zero(),
+                };
+                self.stack.push(hash)?;
+            }
+            BLOBBASEFEE => {
+                self.require(Fork::Cancun, op)?;
+                self.push_value(gas::BASE, host.env().block.blob_base_fee)?
+            }
+
+            POP => {
+                self.charge(gas::BASE)?;
+                self.stack.pop()?;
+            }
+            MLOAD => {
+                self.charge(gas::VERY_LOW)?;
+                let offset = self.stack.pop()?;
+                let range = self.memory_range(offset, U256::from(32))?;
+                self.stack.push(self.memory.word(range.start))?;
+            }
+            MSTORE => {
+                self.charge(gas::VERY_LOW)?;
+                let [offset, value] = self.stack.pop_n()?;
+                let range = self.memory_range(offset, U256::from(32))?;
+                self.memory.set_word(range.start, value);
+            }
+            MSTORE8 => {
+                self.charge(gas::VERY_LOW)?;
+                let [offset, value] = self.stack.pop_n()?;
+                let range = self.memory_range(offset, U256::one())?;
+                self.memory.set(range.start, &[value.byte(0)]);
+            }
+            SLOAD => {
+                let slot = self.stack.pop()?;
+                let value = host.sload(self.contract.address, u256_to_h256(slot))?;
+                self.charge(gas::sload(self.fork, value.is_cold))?;
+                self.stack.push(value.data)?;
+            }
+            SSTORE => self.sstore(host)?,
+            JUMP => {
+                self.charge(gas::MID)?;
+                let destination = self.stack.pop()?;
+                self.jump(destination)?;
+            }
+            JUMPI => {
+                self.charge(gas::HIGH)?;
+                let [destination, condition] = self.stack.pop_n()?;
+                if !condition.is_zero() {
+                    self.jump(destination)?;
+                }
+            }
+            PC => self.push_value(gas::BASE, U256::from(self.pc - 1))?,
+            MSIZE => self.push_value(gas::BASE, U256::from(self.memory.len()))?,
+            GAS => {
+                self.charge(gas::BASE)?;
+                self.stack.push(U256::from(self.gas.remaining()))?;
+            }
+            JUMPDEST => self.charge(gas::JUMPDEST)?,
+            TLOAD => {
+                self.require(Fork::Cancun, op)?;
+                self.charge(gas::WARM_STORAGE_READ)?;
+                let slot = self.stack.pop()?;
+                let value = host.tload(self.contract.address, u256_to_h256(slot));
+                self.stack.push(value)?;
+            }
+            TSTORE => {
+                self.require(Fork::Cancun, op)?;
+                self.require_non_static()?;
+                self.charge(gas::WARM_STORAGE_READ)?;
+                let [slot, value] = self.stack.pop_n()?;
+                host.tstore(self.contract.address, u256_to_h256(slot), value);
+            }
+            MCOPY => {
+                self.require(Fork::Cancun, op)?;
+                let [destination, source, len] = self.stack.pop_n()?;
+                self.charge(gas::VERY_LOW + gas::COPY_WORD * words(len))?;
+                let range = self.memory_range(destination.max(source), len)?;
+                if !range.is_empty() {
+                    self.memory
+                        .copy_within(source.as_usize(), destination.as_usize(), range.len());
+                }
+            }
+            PUSH0 => {
+                self.require(Fork::Shanghai, op)?;
+                self.push_value(gas::BASE, U256::zero())?
+            }
+            PUSH1..=PUSH32 => {
+                self.charge(gas::VERY_LOW)?;
+                let len = immediate_size(op);
+                let value =
+                    U256::from_big_endian(&self.contract.code.padded()[self.pc..self.pc + len]);
+                self.stack.push(value)?;
+                self.pc += len;
+            }
+            DUP1..=DUP16 => {
+                self.charge(gas::VERY_LOW)?;
+                self.stack.dup((op - DUP1 + 1) as usize)?;
+            }
+            SWAP1..=SWAP16 => {
+                self.charge(gas::VERY_LOW)?;
+                self.stack.swap((op - SWAP1 + 1) as usize)?;
+            }
+            LOG0..=LOG4 => {
+                self.require_non_static()?;
+                let topic_count = (op - LOG0) as usize;
+                let [offset, len] = self.stack.pop_n()?;
+                let mut topics = Vec::with_capacity(topic_count);
+                for _ in 0..topic_count {
+                    topics.push(u256_to_h256(self.stack.pop()?));
+                }
+                let data_cost = saturating_u64(len).saturating_mul(gas::LOG_DATA);
+                self.charge(gas::LOG + gas::LOG_TOPIC * topic_count as u64 + data_cost)?;
+                let range = self.memory_range(offset, len)?;
+                host.log(Log {
+                    address: self.contract.address,
+                    topics,
+                    data: Bytes::copy_from_slice(self.memory.get(range)),
+                });
+            }
+
+            DATALOAD => {
+                self.require_eof(op)?;
+                self.charge(gas::DATALOAD)?;
+                let offset = self.stack.pop()?;
+                let word = load_word(self.eof().data(), offset);
+                self.stack.push(word)?;
+            }
+            DATALOADN => {
+                self.require_eof(op)?;
+                self.charge(gas::VERY_LOW)?;
+                let word = load_word(self.eof().data(), U256::from(self.immediate_u16()));
+                self.stack.push(word)?;
+                self.pc += 2;
+            }
+            DATASIZE => {
+                self.require_eof(op)?;
+                self.push_value(gas::BASE, U256::from(self.eof().data().len()))?
+            }
+            DATACOPY => {
+                self.require_eof(op)?;
+                let code = self.contract.code.clone();
+                self.copy_to_memory(code.eof().expect("EOF frames run EOF code").data())?;
+            }
+            RJUMP => {
+                self.require_eof(op)?;
+                self.charge(gas::BASE)?;
+                self.pc = self.relative_target(2, self.immediate_i16(0));
+            }
+            RJUMPI => {
+                self.require_eof(op)?;
+                self.charge(gas::RJUMPI)?;
+                let condition = self.stack.pop()?;
+                let offset = if condition.is_zero() {
+                    0
+                } else {
+                    self.immediate_i16(0)
+                };
+                self.pc = self.relative_target(2, offset);
+            }
+            RJUMPV => {
+                self.require_eof(op)?;
+                self.charge(gas::RJUMPI)?;
+                let case = self.stack.pop()?;
+                let max_index = self.contract.code.padded()[self.pc] as usize;
+                let offset = match usize::try_from(case) {
+                    Ok(case) if case <= max_index => self.immediate_i16(1 + 2 * case),
+                    _ => 0,
+                };
+                self.pc = self.relative_target(1 + 2 * (max_index + 1), offset);
+            }
+            CALLF | JUMPF => {
+                self.require_eof(op)?;
+                self.charge(gas::CALLF)?;
+                let section = self.immediate_u16();
+                let eof = self.eof();
+                let start = eof.code_start(section);
+                // Validation bounds the stack growth within a section, not the height
+                // the section is entered at
+                let overflows = self.stack.len() + eof.types()[section].max_stack_increase as usize
+                    > STACK_LIMIT;
+                if overflows || (op == CALLF && self.return_stack.len() == RETURN_STACK_LIMIT) {
+                    return Err(ExitStatus::StackOverflow.into());
+                }
+                if op == CALLF {
+                    self.return_stack.push(self.pc + 2);
+                }
+                self.pc = start;
+            }
+            RETF => {
+                self.require_eof(op)?;
+                self.charge(gas::RETF)?;
+                // Validation leaves no way into a returning section but `CALLF`
+                self.pc = self
+                    .return_stack
+                    .pop()
+                    .expect("returning sections are entered by CALLF");
+            }
+            DUPN => {
+                self.require_eof(op)?;
+                self.charge(gas::VERY_LOW)?;
+                let n = self.contract.code.padded()[self.pc] as usize;
+                self.stack.dup(n + 1)?;
+                self.pc += 1;
+            }
+            SWAPN => {
+                self.require_eof(op)?;
+                self.charge(gas::VERY_LOW)?;
+                let n = self.contract.code.padded()[self.pc] as usize;
+                self.stack.swap(n + 1)?;
+                self.pc += 1;
+            }
+            EXCHANGE => {
+                self.require_eof(op)?;
+                self.charge(gas::VERY_LOW)?;
+                let (n, m) = eof::exchange_operands(self.contract.code.padded()[self.pc]);
+                self.stack.exchange(n, m)?;
+                self.pc += 1;
+            }
+            EOFCREATE => {
+                self.require_eof(op)?;
+                self.eof_create(host)?
+            }
+            RETURNCONTRACT => {
+                self.require_eof(op)?;
+                self.return_contract()?
+            }
+            RETURNDATALOAD => {
+                self.require_eof(op)?;
+                self.charge(gas::VERY_LOW)?;
+                let offset = self.stack.pop()?;
+                let word = load_word(&self.return_data, offset);
+                self.stack.push(word)?;
+            }
+            EXTCALL => {
+                self.require_eof(op)?;
+                self.ext_call(host, CallScheme::ExtCall)?
+            }
+            EXTDELEGATECALL => {
+                self.require_eof(op)?;
+                self.ext_call(host, CallScheme::ExtDelegateCall)?
+            }
+            EXTSTATICCALL => {
+                self.require_eof(op)?;
+                self.ext_call(host, CallScheme::ExtStaticCall)?
+            }
+
+            CREATE => self.create(host, false)?,
+            CREATE2 => {
+                self.require(Fork::Constantinople, op)?;
+                self.create(host, true)?
+            }
+            CALL => self.call(host, CallScheme::Call)?,
+            CALLCODE => self.call(host, CallScheme::CallCode)?,
+            DELEGATECALL => {
+                self.require(Fork::Homestead, op)?;
+                self.call(host, CallScheme::DelegateCall)?
+            }
+            STATICCALL => {
+                self.require(Fork::Byzantium, op)?;
+                self.call(host, CallScheme::StaticCall)?
+            }
+            RETURN => {
+                let [offset, len] = self.stack.pop_n()?;
+                let range = self.memory_range(offset, len)?;
+                self.output = Bytes::copy_from_slice(self.memory.get(range));
+                return Err(ExitStatus::Return.into());
+            }
+            REVERT => {
+                self.require(Fork::Byzantium, op)?;
+                let [offset, len] = self.stack.pop_n()?;
+                let range = self.memory_range(offset, len)?;
+                self.output = Bytes::copy_from_slice(self.memory.get(range));
+                return Err(ExitStatus::Revert.into());
+            }
+            SELFDESTRUCT => self.selfdestruct(host)?,
+            op => return Err(ExitStatus::InvalidOpcode(op).into()),
+        }
+        Ok(())
+    }
+
+    fn binary(&mut self, cost: u64, f: impl FnOnce(U256, U256) -> U256) -> Result<(), ExitStatus> {
+        self.charge(cost)?;
+        let [a, b] = self.stack.pop_n()?;
+        self.stack.push(f(a, b))
+    }
+
+    fn push_value(&mut self, cost: u64, value: U256) -> Result<(), ExitStatus> {
+        self.charge(cost)?;
+        self.stack.push(value)
+    }
+
+    fn jump(&mut self, destination: U256) -> Result<(), ExitStatus> {
+        match usize::try_from(destination) {
+            Ok(destination) if self.contract.code.is_jumpdest(destination) => {
+                self.pc = destination;
+                Ok(())
+            }
+            _ => Err(ExitStatus::InvalidJump),
+        }
+    }
+
+    /// `CALLDATACOPY` and `CODECOPY`.
+    fn copy_to_memory(&mut self, source: &[u8]) -> Result<(), ExitStatus> {
+        let [memory_offset, source_offset, len] = self.stack.pop_n()?;
+        self.charge(gas::VERY_LOW + gas::COPY_WORD * words(len))?;
+        let range = self.memory_range(memory_offset, len)?;
+        self.memory.set_data(range, source, source_offset);
+        Ok(())
+    }
+
+    fn sstore<H: Host + ?Sized>(&mut self, host: &mut H) -> Result<(), Halt> {
+        self.require_non_static()?;
+        // EIP-2200: a call with only the stipend left cannot write storage
+        if self.fork >= Fork::Istanbul && self.gas.remaining() <= gas::CALL_STIPEND {
+            return Err(ExitStatus::OutOfGas.into());
+        }
+        let [slot, value] = self.stack.pop_n()?;
+        let result = host.sstore(self.contract.address, u256_to_h256(slot), value)?;
+        let (cost, refund) = gas::sstore(self.fork, &result.data, result.is_cold);
+        self.charge(cost)?;
+        self.gas.record_refund(refund);
+        Ok(())
+    }
+
+    fn create<H: Host + ?Sized>(&mut self, host: &mut H, create2: bool) -> Result<(), Halt> {
+        self.require_non_static()?;
+        let [value, offset, len] = self.stack.pop_n()?;
+        let salt = if create2 {
+            Some(self.stack.pop()?)
+        } else {
+            None
+        };
+        if self.fork >= Fork::Shanghai && len > U256::from(MAX_INITCODE_SIZE) {
+            return Err(ExitStatus::InitCodeSizeExceeded.into());
+        }
+        let mut cost = gas::CREATE;
+        if create2 {
+            cost += gas::KECCAK256_WORD * words(len);
+        }
+        if self.fork >= Fork::Shanghai {
+            cost += gas::INITCODE_WORD * words(len);
+        }
+        self.charge(cost)?;
+        let range = self.memory_range(offset, len)?;
+        let init_code = Bytes::copy_from_slice(self.memory.get(range));
+
+        let gas_limit = self.forwardable_gas(self.gas.remaining());
+        self.charge(gas_limit)?;
+        self.return_data = Bytes::new();
+        if self.depth >= CALL_DEPTH_LIMIT || host.balance(self.contract.address)?.data < value {
+            self.gas.erase_cost(gas_limit);
+            self.stack.push(U256::zero())?;
+            return Ok(());
+        }
+        Err(Halt::Create(Box::new(CreateInputs {
+            scheme: match salt {
+                Some(salt) => CreateScheme::Create2 { salt },
+                None => CreateScheme::Create,
+            },
+            caller: self.contract.address,
+            value,
+            init_code,
+            input: Bytes::new(),
+            gas_limit,
+            depth: self.depth + 1,
+        })))
+    }
+
+    fn call<H: Host + ?Sized>(&mut self, host: &mut H, scheme: CallScheme) -> Result<(), Halt> {
+        let [requested_gas, address] = self.stack.pop_n()?;
+        let value = match scheme {
+            CallScheme::Call | CallScheme::CallCode => self.stack.pop()?,
+            _ => U256::zero(),
+        };
+        let [input_offset, input_len, output_offset, output_len] = self.stack.pop_n()?;
+        let address = u256_to_address(address);
+        let transfers_value = !value.is_zero();
+        if scheme == CallScheme::Call && transfers_value {
+            self.require_non_static()?;
+        }
+
+        let input_range = self.memory_range(input_offset, input_len)?;
+        let output_range = self.memory_range(output_offset, output_len)?;
+        let account = host.load_account(address)?;
+        let mut cost = gas::account_access(self.fork, account.is_cold, gas::call(self.fork));
+        // EIP-7702: reaching the code a delegating account runs costs an access of its own
+        if let Some(delegate) = account.data.delegation {
+            cost += gas::account_access(self.fork, delegate.is_cold, 0);
+        }
+        if transfers_value {
+            cost += gas::CALL_VALUE;
+        }
+        if scheme == CallScheme::Call {
+            let new_account = if self.fork >= Fork::SpuriousDragon {
+                transfers_value && account.data.is_empty
+            } else {
+                !account.data.exists
+            };
+            if new_account {
+                cost += gas::NEW_ACCOUNT;
+            }
+        }
+        self.charge(cost)?;
+
+        // EIP-150: at most all but one 64th of the remaining gas is passed on
+        let requested_gas = u64::try_from(requested_gas).unwrap_or(u64::MAX);
+        let forwarded = if self.fork >= Fork::Tangerine {
+            requested_gas.min(self.forwardable_gas(self.gas.remaining()))
+        } else {
+            requested_gas
+        };
+        self.charge(forwarded)?;
+        let gas_limit = if transfers_value {
+            forwarded + gas::CALL_STIPEND
+        } else {
+            forwarded
+        };
+
+        self.return_data = Bytes::new();
+        let transfer = matches!(scheme, CallScheme::Call | CallScheme::CallCode);
+        if self.depth >= CALL_DEPTH_LIMIT
+            || (transfer && transfers_value && host.balance(self.contract.address)?.data < value)
+        {
+            self.gas.erase_cost(gas_limit);
+            self.stack.push(U256::zero())?;
+            return Ok(());
+        }
+
+        let input = Bytes::copy_from_slice(self.memory.get(input_range));
+        self.return_range = output_range;
+        let (target, caller, value) = match scheme {
+            CallScheme::CallCode => (self.contract.address, self.contract.address, value),
+            CallScheme::DelegateCall => (
+                self.contract.address,
+                self.contract.caller,
+                self.contract.value,
+            ),
+            _ => (address, self.contract.address, value),
+        };
+        Err(Halt::Call(Box::new(CallInputs {
+            scheme,
+            target,
+            code_address: address,
+            caller,
+            value,
+            transfer: transfer && transfers_value,
+            input,
+            gas_limit,
+            is_static: self.is_static || scheme == CallScheme::StaticCall,
+            depth: self.depth + 1,
+        })))
+    }
+
+    /// `EOFCREATE`, which runs one of the container's subcontainers as init code.
+    fn eof_create<H: Host + ?Sized>(&mut self, host: &mut H) -> Result<(), Halt> {
+        self.require_non_static()?;
+        let index = self.contract.code.padded()[self.pc] as usize;
+        self.pc += 1;
+        let [value, salt, input_offset, input_len] = self.stack.pop_n()?;
+        self.charge(gas::CREATE)?;
+        let range = self.memory_range(input_offset, input_len)?;
+        let input = Bytes::copy_from_slice(self.memory.get(range));
+        let init_code = self.eof().container(index);
+
+        let gas_limit = self.forwardable_gas(self.gas.remaining());
+        self.charge(gas_limit)?;
+        self.return_data = Bytes::new();
+        if self.depth >= CALL_DEPTH_LIMIT || host.balance(self.contract.address)?.data < value {
+            self.gas.erase_cost(gas_limit);
+            self.stack.push(U256::zero())?;
+            return Ok(());
+        }
+        Err(Halt::Create(Box::new(CreateInputs {
+            scheme: CreateScheme::EofCreate { salt },
+            caller: self.contract.address,
+            value,
+            init_code,
+            input,
+            gas_limit,
+            depth: self.depth + 1,
+        })))
+    }
+
+    /// `RETURNCONTRACT`, which ends init code with one of the container's subcontainers
+    /// as the code to deploy, its data section completed with aux data from memory.
+    fn return_contract(&mut self) -> Result<(), Halt> {
+        let index = self.contract.code.padded()[self.pc] as usize;
+        self.pc += 1;
+        let [offset, len] = self.stack.pop_n()?;
+        let range = self.memory_range(offset, len)?;
+        let container =
+            Eof::decode(self.eof().container(index)).expect("subcontainers were validated");
+        self.output = container
+            .with_aux_data(self.memory.get(range))
+            .ok_or(ExitStatus::InvalidAuxData)?;
+        Err(ExitStatus::Return.into())
+    }
+
+    /// `EXTCALL`, `EXTDELEGATECALL` and `EXTSTATICCALL`, which take no gas operand and
+    /// return no output to memory. Calls that cannot be made, including delegate calls
+    /// to legacy code, leave the caller its gas and push 1 as a revert would.
+    fn ext_call<H: Host + ?Sized>(&mut self, host: &mut H, scheme: CallScheme) -> Result<(), Halt> {
+        let [address, input_offset, input_len] = self.stack.pop_n()?;
+        let value = if scheme == CallScheme::ExtCall {
+            self.stack.pop()?
+        } else {
+            U256::zero()
+        };
+        if address.bits() > 160 {
+            return Err(ExitStatus::AddressOutOfRange.into());
+        }
+        let address = u256_to_address(address);
+        let transfers_value = !value.is_zero();
+        if transfers_value {
+            self.require_non_static()?;
+        }
+
+        let input_range = self.memory_range(input_offset, input_len)?;
+        let account = host.load_account(address)?;
+        let mut cost = gas::account_access(self.fork, account.is_cold, 0);
+        if let Some(delegate) = account.data.delegation {
+            cost += gas::account_access(self.fork, delegate.is_cold, 0);
+        }
+        if transfers_value {
+            cost += gas::CALL_VALUE;
+            if account.data.is_empty {
+                cost += gas::NEW_ACCOUNT;
+            }
+        }
+        self.charge(cost)?;
+
+        let available = self.gas.remaining();
+        let gas_limit = available.saturating_sub((available / 64).max(gas::MIN_RETAINED_GAS));
+        self.return_data = Bytes::new();
+        if gas_limit < gas::MIN_CALLEE_GAS
+            || self.depth >= CALL_DEPTH_LIMIT
+            || (transfers_value && host.balance(self.contract.address)?.data < value)
+            || (scheme == CallScheme::ExtDelegateCall
+                && !host.code(address)?.data.starts_with(&eof::MAGIC))
+        {
+            self.stack.push(U256::one())?;
+            return Ok(());
+        }
+        self.charge(gas_limit)?;
+
+        let input = Bytes::copy_from_slice(self.memory.get(input_range));
+        let (target, caller, value) = match scheme {
+            CallScheme::ExtDelegateCall => (
+                self.contract.address,
+                self.contract.caller,
+                self.contract.value,
+            ),
+            _ => (address, self.contract.address, value),
+        };
+        Err(Halt::Call(Box::new(CallInputs {
+            scheme,
+            target,
+            code_address: address,
+            caller,
+            value,
+            transfer: transfers_value,
+            input,
+            gas_limit,
+            is_static: self.is_static || scheme == CallScheme::ExtStaticCall,
+            depth: self.depth + 1,
+        })))
+    }
+
+    fn selfdestruct<H: Host + ?Sized>(&mut self, host: &mut H) -> Result<(), Halt> {
+        self.require_non_static()?;
+        let target = u256_to_address(self.stack.pop()?);
+        let result = host.selfdestruct(self.contract.address, target)?;
+        let mut cost = if self.fork >= Fork::Tangerine {
+            gas::SELFDESTRUCT
+        } else {
+            0
+        };
+        let new_account = if self.fork >= Fork::SpuriousDragon {
+            !result.data.balance.is_zero() && !result.data.target_exists
+        } else {
+            self.fork >= Fork::Tangerine && !result.data.target_exists
+        };
+        if new_account {
+            cost += gas::NEW_ACCOUNT;
+        }
+        if self.fork >= Fork::Berlin && result.is_cold {
+            cost += gas::COLD_ACCOUNT_ACCESS;
+        }
+        self.charge(cost)?;
+        if self.fork < Fork::London && !result.data.previously_destroyed {
+            self.gas.record_refund(gas::SELFDESTRUCT_REFUND);
+        }
+        Err(ExitStatus::SelfDestruct.into())
+    }
+
+    fn eof(&self) -> &Eof {
+        self.contract.code.eof().expect("EOF frames run EOF code")
+    }
+
+    fn immediate_u16(&self) -> usize {
+        let immediate = &self.contract.code.padded()[self.pc..];
+        u16::from_be_bytes([immediate[0], immediate[1]]) as usize
+    }
+
+    /// The signed immediate `at` bytes into the instruction's immediates.
+    fn immediate_i16(&self, at: usize) -> i16 {
+        let immediate = &self.contract.code.padded()[self.pc + at..];
+        i16::from_be_bytes([immediate[0], immediate[1]])
+    }
+
+    /// Where a relative jump with `immediates` bytes of immediates goes, its offset
+    /// counting from the end of the instruction.
+    fn relative_target(&self, immediates: usize, offset: i16) -> usize {
+        (self.pc + immediates).wrapping_add_signed(offset as isize)
+    }
+
+    /// Gas a call or creation can be given out of `available`.
+    fn forwardable_gas(&self, available: u64) -> u64 {
+        if self.fork >= Fork::Tangerine {
+            available - available / 64
+        } else {
+            available
+        }
+    }
+}
+
+/// Words covered by a length operand, saturating for lengths no memory could hold.
+fn words(len: U256) -> u64 {
+    gas::words(u64::try_from(len).unwrap_or(u64::MAX / 2))
+}
+
+/// The word at `offset` of `source`, with zeroes past its end.
+fn load_word(source: &[u8], offset: U256) -> U256 {
+    let mut word = [0; 32];
+    if offset < U256::from(source.len()) {
+        let offset = offset.as_usize();
+        let len = (source.len() - offset).min(32);
+        word[..len].copy_from_slice(&source[offset..offset + len]);
+    }
+    U256::from_big_endian(&word)
+}
+
+fn saturating_u64(len: U256) -> u64 {
+    u64::try_from(len).unwrap_or(u64::MAX)
+}
+
+fn bool_to_u256(value: bool) -> U256 {
+    U256::from(value as u8)
+}
+
+fn u256_to_h256(value: U256) -> H256 {
+    let mut bytes = [0; 32];
+    value.to_big_endian(&mut bytes);
+    H256(bytes)
+}
+
+fn is_negative(value: U256) -> bool {
+    value.bit(255)
+}
+
+fn negate(value: U256) -> U256 {
+    (!value).overflowing_add(U256::one()).0
+}
+
+fn abs(value: U256) -> U256 {
+    if is_negative(value) {
+        negate(value)
+    } else {
+        value
+    }
+}
+
+fn signed_div(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return b;
+    }
+    let quotient = abs(a) / abs(b);
+    if is_negative(a) != is_negative(b) {
+        negate(quotient)
+    } else {
+        quotient
+    }
+}
+
+fn signed_mod(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return b;
+    }
+    let remainder = abs(a) % abs(b);
+    if is_negative(a) {
+        negate(remainder)
+    } else {
+        remainder
+    }
+}
+
+fn signed_lt(a: U256, b: U256) -> bool {
+    let sign = U256::one() << 255;
+    (a ^ sign) < (b ^ sign)
+}
+
+/// Extends the sign of the low `size + 1` bytes of `value`.
+fn sign_extend(size: U256, value: U256) -> U256 {
+    if size >= U256::from(31) {
+        return value;
+    }
+    let sign_bit = size.as_usize() * 8 + 7;
+    let mask = (U256::one() << (sign_bit + 1)) - 1;
+    if value.bit(sign_bit) {
+        value | !mask
+    } else {
+        value & mask
+    }
+}
@@ -1,14 +1,38 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
-}
+//! The Ethereum Virtual Machine.
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+pub mod block;
+pub mod bytecode;
+pub mod config;
+pub mod db;
+pub mod env;
+pub mod eof;
+mod fork;
+mod frame;
+pub mod gas;
+pub mod host;
+pub mod interpreter;
+pub mod memory;
+pub mod opcode;
+pub mod parallel;
+pub mod precompiles;
+pub mod stack;
+pub mod state;
+pub mod tracer;
+pub mod transaction;
 
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
-    }
-}
+pub use block::{begin_block, execute_block, BlockResult};
+pub use bytecode::{Bytecode, BytecodeCache};
+pub use config::ChainConfig;
+pub use db::MemoryDb;
+pub use env::{BlockEnv, Env, TxEnv};
+pub use fork::Fork;
+pub use frame::{create2_address, eofcreate_address};
+pub use host::{Database, Host};
+pub use interpreter::{Action, ExitStatus, FrameResult, Interpreter};
+pub use parallel::execute_block_parallel;
+pub use state::JournaledState;
+pub use tracer::{Eip3155Tracer, NoopTracer, Tracer};
+pub use transaction::{
+    execute_message_with_tracer, execute_transaction, execute_transaction_with_tracer,
+    ExecutionResult, InvalidTransaction,
+};
@@ -0,0 +1,70 @@
+use std::ops::Range;
+
+use ethereum_types::U256;
+
+/// The byte-addressed memory of a frame. It only grows, a word at a time, and its
+/// expansion is paid for by the frame before it happens.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Memory {
+    data: Vec<u8>,
+}
+
+impl Memory {
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn words(&self) -> usize {
+        self.data.len() / 32
+    }
+
+    /// Grows the memory to `words` words if it is smaller.
+    pub fn resize(&mut self, words: usize) {
+        if words > self.words() {
+            self.data.resize(words * 32, 0);
+        }
+    }
+
+    pub fn get(&self, range: Range<usize>) -> &[u8] {
+        &self.data[range]
+    }
+
+    pub fn word(&self, offset: usize) -> U256 {
+        U256::from_big_endian(&self.data[offset..offset + 32])
+    }
+
+    pub fn set(&mut self, offset: usize, bytes: &[u8]) {
+        self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    pub fn set_word(&mut self, offset: usize, value: U256) {
+        value.to_big_endian(&mut self.data[offset..offset + 32]);
+    }
+
+    /// Copies `range.len()` bytes of `source` from `source_offset` to `range`, filling
+    /// in zeroes past the end of `source`.
+    pub fn set_data(&mut self, range: Range<usize>, source: &[u8], source_offset: U256) {
+        let start = if source_offset > U256::from(source.len()) {
+            source.len()
+        } else {
+            source_offset.as_usize()
+        };
+        let available = &source[start..];
+        let copied = available.len().min(range.len());
+        let (copy, zero) = self.data[range].split_at_mut(copied);
+        copy.copy_from_slice(&available[..copied]);
+        zero.fill(0);
+    }
+
+    pub fn copy_within(&mut self, source: usize, destination: usize, len: usize) {
+        self.data.copy_within(source..source + len, destination);
+    }
+}
@@ -0,0 +1,364 @@
+//! Opcode values and mnemonics.
+
+pub const STOP: u8 = 0x00;
+pub const ADD: u8 = 0x01;
+pub const MUL: u8 = 0x02;
+pub const SUB: u8 = 0x03;
+pub const DIV: u8 = 0x04;
+pub const SDIV: u8 = 0x05;
+pub const MOD: u8 = 0x06;
+pub const SMOD: u8 = 0x07;
+pub const ADDMOD: u8 = 0x08;
+pub const MULMOD: u8 = 0x09;
+pub const EXP: u8 = 0x0a;
+pub const SIGNEXTEND: u8 = 0x0b;
+pub const LT: u8 = 0x10;
+pub const GT: u8 = 0x11;
+pub const SLT: u8 = 0x12;
+pub const SGT: u8 = 0x13;
+pub const EQ: u8 = 0x14;
+pub const ISZERO: u8 = 0x15;
+pub const AND: u8 = 0x16;
+pub const OR: u8 = 0x17;
+pub const XOR: u8 = 0x18;
+pub const NOT: u8 = 0x19;
+pub const BYTE: u8 = 0x1a;
+pub const SHL: u8 = 0x1b;
+pub const SHR: u8 = 0x1c;
+pub const SAR: u8 = 0x1d;
+pub const KECCAK256: u8 = 0x20;
+pub const ADDRESS: u8 = 0x30;
+pub const BALANCE: u8 = 0x31;
+pub const ORIGIN: u8 = 0x32;
+pub const CALLER: u8 = 0x33;
+pub const CALLVALUE: u8 = 0x34;
+pub const CALLDATALOAD: u8 = 0x35;
+pub const CALLDATASIZE: u8 = 0x36;
+pub const CALLDATACOPY: u8 = 0x37;
+pub const CODESIZE: u8 = 0x38;
+pub const CODECOPY: u8 = 0x39;
+pub const GASPRICE: u8 = 0x3a;
+pub const EXTCODESIZE: u8 = 0x3b;
+pub const EXTCODECOPY: u8 = 0x3c;
+pub const RETURNDATASIZE: u8 = 0x3d;
+pub const RETURNDATACOPY: u8 = 0x3e;
+pub const EXTCODEHASH: u8 = 0x3f;
+pub const BLOCKHASH: u8 = 0x40;
+pub const COINBASE: u8 = 0x41;
+pub const TIMESTAMP: u8 = 0x42;
+pub const NUMBER: u8 = 0x43;
+/// `DIFFICULTY` before the merge.
+pub const PREVRANDAO: u8 = 0x44;
+pub const GASLIMIT: u8 = 0x45;
+pub const CHAINID: u8 = 0x46;
+pub const SELFBALANCE: u8 = 0x47;
+pub const BASEFEE: u8 = 0x48;
+pub const BLOBHASH: u8 = 0x49;
+pub const BLOBBASEFEE: u8 = 0x4a;
+pub const POP: u8 = 0x50;
+pub const MLOAD: u8 = 0x51;
+pub const MSTORE: u8 = 0x52;
+pub const MSTORE8: u8 = 0x53;
+pub const SLOAD: u8 = 0x54;
+pub const SSTORE: u8 = 0x55;
+pub const JUMP: u8 = 0x56;
+pub const JUMPI: u8 = 0x57;
+pub const PC: u8 = 0x58;
+pub const MSIZE: u8 = 0x59;
+pub const GAS: u8 = 0x5a;
+pub const JUMPDEST: u8 = 0x5b;
+pub const TLOAD: u8 = 0x5c;
+pub const TSTORE: u8 = 0x5d;
+pub const MCOPY: u8 = 0x5e;
+pub const PUSH0: u8 = 0x5f;
+pub const PUSH1: u8 = 0x60;
+pub const PUSH2: u8 = 0x61;
+pub const PUSH3: u8 = 0x62;
+pub const PUSH4: u8 = 0x63;
+pub const PUSH5: u8 = 0x64;
+pub const PUSH6: u8 = 0x65;
+pub const PUSH7: u8 = 0x66;
+pub const PUSH8: u8 = 0x67;
+pub const PUSH9: u8 = 0x68;
+pub const PUSH10: u8 = 0x69;
+pub const PUSH11: u8 = 0x6a;
+pub const PUSH12: u8 = 0x6b;
+pub const PUSH13: u8 = 0x6c;
+pub const PUSH14: u8 = 0x6d;
+pub const PUSH15: u8 = 0x6e;
+pub const PUSH16: u8 = 0x6f;
+pub const PUSH17: u8 = 0x70;
+pub const PUSH18: u8 = 0x71;
+pub const PUSH19: u8 = 0x72;
+pub const PUSH20: u8 = 0x73;
+pub const PUSH21: u8 = 0x74;
+pub const PUSH22: u8 = 0x75;
+pub const PUSH23: u8 = 0x76;
+pub const PUSH24: u8 = 0x77;
+pub const PUSH25: u8 = 0x78;
+pub const PUSH26: u8 = 0x79;
+pub const PUSH27: u8 = 0x7a;
+pub const PUSH28: u8 = 0x7b;
+pub const PUSH29: u8 = 0x7c;
+pub const PUSH30: u8 = 0x7d;
+pub const PUSH31: u8 = 0x7e;
+pub const PUSH32: u8 = 0x7f;
+pub const DUP1: u8 = 0x80;
+pub const DUP2: u8 = 0x81;
+pub const DUP3: u8 = 0x82;
+pub const DUP4: u8 = 0x83;
+pub const DUP5: u8 = 0x84;
+pub const DUP6: u8 = 0x85;
+pub const DUP7: u8 = 0x86;
+pub const DUP8: u8 = 0x87;
+pub const DUP9: u8 = 0x88;
+pub const DUP10: u8 = 0x89;
+pub const DUP11: u8 = 0x8a;
+pub const DUP12: u8 = 0x8b;
+pub const DUP13: u8 = 0x8c;
+pub const DUP14: u8 = 0x8d;
+pub const DUP15: u8 = 0x8e;
+pub const DUP16: u8 = 0x8f;
+pub const SWAP1: u8 = 0x90;
+pub const SWAP2: u8 = 0x91;
+pub const SWAP3: u8 = 0x92;
+pub const SWAP4: u8 = 0x93;
+pub const SWAP5: u8 = 0x94;
+pub const SWAP6: u8 = 0x95;
+pub const SWAP7: u8 = 0x96;
+pub const SWAP8: u8 = 0x97;
+pub const SWAP9: u8 = 0x98;
+pub const SWAP10: u8 = 0x99;
+pub const SWAP11: u8 = 0x9a;
+pub const SWAP12: u8 = 0x9b;
+pub const SWAP13: u8 = 0x9c;
+pub const SWAP14: u8 = 0x9d;
+pub const SWAP15: u8 = 0x9e;
+pub const SWAP16: u8 = 0x9f;
+pub const LOG0: u8 = 0xa0;
+pub const LOG1: u8 = 0xa1;
+pub const LOG2: u8 = 0xa2;
+pub const LOG3: u8 = 0xa3;
+pub const LOG4: u8 = 0xa4;
+pub const DATALOAD: u8 = 0xd0;
+pub const DATALOADN: u8 = 0xd1;
+pub const DATASIZE: u8 = 0xd2;
+pub const DATACOPY: u8 = 0xd3;
+pub const RJUMP: u8 = 0xe0;
+pub const RJUMPI: u8 = 0xe1;
+pub const RJUMPV: u8 = 0xe2;
+pub const CALLF: u8 = 0xe3;
+pub const RETF: u8 = 0xe4;
+pub const JUMPF: u8 = 0xe5;
+pub const DUPN: u8 = 0xe6;
+pub const SWAPN: u8 = 0xe7;
+pub const EXCHANGE: u8 = 0xe8;
+pub const EOFCREATE: u8 = 0xec;
+pub const RETURNCONTRACT: u8 = 0xee;
+pub const CREATE: u8 = 0xf0;
+pub const CALL: u8 = 0xf1;
+pub const CALLCODE: u8 = 0xf2;
+pub const RETURN: u8 = 0xf3;
+pub const DELEGATECALL: u8 = 0xf4;
+pub const CREATE2: u8 = 0xf5;
+pub const RETURNDATALOAD: u8 = 0xf7;
+pub const EXTCALL: u8 = 0xf8;
+pub const EXTDELEGATECALL: u8 = 0xf9;
+pub const STATICCALL: u8 = 0xfa;
+pub const EXTSTATICCALL: u8 = 0xfb;
+pub const REVERT: u8 = 0xfd;
+pub const INVALID: u8 = 0xfe;
+pub const SELFDESTRUCT: u8 = 0xff;
+
+/// Mnemonics by opcode, `None` for unassigned opcodes.
+const NAMES: [Option<&str>; 256] = {
+    let mut names = [None; 256];
+    names[STOP as usize] = Some("STOP");
+    names[ADD as usize] = Some("ADD");
+    names[MUL as usize] = Some("MUL");
+    names[SUB as usize] = Some("SUB");
+    names[DIV as usize] = Some("DIV");
+    names[SDIV as usize] = Some("SDIV");
+    names[MOD as usize] = Some("MOD");
+    names[SMOD as usize] = Some("SMOD");
+    names[ADDMOD as usize] = Some("ADDMOD");
+    names[MULMOD as usize] = Some("MULMOD");
+    names[EXP as usize] = Some("EXP");
+    names[SIGNEXTEND as usize] = Some("SIGNEXTEND");
+    names[LT as usize] = Some("LT");
+    names[GT as usize] = Some("GT");
+    names[SLT as usize] = Some("SLT");
+    names[SGT as usize] = Some("SGT");
+    names[EQ as usize] = Some("EQ");
+    names[ISZERO as usize] = Some("ISZERO");
+    names[AND as usize] = Some("AND");
+    names[OR as usize] = Some("OR");
+    names[XOR as usize] = Some("XOR");
+    names[NOT as usize] = Some("NOT");
+    names[BYTE as usize] = Some("BYTE");
+    names[SHL as usize] = Some("SHL");
+    names[SHR as usize] = Some("SHR");
+    names[SAR as usize] = Some("SAR");
+    names[KECCAK256 as usize] = Some("KECCAK256");
+    names[ADDRESS as usize] = Some("ADDRESS");
+    names[BALANCE as usize] = Some("BALANCE");
+    names[ORIGIN as usize] = Some("ORIGIN");
+    names[CALLER as usize] = Some("CALLER");
+    names[CALLVALUE as usize] = Some("CALLVALUE");
+    names[CALLDATALOAD as usize] = Some("CALLDATALOAD");
+    names[CALLDATASIZE as usize] = Some("CALLDATASIZE");
+    names[CALLDATACOPY as usize] = Some("CALLDATACOPY");
+    names[CODESIZE as usize] = Some("CODESIZE");
+    names[CODECOPY as usize] = Some("CODECOPY");
+    names[GASPRICE as usize] = Some("GASPRICE");
+    names[EXTCODESIZE as usize] = Some("EXTCODESIZE");
+    names[EXTCODECOPY as usize] = Some("EXTCODECOPY");
+    names[RETURNDATASIZE as usize] = Some("RETURNDATASIZE");
+    names[RETURNDATACOPY as usize] = Some("RETURNDATACOPY");
+    names[EXTCODEHASH as usize] = Some("EXTCODEHASH");
+    names[BLOCKHASH as usize] = Some("BLOCKHASH");
+    names[COINBASE as usize] = Some("COINBASE");
+    names[TIMESTAMP as usize] = Some("TIMESTAMP");
+    names[NUMBER as usize] = Some("NUMBER");
+    // Named as geth still names it in traces
+    names[PREVRANDAO as usize] = Some("DIFFICULTY");
+    names[GASLIMIT as usize] = Some("GASLIMIT");
+    names[CHAINID as usize] = Some("CHAINID");
+    names[SELFBALANCE as usize] = Some("SELFBALANCE");
+    names[BASEFEE as usize] = Some("BASEFEE");
+    names[BLOBHASH as usize] = Some("BLOBHASH");
+    names[BLOBBASEFEE as usize] = Some("BLOBBASEFEE");
+    names[POP as usize] = Some("POP");
+    names[MLOAD as usize] = Some("MLOAD");
+    names[MSTORE as usize] = Some("MSTORE");
+    names[MSTORE8 as usize] = Some("MSTORE8");
+    names[SLOAD as usize] = Some("SLOAD");
+    names[SSTORE as usize] = Some("SSTORE");
+    names[JUMP as usize] = Some("JUMP");
+    names[JUMPI as usize] = Some("JUMPI");
+    names[PC as usize] = Some("PC");
+    names[MSIZE as usize] = Some("MSIZE");
+    names[GAS as usize] = Some("GAS");
+    names[JUMPDEST as usize] = Some("JUMPDEST");
+    names[TLOAD as usize] = Some("TLOAD");
+    names[TSTORE as usize] = Some("TSTORE");
+    names[MCOPY as usize] = Some("MCOPY");
+    names[PUSH0 as usize] = Some("PUSH0");
+    names[PUSH1 as usize] = Some("PUSH1");
+    names[PUSH2 as usize] = Some("PUSH2");
+    names[PUSH3 as usize] = Some("PUSH3");
+    names[PUSH4 as usize] = Some("PUSH4");
+    names[PUSH5 as usize] = Some("PUSH5");
+    names[PUSH6 as usize] = Some("PUSH6");
+    names[PUSH7 as usize] = Some("PUSH7");
+    names[PUSH8 as usize] = Some("PUSH8");
+    names[PUSH9 as usize] = Some("PUSH9");
+    names[PUSH10 as usize] = Some("PUSH10");
+    names[PUSH11 as usize] = Some("PUSH11");
+    names[PUSH12 as usize] = Some("PUSH12");
+    names[PUSH13 as usize] = Some("PUSH13");
+    names[PUSH14 as usize] = Some("PUSH14");
+    names[PUSH15 as usize] = Some("PUSH15");
+    names[PUSH16 as usize] = Some("PUSH16");
+    names[PUSH17 as usize] = Some("PUSH17");
+    names[PUSH18 as usize] = Some("PUSH18");
+    names[PUSH19 as usize] = Some("PUSH19");
+    names[PUSH20 as usize] = Some("PUSH20");
+    names[PUSH21 as usize] = Some("PUSH21");
+    names[PUSH22 as usize] = Some("PUSH22");
+    names[PUSH23 as usize] = Some("PUSH23");
+    names[PUSH24 as usize] = Some("PUSH24");
+    names[PUSH25 as usize] = Some("PUSH25");
+    names[PUSH26 as usize] = Some("PUSH26");
+    names[PUSH27 as usize] = Some("PUSH27");
+    names[PUSH28 as usize] = Some("PUSH28");
+    names[PUSH29 as usize] = Some("PUSH29");
+    names[PUSH30 as usize] = Some("PUSH30");
+    names[PUSH31 as usize] = Some("PUSH31");
+    names[PUSH32 as usize] = Some("PUSH32");
+    names[DUP1 as usize] = Some("DUP1");
+    names[DUP2 as usize] = Some("DUP2");
+    names[DUP3 as usize] = Some("DUP3");
+    names[DUP4 as usize] = Some("DUP4");
+    names[DUP5 as usize] = Some("DUP5");
+    names[DUP6 as usize] = Some("DUP6");
+    names[DUP7 as usize] = Some("DUP7");
+    names[DUP8 as usize] = Some("DUP8");
+    names[DUP9 as usize] = Some("DUP9");
+    names[DUP10 as usize] = Some("DUP10");
+    names[DUP11 as usize] = Some("DUP11");
+    names[DUP12 as usize] = Some("DUP12");
+    names[DUP13 as usize] = Some("DUP13");
+    names[DUP14 as usize] = Some("DUP14");
+    names[DUP15 as usize] = Some("DUP15");
+    names[DUP16 as usize] = Some("DUP16");
+    names[SWAP1 as usize] = Some("SWAP1");
+    names[SWAP2 as usize] = Some("SWAP2");
+    names[SWAP3 as usize] = Some("SWAP3");
+    names[SWAP4 as usize] = Some("SWAP4");
+    names[SWAP5 as usize] = Some("SWAP5");
+    names[SWAP6 as usize] = Some("SWAP6");
+    names[SWAP7 as usize] = Some("SWAP7");
+    names[SWAP8 as usize] = Some("SWAP8");
+    names[SWAP9 as usize] = Some("SWAP9");
+    names[SWAP10 as usize] = Some("SWAP10");
+    names[SWAP11 as usize] = Some("SWAP11");
+    names[SWAP12 as usize] = Some("SWAP12");
+    names[SWAP13 as usize] = Some("SWAP13");
+    names[SWAP14 as usize] = Some("SWAP14");
+    names[SWAP15 as usize] = Some("SWAP15");
+    names[SWAP16 as usize] = Some("SWAP16");
+    names[LOG0 as usize] = Some("LOG0");
+    names[LOG1 as usize] = Some("LOG1");
+    names[LOG2 as usize] = Some("LOG2");
+    names[LOG3 as usize] = Some("LOG3");
+    names[LOG4 as usize] = Some("LOG4");
+    names[DATALOAD as usize] = Some("DATALOAD");
+    names[DATALOADN as usize] = Some("DATALOADN");
+    names[DATASIZE as usize] = Some("DATASIZE");
+    names[DATACOPY as usize] = Some("DATACOPY");
+    names[RJUMP as usize] = Some("RJUMP");
+    names[RJUMPI as usize] = Some("RJUMPI");
+    names[RJUMPV as usize] = Some("RJUMPV");
+    names[CALLF as usize] = Some("CALLF");
+    names[RETF as usize] = Some("RETF");
+    names[JUMPF as usize] = Some("JUMPF");
+    names[DUPN as usize] = Some("DUPN");
+    names[SWAPN as usize] = Some("SWAPN");
+    names[EXCHANGE as usize] = Some("EXCHANGE");
+    names[EOFCREATE as usize] = Some("EOFCREATE");
+    names[RETURNCONTRACT as usize] = Some("RETURNCONTRACT");
+    names[CREATE as usize] = Some("CREATE");
+    names[CALL as usize] = Some("CALL");
+    names[CALLCODE as usize] = Some("CALLCODE");
+    names[RETURN as usize] = Some("RETURN");
+    names[DELEGATECALL as usize] = Some("DELEGATECALL");
+    names[CREATE2 as usize] = Some("CREATE2");
+    names[RETURNDATALOAD as usize] = Some("RETURNDATALOAD");
+    names[EXTCALL as usize] = Some("EXTCALL");
+    names[EXTDELEGATECALL as usize] = Some("EXTDELEGATECALL");
+    names[STATICCALL as usize] = Some("STATICCALL");
+    names[EXTSTATICCALL as usize] = Some("EXTSTATICCALL");
+    names[REVERT as usize] = Some("REVERT");
+    names[INVALID as usize] = Some("INVALID");
+    names[SELFDESTRUCT as usize] = Some("SELFDESTRUCT");
+    names
+};
+
+/// Mnemonic of `opcode`, as printed by execution traces. Unassigned opcodes are shown
+/// the way geth shows them.
+pub fn name(opcode: u8) -> String {
+    match NAMES[opcode as usize] {
+        Some(name) => name.to_string(),
+        None => format!("opcode {opcode:#x} not defined"),
+    }
+}
+
+/// Number of immediate bytes following `opcode` in legacy code. Those of the opcodes
+/// only EOF code has are given by [`crate::eof::immediate_size`].
+pub fn immediate_size(opcode: u8) -> usize {
+    match opcode {
+        PUSH1..=PUSH32 => (opcode - PUSH0) as usize,
+        _ => 0,
+    }
+}
@@ -0,0 +1,379 @@
+//! Block-STM style parallel execution of a block's transactions.
+//!
+//! Transactions run speculatively on worker threads against the state committed so
+//! far, recording every account and storage value they read. They are then committed
+//! in block order, each only if what it read still holds in the committed state, and
+//! run again otherwise, so the outcome is the one sequential execution gives.
+//!
+//! Every transaction pays the coinbase, which would make each depend on the one before
+//! it, so speculative runs set their fees aside and they are paid on commit. A run that
+//! read the coinbase account itself is only kept if it ran on the whole committed state
+//! with the fee paid as usual, which the transaction next in line to commit always does.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use anyhow::{anyhow, Context};
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use reec_core::types::{AccountInfo, Block, BlockNumber, Transaction};
+
+use crate::{
+    begin_block,
+    block::{
+        block_reward, check_block_room, finish_block, transaction_context, transaction_receipt,
+    },
+    state::TransactionChanges,
+    transaction::{execute_transaction, ExecutionResult},
+    BlockResult, ChainConfig, Database, Host, JournaledState,
+};
+
+/// Values a speculative run read from the committed state.
+#[derive(Debug, Default)]
+struct ReadSet {
+    accounts: HashMap<Address, Option<AccountInfo>>,
+    storage: HashMap<(Address, H256), U256>,
+}
+
+/// The committed state as the database of a speculative run, recording what it reads.
+/// Code and block hashes never change within a block, so their reads are not recorded.
+struct SpeculativeDb<'a, DB> {
+    committed: &'a JournaledState<DB>,
+    reads: RefCell<ReadSet>,
+}
+
+impl<DB: Database> Database for SpeculativeDb<'_, DB> {
+    fn account(&self, address: Address) -> anyhow::Result<Option<AccountInfo>> {
+        let info = self.committed.peek_account(address)?;
+        self.reads
+            .borrow_mut()
+            .accounts
+            .insert(address, info.clone());
+        Ok(info)
+    }
+
+    fn storage(&self, address: Address, slot: H256) -> anyhow::Result<U256> {
+        let value = self.committed.peek_storage(address, slot)?;
+        self.reads
+            .borrow_mut()
+            .storage
+            .insert((address, slot), value);
+        Ok(value)
+    }
+
+    fn code(&self, code_hash: H256) -> anyhow::Result<Bytes> {
+        self.committed.peek_code(code_hash)
+    }
+
+    fn block_hash(&self, number: BlockNumber) -> anyhow::Result<Option<H256>> {
+        self.committed.db().block_hash(number)
+    }
+}
+
+/// A transaction's speculative run.
+struct Speculation {
+    reads: ReadSet,
+    /// The fee set aside for the coinbase, `None` if the run paid it.
+    deferred_fee: Option<U256>,
+    outcome: anyhow::Result<(ExecutionResult, TransactionChanges)>,
+}
+
+impl Speculation {
+    /// Whether the run would go the same way on `committed`, so that its changes can
+    /// be taken over.
+    fn holds<DB: Database>(&self, committed: &JournaledState<DB>) -> anyhow::Result<bool> {
+        if self.deferred_fee.is_some() && self.reads_coinbase(committed) {
+            return Ok(false);
+        }
+        for (address, info) in &self.reads.accounts {
+            if committed.peek_account(*address)? != *info {
+                return Ok(false);
+            }
+        }
+        for ((address, slot), value) in &self.reads.storage {
+            if committed.peek_storage(*address, *slot)? != *value {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn reads_coinbase<DB: Database>(&self, committed: &JournaledState<DB>) -> bool {
+        self.reads
+            .accounts
+            .contains_key(&committed.env().block.coinbase)
+    }
+}
+
+/// Executes `block` like [`crate::execute_block`], running its transactions on up to
+/// `threads` threads. The result and errors are the same as sequential execution's.
+pub fn execute_block_parallel<DB: Database + Sync>(
+    db: DB,
+    config: &ChainConfig,
+    block: &Block,
+    threads: usize,
+) -> anyhow::Result<BlockResult> {
+    let header = &block.header;
+    let fork = config.fork(header.number, header.timestamp);
+    let mut state = begin_block(db, config, header)?;
+    let txs = &block.body.transactions;
+
+    let mut speculations: Vec<Option<Speculation>> = txs.iter().map(|_| None).collect();
+    let mut receipts = Vec::with_capacity(txs.len());
+    let (mut gas_used, mut blob_gas_used) = (0, 0);
+    let mut next = 0;
+    while next < txs.len() {
+        // Runs that read the coinbase wait for their turn to commit, as only then can
+        // they see its balance
+        let mut pending = Vec::new();
+        for (index, speculation) in speculations.iter().enumerate().skip(next) {
+            let run = match speculation {
+                None => true,
+                Some(speculation) if speculation.reads_coinbase(&state) => index == next,
+                Some(speculation) => !speculation.holds(&state)?,
+            };
+            if run {
+                pending.push(index);
+            }
+        }
+        for (index, speculation) in speculate_all(&state, txs, &pending, next, threads) {
+            speculations[index] = Some(speculation);
+        }
+
+        // The run of the transaction next in line read the committed state as it is, so
+        // at least that one commits
+        while let Some(speculation) = speculations.get_mut(next).and_then(Option::take) {
+            if !speculation.holds(&state)? {
+                break;
+            }
+            let tx = &txs[next];
+            let context = || transaction_context(next, tx);
+            let blob_gas = check_block_room(header, fork, tx, gas_used, blob_gas_used)
+                .with_context(context)?;
+            let (result, changes) = speculation.outcome.with_context(context)?;
+            state.merge(changes)?;
+            if let Some(fee) = speculation.deferred_fee {
+                state.pay_fee(fee)?;
+                state.finalize_transaction();
+            }
+            gas_used += result.gas_used;
+            blob_gas_used += blob_gas;
+            receipts.push(transaction_receipt(&state, tx, &result, gas_used)?);
+            next += 1;
+        }
+    }
+    finish_block(
+        state,
+        config,
+        block,
+        receipts,
+        gas_used,
+        blob_gas_used,
+        block_reward(fork),
+    )
+}
+
+/// Runs the transactions at `indices` on `committed`, spread over `threads` threads.
+/// Only the one at `next` pays its fee, the others set it aside.
+fn speculate_all<DB: Database + Sync>(
+    committed: &JournaledState<DB>,
+    txs: &[Transaction],
+    indices: &[usize],
+    next: usize,
+    threads: usize,
+) -> Vec<(usize, Speculation)> {
+    let threads = threads.clamp(1, indices.len().max(1));
+    if threads == 1 {
+        return indices
+            .iter()
+            .map(|&index| (index, speculate(committed, &txs[index], index != next)))
+            .collect();
+    }
+    let cursor = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    while let Some(&index) = indices.get(cursor.fetch_add(1, Ordering::Relaxed)) {
+                        done.push((index, speculate(committed, &txs[index], index != next)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("speculative execution panicked"))
+            .collect()
+    })
+}
+
+fn speculate<DB: Database>(
+    committed: &JournaledState<DB>,
+    tx: &Transaction,
+    defer_fee: bool,
+) -> Speculation {
+    let db = SpeculativeDb {
+        committed,
+        reads: RefCell::default(),
+    };
+    let mut state = JournaledState::new(db, committed.env().clone())
+        .with_bytecode_cache(committed.bytecode_cache().clone());
+    if defer_fee {
+        state.defer_fees();
+    }
+    let result = tx
+        .sender()
+        .ok_or_else(|| anyhow!("invalid signature"))
+        .and_then(|sender| execute_transaction(&mut state, tx, sender));
+    let deferred_fee = state.deferred_fees();
+    let (db, changes) = state.into_changes();
+    Speculation {
+        reads: db.reads.into_inner(),
+        deferred_fee,
+        outcome: result.map(|result| (result, changes)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use reec_core::{
+        crypto,
+        types::{BlockBody, BlockHeader, LegacyTransaction, TxKind},
+    };
+
+    use super::*;
+    use crate::{create2_address, execute_block, Fork, MemoryDb};
+
+    /// A block whose transactions conflict every way they can: over a sender, a storage
+    /// slot, the coinbase's balance and code deployed within the block.
+    fn conflicting_block(fork: Fork) -> (MemoryDb, Block) {
+        let keys: Vec<[u8; 32]> = (1..=4).map(|key| [key; 32]).collect();
+        let senders: Vec<_> = keys
+            .iter()
+            .map(|key| crypto::secret_key_to_address(key).unwrap())
+            .collect();
+        let (counter, reader, factory) = (
+            Address::repeat_byte(0xc0),
+            Address::repeat_byte(0xc1),
+            Address::repeat_byte(0xc2),
+        );
+        let mut db = MemoryDb::default();
+        for sender in &senders {
+            db.insert_account(*sender, 0, U256::exp10(18), Bytes::new());
+        }
+        // SSTORE(0, SLOAD(0) + 1)
+        db.insert_account(
+            counter,
+            1,
+            U256::zero(),
+            Bytes::from_static(&[0x60, 0x00, 0x54, 0x60, 0x01, 0x01, 0x60, 0x00, 0x55, 0x00]),
+        );
+        // SSTORE(0, BALANCE(COINBASE))
+        db.insert_account(
+            reader,
+            1,
+            U256::zero(),
+            Bytes::from_static(&[0x41, 0x31, 0x60, 0x00, 0x55, 0x00]),
+        );
+        // CREATE2 of the calldata with salt 0
+        db.insert_account(
+            factory,
+            1,
+            U256::zero(),
+            Bytes::from_static(&[0x36, 0x5f, 0x5f, 0x37, 0x5f, 0x36, 0x5f, 0x5f, 0xf5, 0x00]),
+        );
+        // Deploys SSTORE(1, 7)
+        let init_code = Bytes::from_static(&[
+            0x65, 0x60, 0x07, 0x60, 0x01, 0x55, 0x00, 0x5f, 0x52, 0x60, 0x06, 0x60, 0x1a, 0xf3,
+        ]);
+        let deployed = create2_address(factory, U256::zero(), &init_code);
+
+        let calls = [
+            (0, 0, counter, 0, Bytes::new()),
+            (1, 0, counter, 0, Bytes::new()),
+            (0, 1, Address::repeat_byte(0xee), 1000, Bytes::new()),
+            (2, 0, senders[3], 5, Bytes::new()),
+            (3, 0, reader, 0, Bytes::new()),
+            (2, 1, factory, 0, init_code),
+            (1, 1, deployed, 0, Bytes::new()),
+        ];
+        let chain_id = (fork >= Fork::SpuriousDragon).then_some(1);
+        let transactions = calls
+            .into_iter()
+            .map(|(sender, nonce, to, value, data)| {
+                let mut tx = Transaction::LegacyTransaction(LegacyTransaction {
+                    nonce,
+                    gas_price: U256::from(10),
+                    gas: 200_000,
+                    to: TxKind::Call(to),
+                    value: U256::from(value),
+                    data,
+                    ..Default::default()
+                });
+                tx.sign(&keys[sender], chain_id).unwrap();
+                tx
+            })
+            .collect();
+        let header = BlockHeader {
+            number: 1,
+            coinbase: Address::repeat_byte(0xcb),
+            gas_limit: 30_000_000,
+            base_fee_per_gas: (fork >= Fork::London).then_some(7),
+            parent_beacon_block_root: (fork >= Fork::Cancun).then_some(H256::zero()),
+            ..Default::default()
+        };
+        (
+            db,
+            Block {
+                header,
+                body: BlockBody {
+                    transactions,
+                    ..Default::default()
+                },
+            },
+        )
+    }
+
+    #[test]
+    fn matches_sequential_execution() {
+        for fork in [Fork::Homestead, Fork::Cancun] {
+            let config = ChainConfig::from_genesis(1, fork);
+            let (db, block) = conflicting_block(fork);
+            let sequential = execute_block(&db, &config, &block).unwrap();
+            assert!(
+                sequential
+                    .receipts
+                    .iter()
+                    .all(|receipt| receipt.succeeded() == Some(true))
+                    || fork < Fork::Constantinople
+            );
+            let counter = &sequential.state_diff[&Address::repeat_byte(0xc0)];
+            assert_eq!(counter.storage[&H256::zero()], U256::from(2));
+            for threads in [1, 2, 4, 8] {
+                assert_eq!(
+                    execute_block_parallel(&db, &config, &block, threads).unwrap(),
+                    sequential,
+                    "{fork}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn reports_the_first_invalid_transaction() {
+        let config = ChainConfig::from_genesis(1, Fork::Cancun);
+        let (db, mut block) = conflicting_block(Fork::Cancun);
+        block.body.transactions.swap(0, 2);
+        let sequential = execute_block(&db, &config, &block).unwrap_err();
+        for threads in [1, 2, 4, 8] {
+            let parallel = execute_block_parallel(&db, &config, &block, threads).unwrap_err();
+            assert_eq!(format!("{parallel:#}"), format!("{sequential:#}"));
+        }
+    }
+}
@@ -0,0 +1,349 @@
+//! 0x0b to 0x11: point addition, multi-scalar multiplication, the pairing check and
+//! mapping field elements to points on the BLS12-381 curve (EIP-2537).
+//!
+//! Field elements are 64 bytes, big-endian, with the top 16 bytes zero. G1 points are
+//! their x and y coordinates and G2 points the same over the quadratic extension field,
+//! each coordinate real part first. All zeros stands for the point at infinity.
+
+use ark_bls12_381::{g1, g2, Bls12_381, Fq, Fq2, Fr};
+use ark_ec::hashing::curve_maps::wb::{WBConfig, WBMap};
+use ark_ec::hashing::map_to_curve_hasher::MapToCurve;
+use ark_ec::pairing::Pairing;
+use ark_ec::short_weierstrass::{Affine, Projective, SWCurveConfig};
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::{One, PrimeField, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use bytes::Bytes;
+
+use super::{charge, PrecompileError, PrecompileOutput};
+
+/// Length of an encoded field element.
+const FP_LEN: usize = 64;
+/// Length of the zero padding at the front of an encoded field element.
+const FP_PADDING: usize = 16;
+const G1_LEN: usize = 2 * FP_LEN;
+const G2_LEN: usize = 4 * FP_LEN;
+const SCALAR_LEN: usize = 32;
+/// Length of a G1 and G2 point pair in the pairing check's input.
+const PAIR_LEN: usize = G1_LEN + G2_LEN;
+
+const G1_MSM_BASE: u64 = 12_000;
+const G2_MSM_BASE: u64 = 22_500;
+/// Thousandths of the base price that each pair of an MSM costs, by number of pairs. Larger
+/// MSMs cost the same per pair as the last entry.
+const G1_MSM_DISCOUNTS: [u16; 128] = [
+    1000, 949, 848, 797, 764, 750, 738, 728, 719, 712, 705, 698, 692, 687, 682, 677, 673, 669, 665,
+    661, 658, 654, 651, 648, 645, 642, 640, 637, 635, 632, 630, 627, 625, 623, 621, 619, 617, 615,
+    613, 611, 609, 608, 606, 604, 603, 601, 599, 598, 596, 595, 593, 592, 591, 589, 588, 586, 585,
+    584, 582, 581, 580, 579, 577, 576, 575, 574, 573, 572, 570, 569, 568, 567, 566, 565, 564, 563,
+    562, 561, 560, 559, 558, 557, 556, 555, 554, 553, 552, 551, 550, 549, 548, 547, 547, 546, 545,
+    544, 543, 542, 541, 540, 540, 539, 538, 537, 536, 536, 535, 534, 533, 532, 532, 531, 530, 529,
+    528, 528, 527, 526, 525, 525, 524, 523, 522, 522, 521, 520, 520, 519,
+];
+const G2_MSM_DISCOUNTS: [u16; 128] = [
+    1000, 1000, 923, 884, 855, 832, 812, 796, 782, 770, 759, 749, 740, 732, 724, 717, 711, 704,
+    699, 693, 688, 683, 679, 674, 670, 666, 663, 659, 655, 652, 649, 646, 643, 640, 637, 634, 632,
+    629, 627, 624, 622, 620, 618, 615, 613, 611, 609, 607, 606, 604, 602, 600, 598, 597, 595, 593,
+    592, 590, 589, 587, 586, 584, 583, 582, 580, 579, 578, 576, 575, 574, 573, 571, 570, 569, 568,
+    567, 566, 565, 563, 562, 561, 560, 559, 558, 557, 556, 555, 554, 553, 552, 552, 551, 550, 549,
+    548, 547, 546, 545, 545, 544, 543, 542, 541, 541, 540, 539, 538, 537, 537, 536, 535, 535, 534,
+    533, 532, 532, 531, 530, 530, 529, 528, 528, 527, 526, 526, 525, 524, 524,
+];
+
+const INVALID_INPUT_LENGTH: PrecompileError = PrecompileError::Failure("invalid input length");
+
+/// 0x0b: the sum of two G1 points, which need not be in the subgroup.
+pub(super) fn g1_add(input: &[u8], gas_limit: u64) -> Result<PrecompileOutput, PrecompileError> {
+    add(input, charge(375, gas_limit)?, G1_LEN, read_g1, encode_g1)
+}
+
+/// 0x0c
+pub(super) fn g1_msm(input: &[u8], gas_limit: u64) -> Result<PrecompileOutput, PrecompileError> {
+    let read = |input: &[u8]| in_subgroup(read_g1(input)?, "g1 point is not on correct subgroup");
+    let cost = msm_cost(
+        input.len() / (G1_LEN + SCALAR_LEN),
+        G1_MSM_BASE,
+        &G1_MSM_DISCOUNTS,
+    );
+    msm(input, charge(cost, gas_limit)?, G1_LEN, read, encode_g1)
+}
+
+/// 0x0d: the sum of two G2 points, which need not be in the subgroup.
+pub(super) fn g2_add(input: &[u8], gas_limit: u64) -> Result<PrecompileOutput, PrecompileError> {
+    add(input, charge(600, gas_limit)?, G2_LEN, read_g2, encode_g2)
+}
+
+/// 0x0e
+pub(super) fn g2_msm(input: &[u8], gas_limit: u64) -> Result<PrecompileOutput, PrecompileError> {
+    let read = |input: &[u8]| in_subgroup(read_g2(input)?, "g2 point is not on correct subgroup");
+    let cost = msm_cost(
+        input.len() / (G2_LEN + SCALAR_LEN),
+        G2_MSM_BASE,
+        &G2_MSM_DISCOUNTS,
+    );
+    msm(input, charge(cost, gas_limit)?, G2_LEN, read, encode_g2)
+}
+
+/// 0x0f: returns one if the product of the pairings of each G1 and G2 pair is the
+/// identity, and zero otherwise. Needs at least one pair.
+pub(super) fn pairing(input: &[u8], gas_limit: u64) -> Result<PrecompileOutput, PrecompileError> {
+    let pairs = (input.len() / PAIR_LEN) as u64;
+    let gas_used = charge(
+        32_600u64.saturating_mul(pairs).saturating_add(37_700),
+        gas_limit,
+    )?;
+    if input.is_empty() || !input.len().is_multiple_of(PAIR_LEN) {
+        return Err(INVALID_INPUT_LENGTH);
+    }
+    let (mut g1s, mut g2s) = (Vec::new(), Vec::new());
+    for pair in input.chunks_exact(PAIR_LEN) {
+        let g1 = in_subgroup(
+            read_g1(&pair[..G1_LEN])?,
+            "g1 point is not on correct subgroup",
+        )?;
+        let g2 = in_subgroup(
+            read_g2(&pair[G1_LEN..])?,
+            "g2 point is not on correct subgroup",
+        )?;
+        // Pairings with the point at infinity are the identity
+        if !g1.is_zero() && !g2.is_zero() {
+            g1s.push(g1);
+            g2s.push(g2);
+        }
+    }
+    let holds = g1s.is_empty() || Bls12_381::multi_pairing(g1s, g2s).0.is_one();
+    let mut output = [0; 32];
+    output[31] = holds.into();
+    Ok(PrecompileOutput {
+        gas_used,
+        output: Bytes::copy_from_slice(&output),
+    })
+}
+
+/// 0x10: maps a field element to a G1 point, as hashing to the curve does (RFC 9380).
+pub(super) fn map_fp_to_g1(
+    input: &[u8],
+    gas_limit: u64,
+) -> Result<PrecompileOutput, PrecompileError> {
+    let gas_used = charge(5500, gas_limit)?;
+    if input.len() != FP_LEN {
+        return Err(INVALID_INPUT_LENGTH);
+    }
+    Ok(PrecompileOutput {
+        gas_used,
+        output: encode_g1(map_to_curve(read_fp(input)?)),
+    })
+}
+
+/// 0x11: maps an element of the quadratic extension field to a G2 point.
+pub(super) fn map_fp2_to_g2(
+    input: &[u8],
+    gas_limit: u64,
+) -> Result<PrecompileOutput, PrecompileError> {
+    let gas_used = charge(23_800, gas_limit)?;
+    if input.len() != 2 * FP_LEN {
+        return Err(INVALID_INPUT_LENGTH);
+    }
+    Ok(PrecompileOutput {
+        gas_used,
+        output: encode_g2(map_to_curve(read_fp2(input)?)),
+    })
+}
+
+fn add<C: SWCurveConfig>(
+    input: &[u8],
+    gas_used: u64,
+    point_len: usize,
+    read: fn(&[u8]) -> Result<Affine<C>, PrecompileError>,
+    encode: fn(Affine<C>) -> Bytes,
+) -> Result<PrecompileOutput, PrecompileError> {
+    if input.len() != 2 * point_len {
+        return Err(INVALID_INPUT_LENGTH);
+    }
+    let sum = read(&input[..point_len])? + read(&input[point_len..])?;
+    Ok(PrecompileOutput {
+        gas_used,
+        output: encode(sum.into_affine()),
+    })
+}
+
+/// Cost of an MSM of `pairs` points and scalars, nothing if there are none.
+fn msm_cost(pairs: usize, base: u64, discounts: &[u16; 128]) -> u64 {
+    let Some(discount) = pairs
+        .min(discounts.len())
+        .checked_sub(1)
+        .map(|i| discounts[i])
+    else {
+        return 0;
+    };
+    (pairs as u64)
+        .saturating_mul(base)
+        .saturating_mul(discount.into())
+        / 1000
+}
+
+/// Sums points each multiplied by a scalar. The scalars are big-endian words that may be
+/// the group order or more, and the points must be in the subgroup.
+fn msm<C: SWCurveConfig<ScalarField = Fr>>(
+    input: &[u8],
+    gas_used: u64,
+    point_len: usize,
+    read: impl Fn(&[u8]) -> Result<Affine<C>, PrecompileError>,
+    encode: fn(Affine<C>) -> Bytes,
+) -> Result<PrecompileOutput, PrecompileError> {
+    let pair_len = point_len + SCALAR_LEN;
+    if input.is_empty() || !input.len().is_multiple_of(pair_len) {
+        return Err(INVALID_INPUT_LENGTH);
+    }
+    let (mut points, mut scalars) = (Vec::new(), Vec::new());
+    for pair in input.chunks_exact(pair_len) {
+        let point = read(&pair[..point_len])?;
+        if !point.is_zero() {
+            points.push(point);
+            scalars.push(Fr::from_be_bytes_mod_order(&pair[point_len..]));
+        }
+    }
+    let sum = Projective::<C>::msm_unchecked(&points, &scalars);
+    Ok(PrecompileOutput {
+        gas_used,
+        output: encode(sum.into_affine()),
+    })
+}
+
+fn map_to_curve<C: WBConfig>(element: C::BaseField) -> Affine<C> {
+    WBMap::<C>::map_to_curve(element)
+        .expect("the simplified SWU map is total")
+        .clear_cofactor()
+}
+
+/// Reads a field element, which must have its top bytes zero and be below the modulus.
+fn read_fp(input: &[u8]) -> Result<Fq, PrecompileError> {
+    let (padding, value) = input.split_at(FP_PADDING);
+    if padding.iter().any(|byte| *byte != 0) {
+        return Err(PrecompileError::Failure("invalid field element top bytes"));
+    }
+    let mut little_endian = value.to_vec();
+    little_endian.reverse();
+    Fq::deserialize_uncompressed(&little_endian[..])
+        .map_err(|_| PrecompileError::Failure("invalid fp.Element encoding"))
+}
+
+fn read_fp2(input: &[u8]) -> Result<Fq2, PrecompileError> {
+    Ok(Fq2::new(
+        read_fp(&input[..FP_LEN])?,
+        read_fp(&input[FP_LEN..])?,
+    ))
+}
+
+/// Reads a G1 point, which must be on the curve but not necessarily in the subgroup.
+fn read_g1(input: &[u8]) -> Result<Affine<g1::Config>, PrecompileError> {
+    on_curve(read_fp(&input[..FP_LEN])?, read_fp(&input[FP_LEN..])?)
+}
+
+fn read_g2(input: &[u8]) -> Result<Affine<g2::Config>, PrecompileError> {
+    on_curve(
+        read_fp2(&input[..2 * FP_LEN])?,
+        read_fp2(&input[2 * FP_LEN..])?,
+    )
+}
+
+fn on_curve<C: SWCurveConfig>(
+    x: C::BaseField,
+    y: C::BaseField,
+) -> Result<Affine<C>, PrecompileError> {
+    if x.is_zero() && y.is_zero() {
+        return Ok(Affine::identity());
+    }
+    let point = Affine::new_unchecked(x, y);
+    if !point.is_on_curve() {
+        return Err(PrecompileError::Failure("invalid point: not on curve"));
+    }
+    Ok(point)
+}
+
+fn in_subgroup<C: SWCurveConfig>(
+    point: Affine<C>,
+    error: &'static str,
+) -> Result<Affine<C>, PrecompileError> {
+    if !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(PrecompileError::Failure(error));
+    }
+    Ok(point)
+}
+
+fn write_fp(output: &mut [u8], element: &Fq) {
+    let value = &mut output[FP_PADDING..];
+    element
+        .serialize_uncompressed(&mut *value)
+        .expect("48 bytes fit a field element");
+    value.reverse();
+}
+
+fn encode_g1(point: Affine<g1::Config>) -> Bytes {
+    let mut output = [0; G1_LEN];
+    if let Some((x, y)) = point.xy() {
+        write_fp(&mut output[..FP_LEN], &x);
+        write_fp(&mut output[FP_LEN..], &y);
+    }
+    Bytes::copy_from_slice(&output)
+}
+
+fn encode_g2(point: Affine<g2::Config>) -> Bytes {
+    let mut output = [0; G2_LEN];
+    if let Some((x, y)) = point.xy() {
+        for (chunk, element) in output
+            .chunks_exact_mut(FP_LEN)
+            .zip([x.c0, x.c1, y.c0, y.c1])
+        {
+            write_fp(chunk, &element);
+        }
+    }
+    Bytes::copy_from_slice(&output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::precompiles::test::{run_failure_vectors, run_vectors};
+
+    #[test]
+    fn vectors() {
+        run_vectors("blsG1Add.json", g1_add);
+        run_vectors("blsG1MultiExp.json", g1_msm);
+        run_vectors("blsG2Add.json", g2_add);
+        run_vectors("blsG2MultiExp.json", g2_msm);
+        run_vectors("blsPairing.json", pairing);
+        run_vectors("blsMapG1.json", map_fp_to_g1);
+        run_vectors("blsMapG2.json", map_fp2_to_g2);
+    }
+
+    #[test]
+    fn failure_vectors() {
+        run_failure_vectors("fail-blsG1Add.json", g1_add);
+        run_failure_vectors("fail-blsG1MultiExp.json", g1_msm);
+        run_failure_vectors("fail-blsG2Add.json", g2_add);
+        run_failure_vectors("fail-blsG2MultiExp.json", g2_msm);
+        run_failure_vectors("fail-blsPairing.json", pairing);
+        run_failure_vectors("fail-blsMapG1.json", map_fp_to_g1);
+        run_failure_vectors("fail-blsMapG2.json", map_fp2_to_g2);
+    }
+
+    #[test]
+    fn msm_discounts() {
+        assert_eq!(msm_cost(0, G1_MSM_BASE, &G1_MSM_DISCOUNTS), 0);
+        assert_eq!(msm_cost(1, G1_MSM_BASE, &G1_MSM_DISCOUNTS), G1_MSM_BASE);
+        assert_eq!(msm_cost(2, G2_MSM_BASE, &G2_MSM_DISCOUNTS), 2 * G2_MSM_BASE);
+        // Past the end of the table every pair costs the same
+        assert_eq!(
+            msm_cost(128, G1_MSM_BASE, &G1_MSM_DISCOUNTS),
+            128 * 12_000 * 519 / 1000
+        );
+        assert_eq!(
+            msm_cost(256, G1_MSM_BASE, &G1_MSM_DISCOUNTS),
+            2 * msm_cost(128, G1_MSM_BASE, &G1_MSM_DISCOUNTS)
+        );
+        // Empty input is charged nothing but still fails
+        assert_eq!(g1_msm(&[], 0), Err(INVALID_INPUT_LENGTH));
+        assert_eq!(pairing(&[], 37_699), Err(PrecompileError::OutOfGas));
+    }
+}
@@ -0,0 +1,195 @@
+//! 0x06 to 0x08: point addition, scalar multiplication and the pairing check on the
+//! alt_bn128 curve (EIP-196 and EIP-197), repriced in Istanbul (EIP-1108).
+
+use bytes::Bytes;
+use substrate_bn::{AffineG1, AffineG2, Fq, Fq2, Fr, Group, Gt, G1, G2};
+
+use super::{charge, padded, PrecompileError, PrecompileOutput};
+
+/// Length of an encoded G1 point, two field elements.
+const G1_LEN: usize = 64;
+/// Length of an encoded G2 point, two elements of the quadratic extension field.
+const G2_LEN: usize = 128;
+/// Length of a G1 and G2 point pair in the pairing check's input.
+const PAIR_LEN: usize = G1_LEN + G2_LEN;
+
+const MALFORMED_POINT: PrecompileError = PrecompileError::Failure("bn256: malformed point");
+
+pub(super) fn add_byzantium(
+    input: &[u8],
+    gas_limit: u64,
+) -> Result<PrecompileOutput, PrecompileError> {
+    add(input, charge(500, gas_limit)?)
+}
+
+pub(super) fn add_istanbul(
+    input: &[u8],
+    gas_limit: u64,
+) -> Result<PrecompileOutput, PrecompileError> {
+    add(input, charge(150, gas_limit)?)
+}
+
+pub(super) fn mul_byzantium(
+    input: &[u8],
+    gas_limit: u64,
+) -> Result<PrecompileOutput, PrecompileError> {
+    mul(input, charge(40_000, gas_limit)?)
+}
+
+pub(super) fn mul_istanbul(
+    input: &[u8],
+    gas_limit: u64,
+) -> Result<PrecompileOutput, PrecompileError> {
+    mul(input, charge(6000, gas_limit)?)
+}
+
+pub(super) fn pairing_byzantium(
+    input: &[u8],
+    gas_limit: u64,
+) -> Result<PrecompileOutput, PrecompileError> {
+    pairing(input, gas_limit, 100_000, 80_000)
+}
+
+pub(super) fn pairing_istanbul(
+    input: &[u8],
+    gas_limit: u64,
+) -> Result<PrecompileOutput, PrecompileError> {
+    pairing(input, gas_limit, 45_000, 34_000)
+}
+
+fn add(input: &[u8], gas_used: u64) -> Result<PrecompileOutput, PrecompileError> {
+    let input = padded::<{ 2 * G1_LEN }>(input);
+    let sum = read_g1(&input[..G1_LEN])? + read_g1(&input[G1_LEN..])?;
+    Ok(PrecompileOutput {
+        gas_used,
+        output: encode_g1(sum),
+    })
+}
+
+fn mul(input: &[u8], gas_used: u64) -> Result<PrecompileOutput, PrecompileError> {
+    let input = padded::<{ G1_LEN + 32 }>(input);
+    let point = read_g1(&input[..G1_LEN])?;
+    // Scalars of the group order or more are reduced rather than rejected
+    let scalar = Fr::from_slice(&input[G1_LEN..]).map_err(|_| MALFORMED_POINT)?;
+    Ok(PrecompileOutput {
+        gas_used,
+        output: encode_g1(point * scalar),
+    })
+}
+
+/// Returns one if the product of the pairings of each G1 and G2 pair is the identity,
+/// which holds for no pairs at all, and zero otherwise.
+fn pairing(
+    input: &[u8],
+    gas_limit: u64,
+    base: u64,
+    per_pair: u64,
+) -> Result<PrecompileOutput, PrecompileError> {
+    let pairs = (input.len() / PAIR_LEN) as u64;
+    let gas_used = charge(
+        base.saturating_add(per_pair.saturating_mul(pairs)),
+        gas_limit,
+    )?;
+    if !input.len().is_multiple_of(PAIR_LEN) {
+        return Err(PrecompileError::Failure("bad elliptic curve pairing size"));
+    }
+    let mut points = Vec::with_capacity(input.len() / PAIR_LEN);
+    for pair in input.chunks_exact(PAIR_LEN) {
+        let (g1, g2) = (read_g1(&pair[..G1_LEN])?, read_g2(&pair[G1_LEN..])?);
+        // Pairings with the point at infinity are the identity
+        if !g1.is_zero() && !g2.is_zero() {
+            points.push((g1, g2));
+        }
+    }
+    let holds = points.is_empty() || substrate_bn::pairing_batch(&points) == Gt::one();
+    let mut output = [0; 32];
+    output[31] = holds.into();
+    Ok(PrecompileOutput {
+        gas_used,
+        output: Bytes::copy_from_slice(&output),
+    })
+}
+
+fn read_fq(input: &[u8]) -> Result<Fq, PrecompileError> {
+    Fq::from_slice(input).map_err(|_| MALFORMED_POINT)
+}
+
+/// Reads a G1 point as its x and y coordinates, with (0, 0) standing for the point at
+/// infinity.
+fn read_g1(input: &[u8]) -> Result<G1, PrecompileError> {
+    let (x, y) = (read_fq(&input[..32])?, read_fq(&input[32..])?);
+    if x.is_zero() && y.is_zero() {
+        return Ok(G1::zero());
+    }
+    Ok(AffineG1::new(x, y).map_err(|_| MALFORMED_POINT)?.into())
+}
+
+/// Reads a G2 point, whose coordinates are each encoded imaginary part first. Points
+/// outside the subgroup are rejected.
+fn read_g2(input: &[u8]) -> Result<G2, PrecompileError> {
+    let read_fq2 = |input: &[u8]| {
+        Ok::<_, PrecompileError>(Fq2::new(read_fq(&input[32..])?, read_fq(&input[..32])?))
+    };
+    let (x, y) = (read_fq2(&input[..64])?, read_fq2(&input[64..])?);
+    if x.is_zero() && y.is_zero() {
+        return Ok(G2::zero());
+    }
+    Ok(AffineG2::new(x, y).map_err(|_| MALFORMED_POINT)?.into())
+}
+
+/// Encodes a G1 point as its affine coordinates, or zeros for the point at infinity.
+fn encode_g1(point: G1) -> Bytes {
+    let mut output = [0; G1_LEN];
+    if let Some(point) = AffineG1::from_jacobian(point) {
+        point
+            .x()
+            .to_big_endian(&mut output[..32])
+            .expect("32 bytes fit a field element");
+        point
+            .y()
+            .to_big_endian(&mut output[32..])
+            .expect("32 bytes fit a field element");
+    }
+    Bytes::copy_from_slice(&output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::precompiles::test::run_vectors;
+
+    #[test]
+    fn istanbul_vectors() {
+        run_vectors("bn256Add.json", add_istanbul);
+        run_vectors("bn256ScalarMul.json", mul_istanbul);
+        run_vectors("bn256Pairing.json", pairing_istanbul);
+    }
+
+    #[test]
+    fn byzantium_prices() {
+        assert_eq!(add_byzantium(&[], 500).unwrap().gas_used, 500);
+        assert_eq!(mul_byzantium(&[], 39_999), Err(PrecompileError::OutOfGas));
+        assert_eq!(
+            pairing_byzantium(&[0; 2 * PAIR_LEN], 260_000)
+                .unwrap()
+                .gas_used,
+            260_000
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert_eq!(add_istanbul(&[0x11; 128], 150), Err(MALFORMED_POINT));
+        assert_eq!(mul_istanbul(&[0x11; 96], 6000), Err(MALFORMED_POINT));
+        // The field modulus itself is not a field element
+        let mut input = [0; 64];
+        input[..32].copy_from_slice(&hex_literal::hex!(
+            "30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47"
+        ));
+        assert_eq!(add_istanbul(&input, 150), Err(MALFORMED_POINT));
+        assert_eq!(
+            pairing_istanbul(&[0; PAIR_LEN + 1], 79_000),
+            Err(PrecompileError::Failure("bad elliptic curve pairing size"))
+        );
+    }
+}
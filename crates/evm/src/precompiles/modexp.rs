@@ -0,0 +1,142 @@
+//! 0x05: modular exponentiation of arbitrary length integers (EIP-198), repriced in
+//! Berlin (EIP-2565).
+
+use bytes::Bytes;
+use ethereum_types::U256;
+use num_bigint::BigUint;
+
+use super::{charge, PrecompileError, PrecompileOutput};
+
+pub(super) fn byzantium(input: &[u8], gas_limit: u64) -> Result<PrecompileOutput, PrecompileError> {
+    run(input, gas_limit, byzantium_cost)
+}
+
+pub(super) fn berlin(input: &[u8], gas_limit: u64) -> Result<PrecompileOutput, PrecompileError> {
+    run(input, gas_limit, berlin_cost)
+}
+
+/// EIP-198: the multiplication complexity of the longer of base and modulus, times the
+/// adjusted exponent length, over 20.
+fn byzantium_cost(max_len: u64, exp_len: u64) -> u64 {
+    let x = u128::from(max_len);
+    let complexity = if x <= 64 {
+        x * x
+    } else if x <= 1024 {
+        x * x / 4 + 96 * x - 3072
+    } else {
+        (x.saturating_mul(x) / 16).saturating_add(480 * x) - 199_680
+    };
+    saturate(complexity.saturating_mul(exp_len.max(1).into()) / 20)
+}
+
+/// EIP-2565: the square of the longer length in 64-bit words, times the adjusted exponent
+/// length, over 3, and never less than 200.
+fn berlin_cost(max_len: u64, exp_len: u64) -> u64 {
+    let words = u128::from(max_len.div_ceil(8));
+    let complexity = words.saturating_mul(words);
+    saturate(complexity.saturating_mul(exp_len.max(1).into()) / 3).max(200)
+}
+
+fn saturate(gas: u128) -> u64 {
+    gas.try_into().unwrap_or(u64::MAX)
+}
+
+/// Charges for and computes `base ** exp % modulus`. The input is the three lengths as
+/// words followed by the three numbers, big-endian and padded with zeros if it is short.
+fn run(
+    input: &[u8],
+    gas_limit: u64,
+    cost: fn(u64, u64) -> u64,
+) -> Result<PrecompileOutput, PrecompileError> {
+    let length = |word: usize| {
+        let length = U256::from_big_endian(&super::padded::<32>(
+            input.get(word * 32..).unwrap_or_default(),
+        ));
+        if length > U256::from(u64::MAX) {
+            u64::MAX
+        } else {
+            length.as_u64()
+        }
+    };
+    let (base_len, exp_len, mod_len) = (length(0), length(1), length(2));
+    let data = input.get(96..).unwrap_or_default();
+
+    // Only the first word of the exponent is priced by its value, the rest by its length
+    let exp_offset = base_len;
+    let exp_head = U256::from_big_endian(&slice(data, exp_offset, exp_len.min(32)));
+    let exp_head_bits = (exp_head.bits() as u64).saturating_sub(1);
+    let adjusted_exp_len = if exp_len <= 32 {
+        exp_head_bits
+    } else {
+        (exp_len - 32)
+            .saturating_mul(8)
+            .saturating_add(exp_head_bits)
+    };
+    let gas_used = charge(cost(base_len.max(mod_len), adjusted_exp_len), gas_limit)?;
+
+    if base_len == 0 && mod_len == 0 {
+        return Ok(PrecompileOutput {
+            gas_used,
+            output: Bytes::new(),
+        });
+    }
+    // The gas paid bounds the lengths of base and modulus, and the exponent's with them
+    let base = BigUint::from_bytes_be(&slice(data, 0, base_len));
+    let exp = BigUint::from_bytes_be(&slice(data, exp_offset, exp_len));
+    let modulus = BigUint::from_bytes_be(&slice(data, exp_offset.saturating_add(exp_len), mod_len));
+    let mut output = vec![0; mod_len as usize];
+    if modulus.bits() > 0 {
+        let result = base.modpow(&exp, &modulus).to_bytes_be();
+        output[mod_len as usize - result.len()..].copy_from_slice(&result);
+    }
+    Ok(PrecompileOutput {
+        gas_used,
+        output: output.into(),
+    })
+}
+
+/// `len` bytes of `data` from `offset`, with zeros past its end.
+fn slice(data: &[u8], offset: u64, len: u64) -> Vec<u8> {
+    let mut buf = vec![0; len as usize];
+    if let Some(available) = usize::try_from(offset)
+        .ok()
+        .and_then(|offset| data.get(offset..))
+    {
+        let copied = available.len().min(buf.len());
+        buf[..copied].copy_from_slice(&available[..copied]);
+    }
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::precompiles::test::run_vectors;
+
+    #[test]
+    fn byzantium_vectors() {
+        run_vectors("modexp.json", byzantium);
+    }
+
+    #[test]
+    fn eip2565_vectors() {
+        run_vectors("modexp_eip2565.json", berlin);
+    }
+
+    #[test]
+    fn huge_lengths_run_out_of_gas() {
+        let mut input = [0; 96];
+        input[..32].fill(0xff);
+        assert_eq!(berlin(&input, u64::MAX - 1), Err(PrecompileError::OutOfGas));
+        // A long exponent costs nothing to skip when there is no base or modulus
+        let mut input = [0; 96];
+        input[32..64].fill(0xff);
+        assert_eq!(
+            byzantium(&input, 0).unwrap(),
+            PrecompileOutput {
+                gas_used: 0,
+                output: Bytes::new()
+            }
+        );
+    }
+}
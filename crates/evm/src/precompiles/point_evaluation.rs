@@ -0,0 +1,67 @@
+//! 0x0a: checks that a blob's polynomial evaluates to a given value at a given point
+//! (EIP-4844).
+
+use bytes::Bytes;
+use ethereum_types::H256;
+use reec_core::kzg::{self, BLS_MODULUS, FIELD_ELEMENTS_PER_BLOB};
+
+use super::{charge, PrecompileError, PrecompileOutput};
+
+/// Versioned hash, point, value, commitment and proof.
+const INPUT_LEN: usize = 32 + 32 + 32 + 48 + 48;
+
+/// Returns the number of field elements in a blob and the field's modulus as words, which
+/// never change, when the proof holds.
+pub(super) fn point_evaluation(
+    input: &[u8],
+    gas_limit: u64,
+) -> Result<PrecompileOutput, PrecompileError> {
+    let gas_used = charge(50_000, gas_limit)?;
+    if input.len() != INPUT_LEN {
+        return Err(PrecompileError::Failure("invalid input length"));
+    }
+    let word =
+        |offset: usize| -> [u8; 32] { input[offset..offset + 32].try_into().expect("32 bytes") };
+    let commitment: kzg::Commitment = input[96..144].try_into().expect("48 bytes");
+    let proof: kzg::Proof = input[144..].try_into().expect("48 bytes");
+    if kzg::versioned_hash(&commitment) != H256(word(0)) {
+        return Err(PrecompileError::Failure("mismatched versioned hash"));
+    }
+    if kzg::verify_proof(&commitment, &word(32), &word(64), &proof) != Ok(true) {
+        return Err(PrecompileError::Failure("error verifying kzg proof"));
+    }
+
+    let mut output = [0; 64];
+    output[24..32].copy_from_slice(&(FIELD_ELEMENTS_PER_BLOB as u64).to_be_bytes());
+    output[32..].copy_from_slice(&BLS_MODULUS);
+    Ok(PrecompileOutput {
+        gas_used,
+        output: Bytes::copy_from_slice(&output),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::precompiles::test::run_vectors;
+
+    #[test]
+    fn vectors() {
+        run_vectors("pointEvaluation.json", point_evaluation);
+    }
+
+    #[test]
+    fn rejects_mismatched_hash() {
+        let mut input = [0; INPUT_LEN];
+        input[96] = 0xc0;
+        input[144] = 0xc0;
+        assert_eq!(
+            point_evaluation(&input, 50_000),
+            Err(PrecompileError::Failure("mismatched versioned hash"))
+        );
+        assert_eq!(
+            point_evaluation(&input[1..], 50_000),
+            Err(PrecompileError::Failure("invalid input length"))
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use ethereum_types::U256;
+
+use crate::interpreter::ExitStatus;
+
+pub const STACK_LIMIT: usize = 1024;
+
+/// The operand stack of a frame, its top at the end.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stack {
+    data: Vec<U256>,
+}
+
+impl Stack {
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Items from the bottom of the stack to its top.
+    pub fn data(&self) -> &[U256] {
+        &self.data
+    }
+
+    pub fn push(&mut self, value: U256) -> Result<(), ExitStatus> {
+        if self.data.len() == STACK_LIMIT {
+            return Err(ExitStatus::StackOverflow);
+        }
+        self.data.push(value);
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Result<U256, ExitStatus> {
+        self.data.pop().ok_or(ExitStatus::StackUnderflow)
+    }
+
+    /// Pops `N` items, the top of the stack first.
+    pub fn pop_n<const N: usize>(&mut self) -> Result<[U256; N], ExitStatus> {
+        if self.data.len() < N {
+            return Err(ExitStatus::StackUnderflow);
+        }
+        let mut items = [U256::zero(); N];
+        for item in &mut items {
+            *item = self.data.pop().unwrap_or_default();
+        }
+        Ok(items)
+    }
+
+    /// The item `depth` positions below the top, 0 being the top itself.
+    pub fn peek(&self, depth: usize) -> Result<U256, ExitStatus> {
+        self.data
+            .len()
+            .checked_sub(depth + 1)
+            .map(|index| self.data[index])
+            .ok_or(ExitStatus::StackUnderflow)
+    }
+
+    /// Pushes a copy of the `n`th item, 1 being the top.
+    pub fn dup(&mut self, n: usize) -> Result<(), ExitStatus> {
+        let value = self.peek(n - 1)?;
+        self.push(value)
+    }
+
+    /// Exchanges the top with the item `n` positions below it.
+    pub fn swap(&mut self, n: usize) -> Result<(), ExitStatus> {
+        let len = self.data.len();
+        if len <= n {
+            return Err(ExitStatus::StackUnderflow);
+        }
+        self.data.swap(len - 1, len - 1 - n);
+        Ok(())
+    }
+
+    /// Exchanges the items `n` and `n + m` positions below the top (EIP-663).
+    pub fn exchange(&mut self, n: usize, m: usize) -> Result<(), ExitStatus> {
+        let len = self.data.len();
+        if len <= n + m {
+            return Err(ExitStatus::StackUnderflow);
+        }
+        self.data.swap(len - 1 - n, len - 1 - n - m);
+        Ok(())
+    }
+}
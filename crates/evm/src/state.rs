@@ -0,0 +1,894 @@
+//! State as executing transactions see it: accounts and storage read from a
+//! [`Database`] and cached along with the changes made to them. Every change is
+//! journaled so that a failing frame can undo its own, and changes accumulate over a
+//! block into the [`StateDiff`] that gets persisted.
+
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    sync::Arc,
+};
+
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use keccak_hash::keccak;
+use reec_core::types::{AccountDiff, AccountInfo, BlockNumber, Log, StateDiff, EMPTY_CODE_HASH};
+
+use crate::{
+    bytecode::{delegated_address, Bytecode, BytecodeCache},
+    eof,
+    host::{AccountLoad, SStoreResult, SelfDestructResult, StateLoad},
+    Database, Env, Fork, Host,
+};
+
+impl<T: Database + ?Sized> Database for &T {
+    fn account(&self, address: Address) -> anyhow::Result<Option<AccountInfo>> {
+        (**self).account(address)
+    }
+
+    fn storage(&self, address: Address, slot: H256) -> anyhow::Result<U256> {
+        (**self).storage(address, slot)
+    }
+
+    fn code(&self, code_hash: H256) -> anyhow::Result<Bytes> {
+        (**self).code(code_hash)
+    }
+
+    fn block_hash(&self, number: BlockNumber) -> anyhow::Result<Option<H256>> {
+        (**self).block_hash(number)
+    }
+
+    fn state_root(&self, diff: &StateDiff) -> anyhow::Result<H256> {
+        (**self).state_root(diff)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Account {
+    info: AccountInfo,
+    /// Whether the account is in the state, which an empty account still is before
+    /// Spurious Dragon.
+    exists: bool,
+    /// Loaded on first use.
+    code: Option<Bytes>,
+    storage: HashMap<H256, Slot>,
+    /// Set once the storage held in the database no longer applies, because the account
+    /// was destroyed or created anew within the block.
+    storage_cleared: bool,
+    /// Created by the current transaction, which matters for EIP-6780.
+    created: bool,
+    selfdestructed: bool,
+    /// Touched by the current transaction, which deletes it at the end of it if it is
+    /// empty (EIP-161).
+    touched: bool,
+    /// Possibly changed within the block, which makes it part of the state diff.
+    changed: bool,
+    code_changed: bool,
+}
+
+impl Account {
+    fn new(info: Option<AccountInfo>) -> Self {
+        Account {
+            exists: info.is_some(),
+            info: info.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.exists || self.info.is_empty()
+    }
+
+    /// Removes the account from the state.
+    fn delete(&mut self) {
+        *self = Account {
+            code: Some(Bytes::new()),
+            storage_cleared: true,
+            changed: true,
+            ..Default::default()
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Slot {
+    /// Value at the start of the transaction.
+    original: U256,
+    present: U256,
+    /// Possibly changed within the block.
+    changed: bool,
+}
+
+/// How to undo a change.
+#[derive(Clone, Debug)]
+enum JournalEntry {
+    AddressWarmed(Address),
+    SlotWarmed(Address, H256),
+    /// The account was brought into existence.
+    AccountCreated(Address),
+    /// The account was replaced as a whole, on contract creation.
+    AccountReplaced {
+        address: Address,
+        previous: Box<Account>,
+    },
+    Touched(Address),
+    SelfDestructed(Address),
+    Balance {
+        address: Address,
+        previous: U256,
+    },
+    Nonce {
+        address: Address,
+        previous: u64,
+    },
+    Code {
+        address: Address,
+        previous_hash: H256,
+        previous: Option<Bytes>,
+    },
+    Storage {
+        address: Address,
+        slot: H256,
+        previous: U256,
+    },
+    Transient {
+        address: Address,
+        slot: H256,
+        previous: U256,
+    },
+}
+
+/// Point in the journal that changes made after it can be reverted to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    journal: usize,
+    logs: usize,
+}
+
+/// The precompile at 0x03, whose touch survives a revert. Block 2675119 on mainnet ran
+/// out of gas right after touching it, and clients kept the touch to agree with the
+/// state that block produced.
+fn is_ripemd(address: Address) -> bool {
+    address == Address::from_low_u64_be(3)
+}
+
+/// What one transaction did to the state, apart from the state it ran on.
+pub(crate) struct TransactionChanges {
+    accounts: HashMap<Address, Account>,
+    deployed: HashMap<H256, Bytes>,
+}
+
+/// A [`Host`] over a [`Database`], for executing the transactions of one block.
+#[derive(Clone)]
+pub struct JournaledState<DB> {
+    db: DB,
+    env: Env,
+    accounts: HashMap<Address, Account>,
+    transient: HashMap<(Address, H256), U256>,
+    warm_addresses: HashSet<Address>,
+    warm_slots: HashSet<(Address, H256)>,
+    logs: Vec<Log>,
+    /// Changes made by the current transaction.
+    journal: Vec<JournalEntry>,
+    /// Code deployed within the block, which the database does not hold yet.
+    deployed: HashMap<H256, Bytes>,
+    /// Fees owed to the coinbase, when they are collected apart from the transactions
+    /// rather than paid as each one ends.
+    deferred_fees: Option<U256>,
+    bytecode_cache: Arc<BytecodeCache>,
+}
+
+impl<DB: Database> JournaledState<DB> {
+    pub fn new(db: DB, env: Env) -> Self {
+        JournaledState {
+            db,
+            env,
+            accounts: HashMap::new(),
+            transient: HashMap::new(),
+            warm_addresses: HashSet::new(),
+            warm_slots: HashSet::new(),
+            logs: Vec::new(),
+            journal: Vec::new(),
+            deployed: HashMap::new(),
+            deferred_fees: None,
+            bytecode_cache: BytecodeCache::shared(),
+        }
+    }
+
+    /// Analyzes code with `cache` instead of the shared [`BytecodeCache`].
+    pub fn with_bytecode_cache(mut self, cache: Arc<BytecodeCache>) -> Self {
+        self.bytecode_cache = cache;
+        self
+    }
+
+    pub fn bytecode_cache(&self) -> &Arc<BytecodeCache> {
+        &self.bytecode_cache
+    }
+
+    pub fn db(&self) -> &DB {
+        &self.db
+    }
+
+    pub fn env_mut(&mut self) -> &mut Env {
+        &mut self.env
+    }
+
+    fn fork(&self) -> Fork {
+        self.env.fork
+    }
+
+    fn load(&mut self, address: Address) -> anyhow::Result<&mut Account> {
+        Ok(match self.accounts.entry(address) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Account::new(self.db.account(address)?)),
+        })
+    }
+
+    /// The account as of the latest change, `None` if it does not exist.
+    pub fn account_info(&mut self, address: Address) -> anyhow::Result<Option<AccountInfo>> {
+        let account = self.load(address)?;
+        Ok(account.exists.then_some(account.info.clone()))
+    }
+
+    pub fn account_code(&mut self, address: Address) -> anyhow::Result<Bytes> {
+        let account = match self.accounts.entry(address) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Account::new(self.db.account(address)?)),
+        };
+        if account.code.is_none() {
+            let code = if account.info.code_hash == EMPTY_CODE_HASH {
+                Bytes::new()
+            } else {
+                self.db.code(account.info.code_hash)?
+            };
+            account.code = Some(code);
+        }
+        Ok(account.code.clone().unwrap_or_default())
+    }
+
+    /// The account's code analyzed for execution, from the cache when it was run before.
+    pub(crate) fn analyzed_code(&mut self, address: Address) -> anyhow::Result<Arc<Bytecode>> {
+        let code = self.account_code(address)?;
+        let code_hash = self.load(address)?.info.code_hash;
+        Ok(self.bytecode_cache.get_or_analyze(code_hash, code))
+    }
+
+    /// The value of a storage slot as of the latest change.
+    pub fn storage(&mut self, address: Address, slot: H256) -> anyhow::Result<U256> {
+        Ok(self.slot(address, slot)?.present)
+    }
+
+    /// Like [`JournaledState::account_info`], without caching what it reads.
+    pub(crate) fn peek_account(&self, address: Address) -> anyhow::Result<Option<AccountInfo>> {
+        match self.accounts.get(&address) {
+            Some(account) => Ok(account.exists.then(|| account.info.clone())),
+            None => self.db.account(address),
+        }
+    }
+
+    /// Like [`JournaledState::storage`], without caching what it reads.
+    pub(crate) fn peek_storage(&self, address: Address, slot: H256) -> anyhow::Result<U256> {
+        let Some(account) = self.accounts.get(&address) else {
+            return self.db.storage(address, slot);
+        };
+        match account.storage.get(&slot) {
+            Some(slot) => Ok(slot.present),
+            None if account.storage_cleared || !account.exists => Ok(U256::zero()),
+            None => self.db.storage(address, slot),
+        }
+    }
+
+    /// The code with the given hash, deployed within the block or before it.
+    pub(crate) fn peek_code(&self, code_hash: H256) -> anyhow::Result<Bytes> {
+        match self.deployed.get(&code_hash) {
+            Some(code) => Ok(code.clone()),
+            None => self.db.code(code_hash),
+        }
+    }
+
+    /// Keeps the fees of the transactions from here on as [`JournaledState::deferred_fees`]
+    /// instead of paying them to the coinbase.
+    pub(crate) fn defer_fees(&mut self) {
+        self.deferred_fees = Some(U256::zero());
+    }
+
+    pub(crate) fn deferred_fees(&self) -> Option<U256> {
+        self.deferred_fees
+    }
+
+    /// Pays a transaction's fee to the coinbase, unless fees are deferred.
+    pub(crate) fn pay_fee(&mut self, fee: U256) -> anyhow::Result<()> {
+        match &mut self.deferred_fees {
+            Some(deferred) => {
+                *deferred = deferred.saturating_add(fee);
+                Ok(())
+            }
+            None => self.add_balance(self.env.block.coinbase, fee),
+        }
+    }
+
+    /// Splits a state that ran a single, finalized transaction into its database and
+    /// the changes the transaction made.
+    pub(crate) fn into_changes(self) -> (DB, TransactionChanges) {
+        (
+            self.db,
+            TransactionChanges {
+                accounts: self.accounts,
+                deployed: self.deployed,
+            },
+        )
+    }
+
+    /// Takes over the changes of a transaction that read its state from this one, as if
+    /// it had run here.
+    pub(crate) fn merge(&mut self, changes: TransactionChanges) -> anyhow::Result<()> {
+        self.deployed.extend(changes.deployed);
+        for (address, changes) in changes.accounts {
+            if !changes.changed && !changes.storage.values().any(|slot| slot.changed) {
+                continue;
+            }
+            let account = self.load(address)?;
+            // Deleting an account forgets that its code changed
+            let deleted = changes.storage_cleared && !changes.exists;
+            account.code_changed = !deleted && (account.code_changed || changes.code_changed);
+            account.changed |= changes.changed;
+            account.info = changes.info;
+            account.exists = changes.exists;
+            if changes.code.is_some() {
+                account.code = changes.code;
+            }
+            if changes.storage_cleared {
+                account.storage_cleared = true;
+                account.storage = changes.storage;
+            } else {
+                let written = changes.storage.into_iter().filter(|(_, slot)| slot.changed);
+                for (key, slot) in written {
+                    account.storage.insert(
+                        key,
+                        Slot {
+                            original: slot.present,
+                            ..slot
+                        },
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn slot(&mut self, address: Address, slot: H256) -> anyhow::Result<&mut Slot> {
+        let account = match self.accounts.entry(address) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Account::new(self.db.account(address)?)),
+        };
+        Ok(match account.storage.entry(slot) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = if account.storage_cleared || !account.exists {
+                    U256::zero()
+                } else {
+                    self.db.storage(address, slot)?
+                };
+                entry.insert(Slot {
+                    original: value,
+                    present: value,
+                    changed: false,
+                })
+            }
+        })
+    }
+
+    /// Marks an address warm, returning whether it was cold.
+    pub(crate) fn warm_address(&mut self, address: Address) -> bool {
+        let is_cold = self.warm_addresses.insert(address);
+        if is_cold {
+            self.journal.push(JournalEntry::AddressWarmed(address));
+        }
+        is_cold
+    }
+
+    /// Marks a storage slot warm, returning whether it was cold.
+    pub(crate) fn warm_slot(&mut self, address: Address, slot: H256) -> bool {
+        let is_cold = self.warm_slots.insert((address, slot));
+        if is_cold {
+            self.journal.push(JournalEntry::SlotWarmed(address, slot));
+        }
+        is_cold
+    }
+
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            journal: self.journal.len(),
+            logs: self.logs.len(),
+        }
+    }
+
+    /// Undoes every change made since the checkpoint.
+    pub(crate) fn revert(&mut self, checkpoint: Checkpoint) {
+        self.logs.truncate(checkpoint.logs);
+        while self.journal.len() > checkpoint.journal {
+            let Some(entry) = self.journal.pop() else {
+                break;
+            };
+            self.undo(entry);
+        }
+    }
+
+    fn undo(&mut self, entry: JournalEntry) {
+        // Anything a journal entry refers to was loaded when it was made
+        fn account(accounts: &mut HashMap<Address, Account>, address: Address) -> &mut Account {
+            accounts
+                .get_mut(&address)
+                .expect("journaled accounts are loaded")
+        }
+        match entry {
+            JournalEntry::AddressWarmed(address) => {
+                self.warm_addresses.remove(&address);
+            }
+            JournalEntry::SlotWarmed(address, slot) => {
+                self.warm_slots.remove(&(address, slot));
+            }
+            JournalEntry::AccountCreated(address) => {
+                account(&mut self.accounts, address).exists = false
+            }
+            JournalEntry::AccountReplaced { address, previous } => {
+                self.accounts.insert(address, *previous);
+            }
+            JournalEntry::Touched(address) => {
+                if !is_ripemd(address) {
+                    account(&mut self.accounts, address).touched = false;
+                }
+            }
+            JournalEntry::SelfDestructed(address) => {
+                account(&mut self.accounts, address).selfdestructed = false
+            }
+            JournalEntry::Balance { address, previous } => {
+                account(&mut self.accounts, address).info.balance = previous
+            }
+            JournalEntry::Nonce { address, previous } => {
+                account(&mut self.accounts, address).info.nonce = previous
+            }
+            JournalEntry::Code {
+                address,
+                previous_hash,
+                previous,
+            } => {
+                let account = account(&mut self.accounts, address);
+                account.info.code_hash = previous_hash;
+                account.code = previous;
+            }
+            JournalEntry::Storage {
+                address,
+                slot,
+                previous,
+            } => {
+                if let Some(slot) = account(&mut self.accounts, address).storage.get_mut(&slot) {
+                    slot.present = previous;
+                }
+            }
+            JournalEntry::Transient {
+                address,
+                slot,
+                previous,
+            } => {
+                self.transient.insert((address, slot), previous);
+            }
+        }
+    }
+
+    /// Brings the account into existence, as sending it ether does.
+    fn ensure_exists(&mut self, address: Address) -> anyhow::Result<()> {
+        let account = self.load(address)?;
+        if !account.exists {
+            account.exists = true;
+            account.changed = true;
+            self.journal.push(JournalEntry::AccountCreated(address));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn touch(&mut self, address: Address) -> anyhow::Result<()> {
+        let account = self.load(address)?;
+        if !account.touched {
+            account.touched = true;
+            self.journal.push(JournalEntry::Touched(address));
+        }
+        Ok(())
+    }
+
+    fn set_balance(&mut self, address: Address, balance: U256) -> anyhow::Result<()> {
+        self.ensure_exists(address)?;
+        self.touch(address)?;
+        let account = self.load(address)?;
+        let previous = std::mem::replace(&mut account.info.balance, balance);
+        account.changed = true;
+        self.journal
+            .push(JournalEntry::Balance { address, previous });
+        Ok(())
+    }
+
+    /// Credits the account, creating it if needed. Adding zero still touches it.
+    pub(crate) fn add_balance(&mut self, address: Address, value: U256) -> anyhow::Result<()> {
+        let balance = self.load(address)?.info.balance;
+        self.set_balance(address, balance.saturating_add(value))
+    }
+
+    /// Debits the account; callers check that the balance covers `value`.
+    pub(crate) fn sub_balance(&mut self, address: Address, value: U256) -> anyhow::Result<()> {
+        let balance = self.load(address)?.info.balance;
+        self.set_balance(address, balance.saturating_sub(value))
+    }
+
+    pub(crate) fn transfer(
+        &mut self,
+        from: Address,
+        to: Address,
+        value: U256,
+    ) -> anyhow::Result<()> {
+        self.sub_balance(from, value)?;
+        self.add_balance(to, value)
+    }
+
+    pub(crate) fn increment_nonce(&mut self, address: Address) -> anyhow::Result<()> {
+        self.ensure_exists(address)?;
+        let account = self.load(address)?;
+        let previous = account.info.nonce;
+        account.info.nonce = previous + 1;
+        account.changed = true;
+        self.journal.push(JournalEntry::Nonce { address, previous });
+        Ok(())
+    }
+
+    pub(crate) fn set_code(&mut self, address: Address, code: Bytes) -> anyhow::Result<()> {
+        let code_hash = keccak(&code);
+        self.deployed.insert(code_hash, code.clone());
+        let account = self.load(address)?;
+        let previous_hash = std::mem::replace(&mut account.info.code_hash, code_hash);
+        let previous = account.code.replace(code);
+        account.changed = true;
+        account.code_changed = true;
+        self.journal.push(JournalEntry::Code {
+            address,
+            previous_hash,
+            previous,
+        });
+        Ok(())
+    }
+
+    /// Whether creating a contract at the address collides with an existing one.
+    pub(crate) fn has_code_or_nonce(&mut self, address: Address) -> anyhow::Result<bool> {
+        let account = self.load(address)?;
+        Ok(account.info.nonce != 0 || account.info.code_hash != EMPTY_CODE_HASH)
+    }
+
+    /// Turns the address into a fresh contract account, keeping only its balance.
+    pub(crate) fn create_account(&mut self, address: Address) -> anyhow::Result<()> {
+        let nonce = if self.fork() >= Fork::SpuriousDragon {
+            1
+        } else {
+            0
+        };
+        let account = self.load(address)?;
+        let previous = Box::new(account.clone());
+        *account = Account {
+            info: AccountInfo {
+                nonce,
+                balance: account.info.balance,
+                code_hash: EMPTY_CODE_HASH,
+            },
+            exists: true,
+            code: Some(Bytes::new()),
+            storage: HashMap::new(),
+            storage_cleared: true,
+            created: true,
+            selfdestructed: false,
+            touched: account.touched,
+            changed: true,
+            code_changed: account.code_changed,
+        };
+        self.journal
+            .push(JournalEntry::AccountReplaced { address, previous });
+        Ok(())
+    }
+
+    /// Ends the current transaction: deletes the accounts it destroyed and, from
+    /// Spurious Dragon, the empty ones it touched, and forgets what was warm. Returns
+    /// the logs it emitted.
+    pub(crate) fn finalize_transaction(&mut self) -> Vec<Log> {
+        let clear_empty = self.fork() >= Fork::SpuriousDragon;
+        for entry in std::mem::take(&mut self.journal) {
+            match entry {
+                JournalEntry::Storage { address, slot, .. } => {
+                    if let Some(slot) = self
+                        .accounts
+                        .get_mut(&address)
+                        .and_then(|account| account.storage.get_mut(&slot))
+                    {
+                        slot.original = slot.present;
+                    }
+                }
+                JournalEntry::AccountReplaced { address, .. }
+                | JournalEntry::SelfDestructed(address)
+                | JournalEntry::Touched(address) => {
+                    let Some(account) = self.accounts.get_mut(&address) else {
+                        continue;
+                    };
+                    if account.selfdestructed
+                        || (clear_empty
+                            && account.touched
+                            && account.exists
+                            && account.info.is_empty())
+                    {
+                        account.delete();
+                    }
+                    account.created = false;
+                    account.touched = false;
+                }
+                _ => {}
+            }
+        }
+        self.transient.clear();
+        self.warm_addresses.clear();
+        self.warm_slots.clear();
+        std::mem::take(&mut self.logs)
+    }
+
+    /// Every change made so far, for storage to persist.
+    pub fn state_diff(&self) -> StateDiff {
+        self.accounts
+            .iter()
+            .filter(|(_, account)| {
+                account.changed || account.storage.values().any(|slot| slot.changed)
+            })
+            .map(|(address, account)| {
+                let diff = AccountDiff {
+                    info: account.exists.then_some(account.info.clone()),
+                    storage_cleared: account.storage_cleared,
+                    storage: account
+                        .storage
+                        .iter()
+                        .filter(|(_, slot)| slot.changed)
+                        .map(|(slot, value)| (*slot, value.present))
+                        .collect(),
+                    code: if account.code_changed {
+                        account.code.clone()
+                    } else {
+                        None
+                    },
+                };
+                (*address, diff)
+            })
+            .collect()
+    }
+}
+
+impl<DB: Database> Host for JournaledState<DB> {
+    fn env(&self) -> &Env {
+        &self.env
+    }
+
+    fn load_account(&mut self, address: Address) -> anyhow::Result<StateLoad<AccountLoad>> {
+        let is_cold = self.warm_address(address);
+        let account = self.load(address)?;
+        let mut load = AccountLoad {
+            exists: account.exists,
+            is_empty: account.is_empty(),
+            delegation: None,
+        };
+        if self.fork() >= Fork::Prague {
+            if let Some(delegate) = delegated_address(&self.account_code(address)?) {
+                load.delegation = Some(StateLoad::new(delegate, self.warm_address(delegate)));
+            }
+        }
+        Ok(StateLoad::new(load, is_cold))
+    }
+
+    fn balance(&mut self, address: Address) -> anyhow::Result<StateLoad<U256>> {
+        let is_cold = self.warm_address(address);
+        Ok(StateLoad::new(self.load(address)?.info.balance, is_cold))
+    }
+
+    // From the EOF fork, code reads EOF contracts as just the magic (EIP-3540)
+    fn code(&mut self, address: Address) -> anyhow::Result<StateLoad<Bytes>> {
+        let is_cold = self.warm_address(address);
+        let mut code = self.account_code(address)?;
+        if self.env.fork >= Fork::Eof && code.starts_with(&eof::MAGIC) {
+            code = Bytes::from_static(&eof::MAGIC);
+        }
+        Ok(StateLoad::new(code, is_cold))
+    }
+
+    fn code_hash(&mut self, address: Address) -> anyhow::Result<StateLoad<H256>> {
+        let is_cold = self.warm_address(address);
+        let account = self.load(address)?;
+        let hash = if account.is_empty() {
+            H256::zero()
+        } else {
+            account.info.code_hash
+        };
+        if self.env.fork >= Fork::Eof
+            && hash != EMPTY_CODE_HASH
+            && self.account_code(address)?.starts_with(&eof::MAGIC)
+        {
+            return Ok(StateLoad::new(keccak(eof::MAGIC), is_cold));
+        }
+        Ok(StateLoad::new(hash, is_cold))
+    }
+
+    fn sload(&mut self, address: Address, slot: H256) -> anyhow::Result<StateLoad<U256>> {
+        let is_cold = self.warm_slot(address, slot);
+        Ok(StateLoad::new(self.storage(address, slot)?, is_cold))
+    }
+
+    fn sstore(
+        &mut self,
+        address: Address,
+        slot: H256,
+        value: U256,
+    ) -> anyhow::Result<StateLoad<SStoreResult>> {
+        let is_cold = self.warm_slot(address, slot);
+        let entry = self.slot(address, slot)?;
+        let result = SStoreResult {
+            original: entry.original,
+            present: entry.present,
+            new: value,
+        };
+        if value != entry.present {
+            entry.present = value;
+            entry.changed = true;
+            self.journal.push(JournalEntry::Storage {
+                address,
+                slot,
+                previous: result.present,
+            });
+        }
+        Ok(StateLoad::new(result, is_cold))
+    }
+
+    fn tload(&mut self, address: Address, slot: H256) -> U256 {
+        self.transient
+            .get(&(address, slot))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn tstore(&mut self, address: Address, slot: H256, value: U256) {
+        let previous = self
+            .transient
+            .insert((address, slot), value)
+            .unwrap_or_default();
+        if previous != value {
+            self.journal.push(JournalEntry::Transient {
+                address,
+                slot,
+                previous,
+            });
+        }
+    }
+
+    fn block_hash(&mut self, number: BlockNumber) -> anyhow::Result<H256> {
+        Ok(self.db.block_hash(number)?.unwrap_or_default())
+    }
+
+    fn log(&mut self, log: Log) {
+        self.logs.push(log);
+    }
+
+    fn selfdestruct(
+        &mut self,
+        address: Address,
+        target: Address,
+    ) -> anyhow::Result<StateLoad<SelfDestructResult>> {
+        let is_cold = self.warm_address(target);
+        let spurious = self.fork() >= Fork::SpuriousDragon;
+        let target_account = self.load(target)?;
+        let target_exists = if spurious {
+            !target_account.is_empty()
+        } else {
+            target_account.exists
+        };
+        let account = self.load(address)?;
+        let (balance, created, previously_destroyed) = (
+            account.info.balance,
+            account.created,
+            account.selfdestructed,
+        );
+
+        // From Cancun (EIP-6780) only contracts created in the same transaction are
+        // destroyed, the others just send their balance away
+        let destroys = self.fork() < Fork::Cancun || created;
+        if target != address {
+            self.transfer(address, target, balance)?;
+        } else if destroys {
+            self.set_balance(address, U256::zero())?;
+        }
+        if destroys && !previously_destroyed {
+            self.load(address)?.selfdestructed = true;
+            self.journal.push(JournalEntry::SelfDestructed(address));
+        }
+        let result = SelfDestructResult {
+            balance,
+            target_exists,
+            previously_destroyed,
+        };
+        Ok(StateLoad::new(result, is_cold))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::MemoryDb;
+
+    fn funded(address: Address, balance: u64) -> MemoryDb {
+        let mut db = MemoryDb::default();
+        db.insert_account(address, 0, balance.into(), Bytes::new());
+        db
+    }
+
+    #[test]
+    fn reverts_changes_after_checkpoint() {
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let slot = H256::repeat_byte(3);
+        let mut state = JournaledState::new(funded(alice, 100), Env::default());
+        state.sstore(alice, slot, 1.into()).unwrap();
+        let checkpoint = state.checkpoint();
+        state.transfer(alice, bob, 40.into()).unwrap();
+        let result = state.sstore(alice, slot, 2.into()).unwrap();
+        assert_eq!(
+            (result.data.original, result.data.present),
+            (U256::zero(), 1.into())
+        );
+        assert!(!result.is_cold);
+        state.tstore(alice, slot, 5.into());
+        state.log(Log::default());
+        state.revert(checkpoint);
+
+        assert_eq!(
+            state.account_info(alice).unwrap().unwrap().balance,
+            100.into()
+        );
+        assert_eq!(state.account_info(bob).unwrap(), None);
+        assert_eq!(state.storage(alice, slot).unwrap(), 1.into());
+        assert_eq!(state.tload(alice, slot), U256::zero());
+        assert!(state.finalize_transaction().is_empty());
+    }
+
+    #[test]
+    fn diff_drops_touched_empty_accounts() {
+        let (alice, bob, carol) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+        );
+        let mut db = funded(alice, 100);
+        db.insert_account(carol, 0, U256::zero(), Bytes::new());
+        db.insert_storage(alice, H256::zero(), 7.into());
+        let env = Env {
+            fork: Fork::Cancun,
+            ..Default::default()
+        };
+        let mut state = JournaledState::new(&db, env);
+        state.transfer(alice, bob, 100.into()).unwrap();
+        state.touch(carol).unwrap();
+        state.sstore(bob, H256::zero(), 9.into()).unwrap();
+        state.finalize_transaction();
+
+        let diff = state.state_diff();
+        assert_eq!(diff.len(), 3);
+        // Alice is empty once her balance is gone
+        assert_eq!(diff[&alice].info, None);
+        assert!(diff[&alice].storage_cleared);
+        assert_eq!(diff[&bob].info.as_ref().unwrap().balance, 100.into());
+        assert_eq!(diff[&bob].storage, [(H256::zero(), 9.into())].into());
+        assert_eq!(diff[&carol].info, None);
+        // Originals move on with the transaction
+        assert_eq!(
+            state
+                .sstore(bob, H256::zero(), 1.into())
+                .unwrap()
+                .data
+                .original,
+            9.into()
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Hooks into execution, for debugging consensus failures and for the tracing RPC
+//! methods.
+
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use reec_core::types::{BlockNumber, Log, Transaction};
+use serde::Serializer;
+
+use crate::{
+    host::{AccountLoad, SStoreResult, SelfDestructResult, StateLoad},
+    interpreter::{CallInputs, CreateInputs},
+    Env, ExecutionResult, ExitStatus, FrameResult, Host, Interpreter,
+};
+
+mod call;
+mod eip3155;
+mod prestate;
+
+pub use call::{revert_reason, CallFrame, CallLog, CallTracer};
+pub use eip3155::Eip3155Tracer;
+pub use prestate::{PrestateAccount, PrestateTrace, PrestateTracer};
+
+/// Observes a transaction as it executes. Every hook does nothing by default, and
+/// execution is the same whichever tracer watches it.
+pub trait Tracer {
+    /// Called once the transaction is found valid, before its gas is bought.
+    fn transaction_start(&mut self, _env: &Env, _tx: &Transaction, _sender: Address) {}
+
+    /// Called when a call starts, the transaction's own included, before the value
+    /// moves. Calls that fail before starting, for lack of balance or depth, are not
+    /// seen.
+    fn call_start(&mut self, _inputs: &CallInputs) {}
+
+    /// Called when a contract creation starts, with the address of the new contract.
+    fn create_start(&mut self, _inputs: &CreateInputs, _address: Address) {}
+
+    /// Called when the latest call or creation to start has ended.
+    fn frame_end(&mut self, _result: &FrameResult) {}
+
+    /// Called before each instruction, with the frame about to run it.
+    fn step(&mut self, _interpreter: &Interpreter) {}
+
+    /// Called after each instruction, with the status it ended its frame with if it
+    /// did. Instructions that make a call or creation end before it runs.
+    fn step_end(&mut self, _interpreter: &Interpreter, _status: Option<ExitStatus>) {}
+
+    /// Called when running code emits a log. It is dropped again if its frame fails.
+    fn log(&mut self, _log: &Log) {}
+
+    /// Called when running code self-destructs, with the balance it sends to `target`.
+    fn selfdestruct(&mut self, _address: Address, _target: Address, _value: U256) {}
+
+    /// Called once the transaction is done and its fees are settled.
+    fn transaction_end(&mut self, _result: &ExecutionResult) {}
+}
+
+/// The tracer for untraced execution.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopTracer;
+
+impl Tracer for NoopTracer {}
+
+/// The host of an instruction, which shows the logs and self-destructs it makes to the
+/// tracer.
+pub(crate) struct TracedHost<'a, H: ?Sized, T: ?Sized> {
+    pub host: &'a mut H,
+    pub tracer: &'a mut T,
+}
+
+impl<H: Host + ?Sized, T: Tracer + ?Sized> Host for TracedHost<'_, H, T> {
+    fn env(&self) -> &Env {
+        self.host.env()
+    }
+
+    fn load_account(&mut self, address: Address) -> anyhow::Result<StateLoad<AccountLoad>> {
+        self.host.load_account(address)
+    }
+
+    fn balance(&mut self, address: Address) -> anyhow::Result<StateLoad<U256>> {
+        self.host.balance(address)
+    }
+
+    fn code(&mut self, address: Address) -> anyhow::Result<StateLoad<Bytes>> {
+        self.host.code(address)
+    }
+
+    fn code_hash(&mut self, address: Address) -> anyhow::Result<StateLoad<H256>> {
+        self.host.code_hash(address)
+    }
+
+    fn sload(&mut self, address: Address, slot: H256) -> anyhow::Result<StateLoad<U256>> {
+        self.host.sload(address, slot)
+    }
+
+    fn sstore(
+        &mut self,
+        address: Address,
+        slot: H256,
+        value: U256,
+    ) -> anyhow::Result<StateLoad<SStoreResult>> {
+        self.host.sstore(address, slot, value)
+    }
+
+    fn tload(&mut self, address: Address, slot: H256) -> U256 {
+        self.host.tload(address, slot)
+    }
+
+    fn tstore(&mut self, address: Address, slot: H256, value: U256) {
+        self.host.tstore(address, slot, value)
+    }
+
+    fn block_hash(&mut self, number: BlockNumber) -> anyhow::Result<H256> {
+        self.host.block_hash(number)
+    }
+
+    fn log(&mut self, log: Log) {
+        self.tracer.log(&log);
+        self.host.log(log)
+    }
+
+    fn selfdestruct(
+        &mut self,
+        address: Address,
+        target: Address,
+    ) -> anyhow::Result<StateLoad<SelfDestructResult>> {
+        let result = self.host.selfdestruct(address, target)?;
+        self.tracer
+            .selfdestruct(address, target, result.data.balance);
+        Ok(result)
+    }
+}
+
+/// Serializes a number as a JSON-RPC quantity.
+fn quantity<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{value:#x}"))
+}
+
+/// Serializes bytes as 0x-prefixed hex.
+fn hex<S: Serializer>(value: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!(
+        "0x{}",
+        value
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+    ))
+}
@@ -0,0 +1,381 @@
+//! The call tree of a transaction in the format of geth's `callTracer`: every call and
+//! creation with its input, output, gas and error, and optionally the logs it emitted.
+
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use reec_core::types::{Log, Transaction};
+use serde::Serialize;
+
+use super::{hex, quantity, Tracer};
+use crate::{
+    interpreter::{CallInputs, CallScheme, CreateInputs, CreateScheme},
+    Env, ExecutionResult, ExitStatus, FrameResult,
+};
+
+/// Selector of Solidity's `Error(string)` revert data.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// Selector of Solidity's `Panic(uint256)` revert data.
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    /// The opcode that made the call: `CALL`, `CREATE2`, `SELFDESTRUCT` and so on.
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub from: Address,
+    /// The callee, the code address for `CALLCODE` and `DELEGATECALL`. `None` for
+    /// creations that failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Address>,
+    /// `None` for static calls, which carry no value.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<U256>,
+    #[serde(serialize_with = "quantity")]
+    pub gas: u64,
+    #[serde(serialize_with = "quantity")]
+    pub gas_used: u64,
+    #[serde(serialize_with = "hex")]
+    pub input: Bytes,
+    /// Return data, or revert data if the call reverted.
+    #[serde(skip_serializing_if = "Bytes::is_empty", serialize_with = "hex")]
+    pub output: Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The reason in Solidity revert data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallFrame>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub logs: Vec<CallLog>,
+}
+
+impl CallFrame {
+    fn new(
+        kind: &'static str,
+        from: Address,
+        to: Address,
+        value: Option<U256>,
+        gas: u64,
+        input: Bytes,
+    ) -> Self {
+        CallFrame {
+            kind,
+            from,
+            to: Some(to),
+            value,
+            gas,
+            gas_used: 0,
+            input,
+            output: Bytes::new(),
+            error: None,
+            revert_reason: None,
+            calls: Vec::new(),
+            logs: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CallLog {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    #[serde(serialize_with = "hex")]
+    pub data: Bytes,
+    /// Number of calls the frame had made when it emitted the log.
+    #[serde(serialize_with = "quantity")]
+    pub position: u64,
+}
+
+/// Builds the call tree as the transaction runs. The top frame's gas is the
+/// transaction's gas limit and its gas used is the transaction's, refunds deducted.
+#[derive(Debug, Default)]
+pub struct CallTracer {
+    only_top_call: bool,
+    with_log: bool,
+    gas_limit: u64,
+    /// Frames that have started and not ended yet, the transaction's own first.
+    stack: Vec<CallFrame>,
+    top: Option<CallFrame>,
+}
+
+impl CallTracer {
+    /// With `only_top_call` the frames of inner calls are left out, with `with_log`
+    /// the logs of the frames that did not fail are kept.
+    pub fn new(only_top_call: bool, with_log: bool) -> Self {
+        CallTracer {
+            only_top_call,
+            with_log,
+            ..Default::default()
+        }
+    }
+
+    /// The transaction's frame, `None` if the transaction was not run.
+    pub fn finish(self) -> Option<CallFrame> {
+        self.top
+    }
+
+    fn start(&mut self, mut frame: CallFrame) {
+        if self.stack.is_empty() {
+            frame.gas = self.gas_limit;
+        }
+        self.stack.push(frame);
+    }
+
+    /// Whether the events of the running frame are left out.
+    fn skips_inner(&self) -> bool {
+        self.only_top_call && self.stack.len() > 1
+    }
+}
+
+impl Tracer for CallTracer {
+    fn transaction_start(&mut self, _env: &Env, tx: &Transaction, _sender: Address) {
+        self.gas_limit = tx.gas_limit();
+    }
+
+    fn call_start(&mut self, inputs: &CallInputs) {
+        let (kind, from, to) = match inputs.scheme {
+            CallScheme::Call => ("CALL", inputs.caller, inputs.target),
+            CallScheme::StaticCall => ("STATICCALL", inputs.caller, inputs.target),
+            CallScheme::CallCode => ("CALLCODE", inputs.target, inputs.code_address),
+            CallScheme::DelegateCall => ("DELEGATECALL", inputs.target, inputs.code_address),
+            CallScheme::ExtCall => ("EXTCALL", inputs.caller, inputs.target),
+            CallScheme::ExtStaticCall => ("EXTSTATICCALL", inputs.caller, inputs.target),
+            CallScheme::ExtDelegateCall => ("EXTDELEGATECALL", inputs.target, inputs.code_address),
+        };
+        let is_static = matches!(
+            inputs.scheme,
+            CallScheme::StaticCall | CallScheme::ExtStaticCall
+        );
+        let value = (!is_static).then_some(inputs.value);
+        self.start(CallFrame::new(
+            kind,
+            from,
+            to,
+            value,
+            inputs.gas_limit,
+            inputs.input.clone(),
+        ));
+    }
+
+    fn create_start(&mut self, inputs: &CreateInputs, address: Address) {
+        let kind = match inputs.scheme {
+            CreateScheme::Create => "CREATE",
+            CreateScheme::Create2 { .. } => "CREATE2",
+            CreateScheme::EofCreate { .. } => "EOFCREATE",
+        };
+        let frame = CallFrame::new(
+            kind,
+            inputs.caller,
+            address,
+            Some(inputs.value),
+            inputs.gas_limit,
+            inputs.init_code.clone(),
+        );
+        self.start(frame);
+    }
+
+    fn frame_end(&mut self, result: &FrameResult) {
+        let Some(mut frame) = self.stack.pop() else {
+            return;
+        };
+        frame.gas_used = frame.gas.saturating_sub(result.gas.remaining());
+        if result.status.is_success() {
+            frame.output = result.output.clone();
+        } else {
+            frame.error = Some(result.status.to_string());
+            if frame.kind.contains("CREATE") {
+                frame.to = None;
+            }
+            if result.status == ExitStatus::Revert {
+                frame.output = result.output.clone();
+                frame.revert_reason = revert_reason(&result.output);
+            }
+        }
+        match self.stack.last_mut() {
+            Some(parent) if !self.only_top_call => parent.calls.push(frame),
+            Some(_) => {}
+            None => self.top = Some(frame),
+        }
+    }
+
+    fn log(&mut self, log: &Log) {
+        if !self.with_log || self.skips_inner() {
+            return;
+        }
+        let Some(frame) = self.stack.last_mut() else {
+            return;
+        };
+        frame.logs.push(CallLog {
+            address: log.address,
+            topics: log.topics.clone(),
+            data: log.data.clone(),
+            position: frame.calls.len() as u64,
+        });
+    }
+
+    fn selfdestruct(&mut self, address: Address, target: Address, value: U256) {
+        if self.only_top_call {
+            return;
+        }
+        let Some(frame) = self.stack.last_mut() else {
+            return;
+        };
+        frame.calls.push(CallFrame::new(
+            "SELFDESTRUCT",
+            address,
+            target,
+            Some(value),
+            0,
+            Bytes::new(),
+        ));
+    }
+
+    fn transaction_end(&mut self, result: &ExecutionResult) {
+        let Some(top) = &mut self.top else { return };
+        top.gas_used = result.gas_used;
+        clear_failed_logs(top, false);
+    }
+}
+
+/// Drops the logs of the frames that failed, along with those of all their inner
+/// frames, as their changes were reverted.
+fn clear_failed_logs(frame: &mut CallFrame, parent_failed: bool) {
+    let failed = parent_failed || frame.error.is_some();
+    if failed {
+        frame.logs.clear();
+    }
+    for call in &mut frame.calls {
+        clear_failed_logs(call, failed);
+    }
+}
+
+/// Decodes the `Error(string)` and `Panic(uint256)` revert data Solidity produces.
+pub fn revert_reason(output: &[u8]) -> Option<String> {
+    let (selector, data) = output.split_first_chunk::<4>()?;
+    let word = |index: usize| {
+        data.get(index * 32..(index + 1) * 32)
+            .map(U256::from_big_endian)
+    };
+    match *selector {
+        ERROR_SELECTOR => {
+            let offset = usize::try_from(word(0)?).ok()?;
+            let len_word = data
+                .get(offset..offset.checked_add(32)?)
+                .map(U256::from_big_endian)?;
+            let len = usize::try_from(len_word).ok()?;
+            let start = offset + 32;
+            let reason = data.get(start..start.checked_add(len)?)?;
+            String::from_utf8(reason.to_vec()).ok()
+        }
+        PANIC_SELECTOR => {
+            let code = word(0)?;
+            let reason = match code.low_u64() {
+                _ if code > U256::from(u64::MAX) => None,
+                0x00 => Some("generic panic"),
+                0x01 => Some("assert(false)"),
+                0x11 => Some("arithmetic underflow or overflow"),
+                0x12 => Some("division or modulo by zero"),
+                0x21 => Some("enum overflow"),
+                0x22 => Some("invalid encoded storage byte array accessed"),
+                0x31 => Some("out-of-bounds array access; popping on an empty array"),
+                0x32 => Some("out-of-bounds access of an array or bytesN"),
+                0x41 => Some("out of memory"),
+                0x51 => Some("uninitialized function"),
+                _ => None,
+            };
+            Some(reason.map_or_else(|| format!("unknown panic code: {code:#x}"), str::to_string))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethereum_types::{Address, U256};
+    use reec_core::types::{EIP1559Transaction, Transaction, TxKind};
+
+    use super::*;
+    use crate::{execute_transaction_with_tracer, BlockEnv, Env, Fork, JournaledState, MemoryDb};
+
+    #[test]
+    fn nests_calls_with_their_logs() {
+        let (sender, caller, callee) = (
+            Address::repeat_byte(0xaa),
+            Address::repeat_byte(0xdd),
+            Address::repeat_byte(0xbb),
+        );
+        let mut db = MemoryDb::default();
+        db.insert_account(sender, 0, U256::exp10(18), Bytes::new());
+        // CALL(GAS, callee, 0, 0, 0, 0, 0)
+        let mut code = vec![0x5f, 0x5f, 0x5f, 0x5f, 0x5f, 0x73];
+        code.extend_from_slice(callee.as_bytes());
+        code.extend_from_slice(&[0x5a, 0xf1, 0x00]);
+        db.insert_account(caller, 1, U256::zero(), code.into());
+        // LOG0(0, 0)
+        db.insert_account(
+            callee,
+            1,
+            U256::zero(),
+            Bytes::from_static(&[0x5f, 0x5f, 0xa0, 0x00]),
+        );
+        let block = BlockEnv {
+            gas_limit: 30_000_000,
+            ..Default::default()
+        };
+        let mut state = JournaledState::new(
+            &db,
+            Env {
+                fork: Fork::Cancun,
+                chain_id: 1,
+                block,
+                ..Default::default()
+            },
+        );
+        let tx = Transaction::EIP1559Transaction(EIP1559Transaction {
+            chain_id: 1,
+            max_fee_per_gas: 1.into(),
+            gas_limit: 100_000,
+            to: TxKind::Call(caller),
+            data: Bytes::from_static(&[0x12, 0x34]),
+            ..Default::default()
+        });
+        let mut tracer = CallTracer::new(false, true);
+        let result = execute_transaction_with_tracer(&mut state, &tx, sender, &mut tracer).unwrap();
+
+        let top = tracer.finish().unwrap();
+        assert_eq!((top.kind, top.from, top.to), ("CALL", sender, Some(caller)));
+        assert_eq!((top.gas, top.gas_used), (100_000, result.gas_used));
+        assert_eq!(top.calls.len(), 1);
+        let inner = serde_json::to_value(&top.calls[0]).unwrap();
+        let callee = format!("{callee:?}");
+        assert_eq!(inner["type"], "CALL");
+        assert_eq!(inner["to"], callee);
+        assert_eq!(inner["value"], "0x0");
+        assert_eq!(inner["input"], "0x");
+        assert_eq!(
+            inner["logs"],
+            serde_json::json!([{ "address": callee, "topics": [], "data": "0x", "position": "0x0" }])
+        );
+        assert!(inner.get("output").is_none() && inner.get("error").is_none());
+    }
+
+    #[test]
+    fn decodes_revert_reasons() {
+        let mut error = ERROR_SELECTOR.to_vec();
+        error.extend_from_slice(H256::from_low_u64_be(32).as_bytes());
+        error.extend_from_slice(H256::from_low_u64_be(2).as_bytes());
+        error.extend_from_slice(b"no");
+        error.extend_from_slice(&[0; 30]);
+        assert_eq!(revert_reason(&error).as_deref(), Some("no"));
+
+        let mut panic = PANIC_SELECTOR.to_vec();
+        panic.extend_from_slice(H256::from_low_u64_be(0x11).as_bytes());
+        assert_eq!(
+            revert_reason(&panic).as_deref(),
+            Some("arithmetic underflow or overflow")
+        );
+        assert_eq!(revert_reason(&[0xde, 0xad]), None);
+    }
+}
@@ -0,0 +1,202 @@
+//! Step traces in the JSON lines format of EIP-3155, as printed by geth's `evm --json`,
+//! so that the two can be diffed line by line.
+
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use super::Tracer;
+use crate::{opcode, ExecutionResult, ExitStatus, Interpreter};
+
+/// Writes a line for every instruction and a summary line for the transaction.
+///
+/// Writing stops at the first I/O error, which [`Eip3155Tracer::finish`] returns.
+pub struct Eip3155Tracer<W> {
+    writer: W,
+    /// The line of the instruction being run and the gas before it, completed once it
+    /// has run.
+    pending: Option<(Line, u64)>,
+    /// Refund of each frame on the call stack as of its latest instruction. geth keeps
+    /// a single counter for the transaction, which is their sum.
+    refunds: Vec<i64>,
+    error: Option<io::Error>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Line {
+    pc: usize,
+    op: u8,
+    gas: String,
+    gas_cost: String,
+    mem_size: usize,
+    stack: Vec<String>,
+    /// Starts at one for the transaction's own frame.
+    depth: usize,
+    refund: u64,
+    op_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Summary {
+    /// Hex without a prefix, as geth prints it.
+    output: String,
+    gas_used: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl<W: Write> Eip3155Tracer<W> {
+    pub fn new(writer: W) -> Self {
+        Eip3155Tracer {
+            writer,
+            pending: None,
+            refunds: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Returns the writer, or the error that stopped the trace.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write(&mut self, line: &impl Serialize) {
+        if self.error.is_some() {
+            return;
+        }
+        let result = serde_json::to_writer(&mut self.writer, line)
+            .map_err(io::Error::from)
+            .and_then(|()| self.writer.write_all(b"\n"));
+        self.error = result.err();
+    }
+}
+
+impl<W: Write> Tracer for Eip3155Tracer<W> {
+    fn step(&mut self, interpreter: &Interpreter) {
+        self.refunds.truncate(interpreter.depth);
+        self.refunds.resize(interpreter.depth, 0);
+        self.refunds.push(interpreter.gas.refunded());
+        let op = interpreter.current_opcode();
+        let gas = interpreter.gas.remaining();
+        let line = Line {
+            pc: interpreter.pc,
+            op,
+            gas: format!("{gas:#x}"),
+            gas_cost: String::new(),
+            mem_size: interpreter.memory.len(),
+            stack: interpreter
+                .stack
+                .data()
+                .iter()
+                .map(|item| format!("{item:#x}"))
+                .collect(),
+            depth: interpreter.depth + 1,
+            refund: self.refunds.iter().sum::<i64>().max(0) as u64,
+            op_name: opcode::name(op),
+            error: None,
+        };
+        self.pending = Some((line, gas));
+    }
+
+    fn step_end(&mut self, interpreter: &Interpreter, status: Option<ExitStatus>) {
+        let Some((mut line, gas)) = self.pending.take() else {
+            return;
+        };
+        line.gas_cost = format!("{:#x}", gas.saturating_sub(interpreter.gas.remaining()));
+        line.error = status
+            .filter(|status| !status.is_success())
+            .map(|status| status.to_string());
+        self.write(&line);
+    }
+
+    fn transaction_end(&mut self, result: &ExecutionResult) {
+        let summary = Summary {
+            output: result
+                .output
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+            gas_used: format!("{:#x}", result.gas_used),
+            error: (!result.is_success()).then(|| result.status.to_string()),
+        };
+        self.write(&summary);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use ethereum_types::{Address, U256};
+    use reec_core::types::{EIP1559Transaction, Transaction, TxKind};
+
+    use super::*;
+    use crate::{execute_transaction_with_tracer, BlockEnv, Env, Fork, JournaledState, MemoryDb};
+
+    fn trace(code: &[u8]) -> Vec<String> {
+        let (sender, contract) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xdd));
+        let mut db = MemoryDb::default();
+        db.insert_account(sender, 0, U256::exp10(18), Bytes::new());
+        db.insert_account(contract, 1, U256::zero(), Bytes::copy_from_slice(code));
+        let block = BlockEnv {
+            gas_limit: 30_000_000,
+            ..Default::default()
+        };
+        let mut state = JournaledState::new(
+            &db,
+            Env {
+                fork: Fork::Cancun,
+                chain_id: 1,
+                block,
+                ..Default::default()
+            },
+        );
+        let tx = Transaction::EIP1559Transaction(EIP1559Transaction {
+            chain_id: 1,
+            max_fee_per_gas: 1.into(),
+            gas_limit: 100_000,
+            to: TxKind::Call(contract),
+            ..Default::default()
+        });
+        let mut tracer = Eip3155Tracer::new(Vec::new());
+        execute_transaction_with_tracer(&mut state, &tx, sender, &mut tracer).unwrap();
+        let output = String::from_utf8(tracer.finish().unwrap()).unwrap();
+        output.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn matches_geth_output() {
+        // MSTORE(0, 42) then RETURN(0, 32)
+        let lines = trace(&[0x60, 0x2a, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xf3]);
+        let expected = [
+            r#"{"pc":0,"op":96,"gas":"0x13498","gasCost":"0x3","memSize":0,"stack":[],"depth":1,"refund":0,"opName":"PUSH1"}"#,
+            r#"{"pc":2,"op":95,"gas":"0x13495","gasCost":"0x2","memSize":0,"stack":["0x2a"],"depth":1,"refund":0,"opName":"PUSH0"}"#,
+            r#"{"pc":3,"op":82,"gas":"0x13493","gasCost":"0x6","memSize":0,"stack":["0x2a","0x0"],"depth":1,"refund":0,"opName":"MSTORE"}"#,
+            r#"{"pc":4,"op":96,"gas":"0x1348d","gasCost":"0x3","memSize":32,"stack":[],"depth":1,"refund":0,"opName":"PUSH1"}"#,
+            r#"{"pc":6,"op":95,"gas":"0x1348a","gasCost":"0x2","memSize":32,"stack":["0x20"],"depth":1,"refund":0,"opName":"PUSH0"}"#,
+            r#"{"pc":7,"op":243,"gas":"0x13488","gasCost":"0x0","memSize":32,"stack":["0x20","0x0"],"depth":1,"refund":0,"opName":"RETURN"}"#,
+            r#"{"output":"000000000000000000000000000000000000000000000000000000000000002a","gasUsed":"0x5218"}"#,
+        ];
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn reports_errors() {
+        // ADD on an empty stack
+        let lines = trace(&[0x01]);
+        assert_eq!(
+            lines,
+            [
+                r#"{"pc":0,"op":1,"gas":"0x13498","gasCost":"0x3","memSize":0,"stack":[],"depth":1,"refund":0,"opName":"ADD","error":"stack underflow"}"#,
+                r#"{"output":"","gasUsed":"0x186a0","error":"stack underflow"}"#,
+            ]
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! The accounts a transaction touches in the format of geth's `prestateTracer`: their
+//! state before the transaction or, in diff mode, what it changed and from what.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use reec_core::types::{AccountInfo, Transaction, TxKind};
+use serde::Serialize;
+
+use super::{hex, Tracer};
+use crate::{
+    interpreter::{u256_to_address, CreateInputs},
+    opcode, Database, Env, Interpreter, JournaledState,
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct PrestateAccount {
+    /// Always present in the state before, only if it changed in the state after.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub nonce: u64,
+    #[serde(skip_serializing_if = "Bytes::is_empty", serialize_with = "hex")]
+    pub code: Bytes,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<H256, H256>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum PrestateTrace {
+    /// Every account the transaction touched, with the slots it read or wrote.
+    Prestate(BTreeMap<Address, PrestateAccount>),
+    /// The accounts the transaction changed, before with every field and after with
+    /// the changed ones. Only changed slots are kept, and zero slots are left out of
+    /// both sides. Deleted accounts are left out of `post`.
+    Diff {
+        pre: BTreeMap<Address, PrestateAccount>,
+        post: BTreeMap<Address, PrestateAccount>,
+    },
+}
+
+/// Records the accounts and slots a transaction touches as it runs. Their values are
+/// read afterwards by [`PrestateTracer::finish`], from the state the transaction ran
+/// on and the state it left.
+#[derive(Debug, Default)]
+pub struct PrestateTracer {
+    diff_mode: bool,
+    /// Touched accounts, with the slots read or written in each.
+    touched: BTreeMap<Address, BTreeSet<H256>>,
+    /// Contracts the transaction created.
+    created: BTreeSet<Address>,
+}
+
+impl PrestateTracer {
+    pub fn new(diff_mode: bool) -> Self {
+        PrestateTracer {
+            diff_mode,
+            ..Default::default()
+        }
+    }
+
+    /// Builds the trace from `pre`, a copy of the state taken before the transaction,
+    /// and `post`, the state it ran on.
+    pub fn finish<DB: Database>(
+        self,
+        pre: &mut JournaledState<DB>,
+        post: &mut JournaledState<DB>,
+    ) -> anyhow::Result<PrestateTrace> {
+        let mut accounts = BTreeMap::new();
+        for (&address, slots) in &self.touched {
+            let info = pre.account_info(address)?;
+            let account = PrestateAccount {
+                balance: Some(info.as_ref().map_or_else(U256::zero, |info| info.balance)),
+                nonce: info.as_ref().map_or(0, |info| info.nonce),
+                code: pre.account_code(address)?,
+                storage: slots
+                    .iter()
+                    .map(|&slot| Ok((slot, u256_to_h256(pre.storage(address, slot)?))))
+                    .collect::<anyhow::Result<_>>()?,
+            };
+            accounts.insert(address, (info, account));
+        }
+
+        if !self.diff_mode {
+            let prestate = accounts
+                .into_iter()
+                .filter(|(address, (info, _))| !self.created_from_nothing(*address, info.as_ref()))
+                .map(|(address, (_, account))| (address, account))
+                .collect();
+            return Ok(PrestateTrace::Prestate(prestate));
+        }
+
+        let mut pre_accounts = BTreeMap::new();
+        let mut post_accounts = BTreeMap::new();
+        for (address, (info, mut before)) in accounts {
+            let Some(post_info) = post.account_info(address)? else {
+                // Deleted accounts are kept whole, those that never existed dropped.
+                if info.is_some() {
+                    pre_accounts.insert(address, before);
+                }
+                continue;
+            };
+            let post_code = post.account_code(address)?;
+            let mut after = PrestateAccount::default();
+            let mut modified = false;
+            if before.balance != Some(post_info.balance) {
+                after.balance = Some(post_info.balance);
+                modified = true;
+            }
+            if before.nonce != post_info.nonce {
+                after.nonce = post_info.nonce;
+                modified = true;
+            }
+            if before.code != post_code {
+                after.code = post_code;
+                modified = true;
+            }
+            let mut storage = BTreeMap::new();
+            for (slot, value) in before.storage {
+                let post_value = u256_to_h256(post.storage(address, slot)?);
+                if value == post_value {
+                    continue;
+                }
+                modified = true;
+                if !value.is_zero() {
+                    storage.insert(slot, value);
+                }
+                if !post_value.is_zero() {
+                    after.storage.insert(slot, post_value);
+                }
+            }
+            before.storage = storage;
+            if modified {
+                if !self.created_from_nothing(address, info.as_ref()) {
+                    pre_accounts.insert(address, before);
+                }
+                post_accounts.insert(address, after);
+            }
+        }
+        Ok(PrestateTrace::Diff {
+            pre: pre_accounts,
+            post: post_accounts,
+        })
+    }
+
+    fn touch(&mut self, address: Address) {
+        self.touched.entry(address).or_default();
+    }
+
+    fn touch_slot(&mut self, address: Address, slot: H256) {
+        self.touched.entry(address).or_default().insert(slot);
+    }
+
+    /// Whether the transaction created the contract in the place of an empty account,
+    /// so it had no state before to report.
+    fn created_from_nothing(&self, address: Address, info: Option<&AccountInfo>) -> bool {
+        self.created.contains(&address) && info.is_none_or(AccountInfo::is_empty)
+    }
+}
+
+impl Tracer for PrestateTracer {
+    fn transaction_start(&mut self, env: &Env, tx: &Transaction, sender: Address) {
+        self.touch(sender);
+        self.touch(env.block.coinbase);
+        if let TxKind::Call(to) = tx.to() {
+            self.touch(to);
+        }
+        for authorization in tx.authorization_list().unwrap_or_default() {
+            if let Some(authority) = authorization.authority() {
+                self.touch(authority);
+            }
+        }
+    }
+
+    fn create_start(&mut self, _inputs: &CreateInputs, address: Address) {
+        self.touch(address);
+        self.created.insert(address);
+    }
+
+    fn step(&mut self, interpreter: &Interpreter) {
+        let stack = &interpreter.stack;
+        match interpreter.current_opcode() {
+            opcode::SLOAD | opcode::SSTORE => {
+                if let Ok(slot) = stack.peek(0) {
+                    self.touch_slot(interpreter.contract.address, u256_to_h256(slot));
+                }
+            }
+            opcode::BALANCE
+            | opcode::EXTCODESIZE
+            | opcode::EXTCODECOPY
+            | opcode::EXTCODEHASH
+            | opcode::SELFDESTRUCT
+            | opcode::EXTCALL
+            | opcode::EXTDELEGATECALL
+            | opcode::EXTSTATICCALL => {
+                if let Ok(address) = stack.peek(0) {
+                    self.touch(u256_to_address(address));
+                }
+            }
+            opcode::CALL | opcode::CALLCODE | opcode::DELEGATECALL | opcode::STATICCALL => {
+                if let Ok(address) = stack.peek(1) {
+                    self.touch(u256_to_address(address));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn u256_to_h256(value: U256) -> H256 {
+    let mut bytes = [0; 32];
+    value.to_big_endian(&mut bytes);
+    H256(bytes)
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+#[cfg(test)]
+mod test {
+    use reec_core::types::EIP1559Transaction;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{execute_transaction_with_tracer, BlockEnv, Fork, MemoryDb};
+
+    fn trace(diff_mode: bool) -> serde_json::Value {
+        let (sender, contract) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xdd));
+        let mut db = MemoryDb::default();
+        db.insert_account(sender, 0, 1_000_000.into(), Bytes::new());
+        // SSTORE(0, SLOAD(1))
+        db.insert_account(
+            contract,
+            1,
+            U256::zero(),
+            Bytes::from_static(&[0x60, 0x01, 0x54, 0x5f, 0x55, 0x00]),
+        );
+        db.insert_storage(contract, H256::zero(), 5.into());
+        db.insert_storage(contract, H256::from_low_u64_be(1), 7.into());
+        let block = BlockEnv {
+            gas_limit: 30_000_000,
+            coinbase: Address::repeat_byte(0xcc),
+            ..Default::default()
+        };
+        let mut state = JournaledState::new(
+            &db,
+            Env {
+                fork: Fork::Cancun,
+                chain_id: 1,
+                block,
+                ..Default::default()
+            },
+        );
+        let tx = Transaction::EIP1559Transaction(EIP1559Transaction {
+            chain_id: 1,
+            max_priority_fee_per_gas: 1.into(),
+            max_fee_per_gas: 1.into(),
+            gas_limit: 100_000,
+            to: TxKind::Call(contract),
+            ..Default::default()
+        });
+        let mut pre = state.clone();
+        let mut tracer = PrestateTracer::new(diff_mode);
+        execute_transaction_with_tracer(&mut state, &tx, sender, &mut tracer).unwrap();
+        serde_json::to_value(tracer.finish(&mut pre, &mut state).unwrap()).unwrap()
+    }
+
+    fn slot(value: u64) -> String {
+        format!("{:?}", H256::from_low_u64_be(value))
+    }
+
+    #[test]
+    fn reports_touched_state() {
+        let contract = json!({
+            "balance": "0x0",
+            "nonce": 1,
+            "code": "0x6001545f5500",
+            "storage": { slot(0): slot(5), slot(1): slot(7) },
+        });
+        assert_eq!(
+            trace(false),
+            json!({
+                "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa": { "balance": "0xf4240" },
+                "0xcccccccccccccccccccccccccccccccccccccccc": { "balance": "0x0" },
+                "0xdddddddddddddddddddddddddddddddddddddddd": contract,
+            })
+        );
+    }
+
+    #[test]
+    fn reports_changes_in_diff_mode() {
+        let trace = trace(true);
+        let contract = "0xdddddddddddddddddddddddddddddddddddddddd";
+        assert_eq!(
+            trace["pre"][contract],
+            json!({ "balance": "0x0", "nonce": 1, "code": "0x6001545f5500", "storage": { slot(0): slot(5) } })
+        );
+        assert_eq!(
+            trace["post"][contract],
+            json!({ "storage": { slot(0): slot(7) } })
+        );
+        let sender = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+        assert_eq!(trace["pre"][sender], json!({ "balance": "0xf4240" }));
+        assert_eq!(trace["post"][sender]["nonce"], 1);
+        assert!(trace["post"]["0xcccccccccccccccccccccccccccccccccccccccc"]["balance"].is_string());
+    }
+}
@@ -0,0 +1,931 @@
+//! Everything around the frames of a transaction: checking it against the sender's
+//! account and the block, buying its gas, and settling refunds and fees afterwards.
+
+use std::fmt;
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use reec_core::types::{
+    AuthorizationTuple, Log, Receipt, Transaction, TxKind, TxType, EMPTY_CODE_HASH,
+};
+
+use crate::{
+    bytecode::{delegated_address, delegation_designator},
+    frame,
+    gas::{self, words},
+    interpreter::{CallInputs, CallScheme, CreateInputs, CreateScheme, MAX_INITCODE_SIZE},
+    precompiles,
+    tracer::{NoopTracer, Tracer},
+    Action, Database, ExitStatus, Fork, Host, JournaledState, TxEnv,
+};
+
+/// EIP-4844 version byte of a KZG commitment hash.
+const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+
+/// Reasons a transaction cannot be included in a block. It changes no state.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvalidTransaction {
+    TypeNotSupported(TxType),
+    ChainIdMismatch,
+    NonceTooLow {
+        expected: u64,
+        got: u64,
+    },
+    NonceTooHigh {
+        expected: u64,
+        got: u64,
+    },
+    /// EIP-2681: the nonce cannot be incremented any further.
+    NonceMax,
+    /// EIP-3607: transactions cannot come from accounts with code, other than a
+    /// delegation designator (EIP-7702).
+    SenderNotEoa,
+    GasLimitExceedsBlock {
+        gas_limit: u64,
+        available: u64,
+    },
+    FeeCapBelowBaseFee,
+    TipAboveFeeCap,
+    IntrinsicGasTooLow {
+        intrinsic: u64,
+        gas_limit: u64,
+    },
+    InitCodeSizeExceeded(usize),
+    InsufficientFunds {
+        balance: U256,
+        cost: U256,
+    },
+    NoBlobs,
+    TooManyBlobs(usize),
+    InvalidBlobVersion,
+    BlobFeeCapBelowBlobBaseFee,
+    EmptyAuthorizationList,
+}
+
+impl fmt::Display for InvalidTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidTransaction::TypeNotSupported(tx_type) => {
+                write!(f, "transaction type {} not supported", *tx_type as u8)
+            }
+            InvalidTransaction::ChainIdMismatch => f.write_str("invalid chain id for signer"),
+            InvalidTransaction::NonceTooLow { expected, got } => {
+                write!(f, "nonce too low: tx {got}, state {expected}")
+            }
+            InvalidTransaction::NonceTooHigh { expected, got } => {
+                write!(f, "nonce too high: tx {got}, state {expected}")
+            }
+            InvalidTransaction::NonceMax => f.write_str("nonce has max value"),
+            InvalidTransaction::SenderNotEoa => f.write_str("sender not an eoa"),
+            InvalidTransaction::GasLimitExceedsBlock {
+                gas_limit,
+                available,
+            } => {
+                write!(
+                    f,
+                    "gas limit reached: tx {gas_limit}, available {available}"
+                )
+            }
+            InvalidTransaction::FeeCapBelowBaseFee => {
+                f.write_str("max fee per gas less than block base fee")
+            }
+            InvalidTransaction::TipAboveFeeCap => {
+                f.write_str("max priority fee per gas higher than max fee per gas")
+            }
+            InvalidTransaction::IntrinsicGasTooLow {
+                intrinsic,
+                gas_limit,
+            } => {
+                write!(
+                    f,
+                    "intrinsic gas too low: have {gas_limit}, want {intrinsic}"
+                )
+            }
+            InvalidTransaction::InitCodeSizeExceeded(len) => {
+                write!(f, "max initcode size exceeded: {len}")
+            }
+            InvalidTransaction::InsufficientFunds { balance, cost } => {
+                write!(
+                    f,
+                    "insufficient funds for gas * price + value: have {balance}, want {cost}"
+                )
+            }
+            InvalidTransaction::NoBlobs => f.write_str("blob transaction without blobs"),
+            InvalidTransaction::TooManyBlobs(count) => write!(f, "too many blobs: {count}"),
+            InvalidTransaction::InvalidBlobVersion => {
+                f.write_str("invalid blob versioned hash version")
+            }
+            InvalidTransaction::BlobFeeCapBelowBlobBaseFee => {
+                f.write_str("max fee per blob gas less than block blob gas fee")
+            }
+            InvalidTransaction::EmptyAuthorizationList => {
+                f.write_str("EIP-7702 transaction with empty auth list")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvalidTransaction {}
+
+/// Outcome of an included transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecutionResult {
+    pub status: ExitStatus,
+    /// Gas paid for, after refunds.
+    pub gas_used: u64,
+    pub gas_refunded: u64,
+    /// Return or revert data, or the deployed code for a creation.
+    pub output: Bytes,
+    /// Address of the deployed contract, if the transaction created one.
+    pub contract_address: Option<Address>,
+    pub logs: Vec<Log>,
+}
+
+impl ExecutionResult {
+    pub fn is_success(&self) -> bool {
+        self.status.is_success()
+    }
+
+    pub fn receipt(&self, tx_type: TxType, cumulative_gas_used: u64) -> Receipt {
+        Receipt::new(
+            tx_type,
+            self.is_success(),
+            cumulative_gas_used,
+            self.logs.clone(),
+        )
+    }
+}
+
+/// Gas a transaction pays before executing anything.
+pub fn intrinsic_gas(tx: &Transaction, fork: Fork) -> u64 {
+    let data = tx.data();
+    let zeros = data.iter().filter(|byte| **byte == 0).count() as u64;
+    let non_zero_cost = if fork >= Fork::Istanbul {
+        gas::TX_DATA_NON_ZERO
+    } else {
+        gas::TX_DATA_NON_ZERO_FRONTIER
+    };
+    let mut intrinsic =
+        gas::TX + zeros * gas::TX_DATA_ZERO + (data.len() as u64 - zeros) * non_zero_cost;
+    if tx.to() == TxKind::Create {
+        if fork >= Fork::Homestead {
+            intrinsic += gas::CREATE;
+        }
+        if fork >= Fork::Shanghai {
+            intrinsic += gas::INITCODE_WORD * words(data.len() as u64);
+        }
+    }
+    for (_, keys) in tx.access_list() {
+        intrinsic += gas::ACCESS_LIST_ADDRESS + gas::ACCESS_LIST_STORAGE_KEY * keys.len() as u64;
+    }
+    intrinsic +=
+        gas::PER_EMPTY_ACCOUNT * tx.authorization_list().map_or(0, |list| list.len() as u64);
+    intrinsic
+}
+
+/// EIP-7623 least gas a transaction uses, from Prague, so that calldata heavy ones pay
+/// for the block space they take.
+pub fn floor_data_gas(tx: &Transaction) -> u64 {
+    let data = tx.data();
+    let zeros = data.iter().filter(|byte| **byte == 0).count() as u64;
+    let tokens = zeros + (data.len() as u64 - zeros) * 4;
+    gas::TX + tokens * gas::TOTAL_COST_FLOOR_PER_TOKEN
+}
+
+/// Executes a transaction sent by `sender` on top of `state`, with the block given by
+/// its environment.
+///
+/// Fails with an [`InvalidTransaction`] if the transaction cannot be included, in which
+/// case the state is left as it was. Any other error is fatal.
+pub fn execute_transaction<DB: Database>(
+    state: &mut JournaledState<DB>,
+    tx: &Transaction,
+    sender: Address,
+) -> anyhow::Result<ExecutionResult> {
+    execute_transaction_with_tracer(state, tx, sender, &mut NoopTracer)
+}
+
+/// Executes a transaction like [`execute_transaction`], showing every instruction it
+/// runs to `tracer`. Invalid transactions are not traced.
+pub fn execute_transaction_with_tracer<DB: Database, T: Tracer + ?Sized>(
+    state: &mut JournaledState<DB>,
+    tx: &Transaction,
+    sender: Address,
+    tracer: &mut T,
+) -> anyhow::Result<ExecutionResult> {
+    let fork = state.env().fork;
+    let block = state.env().block.clone();
+    let gas_price = validate(state, tx, sender)?;
+    let gas_limit = tx.gas_limit();
+    let intrinsic = intrinsic_gas(tx, fork);
+    let floor = if fork >= Fork::Prague {
+        floor_data_gas(tx)
+    } else {
+        0
+    };
+
+    state.env_mut().tx = TxEnv {
+        origin: sender,
+        gas_price,
+        blob_hashes: tx.blob_versioned_hashes().to_vec(),
+    };
+    tracer.transaction_start(state.env(), tx, sender);
+    let blob_fee =
+        U256::from(gas::PER_BLOB * tx.blob_versioned_hashes().len() as u64) * block.blob_base_fee;
+    state.sub_balance(sender, U256::from(gas_limit) * gas_price + blob_fee)?;
+
+    warm_accounts(state, sender, tx.to(), tx.access_list());
+
+    let mut authorization_refund = 0;
+    let action = match tx.to() {
+        TxKind::Call(to) => {
+            state.increment_nonce(sender)?;
+            if let Some(authorizations) = tx.authorization_list() {
+                authorization_refund = apply_authorizations(state, authorizations)?;
+            }
+            // The code a delegating recipient runs is warm as well
+            if fork >= Fork::Prague {
+                if let Some(delegate) = delegated_address(&state.account_code(to)?) {
+                    state.warm_address(delegate);
+                }
+            }
+            Action::Call(Box::new(CallInputs {
+                scheme: CallScheme::Call,
+                target: to,
+                code_address: to,
+                caller: sender,
+                value: tx.value(),
+                transfer: true,
+                input: tx.data().clone(),
+                gas_limit: gas_limit - intrinsic,
+                is_static: false,
+                depth: 0,
+            }))
+        }
+        // The nonce is bumped along with the creation, as for `CREATE`
+        TxKind::Create => Action::Create(Box::new(CreateInputs {
+            scheme: CreateScheme::Create,
+            caller: sender,
+            value: tx.value(),
+            init_code: tx.data().clone(),
+            input: Bytes::new(),
+            gas_limit: gas_limit - intrinsic,
+            depth: 0,
+        })),
+    };
+    let (result, contract_address) = frame::execute(state, action, tracer)?;
+
+    let spent = gas_limit - result.gas.remaining();
+    // Authorizations are applied outside of the frames, so their refund survives a failure
+    let execution_refund = if result.status.is_success() {
+        result.gas.refunded().max(0) as u64
+    } else {
+        0
+    };
+    let gas_refunded =
+        (authorization_refund + execution_refund).min(spent / gas::max_refund_quotient(fork));
+    let gas_used = (spent - gas_refunded).max(floor);
+    state.add_balance(sender, U256::from(gas_limit - gas_used) * gas_price)?;
+    let tip = if fork >= Fork::London {
+        gas_price - block.base_fee
+    } else {
+        gas_price
+    };
+    state.pay_fee(U256::from(gas_used) * tip)?;
+
+    let logs = state.finalize_transaction();
+    let result = ExecutionResult {
+        status: result.status,
+        gas_used,
+        gas_refunded,
+        output: result.output,
+        contract_address,
+        logs,
+    };
+    tracer.transaction_end(&result);
+    Ok(result)
+}
+
+/// Runs a call or creation outside of any transaction, as `evm run` tools do: no gas is
+/// bought or refunded, no intrinsic gas is charged and the caller's nonce is only bumped
+/// by a creation. Accounts start warm as for a transaction from the caller, whose
+/// origin and gas price are taken from the environment of `state`.
+///
+/// Fails if the caller cannot pay the value. The result's gas used is the gas the
+/// frames used.
+pub fn execute_message_with_tracer<DB: Database, T: Tracer + ?Sized>(
+    state: &mut JournaledState<DB>,
+    action: Action,
+    tracer: &mut T,
+) -> anyhow::Result<ExecutionResult> {
+    let (caller, to, value, gas_limit) = match &action {
+        Action::Call(inputs) => (
+            inputs.caller,
+            TxKind::Call(inputs.target),
+            inputs.value,
+            inputs.gas_limit,
+        ),
+        Action::Create(inputs) => (
+            inputs.caller,
+            TxKind::Create,
+            inputs.value,
+            inputs.gas_limit,
+        ),
+        Action::Return(_) => return Err(anyhow!("only calls and creations can be run")),
+    };
+    let balance = state.account_info(caller)?.unwrap_or_default().balance;
+    if balance < value {
+        return Err(InvalidTransaction::InsufficientFunds {
+            balance,
+            cost: value,
+        }
+        .into());
+    }
+    warm_accounts(state, caller, to, &[]);
+
+    let (result, contract_address) = frame::execute(state, action, tracer)?;
+    let logs = state.finalize_transaction();
+    let result = ExecutionResult {
+        status: result.status,
+        gas_used: gas_limit - result.gas.remaining(),
+        gas_refunded: 0,
+        output: result.output,
+        contract_address,
+        logs,
+    };
+    tracer.transaction_end(&result);
+    Ok(result)
+}
+
+/// Warms the accounts a transaction starts with: from Berlin the sender, the recipient,
+/// the precompiles and the access list (EIP-2929 and EIP-2930), and the coinbase too
+/// from Shanghai (EIP-3651).
+fn warm_accounts<DB: Database>(
+    state: &mut JournaledState<DB>,
+    sender: Address,
+    to: TxKind,
+    access_list: &[(Address, Vec<H256>)],
+) {
+    let fork = state.env().fork;
+    if fork < Fork::Berlin {
+        return;
+    }
+    state.warm_address(sender);
+    if let TxKind::Call(to) = to {
+        state.warm_address(to);
+    }
+    for address in precompiles::addresses(fork) {
+        state.warm_address(address);
+    }
+    for (address, keys) in access_list {
+        state.warm_address(*address);
+        for key in keys {
+            state.warm_slot(*address, *key);
+        }
+    }
+    if fork >= Fork::Shanghai {
+        let coinbase = state.env().block.coinbase;
+        state.warm_address(coinbase);
+    }
+}
+
+/// Installs the delegation designators of an EIP-7702 authorization list, skipping the
+/// authorizations that are invalid. Returns the refund for those whose authority already
+/// existed, having been charged as if it were new.
+fn apply_authorizations<DB: Database>(
+    state: &mut JournaledState<DB>,
+    authorizations: &[AuthorizationTuple],
+) -> anyhow::Result<u64> {
+    let chain_id = U256::from(state.env().chain_id);
+    let mut refund = 0;
+    for authorization in authorizations {
+        if !authorization.chain_id.is_zero() && authorization.chain_id != chain_id {
+            continue;
+        }
+        if authorization.nonce == u64::MAX {
+            continue;
+        }
+        let Some(authority) = authorization.authority() else {
+            continue;
+        };
+        state.warm_address(authority);
+        // Accounts with code of their own cannot be taken over
+        let code = state.account_code(authority)?;
+        if !code.is_empty() && delegated_address(&code).is_none() {
+            continue;
+        }
+        let account = state.account_info(authority)?;
+        if account.as_ref().map_or(0, |account| account.nonce) != authorization.nonce {
+            continue;
+        }
+        if account.is_some() {
+            refund += gas::PER_EMPTY_ACCOUNT - gas::PER_AUTH_BASE;
+        }
+        // Delegating to the zero address removes the delegation
+        let code = if authorization.address.is_zero() {
+            Bytes::new()
+        } else {
+            delegation_designator(authorization.address)
+        };
+        state.set_code(authority, code)?;
+        state.increment_nonce(authority)?;
+    }
+    Ok(refund)
+}
+
+/// Checks that the transaction can be included, returning the price it pays per unit of
+/// gas.
+fn validate<DB: Database>(
+    state: &mut JournaledState<DB>,
+    tx: &Transaction,
+    sender: Address,
+) -> anyhow::Result<U256> {
+    let env = state.env().clone();
+    let fork = env.fork;
+    let introduced = match tx.tx_type() {
+        TxType::Legacy => Fork::Frontier,
+        TxType::EIP2930 => Fork::Berlin,
+        TxType::EIP1559 => Fork::London,
+        TxType::EIP4844 => Fork::Cancun,
+        TxType::EIP7702 => Fork::Prague,
+    };
+    if fork < introduced {
+        return Err(InvalidTransaction::TypeNotSupported(tx.tx_type()).into());
+    }
+    if tx
+        .chain_id()
+        .is_some_and(|chain_id| chain_id != env.chain_id)
+    {
+        return Err(InvalidTransaction::ChainIdMismatch.into());
+    }
+
+    let account = state.account_info(sender)?.unwrap_or_default();
+    if tx.nonce() < account.nonce {
+        return Err(InvalidTransaction::NonceTooLow {
+            expected: account.nonce,
+            got: tx.nonce(),
+        }
+        .into());
+    }
+    if tx.nonce() > account.nonce {
+        return Err(InvalidTransaction::NonceTooHigh {
+            expected: account.nonce,
+            got: tx.nonce(),
+        }
+        .into());
+    }
+    if tx.nonce() == u64::MAX {
+        return Err(InvalidTransaction::NonceMax.into());
+    }
+    if account.code_hash != EMPTY_CODE_HASH
+        && delegated_address(&state.account_code(sender)?).is_none()
+    {
+        return Err(InvalidTransaction::SenderNotEoa.into());
+    }
+    if tx.authorization_list().is_some_and(|list| list.is_empty()) {
+        return Err(InvalidTransaction::EmptyAuthorizationList.into());
+    }
+    if tx.gas_limit() > env.block.gas_limit {
+        return Err(InvalidTransaction::GasLimitExceedsBlock {
+            gas_limit: tx.gas_limit(),
+            available: env.block.gas_limit,
+        }
+        .into());
+    }
+
+    let max_fee = tx.gas_price();
+    let gas_price = if fork >= Fork::London {
+        if max_fee < env.block.base_fee {
+            return Err(InvalidTransaction::FeeCapBelowBaseFee.into());
+        }
+        match tx.max_priority_fee() {
+            Some(tip) if tip > max_fee => return Err(InvalidTransaction::TipAboveFeeCap.into()),
+            Some(tip) => max_fee.min(tip.saturating_add(env.block.base_fee)),
+            None => max_fee,
+        }
+    } else {
+        max_fee
+    };
+
+    let intrinsic = intrinsic_gas(tx, fork);
+    let intrinsic = if fork >= Fork::Prague {
+        intrinsic.max(floor_data_gas(tx))
+    } else {
+        intrinsic
+    };
+    if tx.gas_limit() < intrinsic {
+        return Err(InvalidTransaction::IntrinsicGasTooLow {
+            intrinsic,
+            gas_limit: tx.gas_limit(),
+        }
+        .into());
+    }
+    if fork >= Fork::Shanghai && tx.to() == TxKind::Create && tx.data().len() > MAX_INITCODE_SIZE {
+        return Err(InvalidTransaction::InitCodeSizeExceeded(tx.data().len()).into());
+    }
+
+    let mut cost = Some(U256::from(tx.gas_limit())).and_then(|gas| gas.checked_mul(max_fee));
+    if let Some(max_blob_fee) = tx.max_fee_per_blob_gas() {
+        let blobs = tx.blob_versioned_hashes();
+        if blobs.is_empty() {
+            return Err(InvalidTransaction::NoBlobs.into());
+        }
+        if blobs.len() as u64 * gas::PER_BLOB > gas::max_blob_gas_per_block(fork) {
+            return Err(InvalidTransaction::TooManyBlobs(blobs.len()).into());
+        }
+        if blobs
+            .iter()
+            .any(|hash| hash[0] != VERSIONED_HASH_VERSION_KZG)
+        {
+            return Err(InvalidTransaction::InvalidBlobVersion.into());
+        }
+        if max_blob_fee < env.block.blob_base_fee {
+            return Err(InvalidTransaction::BlobFeeCapBelowBlobBaseFee.into());
+        }
+        let blob_cost = U256::from(blobs.len() as u64 * gas::PER_BLOB).checked_mul(max_blob_fee);
+        cost = cost
+            .zip(blob_cost)
+            .and_then(|(cost, blob_cost)| cost.checked_add(blob_cost));
+    }
+    let cost = cost
+        .and_then(|cost| cost.checked_add(tx.value()))
+        .unwrap_or(U256::MAX);
+    if account.balance < cost {
+        return Err(InvalidTransaction::InsufficientFunds {
+            balance: account.balance,
+            cost,
+        }
+        .into());
+    }
+    Ok(gas_price)
+}
+
+#[cfg(test)]
+mod test {
+    use reec_core::crypto::secret_key_to_address;
+    use reec_core::types::{
+        contract_address, EIP1559Transaction, EIP7702Transaction, LegacyTransaction,
+    };
+
+    use super::*;
+    use crate::{db::MemoryDb, BlockEnv, Env};
+
+    const SENDER: Address = Address::repeat_byte(0xaa);
+    const COINBASE: Address = Address::repeat_byte(0xcc);
+    const CONTRACT: Address = Address::repeat_byte(0xdd);
+
+    fn state(db: &MemoryDb) -> JournaledState<&MemoryDb> {
+        state_at(db, Fork::Cancun)
+    }
+
+    fn state_at(db: &MemoryDb, fork: Fork) -> JournaledState<&MemoryDb> {
+        let block = BlockEnv {
+            coinbase: COINBASE,
+            gas_limit: 30_000_000,
+            base_fee: 7.into(),
+            ..Default::default()
+        };
+        JournaledState::new(
+            db,
+            Env {
+                fork,
+                chain_id: 1,
+                block,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn db_with(code: &[u8]) -> MemoryDb {
+        let mut db = MemoryDb::default();
+        db.insert_account(SENDER, 0, U256::exp10(18), Bytes::new());
+        db.insert_account(CONTRACT, 1, U256::zero(), Bytes::copy_from_slice(code));
+        db
+    }
+
+    fn call(to: TxKind, gas_limit: u64, data: &[u8]) -> Transaction {
+        Transaction::EIP1559Transaction(EIP1559Transaction {
+            chain_id: 1,
+            max_priority_fee_per_gas: 2.into(),
+            max_fee_per_gas: 10.into(),
+            gas_limit,
+            to,
+            value: 1000.into(),
+            data: Bytes::copy_from_slice(data),
+            ..Default::default()
+        })
+    }
+
+    fn set_code(
+        to: Address,
+        gas_limit: u64,
+        authorization_list: Vec<AuthorizationTuple>,
+    ) -> Transaction {
+        Transaction::EIP7702Transaction(EIP7702Transaction {
+            chain_id: 1,
+            max_priority_fee_per_gas: 2.into(),
+            max_fee_per_gas: 10.into(),
+            gas_limit,
+            to,
+            authorization_list,
+            ..Default::default()
+        })
+    }
+
+    fn authorization(
+        secret_key: &[u8; 32],
+        chain_id: u64,
+        address: Address,
+        nonce: u64,
+    ) -> AuthorizationTuple {
+        let mut authorization = AuthorizationTuple {
+            chain_id: chain_id.into(),
+            address,
+            nonce,
+            ..Default::default()
+        };
+        authorization.sign(secret_key).unwrap();
+        authorization
+    }
+
+    #[test]
+    fn transfer_pays_tip_and_burns_base_fee() {
+        let db = db_with(&[]);
+        let mut state = state(&db);
+        let bob = Address::repeat_byte(0xbb);
+        let result =
+            execute_transaction(&mut state, &call(TxKind::Call(bob), 50_000, &[]), SENDER).unwrap();
+        assert_eq!(result.status, ExitStatus::Stop);
+        assert_eq!(result.gas_used, 21000);
+
+        let sender = state.account_info(SENDER).unwrap().unwrap();
+        assert_eq!(sender.nonce, 1);
+        assert_eq!(sender.balance, U256::exp10(18) - 1000 - 21000 * 9);
+        assert_eq!(
+            state.account_info(bob).unwrap().unwrap().balance,
+            1000.into()
+        );
+        assert_eq!(
+            state.account_info(COINBASE).unwrap().unwrap().balance,
+            (21000 * 2).into()
+        );
+    }
+
+    #[test]
+    fn creation_deploys_returned_code() {
+        let db = db_with(&[]);
+        let mut state = state(&db);
+        // Returns the single byte 0x2a
+        let init_code = [0x60, 0x2a, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xf3];
+        let result = execute_transaction(
+            &mut state,
+            &call(TxKind::Create, 100_000, &init_code),
+            SENDER,
+        )
+        .unwrap();
+        let address = contract_address(SENDER, 0);
+        assert_eq!(result.contract_address, Some(address));
+        assert_eq!(result.output, Bytes::from_static(&[0x2a]));
+        // Intrinsic 53000 + 136 + 2, execution 18, code deposit 200
+        assert_eq!(result.gas_used, 53356);
+        assert_eq!(
+            state.account_code(address).unwrap(),
+            Bytes::from_static(&[0x2a])
+        );
+        assert_eq!(state.account_info(address).unwrap().unwrap().nonce, 1);
+        assert_eq!(state.account_info(SENDER).unwrap().unwrap().nonce, 1);
+    }
+
+    #[test]
+    fn calls_precompile() {
+        let db = db_with(&[]);
+        let mut state = state(&db);
+        let identity = Address::from_low_u64_be(4);
+        let result = execute_transaction(
+            &mut state,
+            &call(TxKind::Call(identity), 21_082, b"reec"),
+            SENDER,
+        )
+        .unwrap();
+        assert_eq!(result.output, Bytes::from_static(b"reec"));
+        // Intrinsic 21000 + 64, identity 15 + 3
+        assert_eq!(result.gas_used, 21_082);
+        assert_eq!(
+            state.account_info(identity).unwrap().unwrap().balance,
+            1000.into()
+        );
+
+        // Running out of gas inside the precompile undoes the transfer
+        let mut state = self::state(&db);
+        let result = execute_transaction(
+            &mut state,
+            &call(TxKind::Call(identity), 21_081, b"reec"),
+            SENDER,
+        )
+        .unwrap();
+        assert_eq!(result.status, ExitStatus::OutOfGas);
+        assert_eq!(result.gas_used, 21_081);
+        assert_eq!(state.account_info(identity).unwrap(), None);
+    }
+
+    #[test]
+    fn authorizations_install_delegations() {
+        // SSTORE(0, 1)
+        let db = db_with(&[0x60, 0x01, 0x5f, 0x55, 0x00]);
+        let mut state = state_at(&db, Fork::Prague);
+        let secret_key = [0x22; 32];
+        let authority = secret_key_to_address(&secret_key).unwrap();
+        let authorizations = vec![
+            authorization(&secret_key, 1, CONTRACT, 0),
+            // Skipped: another chain, then a nonce that is no longer current
+            authorization(&secret_key, 5, Address::repeat_byte(0x01), 1),
+            authorization(&secret_key, 0, Address::repeat_byte(0x02), 0),
+        ];
+        let result = execute_transaction(
+            &mut state,
+            &set_code(authority, 150_000, authorizations),
+            SENDER,
+        )
+        .unwrap();
+        assert_eq!(result.status, ExitStatus::Stop);
+        // Intrinsic 21000 + 3 * 25000, then the delegate's code: 3 + 2 + 22100
+        assert_eq!(result.gas_used, 118_105);
+        assert_eq!(
+            state.account_code(authority).unwrap(),
+            delegation_designator(CONTRACT)
+        );
+        assert_eq!(state.account_info(authority).unwrap().unwrap().nonce, 1);
+        assert_eq!(
+            state.storage(authority, Default::default()).unwrap(),
+            1.into()
+        );
+
+        // The authority now exists, so authorizing again is partly refunded. Delegating
+        // to the zero address clears the designator, and the authority can send
+        // transactions of its own while delegating.
+        let mut db = db_with(&[]);
+        db.insert_account(
+            authority,
+            1,
+            U256::exp10(18),
+            delegation_designator(CONTRACT),
+        );
+        let mut state = state_at(&db, Fork::Prague);
+        // The transaction bumps the nonce before the authorization is checked against it
+        let mut tx = set_code(
+            CONTRACT,
+            100_000,
+            vec![authorization(&secret_key, 0, Address::zero(), 2)],
+        );
+        if let Transaction::EIP7702Transaction(tx) = &mut tx {
+            tx.nonce = 1;
+        }
+        let result = execute_transaction(&mut state, &tx, authority).unwrap();
+        assert_eq!(
+            (result.gas_used, result.gas_refunded),
+            (46_000 - 9200, 9200)
+        );
+        assert_eq!(state.account_code(authority).unwrap(), Bytes::new());
+        assert_eq!(state.account_info(authority).unwrap().unwrap().nonce, 3);
+    }
+
+    #[test]
+    fn calls_to_delegating_accounts_reach_the_delegate() {
+        let authority = Address::repeat_byte(0xee);
+        let proxy = Address::repeat_byte(0xab);
+        // CALL(0xffff, authority, 0, 0, 0, 0, 0) then EXTCODESIZE(authority)
+        let mut code = vec![0x5f, 0x5f, 0x5f, 0x5f, 0x5f, 0x73];
+        code.extend_from_slice(authority.as_bytes());
+        code.extend_from_slice(&[0x61, 0xff, 0xff, 0xf1, 0x73]);
+        code.extend_from_slice(authority.as_bytes());
+        code.extend_from_slice(&[0x3b, 0x5f, 0x55, 0x00]);
+        // SSTORE(0, 1)
+        let mut db = db_with(&[0x60, 0x01, 0x5f, 0x55, 0x00]);
+        db.insert_account(proxy, 1, U256::zero(), code.into());
+        db.insert_account(authority, 1, U256::zero(), delegation_designator(CONTRACT));
+        let mut state = state_at(&db, Fork::Prague);
+        let result =
+            execute_transaction(&mut state, &call(TxKind::Call(proxy), 200_000, &[]), SENDER)
+                .unwrap();
+        assert_eq!(result.status, ExitStatus::Stop);
+        // The delegate's code writes to the authority's storage
+        assert_eq!(
+            state.storage(authority, Default::default()).unwrap(),
+            1.into()
+        );
+        // EXTCODESIZE sees the designator rather than the delegate's code
+        assert_eq!(state.storage(proxy, Default::default()).unwrap(), 23.into());
+        // Pushes 21, CALL 2600 for the authority and 2600 for the delegate, the
+        // delegate's 22105, EXTCODESIZE 100 and SSTORE(0, 23) 22100
+        assert_eq!(result.gas_used, 21_000 + 21 + 5200 + 22_105 + 100 + 22_100);
+    }
+
+    #[test]
+    fn revert_keeps_nonce_and_fee() {
+        // SSTORE(0, 1) then REVERT(0, 0)
+        let db = db_with(&[0x60, 0x01, 0x60, 0x00, 0x55, 0x60, 0x00, 0x60, 0x00, 0xfd]);
+        let mut state = state(&db);
+        let result = execute_transaction(
+            &mut state,
+            &call(TxKind::Call(CONTRACT), 100_000, &[]),
+            SENDER,
+        )
+        .unwrap();
+        assert_eq!(result.status, ExitStatus::Revert);
+        assert_eq!(
+            state.storage(CONTRACT, Default::default()).unwrap(),
+            U256::zero()
+        );
+        assert_eq!(
+            state.account_info(CONTRACT).unwrap().unwrap().balance,
+            U256::zero()
+        );
+        let sender = state.account_info(SENDER).unwrap().unwrap();
+        assert_eq!(sender.nonce, 1);
+        assert_eq!(sender.balance, U256::exp10(18) - result.gas_used * 9);
+    }
+
+    #[test]
+    fn refund_is_capped() {
+        // SSTORE(0, 0) on a slot holding 1
+        let mut db = db_with(&[0x60, 0x00, 0x60, 0x00, 0x55, 0x00]);
+        db.insert_storage(CONTRACT, Default::default(), 1.into());
+        let mut state = state(&db);
+        let result = execute_transaction(
+            &mut state,
+            &call(TxKind::Call(CONTRACT), 100_000, &[]),
+            SENDER,
+        )
+        .unwrap();
+        // 26006 spent, of which 4800 come back
+        assert_eq!((result.gas_used, result.gas_refunded), (21206, 4800));
+
+        let diff = state.state_diff();
+        assert_eq!(
+            diff[&CONTRACT].storage,
+            [(Default::default(), U256::zero())].into()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_transactions() {
+        let db = db_with(&[]);
+        let mut state = state(&db);
+        let invalid = |state: &mut JournaledState<&MemoryDb>, tx: &Transaction| {
+            let err = execute_transaction(state, tx, SENDER).unwrap_err();
+            err.downcast::<InvalidTransaction>().unwrap()
+        };
+        let mut tx = call(TxKind::Call(CONTRACT), 21000, &[]);
+        if let Transaction::EIP1559Transaction(tx) = &mut tx {
+            tx.nonce = 1;
+        }
+        assert_eq!(
+            invalid(&mut state, &tx),
+            InvalidTransaction::NonceTooHigh {
+                expected: 0,
+                got: 1
+            }
+        );
+        assert!(matches!(
+            invalid(&mut state, &call(TxKind::Call(CONTRACT), 20000, &[])),
+            InvalidTransaction::IntrinsicGasTooLow {
+                intrinsic: 21000,
+                ..
+            }
+        ));
+        let legacy = Transaction::LegacyTransaction(LegacyTransaction {
+            gas_price: 6.into(),
+            gas: 21000,
+            to: TxKind::Call(CONTRACT),
+            ..Default::default()
+        });
+        assert_eq!(
+            invalid(&mut state, &legacy),
+            InvalidTransaction::FeeCapBelowBaseFee
+        );
+        assert_eq!(
+            invalid(&mut state, &set_code(CONTRACT, 50_000, Vec::new())),
+            InvalidTransaction::TypeNotSupported(TxType::EIP7702)
+        );
+        assert_eq!(
+            invalid(
+                &mut state_at(&db, Fork::Prague),
+                &set_code(CONTRACT, 50_000, Vec::new())
+            ),
+            InvalidTransaction::EmptyAuthorizationList
+        );
+        let mut tx = call(TxKind::Call(CONTRACT), 21000, &[]);
+        if let Transaction::EIP1559Transaction(tx) = &mut tx {
+            tx.value = U256::exp10(18);
+        }
+        assert_eq!(
+            invalid(&mut state, &tx),
+            InvalidTransaction::InsufficientFunds {
+                balance: U256::exp10(18),
+                cost: U256::exp10(18) + 210_000
+            }
+        );
+        assert!(state.state_diff().is_empty());
+    }
+}
@@ -0,0 +1,409 @@
+use ethereum_types::{Address, H256, U256};
+use evm::{
+    begin_block,
+    block::block_env,
+    tracer::{CallTracer, PrestateTracer},
+    transaction::{execute_transaction, execute_transaction_with_tracer},
+    Database, Env, JournaledState,
+};
+use reec_core::types::{BlockHeader, BlockNumber, Transaction};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use storage::{StateAt, Store};
+
+use crate::{
+    types::{block_identifier::BlockIdentifier, call::RpcCallRequest},
+    utils::{parse_param, to_json, RpcErr},
+    RpcApiContext,
+};
+
+/// Options object the `debug_trace*` methods take last.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TraceOptions {
+    tracer: Option<String>,
+    #[serde(default)]
+    tracer_config: TracerConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct TracerConfig {
+    only_top_call: bool,
+    with_log: bool,
+    diff_mode: bool,
+}
+
+/// The tracers that can be asked for. geth's default opcode logger is not one of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TracerKind {
+    Call { only_top_call: bool, with_log: bool },
+    Prestate { diff_mode: bool },
+}
+
+impl TracerKind {
+    fn parse(options: Option<&Value>) -> Result<Self, RpcErr> {
+        let options: TraceOptions = match options {
+            None | Some(Value::Null) => TraceOptions::default(),
+            Some(options) => parse_param(options)?,
+        };
+        let TracerConfig {
+            only_top_call,
+            with_log,
+            diff_mode,
+        } = options.tracer_config;
+        match options.tracer.as_deref() {
+            Some("callTracer") => Ok(TracerKind::Call {
+                only_top_call,
+                with_log,
+            }),
+            Some("prestateTracer") => Ok(TracerKind::Prestate { diff_mode }),
+            Some(tracer) => Err(RpcErr::BadParams(format!("unsupported tracer {tracer}"))),
+            None => Err(RpcErr::BadParams(
+                "a tracer is required: callTracer or prestateTracer".to_string(),
+            )),
+        }
+    }
+}
+
+/// Trace of one transaction of a block, as `debug_traceBlockByNumber` returns them.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockTxTrace {
+    tx_hash: H256,
+    result: Value,
+}
+
+/// `debug_traceTransaction`: params are the transaction hash and optionally the trace
+/// options. The transaction is replayed on the state its block left before it.
+pub fn trace_transaction(params: &Option<Value>, context: &RpcApiContext) -> Result<Value, RpcErr> {
+    let ([hash], options) = params_with_options(params)?;
+    let tracer = TracerKind::parse(options)?;
+    let store = &context.store;
+    let Some((number, index)) = store.get_transaction_location(parse_param(hash)?)? else {
+        return Ok(Value::Null);
+    };
+    let Some((header, txs)) = canonical_block(store, number)? else {
+        return Ok(Value::Null);
+    };
+    let db = parent_state(store, &header)?;
+    let mut state = begin_block(&db, &context.chain_config, &header)?;
+    let (before, rest) = txs.split_at(index as usize);
+    for tx in before {
+        execute_transaction(&mut state, tx, sender(tx)?)?;
+    }
+    let tx = rest
+        .first()
+        .ok_or_else(|| RpcErr::Internal(format!("block {number} lacks transaction {index}")))?;
+    trace_with(&mut state, tx, sender(tx)?, tracer)
+}
+
+/// `debug_traceBlockByNumber`: params are the block identifier and optionally the
+/// trace options. Returns the trace of every transaction of the block, in order.
+pub fn trace_block_by_number(
+    params: &Option<Value>,
+    context: &RpcApiContext,
+) -> Result<Value, RpcErr> {
+    let ([identifier], options) = params_with_options(params)?;
+    let tracer = TracerKind::parse(options)?;
+    let store = &context.store;
+    let Some(number) = BlockIdentifier::parse(identifier)?.resolve(store)? else {
+        return Ok(Value::Null);
+    };
+    let Some((header, txs)) = canonical_block(store, number)? else {
+        return Ok(Value::Null);
+    };
+    if txs.is_empty() {
+        return Ok(Value::Array(Vec::new()));
+    }
+    let db = parent_state(store, &header)?;
+    let mut state = begin_block(&db, &context.chain_config, &header)?;
+    let traces = txs
+        .iter()
+        .map(|tx| {
+            let result = trace_with(&mut state, tx, sender(tx)?, tracer)?;
+            Ok(BlockTxTrace {
+                tx_hash: tx.compute_hash(),
+                result,
+            })
+        })
+        .collect::<Result<Vec<_>, RpcErr>>()?;
+    to_json(traces)
+}
+
+/// `debug_traceCall`: params are the call object, the block identifier and optionally
+/// the trace options. The call runs on the state the block left, in the block's
+/// environment, with the sender's nonce. Without a gas price the base fee is waived so
+/// that senders without funds can be traced, as geth does.
+pub fn trace_call(params: &Option<Value>, context: &RpcApiContext) -> Result<Value, RpcErr> {
+    let ([call, identifier], options) = params_with_options(params)?;
+    let tracer = TracerKind::parse(options)?;
+    let call: RpcCallRequest = parse_param(call)?;
+    let store = &context.store;
+    let Some(number) = BlockIdentifier::parse(identifier)?.resolve(store)? else {
+        return Err(RpcErr::BadParams("unknown block".to_string()));
+    };
+    let Some((header, _)) = canonical_block(store, number)? else {
+        return Err(RpcErr::BadParams(format!("unknown block {number}")));
+    };
+    let db = state_after(store, number)?;
+    let fork = context.chain_config.fork(header.number, header.timestamp);
+    let mut block = block_env(&header, fork);
+    if !call.sets_fees() {
+        block.base_fee = U256::zero();
+    }
+    let env = Env {
+        fork,
+        chain_id: context.chain_config.chain_id,
+        block,
+        ..Default::default()
+    };
+    let mut state = JournaledState::new(&db, env);
+    let sender = call.sender();
+    let nonce = state.account_info(sender)?.map_or(0, |info| info.nonce);
+    let tx = call.to_transaction(
+        context.chain_config.chain_id,
+        nonce,
+        call.gas.unwrap_or(header.gas_limit),
+    );
+    trace_with(&mut state, &tx, sender, tracer)
+}
+
+/// Runs `tx` on `state` under the tracer asked for and returns its trace.
+fn trace_with<DB: Database + Clone>(
+    state: &mut JournaledState<DB>,
+    tx: &Transaction,
+    sender: Address,
+    tracer: TracerKind,
+) -> Result<Value, RpcErr> {
+    match tracer {
+        TracerKind::Call {
+            only_top_call,
+            with_log,
+        } => {
+            let mut tracer = CallTracer::new(only_top_call, with_log);
+            execute_transaction_with_tracer(state, tx, sender, &mut tracer)?;
+            to_json(tracer.finish())
+        }
+        TracerKind::Prestate { diff_mode } => {
+            let mut pre = state.clone();
+            let mut tracer = PrestateTracer::new(diff_mode);
+            execute_transaction_with_tracer(state, tx, sender, &mut tracer)?;
+            to_json(tracer.finish(&mut pre, state)?)
+        }
+    }
+}
+
+/// Splits the params into the `N` required ones and the trace options that may follow.
+fn params_with_options<const N: usize>(
+    params: &Option<Value>,
+) -> Result<(&[Value; N], Option<&Value>), RpcErr> {
+    let params = params
+        .as_ref()
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let (required, options) = match params.len() {
+        len if len == N => (params, None),
+        len if len == N + 1 => (&params[..N], params.last()),
+        _ => {
+            return Err(RpcErr::BadParams(format!(
+                "expected {N} params and optionally trace options"
+            )))
+        }
+    };
+    let required = required.try_into().expect("length was checked");
+    Ok((required, options))
+}
+
+pub(crate) fn canonical_block(
+    store: &Store,
+    number: BlockNumber,
+) -> Result<Option<(BlockHeader, Vec<Transaction>)>, RpcErr> {
+    let (Some(header), Some(body)) = (
+        store.get_block_header(number)?,
+        store.get_block_body(number)?,
+    ) else {
+        return Ok(None);
+    };
+    Ok(Some((header, body.transactions)))
+}
+
+/// The state the transactions of the block with `header` run on.
+fn parent_state<'a>(store: &'a Store, header: &BlockHeader) -> Result<StateAt<'a>, RpcErr> {
+    let parent = header
+        .number
+        .checked_sub(1)
+        .ok_or_else(|| RpcErr::BadParams("the genesis block has no transactions".to_string()))?;
+    state_after(store, parent)
+}
+
+pub(crate) fn state_after(store: &Store, number: BlockNumber) -> Result<StateAt<'_>, RpcErr> {
+    let latest = store.get_latest_block_number()?.unwrap_or_default();
+    if number < latest && number + 1 < store.earliest_state_block()? {
+        return Err(RpcErr::PrunedHistory);
+    }
+    Ok(store.state_at(number)?)
+}
+
+fn sender(tx: &Transaction) -> Result<Address, RpcErr> {
+    tx.sender()
+        .ok_or_else(|| RpcErr::Internal(format!("invalid signature on {:#x}", tx.compute_hash())))
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use evm::{execute_block, ChainConfig, Fork};
+    use reec_core::types::{
+        AccountDiff, AccountInfo, Block, BlockBody, EIP1559Transaction, StateDiff, TxKind,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    /// Stores a genesis with a funded sender and a contract running SSTORE(0, 42), and
+    /// a block calling it. Returns the contract and the call's hash and gas used.
+    fn setup(store: &Store, config: &ChainConfig) -> (Address, H256, u64) {
+        let secret_key = [0x42; 32];
+        let sender = reec_core::crypto::secret_key_to_address(&secret_key).unwrap();
+        let contract = Address::repeat_byte(0xdd);
+        let code = Bytes::from_static(&[0x60, 0x2a, 0x5f, 0x55, 0x00]);
+        let mut genesis = StateDiff::new();
+        let info = AccountInfo {
+            balance: U256::exp10(18),
+            ..Default::default()
+        };
+        genesis.insert(
+            sender,
+            AccountDiff {
+                info: Some(info),
+                ..Default::default()
+            },
+        );
+        let info = AccountInfo {
+            nonce: 1,
+            code_hash: keccak_hash::keccak(&code),
+            ..Default::default()
+        };
+        genesis.insert(
+            contract,
+            AccountDiff {
+                info: Some(info),
+                code: Some(code),
+                ..Default::default()
+            },
+        );
+        store.write_state(0, &genesis).unwrap();
+        let header = BlockHeader {
+            gas_limit: 30_000_000,
+            base_fee_per_gas: Some(7),
+            ..Default::default()
+        };
+        let genesis_hash = header.compute_block_hash();
+        store.add_block(header, BlockBody::default()).unwrap();
+
+        let mut tx = Transaction::EIP1559Transaction(EIP1559Transaction {
+            chain_id: 1,
+            max_priority_fee_per_gas: U256::one(),
+            max_fee_per_gas: U256::from(10),
+            gas_limit: 100_000,
+            to: TxKind::Call(contract),
+            ..Default::default()
+        });
+        tx.sign(&secret_key, Some(1)).unwrap();
+        let header = BlockHeader {
+            parent_hash: genesis_hash,
+            number: 1,
+            gas_limit: 30_000_000,
+            base_fee_per_gas: Some(7),
+            ..Default::default()
+        };
+        let block = Block {
+            header,
+            body: BlockBody {
+                transactions: vec![tx.clone()],
+                ..Default::default()
+            },
+        };
+        let result = execute_block(store, config, &block).unwrap();
+        store.write_state(1, &result.state_diff).unwrap();
+        let hash = block.header.compute_block_hash();
+        store.add_block(block.header, block.body).unwrap();
+        store.set_canonical_head(hash).unwrap();
+        (contract, tx.compute_hash(), result.gas_used)
+    }
+
+    #[test]
+    fn traces_stored_transactions() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = RpcApiContext {
+            store: Store::new(dir.path()).unwrap(),
+            chain_config: ChainConfig::from_genesis(1, Fork::Shanghai),
+        };
+        let (contract, hash, gas_used) = setup(&context.store, &context.chain_config);
+
+        let params = Some(json!([hash, { "tracer": "callTracer" }]));
+        let trace = trace_transaction(&params, &context).unwrap();
+        assert_eq!(trace["type"], json!("CALL"));
+        assert_eq!(trace["to"], json!(contract));
+        assert_eq!(trace["gas"], json!("0x186a0"));
+        assert_eq!(trace["gasUsed"], json!(format!("{gas_used:#x}")));
+
+        let options = json!({ "tracer": "prestateTracer", "tracerConfig": { "diffMode": true } });
+        let trace = trace_transaction(&Some(json!([hash, options])), &context).unwrap();
+        let slot = json!(H256::zero());
+        assert_eq!(
+            trace["pre"][format!("{contract:?}")]["storage"],
+            Value::Null
+        );
+        assert_eq!(
+            trace["post"][format!("{contract:?}")],
+            json!({ "storage": { slot.as_str().unwrap(): H256::from_low_u64_be(42) } })
+        );
+
+        let params = Some(json!(["0x1", { "tracer": "prestateTracer" }]));
+        let traces = trace_block_by_number(&params, &context).unwrap();
+        assert_eq!(traces[0]["txHash"], json!(hash));
+        assert_eq!(
+            traces[0]["result"][format!("{contract:?}")]["storage"],
+            json!({ slot.as_str().unwrap(): H256::zero() })
+        );
+
+        assert!(matches!(
+            trace_transaction(&Some(json!([hash])), &context),
+            Err(RpcErr::BadParams(_))
+        ));
+        assert_eq!(
+            trace_transaction(&Some(json!([H256::zero(), options])), &context).unwrap(),
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn traces_calls_on_stored_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = RpcApiContext {
+            store: Store::new(dir.path()).unwrap(),
+            chain_config: ChainConfig::from_genesis(1, Fork::Shanghai),
+        };
+        let (contract, _, _) = setup(&context.store, &context.chain_config);
+
+        // A sender without funds, which only works as the base fee is waived
+        let call = json!({ "from": Address::repeat_byte(0x11), "to": contract, "gas": "0x10000" });
+        let options = json!({ "tracer": "prestateTracer", "tracerConfig": { "diffMode": true } });
+        let trace = trace_call(&Some(json!([call, "latest", options])), &context).unwrap();
+        // The slot was set by the stored block, so writing it again changes nothing
+        assert_eq!(trace["post"][format!("{contract:?}")], Value::Null);
+
+        let call = json!({ "to": contract, "input": "0x1234" });
+        let trace = trace_call(
+            &Some(json!([call, "0x0", { "tracer": "callTracer" }])),
+            &context,
+        )
+        .unwrap();
+        assert_eq!(trace["input"], json!("0x1234"));
+        assert_eq!(trace["gas"], json!("0x1c9c380"));
+        assert!(trace.get("error").is_none());
+    }
+}
@@ -1,7 +1,53 @@
+use ethereum_types::H256;
+use reec_core::types::{BlockHash, BlockNumber};
 use serde_json::Value;
+use storage::Store;
 
-use crate::utils::RpcErr;
+use crate::{
+    types::{block::RpcBlock, block_identifier::BlockIdentifier},
+    utils::{parse_param, positional_params, to_json, RpcErr},
+};
 
-pub fn get_block_by_number() -> Result<Value, RpcErr> {
-    Ok(Value::Null)
-}
\ No newline at end of file
+/// `eth_getBlockByNumber`: params are the block identifier and whether to return full
+/// transaction objects.
+pub fn get_block_by_number(params: &Option<Value>, store: &Store) -> Result<Value, RpcErr> {
+    let [identifier, hydrated] = positional_params(params)?;
+    let hydrated = parse_param(hydrated)?;
+    let Some(number) = BlockIdentifier::parse(identifier)?.resolve(store)? else {
+        return Ok(Value::Null);
+    };
+    let Some(hash) = store.get_canonical_block_hash(number)? else {
+        return Ok(Value::Null);
+    };
+    block_response(store, hash, number, hydrated)
+}
+
+/// `eth_getBlockByHash`: params are the block hash and whether to return full
+/// transaction objects.
+pub fn get_block_by_hash(params: &Option<Value>, store: &Store) -> Result<Value, RpcErr> {
+    let [hash, hydrated] = positional_params(params)?;
+    let hash: H256 = parse_param(hash)?;
+    let hydrated = parse_param(hydrated)?;
+    let Some(number) = store.get_block_number(hash)? else {
+        return Ok(Value::Null);
+    };
+    block_response(store, hash, number, hydrated)
+}
+
+fn block_response(
+    store: &Store,
+    hash: BlockHash,
+    number: BlockNumber,
+    hydrated: bool,
+) -> Result<Value, RpcErr> {
+    let Some(header) = store.get_block_header_by_hash(hash)? else {
+        return Ok(Value::Null);
+    };
+    let Some(body) = store.get_block_body_by_hash(hash)? else {
+        if number < store.earliest_history_block()? {
+            return Err(RpcErr::PrunedHistory);
+        }
+        return Ok(Value::Null);
+    };
+    to_json(RpcBlock::build(header, body, hydrated)?)
+}
@@ -0,0 +1,411 @@
+use ethereum_types::U256;
+use evm::{
+    block::block_env, execute_transaction, Database, Env, ExecutionResult, ExitStatus,
+    InvalidTransaction, JournaledState,
+};
+use serde_json::Value;
+
+use crate::{
+    debug::trace::{canonical_block, state_after},
+    types::{
+        block_identifier::BlockIdentifier,
+        call::RpcCallRequest,
+        overrides::{BlockOverrides, StateOverride},
+    },
+    utils::{parse_param, RpcErr},
+    RpcApiContext,
+};
+
+/// Gas of a plain transfer, the least any transaction needs.
+const TRANSFER_GAS: u64 = 21_000;
+/// Gas a call with value gives its callee for free, which the first guess leaves room for.
+const CALL_STIPEND: u64 = 2_300;
+
+/// `eth_estimateGas`: params are the call object and optionally the block identifier,
+/// `latest` by default, the state overrides and the block overrides. Returns the least
+/// gas limit the call succeeds with on the state the block left, found by a binary
+/// search as geth does. Without a gas price or EIP-1559 fees the base fee is waived,
+/// as for `debug_traceCall`.
+pub fn estimate_gas(params: &Option<Value>, context: &RpcApiContext) -> Result<Value, RpcErr> {
+    let params = params
+        .as_ref()
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let [call, rest @ ..] = params else {
+        return Err(RpcErr::BadParams("expected a call object".to_string()));
+    };
+    if rest.len() > 3 {
+        return Err(RpcErr::BadParams(
+            "expected a call object, a block identifier, state overrides and block overrides at most".to_string(),
+        ));
+    }
+    let call: RpcCallRequest = parse_param(call)?;
+    let identifier = match rest.first() {
+        Some(identifier) => BlockIdentifier::parse(identifier)?,
+        None => BlockIdentifier::Latest,
+    };
+    let optional = |index: usize| rest.get(index).filter(|param| !param.is_null());
+    let state_override: StateOverride = optional(1)
+        .map(parse_param)
+        .transpose()?
+        .unwrap_or_default();
+    let block_overrides: BlockOverrides = optional(2)
+        .map(parse_param)
+        .transpose()?
+        .unwrap_or_default();
+
+    let store = &context.store;
+    let Some(number) = identifier.resolve(store)? else {
+        return Err(RpcErr::BadParams("unknown block".to_string()));
+    };
+    let Some((header, _)) = canonical_block(store, number)? else {
+        return Err(RpcErr::BadParams(format!("unknown block {number}")));
+    };
+    let db = state_override.apply(state_after(store, number)?)?;
+    let fork = context.chain_config.fork(header.number, header.timestamp);
+    let mut block = block_env(&header, fork);
+    block_overrides.apply(&mut block);
+    if !call.sets_fees() {
+        block.base_fee = U256::zero();
+    }
+    let env = Env {
+        fork,
+        chain_id: context.chain_config.chain_id,
+        block,
+        ..Default::default()
+    };
+    let nonce = db.account(call.sender())?.map_or(0, |info| info.nonce);
+    let gas = GasEstimator {
+        db: &db,
+        env,
+        call: &call,
+        nonce,
+    }
+    .estimate()?;
+    Ok(Value::String(format!("{gas:#x}")))
+}
+
+struct GasEstimator<'a, DB> {
+    db: &'a DB,
+    env: Env,
+    call: &'a RpcCallRequest,
+    nonce: u64,
+}
+
+impl<DB: Database> GasEstimator<'_, DB> {
+    fn estimate(&self) -> Result<u64, RpcErr> {
+        let mut hi = self.cap()?;
+        // A call without data that goes through as a plain transfer needs no search
+        if self
+            .call
+            .input
+            .as_ref()
+            .or(self.call.data.as_ref())
+            .is_none_or(|data| data.is_empty())
+            && self.succeeds(TRANSFER_GAS)?
+        {
+            return Ok(TRANSFER_GAS);
+        }
+
+        let result = self.run(hi).map_err(execution_error)?;
+        match result.status {
+            _ if result.is_success() => {}
+            ExitStatus::Revert => return Err(RpcErr::Revert(result.output)),
+            ExitStatus::OutOfGas => {
+                return Err(RpcErr::Execution(format!(
+                    "gas required exceeds allowance ({hi})"
+                )))
+            }
+            status => return Err(RpcErr::Execution(status.to_string())),
+        }
+
+        // The call needs at least the gas it used, and most calls need little more than
+        // that and what they were refunded, plus what forwarding gas to a callee keeps back
+        let mut lo = result.gas_used - 1;
+        let optimistic =
+            (result.gas_used + result.gas_refunded + CALL_STIPEND).saturating_mul(64) / 63;
+        if optimistic < hi {
+            if self.succeeds(optimistic)? {
+                hi = optimistic;
+            } else {
+                lo = optimistic;
+            }
+        }
+        while lo + 1 < hi {
+            // Gas needs are usually near the lower bound, so the middle is kept close to it
+            let mid = ((lo + hi) / 2).min(lo * 2);
+            if self.succeeds(mid)? {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        Ok(hi)
+    }
+
+    /// The most gas the call can have: the gas it gives or the block's gas limit, and no
+    /// more than the sender can pay for at the call's gas price, or its max fee per gas
+    /// for an EIP-1559 call.
+    fn cap(&self) -> Result<u64, RpcErr> {
+        let mut cap = match self.call.gas {
+            Some(gas) if gas >= TRANSFER_GAS => gas,
+            _ => self.env.block.gas_limit,
+        };
+        let fee_cap = self.call.fee_cap();
+        if !fee_cap.is_zero() {
+            let balance = self
+                .db
+                .account(self.call.sender())?
+                .unwrap_or_default()
+                .balance;
+            let value = self.call.value.unwrap_or_default();
+            let available = balance
+                .checked_sub(value)
+                .ok_or_else(|| RpcErr::Execution("insufficient funds for transfer".to_string()))?;
+            let allowance = available / fee_cap;
+            if allowance < U256::from(cap) {
+                cap = allowance.low_u64();
+            }
+        }
+        Ok(cap)
+    }
+
+    fn run(&self, gas_limit: u64) -> anyhow::Result<ExecutionResult> {
+        let mut state = JournaledState::new(self.db, self.env.clone());
+        let tx = self
+            .call
+            .to_transaction(self.env.chain_id, self.nonce, gas_limit);
+        execute_transaction(&mut state, &tx, self.call.sender())
+    }
+
+    /// Whether the call succeeds with `gas_limit`. Limits below the intrinsic gas fail
+    /// like the others, while any other reason the call cannot run is an error.
+    fn succeeds(&self, gas_limit: u64) -> Result<bool, RpcErr> {
+        match self.run(gas_limit) {
+            Ok(result) => Ok(result.is_success()),
+            Err(err)
+                if matches!(
+                    err.downcast_ref(),
+                    Some(InvalidTransaction::IntrinsicGasTooLow { .. })
+                ) =>
+            {
+                Ok(false)
+            }
+            Err(err) => Err(execution_error(err)),
+        }
+    }
+}
+
+fn execution_error(err: anyhow::Error) -> RpcErr {
+    match err.downcast::<InvalidTransaction>() {
+        Ok(invalid) => RpcErr::Execution(invalid.to_string()),
+        Err(err) => err.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use ethereum_types::{Address, H256};
+    use evm::{ChainConfig, Fork};
+    use reec_core::types::{AccountDiff, AccountInfo, BlockBody, BlockHeader, StateDiff};
+    use serde_json::json;
+    use storage::Store;
+
+    use super::*;
+
+    /// Stores a genesis block whose state holds a funded sender and `contracts`.
+    fn context(
+        contracts: &[(Address, &'static [u8])],
+    ) -> (tempfile::TempDir, RpcApiContext, Address) {
+        let dir = tempfile::tempdir().unwrap();
+        let context = RpcApiContext {
+            store: Store::new(dir.path()).unwrap(),
+            chain_config: ChainConfig::from_genesis(1, Fork::Cancun),
+        };
+        let sender = Address::repeat_byte(0xaa);
+        let mut genesis = StateDiff::new();
+        let info = AccountInfo {
+            balance: U256::exp10(18),
+            ..Default::default()
+        };
+        genesis.insert(
+            sender,
+            AccountDiff {
+                info: Some(info),
+                ..Default::default()
+            },
+        );
+        for &(address, code) in contracts {
+            let code = Bytes::from_static(code);
+            let info = AccountInfo {
+                nonce: 1,
+                code_hash: keccak_hash::keccak(&code),
+                ..Default::default()
+            };
+            genesis.insert(
+                address,
+                AccountDiff {
+                    info: Some(info),
+                    code: Some(code),
+                    ..Default::default()
+                },
+            );
+        }
+        context.store.write_state(0, &genesis).unwrap();
+        let header = BlockHeader {
+            gas_limit: 30_000_000,
+            base_fee_per_gas: Some(7),
+            excess_blob_gas: Some(0),
+            ..Default::default()
+        };
+        let hash = header.compute_block_hash();
+        context
+            .store
+            .add_block(header, BlockBody::default())
+            .unwrap();
+        context.store.set_canonical_head(hash).unwrap();
+        (dir, context, sender)
+    }
+
+    fn estimate(context: &RpcApiContext, params: Value) -> Result<u64, RpcErr> {
+        let gas = estimate_gas(&Some(params), context)?;
+        Ok(u64::from_str_radix(gas.as_str().unwrap().trim_start_matches("0x"), 16).unwrap())
+    }
+
+    /// Whether the call succeeds with exactly `gas` and fails with one less.
+    fn is_least_gas(context: &RpcApiContext, call: &Value, gas: u64) -> bool {
+        let runs = |gas: u64| {
+            let (header, _) = canonical_block(&context.store, 0).unwrap().unwrap();
+            let call: RpcCallRequest = serde_json::from_value(call.clone()).unwrap();
+            let db = state_after(&context.store, 0).unwrap();
+            let mut block = block_env(&header, Fork::Cancun);
+            block.base_fee = U256::zero();
+            let env = Env {
+                fork: Fork::Cancun,
+                chain_id: 1,
+                block,
+                ..Default::default()
+            };
+            GasEstimator {
+                db: &db,
+                env,
+                call: &call,
+                nonce: 0,
+            }
+            .succeeds(gas)
+            .unwrap()
+        };
+        runs(gas) && !runs(gas - 1)
+    }
+
+    #[test]
+    fn finds_the_least_gas() {
+        // SSTORE(0, 1), refunded when a second call sets it back to zero
+        let store = Address::repeat_byte(0x51);
+        let store_code: &[u8] = &[0x60, 0x01, 0x5f, 0x55, 0x5f, 0x5f, 0x55, 0x00];
+        // Calls the store contract with all but a 64th of its gas, and needs what is left
+        // after the call returns to go on
+        let forwarder = Address::repeat_byte(0xf0);
+        let mut forwarder_code = vec![0x5f, 0x5f, 0x5f, 0x5f, 0x5f, 0x73];
+        forwarder_code.extend_from_slice(store.as_bytes());
+        forwarder_code.extend_from_slice(&[0x5a, 0xf1, 0x50, 0x60, 0x01, 0x60, 0x01, 0x55, 0x00]);
+        let forwarder_code: &'static [u8] = forwarder_code.leak();
+        let (_dir, context, sender) = context(&[(store, store_code), (forwarder, forwarder_code)]);
+
+        assert_eq!(
+            estimate(
+                &context,
+                json!([{ "from": sender, "to": Address::repeat_byte(0xee) }])
+            )
+            .unwrap(),
+            21_000
+        );
+        for to in [store, forwarder] {
+            let call = json!({ "from": sender, "to": to, "input": "0x01" });
+            let gas = estimate(&context, json!([call, "latest"])).unwrap();
+            assert!(is_least_gas(&context, &call, gas), "{gas} for {to:#x}");
+            // The gas the call gives only caps the search
+            let call = json!({ "from": sender, "to": to, "input": "0x01", "gas": "0x186a0" });
+            assert_eq!(estimate(&context, json!([call])).unwrap(), gas);
+        }
+    }
+
+    #[test]
+    fn reports_reverts_and_allowance() {
+        // Reverts with Error("no"), copied from the code after the 10 bytes running it
+        let reverter = Address::repeat_byte(0xee);
+        let mut code = vec![
+            0x60, 100, 0x60, 10, 0x5f, 0x39, 0x60, 100, 0x5f, 0xfd, 0x08, 0xc3, 0x79, 0xa0,
+        ];
+        for word in [&[0x20][..], &[0x02], &[]] {
+            code.extend_from_slice(&[0; 32][word.len()..]);
+            code.extend_from_slice(word);
+        }
+        code[10 + 4 + 64..][..2].copy_from_slice(b"no");
+        let reverter_code: &'static [u8] = code.leak();
+        // An infinite loop
+        let looper = Address::repeat_byte(0x10);
+        let (_dir, context, sender) =
+            context(&[(reverter, reverter_code), (looper, &[0x5b, 0x5f, 0x56])]);
+
+        let err = estimate_gas(
+            &Some(json!([{ "from": sender, "to": reverter, "input": "0x01" }])),
+            &context,
+        )
+        .unwrap_err();
+        let RpcErr::Revert(data) = &err else {
+            panic!("{err:?}")
+        };
+        assert_eq!(data.len(), 100);
+        let metadata = serde_json::to_value(crate::utils::RpcErrorMetadata::from(err)).unwrap();
+        assert_eq!(metadata["code"], 3);
+        assert_eq!(metadata["message"], "execution reverted: no");
+
+        let call = json!({ "from": sender, "to": looper, "input": "0x01", "gas": "0x186a0" });
+        let err = estimate_gas(&Some(json!([call])), &context).unwrap_err();
+        assert!(
+            matches!(&err, RpcErr::Execution(message) if message == "gas required exceeds allowance (100000)")
+        );
+
+        // The sender can pay for 30000 gas, too little for the loop or more than a transfer
+        let fee = format!("{:#x}", U256::exp10(18) / 30_000);
+        let call = json!({ "from": sender, "to": looper, "input": "0x01", "gasPrice": fee });
+        let err = estimate_gas(&Some(json!([call])), &context).unwrap_err();
+        assert!(
+            matches!(&err, RpcErr::Execution(message) if message == "gas required exceeds allowance (30000)")
+        );
+        // As for an EIP-1559 call paying up to the same fee
+        let call = json!({ "from": sender, "to": looper, "input": "0x01", "maxFeePerGas": fee, "maxPriorityFeePerGas": "0x1" });
+        let err = estimate_gas(&Some(json!([call])), &context).unwrap_err();
+        assert!(
+            matches!(&err, RpcErr::Execution(message) if message == "gas required exceeds allowance (30000)"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn applies_overrides() {
+        let contract = Address::repeat_byte(0xcc);
+        let (_dir, context, _) = context(&[]);
+        // A sender without funds, given some, calling code put in place: SSTORE(0, NUMBER)
+        let sender = Address::repeat_byte(0x11);
+        let call = json!({ "from": sender, "to": contract, "input": "0x01", "value": "0x1" });
+        let state = json!({
+            format!("{sender:?}"): { "balance": "0xde0b6b3a7640000" },
+            format!("{contract:?}"): { "code": "0x435f5500", "stateDiff": { format!("{:?}", H256::zero()): H256::from_low_u64_be(5) } },
+        });
+        // Rewriting the slot costs less than setting it, which the state override makes it
+        let with_state = estimate(&context, json!([call, "latest", state])).unwrap();
+        let blocks = json!({ "number": "0x5" });
+        let same_value = estimate(&context, json!([call, "latest", state, blocks])).unwrap();
+        assert!(same_value < with_state, "{same_value} {with_state}");
+
+        let err = estimate_gas(&Some(json!([call])), &context).unwrap_err();
+        assert!(
+            matches!(&err, RpcErr::Execution(message) if message.starts_with("insufficient funds")),
+            "{err:?}"
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use serde_json::Value;
+use storage::Store;
+
+use crate::{
+    types::{filter::RpcLogFilter, receipt::RpcLog, transaction::TxBlockInfo},
+    utils::{parse_param, positional_params, to_json, RpcErr},
+};
+
+/// Most logs returned by a single `eth_getLogs` call.
+const MAX_LOGS: usize = 10_000;
+/// Widest block range accepted for filters without any address or topic, which
+/// cannot use the log index and read the receipts of every block in range.
+const MAX_UNFILTERED_RANGE: u64 = 10_000;
+
+/// `eth_getLogs`: returns the canonical logs matching a filter object.
+pub fn get_logs(params: &Option<Value>, store: &Store) -> Result<Value, RpcErr> {
+    let [filter] = positional_params(params)?;
+    let Some(filter) = parse_param::<RpcLogFilter>(filter)?.resolve(store)? else {
+        return Ok(Value::Array(Vec::new()));
+    };
+    if filter.from_block < store.earliest_history_block()? {
+        return Err(RpcErr::PrunedHistory);
+    }
+    let unfiltered = filter.addresses.is_empty() && filter.topics.iter().all(Vec::is_empty);
+    if unfiltered && filter.to_block - filter.from_block >= MAX_UNFILTERED_RANGE {
+        return Err(RpcErr::BadParams(format!(
+            "block range is limited to {MAX_UNFILTERED_RANGE} blocks without an address or topic"
+        )));
+    }
+
+    let mut logs = Vec::new();
+    for number in store.get_log_blocks(&filter)? {
+        let (Some(block_hash), Some(receipts)) = (
+            store.get_canonical_block_hash(number)?,
+            store.get_receipts(number)?,
+        ) else {
+            continue;
+        };
+        if !receipts
+            .iter()
+            .flat_map(|r| &r.logs)
+            .any(|log| filter.matches(log))
+        {
+            continue;
+        }
+        let body = store
+            .get_block_body(number)?
+            .ok_or_else(|| RpcErr::Internal(format!("missing body of block {number}")))?;
+        let mut log_index = 0;
+        for (index, (receipt, tx)) in receipts.iter().zip(&body.transactions).enumerate() {
+            let block = TxBlockInfo {
+                block_hash,
+                block_number: number,
+                index: index as u64,
+                base_fee_per_gas: None,
+            };
+            for log in &receipt.logs {
+                if filter.matches(log) {
+                    logs.push(RpcLog::build(log, block, tx.compute_hash(), log_index));
+                }
+                log_index += 1;
+            }
+        }
+        if logs.len() > MAX_LOGS {
+            return Err(RpcErr::BadParams(format!(
+                "query returned more than {MAX_LOGS} results, narrow the block range"
+            )));
+        }
+    }
+    to_json(logs)
+}
+
+#[cfg(test)]
+mod test {
+    use ethereum_types::{Address, H256, U256};
+    use reec_core::types::{
+        compute_receipts_root, Block, BlockBody, BlockHeader, LegacyTransaction, Log, Receipt,
+        ReceiptOutcome, Transaction, TxType,
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::eth::transaction::get_transaction_receipt;
+
+    fn log(address: u8, topic: u8) -> Log {
+        Log {
+            address: Address::repeat_byte(address),
+            topics: vec![H256::repeat_byte(topic)],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn returns_matching_logs_with_positions() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let mut parent_hash = H256::zero();
+        for number in 0..3u64 {
+            let header = BlockHeader {
+                parent_hash,
+                number,
+                ..Default::default()
+            };
+            let transactions = (0..2)
+                .map(|nonce| {
+                    Transaction::LegacyTransaction(LegacyTransaction {
+                        nonce: number * 2 + nonce,
+                        ..Default::default()
+                    })
+                })
+                .collect();
+            let receipts = vec![
+                Receipt::new(TxType::Legacy, true, 21_000, vec![log(1, 5), log(2, 6)]),
+                Receipt::new(TxType::Legacy, true, 42_000, vec![log(number as u8, 6)]),
+            ];
+            parent_hash = header.compute_block_hash();
+            store
+                .add_block(
+                    header,
+                    BlockBody {
+                        transactions,
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            store.add_receipts(parent_hash, receipts).unwrap();
+        }
+        store.set_canonical_head(parent_hash).unwrap();
+
+        let filter = json!([{
+            "fromBlock": "0x1",
+            "address": ["0x0202020202020202020202020202020202020202"],
+            "topics": [[H256::repeat_byte(6)]]
+        }]);
+        let logs = get_logs(&Some(filter), &store).unwrap();
+        let positions: Vec<_> = logs
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|log| {
+                (
+                    log["blockNumber"].clone(),
+                    log["transactionIndex"].clone(),
+                    log["logIndex"].clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (json!("0x1"), json!("0x0"), json!("0x1")),
+                (json!("0x2"), json!("0x0"), json!("0x1")),
+                (json!("0x2"), json!("0x1"), json!("0x2")),
+            ]
+        );
+
+        let none = json!([{ "address": "0x0909090909090909090909090909090909090909" }]);
+        assert_eq!(get_logs(&Some(none), &store).unwrap(), json!([]));
+        let combined = json!([{ "blockHash": parent_hash, "fromBlock": "0x0" }]);
+        assert!(get_logs(&Some(combined), &store).is_err());
+    }
+
+    #[test]
+    fn serves_receipts_imported_from_era1_before_byzantium() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let mut parent_hash = H256::zero();
+        let mut blocks = Vec::new();
+        for number in 0..2u64 {
+            let mut tx = Transaction::LegacyTransaction(LegacyTransaction {
+                nonce: number,
+                gas: 21_000,
+                ..Default::default()
+            });
+            tx.sign(&[0x42; 32], None).unwrap();
+            let body = BlockBody {
+                transactions: vec![tx],
+                ..Default::default()
+            };
+            let outcome = ReceiptOutcome::StateRoot(H256::repeat_byte(number as u8 + 1));
+            let receipts = vec![Receipt::with_outcome(
+                TxType::Legacy,
+                outcome,
+                21_000,
+                vec![log(number as u8, 6)],
+            )];
+            let header = BlockHeader {
+                parent_hash,
+                number,
+                difficulty: U256::one(),
+                ommers_hash: body.compute_ommers_hash(),
+                transactions_root: body.compute_transactions_root(),
+                receipts_root: compute_receipts_root(&receipts),
+                ..Default::default()
+            };
+            parent_hash = header.compute_block_hash();
+            blocks.push((Block { header, body }, receipts));
+        }
+        let path = dir.path().join("frontier.era1");
+        let root = storage::era::write_era1(&path, &blocks, U256::zero()).unwrap();
+        store
+            .import_era1(&path, &storage::era::EpochRoots(vec![root]))
+            .unwrap();
+
+        let tx_hash = blocks[1].0.body.transactions[0].compute_hash();
+        let receipt = get_transaction_receipt(&Some(json!([tx_hash])), &store).unwrap();
+        assert_eq!(receipt["root"], json!(H256::repeat_byte(2)));
+        assert!(receipt.get("status").is_none());
+        assert_eq!(
+            receipt["logs"][0]["address"],
+            json!(Address::repeat_byte(1))
+        );
+
+        let filter = json!([{ "fromBlock": "0x0", "topics": [H256::repeat_byte(6)] }]);
+        let logs = get_logs(&Some(filter), &store).unwrap();
+        let hashes: Vec<_> = logs
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|log| log["transactionHash"].clone())
+            .collect();
+        let expected: Vec<_> = blocks
+            .iter()
+            .map(|(block, _)| json!(block.body.transactions[0].compute_hash()))
+            .collect();
+        assert_eq!(hashes, expected);
+    }
+}
@@ -1,2 +1,5 @@
 pub(crate) mod block;
-pub(crate) mod client;
\ No newline at end of file
+pub(crate) mod client;
+pub(crate) mod gas;
+pub(crate) mod logs;
+pub(crate) mod transaction;
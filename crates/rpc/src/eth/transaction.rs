@@ -0,0 +1,147 @@
+use ethereum_types::H256;
+use reec_core::types::Transaction;
+use serde_json::Value;
+use storage::Store;
+
+use crate::{
+    types::{
+        receipt::RpcReceipt,
+        transaction::{RpcTransaction, TxBlockInfo},
+    },
+    utils::{parse_param, positional_params, to_json, RpcErr},
+};
+
+/// `eth_getTransactionByHash`: looks up a canonical transaction by its hash.
+pub fn get_transaction_by_hash(params: &Option<Value>, store: &Store) -> Result<Value, RpcErr> {
+    let [hash] = positional_params(params)?;
+    let Some((tx, block)) = find_transaction(store, parse_param(hash)?)? else {
+        return Ok(Value::Null);
+    };
+    to_json(RpcTransaction::build(&tx, Some(block))?)
+}
+
+/// `eth_getTransactionReceipt`: returns the receipt of a canonical transaction.
+pub fn get_transaction_receipt(params: &Option<Value>, store: &Store) -> Result<Value, RpcErr> {
+    let [hash] = positional_params(params)?;
+    let Some((tx, block)) = find_transaction(store, parse_param(hash)?)? else {
+        return Ok(Value::Null);
+    };
+    let Some(receipts) = store.get_receipts_by_hash(block.block_hash)? else {
+        if block.block_number < store.earliest_history_block()? {
+            return Err(RpcErr::PrunedHistory);
+        }
+        return Ok(Value::Null);
+    };
+    to_json(RpcReceipt::build(&tx, block, &receipts)?)
+}
+
+fn find_transaction(
+    store: &Store,
+    hash: H256,
+) -> Result<Option<(Transaction, TxBlockInfo)>, RpcErr> {
+    let Some((number, index)) = store.get_transaction_location(hash)? else {
+        return Ok(None);
+    };
+    let Some(block_hash) = store.get_canonical_block_hash(number)? else {
+        return Ok(None);
+    };
+    let (Some(header), Some(body)) = (
+        store.get_block_header_by_hash(block_hash)?,
+        store.get_block_body_by_hash(block_hash)?,
+    ) else {
+        return Ok(None);
+    };
+    let Some(tx) = body.transactions.into_iter().nth(index as usize) else {
+        return Ok(None);
+    };
+    let block = TxBlockInfo {
+        block_hash,
+        block_number: number,
+        index,
+        base_fee_per_gas: header.base_fee_per_gas,
+    };
+    Ok(Some((tx, block)))
+}
+
+#[cfg(test)]
+mod test {
+    use ethereum_types::{Address, U256};
+    use reec_core::types::{
+        BlockBody, BlockHeader, EIP1559Transaction, Log, Receipt, TxKind, TxType,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn receipt_of_canonical_transaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let secret_key = [0x42; 32];
+        let txs: Vec<_> = (0..2)
+            .map(|nonce| {
+                let mut tx = Transaction::EIP1559Transaction(EIP1559Transaction {
+                    nonce,
+                    max_priority_fee_per_gas: U256::from(2),
+                    max_fee_per_gas: U256::from(20),
+                    gas_limit: 50_000,
+                    to: if nonce == 0 {
+                        TxKind::Call(Address::repeat_byte(1))
+                    } else {
+                        TxKind::Create
+                    },
+                    ..Default::default()
+                });
+                tx.sign(&secret_key, Some(1)).unwrap();
+                tx
+            })
+            .collect();
+        let log = Log {
+            address: Address::repeat_byte(9),
+            ..Default::default()
+        };
+        let receipts = vec![
+            Receipt::new(
+                TxType::EIP1559,
+                true,
+                21_000,
+                vec![log.clone(), log.clone()],
+            ),
+            Receipt::new(TxType::EIP1559, false, 74_000, vec![log]),
+        ];
+        let header = BlockHeader {
+            base_fee_per_gas: Some(10),
+            ..Default::default()
+        };
+        let hash = header.compute_block_hash();
+        let body = BlockBody {
+            transactions: txs.clone(),
+            ..Default::default()
+        };
+        store.add_block(header, body).unwrap();
+        store.add_receipts(hash, receipts).unwrap();
+        store.set_canonical_head(hash).unwrap();
+
+        let params = Some(json!([txs[1].compute_hash()]));
+        let receipt = get_transaction_receipt(&params, &store).unwrap();
+        assert_eq!(receipt["gasUsed"], json!("0xcf08"));
+        assert_eq!(receipt["status"], json!("0x0"));
+        assert_eq!(receipt["effectiveGasPrice"], json!("0xc"));
+        assert_eq!(receipt["logs"][0]["logIndex"], json!("0x2"));
+        assert_eq!(receipt["to"], Value::Null);
+        assert!(receipt["contractAddress"].is_string());
+
+        let tx = get_transaction_by_hash(&params, &store).unwrap();
+        assert_eq!(tx["transactionIndex"], json!("0x1"));
+        assert_eq!(
+            tx["from"],
+            json!(reec_core::crypto::secret_key_to_address(&secret_key).unwrap())
+        );
+
+        let missing = Some(json!([H256::zero()]));
+        assert_eq!(
+            get_transaction_receipt(&missing, &store).unwrap(),
+            Value::Null
+        );
+    }
+}
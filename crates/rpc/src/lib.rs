@@ -1,95 +1,214 @@
-use std::future::IntoFuture;
-use axum::{routing::post, Json, Router};
-use serde_json::Value;
-use tracing::info;
-use tokio::net::TcpListener;
+use axum::{extract::State, routing::post, Json, Router};
+use debug::trace::{trace_block_by_number, trace_call, trace_transaction};
 use engine::capabilities::exchange_capabilities;
 use eth::{
-    block::get_block_by_number, 
-    client::{chain_id, syncing}
+    block::{get_block_by_hash, get_block_by_number},
+    client::{chain_id, syncing},
+    gas::estimate_gas,
+    logs::get_logs,
+    transaction::{get_transaction_by_hash, get_transaction_receipt},
 };
+use evm::ChainConfig;
+use serde_json::Value;
+use std::future::IntoFuture;
+use storage::Store;
+use tokio::net::TcpListener;
+use tracing::info;
 use utils::{RpcErr, RpcErrorMetadata, RpcErrorResponse, RpcRequest, RpcSuccessResponse};
 
+mod debug;
 mod engine;
-mod utils;
 mod eth;
+mod types;
+mod utils;
+
+/// What the HTTP methods are served from.
+#[derive(Clone, Debug)]
+pub struct RpcApiContext {
+    pub store: Store,
+    /// Fork schedule for re-executing the chain's blocks.
+    pub chain_config: ChainConfig,
+}
 
 #[tokio::main]
-pub async fn start_api(http_addr: &str, http_port: &str, authrpc_addr: &str, authrpc_port: &str) {
-    let http_router = Router::new().route("/", post(handle_http_request));
+pub async fn start_api(
+    http_addr: &str,
+    http_port: &str,
+    authrpc_addr: &str,
+    authrpc_port: &str,
+    context: RpcApiContext,
+) {
+    let http_router = Router::new()
+        .route("/", post(handle_http_request))
+        .with_state(context);
     let http_url = create_url(http_addr, http_port);
     let http_listener = TcpListener::bind(&http_url).await.unwrap();
-    let http_server = axum::serve(http_listener, http_router).with_graceful_shutdown(shutdown_signal()).into_future();
+    let http_server = axum::serve(http_listener, http_router)
+        .with_graceful_shutdown(shutdown_signal())
+        .into_future();
     info!("HTTP Server listening on {}", http_url);
 
     let authrpc_router = Router::new().route("/", post(handle_authrpc_request));
     let authrpc_url = create_url(authrpc_addr, authrpc_port);
     let authrpc_listener = TcpListener::bind(&authrpc_url).await.unwrap();
-    let authrpc_server = axum::serve(authrpc_listener, authrpc_router).with_graceful_shutdown(shutdown_signal()).into_future();
+    let authrpc_server = axum::serve(authrpc_listener, authrpc_router)
+        .with_graceful_shutdown(shutdown_signal())
+        .into_future();
     info!("AuthRPC Server listening on {}", authrpc_url);
 
     info!("Servers started successfully. Press Ctrl+C to stop.");
 
     let res = tokio::try_join!(http_server, authrpc_server);
     match res {
-        Ok(_) => {},
+        Ok(_) => {}
         Err(e) => info!("Error, shutting down servers: {:?}", e),
     }
 }
 
-async fn shutdown_signal(){
-    tokio::signal::ctrl_c().await.expect("Failed to listen to the shutdown signal");
+async fn shutdown_signal() {
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Failed to listen to the shutdown signal");
 }
 
-
 fn create_url(addr: &str, port: &str) -> String {
     format!("{}:{}", addr, port)
 }
 
-pub async fn handle_http_request(body: String) -> Json<Value> {
-    let req: RpcRequest = serde_json::from_str(&body).unwrap();
+pub async fn handle_http_request(
+    State(context): State<RpcApiContext>,
+    body: String,
+) -> Json<Value> {
+    let req = match parse_request(&body) {
+        Ok(req) => req,
+        Err(error) => return rpc_error_response(None, error),
+    };
+    let store = &context.store;
 
     let res: Result<Value, RpcErr> = match req.method.as_str() {
         "engine_exchangeCapabilities" => exchange_capabilities(),
         "eth_chainId" => chain_id(),
         "eth_syncing" => syncing(),
-        "eth_getBlockByNumber" => get_block_by_number(),
+        "eth_getBlockByNumber" => get_block_by_number(&req.params, store),
+        "eth_getBlockByHash" => get_block_by_hash(&req.params, store),
+        "eth_getTransactionByHash" => get_transaction_by_hash(&req.params, store),
+        "eth_getTransactionReceipt" => get_transaction_receipt(&req.params, store),
+        "eth_getLogs" => get_logs(&req.params, store),
+        "eth_estimateGas" => blocking(estimate_gas, &req.params, &context).await,
+        "debug_traceTransaction" => blocking(trace_transaction, &req.params, &context).await,
+        "debug_traceCall" => blocking(trace_call, &req.params, &context).await,
+        "debug_traceBlockByNumber" => blocking(trace_block_by_number, &req.params, &context).await,
         _ => Err(RpcErr::MethodNotFound),
     };
 
     rpc_response(req, res)
 }
 
-pub async fn handle_authrpc_request(body: String) -> Json<Value>{
-    let req: RpcRequest = serde_json::from_str(&body).unwrap();
+pub async fn handle_authrpc_request(body: String) -> Json<Value> {
+    let req = match parse_request(&body) {
+        Ok(req) => req,
+        Err(error) => return rpc_error_response(None, error),
+    };
 
     let res: Result<Value, RpcErr> = match req.method.as_str() {
         "engine_exchangeCapabilities" => exchange_capabilities(),
-        _ => Err(RpcErr::MethodNotFound)
+        _ => Err(RpcErr::MethodNotFound),
     };
 
     rpc_response(req, res)
 }
 
+/// Runs a handler that executes transactions on tokio's blocking pool, so that a long
+/// replay does not hold up the workers serving other requests.
+async fn blocking(
+    handler: fn(&Option<Value>, &RpcApiContext) -> Result<Value, RpcErr>,
+    params: &Option<Value>,
+    context: &RpcApiContext,
+) -> Result<Value, RpcErr> {
+    let (params, context) = (params.clone(), context.clone());
+    tokio::task::spawn_blocking(move || handler(&params, &context))
+        .await
+        .map_err(|err| RpcErr::Internal(err.to_string()))?
+}
+
+/// Parses a request body, telling malformed JSON apart from JSON that is not a request.
+fn parse_request(body: &str) -> Result<RpcRequest, RpcErr> {
+    let value: Value =
+        serde_json::from_str(body).map_err(|err| RpcErr::ParseError(err.to_string()))?;
+    serde_json::from_value(value).map_err(|err| RpcErr::InvalidRequest(err.to_string()))
+}
+
 fn rpc_response<E>(req: RpcRequest, res: Result<Value, E>) -> Json<Value>
-where E: Into<RpcErrorMetadata>
+where
+    E: Into<RpcErrorMetadata>,
 {
     match res {
         Ok(result) => Json(
             serde_json::to_value(&RpcSuccessResponse {
                 id: req.id,
                 jsonrpc: "2.0".to_string(),
-                result: result,
-            }).unwrap(),
+                result,
+            })
+            .unwrap(),
         ),
-        Err(error) => Json(
-            serde_json::to_value(&RpcErrorResponse {
-                id: req.id,
-                jsonrpc: "2.0".to_string(),
-                error: error.into(),
-            }).unwrap(),
-        )
+        Err(error) => rpc_error_response(Some(req.id), error),
     }
 }
 
+/// An error response, with a null id for requests whose id could not be read.
+fn rpc_error_response(id: Option<i32>, error: impl Into<RpcErrorMetadata>) -> Json<Value> {
+    Json(
+        serde_json::to_value(&RpcErrorResponse {
+            id,
+            jsonrpc: "2.0".to_string(),
+            error: error.into(),
+        })
+        .unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
 
+    use super::*;
+
+    fn context() -> (tempfile::TempDir, RpcApiContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let context = RpcApiContext {
+            store: Store::new(dir.path()).unwrap(),
+            chain_config: ChainConfig::mainnet(),
+        };
+        (dir, context)
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_requests() {
+        let (_dir, context) = context();
+        let Json(response) =
+            handle_http_request(State(context.clone()), "{\"id\": 1,".to_string()).await;
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["error"]["code"], -32700);
+
+        let Json(response) =
+            handle_http_request(State(context), json!({"id": 1}).to_string()).await;
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["error"]["code"], -32600);
+
+        let Json(response) = handle_authrpc_request("[".to_string()).await;
+        assert_eq!(response["error"]["code"], -32700);
+    }
+
+    #[tokio::test]
+    async fn traces_on_the_blocking_pool() {
+        let (_dir, context) = context();
+        let request = json!({
+            "id": 7,
+            "jsonrpc": "2.0",
+            "method": "debug_traceTransaction",
+            "params": [format!("{:#x}", ethereum_types::H256::zero()), {"tracer": "callTracer"}],
+        });
+        let Json(response) = handle_http_request(State(context), request.to_string()).await;
+        assert_eq!(response, json!({"id": 7, "jsonrpc": "2.0", "result": null}));
+    }
+}
@@ -0,0 +1,151 @@
+use ethereum_types::{Address, Bloom, H256, H64, U256};
+use reec_core::{
+    rlp::encode::RLPEncode,
+    types::{Block, BlockBody, BlockHeader, BlockNumber, Withdrawal},
+};
+use serde::Serialize;
+
+use super::{
+    serde_utils::{bytes, quantity},
+    transaction::{RpcTransaction, TxBlockInfo},
+};
+use crate::utils::RpcErr;
+
+/// A block as returned by `eth_getBlockBy*`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcBlock {
+    hash: H256,
+    #[serde(with = "quantity")]
+    number: BlockNumber,
+    parent_hash: H256,
+    sha3_uncles: H256,
+    miner: Address,
+    state_root: H256,
+    transactions_root: H256,
+    receipts_root: H256,
+    logs_bloom: Bloom,
+    difficulty: U256,
+    #[serde(with = "quantity")]
+    gas_limit: u64,
+    #[serde(with = "quantity")]
+    gas_used: u64,
+    #[serde(with = "quantity")]
+    timestamp: u64,
+    #[serde(with = "bytes")]
+    extra_data: ::bytes::Bytes,
+    mix_hash: H256,
+    nonce: H64,
+    #[serde(with = "quantity::option", skip_serializing_if = "Option::is_none")]
+    base_fee_per_gas: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    withdrawals_root: Option<H256>,
+    #[serde(with = "quantity::option", skip_serializing_if = "Option::is_none")]
+    blob_gas_used: Option<u64>,
+    #[serde(with = "quantity::option", skip_serializing_if = "Option::is_none")]
+    excess_blob_gas: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_beacon_block_root: Option<H256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    requests_hash: Option<H256>,
+    #[serde(with = "quantity")]
+    size: u64,
+    transactions: BlockTransactions,
+    uncles: Vec<H256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    withdrawals: Option<Vec<RpcWithdrawal>>,
+}
+
+impl RpcBlock {
+    /// Builds the block with either full transaction objects (`hydrated`) or only their
+    /// hashes.
+    pub fn build(header: BlockHeader, body: BlockBody, hydrated: bool) -> Result<Self, RpcErr> {
+        let block = Block { header, body };
+        let size = block.encode_to_vec().len() as u64;
+        let Block { header, body } = block;
+        let hash = header.compute_block_hash();
+        let transactions = if hydrated {
+            let txs = body.transactions.iter().enumerate().map(|(index, tx)| {
+                let block = TxBlockInfo {
+                    block_hash: hash,
+                    block_number: header.number,
+                    index: index as u64,
+                    base_fee_per_gas: header.base_fee_per_gas,
+                };
+                RpcTransaction::build(tx, Some(block))
+            });
+            BlockTransactions::Full(txs.collect::<Result<_, _>>()?)
+        } else {
+            BlockTransactions::Hashes(
+                body.transactions
+                    .iter()
+                    .map(|tx| tx.compute_hash())
+                    .collect(),
+            )
+        };
+        Ok(RpcBlock {
+            hash,
+            number: header.number,
+            parent_hash: header.parent_hash,
+            sha3_uncles: header.ommers_hash,
+            miner: header.coinbase,
+            state_root: header.state_root,
+            transactions_root: header.transactions_root,
+            receipts_root: header.receipts_root,
+            logs_bloom: header.logs_bloom,
+            difficulty: header.difficulty,
+            gas_limit: header.gas_limit,
+            gas_used: header.gas_used,
+            timestamp: header.timestamp,
+            extra_data: header.extra_data,
+            mix_hash: header.prev_randao,
+            nonce: H64::from_low_u64_be(header.nonce),
+            base_fee_per_gas: header.base_fee_per_gas,
+            withdrawals_root: header.withdrawals_root,
+            blob_gas_used: header.blob_gas_used,
+            excess_blob_gas: header.excess_blob_gas,
+            parent_beacon_block_root: header.parent_beacon_block_root,
+            requests_hash: header.requests_hash,
+            size,
+            transactions,
+            uncles: body
+                .ommers
+                .iter()
+                .map(|ommer| ommer.compute_block_hash())
+                .collect(),
+            withdrawals: body
+                .withdrawals
+                .map(|withdrawals| withdrawals.into_iter().map(RpcWithdrawal::from).collect()),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum BlockTransactions {
+    Hashes(Vec<H256>),
+    Full(Vec<RpcTransaction>),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcWithdrawal {
+    #[serde(with = "quantity")]
+    index: u64,
+    #[serde(with = "quantity")]
+    validator_index: u64,
+    address: Address,
+    #[serde(with = "quantity")]
+    amount: u64,
+}
+
+impl From<Withdrawal> for RpcWithdrawal {
+    fn from(withdrawal: Withdrawal) -> Self {
+        RpcWithdrawal {
+            index: withdrawal.index,
+            validator_index: withdrawal.validator_index,
+            address: withdrawal.address,
+            amount: withdrawal.amount,
+        }
+    }
+}
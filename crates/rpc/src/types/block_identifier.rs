@@ -0,0 +1,73 @@
+use reec_core::types::BlockNumber;
+use serde_json::Value;
+use storage::Store;
+
+use super::serde_utils::quantity;
+use crate::utils::RpcErr;
+
+/// A block number or one of the named block tags accepted by the `eth` namespace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockIdentifier {
+    Number(BlockNumber),
+    Earliest,
+    Latest,
+    Safe,
+    Finalized,
+    Pending,
+}
+
+impl BlockIdentifier {
+    pub fn parse(value: &Value) -> Result<Self, RpcErr> {
+        let Value::String(value) = value else {
+            return Err(RpcErr::BadParams(
+                "block identifier must be a string".to_string(),
+            ));
+        };
+        match value.as_str() {
+            "earliest" => Ok(BlockIdentifier::Earliest),
+            "latest" => Ok(BlockIdentifier::Latest),
+            "safe" => Ok(BlockIdentifier::Safe),
+            "finalized" => Ok(BlockIdentifier::Finalized),
+            "pending" => Ok(BlockIdentifier::Pending),
+            number => quantity::parse(number)
+                .map(BlockIdentifier::Number)
+                .map_err(RpcErr::BadParams),
+        }
+    }
+
+    /// Returns the number of the block this identifier points to, if the node has one.
+    /// There is no pending block yet, so `pending` resolves to the latest one.
+    pub fn resolve(&self, store: &Store) -> Result<Option<BlockNumber>, RpcErr> {
+        let number = match self {
+            BlockIdentifier::Number(number) => Some(*number),
+            BlockIdentifier::Earliest => Some(0),
+            BlockIdentifier::Latest | BlockIdentifier::Pending => {
+                store.get_latest_block_number()?
+            }
+            BlockIdentifier::Safe => store.get_safe_block_number()?,
+            BlockIdentifier::Finalized => store.get_finalized_block_number()?,
+        };
+        Ok(number)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_tags_and_numbers() {
+        assert_eq!(
+            BlockIdentifier::parse(&json!("latest")).unwrap(),
+            BlockIdentifier::Latest
+        );
+        assert_eq!(
+            BlockIdentifier::parse(&json!("0x10")).unwrap(),
+            BlockIdentifier::Number(16)
+        );
+        assert!(BlockIdentifier::parse(&json!("newest")).is_err());
+        assert!(BlockIdentifier::parse(&json!(16)).is_err());
+    }
+}
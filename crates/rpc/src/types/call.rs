@@ -0,0 +1,85 @@
+use bytes::Bytes;
+use ethereum_types::{Address, U256};
+use reec_core::types::{EIP1559Transaction, LegacyTransaction, Transaction, TxKind};
+use serde::Deserialize;
+
+use super::serde_utils;
+
+/// Call object accepted by the methods that run a message without a signed
+/// transaction, such as `debug_traceCall`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcCallRequest {
+    pub from: Option<Address>,
+    pub to: Option<Address>,
+    #[serde(default, with = "serde_utils::quantity::option")]
+    pub gas: Option<u64>,
+    pub gas_price: Option<U256>,
+    /// Make the call an EIP-1559 one unless `gas_price` is given.
+    pub max_fee_per_gas: Option<U256>,
+    pub max_priority_fee_per_gas: Option<U256>,
+    pub value: Option<U256>,
+    /// Accepted as `input` too, which takes precedence.
+    #[serde(default, deserialize_with = "serde_utils::bytes::option::deserialize")]
+    pub data: Option<Bytes>,
+    #[serde(default, deserialize_with = "serde_utils::bytes::option::deserialize")]
+    pub input: Option<Bytes>,
+}
+
+impl RpcCallRequest {
+    /// The account the call is made from, the zero address if none is given.
+    pub fn sender(&self) -> Address {
+        self.from.unwrap_or_default()
+    }
+
+    /// Whether the call offers to pay for its gas. Calls that do not are run with the
+    /// base fee waived, as geth does.
+    pub fn sets_fees(&self) -> bool {
+        self.gas_price.is_some()
+            || self.max_fee_per_gas.is_some()
+            || self.max_priority_fee_per_gas.is_some()
+    }
+
+    /// The most the call pays per gas: its gas price, or its max fee per gas for an
+    /// EIP-1559 call.
+    pub fn fee_cap(&self) -> U256 {
+        self.gas_price.or(self.max_fee_per_gas).unwrap_or_default()
+    }
+
+    /// The call as an unsigned transaction with `gas_limit` gas: a legacy one, or an
+    /// EIP-1559 one if it gives 1559 fees and no gas price. `nonce` should be the
+    /// sender's so the transaction is valid.
+    pub fn to_transaction(&self, chain_id: u64, nonce: u64, gas_limit: u64) -> Transaction {
+        let to = self.to.map_or(TxKind::Create, TxKind::Call);
+        let value = self.value.unwrap_or_default();
+        let data = self
+            .input
+            .clone()
+            .or_else(|| self.data.clone())
+            .unwrap_or_default();
+        if self.gas_price.is_none()
+            && (self.max_fee_per_gas.is_some() || self.max_priority_fee_per_gas.is_some())
+        {
+            return Transaction::EIP1559Transaction(EIP1559Transaction {
+                chain_id,
+                nonce,
+                max_priority_fee_per_gas: self.max_priority_fee_per_gas.unwrap_or_default(),
+                max_fee_per_gas: self.max_fee_per_gas.unwrap_or_default(),
+                gas_limit,
+                to,
+                value,
+                data,
+                ..Default::default()
+            });
+        }
+        Transaction::LegacyTransaction(LegacyTransaction {
+            nonce,
+            gas_price: self.gas_price.unwrap_or_default(),
+            gas: gas_limit,
+            to,
+            value,
+            data,
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,135 @@
+use ethereum_types::{Address, H256};
+use serde::Deserialize;
+use serde_json::Value;
+use storage::{logs::LogFilter, Store};
+
+use super::block_identifier::BlockIdentifier;
+use crate::utils::RpcErr;
+
+/// Topic positions a log can carry.
+const MAX_TOPICS: usize = 4;
+
+/// Filter object accepted by `eth_getLogs`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcLogFilter {
+    from_block: Option<Value>,
+    to_block: Option<Value>,
+    block_hash: Option<H256>,
+    address: Option<OneOrMany<Address>>,
+    #[serde(default)]
+    topics: Vec<Option<OneOrMany<H256>>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> From<OneOrMany<T>> for Vec<T> {
+    fn from(value: OneOrMany<T>) -> Self {
+        match value {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+impl RpcLogFilter {
+    /// Resolves the filter's block range against the canonical chain. Returns `None`
+    /// when the range holds no canonical block, so no log can match.
+    pub fn resolve(self, store: &Store) -> Result<Option<LogFilter>, RpcErr> {
+        if self.topics.len() > MAX_TOPICS {
+            return Err(RpcErr::BadParams(format!(
+                "at most {MAX_TOPICS} topics are allowed"
+            )));
+        }
+        let Some(latest) = store.get_latest_block_number()? else {
+            return Ok(None);
+        };
+        let range = match self.block_hash {
+            Some(_) if self.from_block.is_some() || self.to_block.is_some() => {
+                return Err(RpcErr::BadParams(
+                    "blockHash cannot be combined with fromBlock or toBlock".to_string(),
+                ));
+            }
+            Some(hash) => match store.get_block_number(hash)? {
+                Some(number) if store.get_canonical_block_hash(number)? == Some(hash) => {
+                    Some((number, number))
+                }
+                _ => None,
+            },
+            None => {
+                let from = resolve_or_latest(self.from_block.as_ref(), store, latest)?;
+                let to = resolve_or_latest(self.to_block.as_ref(), store, latest)?;
+                from.zip(to)
+            }
+        };
+        let Some((from_block, to_block)) = range else {
+            return Ok(None);
+        };
+        if from_block > to_block {
+            return Err(RpcErr::BadParams("fromBlock is after toBlock".to_string()));
+        }
+        Ok(Some(LogFilter {
+            from_block,
+            to_block: to_block.min(latest),
+            addresses: self.address.map(Vec::from).unwrap_or_default(),
+            topics: self
+                .topics
+                .into_iter()
+                .map(|accepted| accepted.map(Vec::from).unwrap_or_default())
+                .collect(),
+        }))
+    }
+}
+
+fn resolve_or_latest(
+    identifier: Option<&Value>,
+    store: &Store,
+    latest: u64,
+) -> Result<Option<u64>, RpcErr> {
+    match identifier {
+        Some(identifier) => BlockIdentifier::parse(identifier)?.resolve(store),
+        None => Ok(Some(latest)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_address_and_topic_sets() {
+        let filter: RpcLogFilter = serde_json::from_value(json!({
+            "address": "0x0101010101010101010101010101010101010101",
+            "topics": [
+                null,
+                "0x0202020202020202020202020202020202020202020202020202020202020202",
+                ["0x0303030303030303030303030303030303030303030303030303030303030303"]
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            Vec::from(filter.address.unwrap()),
+            vec![Address::repeat_byte(1)]
+        );
+        let topics: Vec<Vec<H256>> = filter
+            .topics
+            .into_iter()
+            .map(|accepted| accepted.map(Vec::from).unwrap_or_default())
+            .collect();
+        assert_eq!(
+            topics,
+            vec![
+                vec![],
+                vec![H256::repeat_byte(2)],
+                vec![H256::repeat_byte(3)]
+            ]
+        );
+    }
+}
@@ -0,0 +1,8 @@
+pub(crate) mod block;
+pub(crate) mod block_identifier;
+pub(crate) mod call;
+pub(crate) mod filter;
+pub(crate) mod overrides;
+pub(crate) mod receipt;
+pub(crate) mod serde_utils;
+pub(crate) mod transaction;
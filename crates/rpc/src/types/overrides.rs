@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use evm::{BlockEnv, Database};
+use keccak_hash::keccak;
+use reec_core::types::{AccountInfo, BlockNumber, EMPTY_CODE_HASH};
+use serde::Deserialize;
+
+use super::serde_utils;
+use crate::utils::RpcErr;
+
+/// Changes to accounts a call runs with, by address, as geth's state override set.
+#[derive(Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct StateOverride(pub HashMap<Address, AccountOverride>);
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AccountOverride {
+    pub balance: Option<U256>,
+    #[serde(default, with = "serde_utils::quantity::option")]
+    pub nonce: Option<u64>,
+    #[serde(default, deserialize_with = "serde_utils::bytes::option::deserialize")]
+    pub code: Option<Bytes>,
+    /// Replaces the whole storage of the account.
+    pub state: Option<HashMap<H256, H256>>,
+    /// Replaces the given slots and keeps the others.
+    pub state_diff: Option<HashMap<H256, H256>>,
+}
+
+/// Fields of the block environment a call runs in that replace the block's own.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BlockOverrides {
+    #[serde(default, with = "serde_utils::quantity::option")]
+    pub number: Option<u64>,
+    #[serde(default, with = "serde_utils::quantity::option")]
+    pub time: Option<u64>,
+    #[serde(default, with = "serde_utils::quantity::option")]
+    pub gas_limit: Option<u64>,
+    pub fee_recipient: Option<Address>,
+    pub prev_randao: Option<H256>,
+    pub difficulty: Option<U256>,
+    pub base_fee_per_gas: Option<U256>,
+    pub blob_base_fee: Option<U256>,
+}
+
+/// A database with a [`StateOverride`] applied on top.
+#[derive(Debug)]
+pub struct OverriddenState<DB> {
+    db: DB,
+    accounts: HashMap<Address, AccountOverride>,
+    /// Overriding code by its hash.
+    code: HashMap<H256, Bytes>,
+}
+
+impl StateOverride {
+    pub fn apply<DB: Database>(self, db: DB) -> Result<OverriddenState<DB>, RpcErr> {
+        if let Some(address) = self.0.iter().find_map(|(address, account)| {
+            (account.state.is_some() && account.state_diff.is_some()).then_some(address)
+        }) {
+            return Err(RpcErr::BadParams(format!(
+                "account {address:#x} has both state and stateDiff"
+            )));
+        }
+        let code = self
+            .0
+            .values()
+            .filter_map(|account| account.code.clone())
+            .filter(|code| !code.is_empty())
+            .map(|code| (keccak(&code), code))
+            .collect();
+        Ok(OverriddenState {
+            db,
+            accounts: self.0,
+            code,
+        })
+    }
+}
+
+impl BlockOverrides {
+    pub fn apply(&self, block: &mut BlockEnv) {
+        let BlockOverrides {
+            number,
+            time,
+            gas_limit,
+            fee_recipient,
+            prev_randao,
+            difficulty,
+            base_fee_per_gas,
+            blob_base_fee,
+        } = *self;
+        block.number = number.unwrap_or(block.number);
+        block.timestamp = time.unwrap_or(block.timestamp);
+        block.gas_limit = gas_limit.unwrap_or(block.gas_limit);
+        block.coinbase = fee_recipient.unwrap_or(block.coinbase);
+        block.prev_randao = prev_randao.unwrap_or(block.prev_randao);
+        block.difficulty = difficulty.unwrap_or(block.difficulty);
+        block.base_fee = base_fee_per_gas.unwrap_or(block.base_fee);
+        block.blob_base_fee = blob_base_fee.unwrap_or(block.blob_base_fee);
+    }
+}
+
+impl<DB: Database> Database for OverriddenState<DB> {
+    fn account(&self, address: Address) -> anyhow::Result<Option<AccountInfo>> {
+        let info = self.db.account(address)?;
+        let Some(account) = self.accounts.get(&address) else {
+            return Ok(info);
+        };
+        let mut info = info.unwrap_or_default();
+        info.balance = account.balance.unwrap_or(info.balance);
+        info.nonce = account.nonce.unwrap_or(info.nonce);
+        if let Some(code) = &account.code {
+            info.code_hash = if code.is_empty() {
+                EMPTY_CODE_HASH
+            } else {
+                keccak(code)
+            };
+        }
+        Ok(Some(info))
+    }
+
+    fn storage(&self, address: Address, slot: H256) -> anyhow::Result<U256> {
+        let value = |value: &H256| U256::from_big_endian(value.as_bytes());
+        match self.accounts.get(&address) {
+            Some(AccountOverride {
+                state: Some(state), ..
+            }) => Ok(state.get(&slot).map(value).unwrap_or_default()),
+            Some(AccountOverride {
+                state_diff: Some(diff),
+                ..
+            }) if diff.contains_key(&slot) => Ok(value(&diff[&slot])),
+            _ => self.db.storage(address, slot),
+        }
+    }
+
+    fn code(&self, code_hash: H256) -> anyhow::Result<Bytes> {
+        match self.code.get(&code_hash) {
+            Some(code) => Ok(code.clone()),
+            None => self.db.code(code_hash),
+        }
+    }
+
+    fn block_hash(&self, number: BlockNumber) -> anyhow::Result<Option<H256>> {
+        self.db.block_hash(number)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use evm::MemoryDb;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn overrides_accounts_and_storage() {
+        let (address, other) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let (slot, kept) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+        let mut db = MemoryDb::default();
+        db.insert_account(address, 3, U256::from(10), Bytes::new());
+        db.insert_storage(address, slot, U256::one());
+        db.insert_storage(address, kept, U256::from(2));
+        db.insert_storage(other, kept, U256::from(2));
+
+        let key = |value: &dyn std::fmt::Debug| format!("{value:?}");
+        let overrides: StateOverride = serde_json::from_value(json!({
+            key(&address): { "balance": "0x64", "code": "0x6000", "stateDiff": { key(&slot): H256::from_low_u64_be(7) } },
+            key(&other): { "state": { key(&slot): H256::from_low_u64_be(9) } },
+        }))
+        .unwrap();
+        let state = overrides.apply(&db).unwrap();
+        let info = state.account(address).unwrap().unwrap();
+        assert_eq!((info.nonce, info.balance), (3, U256::from(100)));
+        assert_eq!(
+            state.code(info.code_hash).unwrap(),
+            Bytes::from_static(&[0x60, 0x00])
+        );
+        assert_eq!(state.storage(address, slot).unwrap(), U256::from(7));
+        assert_eq!(state.storage(address, kept).unwrap(), U256::from(2));
+        assert_eq!(state.storage(other, slot).unwrap(), U256::from(9));
+        assert_eq!(state.storage(other, kept).unwrap(), U256::zero());
+        assert_eq!(state.account(other).unwrap(), Some(AccountInfo::default()));
+
+        let both: StateOverride =
+            serde_json::from_value(json!({ key(&address): { "state": {}, "stateDiff": {} } }))
+                .unwrap();
+        assert!(matches!(both.apply(&db), Err(RpcErr::BadParams(_))));
+    }
+}
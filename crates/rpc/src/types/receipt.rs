@@ -0,0 +1,136 @@
+use ethereum_types::{Address, Bloom, H256, U256};
+use reec_core::types::{
+    contract_address, BlockHash, BlockNumber, Log, Receipt, ReceiptOutcome, Transaction, TxKind,
+};
+use serde::Serialize;
+
+use super::{
+    serde_utils::{bytes, quantity},
+    transaction::TxBlockInfo,
+};
+use crate::utils::RpcErr;
+
+/// A receipt as returned by `eth_getTransactionReceipt`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcReceipt {
+    transaction_hash: H256,
+    #[serde(with = "quantity")]
+    transaction_index: u64,
+    block_hash: BlockHash,
+    #[serde(with = "quantity")]
+    block_number: BlockNumber,
+    from: Address,
+    to: Option<Address>,
+    #[serde(with = "quantity")]
+    cumulative_gas_used: u64,
+    #[serde(with = "quantity")]
+    gas_used: u64,
+    contract_address: Option<Address>,
+    logs: Vec<RpcLog>,
+    logs_bloom: Bloom,
+    #[serde(rename = "type", with = "quantity")]
+    tx_type: u64,
+    /// State root after the transaction, for receipts from before Byzantium.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    root: Option<H256>,
+    #[serde(with = "quantity::option", skip_serializing_if = "Option::is_none")]
+    status: Option<u64>,
+    effective_gas_price: U256,
+}
+
+impl RpcReceipt {
+    /// Builds the receipt of the transaction at `block.index` from the receipts of every
+    /// transaction in its block, which are needed for gas used and log indices.
+    pub fn build(
+        tx: &Transaction,
+        block: TxBlockInfo,
+        receipts: &[Receipt],
+    ) -> Result<Self, RpcErr> {
+        let index = block.index as usize;
+        let receipt = receipts.get(index).ok_or_else(|| {
+            RpcErr::Internal(format!(
+                "missing receipt {index} of block {}",
+                block.block_number
+            ))
+        })?;
+        let from = tx
+            .sender()
+            .ok_or_else(|| RpcErr::Internal("transaction has an invalid signature".to_string()))?;
+        let previous_gas = index
+            .checked_sub(1)
+            .map_or(0, |previous| receipts[previous].cumulative_gas_used);
+        let first_log_index = receipts[..index]
+            .iter()
+            .map(|r| r.logs.len() as u64)
+            .sum::<u64>();
+        let transaction_hash = tx.compute_hash();
+        let (to, contract_address) = match tx.to() {
+            TxKind::Call(to) => (Some(to), None),
+            TxKind::Create => (None, Some(contract_address(from, tx.nonce()))),
+        };
+        let (root, status) = match receipt.outcome {
+            ReceiptOutcome::StateRoot(root) => (Some(root), None),
+            ReceiptOutcome::Status(succeeded) => (None, Some(succeeded as u64)),
+        };
+        Ok(RpcReceipt {
+            transaction_hash,
+            transaction_index: block.index,
+            block_hash: block.block_hash,
+            block_number: block.block_number,
+            from,
+            to,
+            cumulative_gas_used: receipt.cumulative_gas_used,
+            gas_used: receipt.cumulative_gas_used - previous_gas,
+            contract_address,
+            logs: receipt
+                .logs
+                .iter()
+                .enumerate()
+                .map(|(i, log)| {
+                    RpcLog::build(log, block, transaction_hash, first_log_index + i as u64)
+                })
+                .collect(),
+            logs_bloom: receipt.bloom,
+            tx_type: receipt.tx_type as u64,
+            root,
+            status,
+            effective_gas_price: tx.effective_gas_price(block.base_fee_per_gas),
+        })
+    }
+}
+
+/// A log with the position it was emitted at.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcLog {
+    address: Address,
+    topics: Vec<H256>,
+    #[serde(with = "bytes")]
+    data: ::bytes::Bytes,
+    #[serde(with = "quantity")]
+    block_number: BlockNumber,
+    block_hash: BlockHash,
+    transaction_hash: H256,
+    #[serde(with = "quantity")]
+    transaction_index: u64,
+    #[serde(with = "quantity")]
+    log_index: u64,
+    removed: bool,
+}
+
+impl RpcLog {
+    pub fn build(log: &Log, block: TxBlockInfo, transaction_hash: H256, log_index: u64) -> Self {
+        RpcLog {
+            address: log.address,
+            topics: log.topics.clone(),
+            data: log.data.clone(),
+            block_number: block.block_number,
+            block_hash: block.block_hash,
+            transaction_hash,
+            transaction_index: block.index,
+            log_index,
+            removed: false,
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Serde helpers for the hex encodings used by the JSON-RPC API.
+
+/// Integers as hex quantities: `0x` followed by the shortest hex form, `0x0` for zero.
+pub mod quantity {
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{value:#x}"))
+    }
+
+    pub fn parse(value: &str) -> Result<u64, String> {
+        let digits = value
+            .strip_prefix("0x")
+            .ok_or_else(|| format!("quantity {value} is missing the 0x prefix"))?;
+        if digits.is_empty() || (digits.len() > 1 && digits.starts_with('0')) {
+            return Err(format!("invalid quantity {value}"));
+        }
+        u64::from_str_radix(digits, 16).map_err(|_| format!("invalid quantity {value}"))
+    }
+
+    pub mod option {
+        use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            value: &Option<u64>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(value) => super::serialize(value, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<u64>, D::Error> {
+            Option::<String>::deserialize(deserializer)?
+                .map(|value| super::parse(&value).map_err(D::Error::custom))
+                .transpose()
+        }
+    }
+}
+
+/// Byte strings as `0x` prefixed hex data.
+pub mod bytes {
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(
+        value: &bytes::Bytes,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(value)))
+    }
+
+    pub mod option {
+        use serde::{de::Error, Deserialize, Deserializer};
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<bytes::Bytes>, D::Error> {
+            let Some(value) = Option::<String>::deserialize(deserializer)? else {
+                return Ok(None);
+            };
+            let digits = value.strip_prefix("0x").ok_or_else(|| {
+                D::Error::custom(format!("data {value} is missing the 0x prefix"))
+            })?;
+            hex::decode(digits)
+                .map(|data| Some(data.into()))
+                .map_err(D::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::quantity::parse;
+
+    #[test]
+    fn parses_quantities() {
+        assert_eq!(parse("0x0"), Ok(0));
+        assert_eq!(parse("0x1b4"), Ok(436));
+        assert!(parse("0x").is_err());
+        assert!(parse("0x01").is_err());
+        assert!(parse("1b4").is_err());
+    }
+}
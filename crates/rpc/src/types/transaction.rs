@@ -0,0 +1,146 @@
+use ethereum_types::{Address, H256, U256};
+use reec_core::types::{AuthorizationTuple, BlockHash, BlockNumber, Transaction, TxKind};
+use serde::Serialize;
+
+use super::serde_utils::{bytes, quantity};
+use crate::utils::RpcErr;
+
+/// Where a transaction was included, for transactions that are not pending.
+#[derive(Clone, Copy, Debug)]
+pub struct TxBlockInfo {
+    pub block_hash: BlockHash,
+    pub block_number: BlockNumber,
+    pub index: u64,
+    pub base_fee_per_gas: Option<u64>,
+}
+
+/// A transaction as returned by `eth_getTransactionBy*` and hydrated blocks.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcTransaction {
+    block_hash: Option<H256>,
+    #[serde(with = "quantity::option")]
+    block_number: Option<BlockNumber>,
+    #[serde(with = "quantity::option")]
+    transaction_index: Option<u64>,
+    hash: H256,
+    from: Address,
+    #[serde(rename = "type", with = "quantity")]
+    tx_type: u64,
+    #[serde(with = "quantity")]
+    nonce: u64,
+    #[serde(with = "quantity")]
+    gas: u64,
+    gas_price: U256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_fee_per_gas: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_priority_fee_per_gas: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_fee_per_blob_gas: Option<U256>,
+    to: Option<Address>,
+    value: U256,
+    #[serde(with = "bytes")]
+    input: ::bytes::Bytes,
+    #[serde(with = "quantity::option", skip_serializing_if = "Option::is_none")]
+    chain_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    access_list: Option<Vec<RpcAccessListItem>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blob_versioned_hashes: Option<Vec<H256>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    authorization_list: Option<Vec<RpcAuthorization>>,
+    v: U256,
+    r: U256,
+    s: U256,
+    #[serde(with = "quantity::option", skip_serializing_if = "Option::is_none")]
+    y_parity: Option<u64>,
+}
+
+impl RpcTransaction {
+    pub fn build(tx: &Transaction, block: Option<TxBlockInfo>) -> Result<Self, RpcErr> {
+        let from = tx
+            .sender()
+            .ok_or_else(|| RpcErr::Internal("transaction has an invalid signature".to_string()))?;
+        let (y_parity, r, s) = tx.signature();
+        let (v, y_parity) = match tx {
+            Transaction::LegacyTransaction(legacy) => (legacy.v, None),
+            _ => (U256::from(y_parity as u8), Some(y_parity as u64)),
+        };
+        let typed = !matches!(tx, Transaction::LegacyTransaction(_));
+        Ok(RpcTransaction {
+            block_hash: block.map(|block| block.block_hash),
+            block_number: block.map(|block| block.block_number),
+            transaction_index: block.map(|block| block.index),
+            hash: tx.compute_hash(),
+            from,
+            tx_type: tx.tx_type() as u64,
+            nonce: tx.nonce(),
+            gas: tx.gas_limit(),
+            gas_price: match block {
+                Some(block) => tx.effective_gas_price(block.base_fee_per_gas),
+                None => tx.gas_price(),
+            },
+            max_fee_per_gas: tx.max_priority_fee().map(|_| tx.gas_price()),
+            max_priority_fee_per_gas: tx.max_priority_fee(),
+            max_fee_per_blob_gas: tx.max_fee_per_blob_gas(),
+            to: match tx.to() {
+                TxKind::Call(to) => Some(to),
+                TxKind::Create => None,
+            },
+            value: tx.value(),
+            input: tx.data().clone(),
+            chain_id: tx.chain_id(),
+            access_list: typed.then(|| {
+                tx.access_list()
+                    .iter()
+                    .map(|(address, storage_keys)| RpcAccessListItem {
+                        address: *address,
+                        storage_keys: storage_keys.clone(),
+                    })
+                    .collect()
+            }),
+            blob_versioned_hashes: matches!(tx, Transaction::EIP4844Transaction(_))
+                .then(|| tx.blob_versioned_hashes().to_vec()),
+            authorization_list: tx
+                .authorization_list()
+                .map(|list| list.iter().map(RpcAuthorization::from).collect()),
+            v,
+            r,
+            s,
+            y_parity,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcAccessListItem {
+    address: Address,
+    storage_keys: Vec<H256>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcAuthorization {
+    chain_id: U256,
+    address: Address,
+    #[serde(with = "quantity")]
+    nonce: u64,
+    y_parity: U256,
+    r: U256,
+    s: U256,
+}
+
+impl From<&AuthorizationTuple> for RpcAuthorization {
+    fn from(tuple: &AuthorizationTuple) -> Self {
+        RpcAuthorization {
+            chain_id: tuple.chain_id,
+            address: tuple.address,
+            nonce: tuple.nonce,
+            y_parity: tuple.y_parity,
+            r: tuple.r_signature,
+            s: tuple.s_signature,
+        }
+    }
+}
@@ -1,25 +1,103 @@
-use serde::{Serialize, Deserialize};
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
 
+#[derive(Debug)]
 pub enum RpcErr {
-    MethodNotFound
+    /// A request body that is not JSON.
+    ParseError(String),
+    /// JSON that is not a request object.
+    InvalidRequest(String),
+    MethodNotFound,
+    BadParams(String),
+    Internal(String),
+    /// The requested block data was dropped by the node's prune mode.
+    PrunedHistory,
+    /// A call that could not run, or failed other than by reverting.
+    Execution(String),
+    /// A call that reverted, with its revert data.
+    Revert(Bytes),
 }
 
-impl Into<RpcErrorMetadata> for RpcErr {
-    fn into(self) -> RpcErrorMetadata {
-        match self {
+impl From<RpcErr> for RpcErrorMetadata {
+    fn from(value: RpcErr) -> Self {
+        match value {
+            RpcErr::ParseError(reason) => RpcErrorMetadata {
+                code: -32700,
+                message: format!("Parse error: {reason}"),
+                data: None,
+            },
+            RpcErr::InvalidRequest(reason) => RpcErrorMetadata {
+                code: -32600,
+                message: format!("Invalid request: {reason}"),
+                data: None,
+            },
             RpcErr::MethodNotFound => RpcErrorMetadata {
                 code: -32601,
                 message: "Method Not Found".to_string(),
+                data: None,
+            },
+            RpcErr::BadParams(reason) => RpcErrorMetadata {
+                code: -32602,
+                message: format!("Invalid params: {reason}"),
+                data: None,
+            },
+            RpcErr::Internal(reason) => RpcErrorMetadata {
+                code: -32603,
+                message: format!("Internal Error: {reason}"),
+                data: None,
+            },
+            RpcErr::PrunedHistory => RpcErrorMetadata {
+                code: 4444,
+                message: "Pruned history unavailable".to_string(),
+                data: None,
+            },
+            RpcErr::Execution(reason) => RpcErrorMetadata {
+                code: -32000,
+                message: reason,
+                data: None,
+            },
+            RpcErr::Revert(output) => RpcErrorMetadata {
+                code: 3,
+                message: match evm::tracer::revert_reason(&output) {
+                    Some(reason) => format!("execution reverted: {reason}"),
+                    None => "execution reverted".to_string(),
+                },
+                data: Some(Value::String(format!("0x{}", hex::encode(&output)))),
             },
         }
     }
 }
 
+impl From<anyhow::Error> for RpcErr {
+    fn from(err: anyhow::Error) -> Self {
+        RpcErr::Internal(err.to_string())
+    }
+}
+
+/// Returns the request's positional params, which must be exactly `N`.
+pub fn positional_params<const N: usize>(params: &Option<Value>) -> Result<&[Value; N], RpcErr> {
+    params
+        .as_ref()
+        .and_then(Value::as_array)
+        .and_then(|params| params.as_slice().try_into().ok())
+        .ok_or_else(|| RpcErr::BadParams(format!("expected {N} params")))
+}
+
+pub fn parse_param<T: DeserializeOwned>(param: &Value) -> Result<T, RpcErr> {
+    serde_json::from_value(param.clone()).map_err(|err| RpcErr::BadParams(err.to_string()))
+}
+
+pub fn to_json<T: Serialize>(value: T) -> Result<Value, RpcErr> {
+    serde_json::to_value(value).map_err(|err| RpcErr::Internal(err.to_string()))
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RpcErrorMetadata {
     code: i32,
-    message: String
+    message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -39,7 +117,8 @@ pub struct RpcSuccessResponse {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RpcErrorResponse {
-    pub id: i32,
+    /// `None` when the request's id could not be read.
+    pub id: Option<i32>,
     pub jsonrpc: String,
     pub error: RpcErrorMetadata,
-}
\ No newline at end of file
+}
@@ -0,0 +1,489 @@
+use anyhow::{anyhow, bail};
+use ethereum_types::H256;
+use libmdbx::orm::{Transaction, RW};
+use reec_core::types::{Block, BlockBody, BlockHash, BlockHeader, BlockNumber, Receipt, StateDiff};
+
+use crate::{
+    logs::{index_logs, unindex_logs},
+    read_chain_data_in,
+    state::write_state_in,
+    tables::*,
+    Store,
+};
+
+impl Store {
+    /// Number of the block with the given hash, canonical or not.
+    pub fn get_block_number(&self, hash: BlockHash) -> anyhow::Result<Option<BlockNumber>> {
+        let tx = self.db.begin_read()?;
+        tx.get::<HeaderNumbers>(hash.0)
+    }
+
+    /// Canonical block number and index within that block of a transaction.
+    pub fn get_transaction_location(
+        &self,
+        tx_hash: H256,
+    ) -> anyhow::Result<Option<(BlockNumber, u64)>> {
+        let tx = self.db.begin_read()?;
+        tx.get::<TransactionLocations>(tx_hash.0)
+    }
+
+    /// Makes `hash` the canonical block at `number`, indexing its transactions and logs
+    /// in place of those of the block it replaces. Other heights are left alone, switching to
+    /// another branch is done with [`Store::set_canonical_head`].
+    pub fn set_canonical_block(&self, number: BlockNumber, hash: BlockHash) -> anyhow::Result<()> {
+        let tx = self.db.begin_readwrite()?;
+        canonicalize(&tx, number, Some(hash.0))?;
+        tx.commit()
+    }
+
+    /// Makes the chain ending at `head` canonical and `head` the latest block.
+    ///
+    /// The new branch is followed back through parent hashes until it joins the
+    /// current canonical chain; canonical hashes, transaction locations and log
+    /// indices of the blocks it replaces, or of blocks above the new head, are removed. Reorgs that
+    /// would replace a finalized or frozen block are refused. State is not touched:
+    /// unwinding it is up to the caller.
+    pub fn set_canonical_head(&self, head: BlockHash) -> anyhow::Result<()> {
+        // Read before the write transaction: the freezer lock is always taken before
+        // MDBX's writer lock, never while holding it
+        let frozen = self.ancient_blocks();
+        let tx = self.db.begin_readwrite()?;
+        let head_number = tx
+            .get::<HeaderNumbers>(head.0)?
+            .ok_or_else(|| anyhow!("unknown block {head:#x}"))?;
+
+        let mut branch = Vec::new();
+        let (mut number, mut hash) = (head_number, head.0);
+        while tx.get::<CanonicalHashes>(number)? != Some(hash) {
+            branch.push((number, hash));
+            if number == 0 {
+                break;
+            }
+            let header: BlockHeader = tx
+                .get::<Headers>(hash)?
+                .ok_or_else(|| anyhow!("missing header of block {:#x}", H256(hash)))?
+                .to()?;
+            (number, hash) = (number - 1, header.parent_hash.0);
+        }
+
+        let first_changed = branch.last().map_or(head_number + 1, |(number, _)| *number);
+        let old_head = tx
+            .cursor::<CanonicalHashes>()?
+            .last()?
+            .map(|(number, _)| number);
+        if old_head.is_some_and(|old_head| first_changed <= old_head) {
+            if let Some(finalized) = read_chain_data_in(&tx, ChainDataIndex::FinalizedBlockNumber)?
+            {
+                if first_changed <= finalized {
+                    bail!("new head {head:#x} would reorg finalized block {first_changed}");
+                }
+            }
+            if first_changed < frozen {
+                bail!("new head {head:#x} would reorg frozen block {first_changed}");
+            }
+        }
+
+        for number in (head_number + 1)..=old_head.unwrap_or_default() {
+            canonicalize(&tx, number, None)?;
+        }
+        for (number, hash) in branch.into_iter().rev() {
+            canonicalize(&tx, number, Some(hash))?;
+        }
+        tx.upsert::<ChainData>(
+            ChainDataIndex::LatestBlockNumber,
+            head_number.to_be_bytes().to_vec(),
+        )?;
+        tx.commit()
+    }
+}
+
+impl Store {
+    /// Stores `block`, which must extend the head (or be the genesis of an empty
+    /// database), along with its receipts and the state it produced, and makes it the
+    /// new head. Everything is written in a single transaction, so an interrupted
+    /// import leaves either the whole block or nothing of it.
+    pub fn add_head_block(
+        &self,
+        block: Block,
+        receipts: Vec<Receipt>,
+        state_diff: &StateDiff,
+    ) -> anyhow::Result<()> {
+        let (hash, number) = (block.hash(), block.header.number);
+        let tx = self.db.begin_readwrite()?;
+        let extends_head = match read_chain_data_in(&tx, ChainDataIndex::LatestBlockNumber)? {
+            Some(latest) => {
+                number == latest + 1
+                    && tx.get::<CanonicalHashes>(latest)? == Some(block.header.parent_hash.0)
+            }
+            None => number == 0,
+        };
+        if !extends_head {
+            bail!("block {number} ({hash:#x}) does not extend the head");
+        }
+        write_state_in(&tx, number, state_diff)?;
+        tx.upsert::<HeaderNumbers>(hash.0, number)?;
+        tx.upsert::<Headers>(hash.0, block.header.into())?;
+        tx.upsert::<Bodies>(hash.0, block.body.into())?;
+        tx.upsert::<Receipts>(hash.0, receipts.into())?;
+        canonicalize(&tx, number, Some(hash.0))?;
+        tx.upsert::<ChainData>(
+            ChainDataIndex::LatestBlockNumber,
+            number.to_be_bytes().to_vec(),
+        )?;
+        tx.commit()
+    }
+}
+
+/// Points the canonical chain at `hash` (or nowhere) for `number`, keeping the
+/// transaction locations and log indices in step.
+fn canonicalize(
+    tx: &Transaction<'_, RW>,
+    number: BlockNumber,
+    hash: Option<[u8; 32]>,
+) -> anyhow::Result<()> {
+    let old = tx.get::<CanonicalHashes>(number)?;
+    if old == hash {
+        return Ok(());
+    }
+    if let Some(old) = old {
+        if let Some(body) = tx.get::<Bodies>(old)? {
+            unindex_transactions(tx, number, &body.to()?)?;
+        }
+        if let Some(receipts) = tx.get::<Receipts>(old)? {
+            unindex_logs(tx, number, &receipts.to()?)?;
+        }
+    }
+    match hash {
+        Some(hash) => {
+            tx.upsert::<CanonicalHashes>(number, hash)?;
+            if let Some(body) = tx.get::<Bodies>(hash)? {
+                index_transactions(tx, number, &body.to()?)?;
+            }
+            if let Some(receipts) = tx.get::<Receipts>(hash)? {
+                index_logs(tx, number, &receipts.to()?)?;
+            }
+        }
+        None => {
+            tx.delete::<CanonicalHashes>(number, None)?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn index_transactions(
+    tx: &Transaction<'_, RW>,
+    number: BlockNumber,
+    body: &BlockBody,
+) -> anyhow::Result<()> {
+    for (index, transaction) in body.transactions.iter().enumerate() {
+        tx.upsert::<TransactionLocations>(transaction.compute_hash().0, (number, index as u64))?;
+    }
+    Ok(())
+}
+
+/// Removes the locations of the block's transactions, unless a transaction has since
+/// been indexed in another block.
+pub(crate) fn unindex_transactions(
+    tx: &Transaction<'_, RW>,
+    number: BlockNumber,
+    body: &BlockBody,
+) -> anyhow::Result<()> {
+    for transaction in &body.transactions {
+        let hash = transaction.compute_hash().0;
+        if tx
+            .get::<TransactionLocations>(hash)?
+            .is_some_and(|(indexed, _)| indexed == number)
+        {
+            tx.delete::<TransactionLocations>(hash, None)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc, Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use bytes::Bytes;
+    use reec_core::types::{LegacyTransaction, Transaction as Tx};
+
+    use super::*;
+
+    fn transaction(id: u8) -> Tx {
+        Tx::LegacyTransaction(LegacyTransaction {
+            data: Bytes::from(vec![id]),
+            ..Default::default()
+        })
+    }
+
+    /// Stores a block on top of `parent` without making it canonical.
+    fn add_block(store: &Store, parent: &BlockHeader, salt: u8, txs: &[u8]) -> BlockHeader {
+        let header = BlockHeader {
+            parent_hash: parent.compute_block_hash(),
+            number: parent.number + 1,
+            extra_data: Bytes::from(vec![salt]),
+            ..Default::default()
+        };
+        let body = BlockBody {
+            transactions: txs.iter().map(|id| transaction(*id)).collect(),
+            ..Default::default()
+        };
+        store.add_block(header.clone(), body).unwrap();
+        header
+    }
+
+    fn location(store: &Store, id: u8) -> Option<(BlockNumber, u64)> {
+        store
+            .get_transaction_location(transaction(id).compute_hash())
+            .unwrap()
+    }
+
+    fn genesis(store: &Store) -> BlockHeader {
+        let genesis = BlockHeader::default();
+        store
+            .add_block(genesis.clone(), BlockBody::default())
+            .unwrap();
+        store
+            .set_canonical_head(genesis.compute_block_hash())
+            .unwrap();
+        genesis
+    }
+
+    #[test]
+    fn adds_head_blocks() {
+        use ethereum_types::{Address, U256};
+        use reec_core::types::{AccountDiff, AccountInfo};
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let genesis = Block {
+            header: BlockHeader::default(),
+            body: BlockBody::default(),
+        };
+        let block = Block {
+            header: BlockHeader {
+                parent_hash: genesis.hash(),
+                number: 1,
+                ..Default::default()
+            },
+            body: BlockBody {
+                transactions: vec![transaction(1)],
+                ..Default::default()
+            },
+        };
+        let diff = StateDiff::from([(
+            Address::repeat_byte(1),
+            AccountDiff {
+                info: Some(AccountInfo {
+                    balance: U256::one(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        )]);
+        assert!(store
+            .add_head_block(block.clone(), Vec::new(), &diff)
+            .is_err());
+        store
+            .add_head_block(genesis.clone(), Vec::new(), &StateDiff::new())
+            .unwrap();
+        store
+            .add_head_block(block.clone(), vec![Receipt::default()], &diff)
+            .unwrap();
+
+        assert_eq!(store.get_latest_block_number().unwrap(), Some(1));
+        assert_eq!(
+            store.get_canonical_block_hash(1).unwrap(),
+            Some(block.hash())
+        );
+        assert_eq!(location(&store, 1), Some((1, 0)));
+        assert_eq!(store.get_receipts(1).unwrap().unwrap().len(), 1);
+        assert_eq!(
+            store.get_account_info(Address::repeat_byte(1)).unwrap(),
+            diff[&Address::repeat_byte(1)].info
+        );
+        // Blocks that do not extend the new head
+        assert!(store.add_head_block(block, Vec::new(), &diff).is_err());
+        assert!(store
+            .add_head_block(genesis, Vec::new(), &StateDiff::new())
+            .is_err());
+    }
+
+    #[test]
+    fn indexes_canonical_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let genesis = genesis(&store);
+        let one = add_block(&store, &genesis, 0, &[1, 2]);
+        let two = add_block(&store, &one, 0, &[3]);
+        assert_eq!(location(&store, 1), None);
+
+        store.set_canonical_head(two.compute_block_hash()).unwrap();
+        assert_eq!(store.get_latest_block_number().unwrap(), Some(2));
+        assert_eq!(
+            store.get_canonical_block_hash(1).unwrap(),
+            Some(one.compute_block_hash())
+        );
+        assert_eq!(
+            store.get_block_number(two.compute_block_hash()).unwrap(),
+            Some(2)
+        );
+        assert_eq!(location(&store, 2), Some((1, 1)));
+        assert_eq!(location(&store, 3), Some((2, 0)));
+    }
+
+    #[test]
+    fn reorgs_move_indices() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let genesis = genesis(&store);
+        let a1 = add_block(&store, &genesis, 0, &[1, 2]);
+        let a2 = add_block(&store, &a1, 0, &[3]);
+        let a3 = add_block(&store, &a2, 0, &[4]);
+        store.set_canonical_head(a3.compute_block_hash()).unwrap();
+
+        // Shorter competing branch that includes 3 earlier and drops 2 and 4
+        let b1 = add_block(&store, &genesis, 1, &[1, 3]);
+        let b2 = add_block(&store, &b1, 1, &[5]);
+        store.set_canonical_head(b2.compute_block_hash()).unwrap();
+
+        assert_eq!(store.get_latest_block_number().unwrap(), Some(2));
+        assert_eq!(
+            store.get_canonical_block_hash(1).unwrap(),
+            Some(b1.compute_block_hash())
+        );
+        assert_eq!(
+            store.get_canonical_block_hash(2).unwrap(),
+            Some(b2.compute_block_hash())
+        );
+        assert_eq!(store.get_canonical_block_hash(3).unwrap(), None);
+        assert_eq!(location(&store, 1), Some((1, 0)));
+        assert_eq!(location(&store, 2), None);
+        assert_eq!(location(&store, 3), Some((1, 1)));
+        assert_eq!(location(&store, 4), None);
+        assert_eq!(location(&store, 5), Some((2, 0)));
+        // Blocks of the old branch are still reachable by hash
+        assert_eq!(
+            store.get_block_number(a3.compute_block_hash()).unwrap(),
+            Some(3)
+        );
+
+        // And back again
+        store.set_canonical_head(a3.compute_block_hash()).unwrap();
+        assert_eq!(location(&store, 2), Some((1, 1)));
+        assert_eq!(location(&store, 3), Some((2, 0)));
+        assert_eq!(location(&store, 5), None);
+    }
+
+    #[test]
+    fn rewinds_to_ancestor() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let genesis = genesis(&store);
+        let one = add_block(&store, &genesis, 0, &[1]);
+        let two = add_block(&store, &one, 0, &[2]);
+        store.set_canonical_head(two.compute_block_hash()).unwrap();
+
+        store.set_canonical_head(one.compute_block_hash()).unwrap();
+        assert_eq!(store.get_latest_block_number().unwrap(), Some(1));
+        assert_eq!(store.get_canonical_block_hash(2).unwrap(), None);
+        assert_eq!(location(&store, 1), Some((1, 0)));
+        assert_eq!(location(&store, 2), None);
+    }
+
+    #[test]
+    fn refuses_reorg_of_finalized_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let genesis = genesis(&store);
+        let a1 = add_block(&store, &genesis, 0, &[1]);
+        let a2 = add_block(&store, &a1, 0, &[2]);
+        store.set_canonical_head(a2.compute_block_hash()).unwrap();
+        store.update_finalized_block_number(1).unwrap();
+
+        let b1 = add_block(&store, &genesis, 1, &[3]);
+        assert!(store.set_canonical_head(b1.compute_block_hash()).is_err());
+        assert_eq!(location(&store, 1), Some((1, 0)));
+        let b2 = add_block(&store, &a1, 1, &[3]);
+        store.set_canonical_head(b2.compute_block_hash()).unwrap();
+    }
+
+    #[test]
+    fn indexes_bodies_added_after_canonicalization() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let genesis = genesis(&store);
+        let header = BlockHeader {
+            parent_hash: genesis.compute_block_hash(),
+            number: 1,
+            ..Default::default()
+        };
+        let hash = header.compute_block_hash();
+        store.add_block_header(hash, header).unwrap();
+        store.set_canonical_head(hash).unwrap();
+        let body = BlockBody {
+            transactions: vec![transaction(9)],
+            ..Default::default()
+        };
+        store.add_block_body(hash, body).unwrap();
+        assert_eq!(location(&store, 9), Some((1, 0)));
+    }
+
+    #[test]
+    fn reorgs_while_migrating_to_freezer() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let mut parent = genesis(&store);
+        store
+            .add_receipts(parent.compute_block_hash(), vec![])
+            .unwrap();
+
+        // Imports and reorgs on one thread while another freezes what gets finalized,
+        // as the importer and the pruner do
+        let (finished, done) = mpsc::channel();
+        let importing = Arc::new(AtomicBool::new(true));
+        let pruner = {
+            let (store, importing) = (store.clone(), importing.clone());
+            thread::spawn(move || {
+                while importing.load(Ordering::Relaxed) {
+                    store.migrate_to_freezer(0).unwrap();
+                }
+            })
+        };
+        let importer = {
+            let store = store.clone();
+            thread::spawn(move || {
+                for _ in 0..200 {
+                    let sibling = add_block(&store, &parent, 1, &[]);
+                    store
+                        .set_canonical_head(sibling.compute_block_hash())
+                        .unwrap();
+                    let block = add_block(&store, &parent, 0, &[]);
+                    store
+                        .add_receipts(block.compute_block_hash(), vec![])
+                        .unwrap();
+                    store
+                        .set_canonical_head(block.compute_block_hash())
+                        .unwrap();
+                    store.update_finalized_block_number(block.number).unwrap();
+                    parent = block;
+                }
+                importing.store(false, Ordering::Relaxed);
+                finished.send(()).unwrap();
+            })
+        };
+        done.recv_timeout(Duration::from_secs(60))
+            .expect("importer and pruner deadlocked");
+        importer.join().unwrap();
+        pruner.join().unwrap();
+        assert!(store.ancient_blocks() > 0);
+        assert_eq!(store.get_latest_block_number().unwrap(), Some(200));
+    }
+}
@@ -0,0 +1,698 @@
+//! Reading and importing Era1 archives of pre-merge history.
+//!
+//! An Era1 file is an e2store file: a sequence of `type (u16) | length (u32) |
+//! reserved (u16) | data` entries, little-endian. It starts with a version entry,
+//! holds up to [`ERA1_BLOCKS`] consecutive blocks as (header, body, receipts, total
+//! difficulty) tuples with snappy framed compression, and ends with the accumulator
+//! root of the blocks and an index of their offsets.
+//!
+//! The accumulator is the SSZ root of the list of (block hash, total difficulty)
+//! records, the value stored per epoch in the pre-merge historical accumulator. A file
+//! is only imported if its accumulator is the trusted root of its epoch, given as
+//! [`EpochRoots`], so a rewritten file is rejected even if it is consistent.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, bail, ensure, Context};
+use ethereum_types::{H256, U256};
+use keccak_hash::keccak;
+use reec_core::{
+    rlp::{decode::RLPDecode, encode::RLPEncode},
+    types::{compute_receipts_root, Block, BlockBody, BlockHeader, BlockNumber, Receipt},
+};
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::{
+    canonical::index_transactions, logs::index_logs, read_chain_data_in, tables::*, Store,
+};
+
+const VERSION: u16 = 0x3265;
+const COMPRESSED_HEADER: u16 = 0x03;
+const COMPRESSED_BODY: u16 = 0x04;
+const COMPRESSED_RECEIPTS: u16 = 0x05;
+const TOTAL_DIFFICULTY: u16 = 0x06;
+const ACCUMULATOR: u16 = 0x07;
+const BLOCK_INDEX: u16 = 0x3266;
+
+/// Size of an e2store entry header.
+const ENTRY_HEADER_SIZE: u64 = 8;
+/// Blocks per Era1 file, and the limit of the accumulator list.
+pub const ERA1_BLOCKS: usize = 8192;
+/// Blocks imported per database transaction and freezer sync.
+const IMPORT_BATCH_SIZE: usize = 256;
+
+/// Trusted accumulator roots of the pre-merge epochs of [`ERA1_BLOCKS`] blocks, the
+/// root of epoch `n` at index `n`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EpochRoots(pub Vec<H256>);
+
+impl EpochRoots {
+    /// Reads one 0x-prefixed root per line, in epoch order. Empty lines and lines
+    /// starting with `#` are skipped.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .enumerate()
+            .map(|(epoch, line)| {
+                line.parse()
+                    .map_err(|_| anyhow!("invalid root of epoch {epoch}: {line}"))
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(EpochRoots)
+    }
+
+    pub fn get(&self, epoch: u64) -> Option<H256> {
+        self.0.get(usize::try_from(epoch).ok()?).copied()
+    }
+}
+
+/// Location of an entry's data within the file.
+#[derive(Clone, Copy, Debug)]
+struct EntryData {
+    offset: u64,
+    len: u32,
+}
+
+#[derive(Debug)]
+struct Era1Block {
+    header: BlockHeader,
+    hash: H256,
+    total_difficulty: U256,
+    body: EntryData,
+    receipts: EntryData,
+}
+
+/// An open Era1 file, with its headers read and its structure checked.
+#[derive(Debug)]
+pub struct Era1File {
+    file: BufReader<File>,
+    blocks: Vec<Era1Block>,
+    accumulator: H256,
+}
+
+impl Era1File {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut entries = Vec::new();
+        loop {
+            let mut header = [0; ENTRY_HEADER_SIZE as usize];
+            match file.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err.into()),
+            }
+            let kind = u16::from_le_bytes([header[0], header[1]]);
+            let len = u32::from_le_bytes(header[2..6].try_into()?);
+            ensure!(header[6..] == [0, 0], "invalid e2store entry header");
+            let offset = file.stream_position()?;
+            file.seek_relative(len as i64)?;
+            entries.push((kind, EntryData { offset, len }));
+        }
+
+        let mut era = Era1File {
+            file,
+            blocks: Vec::new(),
+            accumulator: H256::zero(),
+        };
+        let mut entries = entries.into_iter().peekable();
+        ensure!(
+            entries.next().is_some_and(|(kind, _)| kind == VERSION),
+            "not an Era1 file: missing version entry"
+        );
+        while entries
+            .peek()
+            .is_some_and(|(kind, _)| *kind == COMPRESSED_HEADER)
+        {
+            let mut next = |expected: u16| match entries.next() {
+                Some((kind, data)) if kind == expected => Ok(data),
+                _ => Err(anyhow!("incomplete block tuple in Era1 file")),
+            };
+            let (header, body, receipts, td) = (
+                next(COMPRESSED_HEADER)?,
+                next(COMPRESSED_BODY)?,
+                next(COMPRESSED_RECEIPTS)?,
+                next(TOTAL_DIFFICULTY)?,
+            );
+            let header = era.read_compressed(header)?;
+            let td = era.read(td)?;
+            ensure!(td.len() == 32, "invalid total difficulty entry");
+            era.blocks.push(Era1Block {
+                header: BlockHeader::decode(&header)?,
+                hash: keccak(&header),
+                total_difficulty: U256::from_little_endian(&td),
+                body,
+                receipts,
+            });
+        }
+        ensure!(!era.blocks.is_empty(), "Era1 file holds no blocks");
+        ensure!(
+            era.blocks.len() <= ERA1_BLOCKS,
+            "Era1 file holds too many blocks"
+        );
+
+        let mut index = None;
+        for (kind, data) in entries {
+            match kind {
+                ACCUMULATOR => era.accumulator = H256::from_slice(&era.read(data)?),
+                BLOCK_INDEX => index = Some(era.read(data)?),
+                _ => {}
+            }
+        }
+        let index = index.ok_or_else(|| anyhow!("Era1 file has no block index"))?;
+        ensure!(index.len() >= 16, "invalid Era1 block index");
+        let start = u64::from_le_bytes(index[..8].try_into()?);
+        let count = u64::from_le_bytes(index[index.len() - 8..].try_into()?);
+        ensure!(
+            start == era.start_block() && count == era.blocks.len() as u64,
+            "Era1 block index does not match the blocks in the file"
+        );
+        Ok(era)
+    }
+
+    /// Number of the first block in the file.
+    pub fn start_block(&self) -> BlockNumber {
+        self.blocks[0].header.number
+    }
+
+    /// Number of blocks in the file.
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn accumulator(&self) -> H256 {
+        self.accumulator
+    }
+
+    /// Epoch of the blocks in the file.
+    pub fn epoch(&self) -> u64 {
+        self.start_block() / ERA1_BLOCKS as u64
+    }
+
+    /// Checks that the file holds one epoch, that its headers form a chain with
+    /// consistent total difficulties and that they add up to the accumulator root
+    /// stored in the file, which must be the trusted root of the epoch.
+    pub fn verify(&self, trusted: &EpochRoots) -> anyhow::Result<()> {
+        let epoch = self.epoch();
+        ensure!(
+            self.start_block().is_multiple_of(ERA1_BLOCKS as u64),
+            "Era1 file starts at block {}, not at the start of an epoch",
+            self.start_block()
+        );
+        let Some(trusted) = trusted.get(epoch) else {
+            bail!("no trusted accumulator root for epoch {epoch}");
+        };
+        ensure!(
+            self.accumulator == trusted,
+            "accumulator of epoch {epoch} is {:#x}, but the trusted root is {trusted:#x}",
+            self.accumulator
+        );
+        for pair in self.blocks.windows(2) {
+            let (parent, block) = (&pair[0], &pair[1]);
+            ensure!(
+                block.header.number == parent.header.number + 1
+                    && block.header.parent_hash == parent.hash,
+                "block {} does not follow its parent in the Era1 file",
+                block.header.number
+            );
+            ensure!(
+                block.total_difficulty == parent.total_difficulty + block.header.difficulty,
+                "wrong total difficulty for block {}",
+                block.header.number
+            );
+        }
+        let computed =
+            compute_accumulator(self.blocks.iter().map(|b| (b.hash, b.total_difficulty)));
+        ensure!(
+            computed == self.accumulator,
+            "accumulator mismatch: file has {:#x}, blocks add up to {computed:#x}",
+            self.accumulator
+        );
+        Ok(())
+    }
+
+    /// Reads the `index`th block's body and receipts, checking them against its header.
+    fn read_block(&mut self, index: usize) -> anyhow::Result<(BlockBody, Vec<Receipt>)> {
+        let (body, receipts) = (self.blocks[index].body, self.blocks[index].receipts);
+        let body = BlockBody::decode(&self.read_compressed(body)?)?;
+        let receipts = self.read_compressed(receipts)?;
+        let header = &self.blocks[index].header;
+        let receipts = Vec::<Receipt>::decode(&receipts)
+            .with_context(|| format!("receipts of block {} in Era1 file", header.number))?;
+        let block = Block {
+            header: header.clone(),
+            body,
+        };
+        block
+            .validate_body()
+            .with_context(|| format!("block {} in Era1 file", header.number))?;
+        ensure!(
+            compute_receipts_root(&receipts) == header.receipts_root,
+            "receipts root mismatch for block {} in Era1 file",
+            header.number
+        );
+        Ok((block.body, receipts))
+    }
+
+    fn read(&mut self, data: EntryData) -> anyhow::Result<Vec<u8>> {
+        let mut buf = vec![0; data.len as usize];
+        self.file.seek(SeekFrom::Start(data.offset))?;
+        self.file.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn read_compressed(&mut self, data: EntryData) -> anyhow::Result<Vec<u8>> {
+        let compressed = self.read(data)?;
+        let mut decompressed = Vec::new();
+        snap::read::FrameDecoder::new(compressed.as_slice()).read_to_end(&mut decompressed)?;
+        Ok(decompressed)
+    }
+}
+
+/// SSZ root of the `List[HeaderRecord, ERA1_BLOCKS]` of (block hash, total
+/// difficulty) records.
+pub fn compute_accumulator(records: impl Iterator<Item = (H256, U256)>) -> H256 {
+    let mut layer: Vec<[u8; 32]> = records
+        .map(|(hash, total_difficulty)| {
+            let mut difficulty = [0; 32];
+            total_difficulty.to_little_endian(&mut difficulty);
+            sha256(&hash.0, &difficulty)
+        })
+        .collect();
+    let len = layer.len();
+    let mut zero = [0; 32];
+    for _ in 0..ERA1_BLOCKS.trailing_zeros() {
+        if layer.len() % 2 == 1 {
+            layer.push(zero);
+        }
+        layer = layer
+            .chunks(2)
+            .map(|pair| sha256(&pair[0], &pair[1]))
+            .collect();
+        zero = sha256(&zero, &zero);
+    }
+    let root = layer.first().copied().unwrap_or(zero);
+    let mut length = [0; 32];
+    length[..8].copy_from_slice(&(len as u64).to_le_bytes());
+    H256(sha256(&root, &length))
+}
+
+fn sha256(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+/// Writes consecutive `blocks` and their receipts as an Era1 file, returning its
+/// accumulator root. `total_difficulty` is that of the parent of the first block.
+pub fn write_era1(
+    path: &Path,
+    blocks: &[(Block, Vec<Receipt>)],
+    mut total_difficulty: U256,
+) -> anyhow::Result<H256> {
+    ensure!(
+        !blocks.is_empty() && blocks.len() <= ERA1_BLOCKS,
+        "an Era1 file holds 1 to {ERA1_BLOCKS} blocks"
+    );
+    let mut file = Vec::new();
+    write_entry(&mut file, VERSION, &[]);
+    let mut offsets = Vec::new();
+    let mut records = Vec::new();
+    for (block, receipts) in blocks {
+        offsets.push(file.len() as u64);
+        total_difficulty += block.header.difficulty;
+        let mut td = [0; 32];
+        total_difficulty.to_little_endian(&mut td);
+        write_entry(
+            &mut file,
+            COMPRESSED_HEADER,
+            &compress(&block.header.encode_to_vec())?,
+        );
+        write_entry(
+            &mut file,
+            COMPRESSED_BODY,
+            &compress(&block.body.encode_to_vec())?,
+        );
+        write_entry(
+            &mut file,
+            COMPRESSED_RECEIPTS,
+            &compress(&receipts.encode_to_vec())?,
+        );
+        write_entry(&mut file, TOTAL_DIFFICULTY, &td);
+        records.push((block.hash(), total_difficulty));
+    }
+    let accumulator = compute_accumulator(records.into_iter());
+    write_entry(&mut file, ACCUMULATOR, &accumulator.0);
+    // Offsets in the index are relative to the start of the index entry
+    let mut index = blocks[0].0.header.number.to_le_bytes().to_vec();
+    let index_start = file.len() as i64;
+    for offset in offsets {
+        index.extend_from_slice(&(offset as i64 - index_start).to_le_bytes());
+    }
+    index.extend_from_slice(&(blocks.len() as u64).to_le_bytes());
+    write_entry(&mut file, BLOCK_INDEX, &index);
+    std::fs::write(path, file)?;
+    Ok(accumulator)
+}
+
+fn write_entry(file: &mut Vec<u8>, kind: u16, data: &[u8]) {
+    file.extend_from_slice(&kind.to_le_bytes());
+    file.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    file.extend_from_slice(&[0, 0]);
+    file.extend_from_slice(data);
+}
+
+fn compress(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut encoder = snap::write::FrameEncoder::new(Vec::new());
+    encoder.write_all(data)?;
+    Ok(encoder.into_inner()?)
+}
+
+impl Store {
+    /// Imports the blocks of an Era1 file: headers into the database as canonical
+    /// blocks, bodies and receipts straight into the freezer. Returns how many blocks
+    /// were added.
+    ///
+    /// The file must verify against the trusted root of its epoch and continue both
+    /// the freezer and the canonical chain already stored. Files are imported in
+    /// order, and a file whose import was interrupted can be imported again.
+    pub fn import_era1(&self, path: &Path, trusted: &EpochRoots) -> anyhow::Result<u64> {
+        let mut era = Era1File::open(path)?;
+        era.verify(trusted)?;
+        let start = era.start_block();
+        let end = start + era.len() as u64;
+        let frozen = self.ancient_blocks();
+        if frozen >= end {
+            return Ok(0);
+        }
+        if frozen < start {
+            bail!(
+                "Era1 file starts at block {start}, but the freezer continues from block {frozen}"
+            );
+        }
+        if let Some(parent) = start.checked_sub(1) {
+            if let Some(expected) = self.get_canonical_block_hash(parent)? {
+                ensure!(
+                    era.blocks[0].header.parent_hash == expected,
+                    "Era1 file does not continue the stored chain at block {start}"
+                );
+            }
+        }
+        for block in &era.blocks {
+            if let Some(canonical) = self.get_canonical_block_hash(block.header.number)? {
+                ensure!(
+                    canonical == block.hash,
+                    "Era1 block {} conflicts with the canonical chain",
+                    block.header.number
+                );
+            }
+        }
+
+        let skip = (frozen - start) as usize;
+        for batch_start in (skip..era.len()).step_by(IMPORT_BATCH_SIZE) {
+            let batch_end = era.len().min(batch_start + IMPORT_BATCH_SIZE);
+            let blocks = (batch_start..batch_end)
+                .map(|index| era.read_block(index))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            // Headers go in first: a crash before the bodies are frozen leaves them
+            // canonical without a body, and the import is picked up again from the
+            // freezer head.
+            let tx = self.db.begin_readwrite()?;
+            for (index, (body, receipts)) in (batch_start..batch_end).zip(&blocks) {
+                let Era1Block { header, hash, .. } = &era.blocks[index];
+                tx.upsert::<Headers>(hash.0, header.clone().into())?;
+                tx.upsert::<HeaderNumbers>(hash.0, header.number)?;
+                tx.upsert::<CanonicalHashes>(header.number, hash.0)?;
+                tx.delete::<Bodies>(hash.0, None)?;
+                tx.delete::<Receipts>(hash.0, None)?;
+                index_transactions(&tx, header.number, body)?;
+                index_logs(&tx, header.number, receipts)?;
+            }
+            let last = start + batch_end as u64 - 1;
+            let latest = read_chain_data_in(&tx, ChainDataIndex::LatestBlockNumber)?;
+            if latest.is_none_or(|latest| latest < last) {
+                tx.upsert::<ChainData>(
+                    ChainDataIndex::LatestBlockNumber,
+                    last.to_be_bytes().to_vec(),
+                )?;
+            }
+            tx.commit()?;
+
+            let mut freezer = self.freezer.write().unwrap();
+            for (index, (body, receipts)) in (batch_start..batch_end).zip(&blocks) {
+                freezer.append_block(
+                    start + index as u64,
+                    &body.encode_to_vec(),
+                    &receipts.encode_to_vec(),
+                )?;
+            }
+            freezer.sync()?;
+        }
+        info!(
+            "Imported blocks {start} to {} from {}",
+            end - 1,
+            path.display()
+        );
+        Ok(end - frozen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use ethereum_types::Address;
+    use reec_core::types::{LegacyTransaction, Log, ReceiptOutcome, Transaction, TxType};
+
+    use super::*;
+    use crate::logs::LogFilter;
+
+    /// Builds the blocks `start..start + count` on top of `parent_hash`. With an
+    /// `outcome`, each has a transaction whose receipt has it and a log from the
+    /// address one above the block number; without, blocks are empty.
+    fn build_blocks(
+        start: BlockNumber,
+        count: u64,
+        mut parent_hash: H256,
+        outcome: Option<ReceiptOutcome>,
+    ) -> Vec<(Block, Vec<Receipt>)> {
+        (start..start + count)
+            .map(|number| {
+                let transactions = outcome.map(|_| {
+                    Transaction::LegacyTransaction(LegacyTransaction {
+                        nonce: number,
+                        data: Bytes::from(vec![0xab; 64]),
+                        ..Default::default()
+                    })
+                });
+                let body = BlockBody {
+                    transactions: transactions.into_iter().collect(),
+                    ..Default::default()
+                };
+                let log = Log {
+                    address: Address::from_low_u64_be(number + 1),
+                    topics: vec![H256::repeat_byte(0x77)],
+                    data: Bytes::new(),
+                };
+                let receipts = outcome
+                    .map(|outcome| {
+                        Receipt::with_outcome(TxType::Legacy, outcome, 21_000, vec![log])
+                    })
+                    .into_iter()
+                    .collect::<Vec<_>>();
+                let header = BlockHeader {
+                    parent_hash,
+                    number,
+                    difficulty: U256::from(17),
+                    ommers_hash: body.compute_ommers_hash(),
+                    transactions_root: body.compute_transactions_root(),
+                    receipts_root: compute_receipts_root(&receipts),
+                    ..Default::default()
+                };
+                parent_hash = header.compute_block_hash();
+                (Block { header, body }, receipts)
+            })
+            .collect()
+    }
+
+    /// Writes `blocks` as an Era1 file, the first block's parent having the same
+    /// difficulty as every block.
+    fn write_blocks(path: &Path, blocks: &[(Block, Vec<Receipt>)]) -> H256 {
+        let total_difficulty = U256::from(17) * blocks[0].0.header.number;
+        write_era1(path, blocks, total_difficulty).unwrap()
+    }
+
+    #[test]
+    fn accumulator_of_empty_list() {
+        let mut zero = [0; 32];
+        for _ in 0..13 {
+            zero = sha256(&zero, &zero);
+        }
+        assert_eq!(
+            compute_accumulator(std::iter::empty()),
+            H256(sha256(&zero, &[0; 32]))
+        );
+    }
+
+    #[test]
+    fn imports_era1_files_into_freezer() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let status = Some(ReceiptOutcome::Status(true));
+        let first = build_blocks(0, ERA1_BLOCKS as u64, H256::zero(), None);
+        let last = first.last().unwrap().0.hash();
+        let second = build_blocks(ERA1_BLOCKS as u64, 3, last, status);
+        let (first_path, second_path) = (
+            dir.path().join("first.era1"),
+            dir.path().join("second.era1"),
+        );
+        let trusted = EpochRoots(vec![
+            write_blocks(&first_path, &first),
+            write_blocks(&second_path, &second),
+        ]);
+
+        // Out of order
+        assert!(store.import_era1(&second_path, &trusted).is_err());
+        assert_eq!(
+            store.import_era1(&first_path, &trusted).unwrap(),
+            ERA1_BLOCKS as u64
+        );
+        assert_eq!(store.import_era1(&first_path, &trusted).unwrap(), 0);
+        assert_eq!(store.import_era1(&second_path, &trusted).unwrap(), 3);
+
+        let end = ERA1_BLOCKS as u64 + 3;
+        assert_eq!(store.ancient_blocks(), end);
+        assert_eq!(store.get_latest_block_number().unwrap(), Some(end - 1));
+        for (block, receipts) in first.iter().step_by(1000).chain(&second) {
+            let number = block.header.number;
+            assert_eq!(
+                store.get_canonical_block_hash(number).unwrap(),
+                Some(block.hash())
+            );
+            assert_eq!(
+                store.get_block_body(number).unwrap().as_ref(),
+                Some(&block.body)
+            );
+            assert_eq!(store.get_receipts(number).unwrap().as_ref(), Some(receipts));
+        }
+        for (block, _) in &second {
+            let number = block.header.number;
+            let tx_hash = block.body.transactions[0].compute_hash();
+            assert_eq!(
+                store.get_transaction_location(tx_hash).unwrap(),
+                Some((number, 0))
+            );
+        }
+    }
+
+    #[test]
+    fn imports_receipts_with_state_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let path = dir.path().join("frontier.era1");
+        let blocks = build_blocks(
+            0,
+            4,
+            H256::zero(),
+            Some(ReceiptOutcome::StateRoot(H256::repeat_byte(0x5a))),
+        );
+        let trusted = EpochRoots(vec![write_blocks(&path, &blocks)]);
+        assert_eq!(store.import_era1(&path, &trusted).unwrap(), 4);
+
+        for (block, receipts) in &blocks {
+            let hash = block.hash();
+            assert_eq!(
+                store.get_receipts_by_hash(hash).unwrap().as_ref(),
+                Some(receipts)
+            );
+        }
+        let filter = LogFilter {
+            from_block: 0,
+            to_block: 3,
+            addresses: vec![Address::from_low_u64_be(3)],
+            topics: vec![vec![H256::repeat_byte(0x77)]],
+        };
+        assert_eq!(store.get_log_blocks(&filter).unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn rejects_files_not_matching_trusted_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let path = dir.path().join("epoch.era1");
+        let mut blocks = build_blocks(0, 4, H256::zero(), Some(ReceiptOutcome::Status(true)));
+        let trusted = EpochRoots(vec![write_blocks(&path, &blocks)]);
+
+        let err = store
+            .import_era1(&path, &EpochRoots::default())
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("no trusted accumulator root for epoch 0"),
+            "{err}"
+        );
+
+        // A consistent file with other blocks than those of the epoch
+        blocks[3].0.header.extra_data = Bytes::from_static(b"rewritten");
+        write_blocks(&path, &blocks);
+        let err = store.import_era1(&path, &trusted).unwrap_err();
+        assert!(err.to_string().contains("trusted root"), "{err}");
+
+        // The trusted root stored with blocks that do not add up to it
+        let file = std::fs::read(&path).unwrap();
+        let accumulator = Era1File::open(&path).unwrap().accumulator();
+        let at = file
+            .windows(32)
+            .position(|window| window == accumulator.as_bytes())
+            .unwrap();
+        let mut file = file;
+        file[at..at + 32].copy_from_slice(trusted.0[0].as_bytes());
+        std::fs::write(&path, file).unwrap();
+        let err = store.import_era1(&path, &trusted).unwrap_err();
+        assert!(err.to_string().contains("accumulator mismatch"), "{err}");
+        assert_eq!(store.ancient_blocks(), 0);
+    }
+
+    #[test]
+    fn rejects_bodies_not_matching_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let path = dir.path().join("bad.era1");
+        let mut blocks = build_blocks(0, 4, H256::zero(), Some(ReceiptOutcome::Status(true)));
+        blocks[2].1[0].cumulative_gas_used += 1;
+        let trusted = EpochRoots(vec![write_blocks(&path, &blocks)]);
+        let err = store.import_era1(&path, &trusted).unwrap_err();
+        assert!(err.to_string().contains("receipts root mismatch"), "{err}");
+    }
+
+    #[test]
+    fn reads_epoch_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("roots.txt");
+        let roots = format!(
+            "# epoch roots\n{:#x}\n\n{:#x}\n",
+            H256::repeat_byte(1),
+            H256::repeat_byte(2)
+        );
+        std::fs::write(&path, roots).unwrap();
+        let roots = EpochRoots::from_file(&path).unwrap();
+        assert_eq!(roots.get(1), Some(H256::repeat_byte(2)));
+        assert_eq!(roots.get(2), None);
+        std::fs::write(&path, "0x1234\n").unwrap();
+        assert!(EpochRoots::from_file(&path).is_err());
+    }
+}
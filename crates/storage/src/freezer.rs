@@ -0,0 +1,147 @@
+mod table;
+
+use std::path::Path;
+
+use anyhow::bail;
+use reec_core::types::BlockNumber;
+use tracing::warn;
+
+pub use table::FreezerTable;
+
+pub const BODIES_TABLE: &str = "bodies";
+pub const RECEIPTS_TABLE: &str = "receipts";
+
+/// How far behind the finalized block a block must be before it is frozen.
+pub const FREEZER_THRESHOLD: u64 = 90_000;
+
+/// Default size at which a data file is closed and a new one started.
+const DEFAULT_MAX_FILE_SIZE: u32 = 2 * 1024 * 1024 * 1024;
+
+/// Append-only flat-file store for finalized block data that no longer needs to live
+/// in MDBX.
+///
+/// Every table holds one item per block, indexed by block number, and all tables are
+/// kept at the same head and tail.
+#[derive(Debug)]
+pub struct Freezer {
+    bodies: FreezerTable,
+    receipts: FreezerTable,
+}
+
+impl Freezer {
+    pub fn open(dir: &Path) -> anyhow::Result<Self> {
+        Self::open_with_max_file_size(dir, DEFAULT_MAX_FILE_SIZE)
+    }
+
+    pub fn open_with_max_file_size(dir: &Path, max_file_size: u32) -> anyhow::Result<Self> {
+        let mut freezer = Freezer {
+            bodies: FreezerTable::open(dir, BODIES_TABLE, max_file_size)?,
+            receipts: FreezerTable::open(dir, RECEIPTS_TABLE, max_file_size)?,
+        };
+        freezer.align_tables()?;
+        Ok(freezer)
+    }
+
+    /// Number of the first block still held in the freezer.
+    pub fn tail(&self) -> BlockNumber {
+        self.bodies.tail()
+    }
+
+    /// Number of the next block to be frozen.
+    pub fn frozen(&self) -> BlockNumber {
+        self.bodies.items()
+    }
+
+    pub fn contains(&self, number: BlockNumber) -> bool {
+        number >= self.tail() && number < self.frozen()
+    }
+
+    pub fn tables(&self) -> [&FreezerTable; 2] {
+        [&self.bodies, &self.receipts]
+    }
+
+    /// Appends the RLP-encoded body and receipts of the next block.
+    pub fn append_block(
+        &mut self,
+        number: BlockNumber,
+        body: &[u8],
+        receipts: &[u8],
+    ) -> anyhow::Result<()> {
+        if number != self.frozen() {
+            bail!("freezing block {number}, expected {}", self.frozen());
+        }
+        self.bodies.append(number, body)?;
+        self.receipts.append(number, receipts)?;
+        Ok(())
+    }
+
+    pub fn body(&self, number: BlockNumber) -> anyhow::Result<Option<Vec<u8>>> {
+        self.bodies.retrieve(number)
+    }
+
+    pub fn receipts(&self, number: BlockNumber) -> anyhow::Result<Option<Vec<u8>>> {
+        self.receipts.retrieve(number)
+    }
+
+    pub fn sync(&self) -> anyhow::Result<()> {
+        for table in self.tables() {
+            table.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Checks every stored item against its checksum.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        for table in self.tables() {
+            table.verify()?;
+        }
+        Ok(())
+    }
+
+    /// Drops every block from `number` onwards.
+    pub fn truncate_head(&mut self, number: BlockNumber) -> anyhow::Result<()> {
+        self.bodies.truncate_head(number)?;
+        self.receipts.truncate_head(number)
+    }
+
+    /// Drops every block below `number`. If that is past the head, the next block to
+    /// be frozen becomes `number`.
+    pub fn truncate_tail(&mut self, number: BlockNumber) -> anyhow::Result<()> {
+        self.bodies.truncate_tail(number)?;
+        self.receipts.truncate_tail(number)
+    }
+
+    /// A crash between appends to different tables can leave them at different
+    /// heads (or tails); cut them all back to the range they have in common.
+    fn align_tables(&mut self) -> anyhow::Result<()> {
+        let head = self.bodies.items().min(self.receipts.items());
+        let tail = self.bodies.tail().max(self.receipts.tail());
+        if self.bodies.items() != self.receipts.items() {
+            warn!("Freezer tables out of sync, truncating to block {head}");
+        }
+        self.truncate_head(head)?;
+        self.truncate_tail(tail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligns_tables_on_open() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let mut freezer = Freezer::open(dir.path()).unwrap();
+            for n in 0..3 {
+                freezer.append_block(n, &[n as u8; 8], &[0xc0]).unwrap();
+            }
+            // Crash after writing the body of block 3 but before its receipts
+            freezer.bodies.append(3, &[3; 8]).unwrap();
+        }
+        let freezer = Freezer::open(dir.path()).unwrap();
+        assert_eq!(freezer.frozen(), 3);
+        assert_eq!(freezer.body(3).unwrap(), None);
+        assert_eq!(freezer.body(2).unwrap(), Some(vec![2; 8]));
+    }
+}
@@ -0,0 +1,541 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context};
+use tracing::warn;
+
+const INDEX_MAGIC: [u8; 4] = *b"RCFZ";
+const INDEX_VERSION: u32 = 1;
+/// Magic, version and the number of the first item kept in the table.
+const INDEX_HEADER_SIZE: u64 = 16;
+/// Data file number, end offset within that file and crc32 of the stored bytes.
+const INDEX_ENTRY_SIZE: u64 = 12;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct IndexEntry {
+    file: u32,
+    offset: u32,
+    checksum: u32,
+}
+
+impl IndexEntry {
+    fn to_bytes(self) -> [u8; INDEX_ENTRY_SIZE as usize] {
+        let mut bytes = [0; INDEX_ENTRY_SIZE as usize];
+        bytes[0..4].copy_from_slice(&self.file.to_be_bytes());
+        bytes[4..8].copy_from_slice(&self.offset.to_be_bytes());
+        bytes[8..12].copy_from_slice(&self.checksum.to_be_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        IndexEntry {
+            file: u32::from_be_bytes(bytes[0..4].try_into().unwrap()),
+            offset: u32::from_be_bytes(bytes[4..8].try_into().unwrap()),
+            checksum: u32::from_be_bytes(bytes[8..12].try_into().unwrap()),
+        }
+    }
+}
+
+/// A single append-only table of snappy-compressed items split across numbered data
+/// files.
+///
+/// The index file starts with a header followed by a sentinel entry marking where the
+/// first item starts; every following entry marks where an item ends. Items are only
+/// ever appended at the head or dropped from the tail.
+#[derive(Debug)]
+pub struct FreezerTable {
+    name: String,
+    dir: PathBuf,
+    index: File,
+    data_files: BTreeMap<u32, File>,
+    head_id: u32,
+    head_bytes: u32,
+    /// Number of the first item still stored.
+    tail: u64,
+    /// Number of the next item to be appended.
+    items: u64,
+    max_file_size: u32,
+}
+
+impl FreezerTable {
+    /// Opens (or creates) the table, repairing any torn write left by a crash.
+    pub fn open(dir: &Path, name: &str, max_file_size: u32) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let index_path = dir.join(format!("{name}.cidx"));
+        let mut index = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&index_path)
+            .with_context(|| format!("failed to open freezer index {}", index_path.display()))?;
+
+        if index.metadata()?.len() == 0 {
+            let mut header = Vec::with_capacity((INDEX_HEADER_SIZE + INDEX_ENTRY_SIZE) as usize);
+            header.extend_from_slice(&INDEX_MAGIC);
+            header.extend_from_slice(&INDEX_VERSION.to_be_bytes());
+            header.extend_from_slice(&0u64.to_be_bytes());
+            header.extend_from_slice(&IndexEntry::default().to_bytes());
+            index.write_all(&header)?;
+            index.sync_all()?;
+        }
+
+        let mut header = [0; INDEX_HEADER_SIZE as usize];
+        index.read_exact_at(&mut header, 0)?;
+        if header[0..4] != INDEX_MAGIC {
+            bail!(
+                "freezer index {} is not a freezer index",
+                index_path.display()
+            );
+        }
+        let version = u32::from_be_bytes(header[4..8].try_into().unwrap());
+        if version != INDEX_VERSION {
+            bail!(
+                "freezer index {} has version {version}, expected {INDEX_VERSION}",
+                index_path.display()
+            );
+        }
+        let tail = u64::from_be_bytes(header[8..16].try_into().unwrap());
+
+        let mut table = FreezerTable {
+            name: name.to_string(),
+            dir: dir.to_path_buf(),
+            index,
+            data_files: BTreeMap::new(),
+            head_id: 0,
+            head_bytes: 0,
+            tail,
+            items: tail,
+            max_file_size,
+        };
+        table.repair()?;
+        Ok(table)
+    }
+
+    /// Number of the first item still stored.
+    pub fn tail(&self) -> u64 {
+        self.tail
+    }
+
+    /// Number of the next item to be appended.
+    pub fn items(&self) -> u64 {
+        self.items
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Total size of the data files and the index, in bytes.
+    pub fn size(&self) -> anyhow::Result<u64> {
+        let mut size = self.index.metadata()?.len();
+        for file in self.data_files.values() {
+            size += file.metadata()?.len();
+        }
+        Ok(size)
+    }
+
+    pub fn append(&mut self, item: u64, data: &[u8]) -> anyhow::Result<()> {
+        if item != self.items {
+            bail!(
+                "freezer table {} appending item {item}, expected {}",
+                self.name,
+                self.items
+            );
+        }
+        let compressed = snap::raw::Encoder::new().compress_vec(data)?;
+        let len = u32::try_from(compressed.len())
+            .ok()
+            .filter(|len| *len <= self.max_file_size)
+            .ok_or_else(|| anyhow!("freezer item {item} is too large"))?;
+
+        if self.head_bytes > 0 && self.head_bytes as u64 + len as u64 > self.max_file_size as u64 {
+            self.head_id += 1;
+            self.head_bytes = 0;
+            let file = self.open_data_file(self.head_id, true)?;
+            self.data_files.insert(self.head_id, file);
+        }
+        let head = &self.data_files[&self.head_id];
+        head.write_all_at(&compressed, self.head_bytes as u64)?;
+        self.head_bytes += len;
+
+        let entry = IndexEntry {
+            file: self.head_id,
+            offset: self.head_bytes,
+            checksum: crc32fast::hash(&compressed),
+        };
+        let position = self.entry_position(self.items + 1);
+        self.index.write_all_at(&entry.to_bytes(), position)?;
+        self.items += 1;
+        Ok(())
+    }
+
+    /// Returns the decompressed item, or `None` if it is outside the stored range.
+    pub fn retrieve(&self, item: u64) -> anyhow::Result<Option<Vec<u8>>> {
+        if item < self.tail || item >= self.items {
+            return Ok(None);
+        }
+        let compressed = self.read_raw(item)?;
+        let data = snap::raw::Decoder::new()
+            .decompress_vec(&compressed)
+            .with_context(|| format!("freezer table {} item {item} is corrupted", self.name))?;
+        Ok(Some(data))
+    }
+
+    /// Flushes data files and index to disk.
+    pub fn sync(&self) -> anyhow::Result<()> {
+        if let Some(head) = self.data_files.get(&self.head_id) {
+            head.sync_data()?;
+        }
+        self.index.sync_data()?;
+        Ok(())
+    }
+
+    /// Checks the checksum of every stored item.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        for item in self.tail..self.items {
+            self.read_raw(item)?;
+        }
+        Ok(())
+    }
+
+    /// Drops every item from `items` onwards.
+    pub fn truncate_head(&mut self, items: u64) -> anyhow::Result<()> {
+        if items >= self.items {
+            return Ok(());
+        }
+        let items = items.max(self.tail);
+        let last = self.read_entry(items)?;
+        self.index.set_len(self.entry_position(items + 1))?;
+        for (id, _) in self.data_files.split_off(&(last.file + 1)) {
+            fs::remove_file(self.data_file_path(id))?;
+        }
+        self.data_files[&last.file].set_len(last.offset as u64)?;
+        self.head_id = last.file;
+        self.head_bytes = last.offset;
+        self.items = items;
+        self.sync()
+    }
+
+    /// Drops every item below `tail`, deleting data files that no longer hold any
+    /// stored item. The index is rewritten atomically.
+    ///
+    /// A `tail` past the head empties the table and moves its head along, so the next
+    /// item appended is `tail`.
+    pub fn truncate_tail(&mut self, tail: u64) -> anyhow::Result<()> {
+        if tail <= self.tail {
+            return Ok(());
+        }
+        let first_kept = tail.min(self.items);
+        let mut entries =
+            Vec::with_capacity(((self.items - first_kept + 2) * INDEX_ENTRY_SIZE) as usize);
+        let mut buf = vec![0; ((self.items - first_kept + 1) * INDEX_ENTRY_SIZE) as usize];
+        self.index
+            .read_exact_at(&mut buf, self.entry_position(first_kept))?;
+        entries.extend_from_slice(&INDEX_MAGIC);
+        entries.extend_from_slice(&INDEX_VERSION.to_be_bytes());
+        entries.extend_from_slice(&tail.to_be_bytes());
+        entries.extend_from_slice(&buf);
+
+        let index_path = self.dir.join(format!("{}.cidx", self.name));
+        let tmp_path = self.dir.join(format!("{}.cidx.tmp", self.name));
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(&entries)?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, &index_path)?;
+        self.index = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&index_path)?;
+
+        let first = IndexEntry::from_bytes(&buf[..INDEX_ENTRY_SIZE as usize]);
+        let remaining = self.data_files.split_off(&first.file);
+        for id in self.data_files.keys() {
+            fs::remove_file(self.data_file_path(*id))?;
+        }
+        self.data_files = remaining;
+        self.tail = tail;
+        self.items = self.items.max(tail);
+        Ok(())
+    }
+
+    fn read_raw(&self, item: u64) -> anyhow::Result<Vec<u8>> {
+        let start = self.read_entry(item)?;
+        let end = self.read_entry(item + 1)?;
+        let start_offset = if start.file == end.file {
+            start.offset
+        } else {
+            0
+        };
+        let file = self.data_files.get(&end.file).ok_or_else(|| {
+            anyhow!(
+                "freezer table {} is missing data file {}",
+                self.name,
+                end.file
+            )
+        })?;
+        let mut data = vec![0; end.offset.saturating_sub(start_offset) as usize];
+        file.read_exact_at(&mut data, start_offset as u64)?;
+        if crc32fast::hash(&data) != end.checksum {
+            bail!(
+                "freezer table {} item {item} failed its checksum",
+                self.name
+            );
+        }
+        Ok(data)
+    }
+
+    /// Reads the index entry marking the start of `item`, which is the end of the
+    /// previous one.
+    fn read_entry(&self, item: u64) -> anyhow::Result<IndexEntry> {
+        let mut buf = [0; INDEX_ENTRY_SIZE as usize];
+        self.index
+            .read_exact_at(&mut buf, self.entry_position(item))?;
+        Ok(IndexEntry::from_bytes(&buf))
+    }
+
+    fn entry_position(&self, item: u64) -> u64 {
+        INDEX_HEADER_SIZE + (item - self.tail) * INDEX_ENTRY_SIZE
+    }
+
+    fn data_file_path(&self, id: u32) -> PathBuf {
+        self.dir.join(format!("{}.{id:04}.cdat", self.name))
+    }
+
+    fn open_data_file(&self, id: u32, create: bool) -> anyhow::Result<File> {
+        let path = self.data_file_path(id);
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(create)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("failed to open freezer data file {}", path.display()))
+    }
+
+    /// Brings the index and data files back in line after an unclean shutdown: drops
+    /// partial index entries, entries pointing past the end of their data file or
+    /// failing their checksum, and data written after the last indexed item.
+    fn repair(&mut self) -> anyhow::Result<()> {
+        let index_len = self.index.metadata()?.len();
+        if index_len < INDEX_HEADER_SIZE + INDEX_ENTRY_SIZE {
+            bail!("freezer index {} is truncated", self.name);
+        }
+        let excess = (index_len - INDEX_HEADER_SIZE) % INDEX_ENTRY_SIZE;
+        if excess != 0 {
+            warn!("Truncating partial entry from freezer index {}", self.name);
+            self.index.set_len(index_len - excess)?;
+        }
+        let entries = (index_len - excess - INDEX_HEADER_SIZE) / INDEX_ENTRY_SIZE;
+        self.items = self.tail + entries - 1;
+
+        let first = self.read_entry(self.tail)?;
+        loop {
+            let last = self.read_entry(self.items)?;
+            let file_len = fs::metadata(self.data_file_path(last.file))
+                .map(|m| m.len())
+                .ok();
+            if self.items == self.tail {
+                self.head_id = last.file;
+                self.head_bytes = last.offset;
+                if file_len.is_none() {
+                    let file = self.open_data_file(last.file, true)?;
+                    file.set_len(last.offset as u64)?;
+                }
+                break;
+            }
+            let intact = match file_len {
+                Some(len) if len >= last.offset as u64 => {
+                    self.data_files
+                        .insert(last.file, self.open_data_file(last.file, false)?);
+                    self.read_raw(self.items - 1).is_ok()
+                }
+                _ => false,
+            };
+            if intact {
+                self.head_id = last.file;
+                self.head_bytes = last.offset;
+                break;
+            }
+            warn!(
+                "Dropping torn item {} from freezer table {}",
+                self.items - 1,
+                self.name
+            );
+            self.items -= 1;
+            self.index.set_len(self.entry_position(self.items + 1))?;
+        }
+
+        self.data_files.clear();
+        for id in first.file..=self.head_id {
+            let path = self.data_file_path(id);
+            if !path.exists() && id != self.head_id {
+                continue;
+            }
+            self.data_files.insert(id, self.open_data_file(id, true)?);
+        }
+        let head = &self.data_files[&self.head_id];
+        if head.metadata()?.len() > self.head_bytes as u64 {
+            warn!("Truncating unindexed data from freezer table {}", self.name);
+            head.set_len(self.head_bytes as u64)?;
+        }
+        let mut stale = self.head_id + 1;
+        while self.data_file_path(stale).exists() {
+            fs::remove_file(self.data_file_path(stale))?;
+            stale += 1;
+        }
+        self.sync()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+
+    use super::*;
+
+    fn item(n: u64) -> Vec<u8> {
+        vec![n as u8; 10 + n as usize]
+    }
+
+    #[test]
+    fn append_and_retrieve_across_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut table = FreezerTable::open(dir.path(), "test", 64).unwrap();
+        for n in 0..20 {
+            table.append(n, &item(n)).unwrap();
+        }
+        assert!(table.data_files.len() > 1);
+        for n in 0..20 {
+            assert_eq!(table.retrieve(n).unwrap(), Some(item(n)));
+        }
+        assert_eq!(table.retrieve(20).unwrap(), None);
+        assert!(table.append(30, &item(30)).is_err());
+        table.verify().unwrap();
+    }
+
+    #[test]
+    fn reopen_keeps_items() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let mut table = FreezerTable::open(dir.path(), "test", 64).unwrap();
+            for n in 0..10 {
+                table.append(n, &item(n)).unwrap();
+            }
+            table.sync().unwrap();
+        }
+        let table = FreezerTable::open(dir.path(), "test", 64).unwrap();
+        assert_eq!(table.items(), 10);
+        assert_eq!(table.retrieve(9).unwrap(), Some(item(9)));
+    }
+
+    #[test]
+    fn repairs_torn_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let mut table = FreezerTable::open(dir.path(), "test", 1024).unwrap();
+            for n in 0..5 {
+                table.append(n, &item(n)).unwrap();
+            }
+        }
+        // Half-written index entry
+        let index_path = dir.path().join("test.cidx");
+        let mut index = OpenOptions::new().append(true).open(&index_path).unwrap();
+        index.write_all(&[0, 0, 0]).unwrap();
+        // Data appended for an item that never got indexed
+        let data_path = dir.path().join("test.0000.cdat");
+        let mut data = OpenOptions::new().append(true).open(&data_path).unwrap();
+        data.write_all(&[0xff; 7]).unwrap();
+        let data_len = fs::metadata(&data_path).unwrap().len();
+
+        let table = FreezerTable::open(dir.path(), "test", 1024).unwrap();
+        assert_eq!(table.items(), 5);
+        assert_eq!(fs::metadata(&data_path).unwrap().len(), data_len - 7);
+        assert_eq!(table.retrieve(4).unwrap(), Some(item(4)));
+    }
+
+    #[test]
+    fn drops_items_missing_data() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let mut table = FreezerTable::open(dir.path(), "test", 1024).unwrap();
+            for n in 0..5 {
+                table.append(n, &item(n)).unwrap();
+            }
+        }
+        let data_path = dir.path().join("test.0000.cdat");
+        let len = fs::metadata(&data_path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&data_path)
+            .unwrap()
+            .set_len(len - 1)
+            .unwrap();
+
+        let table = FreezerTable::open(dir.path(), "test", 1024).unwrap();
+        assert_eq!(table.items(), 4);
+        assert_eq!(table.retrieve(3).unwrap(), Some(item(3)));
+    }
+
+    #[test]
+    fn detects_corruption() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut table = FreezerTable::open(dir.path(), "test", 1024).unwrap();
+        for n in 0..5 {
+            table.append(n, &item(n)).unwrap();
+        }
+        table.sync().unwrap();
+        let data_path = dir.path().join("test.0000.cdat");
+        let file = OpenOptions::new().write(true).open(&data_path).unwrap();
+        file.write_all_at(&[0xee], 0).unwrap();
+        assert!(table.retrieve(0).is_err());
+        assert!(table.verify().is_err());
+    }
+
+    #[test]
+    fn truncates_tail_and_head() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut table = FreezerTable::open(dir.path(), "test", 64).unwrap();
+        for n in 0..20 {
+            table.append(n, &item(n)).unwrap();
+        }
+        table.truncate_tail(12).unwrap();
+        assert_eq!(table.tail(), 12);
+        assert_eq!(table.retrieve(11).unwrap(), None);
+        assert_eq!(table.retrieve(12).unwrap(), Some(item(12)));
+        assert!(!dir.path().join("test.0000.cdat").exists());
+
+        table.truncate_head(15).unwrap();
+        assert_eq!(table.items(), 15);
+        assert_eq!(table.retrieve(15).unwrap(), None);
+        table.append(15, &item(15)).unwrap();
+        drop(table);
+
+        let table = FreezerTable::open(dir.path(), "test", 64).unwrap();
+        assert_eq!((table.tail(), table.items()), (12, 16));
+        assert_eq!(table.retrieve(15).unwrap(), Some(item(15)));
+    }
+
+    #[test]
+    fn truncates_tail_past_head() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut table = FreezerTable::open(dir.path(), "test", 64).unwrap();
+        for n in 0..5 {
+            table.append(n, &item(n)).unwrap();
+        }
+        table.truncate_tail(9).unwrap();
+        assert_eq!((table.tail(), table.items()), (9, 9));
+        assert!(table.append(5, &item(5)).is_err());
+        table.append(9, &item(9)).unwrap();
+        drop(table);
+
+        let table = FreezerTable::open(dir.path(), "test", 64).unwrap();
+        assert_eq!((table.tail(), table.items()), (9, 10));
+        assert_eq!(table.retrieve(9).unwrap(), Some(item(9)));
+    }
+}
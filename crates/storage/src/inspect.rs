@@ -0,0 +1,646 @@
+//! Inspection and repair of the database for the `reec db` commands: table statistics,
+//! decoded dumps of single entries, consistency checks and dropping whole tables.
+
+use std::fmt::Debug;
+
+use anyhow::bail;
+use ethereum_types::H256;
+use keccak_hash::keccak;
+use libmdbx::orm::{Decodable, Encodable, Table, Transaction, TransactionKind};
+use reec_core::{
+    rlp::{
+        decode::{decode_bytes, decode_rlp_item, get_item_with_prefix, RLPDecode},
+        error::RLPDecodeError,
+    },
+    trie::EMPTY_TRIE_ROOT,
+    types::{BlockNumber, EMPTY_CODE_HASH},
+};
+
+use crate::{
+    read_chain_data_in,
+    rlp::Rlp,
+    tables::*,
+    trie::{decode_node, location, Node, NodeRef},
+    Store,
+};
+
+/// Names of every MDBX table, as accepted by [`Store::get_entries`] and
+/// [`Store::drop_table`].
+pub const TABLES: [&str; 16] = [
+    "Headers",
+    "Bodies",
+    "Receipts",
+    "CanonicalHashes",
+    "HeaderNumbers",
+    "TransactionLocations",
+    "LogAddressBlocks",
+    "LogTopicBlocks",
+    "ChainData",
+    "AccountInfos",
+    "AccountStorages",
+    "AccountCodes",
+    "TrieNodes",
+    "AccountChangeSets",
+    "StorageChangeSets",
+    "TrieChangeSets",
+];
+
+/// Runs `$body` with `$table` standing for the table type called `$name`.
+macro_rules! with_table {
+    ($name:expr, $table:ident => $body:expr) => {
+        match $name {
+            "Headers" => {
+                type $table = Headers;
+                $body
+            }
+            "Bodies" => {
+                type $table = Bodies;
+                $body
+            }
+            "Receipts" => {
+                type $table = Receipts;
+                $body
+            }
+            "CanonicalHashes" => {
+                type $table = CanonicalHashes;
+                $body
+            }
+            "HeaderNumbers" => {
+                type $table = HeaderNumbers;
+                $body
+            }
+            "TransactionLocations" => {
+                type $table = TransactionLocations;
+                $body
+            }
+            "LogAddressBlocks" => {
+                type $table = LogAddressBlocks;
+                $body
+            }
+            "LogTopicBlocks" => {
+                type $table = LogTopicBlocks;
+                $body
+            }
+            "ChainData" => {
+                type $table = ChainData;
+                $body
+            }
+            "AccountInfos" => {
+                type $table = AccountInfos;
+                $body
+            }
+            "AccountStorages" => {
+                type $table = AccountStorages;
+                $body
+            }
+            "AccountCodes" => {
+                type $table = AccountCodes;
+                $body
+            }
+            "TrieNodes" => {
+                type $table = TrieNodes;
+                $body
+            }
+            "AccountChangeSets" => {
+                type $table = AccountChangeSets;
+                $body
+            }
+            "StorageChangeSets" => {
+                type $table = StorageChangeSets;
+                $body
+            }
+            "TrieChangeSets" => {
+                type $table = TrieChangeSets;
+                $body
+            }
+            name => bail!(
+                "unknown table {name}, expected one of {}",
+                TABLES.join(", ")
+            ),
+        }
+    };
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableStats {
+    /// MDBX table name, or `ancient/<table>` for freezer tables.
+    pub name: String,
+    pub entries: u64,
+    /// Bytes used on disk.
+    pub size: u64,
+}
+
+/// Outcome of [`Store::check`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CheckReport {
+    /// Canonical blocks walked, from the first canonical hash to the latest block.
+    pub canonical_blocks: u64,
+    /// Stored trie nodes reachable from the head state root.
+    pub trie_nodes: u64,
+    /// Stored trie nodes that are not, left behind by an interrupted write. They are
+    /// harmless but take space.
+    pub unreachable_trie_nodes: u64,
+    pub problems: Vec<String>,
+}
+
+impl CheckReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl Store {
+    /// Entry counts and sizes of every MDBX table followed by the freezer tables.
+    pub fn table_stats(&self) -> anyhow::Result<Vec<TableStats>> {
+        let tx = self.db.begin_read()?;
+        let mut stats = Vec::new();
+        for name in TABLES {
+            let stat = with_table!(name, T => tx.table_stat::<T>()?);
+            stats.push(TableStats {
+                name: name.to_string(),
+                entries: stat.entries() as u64,
+                size: stat.total_size(),
+            });
+        }
+        let freezer = self.freezer.read().unwrap();
+        for table in freezer.tables() {
+            stats.push(TableStats {
+                name: format!("ancient/{}", table.name()),
+                entries: table.items() - table.tail(),
+                size: table.size()?,
+            });
+        }
+        Ok(stats)
+    }
+
+    /// Decoded values stored under the encoded `key` of `table`: at most one, except in
+    /// dupsort tables.
+    pub fn get_entries(&self, table: &str, key: &[u8]) -> anyhow::Result<Vec<String>> {
+        let tx = self.db.begin_read()?;
+        with_table!(table, T => entries_of::<_, T>(&tx, key))
+    }
+
+    /// Removes every entry of `table`, returning how many there were.
+    pub fn drop_table(&self, table: &str) -> anyhow::Result<u64> {
+        let tx = self.db.begin_readwrite()?;
+        let entries = with_table!(table, T => {
+            let entries = tx.table_stat::<T>()?.entries();
+            tx.clear_table::<T>()?;
+            entries
+        });
+        tx.commit()?;
+        Ok(entries as u64)
+    }
+
+    /// Verifies that the canonical chain is contiguous and linked from its first block
+    /// to the latest one, that the bodies of its unpruned blocks are stored, that the
+    /// freezer matches its checksums and that the whole state trie of the latest block
+    /// is reachable from its state root.
+    pub fn check(&self) -> anyhow::Result<CheckReport> {
+        let mut report = CheckReport::default();
+        let tx = self.db.begin_read()?;
+        let latest = read_chain_data_in(&tx, ChainDataIndex::LatestBlockNumber)?;
+        let head = check_canonical_chain(&tx, latest, &mut report)?;
+
+        let earliest = self.earliest_history_block()?;
+        {
+            let freezer = self.freezer.read().unwrap();
+            for table in freezer.tables() {
+                if let Err(err) = table.verify() {
+                    report.problems.push(format!("ancient store: {err:#}"));
+                }
+            }
+            for number in earliest..latest.map_or(0, |latest| latest + 1) {
+                let Some(hash) = tx.get::<CanonicalHashes>(number)? else {
+                    continue;
+                };
+                if !freezer.contains(number) && tx.get::<Bodies>(hash)?.is_none() {
+                    report
+                        .problems
+                        .push(format!("missing body of canonical block {number}"));
+                }
+            }
+        }
+
+        if let Some(state_root) = head {
+            check_state_trie(&tx, state_root, &mut report)?;
+        }
+        Ok(report)
+    }
+}
+
+/// Walks the canonical hashes, returning the state root of the latest block if its
+/// header is stored.
+fn check_canonical_chain<K: TransactionKind>(
+    tx: &Transaction<'_, K>,
+    latest: Option<BlockNumber>,
+    report: &mut CheckReport,
+) -> anyhow::Result<Option<H256>> {
+    let mut parent: Option<(BlockNumber, [u8; 32])> = None;
+    let mut head = None;
+    for entry in tx.cursor::<CanonicalHashes>()?.walk(None) {
+        let (number, hash) = entry?;
+        report.canonical_blocks += 1;
+        match parent {
+            None if number != 0 => report.problems.push(format!(
+                "canonical chain starts at block {number} instead of genesis"
+            )),
+            Some((parent_number, _)) if number != parent_number + 1 => report.problems.push(
+                format!("canonical chain has a gap between blocks {parent_number} and {number}"),
+            ),
+            _ => {}
+        }
+        if tx.get::<HeaderNumbers>(hash)? != Some(number) {
+            report.problems.push(format!(
+                "block number of canonical block {number} ({:#x}) is not indexed",
+                H256(hash)
+            ));
+        }
+        match tx
+            .get::<Headers>(hash)?
+            .map(|header| header.to())
+            .transpose()
+        {
+            Ok(Some(header)) => {
+                if header.compute_block_hash().0 != hash {
+                    report.problems.push(format!(
+                        "header of canonical block {number} does not match its hash"
+                    ));
+                } else if header.number != number {
+                    report.problems.push(format!(
+                        "canonical block {number} has a header for block {}",
+                        header.number
+                    ));
+                }
+                if let Some((parent_number, parent_hash)) = parent {
+                    if parent_number + 1 == number && header.parent_hash.0 != parent_hash {
+                        report.problems.push(format!(
+                            "canonical block {number} is not a child of block {parent_number}"
+                        ));
+                    }
+                }
+                if Some(number) == latest {
+                    head = Some(header.state_root);
+                }
+            }
+            Ok(None) => report
+                .problems
+                .push(format!("missing header of canonical block {number}")),
+            Err(err) => report.problems.push(format!(
+                "undecodable header of canonical block {number}: {err}"
+            )),
+        }
+        parent = Some((number, hash));
+    }
+    let last = parent.map(|(number, _)| number);
+    if last != latest {
+        report.problems.push(format!(
+            "latest block is {} but the last canonical block is {}",
+            display_number(latest),
+            display_number(last)
+        ));
+    }
+    Ok(head)
+}
+
+fn display_number(number: Option<BlockNumber>) -> String {
+    number.map_or_else(|| "none".to_string(), |number| number.to_string())
+}
+
+/// Walks the account trie and every storage trie it references, reading each node from
+/// `TrieNodes` under its owner ++ nibble path as [`crate::trie`] stores them.
+fn check_state_trie<K: TransactionKind>(
+    tx: &Transaction<'_, K>,
+    state_root: H256,
+    report: &mut CheckReport,
+) -> anyhow::Result<()> {
+    let mut tries = vec![(H256::zero(), state_root)];
+    while let Some((owner, root)) = tries.pop() {
+        if root == EMPTY_TRIE_ROOT {
+            continue;
+        }
+        let mut pending = vec![(Vec::new(), NodeRef::Hash(root))];
+        while let Some((path, reference)) = pending.pop() {
+            let node = match reference {
+                NodeRef::Hash(hash) => {
+                    let Some(node) = tx.get::<TrieNodes>([owner.as_bytes(), &path].concat())?
+                    else {
+                        report
+                            .problems
+                            .push(format!("missing trie node at {}", location(owner, &path)));
+                        continue;
+                    };
+                    report.trie_nodes += 1;
+                    if keccak(&node) != hash {
+                        report.problems.push(format!(
+                            "trie node at {} does not match its hash",
+                            location(owner, &path)
+                        ));
+                        continue;
+                    }
+                    node
+                }
+                NodeRef::Embedded(node) => node,
+            };
+            let decoded = match decode_node(&node) {
+                Ok(decoded) => decoded,
+                Err(err) => {
+                    report.problems.push(format!(
+                        "undecodable trie node at {}: {err}",
+                        location(owner, &path)
+                    ));
+                    continue;
+                }
+            };
+            match decoded {
+                Node::Branch(children) => {
+                    for (nibble, child) in children {
+                        pending.push(([path.as_slice(), &[nibble]].concat(), child));
+                    }
+                }
+                Node::Extension(nibbles, child) => pending.push(([path, nibbles].concat(), child)),
+                Node::Leaf(nibbles, value) => {
+                    let path = [path, nibbles].concat();
+                    if !owner.is_zero() || path.len() != 64 {
+                        continue;
+                    }
+                    let account = H256::from_slice(
+                        &path
+                            .chunks(2)
+                            .map(|pair| (pair[0] << 4) | pair[1])
+                            .collect::<Vec<_>>(),
+                    );
+                    match decode_account(&value) {
+                        Ok((storage_root, code_hash)) => {
+                            if code_hash != EMPTY_CODE_HASH
+                                && tx.get::<AccountCodes>(code_hash.0)?.is_none()
+                            {
+                                report.problems.push(format!(
+                                    "missing code {code_hash:#x} of account {account:#x}"
+                                ));
+                            }
+                            tries.push((account, storage_root));
+                        }
+                        Err(err) => report.problems.push(format!(
+                            "undecodable account at {}: {err}",
+                            location(owner, &path)
+                        )),
+                    }
+                }
+            }
+        }
+    }
+    let stored = tx.table_stat::<TrieNodes>()?.entries() as u64;
+    report.unreachable_trie_nodes = stored.saturating_sub(report.trie_nodes);
+    Ok(())
+}
+
+/// Storage root and code hash of an account trie leaf value.
+fn decode_account(value: &[u8]) -> Result<(H256, H256), RLPDecodeError> {
+    let (is_list, fields, _) = decode_rlp_item(value)?;
+    if !is_list {
+        return Err(RLPDecodeError::UnexpectedString);
+    }
+    let (_nonce, fields) = get_item_with_prefix(fields)?;
+    let (_balance, fields) = get_item_with_prefix(fields)?;
+    let (storage_root, fields) = decode_bytes(fields)?;
+    let (code_hash, _) = decode_bytes(fields)?;
+    if storage_root.len() != 32 || code_hash.len() != 32 {
+        return Err(RLPDecodeError::InvalidLength);
+    }
+    Ok((H256::from_slice(storage_root), H256::from_slice(code_hash)))
+}
+
+/// Reads the values under `key`, walking all of its duplicates in dupsort tables.
+fn entries_of<K: TransactionKind, T: Table>(
+    tx: &Transaction<'_, K>,
+    key: &[u8],
+) -> anyhow::Result<Vec<String>>
+where
+    T::Key: Decodable,
+    T::Value: Inspect,
+{
+    let mut cursor = tx.cursor::<T>()?;
+    let mut entries = Vec::new();
+    let mut entry = cursor.seek_exact(T::Key::decode(key)?)?;
+    while let Some((found, value)) = entry {
+        if found.encode().as_ref() != key {
+            break;
+        }
+        entries.push(value.inspect());
+        entry = cursor.next()?;
+    }
+    Ok(entries)
+}
+
+/// Human readable form of a table value.
+trait Inspect {
+    fn inspect(&self) -> String;
+}
+
+impl<const N: usize> Inspect for [u8; N] {
+    fn inspect(&self) -> String {
+        format!("0x{}", hex::encode(self))
+    }
+}
+
+impl Inspect for Vec<u8> {
+    fn inspect(&self) -> String {
+        format!("0x{}", hex::encode(self))
+    }
+}
+
+impl Inspect for u64 {
+    fn inspect(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl<A: Inspect, B: Inspect> Inspect for (A, B) {
+    fn inspect(&self) -> String {
+        format!("({}, {})", self.0.inspect(), self.1.inspect())
+    }
+}
+
+impl<T: RLPDecode + Debug> Inspect for Rlp<T> {
+    fn inspect(&self) -> String {
+        match self.to() {
+            Ok(value) => format!("{value:#?}"),
+            Err(err) => format!("undecodable ({err}): 0x{}", hex::encode(self.bytes())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum_types::{Address, U256};
+    use reec_core::types::{
+        AccountDiff, AccountInfo, BlockBody, BlockHash, BlockHeader, StateDiff,
+    };
+
+    use super::*;
+
+    fn build_chain(store: &Store, length: u64, head_state_root: H256) -> Vec<BlockHash> {
+        let mut hashes: Vec<BlockHash> = Vec::new();
+        for number in 0..length {
+            let header = BlockHeader {
+                parent_hash: hashes.last().copied().unwrap_or_default(),
+                number,
+                state_root: if number == length - 1 {
+                    head_state_root
+                } else {
+                    EMPTY_TRIE_ROOT
+                },
+                ..Default::default()
+            };
+            let hash = header.compute_block_hash();
+            store.add_block(header, BlockBody::default()).unwrap();
+            store.set_canonical_block(number, hash).unwrap();
+            hashes.push(hash);
+        }
+        store.update_latest_block_number(length - 1).unwrap();
+        hashes
+    }
+
+    #[test]
+    fn reports_and_drops_tables() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let hashes = build_chain(&store, 3, EMPTY_TRIE_ROOT);
+
+        assert!(crate::tables::chart()
+            .keys()
+            .eq(TABLES.iter().collect::<std::collections::BTreeSet<_>>()));
+        let stats = store.table_stats().unwrap();
+        assert_eq!(stats.len(), TABLES.len() + 2);
+        let canonical = stats
+            .iter()
+            .find(|stat| stat.name == "CanonicalHashes")
+            .unwrap();
+        assert_eq!(canonical.entries, 3);
+
+        let entries = store
+            .get_entries("CanonicalHashes", &1u64.to_be_bytes())
+            .unwrap();
+        assert_eq!(entries, vec![format!("{:#x}", hashes[1])]);
+        let entries = store.get_entries("Headers", hashes[2].as_bytes()).unwrap();
+        assert!(entries[0].contains("number: 2"), "{}", entries[0]);
+        assert!(store
+            .get_entries("Headers", H256::zero().as_bytes())
+            .unwrap()
+            .is_empty());
+        assert!(store.get_entries("Nonexistent", &[]).is_err());
+
+        assert_eq!(store.drop_table("HeaderNumbers").unwrap(), 3);
+        assert_eq!(store.get_block_number(hashes[1]).unwrap(), None);
+        assert!(store.drop_table("Nonexistent").is_err());
+    }
+
+    #[test]
+    fn checks_canonical_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let hashes = build_chain(&store, 5, EMPTY_TRIE_ROOT);
+        let report = store.check().unwrap();
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!(report.canonical_blocks, 5);
+
+        // Block 3 replaced by a block that does not build on block 2
+        let orphan = BlockHeader {
+            number: 3,
+            parent_hash: hashes[0],
+            ..Default::default()
+        };
+        let orphan_hash = orphan.compute_block_hash();
+        store.add_block(orphan, BlockBody::default()).unwrap();
+        store
+            .db
+            .begin_readwrite()
+            .and_then(|tx| {
+                tx.upsert::<CanonicalHashes>(3, orphan_hash.0)?;
+                tx.delete::<Bodies>(hashes[1].0, None)?;
+                tx.commit()
+            })
+            .unwrap();
+        let problems = store.check().unwrap().problems;
+        assert_eq!(problems.len(), 3, "{problems:?}");
+        assert!(problems[0].contains("block 3 is not a child of block 2"));
+        assert!(problems[1].contains("block 4 is not a child of block 3"));
+        assert!(problems[2].contains("missing body of canonical block 1"));
+    }
+
+    #[test]
+    fn checks_ancient_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        build_chain(&store, 3, EMPTY_TRIE_ROOT);
+        {
+            let mut freezer = store.freezer.write().unwrap();
+            for number in 0..2 {
+                freezer.append_block(number, &[0xc0], &[0xc0]).unwrap();
+            }
+            freezer.sync().unwrap();
+        }
+        let report = store.check().unwrap();
+        assert!(report.is_ok(), "{:?}", report.problems);
+
+        // Flip a byte of the receipts of block 1 on disk
+        let path = dir.path().join("ancient").join("receipts.0000.cdat");
+        let mut data = std::fs::read(&path).unwrap();
+        *data.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&path, data).unwrap();
+        assert_eq!(
+            store.check().unwrap().problems,
+            vec!["ancient store: freezer table receipts item 1 failed its checksum".to_string()]
+        );
+    }
+
+    #[test]
+    fn checks_state_trie() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let address = Address::repeat_byte(0x11);
+        let owner = keccak(address);
+        let code = bytes::Bytes::from_static(&[0x60, 0x00]);
+        let diff = StateDiff::from([(
+            address,
+            AccountDiff {
+                info: Some(AccountInfo {
+                    nonce: 1,
+                    balance: U256::from(100),
+                    code_hash: keccak(&code),
+                }),
+                storage: [(H256::zero(), U256::from(7))].into(),
+                code: Some(code.clone()),
+                ..Default::default()
+            },
+        )]);
+        let root = store.write_state(0, &diff).unwrap();
+        build_chain(&store, 1, root);
+
+        let report = store.check().unwrap();
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!((report.trie_nodes, report.unreachable_trie_nodes), (2, 0));
+
+        // Storage root lost, and a node left behind by a write that was never completed
+        store
+            .db
+            .begin_readwrite()
+            .and_then(|tx| {
+                tx.delete::<TrieNodes>(owner.as_bytes().to_vec(), None)?;
+                tx.upsert::<TrieNodes>([owner.as_bytes(), &[1, 2]].concat(), vec![0xc0])?;
+                tx.delete::<AccountCodes>(keccak(&code).0, None)?;
+                tx.commit()
+            })
+            .unwrap();
+        let report = store.check().unwrap();
+        assert_eq!(
+            report.problems,
+            vec![
+                format!("missing code {:#x} of account {owner:#x}", keccak(&code)),
+                format!("missing trie node at storage trie {owner:#x} path 0x"),
+            ]
+        );
+        assert_eq!((report.trie_nodes, report.unreachable_trie_nodes), (1, 1));
+    }
+}
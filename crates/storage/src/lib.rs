@@ -1,14 +1,447 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
+use std::{
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{anyhow, bail};
+use ethereum_types::H256;
+use freezer::Freezer;
+use libmdbx::orm::{
+    Database, DatabaseOptions, Mode, ReadWriteOptions, Transaction, TransactionKind,
+};
+use reec_core::types::{BlockBody, BlockHash, BlockHeader, BlockNumber, Receipt};
+use tables::*;
+use tracing::{debug, info};
+
+mod canonical;
+pub mod era;
+pub mod freezer;
+pub mod inspect;
+pub mod logs;
+pub mod migrations;
+pub mod pruner;
+pub mod rlp;
+mod state;
+pub mod tables;
+mod trie;
+
+pub use state::StateAt;
+
+/// Upper bound the chain database may grow to. MDBX otherwise caps new databases at
+/// a few megabytes.
+const MAX_DATABASE_SIZE: isize = 4 << 40;
+
+/// Blocks moved into the freezer per database transaction.
+const FREEZER_BATCH_SIZE: u64 = 1024;
+
+/// Chain storage: recent data lives in MDBX, finalized bodies and receipts older than
+/// the freezer threshold live in the append-only [`Freezer`]. Reads go to whichever
+/// one holds the block.
+#[derive(Clone, Debug)]
+pub struct Store {
+    db: Arc<Database>,
+    freezer: Arc<RwLock<Freezer>>,
+}
+
+impl Store {
+    /// Opens the chain database under `datadir`, creating it if needed.
+    pub fn new(datadir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let datadir = datadir.as_ref();
+        let options = DatabaseOptions {
+            mode: Mode::ReadWrite(ReadWriteOptions {
+                max_size: Some(MAX_DATABASE_SIZE),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let db = Database::create_with_options(
+            Some(datadir.join("chaindata")),
+            options,
+            &tables::chart(),
+        )?;
+        let freezer = Freezer::open(&datadir.join("ancient"))?;
+        let store = Store {
+            db: Arc::new(db),
+            freezer: Arc::new(RwLock::new(freezer)),
+        };
+        migrations::migrate(&store)?;
+        store.check_freezer()?;
+        Ok(store)
+    }
+
+    pub fn add_block_header(&self, hash: BlockHash, header: BlockHeader) -> anyhow::Result<()> {
+        let tx = self.db.begin_readwrite()?;
+        tx.upsert::<HeaderNumbers>(hash.0, header.number)?;
+        tx.upsert::<Headers>(hash.0, header.into())?;
+        tx.commit()
+    }
+
+    pub fn get_block_header_by_hash(&self, hash: BlockHash) -> anyhow::Result<Option<BlockHeader>> {
+        let tx = self.db.begin_read()?;
+        Ok(tx.get::<Headers>(hash.0)?.map(|h| h.to()).transpose()?)
+    }
+
+    pub fn get_block_header(&self, number: BlockNumber) -> anyhow::Result<Option<BlockHeader>> {
+        match self.get_canonical_block_hash(number)? {
+            Some(hash) => self.get_block_header_by_hash(hash),
+            None => Ok(None),
+        }
+    }
+
+    /// Stores a block's body, indexing its transactions if the block is canonical.
+    pub fn add_block_body(&self, hash: BlockHash, body: BlockBody) -> anyhow::Result<()> {
+        let tx = self.db.begin_readwrite()?;
+        if let Some(number) = tx.get::<HeaderNumbers>(hash.0)? {
+            if tx.get::<CanonicalHashes>(number)? == Some(hash.0) {
+                canonical::index_transactions(&tx, number, &body)?;
+            }
+        }
+        tx.upsert::<Bodies>(hash.0, body.into())?;
+        tx.commit()
+    }
+
+    /// Stores a block's header and body.
+    pub fn add_block(&self, header: BlockHeader, body: BlockBody) -> anyhow::Result<()> {
+        let hash = header.compute_block_hash();
+        let number = header.number;
+        let tx = self.db.begin_readwrite()?;
+        tx.upsert::<HeaderNumbers>(hash.0, number)?;
+        tx.upsert::<Headers>(hash.0, header.into())?;
+        if tx.get::<CanonicalHashes>(number)? == Some(hash.0) {
+            canonical::index_transactions(&tx, number, &body)?;
+        }
+        tx.upsert::<Bodies>(hash.0, body.into())?;
+        tx.commit()
+    }
+
+    pub fn get_block_body(&self, number: BlockNumber) -> anyhow::Result<Option<BlockBody>> {
+        if let Some(body) = self.read_ancient(number, Freezer::body)? {
+            return Ok(Some(body));
+        }
+        match self.get_canonical_block_hash(number)? {
+            Some(hash) => self.get_block_body_by_hash(hash),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_block_body_by_hash(&self, hash: BlockHash) -> anyhow::Result<Option<BlockBody>> {
+        let tx = self.db.begin_read()?;
+        if let Some(body) = tx.get::<Bodies>(hash.0)? {
+            return Ok(Some(body.to()?));
+        }
+        match self.ancient_number(hash)? {
+            Some(number) => self.read_ancient(number, Freezer::body),
+            None => Ok(None),
+        }
+    }
+
+    /// Stores a block's receipts, indexing its logs if the block is canonical.
+    pub fn add_receipts(&self, hash: BlockHash, receipts: Vec<Receipt>) -> anyhow::Result<()> {
+        let tx = self.db.begin_readwrite()?;
+        if let Some(number) = tx.get::<HeaderNumbers>(hash.0)? {
+            if tx.get::<CanonicalHashes>(number)? == Some(hash.0) {
+                logs::index_logs(&tx, number, &receipts)?;
+            }
+        }
+        tx.upsert::<Receipts>(hash.0, receipts.into())?;
+        tx.commit()
+    }
+
+    pub fn get_receipts(&self, number: BlockNumber) -> anyhow::Result<Option<Vec<Receipt>>> {
+        if let Some(receipts) = self.read_ancient(number, Freezer::receipts)? {
+            return Ok(Some(receipts));
+        }
+        match self.get_canonical_block_hash(number)? {
+            Some(hash) => self.get_receipts_by_hash(hash),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_receipts_by_hash(&self, hash: BlockHash) -> anyhow::Result<Option<Vec<Receipt>>> {
+        let tx = self.db.begin_read()?;
+        if let Some(receipts) = tx.get::<Receipts>(hash.0)? {
+            return Ok(Some(receipts.to()?));
+        }
+        match self.ancient_number(hash)? {
+            Some(number) => self.read_ancient(number, Freezer::receipts),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_canonical_block_hash(
+        &self,
+        number: BlockNumber,
+    ) -> anyhow::Result<Option<BlockHash>> {
+        let tx = self.db.begin_read()?;
+        Ok(tx.get::<CanonicalHashes>(number)?.map(H256))
+    }
+
+    pub fn update_latest_block_number(&self, number: BlockNumber) -> anyhow::Result<()> {
+        self.write_chain_data(ChainDataIndex::LatestBlockNumber, number)
+    }
+
+    pub fn get_latest_block_number(&self) -> anyhow::Result<Option<BlockNumber>> {
+        self.read_chain_data(ChainDataIndex::LatestBlockNumber)
+    }
+
+    pub fn update_finalized_block_number(&self, number: BlockNumber) -> anyhow::Result<()> {
+        self.write_chain_data(ChainDataIndex::FinalizedBlockNumber, number)
+    }
+
+    pub fn get_finalized_block_number(&self) -> anyhow::Result<Option<BlockNumber>> {
+        self.read_chain_data(ChainDataIndex::FinalizedBlockNumber)
+    }
+
+    pub fn update_safe_block_number(&self, number: BlockNumber) -> anyhow::Result<()> {
+        self.write_chain_data(ChainDataIndex::SafeBlockNumber, number)
+    }
+
+    pub fn get_safe_block_number(&self) -> anyhow::Result<Option<BlockNumber>> {
+        self.read_chain_data(ChainDataIndex::SafeBlockNumber)
+    }
+
+    /// Number of the next block to be moved to the freezer.
+    pub fn ancient_blocks(&self) -> BlockNumber {
+        self.freezer.read().unwrap().frozen()
+    }
+
+    /// Moves the bodies and receipts of canonical blocks more than `threshold` blocks
+    /// behind the finalized block from MDBX into the freezer. Returns how many blocks
+    /// were moved.
+    ///
+    /// Blocks are appended and synced to the freezer before being deleted from MDBX,
+    /// so a crash in between leaves a harmless duplicate rather than a gap. The freezer
+    /// lock is held across the MDBX write transactions, so no other path may take it
+    /// while in one.
+    pub fn migrate_to_freezer(&self, threshold: u64) -> anyhow::Result<u64> {
+        let Some(finalized) = self.get_finalized_block_number()? else {
+            return Ok(0);
+        };
+        let target = finalized.saturating_sub(threshold);
+        let mut freezer = self.freezer.write().unwrap();
+        let start = freezer.frozen();
+        while freezer.frozen() < target {
+            let from = freezer.frozen();
+            let to = target.min(from + FREEZER_BATCH_SIZE);
+            let mut moved = Vec::new();
+            {
+                let tx = self.db.begin_read()?;
+                for number in from..to {
+                    let hash = tx
+                        .get::<CanonicalHashes>(number)?
+                        .ok_or_else(|| anyhow!("missing canonical hash for block {number}"))?;
+                    let (Some(body), Some(receipts)) =
+                        (tx.get::<Bodies>(hash)?, tx.get::<Receipts>(hash)?)
+                    else {
+                        debug!("Block {number} has no body or receipts yet, stopping freezer migration");
+                        break;
+                    };
+                    freezer.append_block(number, body.bytes(), receipts.bytes())?;
+                    moved.push(hash);
+                }
+            }
+            freezer.sync()?;
+            let tx = self.db.begin_readwrite()?;
+            for hash in &moved {
+                tx.delete::<Bodies>(*hash, None)?;
+                tx.delete::<Receipts>(*hash, None)?;
+            }
+            tx.commit()?;
+            if (moved.len() as u64) < to - from {
+                break;
+            }
+        }
+        let moved = freezer.frozen() - start;
+        if moved > 0 {
+            info!(
+                "Moved {moved} blocks to the freezer, {} frozen",
+                freezer.frozen()
+            );
+        }
+        Ok(moved)
+    }
+
+    /// Reads and decodes a block's item from the freezer, if it holds that block.
+    fn read_ancient<T: reec_core::rlp::decode::RLPDecode>(
+        &self,
+        number: BlockNumber,
+        read: fn(&Freezer, BlockNumber) -> anyhow::Result<Option<Vec<u8>>>,
+    ) -> anyhow::Result<Option<T>> {
+        let freezer = self.freezer.read().unwrap();
+        match read(&freezer, number)? {
+            Some(bytes) => Ok(Some(T::decode(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the block number if `hash` is a canonical block held in the freezer.
+    fn ancient_number(&self, hash: BlockHash) -> anyhow::Result<Option<BlockNumber>> {
+        let Some(number) = self.get_block_number(hash)? else {
+            return Ok(None);
+        };
+        let canonical = self.get_canonical_block_hash(number)? == Some(hash);
+        let frozen = self.freezer.read().unwrap().contains(number);
+        Ok((canonical && frozen).then_some(number))
+    }
+
+    /// The freezer must never run ahead of the database it was migrated from, which
+    /// would happen if the MDBX files were replaced or rolled back.
+    fn check_freezer(&self) -> anyhow::Result<()> {
+        let frozen = self.freezer.read().unwrap().frozen();
+        if frozen == 0 {
+            return Ok(());
+        }
+        if self.get_canonical_block_hash(frozen - 1)?.is_none() {
+            bail!(
+                "ancient store holds blocks up to {} but the database has no such canonical block",
+                frozen - 1
+            );
+        }
+        Ok(())
+    }
+
+    fn write_chain_data(&self, index: ChainDataIndex, number: BlockNumber) -> anyhow::Result<()> {
+        let tx = self.db.begin_readwrite()?;
+        tx.upsert::<ChainData>(index, number.to_be_bytes().to_vec())?;
+        tx.commit()
+    }
+
+    fn read_chain_data(&self, index: ChainDataIndex) -> anyhow::Result<Option<BlockNumber>> {
+        let tx = self.db.begin_read()?;
+        read_chain_data_in(&tx, index)
+    }
+}
+
+fn read_chain_data_in<K: TransactionKind>(
+    tx: &Transaction<'_, K>,
+    index: ChainDataIndex,
+) -> anyhow::Result<Option<BlockNumber>> {
+    match tx.get::<ChainData>(index)? {
+        Some(bytes) => Ok(Some(BlockNumber::from_be_bytes(
+            bytes.as_slice().try_into()?,
+        ))),
+        None => Ok(None),
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use bytes::Bytes;
+    use reec_core::types::{Log, TxType};
+
     use super::*;
 
+    fn build_chain(store: &Store, length: u64) -> Vec<BlockHash> {
+        let mut hashes = Vec::new();
+        let mut parent_hash = H256::zero();
+        for number in 0..length {
+            let header = BlockHeader {
+                parent_hash,
+                number,
+                ..Default::default()
+            };
+            let hash = header.compute_block_hash();
+            let body = BlockBody {
+                ommers: vec![],
+                transactions: vec![],
+                withdrawals: Some(vec![]),
+            };
+            let receipts = vec![Receipt::new(
+                TxType::EIP1559,
+                true,
+                number,
+                vec![Log {
+                    data: Bytes::from(number.to_be_bytes().to_vec()),
+                    ..Default::default()
+                }],
+            )];
+            store.add_block(header, body).unwrap();
+            store.add_receipts(hash, receipts).unwrap();
+            store.set_canonical_block(number, hash).unwrap();
+            parent_hash = hash;
+            hashes.push(hash);
+        }
+        store.update_latest_block_number(length - 1).unwrap();
+        hashes
+    }
+
+    #[test]
+    fn reads_are_transparent_across_freezer() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let hashes = build_chain(&store, 20);
+        let expected: Vec<_> = (0..20)
+            .map(|n| store.get_receipts(n).unwrap().unwrap())
+            .collect();
+
+        // Nothing is frozen until there is a finalized block
+        assert_eq!(store.migrate_to_freezer(5).unwrap(), 0);
+        store.update_finalized_block_number(15).unwrap();
+        assert_eq!(store.migrate_to_freezer(5).unwrap(), 10);
+        assert_eq!(store.ancient_blocks(), 10);
+
+        {
+            let tx = store.db.begin_read().unwrap();
+            assert!(tx.get::<Bodies>(hashes[3].0).unwrap().is_none());
+            assert!(tx.get::<Bodies>(hashes[10].0).unwrap().is_some());
+        }
+        for number in 0..20 {
+            let hash = hashes[number as usize];
+            assert_eq!(
+                store.get_receipts(number).unwrap().as_ref(),
+                Some(&expected[number as usize])
+            );
+            assert_eq!(
+                store.get_receipts_by_hash(hash).unwrap().as_ref(),
+                Some(&expected[number as usize])
+            );
+            assert!(store.get_block_body(number).unwrap().is_some());
+            assert!(store.get_block_body_by_hash(hash).unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn freezer_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let store = Store::new(dir.path()).unwrap();
+            build_chain(&store, 10);
+            store.update_finalized_block_number(9).unwrap();
+            store.migrate_to_freezer(0).unwrap();
+        }
+        let store = Store::new(dir.path()).unwrap();
+        assert_eq!(store.ancient_blocks(), 9);
+        assert!(store.get_block_body(4).unwrap().is_some());
+        assert_eq!(store.migrate_to_freezer(0).unwrap(), 0);
+    }
+
+    #[test]
+    fn stops_at_blocks_without_receipts() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        build_chain(&store, 10);
+        let header = BlockHeader {
+            number: 10,
+            ..Default::default()
+        };
+        let hash = header.compute_block_hash();
+        store.add_block(header, BlockBody::default()).unwrap();
+        store.set_canonical_block(10, hash).unwrap();
+        store.update_finalized_block_number(20).unwrap();
+        assert_eq!(store.migrate_to_freezer(0).unwrap(), 10);
+        assert!(store.get_block_body(10).unwrap().is_some());
+    }
+
     #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+    fn refuses_freezer_ahead_of_database() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let store = Store::new(dir.path()).unwrap();
+            build_chain(&store, 10);
+            store.update_finalized_block_number(9).unwrap();
+            store.migrate_to_freezer(0).unwrap();
+        }
+        std::fs::remove_dir_all(dir.path().join("chaindata")).unwrap();
+        assert!(Store::new(dir.path()).is_err());
     }
 }
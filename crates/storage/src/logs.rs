@@ -0,0 +1,302 @@
+//! Index of the canonical blocks that emitted logs from each address and with each
+//! topic, so that log filters over long block ranges only read the receipts of blocks
+//! that can match.
+//!
+//! Topics are indexed regardless of their position within the log: the index only
+//! narrows down the candidate blocks, whose logs are then matched exactly.
+
+use std::collections::BTreeSet;
+
+use ethereum_types::{Address, H256};
+use libmdbx::orm::{Decodable, DupSort, Transaction, TransactionKind, RW};
+use reec_core::types::{BlockNumber, Log, Receipt};
+
+use crate::{tables::*, Store};
+
+/// Logs emitted by canonical blocks in `from_block..=to_block` that match the address
+/// and topic sets.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    pub from_block: BlockNumber,
+    pub to_block: BlockNumber,
+    /// Emitting contracts; any contract if empty.
+    pub addresses: Vec<Address>,
+    /// Accepted values for each topic position; an empty set accepts any value.
+    pub topics: Vec<Vec<H256>>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, log: &Log) -> bool {
+        if !self.addresses.is_empty() && !self.addresses.contains(&log.address) {
+            return false;
+        }
+        self.topics.iter().enumerate().all(|(position, accepted)| {
+            accepted.is_empty()
+                || log
+                    .topics
+                    .get(position)
+                    .is_some_and(|t| accepted.contains(t))
+        })
+    }
+}
+
+impl Store {
+    /// Canonical blocks in the filter's range that emitted at least one log from a
+    /// filtered address and with a value of every filtered topic position, in
+    /// ascending order. Blocks without any such log are never returned, but the
+    /// returned ones may still have no log matching the filter exactly.
+    pub fn get_log_blocks(&self, filter: &LogFilter) -> anyhow::Result<Vec<BlockNumber>> {
+        if filter.from_block > filter.to_block {
+            return Ok(Vec::new());
+        }
+        let tx = self.db.begin_read()?;
+        let mut candidates: Option<BTreeSet<BlockNumber>> = None;
+        if !filter.addresses.is_empty() {
+            let keys = filter.addresses.iter().map(|address| address.0);
+            candidates = Some(indexed_blocks::<_, LogAddressBlocks>(&tx, keys, filter)?);
+        }
+        for accepted in filter.topics.iter().filter(|accepted| !accepted.is_empty()) {
+            let keys = accepted.iter().map(|topic| topic.0);
+            let blocks = indexed_blocks::<_, LogTopicBlocks>(&tx, keys, filter)?;
+            candidates = Some(match candidates {
+                Some(candidates) => candidates.intersection(&blocks).copied().collect(),
+                None => blocks,
+            });
+            if candidates.as_ref().is_some_and(BTreeSet::is_empty) {
+                break;
+            }
+        }
+        Ok(match candidates {
+            Some(candidates) => candidates.into_iter().collect(),
+            None => (filter.from_block..=filter.to_block).collect(),
+        })
+    }
+}
+
+/// Blocks in the filter's range indexed under any of `keys`.
+fn indexed_blocks<K, T>(
+    tx: &Transaction<'_, K>,
+    keys: impl Iterator<Item = T::Key>,
+    filter: &LogFilter,
+) -> anyhow::Result<BTreeSet<BlockNumber>>
+where
+    K: TransactionKind,
+    T: DupSort<Value = BlockNumber, SeekValue = BlockNumber>,
+    T::Key: Clone + Decodable,
+{
+    let mut blocks = BTreeSet::new();
+    for key in keys {
+        for number in tx.cursor::<T>()?.walk_key(key, Some(filter.from_block)) {
+            let number = number?;
+            if number > filter.to_block {
+                break;
+            }
+            blocks.insert(number);
+        }
+    }
+    Ok(blocks)
+}
+
+pub(crate) fn index_logs(
+    tx: &Transaction<'_, RW>,
+    number: BlockNumber,
+    receipts: &[Receipt],
+) -> anyhow::Result<()> {
+    let (addresses, topics) = log_keys(receipts);
+    for address in addresses {
+        tx.upsert::<LogAddressBlocks>(address.0, number)?;
+    }
+    for topic in topics {
+        tx.upsert::<LogTopicBlocks>(topic.0, number)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn unindex_logs(
+    tx: &Transaction<'_, RW>,
+    number: BlockNumber,
+    receipts: &[Receipt],
+) -> anyhow::Result<()> {
+    let (addresses, topics) = log_keys(receipts);
+    for address in addresses {
+        tx.delete::<LogAddressBlocks>(address.0, Some(number))?;
+    }
+    for topic in topics {
+        tx.delete::<LogTopicBlocks>(topic.0, Some(number))?;
+    }
+    Ok(())
+}
+
+fn log_keys(receipts: &[Receipt]) -> (BTreeSet<Address>, BTreeSet<H256>) {
+    let logs = receipts.iter().flat_map(|receipt| &receipt.logs);
+    let addresses = logs.clone().map(|log| log.address).collect();
+    let topics = logs.flat_map(|log| log.topics.iter().copied()).collect();
+    (addresses, topics)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use reec_core::types::{BlockBody, BlockHeader, TxType};
+
+    use super::*;
+
+    fn log(address: u8, topics: &[u8]) -> Log {
+        Log {
+            address: Address::repeat_byte(address),
+            topics: topics.iter().map(|t| H256::repeat_byte(*t)).collect(),
+            data: Bytes::new(),
+        }
+    }
+
+    /// Stores a block with a single receipt holding `logs` on top of `parent`.
+    fn add_block(store: &Store, parent: &BlockHeader, salt: u8, logs: Vec<Log>) -> BlockHeader {
+        let header = BlockHeader {
+            parent_hash: parent.compute_block_hash(),
+            number: parent.number + 1,
+            extra_data: Bytes::from(vec![salt]),
+            ..Default::default()
+        };
+        let hash = header.compute_block_hash();
+        store
+            .add_block(header.clone(), BlockBody::default())
+            .unwrap();
+        store
+            .add_receipts(hash, vec![Receipt::new(TxType::Legacy, true, 21_000, logs)])
+            .unwrap();
+        header
+    }
+
+    fn filter(addresses: &[u8], topics: &[&[u8]]) -> LogFilter {
+        LogFilter {
+            from_block: 0,
+            to_block: 100,
+            addresses: addresses.iter().map(|a| Address::repeat_byte(*a)).collect(),
+            topics: topics
+                .iter()
+                .map(|accepted| accepted.iter().map(|t| H256::repeat_byte(*t)).collect())
+                .collect(),
+        }
+    }
+
+    fn genesis(store: &Store) -> BlockHeader {
+        let genesis = BlockHeader::default();
+        store
+            .add_block(genesis.clone(), BlockBody::default())
+            .unwrap();
+        store
+            .set_canonical_head(genesis.compute_block_hash())
+            .unwrap();
+        genesis
+    }
+
+    #[test]
+    fn matches_logs_by_address_and_topic_position() {
+        let f = filter(&[1, 2], &[&[], &[5, 6]]);
+        assert!(f.matches(&log(1, &[9, 5])));
+        assert!(f.matches(&log(2, &[9, 6, 7])));
+        assert!(!f.matches(&log(3, &[9, 5])));
+        assert!(!f.matches(&log(1, &[5, 9])));
+        assert!(!f.matches(&log(1, &[9])));
+        assert!(LogFilter::default().matches(&log(1, &[])));
+    }
+
+    #[test]
+    fn finds_candidate_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let genesis = genesis(&store);
+        let one = add_block(&store, &genesis, 0, vec![log(1, &[5])]);
+        let two = add_block(&store, &one, 0, vec![log(2, &[5, 6])]);
+        let three = add_block(&store, &two, 0, vec![log(1, &[6])]);
+        store
+            .set_canonical_head(three.compute_block_hash())
+            .unwrap();
+
+        assert_eq!(
+            store.get_log_blocks(&filter(&[1], &[])).unwrap(),
+            vec![1, 3]
+        );
+        assert_eq!(
+            store.get_log_blocks(&filter(&[], &[&[6]])).unwrap(),
+            vec![2, 3]
+        );
+        assert_eq!(
+            store.get_log_blocks(&filter(&[1], &[&[6]])).unwrap(),
+            vec![3]
+        );
+        assert_eq!(
+            store.get_log_blocks(&filter(&[1, 2], &[&[5]])).unwrap(),
+            vec![1, 2]
+        );
+        assert!(store.get_log_blocks(&filter(&[3], &[])).unwrap().is_empty());
+        let range = LogFilter {
+            from_block: 2,
+            to_block: 2,
+            ..filter(&[1, 2], &[])
+        };
+        assert_eq!(store.get_log_blocks(&range).unwrap(), vec![2]);
+        let unfiltered = LogFilter {
+            from_block: 1,
+            to_block: 3,
+            ..Default::default()
+        };
+        assert_eq!(store.get_log_blocks(&unfiltered).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn reorgs_move_log_indices() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let genesis = genesis(&store);
+        let a1 = add_block(&store, &genesis, 0, vec![log(1, &[5])]);
+        let a2 = add_block(&store, &a1, 0, vec![log(2, &[5])]);
+        store.set_canonical_head(a2.compute_block_hash()).unwrap();
+
+        let b1 = add_block(&store, &genesis, 1, vec![log(3, &[6])]);
+        store.set_canonical_head(b1.compute_block_hash()).unwrap();
+        assert!(store
+            .get_log_blocks(&filter(&[1, 2], &[]))
+            .unwrap()
+            .is_empty());
+        assert!(store
+            .get_log_blocks(&filter(&[], &[&[5]]))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store.get_log_blocks(&filter(&[3], &[&[6]])).unwrap(),
+            vec![1]
+        );
+
+        store.set_canonical_head(a2.compute_block_hash()).unwrap();
+        assert_eq!(
+            store.get_log_blocks(&filter(&[], &[&[5]])).unwrap(),
+            vec![1, 2]
+        );
+        assert!(store.get_log_blocks(&filter(&[3], &[])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn indexes_receipts_added_after_canonicalization() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let genesis = genesis(&store);
+        let header = BlockHeader {
+            parent_hash: genesis.compute_block_hash(),
+            number: 1,
+            ..Default::default()
+        };
+        let hash = header.compute_block_hash();
+        store.add_block(header, BlockBody::default()).unwrap();
+        store.set_canonical_head(hash).unwrap();
+        assert!(store.get_log_blocks(&filter(&[1], &[])).unwrap().is_empty());
+
+        store
+            .add_receipts(
+                hash,
+                vec![Receipt::new(TxType::Legacy, true, 0, vec![log(1, &[])])],
+            )
+            .unwrap();
+        assert_eq!(store.get_log_blocks(&filter(&[1], &[])).unwrap(), vec![1]);
+    }
+}
@@ -0,0 +1,456 @@
+//! Database layout versioning.
+//!
+//! Every database is stamped with the schema version it was written with. Opening a
+//! database from an older version runs the migrations between its version and
+//! [`SCHEMA_VERSION`] in order, stamping the database after each one. Databases from
+//! a newer version, or too old to migrate, are refused.
+
+use anyhow::bail;
+use ethereum_types::{Address, H256, U256};
+use reec_core::types::{AccountDiff, StateDiff};
+use tracing::{info, warn};
+
+use crate::{
+    canonical::index_transactions, logs::index_logs, tables::*, trie::update_state_trie, Store,
+};
+
+/// Version of the layout written by this build.
+pub const SCHEMA_VERSION: u64 = 4;
+
+/// Databases created before versioning was introduced carry no stamp and are treated
+/// as this version.
+const UNVERSIONED: u64 = 1;
+
+/// An in-place upgrade from one schema version to the next.
+///
+/// A crash after a migration runs but before the database is stamped reruns it on the
+/// next start, so migrations must be safe to run again.
+#[derive(Debug)]
+pub struct Migration {
+    /// Version the migration upgrades from; it leaves the database at `from + 1`.
+    pub from: u64,
+    pub description: &'static str,
+    pub run: fn(&Store) -> anyhow::Result<()>,
+}
+
+/// Entries written per database transaction by migrations that rewrite whole tables.
+const MIGRATION_BATCH_SIZE: usize = 10_000;
+
+/// Migrations from every supported version, oldest first.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 1,
+        description: "index block numbers and canonical transactions",
+        run: index_blocks,
+    },
+    Migration {
+        from: 2,
+        description: "index logs of canonical blocks",
+        run: index_canonical_logs,
+    },
+    Migration {
+        from: 3,
+        description: "build the state trie",
+        run: build_state_trie,
+    },
+];
+
+impl Store {
+    /// Schema version the database is stamped with, `None` for databases that predate
+    /// versioning.
+    pub fn schema_version(&self) -> anyhow::Result<Option<u64>> {
+        self.read_chain_data(ChainDataIndex::SchemaVersion)
+    }
+
+    fn set_schema_version(&self, version: u64) -> anyhow::Result<()> {
+        self.write_chain_data(ChainDataIndex::SchemaVersion, version)
+    }
+
+    /// A database with no blocks and no ancient data was just created.
+    fn is_empty(&self) -> anyhow::Result<bool> {
+        let tx = self.db.begin_read()?;
+        let no_headers = tx.cursor::<Headers>()?.first()?.is_none();
+        Ok(no_headers && self.ancient_blocks() == 0)
+    }
+}
+
+/// Brings the database up to [`SCHEMA_VERSION`], stamping new databases with it.
+pub fn migrate(store: &Store) -> anyhow::Result<()> {
+    run_migrations(store, MIGRATIONS, SCHEMA_VERSION)
+}
+
+fn run_migrations(store: &Store, migrations: &[Migration], target: u64) -> anyhow::Result<()> {
+    let mut version = match store.schema_version()? {
+        Some(version) => version,
+        None if store.is_empty()? => {
+            store.set_schema_version(target)?;
+            return Ok(());
+        }
+        None => UNVERSIONED,
+    };
+    if version > target {
+        bail!(
+            "database schema version {version} is newer than version {target} supported by this \
+             build, upgrade reec or use a different datadir"
+        );
+    }
+    while version < target {
+        let Some(migration) = migrations.iter().find(|m| m.from == version) else {
+            bail!(
+                "database schema version {version} can no longer be upgraded to version {target}, \
+                 the datadir must be removed and resynced"
+            );
+        };
+        info!(
+            "Migrating database from schema version {version} to {}: {}",
+            version + 1,
+            migration.description
+        );
+        (migration.run)(store)?;
+        version += 1;
+        store.set_schema_version(version)?;
+    }
+    if store.schema_version()?.is_none() {
+        store.set_schema_version(version)?;
+    }
+    Ok(())
+}
+
+/// Version 2 added the block hash to number and transaction location indices.
+fn index_blocks(store: &Store) -> anyhow::Result<()> {
+    let mut start = None;
+    loop {
+        let tx = store.db.begin_readwrite()?;
+        let mut headers = tx
+            .cursor::<Headers>()?
+            .walk(start)
+            .take(MIGRATION_BATCH_SIZE + 1)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        start = (headers.len() > MIGRATION_BATCH_SIZE).then(|| headers.pop().unwrap().0);
+        for (hash, header) in headers {
+            tx.upsert::<HeaderNumbers>(hash, header.to()?.number)?;
+        }
+        tx.commit()?;
+        if start.is_none() {
+            break;
+        }
+    }
+
+    let Some(head) = store.db.begin_read()?.cursor::<CanonicalHashes>()?.last()? else {
+        return Ok(());
+    };
+    let mut from = 0;
+    while from <= head.0 {
+        let to = (from + MIGRATION_BATCH_SIZE as u64).min(head.0 + 1);
+        let bodies = (from..to)
+            .map(|number| Ok((number, store.get_block_body(number)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let tx = store.db.begin_readwrite()?;
+        for (number, body) in bodies {
+            if let Some(body) = body {
+                index_transactions(&tx, number, &body)?;
+            }
+        }
+        tx.commit()?;
+        from = to;
+    }
+    Ok(())
+}
+
+/// Version 3 added the log address and topic indices.
+fn index_canonical_logs(store: &Store) -> anyhow::Result<()> {
+    let Some(head) = store.db.begin_read()?.cursor::<CanonicalHashes>()?.last()? else {
+        return Ok(());
+    };
+    let mut from = store.earliest_history_block()?;
+    while from <= head.0 {
+        let to = (from + MIGRATION_BATCH_SIZE as u64).min(head.0 + 1);
+        let receipts = (from..to)
+            .map(|number| Ok((number, store.get_receipts(number)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let tx = store.db.begin_readwrite()?;
+        for (number, receipts) in receipts {
+            if let Some(receipts) = receipts {
+                index_logs(&tx, number, &receipts)?;
+            }
+        }
+        tx.commit()?;
+        from = to;
+    }
+    Ok(())
+}
+
+/// Version 4 stored the nodes of the state trie, which is built from the flat state.
+/// The change sets of earlier blocks hold no trie nodes to unwind them with, so they
+/// are dropped as pruning would.
+fn build_state_trie(store: &Store) -> anyhow::Result<()> {
+    let tx = store.db.begin_readwrite()?;
+    tx.clear_table::<TrieNodes>()?;
+    tx.clear_table::<TrieChangeSets>()?;
+    tx.commit()?;
+
+    let mut start = None;
+    loop {
+        let tx = store.db.begin_readwrite()?;
+        let mut accounts = tx
+            .cursor::<AccountInfos>()?
+            .walk(start)
+            .take(MIGRATION_BATCH_SIZE + 1)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        start = (accounts.len() > MIGRATION_BATCH_SIZE).then(|| accounts.pop().unwrap().0);
+        let mut diff = StateDiff::new();
+        for (address, info) in accounts {
+            let storage = tx
+                .cursor::<AccountStorages>()?
+                .walk_key(address, None)
+                .map(|entry| {
+                    let (slot, value) = entry?;
+                    Ok((H256(slot), U256::from_big_endian(&value)))
+                })
+                .collect::<anyhow::Result<_>>()?;
+            let account = AccountDiff {
+                info: Some(info.to()?),
+                storage,
+                ..Default::default()
+            };
+            diff.insert(Address::from(address), account);
+        }
+        for (key, node) in update_state_trie(&tx, &diff)?.1 {
+            match node {
+                Some(node) => tx.upsert::<TrieNodes>(key, node)?,
+                None => {
+                    tx.delete::<TrieNodes>(key, None)?;
+                }
+            }
+        }
+        tx.commit()?;
+        if start.is_none() {
+            break;
+        }
+    }
+
+    let Some(latest) = store.get_latest_block_number()? else {
+        return Ok(());
+    };
+    let tx = store.db.begin_readwrite()?;
+    tx.clear_table::<AccountChangeSets>()?;
+    tx.clear_table::<StorageChangeSets>()?;
+    tx.upsert::<ChainData>(
+        ChainDataIndex::EarliestStateBlock,
+        (latest + 1).to_be_bytes().to_vec(),
+    )?;
+    tx.commit()?;
+    let root = evm::Database::state_root(store, &StateDiff::new())?;
+    if let Some(header) = store.get_block_header(latest)? {
+        if header.state_root != root {
+            warn!(
+                "State root {root:#x} does not match the root {:#x} of the latest block {latest}",
+                header.state_root
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use evm::Database as _;
+    use libmdbx::orm::Database;
+    use reec_core::types::{compute_state_root, AccountInfo, BlockHeader, Transaction};
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::logs::LogFilter;
+
+    /// Copies a datadir written by an earlier build from `testdata/migrations/{name}`.
+    ///
+    /// Every fixture holds the same chain: a default genesis header whose body has one
+    /// legacy transaction with nonce 1, a receipt with a log from `0x07..07`, accounts
+    /// `0x01..01` to `0x04..04` with their byte as nonce and slot `0x01..01` of the last
+    /// one set to 7.
+    fn datadir(name: &str) -> TempDir {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/migrations")
+            .join(name);
+        let dir = tempfile::tempdir().unwrap();
+        for subdir in ["chaindata", "ancient"] {
+            std::fs::create_dir(dir.path().join(subdir)).unwrap();
+            for entry in std::fs::read_dir(fixture.join(subdir)).unwrap() {
+                let path = entry.unwrap().path();
+                let target = dir.path().join(subdir).join(path.file_name().unwrap());
+                std::fs::copy(&path, target).unwrap();
+            }
+        }
+        dir
+    }
+
+    /// Checks the fixture chain is readable with every index built by the migrations.
+    fn assert_migrated(store: &Store) {
+        assert_eq!(store.schema_version().unwrap(), Some(SCHEMA_VERSION));
+
+        let hash = BlockHeader::default().compute_block_hash();
+        assert_eq!(store.get_block_number(hash).unwrap(), Some(0));
+        let body = store.get_block_body(0).unwrap().unwrap();
+        let [transaction] = &body.transactions[..] else {
+            panic!("expected a single transaction, got {body:?}");
+        };
+        assert!(matches!(transaction, Transaction::LegacyTransaction(tx) if tx.nonce == 1));
+        assert_eq!(
+            store
+                .get_transaction_location(transaction.compute_hash())
+                .unwrap(),
+            Some((0, 0))
+        );
+
+        let filter = LogFilter {
+            addresses: vec![Address::repeat_byte(7)],
+            ..Default::default()
+        };
+        assert_eq!(store.get_log_blocks(&filter).unwrap(), vec![0]);
+
+        let account = |nonce| AccountInfo {
+            nonce,
+            ..Default::default()
+        };
+        let storage = [(H256::repeat_byte(1), U256::from(7))].into();
+        let state = (1..=3)
+            .map(|byte| {
+                (
+                    Address::repeat_byte(byte),
+                    (account(byte as u64), Default::default()),
+                )
+            })
+            .chain([(Address::repeat_byte(4), (account(4), storage))])
+            .collect();
+        let root = compute_state_root(state, &StateDiff::new());
+        assert_eq!(store.state_root(&StateDiff::new()).unwrap(), root);
+        // The change sets of the genesis block were dropped with the trie build
+        assert_eq!(store.earliest_state_block().unwrap(), 1);
+        assert!(store.unwind_state(0).is_err());
+    }
+
+    fn open_without_migrating(datadir: &Path) -> Store {
+        let db = Database::create(Some(datadir.join("chaindata")), &chart()).unwrap();
+        let freezer = crate::freezer::Freezer::open(&datadir.join("ancient")).unwrap();
+        Store {
+            db: db.into(),
+            freezer: std::sync::RwLock::new(freezer).into(),
+        }
+    }
+
+    fn mark(store: &Store, number: u64) -> anyhow::Result<()> {
+        let tx = store.db.begin_readwrite()?;
+        tx.upsert::<CanonicalHashes>(number, [number as u8; 32])?;
+        tx.commit()
+    }
+
+    fn marks(store: &Store) -> Vec<u64> {
+        let tx = store.db.begin_read().unwrap();
+        tx.cursor::<CanonicalHashes>()
+            .unwrap()
+            .walk(None)
+            .map(|entry| entry.unwrap().0)
+            // The genesis block of the fixture is canonical too
+            .filter(|&number| number > 0)
+            .collect()
+    }
+
+    const TEST_MIGRATIONS: &[Migration] = &[
+        Migration {
+            from: 1,
+            description: "first",
+            run: |store| mark(store, 1),
+        },
+        Migration {
+            from: 2,
+            description: "second",
+            run: |store| mark(store, 2),
+        },
+    ];
+
+    #[test]
+    fn stamps_new_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        assert_eq!(store.schema_version().unwrap(), Some(SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn migrates_unversioned_databases() {
+        let dir = datadir("unversioned");
+        assert_migrated(&Store::new(dir.path()).unwrap());
+    }
+
+    #[test]
+    fn migrates_version_2_databases() {
+        let dir = datadir("v2");
+        assert_migrated(&Store::new(dir.path()).unwrap());
+    }
+
+    #[test]
+    fn migrates_version_3_databases() {
+        let dir = datadir("v3");
+        assert_migrated(&Store::new(dir.path()).unwrap());
+    }
+
+    #[test]
+    fn refuses_newer_databases() {
+        let dir = datadir("v3");
+        open_without_migrating(dir.path())
+            .set_schema_version(SCHEMA_VERSION + 1)
+            .unwrap();
+        let err = Store::new(dir.path()).unwrap_err();
+        assert!(err.to_string().contains("newer"), "{err}");
+    }
+
+    #[test]
+    fn runs_migrations_in_order() {
+        let dir = datadir("unversioned");
+        let store = open_without_migrating(dir.path());
+        run_migrations(&store, TEST_MIGRATIONS, 3).unwrap();
+        assert_eq!(store.schema_version().unwrap(), Some(3));
+        assert_eq!(marks(&store), vec![1, 2]);
+
+        // Already up to date
+        run_migrations(&store, &[], 3).unwrap();
+    }
+
+    #[test]
+    fn resumes_from_stamped_version() {
+        let dir = datadir("v2");
+        let store = open_without_migrating(dir.path());
+        run_migrations(&store, TEST_MIGRATIONS, 3).unwrap();
+        assert_eq!(marks(&store), vec![2]);
+    }
+
+    #[test]
+    fn refuses_versions_without_migration() {
+        let dir = datadir("unversioned");
+        let store = open_without_migrating(dir.path());
+        let err = run_migrations(&store, &TEST_MIGRATIONS[1..], 3).unwrap_err();
+        assert!(err.to_string().contains("resynced"), "{err}");
+        assert_eq!(store.schema_version().unwrap(), None);
+    }
+
+    #[test]
+    fn failed_migration_keeps_last_version() {
+        let dir = datadir("unversioned");
+        let store = open_without_migrating(dir.path());
+        let migrations = [
+            Migration {
+                from: 1,
+                description: "works",
+                run: |store| mark(store, 1),
+            },
+            Migration {
+                from: 2,
+                description: "fails",
+                run: |_| bail!("disk on fire"),
+            },
+        ];
+        assert!(run_migrations(&store, &migrations, 3).is_err());
+        assert_eq!(store.schema_version().unwrap(), Some(2));
+    }
+}
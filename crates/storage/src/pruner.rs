@@ -0,0 +1,378 @@
+use std::{
+    fmt,
+    str::FromStr,
+    sync::mpsc::{self, RecvTimeoutError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail};
+use reec_core::types::BlockNumber;
+use tracing::{error, info};
+
+use crate::{
+    canonical::unindex_transactions, freezer::FREEZER_THRESHOLD, logs::unindex_logs, tables::*,
+    Store,
+};
+
+/// Blocks with change sets behind the head kept by full and minimal nodes, enough to
+/// unwind any reorg short of finality.
+pub const RECENT_STATE_BLOCKS: u64 = 128;
+/// Blocks behind the finalized block whose bodies and receipts minimal nodes keep:
+/// roughly one year of 12 second slots, as suggested by EIP-4444.
+pub const HISTORY_RETENTION_BLOCKS: u64 = 2_628_000;
+/// Blocks pruned per database transaction.
+const PRUNE_BATCH_SIZE: u64 = 1024;
+/// Time between pruning runs of the background [`Pruner`].
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How much history the node keeps. Modes are ordered from least to most pruned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PruneMode {
+    /// Keep everything, including the state change sets of every block.
+    Archive,
+    /// Keep every block, but only the change sets of recent blocks.
+    #[default]
+    Full,
+    /// Like full, and also drop bodies and receipts older than the EIP-4444 window.
+    Minimal,
+}
+
+impl FromStr for PruneMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "archive" => Ok(PruneMode::Archive),
+            "full" => Ok(PruneMode::Full),
+            "minimal" => Ok(PruneMode::Minimal),
+            _ => Err(anyhow!(
+                "unknown prune mode {s}, expected archive, full or minimal"
+            )),
+        }
+    }
+}
+
+impl TryFrom<u64> for PruneMode {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(PruneMode::Archive),
+            1 => Ok(PruneMode::Full),
+            2 => Ok(PruneMode::Minimal),
+            _ => Err(anyhow!("invalid prune mode {value}")),
+        }
+    }
+}
+
+impl fmt::Display for PruneMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PruneMode::Archive => write!(f, "archive"),
+            PruneMode::Full => write!(f, "full"),
+            PruneMode::Minimal => write!(f, "minimal"),
+        }
+    }
+}
+
+impl Store {
+    /// Records the prune mode the database is used with. Pruned data cannot be brought
+    /// back, so switching to a mode that keeps more than before is refused.
+    pub fn set_prune_mode(&self, mode: PruneMode) -> anyhow::Result<()> {
+        if let Some(stored) = self.get_prune_mode()? {
+            if mode < stored {
+                bail!("database was pruned in {stored} mode and cannot be used in {mode} mode");
+            }
+        }
+        self.write_chain_data(ChainDataIndex::PruneMode, mode as u64)
+    }
+
+    pub fn get_prune_mode(&self) -> anyhow::Result<Option<PruneMode>> {
+        self.read_chain_data(ChainDataIndex::PruneMode)?
+            .map(PruneMode::try_from)
+            .transpose()
+    }
+
+    /// First block whose body and receipts are still available.
+    pub fn earliest_history_block(&self) -> anyhow::Result<BlockNumber> {
+        Ok(self
+            .read_chain_data(ChainDataIndex::EarliestHistoryBlock)?
+            .unwrap_or_default())
+    }
+
+    /// First block whose state changes are still available, and so the oldest block
+    /// whose state can be unwound.
+    pub fn earliest_state_block(&self) -> anyhow::Result<BlockNumber> {
+        Ok(self
+            .read_chain_data(ChainDataIndex::EarliestStateBlock)?
+            .unwrap_or_default())
+    }
+
+    /// Drops whatever data `mode` does not keep. Nothing is pruned until there is a
+    /// finalized block.
+    pub fn prune(&self, mode: PruneMode) -> anyhow::Result<()> {
+        if mode == PruneMode::Archive {
+            return Ok(());
+        }
+        let (Some(latest), Some(finalized)) = (
+            self.get_latest_block_number()?,
+            self.get_finalized_block_number()?,
+        ) else {
+            return Ok(());
+        };
+        self.prune_state_history(latest.saturating_sub(RECENT_STATE_BLOCKS).min(finalized))?;
+        if mode == PruneMode::Minimal {
+            self.prune_block_history(finalized.saturating_sub(HISTORY_RETENTION_BLOCKS))?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the change sets of every block below `before`.
+    fn prune_state_history(&self, before: BlockNumber) -> anyhow::Result<()> {
+        let start = self.earliest_state_block()?;
+        let mut from = start;
+        while from < before {
+            let to = before.min(from + PRUNE_BATCH_SIZE);
+            let tx = self.db.begin_readwrite()?;
+            for number in from..to {
+                tx.delete::<AccountChangeSets>(number, None)?;
+                tx.delete::<StorageChangeSets>(number, None)?;
+                tx.delete::<TrieChangeSets>(number, None)?;
+            }
+            tx.upsert::<ChainData>(
+                ChainDataIndex::EarliestStateBlock,
+                to.to_be_bytes().to_vec(),
+            )?;
+            tx.commit()?;
+            from = to;
+        }
+        if from > start {
+            info!("Pruned state changes of blocks {start} to {}", from - 1);
+        }
+        Ok(())
+    }
+
+    /// Deletes the bodies, receipts, transaction locations and log indices of every
+    /// block below `before`, both from the freezer and from MDBX.
+    fn prune_block_history(&self, before: BlockNumber) -> anyhow::Result<()> {
+        let start = self.earliest_history_block()?;
+        if before <= start {
+            return Ok(());
+        }
+        let mut from = start;
+        while from < before {
+            let to = before.min(from + PRUNE_BATCH_SIZE);
+            let blocks = (from..to)
+                .map(|number| {
+                    Ok((
+                        number,
+                        self.get_block_body(number)?,
+                        self.get_receipts(number)?,
+                    ))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let tx = self.db.begin_readwrite()?;
+            for (number, body, receipts) in blocks {
+                if let Some(body) = body {
+                    unindex_transactions(&tx, number, &body)?;
+                }
+                if let Some(receipts) = receipts {
+                    unindex_logs(&tx, number, &receipts)?;
+                }
+                if let Some(hash) = tx.get::<CanonicalHashes>(number)? {
+                    tx.delete::<Bodies>(hash, None)?;
+                    tx.delete::<Receipts>(hash, None)?;
+                }
+            }
+            tx.commit()?;
+            from = to;
+        }
+        self.freezer.write().unwrap().truncate_tail(before)?;
+        self.write_chain_data(ChainDataIndex::EarliestHistoryBlock, before)?;
+        info!(
+            "Pruned bodies and receipts of blocks {start} to {}",
+            before - 1
+        );
+        Ok(())
+    }
+}
+
+/// Periodically moves finalized blocks to the freezer and prunes what the configured
+/// [`PruneMode`] does not keep.
+#[derive(Debug)]
+pub struct Pruner {
+    store: Store,
+    mode: PruneMode,
+    interval: Duration,
+}
+
+impl Pruner {
+    pub fn new(store: Store, mode: PruneMode) -> Self {
+        Pruner {
+            store,
+            mode,
+            interval: PRUNE_INTERVAL,
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn run_once(&self) -> anyhow::Result<()> {
+        self.store.migrate_to_freezer(FREEZER_THRESHOLD)?;
+        self.store.prune(self.mode)
+    }
+
+    /// Runs the pruner on a background thread until the returned handle is stopped.
+    pub fn spawn(self) -> PrunerHandle {
+        let (stop, stopped) = mpsc::channel();
+        let thread = thread::spawn(move || loop {
+            if let Err(err) = self.run_once() {
+                error!("Pruning failed: {err:?}");
+            }
+            match stopped.recv_timeout(self.interval) {
+                Err(RecvTimeoutError::Timeout) => continue,
+                _ => break,
+            }
+        });
+        PrunerHandle { stop, thread }
+    }
+}
+
+#[derive(Debug)]
+pub struct PrunerHandle {
+    stop: mpsc::Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+impl PrunerHandle {
+    /// Stops the pruner, waiting for a run in progress to finish.
+    pub fn stop(self) {
+        let _ = self.stop.send(());
+        let _ = self.thread.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum_types::{Address, U256};
+    use reec_core::types::{
+        compute_state_root, AccountDiff, AccountInfo, BlockBody, BlockHeader, Receipt, StateDiff,
+    };
+
+    use super::*;
+
+    fn build_chain(store: &Store, length: u64) {
+        for number in 0..length {
+            let header = BlockHeader {
+                number,
+                ..Default::default()
+            };
+            let hash = header.compute_block_hash();
+            store.add_block(header, BlockBody::default()).unwrap();
+            store.add_receipts(hash, vec![Receipt::default()]).unwrap();
+            store.set_canonical_block(number, hash).unwrap();
+
+            let mut diff = StateDiff::new();
+            diff.insert(
+                Address::repeat_byte(1),
+                AccountDiff {
+                    info: Some(AccountInfo {
+                        nonce: number,
+                        balance: U256::one(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            );
+            store.write_state(number, &diff).unwrap();
+        }
+        store.update_latest_block_number(length - 1).unwrap();
+        store.update_finalized_block_number(length - 1).unwrap();
+    }
+
+    #[test]
+    fn parses_prune_modes() {
+        for mode in [PruneMode::Archive, PruneMode::Full, PruneMode::Minimal] {
+            assert_eq!(mode.to_string().parse::<PruneMode>().unwrap(), mode);
+        }
+        assert!("everything".parse::<PruneMode>().is_err());
+    }
+
+    #[test]
+    fn full_mode_keeps_recent_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        build_chain(&store, 200);
+        store.prune(PruneMode::Full).unwrap();
+
+        assert_eq!(
+            store.earliest_state_block().unwrap(),
+            199 - RECENT_STATE_BLOCKS
+        );
+        assert!(store.unwind_state(10).is_err());
+        let trie_changes = |number: u64| {
+            store
+                .get_entries("TrieChangeSets", &number.to_be_bytes())
+                .unwrap()
+        };
+        assert!(trie_changes(10).is_empty());
+        assert!(!trie_changes(199).is_empty());
+        store.unwind_state(199).unwrap();
+        let account = store
+            .get_account_info(Address::repeat_byte(1))
+            .unwrap()
+            .unwrap();
+        assert_eq!(account.nonce, 198);
+        let state = [(Address::repeat_byte(1), (account, Default::default()))].into();
+        assert_eq!(
+            evm::Database::state_root(&store, &StateDiff::new()).unwrap(),
+            compute_state_root(state, &StateDiff::new())
+        );
+        assert_eq!(store.earliest_history_block().unwrap(), 0);
+        assert!(store.get_block_body(0).unwrap().is_some());
+    }
+
+    #[test]
+    fn archive_mode_keeps_everything() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        build_chain(&store, 200);
+        store.prune(PruneMode::Archive).unwrap();
+        assert_eq!(store.earliest_state_block().unwrap(), 0);
+    }
+
+    #[test]
+    fn minimal_mode_drops_old_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        build_chain(&store, 20);
+        store.migrate_to_freezer(10).unwrap();
+        assert_eq!(store.ancient_blocks(), 9);
+
+        // Cuts past the freezer head, so blocks still in MDBX go too
+        store.prune_block_history(14).unwrap();
+
+        assert_eq!(store.earliest_history_block().unwrap(), 14);
+        assert_eq!(store.ancient_blocks(), 14);
+        for number in 0..14 {
+            assert!(store.get_block_body(number).unwrap().is_none());
+            assert!(store.get_receipts(number).unwrap().is_none());
+        }
+        assert!(store.get_block_body(14).unwrap().is_some());
+        assert!(store.get_block_header(0).unwrap().is_some());
+    }
+
+    #[test]
+    fn refuses_less_pruned_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        store.set_prune_mode(PruneMode::Full).unwrap();
+        store.set_prune_mode(PruneMode::Minimal).unwrap();
+        assert!(store.set_prune_mode(PruneMode::Full).is_err());
+        assert!(store.set_prune_mode(PruneMode::Archive).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+use std::marker::PhantomData;
+
+use libmdbx::orm::{Decodable, Encodable};
+use reec_core::rlp::{decode::RLPDecode, encode::RLPEncode, error::RLPDecodeError};
+
+/// Table value holding the RLP encoding of a `T`, decoded lazily with [`Rlp::to`].
+#[derive(Clone, Debug)]
+pub struct Rlp<T>(Vec<u8>, PhantomData<T>);
+
+impl<T: RLPEncode> From<T> for Rlp<T> {
+    fn from(value: T) -> Self {
+        Rlp(value.encode_to_vec(), PhantomData)
+    }
+}
+
+impl<T: RLPDecode> Rlp<T> {
+    pub fn to(&self) -> Result<T, RLPDecodeError> {
+        T::decode(&self.0)
+    }
+}
+
+impl<T> Rlp<T> {
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Rlp(bytes, PhantomData)
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl<T: Send + Sync> Encodable for Rlp<T> {
+    type Encoded = Vec<u8>;
+
+    fn encode(self) -> Self::Encoded {
+        self.0
+    }
+}
+
+impl<T: Send + Sync> Decodable for Rlp<T> {
+    fn decode(b: &[u8]) -> anyhow::Result<Self> {
+        Ok(Rlp(b.to_vec(), PhantomData))
+    }
+}
@@ -0,0 +1,547 @@
+use std::collections::{BTreeSet, HashMap};
+
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use keccak_hash::keccak;
+use libmdbx::orm::{Transaction, TransactionKind, RO, RW};
+use reec_core::types::{AccountDiff, AccountInfo, BlockNumber, StateDiff};
+
+use crate::{read_chain_data_in, rlp::Rlp, tables::*, trie::update_state_trie, Store};
+
+impl Store {
+    pub fn get_account_info(&self, address: Address) -> anyhow::Result<Option<AccountInfo>> {
+        let tx = self.db.begin_read()?;
+        Ok(tx
+            .get::<AccountInfos>(address.0)?
+            .map(|a| a.to())
+            .transpose()?)
+    }
+
+    /// Returns the value of a storage slot, zero if it was never set.
+    pub fn get_storage_at(&self, address: Address, slot: H256) -> anyhow::Result<U256> {
+        let tx = self.db.begin_read()?;
+        Ok(read_slot(&tx, address, slot)?
+            .map(|v| U256::from_big_endian(&v))
+            .unwrap_or_default())
+    }
+
+    pub fn get_account_code(&self, code_hash: H256) -> anyhow::Result<Option<Bytes>> {
+        let tx = self.db.begin_read()?;
+        Ok(tx.get::<AccountCodes>(code_hash.0)?.map(Bytes::from))
+    }
+
+    pub fn get_trie_node(&self, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        let tx = self.db.begin_read()?;
+        tx.get::<TrieNodes>(key.to_vec())
+    }
+
+    /// Persists the state produced by `block_number` along with the trie nodes it
+    /// changes, recording the previous value of everything it overwrites in the change
+    /// sets so the block can be unwound. Returns the new state root.
+    ///
+    /// Must be called once per block, in block order.
+    pub fn write_state(&self, block_number: BlockNumber, diff: &StateDiff) -> anyhow::Result<H256> {
+        let tx = self.db.begin_readwrite()?;
+        let root = write_state_in(&tx, block_number, diff)?;
+        tx.commit()?;
+        Ok(root)
+    }
+
+    /// Reverts the state written by `block_number` using its change sets. Only the
+    /// most recently written block can be unwound, and only while its change sets have
+    /// not been pruned.
+    pub fn unwind_state(&self, block_number: BlockNumber) -> anyhow::Result<()> {
+        if block_number < self.earliest_state_block()? {
+            anyhow::bail!("state changes of block {block_number} have been pruned");
+        }
+        let tx = self.db.begin_readwrite()?;
+
+        let changes = tx
+            .cursor::<TrieChangeSets>()?
+            .walk_key(block_number, None)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for change in changes {
+            let key_len = u16::from_be_bytes([change[0], change[1]]) as usize;
+            let key = change[2..2 + key_len].to_vec();
+            let prior = &change[2 + key_len..];
+            if prior.is_empty() {
+                tx.delete::<TrieNodes>(key, None)?;
+            } else {
+                tx.upsert::<TrieNodes>(key, prior.to_vec())?;
+            }
+        }
+
+        let changes = tx
+            .cursor::<StorageChangeSets>()?
+            .walk_key(block_number, None)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for change in changes {
+            let address = Address::from_slice(&change[..20]);
+            let slot = H256::from_slice(&change[20..52]);
+            let value = U256::from_big_endian(&change[52..84]);
+            let current = read_slot(&tx, address, slot)?;
+            write_slot(&tx, address, slot, current, value)?;
+        }
+
+        let changes = tx
+            .cursor::<AccountChangeSets>()?
+            .walk_key(block_number, None)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for change in changes {
+            let key: [u8; 20] = change[..20].try_into()?;
+            if change.len() == 20 {
+                tx.delete::<AccountInfos>(key, None)?;
+            } else {
+                tx.upsert::<AccountInfos>(key, Rlp::from_bytes(change[20..].to_vec()))?;
+            }
+        }
+
+        tx.delete::<TrieChangeSets>(block_number, None)?;
+        tx.delete::<StorageChangeSets>(block_number, None)?;
+        tx.delete::<AccountChangeSets>(block_number, None)?;
+        tx.commit()
+    }
+}
+
+/// The state as it was after an earlier block: the head state with the changes of the
+/// blocks after it undone from their change sets. Reads go through a single database
+/// transaction, so blocks written in the meantime do not show.
+pub struct StateAt<'a> {
+    tx: Transaction<'a, RO>,
+    /// Values from before the first change made after the block.
+    accounts: HashMap<Address, Option<AccountInfo>>,
+    storage: HashMap<(Address, H256), U256>,
+}
+
+impl Store {
+    /// The state after block `number`, which must not be ahead of the head, nor so far
+    /// behind it that the state changes since have been pruned.
+    pub fn state_at(&self, number: BlockNumber) -> anyhow::Result<StateAt<'_>> {
+        let tx = self.db.begin_read()?;
+        let latest =
+            read_chain_data_in(&tx, ChainDataIndex::LatestBlockNumber)?.unwrap_or_default();
+        if number > latest {
+            anyhow::bail!("no state for block {number}, the head is {latest}");
+        }
+        if number < latest && number + 1 < self.earliest_state_block()? {
+            anyhow::bail!("state of block {number} has been pruned");
+        }
+
+        let mut accounts = HashMap::new();
+        let mut storage = HashMap::new();
+        // The earliest change after the block holds the value the block left
+        for block_number in number + 1..=latest {
+            for change in tx
+                .cursor::<AccountChangeSets>()?
+                .walk_key(block_number, None)
+            {
+                let change = change?;
+                let address = Address::from_slice(&change[..20]);
+                let prior = match change.len() {
+                    20 => None,
+                    _ => Some(Rlp::<AccountInfo>::from_bytes(change[20..].to_vec()).to()?),
+                };
+                accounts.entry(address).or_insert(prior);
+            }
+            for change in tx
+                .cursor::<StorageChangeSets>()?
+                .walk_key(block_number, None)
+            {
+                let change = change?;
+                let address = Address::from_slice(&change[..20]);
+                let slot = H256::from_slice(&change[20..52]);
+                storage
+                    .entry((address, slot))
+                    .or_insert(U256::from_big_endian(&change[52..84]));
+            }
+        }
+        Ok(StateAt {
+            tx,
+            accounts,
+            storage,
+        })
+    }
+}
+
+impl evm::Database for StateAt<'_> {
+    fn account(&self, address: Address) -> anyhow::Result<Option<AccountInfo>> {
+        if let Some(account) = self.accounts.get(&address) {
+            return Ok(account.clone());
+        }
+        Ok(self
+            .tx
+            .get::<AccountInfos>(address.0)?
+            .map(|a| a.to())
+            .transpose()?)
+    }
+
+    fn storage(&self, address: Address, slot: H256) -> anyhow::Result<U256> {
+        if let Some(value) = self.storage.get(&(address, slot)) {
+            return Ok(*value);
+        }
+        Ok(read_slot(&self.tx, address, slot)?
+            .map(|v| U256::from_big_endian(&v))
+            .unwrap_or_default())
+    }
+
+    fn code(&self, code_hash: H256) -> anyhow::Result<Bytes> {
+        Ok(self
+            .tx
+            .get::<AccountCodes>(code_hash.0)?
+            .map(Bytes::from)
+            .unwrap_or_default())
+    }
+
+    fn block_hash(&self, number: BlockNumber) -> anyhow::Result<Option<H256>> {
+        Ok(self.tx.get::<CanonicalHashes>(number)?.map(H256))
+    }
+
+    fn state_root(&self, diff: &StateDiff) -> anyhow::Result<H256> {
+        // The head tries with the changes since the block undone, then `diff` on top
+        let mut state = StateDiff::new();
+        let changed = self
+            .accounts
+            .keys()
+            .chain(self.storage.keys().map(|(a, _)| a));
+        for address in changed {
+            if !state.contains_key(address) {
+                let info = self.account(*address)?;
+                state.insert(
+                    *address,
+                    AccountDiff {
+                        info,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+        for ((address, slot), value) in &self.storage {
+            state
+                .entry(*address)
+                .or_default()
+                .storage
+                .insert(*slot, *value);
+        }
+        for (address, diff) in diff {
+            let account = state.entry(*address).or_default();
+            account.info = diff.info.clone();
+            if diff.storage_cleared {
+                account.storage_cleared = true;
+                account.storage.clear();
+            }
+            account.storage.extend(&diff.storage);
+        }
+        Ok(update_state_trie(&self.tx, &state)?.0)
+    }
+}
+
+/// Blocks execute on top of the head state as the store holds it.
+impl evm::Database for Store {
+    fn account(&self, address: Address) -> anyhow::Result<Option<AccountInfo>> {
+        self.get_account_info(address)
+    }
+
+    fn storage(&self, address: Address, slot: H256) -> anyhow::Result<U256> {
+        self.get_storage_at(address, slot)
+    }
+
+    fn code(&self, code_hash: H256) -> anyhow::Result<Bytes> {
+        Ok(self.get_account_code(code_hash)?.unwrap_or_default())
+    }
+
+    fn block_hash(&self, number: BlockNumber) -> anyhow::Result<Option<H256>> {
+        self.get_canonical_block_hash(number)
+    }
+
+    fn state_root(&self, diff: &StateDiff) -> anyhow::Result<H256> {
+        let tx = self.db.begin_read()?;
+        Ok(update_state_trie(&tx, diff)?.0)
+    }
+}
+
+/// Writes the state of `block_number` and its change sets in `tx`, returning the new
+/// state root.
+pub(crate) fn write_state_in(
+    tx: &Transaction<'_, RW>,
+    block_number: BlockNumber,
+    diff: &StateDiff,
+) -> anyhow::Result<H256> {
+    let (root, trie_updates) = update_state_trie(tx, diff)?;
+    for (address, account) in diff {
+        let key = address.0;
+        let mut change = key.to_vec();
+        if let Some(prior) = tx.get::<AccountInfos>(key)? {
+            change.extend_from_slice(prior.bytes());
+        }
+        tx.upsert::<AccountChangeSets>(block_number, change)?;
+        match &account.info {
+            Some(info) => tx.upsert::<AccountInfos>(key, info.clone().into())?,
+            None => {
+                tx.delete::<AccountInfos>(key, None)?;
+            }
+        }
+
+        let mut recorded = BTreeSet::new();
+        if account.storage_cleared || account.info.is_none() {
+            let slots = tx
+                .cursor::<AccountStorages>()?
+                .walk_key(key, None)
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            for (slot, value) in slots {
+                record_storage_change(tx, block_number, address, slot, value)?;
+                recorded.insert(slot);
+            }
+            tx.delete::<AccountStorages>(key, None)?;
+        }
+        for (slot, value) in &account.storage {
+            let prior = read_slot(tx, *address, *slot)?;
+            if recorded.insert(slot.0) {
+                record_storage_change(
+                    tx,
+                    block_number,
+                    address,
+                    slot.0,
+                    prior.unwrap_or_default(),
+                )?;
+            }
+            write_slot(tx, *address, *slot, prior, *value)?;
+        }
+
+        if let Some(code) = &account.code {
+            tx.upsert::<AccountCodes>(keccak(code).0, code.to_vec())?;
+        }
+    }
+
+    for (key, node) in trie_updates {
+        let prior = tx.get::<TrieNodes>(key.clone())?;
+        let mut change = (key.len() as u16).to_be_bytes().to_vec();
+        change.extend_from_slice(&key);
+        change.extend_from_slice(prior.as_deref().unwrap_or_default());
+        tx.upsert::<TrieChangeSets>(block_number, change)?;
+        match node {
+            Some(node) => tx.upsert::<TrieNodes>(key, node)?,
+            None => {
+                tx.delete::<TrieNodes>(key, None)?;
+            }
+        }
+    }
+    Ok(root)
+}
+
+fn read_slot<K: TransactionKind>(
+    tx: &Transaction<'_, K>,
+    address: Address,
+    slot: H256,
+) -> anyhow::Result<Option<[u8; 32]>> {
+    let mut cursor = tx.cursor::<AccountStorages>()?;
+    Ok(cursor
+        .seek_value(address.0, slot.0)?
+        .filter(|(found, _)| *found == slot.0)
+        .map(|(_, value)| value))
+}
+
+fn write_slot(
+    tx: &Transaction<'_, RW>,
+    address: Address,
+    slot: H256,
+    prior: Option<[u8; 32]>,
+    value: U256,
+) -> anyhow::Result<()> {
+    if let Some(prior) = prior {
+        tx.delete::<AccountStorages>(address.0, Some((slot.0, prior)))?;
+    }
+    if !value.is_zero() {
+        let mut bytes = [0; 32];
+        value.to_big_endian(&mut bytes);
+        tx.upsert::<AccountStorages>(address.0, (slot.0, bytes))?;
+    }
+    Ok(())
+}
+
+fn record_storage_change(
+    tx: &Transaction<'_, RW>,
+    block_number: BlockNumber,
+    address: &Address,
+    slot: [u8; 32],
+    value: [u8; 32],
+) -> anyhow::Result<()> {
+    let mut change = address.0.to_vec();
+    change.extend_from_slice(&slot);
+    change.extend_from_slice(&value);
+    tx.upsert::<StorageChangeSets>(block_number, change)
+}
+
+#[cfg(test)]
+mod tests {
+    use evm::Database;
+    use reec_core::types::compute_state_root;
+
+    use super::*;
+
+    fn account(nonce: u64) -> AccountInfo {
+        AccountInfo {
+            nonce,
+            balance: U256::from(nonce * 100),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unwinds_state_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let alice = Address::repeat_byte(0xa1);
+        let bob = Address::repeat_byte(0xb0);
+        let slot = H256::repeat_byte(1);
+
+        let mut diff = StateDiff::new();
+        diff.insert(
+            alice,
+            AccountDiff {
+                info: Some(account(1)),
+                storage: [(slot, U256::from(5))].into(),
+                code: Some(Bytes::from_static(&[0x60, 0x00])),
+                ..Default::default()
+            },
+        );
+        let root = store.write_state(1, &diff).unwrap();
+
+        let mut diff = StateDiff::new();
+        diff.insert(
+            alice,
+            AccountDiff {
+                info: Some(account(2)),
+                storage: [(slot, U256::from(7)), (H256::repeat_byte(2), U256::one())].into(),
+                ..Default::default()
+            },
+        );
+        diff.insert(
+            bob,
+            AccountDiff {
+                info: Some(account(9)),
+                ..Default::default()
+            },
+        );
+        store.write_state(2, &diff).unwrap();
+        assert_eq!(store.get_account_info(alice).unwrap(), Some(account(2)));
+        assert_eq!(store.get_storage_at(alice, slot).unwrap(), U256::from(7));
+
+        store.unwind_state(2).unwrap();
+        assert_eq!(store.get_account_info(alice).unwrap(), Some(account(1)));
+        assert_eq!(store.get_account_info(bob).unwrap(), None);
+        assert_eq!(store.get_storage_at(alice, slot).unwrap(), U256::from(5));
+        assert_eq!(
+            store.get_storage_at(alice, H256::repeat_byte(2)).unwrap(),
+            U256::zero()
+        );
+        assert_eq!(store.state_root(&StateDiff::new()).unwrap(), root);
+        assert!(store
+            .get_account_code(keccak([0x60, 0x00]))
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn unwinds_cleared_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let address = Address::repeat_byte(0xc0);
+        let slots: Vec<_> = (1..4u8).map(H256::repeat_byte).collect();
+
+        let mut diff = StateDiff::new();
+        diff.insert(
+            address,
+            AccountDiff {
+                info: Some(account(1)),
+                storage: slots.iter().map(|s| (*s, U256::from(s.0[0]))).collect(),
+                ..Default::default()
+            },
+        );
+        store.write_state(1, &diff).unwrap();
+
+        let mut diff = StateDiff::new();
+        diff.insert(
+            address,
+            AccountDiff {
+                info: Some(account(1)),
+                storage_cleared: true,
+                storage: [(slots[0], U256::from(42))].into(),
+                ..Default::default()
+            },
+        );
+        store.write_state(2, &diff).unwrap();
+        assert_eq!(
+            store.get_storage_at(address, slots[0]).unwrap(),
+            U256::from(42)
+        );
+        assert_eq!(
+            store.get_storage_at(address, slots[1]).unwrap(),
+            U256::zero()
+        );
+
+        store.unwind_state(2).unwrap();
+        for slot in &slots {
+            assert_eq!(
+                store.get_storage_at(address, *slot).unwrap(),
+                U256::from(slot.0[0])
+            );
+        }
+    }
+
+    #[test]
+    fn reads_state_of_earlier_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let (alice, bob) = (Address::repeat_byte(0xa1), Address::repeat_byte(0xb0));
+        let slot = H256::repeat_byte(1);
+        for number in 1..4 {
+            let mut diff = StateDiff::new();
+            diff.insert(
+                alice,
+                AccountDiff {
+                    info: Some(account(number)),
+                    storage: [(slot, U256::from(number))].into(),
+                    ..Default::default()
+                },
+            );
+            if number == 3 {
+                diff.insert(
+                    bob,
+                    AccountDiff {
+                        info: Some(account(9)),
+                        ..Default::default()
+                    },
+                );
+            }
+            store.write_state(number, &diff).unwrap();
+        }
+        store.update_latest_block_number(3).unwrap();
+
+        let root = |accounts: &[(Address, u64, u64)]| {
+            let state = accounts
+                .iter()
+                .map(|&(address, nonce, value)| {
+                    let storage = [(slot, U256::from(value))].into();
+                    (address, (account(nonce), storage))
+                })
+                .collect();
+            compute_state_root(state, &StateDiff::new())
+        };
+        let state = store.state_at(1).unwrap();
+        assert_eq!(state.account(alice).unwrap(), Some(account(1)));
+        assert_eq!(state.storage(alice, slot).unwrap(), U256::one());
+        assert_eq!(state.account(bob).unwrap(), None);
+        assert_eq!(
+            state.state_root(&StateDiff::new()).unwrap(),
+            root(&[(alice, 1, 1)])
+        );
+        assert_eq!(
+            store.state_root(&StateDiff::new()).unwrap(),
+            root(&[(alice, 3, 3), (bob, 9, 0)])
+        );
+        let state = store.state_at(3).unwrap();
+        assert_eq!(state.account(bob).unwrap(), Some(account(9)));
+        assert_eq!(state.storage(alice, slot).unwrap(), U256::from(3));
+        assert!(store.state_at(4).is_err());
+    }
+}
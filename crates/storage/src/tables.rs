@@ -0,0 +1,141 @@
+use anyhow::anyhow;
+use libmdbx::{
+    dupsort,
+    orm::{table, DatabaseChart, Decodable, Encodable},
+    table_info,
+};
+use reec_core::types::{AccountInfo, BlockBody, BlockHeader, BlockNumber, Receipt};
+
+use crate::rlp::Rlp;
+
+table!(
+    /// Block headers by block hash.
+    ( Headers ) [u8; 32] => Rlp<BlockHeader>
+);
+table!(
+    /// Block bodies by block hash, until they are moved to the freezer.
+    ( Bodies ) [u8; 32] => Rlp<BlockBody>
+);
+table!(
+    /// Receipts of every transaction in a block by block hash, until they are moved
+    /// to the freezer.
+    ( Receipts ) [u8; 32] => Rlp<Vec<Receipt>>
+);
+table!(
+    /// Canonical block hashes by block number.
+    ( CanonicalHashes ) BlockNumber => [u8; 32]
+);
+table!(
+    /// Block numbers by block hash, for every stored header.
+    ( HeaderNumbers ) [u8; 32] => BlockNumber
+);
+table!(
+    /// Block number and position within the block of every canonical transaction, by
+    /// transaction hash.
+    ( TransactionLocations ) [u8; 32] => (BlockNumber, u64)
+);
+dupsort!(
+    /// Canonical blocks with a log emitted by each contract address.
+    ( LogAddressBlocks ) [u8; 20] => BlockNumber
+);
+dupsort!(
+    /// Canonical blocks with a log carrying each topic, at any position.
+    ( LogTopicBlocks ) [u8; 32] => BlockNumber
+);
+table!(
+    /// Chain metadata such as the latest and finalized block numbers.
+    ( ChainData ) ChainDataIndex => Vec<u8>
+);
+table!(
+    /// Latest state of every account by address.
+    ( AccountInfos ) [u8; 20] => Rlp<AccountInfo>
+);
+dupsort!(
+    /// Latest storage of every account: address => slot ++ value.
+    ( AccountStorages ) [u8; 20] => ([u8; 32], [u8; 32]) [[u8; 32]]
+);
+table!(
+    /// Contract code by code hash.
+    ( AccountCodes ) [u8; 32] => Vec<u8>
+);
+table!(
+    /// Latest trie nodes by path: owner (zero for the account trie, hashed address
+    /// for storage tries) ++ nibbles.
+    ( TrieNodes ) Vec<u8> => Vec<u8>
+);
+dupsort!(
+    /// Accounts changed by each block with their value before it: address ++ RLP
+    /// account, empty if the account did not exist.
+    ( AccountChangeSets ) BlockNumber => Vec<u8>
+);
+dupsort!(
+    /// Storage slots changed by each block with their value before it: address ++
+    /// slot ++ value.
+    ( StorageChangeSets ) BlockNumber => Vec<u8>
+);
+dupsort!(
+    /// Trie nodes changed by each block with their value before it: key length
+    /// (u16) ++ key ++ node, empty if the node did not exist.
+    ( TrieChangeSets ) BlockNumber => Vec<u8>
+);
+
+pub(crate) fn chart() -> DatabaseChart {
+    [
+        table_info!(Headers),
+        table_info!(Bodies),
+        table_info!(Receipts),
+        table_info!(CanonicalHashes),
+        table_info!(HeaderNumbers),
+        table_info!(TransactionLocations),
+        table_info!(LogAddressBlocks),
+        table_info!(LogTopicBlocks),
+        table_info!(ChainData),
+        table_info!(AccountInfos),
+        table_info!(AccountStorages),
+        table_info!(AccountCodes),
+        table_info!(TrieNodes),
+        table_info!(AccountChangeSets),
+        table_info!(StorageChangeSets),
+        table_info!(TrieChangeSets),
+    ]
+    .into_iter()
+    .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ChainDataIndex {
+    LatestBlockNumber = 0,
+    FinalizedBlockNumber = 1,
+    SafeBlockNumber = 2,
+    /// Bodies and receipts below this block have been pruned.
+    EarliestHistoryBlock = 3,
+    /// State change sets below this block have been pruned.
+    EarliestStateBlock = 4,
+    PruneMode = 5,
+    /// Layout version of the database, see [`crate::migrations`].
+    SchemaVersion = 6,
+}
+
+impl Encodable for ChainDataIndex {
+    type Encoded = [u8; 1];
+
+    fn encode(self) -> Self::Encoded {
+        [self as u8]
+    }
+}
+
+impl Decodable for ChainDataIndex {
+    fn decode(b: &[u8]) -> anyhow::Result<Self> {
+        match b {
+            [0] => Ok(ChainDataIndex::LatestBlockNumber),
+            [1] => Ok(ChainDataIndex::FinalizedBlockNumber),
+            [2] => Ok(ChainDataIndex::SafeBlockNumber),
+            [3] => Ok(ChainDataIndex::EarliestHistoryBlock),
+            [4] => Ok(ChainDataIndex::EarliestStateBlock),
+            [5] => Ok(ChainDataIndex::PruneMode),
+            [6] => Ok(ChainDataIndex::SchemaVersion),
+            _ => Err(anyhow!("invalid chain data index {b:?}")),
+        }
+    }
+}
@@ -0,0 +1,609 @@
+//! The state trie as `TrieNodes` keeps it: the account trie and every storage trie,
+//! stored node by node under their owner ++ nibble path (one nibble per byte). The
+//! account trie is owned by the zero hash and each storage trie by the hash of its
+//! account address. Roots are always stored, other nodes only if they are referenced
+//! by hash, as nodes shorter than a hash are embedded in their parent.
+//!
+//! Blocks update the tries in place, reading only the nodes on the paths they change.
+
+use std::{collections::BTreeMap, mem};
+
+use anyhow::{anyhow, bail};
+use ethereum_types::H256;
+use keccak_hash::keccak;
+use libmdbx::orm::{Transaction, TransactionKind};
+use reec_core::{
+    rlp::{
+        decode::{decode_bytes, decode_rlp_item, get_item_with_prefix},
+        encode::RLPEncode,
+        error::RLPDecodeError,
+        structs::Encoder,
+    },
+    trie::{compact_path, encode_list, nibbles, EMPTY_TRIE_ROOT},
+    types::{AccountInfo, StateDiff},
+};
+
+use crate::tables::TrieNodes;
+
+/// A trie node write: the node's owner ++ path key and its new encoding, `None` to
+/// delete it.
+pub(crate) type TrieUpdate = (Vec<u8>, Option<Vec<u8>>);
+
+/// How a parent node refers to a child: by hash for nodes stored on their own, or by
+/// embedding the encoding of nodes shorter than a hash.
+pub(crate) enum NodeRef {
+    Hash(H256),
+    Embedded(Vec<u8>),
+}
+
+/// A decoded trie node. State trie keys all have the same length, so branches never
+/// hold a value.
+pub(crate) enum Node {
+    Branch(Vec<(u8, NodeRef)>),
+    Extension(Vec<u8>, NodeRef),
+    Leaf(Vec<u8>, Vec<u8>),
+}
+
+/// Root of the state with `diff` applied on top of the stored tries, and the node
+/// writes that bring the stored tries to it.
+pub(crate) fn update_state_trie<K: TransactionKind>(
+    tx: &Transaction<'_, K>,
+    diff: &StateDiff,
+) -> anyhow::Result<(H256, Vec<TrieUpdate>)> {
+    let mut updates = Vec::new();
+    let mut accounts = Trie::open(tx, H256::zero())?;
+    for (address, account) in diff {
+        let owner = keccak(address);
+        let Some(info) = &account.info else {
+            updates.extend(Trie::cleared(tx, owner)?.commit()?.1);
+            accounts.remove(&nibbles(owner.as_bytes()))?;
+            continue;
+        };
+        let mut storage = match account.storage_cleared {
+            true => Trie::cleared(tx, owner)?,
+            false => Trie::open(tx, owner)?,
+        };
+        for (slot, value) in &account.storage {
+            let key = nibbles(keccak(slot).as_bytes());
+            match value.is_zero() {
+                true => storage.remove(&key)?,
+                false => storage.insert(&key, value.encode_to_vec())?,
+            }
+        }
+        let (storage_root, storage_updates) = storage.commit()?;
+        updates.extend(storage_updates);
+        accounts.insert(&nibbles(owner.as_bytes()), account_leaf(info, storage_root))?;
+    }
+    let (root, account_updates) = accounts.commit()?;
+    updates.extend(account_updates);
+    Ok((root, updates))
+}
+
+/// The account as the state trie holds it.
+fn account_leaf(info: &AccountInfo, storage_root: H256) -> Vec<u8> {
+    let mut buf = Vec::new();
+    Encoder::new(&mut buf)
+        .encode_field(&info.nonce)
+        .encode_field(&info.balance)
+        .encode_field(&storage_root)
+        .encode_field(&info.code_hash)
+        .finish();
+    buf
+}
+
+/// A position in a trie being updated: empty, a node as stored, or a node read into
+/// memory to be changed.
+#[derive(Default)]
+enum Slot {
+    #[default]
+    Empty,
+    Stored(NodeRef),
+    Open(Box<OpenNode>),
+}
+
+enum OpenNode {
+    Branch(Box<[Slot; 16]>),
+    Extension(Vec<u8>, Slot),
+    Leaf(Vec<u8>, Vec<u8>),
+}
+
+/// One trie of the state being updated. Only the nodes on the paths of changed keys
+/// are read, and only a node that moves or changes is written, so the other stored
+/// nodes keep their paths: a leaf or extension only moves once read, and branches
+/// never do.
+struct Trie<'a, 'tx, K: TransactionKind> {
+    tx: &'a Transaction<'tx, K>,
+    owner: H256,
+    root: Slot,
+    /// Encodings of the stored nodes read, by path, to tell which were changed.
+    read: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl<'a, 'tx, K: TransactionKind> Trie<'a, 'tx, K> {
+    /// The trie stored under `owner`, empty if it has no root.
+    fn open(tx: &'a Transaction<'tx, K>, owner: H256) -> anyhow::Result<Self> {
+        let mut trie = Trie {
+            tx,
+            owner,
+            root: Slot::Empty,
+            read: BTreeMap::new(),
+        };
+        if let Some(node) = tx.get::<TrieNodes>(owner.as_bytes().to_vec())? {
+            trie.root = Slot::Open(Box::new(decode_open(&node)?));
+            trie.read.insert(Vec::new(), node);
+        }
+        Ok(trie)
+    }
+
+    /// An empty trie replacing the one stored under `owner`, whose nodes are all
+    /// deleted when it is committed.
+    fn cleared(tx: &'a Transaction<'tx, K>, owner: H256) -> anyhow::Result<Self> {
+        let mut read = BTreeMap::new();
+        for entry in tx
+            .cursor::<TrieNodes>()?
+            .walk(Some(owner.as_bytes().to_vec()))
+        {
+            let (key, node) = entry?;
+            let Some(path) = key.strip_prefix(owner.as_bytes()) else {
+                break;
+            };
+            read.insert(path.to_vec(), node);
+        }
+        Ok(Trie {
+            tx,
+            owner,
+            root: Slot::Empty,
+            read,
+        })
+    }
+
+    fn insert(&mut self, key: &[u8], value: Vec<u8>) -> anyhow::Result<()> {
+        let mut root = mem::replace(&mut self.root, Slot::Empty);
+        let result = self.insert_at(&mut root, Vec::new(), key, value);
+        self.root = root;
+        result
+    }
+
+    fn remove(&mut self, key: &[u8]) -> anyhow::Result<()> {
+        let mut root = mem::replace(&mut self.root, Slot::Empty);
+        let result = self.remove_at(&mut root, Vec::new(), key);
+        self.root = root;
+        result
+    }
+
+    /// Inserts `value` under the rest of the `key` into the subtrie at `path`.
+    fn insert_at(
+        &mut self,
+        slot: &mut Slot,
+        path: Vec<u8>,
+        key: &[u8],
+        value: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        self.load(slot, &path)?;
+        let Slot::Open(node) = slot else {
+            *slot = leaf(key.to_vec(), value);
+            return Ok(());
+        };
+        match node.as_mut() {
+            OpenNode::Branch(children) => {
+                let nibble = key[0] as usize;
+                let path = [path.as_slice(), &key[..1]].concat();
+                self.insert_at(&mut children[nibble], path, &key[1..], value)
+            }
+            OpenNode::Extension(prefix, child) if key.starts_with(prefix) => {
+                let path = [path.as_slice(), prefix].concat();
+                let key = &key[prefix.len()..];
+                self.insert_at(child, path, key, value)
+            }
+            OpenNode::Leaf(rest, stored) if *rest == key => {
+                *stored = value;
+                Ok(())
+            }
+            OpenNode::Extension(prefix, _) | OpenNode::Leaf(prefix, _) => {
+                let shared = shared_prefix(prefix, key);
+                let mut children: [Slot; 16] = Default::default();
+                let existing = match mem::replace(slot, Slot::Empty) {
+                    Slot::Open(node) => *node,
+                    _ => unreachable!(),
+                };
+                let (nibble, existing) = match existing {
+                    OpenNode::Leaf(rest, stored) => {
+                        (rest[shared], leaf(rest[shared + 1..].to_vec(), stored))
+                    }
+                    OpenNode::Extension(prefix, child) => {
+                        let rest = prefix[shared + 1..].to_vec();
+                        (prefix[shared], extension(rest, child))
+                    }
+                    OpenNode::Branch(_) => unreachable!(),
+                };
+                children[nibble as usize] = existing;
+                children[key[shared] as usize] = leaf(key[shared + 1..].to_vec(), value);
+                let branch = Slot::Open(Box::new(OpenNode::Branch(Box::new(children))));
+                *slot = extension(key[..shared].to_vec(), branch);
+                Ok(())
+            }
+        }
+    }
+
+    /// Removes the rest of the `key` from the subtrie at `path`, collapsing branches
+    /// left with a single child.
+    fn remove_at(&mut self, slot: &mut Slot, path: Vec<u8>, key: &[u8]) -> anyhow::Result<()> {
+        self.load(slot, &path)?;
+        let Slot::Open(node) = slot else {
+            return Ok(());
+        };
+        match node.as_mut() {
+            OpenNode::Leaf(rest, _) => {
+                if *rest == key {
+                    *slot = Slot::Empty;
+                }
+                Ok(())
+            }
+            OpenNode::Extension(prefix, child) => {
+                if !key.starts_with(prefix) {
+                    return Ok(());
+                }
+                let child_path = [path.as_slice(), prefix].concat();
+                self.remove_at(child, child_path, &key[prefix.len()..])?;
+                let prefix = mem::take(prefix);
+                let child = mem::replace(child, Slot::Empty);
+                *slot = extension(prefix, child);
+                Ok(())
+            }
+            OpenNode::Branch(children) => {
+                let nibble = key[0] as usize;
+                let child_path = [path.as_slice(), &key[..1]].concat();
+                self.remove_at(&mut children[nibble], child_path, &key[1..])?;
+                let mut left = children
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, child)| !matches!(child, Slot::Empty));
+                let (Some((only, _)), None) = (left.next(), left.next()) else {
+                    return Ok(());
+                };
+                let mut child = mem::replace(&mut children[only], Slot::Empty);
+                // The child moves up into this node's place, so it must be read
+                self.load(&mut child, &[path.as_slice(), &[only as u8]].concat())?;
+                *slot = extension(vec![only as u8], child);
+                Ok(())
+            }
+        }
+    }
+
+    /// Reads a stored node into memory, remembering its encoding if it was stored on
+    /// its own.
+    fn load(&mut self, slot: &mut Slot, path: &[u8]) -> anyhow::Result<()> {
+        let node = match slot {
+            Slot::Stored(NodeRef::Embedded(node)) => mem::take(node),
+            Slot::Stored(NodeRef::Hash(hash)) => {
+                let key = [self.owner.as_bytes(), path].concat();
+                let node = self.tx.get::<TrieNodes>(key)?.ok_or_else(|| {
+                    anyhow!("missing trie node at {}", location(self.owner, path))
+                })?;
+                if keccak(&node) != *hash {
+                    bail!(
+                        "trie node at {} does not match its hash",
+                        location(self.owner, path)
+                    );
+                }
+                self.read.insert(path.to_vec(), node.clone());
+                node
+            }
+            Slot::Empty | Slot::Open(_) => return Ok(()),
+        };
+        *slot = Slot::Open(Box::new(decode_open(&node)?));
+        Ok(())
+    }
+
+    /// Root of the updated trie, and the writes that store it in place of the nodes
+    /// read.
+    fn commit(self) -> anyhow::Result<(H256, Vec<TrieUpdate>)> {
+        let mut written = BTreeMap::new();
+        let root = match &self.root {
+            Slot::Empty => EMPTY_TRIE_ROOT,
+            Slot::Stored(_) => unreachable!("the root is read on open"),
+            Slot::Open(node) => {
+                let node = encode_open(node, &mut Vec::new(), &mut written);
+                let root = keccak(&node);
+                written.insert(Vec::new(), node);
+                root
+            }
+        };
+        let key = |path: &[u8]| [self.owner.as_bytes(), path].concat();
+        let mut updates: Vec<TrieUpdate> = self
+            .read
+            .keys()
+            .filter(|path| !written.contains_key(*path))
+            .map(|path| (key(path), None))
+            .collect();
+        updates.extend(
+            written
+                .into_iter()
+                .filter(|(path, node)| self.read.get(path) != Some(node))
+                .map(|(path, node)| (key(&path), Some(node))),
+        );
+        Ok((root, updates))
+    }
+}
+
+fn leaf(rest: Vec<u8>, value: Vec<u8>) -> Slot {
+    Slot::Open(Box::new(OpenNode::Leaf(rest, value)))
+}
+
+/// An extension of `child` by `prefix`, merged into the child if it is a leaf or an
+/// extension itself. The child must have been read.
+fn extension(prefix: Vec<u8>, child: Slot) -> Slot {
+    if prefix.is_empty() {
+        return child;
+    }
+    let Slot::Open(node) = child else {
+        return match child {
+            Slot::Empty => Slot::Empty,
+            child => Slot::Open(Box::new(OpenNode::Extension(prefix, child))),
+        };
+    };
+    Slot::Open(Box::new(match *node {
+        OpenNode::Leaf(rest, value) => OpenNode::Leaf([prefix, rest].concat(), value),
+        OpenNode::Extension(rest, child) => OpenNode::Extension([prefix, rest].concat(), child),
+        branch => OpenNode::Extension(prefix, Slot::Open(Box::new(branch))),
+    }))
+}
+
+fn shared_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+/// Encodes the node at `path`, adding it and its changed descendants that are
+/// referenced by hash to `written`.
+fn encode_open(
+    node: &OpenNode,
+    path: &mut Vec<u8>,
+    written: &mut BTreeMap<Vec<u8>, Vec<u8>>,
+) -> Vec<u8> {
+    match node {
+        OpenNode::Leaf(rest, value) => encode_list(&[
+            compact_path(rest, true).as_slice().encode_to_vec(),
+            value.as_slice().encode_to_vec(),
+        ]),
+        OpenNode::Extension(prefix, child) => {
+            let len = path.len();
+            path.extend_from_slice(prefix);
+            let child = reference(child, path, written);
+            path.truncate(len);
+            encode_list(&[
+                compact_path(prefix, false).as_slice().encode_to_vec(),
+                child,
+            ])
+        }
+        OpenNode::Branch(children) => {
+            let mut items = Vec::with_capacity(17);
+            for (nibble, child) in children.iter().enumerate() {
+                path.push(nibble as u8);
+                items.push(reference(child, path, written));
+                path.pop();
+            }
+            items.push([0x80].to_vec());
+            encode_list(&items)
+        }
+    }
+}
+
+/// How the parent of the node in `slot`, at `path`, refers to it.
+fn reference(slot: &Slot, path: &mut Vec<u8>, written: &mut BTreeMap<Vec<u8>, Vec<u8>>) -> Vec<u8> {
+    match slot {
+        Slot::Empty => [0x80].to_vec(),
+        Slot::Stored(NodeRef::Hash(hash)) => hash.encode_to_vec(),
+        Slot::Stored(NodeRef::Embedded(node)) => node.clone(),
+        Slot::Open(node) => {
+            let node = encode_open(node, path, written);
+            if node.len() < 32 {
+                return node;
+            }
+            let hash = keccak(&node);
+            written.insert(path.clone(), node);
+            hash.encode_to_vec()
+        }
+    }
+}
+
+fn decode_open(node: &[u8]) -> anyhow::Result<OpenNode> {
+    Ok(match decode_node(node)? {
+        Node::Branch(refs) => {
+            let mut children: [Slot; 16] = Default::default();
+            for (nibble, child) in refs {
+                children[nibble as usize] = Slot::Stored(child);
+            }
+            OpenNode::Branch(Box::new(children))
+        }
+        Node::Extension(prefix, child) => OpenNode::Extension(prefix, Slot::Stored(child)),
+        Node::Leaf(rest, value) => OpenNode::Leaf(rest, value),
+    })
+}
+
+/// Human readable position of a node, for reporting problems.
+pub(crate) fn location(owner: H256, path: &[u8]) -> String {
+    let path: String = path.iter().map(|nibble| format!("{nibble:x}")).collect();
+    if owner.is_zero() {
+        format!("account trie path 0x{path}")
+    } else {
+        format!("storage trie {owner:#x} path 0x{path}")
+    }
+}
+
+pub(crate) fn decode_node(node: &[u8]) -> Result<Node, RLPDecodeError> {
+    let (is_list, mut items, _) = decode_rlp_item(node)?;
+    if !is_list {
+        return Err(RLPDecodeError::UnexpectedString);
+    }
+    let mut fields = Vec::new();
+    while !items.is_empty() {
+        let (item, rest) = get_item_with_prefix(items)?;
+        fields.push(item);
+        items = rest;
+    }
+    match fields.as_slice() {
+        [path, child] => {
+            let (path, _) = decode_bytes(path)?;
+            let (&flag, rest) = path.split_first().ok_or(RLPDecodeError::InvalidLength)?;
+            let mut nibbles = Vec::with_capacity(rest.len() * 2 + 1);
+            if flag & 0x10 != 0 {
+                nibbles.push(flag & 0x0f);
+            }
+            nibbles.extend(rest.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]));
+            if flag & 0x20 != 0 {
+                Ok(Node::Leaf(nibbles, decode_bytes(child)?.0.to_vec()))
+            } else {
+                let child = decode_ref(child)?.ok_or(RLPDecodeError::MalformedData)?;
+                Ok(Node::Extension(nibbles, child))
+            }
+        }
+        [children @ .., _value] if children.len() == 16 => {
+            let mut refs = Vec::new();
+            for (nibble, child) in children.iter().enumerate() {
+                if let Some(child) = decode_ref(child)? {
+                    refs.push((nibble as u8, child));
+                }
+            }
+            Ok(Node::Branch(refs))
+        }
+        _ => Err(RLPDecodeError::MalformedData),
+    }
+}
+
+fn decode_ref(item: &[u8]) -> Result<Option<NodeRef>, RLPDecodeError> {
+    let (is_list, payload, _) = decode_rlp_item(item)?;
+    match payload.len() {
+        _ if is_list => Ok(Some(NodeRef::Embedded(item.to_vec()))),
+        0 => Ok(None),
+        32 => Ok(Some(NodeRef::Hash(H256::from_slice(payload)))),
+        _ => Err(RLPDecodeError::MalformedData),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum_types::{Address, U256};
+    use reec_core::types::{compute_state_root, AccountDiff, FullState};
+
+    use super::*;
+    use crate::Store;
+
+    fn stored_nodes(store: &Store) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        let tx = store.db.begin_read().unwrap();
+        tx.cursor::<TrieNodes>()
+            .unwrap()
+            .walk(None)
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    /// Blocks touching a few accounts each, from a fixed pseudo-random sequence:
+    /// creating, updating and deleting accounts, and setting, zeroing and clearing
+    /// storage, enough to split and collapse nodes at every depth the tries reach.
+    fn blocks() -> Vec<StateDiff> {
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move |bound: u64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed % bound
+        };
+        (0..40)
+            .map(|_| {
+                (0..8)
+                    .map(|_| {
+                        let address = Address::from_low_u64_be(next(48));
+                        let mut diff = AccountDiff::default();
+                        if next(10) > 0 {
+                            diff.info = Some(AccountInfo {
+                                nonce: next(1000),
+                                balance: U256::from(next(u64::MAX)),
+                                ..Default::default()
+                            });
+                            diff.storage_cleared = next(10) == 0;
+                            diff.storage = (0..next(6))
+                                .map(|_| (H256::from_low_u64_be(next(24)), U256::from(next(3))))
+                                .collect();
+                        }
+                        (address, diff)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn updates_tries_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let mut state = FullState::new();
+        let mut history = vec![stored_nodes(&store)];
+        for (number, diff) in blocks().iter().enumerate() {
+            let expected = compute_state_root(state.clone(), diff);
+            assert_eq!(store.write_state(number as u64, diff).unwrap(), expected);
+            for (address, diff) in diff {
+                let Some(info) = &diff.info else {
+                    state.remove(address);
+                    continue;
+                };
+                let (account, storage) = state.entry(*address).or_default();
+                *account = info.clone();
+                if diff.storage_cleared {
+                    storage.clear();
+                }
+                storage.extend(&diff.storage);
+                storage.retain(|_, value| !value.is_zero());
+            }
+            history.push(stored_nodes(&store));
+        }
+
+        // The same nodes as the trie built from scratch, with none left behind
+        let scratch = tempfile::tempdir().unwrap();
+        let rebuilt = Store::new(scratch.path()).unwrap();
+        let full: StateDiff = state
+            .into_iter()
+            .map(|(address, (info, storage))| {
+                let diff = AccountDiff {
+                    info: Some(info),
+                    storage,
+                    ..Default::default()
+                };
+                (address, diff)
+            })
+            .collect();
+        rebuilt.write_state(0, &full).unwrap();
+        assert_eq!(stored_nodes(&store), stored_nodes(&rebuilt));
+
+        for number in (0..history.len() - 1).rev() {
+            store.unwind_state(number as u64).unwrap();
+            assert_eq!(stored_nodes(&store), history[number], "block {number}");
+        }
+    }
+
+    #[test]
+    fn reports_missing_nodes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let diff = blocks().remove(0);
+        store.write_state(0, &diff).unwrap();
+        // A node of the account trie below its root
+        let key = stored_nodes(&store)
+            .into_keys()
+            .find(|key| key.len() > 32 && key[..32] == [0; 32])
+            .unwrap();
+        let tx = store.db.begin_readwrite().unwrap();
+        tx.delete::<TrieNodes>(key, None).unwrap();
+        tx.commit().unwrap();
+
+        let mut deleted = diff.clone();
+        for account in deleted.values_mut() {
+            account.info = None;
+        }
+        let err = store.write_state(1, &deleted).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("missing trie node at account trie path 0x"),
+            "{err}"
+        );
+    }
+}
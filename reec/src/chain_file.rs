@@ -0,0 +1,427 @@
+//! Export and import of canonical blocks as a file of concatenated RLP encoded blocks,
+//! the format of `geth export` and `geth import`.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, bail, Context};
+use ef_tests::Genesis;
+use evm::{block::validate_header, execute_block, BlockResult, ChainConfig, Database};
+use reec_core::{
+    rlp::{decode::RLPDecode, encode::RLPEncode},
+    types::{Block, BlockNumber, InvalidBlockError, StateDiff},
+};
+use storage::Store;
+use tracing::info;
+
+/// Imported blocks between progress reports.
+const IMPORT_LOG_INTERVAL: u64 = 1024;
+/// Longest block accepted from a file, far above what the gas limit allows.
+const MAX_BLOCK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Writes the canonical blocks `from..=to` to `path`, replacing the file. Returns how
+/// many blocks were written.
+pub fn export_blocks(
+    store: &Store,
+    path: &Path,
+    from: BlockNumber,
+    to: BlockNumber,
+) -> anyhow::Result<u64> {
+    if from > to {
+        bail!("export range {from}..={to} is empty");
+    }
+    if from < store.earliest_history_block()? {
+        bail!(
+            "blocks before {} have been pruned",
+            store.earliest_history_block()?
+        );
+    }
+    let mut file = BufWriter::new(File::create(path)?);
+    for number in from..=to {
+        let (Some(header), Some(body)) = (
+            store.get_block_header(number)?,
+            store.get_block_body(number)?,
+        ) else {
+            bail!("missing canonical block {number}");
+        };
+        file.write_all(&Block { header, body }.encode_to_vec())?;
+    }
+    file.flush()?;
+    info!("Exported blocks {from} to {to} to {}", path.display());
+    Ok(to - from + 1)
+}
+
+/// Imports the blocks in `path` on top of the chain in `store`, making the last one the
+/// canonical head. Returns how many blocks were added.
+///
+/// Every block must extend the head, either the stored one or the block before it in the
+/// file. Each is checked against its parent and its own header, then executed on the
+/// head state, and only stored once its execution matches what the header commits to,
+/// state root included. A block is stored with its receipts and state in a single
+/// transaction, and blocks already in the canonical chain are skipped, so an
+/// interrupted import can be run again. A genesis block is only accepted into an empty
+/// store, and only if its state root is that of the stored state: a chain whose genesis
+/// allocates accounts needs the store initialized with [`init_genesis`] first.
+pub fn import_blocks(store: &Store, config: &ChainConfig, path: &Path) -> anyhow::Result<u64> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut imported = 0;
+    let mut head = None;
+    while let Some(encoded) = read_rlp_item(&mut file)? {
+        let block =
+            Block::decode(&encoded).map_err(|err| anyhow!("{err} in block {}", imported + 1))?;
+        let (number, hash) = (block.header.number, block.hash());
+        if store.get_canonical_block_hash(number)? == Some(hash) {
+            continue;
+        }
+        import_block(store, config, block)
+            .with_context(|| format!("block {number} ({hash:#x})"))?;
+        head = Some(number);
+        imported += 1;
+        if number % IMPORT_LOG_INTERVAL == 0 {
+            info!("Imported up to block {number}");
+        }
+    }
+    if let Some(number) = head {
+        info!(
+            "Imported {imported} blocks from {}, head is now {number}",
+            path.display()
+        );
+    }
+    Ok(imported)
+}
+
+/// Stores the genesis block of `genesis`, with its alloc as the state, into an empty
+/// store, or checks that the chain in the store starts with that block.
+pub fn init_genesis(store: &Store, genesis: &Genesis) -> anyhow::Result<()> {
+    let block = genesis.block()?;
+    let hash = block.hash();
+    match store.get_canonical_block_hash(0)? {
+        Some(stored) if stored == hash => Ok(()),
+        Some(stored) => bail!("the datadir holds a chain with genesis {stored:#x}, not {hash:#x}"),
+        None => {
+            store.add_head_block(block, Vec::new(), &genesis.alloc.to_state_diff())?;
+            info!("Initialized the datadir with genesis {hash:#x}");
+            Ok(())
+        }
+    }
+}
+
+/// Validates and executes `block` on the head state, then stores it with its state and
+/// receipts as the new head.
+fn import_block(store: &Store, config: &ChainConfig, block: Block) -> anyhow::Result<()> {
+    let header = &block.header;
+    let result = if header.number == 0 {
+        if store.get_latest_block_number()?.is_some() {
+            bail!("genesis does not match the stored chain");
+        }
+        if store.state_root(&StateDiff::new())? != header.state_root {
+            bail!("genesis state root does not match the stored state, the chain's genesis file is needed to initialize the datadir");
+        }
+        block.validate_body()?;
+        BlockResult::default()
+    } else {
+        let latest = store
+            .get_latest_block_number()?
+            .ok_or_else(|| anyhow!("parent is unknown"))?;
+        if store.get_canonical_block_hash(latest)? != Some(header.parent_hash) {
+            bail!("block does not extend the head, side chains are not supported");
+        }
+        let parent = store
+            .get_block_header_by_hash(header.parent_hash)?
+            .ok_or_else(|| anyhow!("parent is unknown"))?;
+        let fork = config.fork(header.number, header.timestamp);
+        header.validate_against_parent(&parent)?;
+        validate_header(header, &parent, fork)?;
+        block.validate_body()?;
+
+        let result = execute_block(store, config, &block)?;
+        result.validate(header)?;
+        if store.state_root(&result.state_diff)? != header.state_root {
+            return Err(InvalidBlockError::StateRootMismatch.into());
+        }
+        result
+    };
+    store.add_head_block(block, result.receipts, &result.state_diff)
+}
+
+/// Reads the next top-level RLP item, `None` at the end of the file.
+fn read_rlp_item(reader: &mut impl Read) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut prefix = [0; 1];
+    match reader.read_exact(&mut prefix) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+    let mut item = prefix.to_vec();
+    let payload_len = match prefix[0] {
+        0x00..=0x7f => 0,
+        prefix @ 0x80..=0xb7 => (prefix - 0x80) as usize,
+        prefix @ 0xc0..=0xf7 => (prefix - 0xc0) as usize,
+        prefix => {
+            let len_of_len = (prefix - if prefix >= 0xf8 { 0xf7 } else { 0xb7 }) as usize;
+            let mut len = [0; 8];
+            reader.read_exact(&mut len[8 - len_of_len..])?;
+            item.extend_from_slice(&len[8 - len_of_len..]);
+            usize::try_from(u64::from_be_bytes(len))?
+        }
+    };
+    if payload_len > MAX_BLOCK_SIZE {
+        bail!("RLP item of {payload_len} bytes is too large");
+    }
+    let header_len = item.len();
+    item.resize(header_len + payload_len, 0);
+    reader
+        .read_exact(&mut item[header_len..])
+        .context("file ends in the middle of a block")?;
+    Ok(Some(item))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use ethereum_types::{Address, Bloom, U256};
+    use evm::Fork;
+    use reec_core::{
+        crypto,
+        trie::EMPTY_TRIE_ROOT,
+        types::{
+            compute_receipts_root, BlockBody, BlockHeader, LegacyTransaction, Transaction, TxKind,
+        },
+    };
+
+    use super::*;
+
+    const KEY: [u8; 32] = [1; 32];
+
+    fn config() -> ChainConfig {
+        ChainConfig::from_genesis(1, Fork::Homestead)
+    }
+
+    /// Builds a chain whose miner spends the reward of each block in the next one, with
+    /// every block executed and stored in `store`.
+    fn build_chain(store: &Store, length: u64) {
+        let miner = crypto::secret_key_to_address(&KEY).unwrap();
+        let mut parent = BlockHeader {
+            gas_limit: 30_000_000,
+            ommers_hash: BlockBody::default().compute_ommers_hash(),
+            transactions_root: BlockBody::default().compute_transactions_root(),
+            state_root: EMPTY_TRIE_ROOT,
+            ..Default::default()
+        };
+        let hash = parent.compute_block_hash();
+        store
+            .add_block(parent.clone(), BlockBody::default())
+            .unwrap();
+        store.add_receipts(hash, Vec::new()).unwrap();
+        store.set_canonical_head(hash).unwrap();
+        for number in 1..length {
+            let transactions = (number > 1)
+                .then(|| {
+                    let mut tx = Transaction::LegacyTransaction(LegacyTransaction {
+                        nonce: number - 2,
+                        gas_price: U256::one(),
+                        gas: 100_000,
+                        to: TxKind::Call(Address::repeat_byte(0xee)),
+                        value: U256::from(number),
+                        data: Bytes::from(vec![0xab; 100]),
+                        ..Default::default()
+                    });
+                    tx.sign(&KEY, None).unwrap();
+                    tx
+                })
+                .into_iter()
+                .collect();
+            let body = BlockBody {
+                transactions,
+                ..Default::default()
+            };
+            let mut block = Block {
+                header: BlockHeader {
+                    parent_hash: parent.compute_block_hash(),
+                    ommers_hash: body.compute_ommers_hash(),
+                    transactions_root: body.compute_transactions_root(),
+                    number,
+                    coinbase: miner,
+                    gas_limit: 30_000_000,
+                    timestamp: number * 12,
+                    ..Default::default()
+                },
+                body,
+            };
+            let result = execute_block(store, &config(), &block).unwrap();
+            let mut bloom = Bloom::zero();
+            for receipt in &result.receipts {
+                bloom.accrue_bloom(&receipt.bloom);
+            }
+            let header = &mut block.header;
+            header.gas_used = result.gas_used;
+            header.receipts_root = compute_receipts_root(&result.receipts);
+            header.logs_bloom = bloom;
+            header.state_root = store.state_root(&result.state_diff).unwrap();
+
+            let hash = block.hash();
+            store.write_state(number, &result.state_diff).unwrap();
+            store.add_block(block.header.clone(), block.body).unwrap();
+            store.add_receipts(hash, result.receipts).unwrap();
+            store.set_canonical_head(hash).unwrap();
+            parent = block.header;
+        }
+    }
+
+    #[test]
+    fn exports_and_imports_blocks() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source = Store::new(source_dir.path()).unwrap();
+        build_chain(&source, 10);
+        let file = source_dir.path().join("chain.rlp");
+
+        let target_dir = tempfile::tempdir().unwrap();
+        let target = Store::new(target_dir.path()).unwrap();
+        assert_eq!(export_blocks(&source, &file, 0, 5).unwrap(), 6);
+        assert_eq!(import_blocks(&target, &config(), &file).unwrap(), 6);
+        assert_eq!(export_blocks(&source, &file, 3, 9).unwrap(), 7);
+        // Blocks 3 to 5 are already there
+        assert_eq!(import_blocks(&target, &config(), &file).unwrap(), 4);
+
+        assert_eq!(target.get_latest_block_number().unwrap(), Some(9));
+        for number in 0..10 {
+            assert_eq!(
+                target.get_canonical_block_hash(number).unwrap(),
+                source.get_canonical_block_hash(number).unwrap()
+            );
+            assert_eq!(
+                target.get_block_body(number).unwrap(),
+                source.get_block_body(number).unwrap()
+            );
+            assert_eq!(
+                target.get_receipts(number).unwrap(),
+                source.get_receipts(number).unwrap()
+            );
+        }
+        let miner = crypto::secret_key_to_address(&KEY).unwrap();
+        assert_eq!(
+            target.get_account_info(miner).unwrap(),
+            source.get_account_info(miner).unwrap()
+        );
+    }
+
+    #[test]
+    fn imported_datadir_passes_the_check() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source = Store::new(source_dir.path()).unwrap();
+        build_chain(&source, 10);
+        let file = source_dir.path().join("chain.rlp");
+        export_blocks(&source, &file, 0, 9).unwrap();
+
+        let target_dir = tempfile::tempdir().unwrap();
+        let target = Store::new(target_dir.path()).unwrap();
+        import_blocks(&target, &config(), &file).unwrap();
+        let report = target.check().unwrap();
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!(report.canonical_blocks, 10);
+        // A branch over the miner and the receiver of its transfers
+        assert_eq!((report.trie_nodes, report.unreachable_trie_nodes), (3, 0));
+    }
+
+    #[test]
+    fn imports_a_chain_from_its_genesis_file() {
+        let testdata = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata"));
+        let genesis = Genesis::from_file(&testdata.join("genesis.json")).unwrap();
+        let config = genesis.chain_config().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let err = import_blocks(&store, &config, &testdata.join("chain.rlp")).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("genesis file is needed"),
+            "{err:#}"
+        );
+
+        init_genesis(&store, &genesis).unwrap();
+        // Initializing again only checks the genesis block
+        init_genesis(&store, &genesis).unwrap();
+        assert_eq!(
+            import_blocks(&store, &config, &testdata.join("chain.rlp")).unwrap(),
+            2
+        );
+        assert_eq!(store.get_latest_block_number().unwrap(), Some(2));
+        assert!(store.check().unwrap().is_ok());
+
+        let mut other = genesis.clone();
+        other.timestamp = Some(1);
+        let err = init_genesis(&store, &other).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("holds a chain with genesis"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_blocks() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source = Store::new(source_dir.path()).unwrap();
+        build_chain(&source, 3);
+        let file = source_dir.path().join("chain.rlp");
+        let target_dir = tempfile::tempdir().unwrap();
+        let target = Store::new(target_dir.path()).unwrap();
+
+        // Missing parent
+        export_blocks(&source, &file, 2, 2).unwrap();
+        assert!(import_blocks(&target, &config(), &file).is_err());
+
+        // Body that does not match its header
+        export_blocks(&source, &file, 0, 2).unwrap();
+        let mut blocks = std::fs::read(&file).unwrap();
+        let data = blocks.iter().rposition(|byte| *byte == 0xab).unwrap();
+        blocks[data] ^= 1;
+        std::fs::write(&file, &blocks).unwrap();
+        let err = import_blocks(&target, &config(), &file).unwrap_err();
+        assert!(format!("{err:#}").contains("transactions root"), "{err:#}");
+        // The blocks before it were imported
+        assert_eq!(target.get_latest_block_number().unwrap(), Some(1));
+
+        // Truncated file
+        std::fs::write(&file, &blocks[..blocks.len() - 1]).unwrap();
+        assert!(import_blocks(&target, &config(), &file).is_err());
+    }
+
+    #[test]
+    fn rejects_blocks_not_matching_their_execution() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source = Store::new(source_dir.path()).unwrap();
+        build_chain(&source, 3);
+        let file = source_dir.path().join("chain.rlp");
+        let block = Block {
+            header: source.get_block_header(2).unwrap().unwrap(),
+            body: source.get_block_body(2).unwrap().unwrap(),
+        };
+
+        let target_dir = tempfile::tempdir().unwrap();
+        let target = Store::new(target_dir.path()).unwrap();
+        export_blocks(&source, &file, 0, 1).unwrap();
+        import_blocks(&target, &config(), &file).unwrap();
+        let state_root = target.state_root(&StateDiff::new()).unwrap();
+
+        let mut wrong_state_root = block.clone();
+        wrong_state_root.header.state_root = EMPTY_TRIE_ROOT;
+        let mut wrong_gas_used = block.clone();
+        wrong_gas_used.header.gas_used += 1;
+        let tampered = [
+            (wrong_state_root, InvalidBlockError::StateRootMismatch),
+            (wrong_gas_used, InvalidBlockError::GasUsedMismatch),
+        ];
+        for (tampered, expected) in tampered {
+            std::fs::write(&file, tampered.encode_to_vec()).unwrap();
+            let err = import_blocks(&target, &config(), &file).unwrap_err();
+            assert_eq!(err.downcast_ref::<InvalidBlockError>(), Some(&expected));
+            // Nothing of the block was kept
+            assert_eq!(target.get_latest_block_number().unwrap(), Some(1));
+            assert_eq!(target.state_root(&StateDiff::new()).unwrap(), state_root);
+        }
+
+        std::fs::write(&file, block.encode_to_vec()).unwrap();
+        assert_eq!(import_blocks(&target, &config(), &file).unwrap(), 1);
+    }
+}
@@ -32,4 +32,288 @@ pub fn cli() -> Command {
                 .value_name("PORT")
                 .action(ArgAction::Set)
         )
-}
\ No newline at end of file
+        .arg(
+            Arg::new("datadir")
+                .long("datadir")
+                .default_value("reec-data")
+                .value_name("DIR")
+                .help("Directory holding the chain database and the ancient store")
+                .global(true)
+                .action(ArgAction::Set)
+        )
+        .arg(
+            Arg::new("genesis")
+                .long("genesis")
+                .value_name("FILE")
+                .help("Genesis file of the chain, in geth's format, initializing an empty datadir with it. Mainnet by default")
+                .global(true)
+                .action(ArgAction::Set)
+        )
+        .arg(
+            Arg::new("prune")
+                .long("prune")
+                .default_value("full")
+                .value_name("MODE")
+                .value_parser(["archive", "full", "minimal"])
+                .help("How much history to keep: archive keeps everything, full only recent state changes, minimal also drops bodies and receipts older than the EIP-4444 window")
+                .action(ArgAction::Set)
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export canonical blocks to a file of concatenated RLP blocks")
+                .arg(Arg::new("file").required(true).value_name("FILE"))
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .default_value("0")
+                        .value_name("BLOCK")
+                        .value_parser(clap::value_parser!(u64))
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .value_name("BLOCK")
+                        .value_parser(clap::value_parser!(u64))
+                        .help("Last block to export, the latest block by default")
+                        .action(ArgAction::Set)
+                )
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Execute and import blocks from a file of concatenated RLP blocks")
+                .arg(Arg::new("file").required(true).value_name("FILE"))
+        )
+        .subcommand(
+            Command::new("import-era")
+                .about("Import pre-merge history from Era1 archives into the ancient store")
+                .arg(
+                    Arg::new("files")
+                        .required(true)
+                        .num_args(1..)
+                        .value_name("FILE")
+                        .help("Era1 files, imported in file name order")
+                )
+                .arg(
+                    Arg::new("epoch-roots")
+                        .long("epoch-roots")
+                        .required(true)
+                        .value_name("FILE")
+                        .help("Trusted accumulator roots of the pre-merge epochs, one per line in epoch order")
+                        .action(ArgAction::Set)
+                )
+        )
+        .subcommand(
+            Command::new("db")
+                .about("Inspect and repair the chain database")
+                .subcommand_required(true)
+                .subcommand(Command::new("stats").about("Show the entry count and size of every table"))
+                .subcommand(
+                    Command::new("get")
+                        .about("Print the decoded values stored under a key")
+                        .arg(Arg::new("table").required(true).value_name("TABLE"))
+                        .arg(
+                            Arg::new("key")
+                                .required(true)
+                                .value_name("KEY")
+                                .help("Encoded key as 0x-prefixed hex, or a decimal block number")
+                        )
+                )
+                .subcommand(
+                    Command::new("check")
+                        .about("Verify the canonical chain, the ancient store and the head state trie")
+                )
+                .subcommand(
+                    Command::new("drop-table")
+                        .about("Delete every entry of a table")
+                        .arg(Arg::new("table").required(true).value_name("TABLE"))
+                        .arg(
+                            Arg::new("yes")
+                                .long("yes")
+                                .help("Do not ask for confirmation")
+                                .action(ArgAction::SetTrue)
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("evm")
+                .about("Run the EVM on its own, for testing it against other clients")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("run")
+                        .about("Run bytecode as the code of the receiver and print its output")
+                        .arg(
+                            Arg::new("code")
+                                .value_name("CODE")
+                                .required_unless_present("codefile")
+                                .help("Bytecode as hex")
+                        )
+                        .arg(
+                            Arg::new("codefile")
+                                .long("codefile")
+                                .value_name("FILE")
+                                .conflicts_with("code")
+                                .help("File holding the bytecode as hex")
+                                .action(ArgAction::Set)
+                        )
+                        .arg(
+                            Arg::new("input")
+                                .long("input")
+                                .value_name("HEX")
+                                .help("Call data, or the input of EOF init code with --create")
+                                .action(ArgAction::Set)
+                        )
+                        .arg(
+                            Arg::new("gas")
+                                .long("gas")
+                                .default_value("10000000")
+                                .value_name("GAS")
+                                .value_parser(clap::value_parser!(u64))
+                                .action(ArgAction::Set)
+                        )
+                        .arg(
+                            Arg::new("price")
+                                .long("price")
+                                .default_value("0")
+                                .value_name("WEI")
+                                .help("Gas price seen by GASPRICE")
+                                .action(ArgAction::Set)
+                        )
+                        .arg(
+                            Arg::new("value")
+                                .long("value")
+                                .default_value("0")
+                                .value_name("WEI")
+                                .action(ArgAction::Set)
+                        )
+                        .arg(
+                            Arg::new("sender")
+                                .long("sender")
+                                .value_name("ADDRESS")
+                                .help("Caller and origin, the address spelling \"sender\" by default")
+                                .action(ArgAction::Set)
+                        )
+                        .arg(
+                            Arg::new("receiver")
+                                .long("receiver")
+                                .value_name("ADDRESS")
+                                .help("Account the code runs as, the address spelling \"receiver\" by default")
+                                .action(ArgAction::Set)
+                        )
+                        .arg(
+                            Arg::new("prestate")
+                                .long("prestate")
+                                .value_name("FILE")
+                                .help("Genesis file whose alloc is the state to run on")
+                                .action(ArgAction::Set)
+                        )
+                        .arg(
+                            Arg::new("create")
+                                .long("create")
+                                .help("Run the code as init code of a contract created by the sender")
+                                .action(ArgAction::SetTrue)
+                        )
+                        .arg(
+                            Arg::new("fork")
+                                .long("fork")
+                                .default_value("Prague")
+                                .value_name("FORK")
+                                .action(ArgAction::Set)
+                        )
+                        .arg(
+                            Arg::new("json")
+                                .long("json")
+                                .help("Write an EIP-3155 trace to stderr")
+                                .action(ArgAction::SetTrue)
+                        )
+                        .arg(
+                            Arg::new("dump")
+                                .long("dump")
+                                .help("Print the state after the run")
+                                .action(ArgAction::SetTrue)
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("t8n")
+                .about("Run a block as the transition tool of execution-spec-tests, with the interface of geth's evm t8n")
+                .arg(
+                    Arg::new("input.alloc")
+                        .long("input.alloc")
+                        .default_value("alloc.json")
+                        .value_name("FILE")
+                        .help("State before the block, \"stdin\" to read it from the object on stdin")
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("input.env")
+                        .long("input.env")
+                        .default_value("env.json")
+                        .value_name("FILE")
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("input.txs")
+                        .long("input.txs")
+                        .default_value("txs.json")
+                        .value_name("FILE")
+                        .help("Transactions as a JSON list, or as the hex of their RLP list in a JSON string")
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("output.basedir")
+                        .long("output.basedir")
+                        .default_value("")
+                        .value_name("DIR")
+                        .help("Directory the output files are written to")
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("output.result")
+                        .long("output.result")
+                        .default_value("result.json")
+                        .value_name("FILE")
+                        .help("Where to write the result, \"stdout\" or \"stderr\" to put it in the object written there")
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("output.alloc")
+                        .long("output.alloc")
+                        .default_value("alloc.json")
+                        .value_name("FILE")
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("output.body")
+                        .long("output.body")
+                        .value_name("FILE")
+                        .help("Where to write the RLP of the included transactions, not written by default")
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("state.fork")
+                        .long("state.fork")
+                        .default_value("Prague")
+                        .value_name("FORK")
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("state.chainid")
+                        .long("state.chainid")
+                        .default_value("1")
+                        .value_name("ID")
+                        .value_parser(clap::value_parser!(u64))
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("state.reward")
+                        .long("state.reward")
+                        .default_value("0")
+                        .value_name("WEI")
+                        .allow_negative_numbers(true)
+                        .value_parser(clap::value_parser!(i64))
+                        .help("Mining reward, -1 to leave the miner and ommers untouched")
+                        .action(ArgAction::Set)
+                )
+        )
+}
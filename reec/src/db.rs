@@ -0,0 +1,100 @@
+//! The `reec db` commands, printing to stdout so their output can be piped.
+
+use std::io::{self, BufRead, Write};
+
+use anyhow::{bail, Context};
+use clap::ArgMatches;
+use storage::Store;
+
+pub fn run(store: &Store, args: &ArgMatches) -> anyhow::Result<()> {
+    match args.subcommand() {
+        Some(("stats", _)) => {
+            let stats = store.table_stats()?;
+            println!("{:<24} {:>14} {:>12}", "TABLE", "ENTRIES", "SIZE");
+            for stat in &stats {
+                println!(
+                    "{:<24} {:>14} {:>12}",
+                    stat.name,
+                    stat.entries,
+                    human_size(stat.size)
+                );
+            }
+            println!(
+                "{:<24} {:>14} {:>12}",
+                "total",
+                "",
+                human_size(stats.iter().map(|stat| stat.size).sum())
+            );
+        }
+        Some(("get", args)) => {
+            let table = args.get_one::<String>("table").expect("table is required");
+            let key = parse_key(args.get_one::<String>("key").expect("key is required"))?;
+            let entries = store.get_entries(table, &key)?;
+            if entries.is_empty() {
+                bail!("no entry for key 0x{} in {table}", hex::encode(&key));
+            }
+            for entry in entries {
+                println!("{entry}");
+            }
+        }
+        Some(("check", _)) => {
+            let report = store.check()?;
+            for problem in &report.problems {
+                println!("{problem}");
+            }
+            println!(
+                "Checked {} canonical blocks and {} trie nodes, {} stored trie nodes are unreachable",
+                report.canonical_blocks, report.trie_nodes, report.unreachable_trie_nodes
+            );
+            if !report.is_ok() {
+                bail!("found {} problems", report.problems.len());
+            }
+        }
+        Some(("drop-table", args)) => {
+            let table = args.get_one::<String>("table").expect("table is required");
+            if !args.get_flag("yes") && !confirm(&format!("Delete every entry of {table}?"))? {
+                return Ok(());
+            }
+            let entries = store.drop_table(table)?;
+            println!("Deleted {entries} entries from {table}");
+        }
+        _ => unreachable!("clap requires a db subcommand"),
+    }
+    Ok(())
+}
+
+/// Parses a table key given as 0x-prefixed hex, or as a decimal block number for the
+/// tables keyed by one.
+fn parse_key(key: &str) -> anyhow::Result<Vec<u8>> {
+    match key.strip_prefix("0x") {
+        Some(hex) => hex::decode(hex).context("invalid hex key"),
+        None => Ok(key
+            .parse::<u64>()
+            .context("invalid key")?
+            .to_be_bytes()
+            .to_vec()),
+    }
+}
+
+fn confirm(question: &str) -> anyhow::Result<bool> {
+    print!("{question} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
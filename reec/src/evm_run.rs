@@ -0,0 +1,193 @@
+//! `reec evm run`, which runs bytecode outside of any block or transaction, like geth's
+//! `evm run`, to compare the EVM with other clients' on the same code.
+
+use std::{fs, io};
+
+use anyhow::{anyhow, Context};
+use bytes::Bytes;
+use clap::ArgMatches;
+use ef_tests::Alloc;
+use ethereum_types::{Address, U256};
+use evm::{
+    execute_message_with_tracer,
+    interpreter::{CallInputs, CallScheme, CreateInputs, CreateScheme},
+    Action, BlockEnv, Eip3155Tracer, Env, ExecutionResult, Fork, JournaledState, NoopTracer, TxEnv,
+};
+use serde_json::Value;
+
+pub fn run(args: &ArgMatches) -> anyhow::Result<()> {
+    let (result, post) = execute(args)?;
+    println!("0x{}", hex::encode(&result.output));
+    if !result.is_success() {
+        eprintln!("error: {}", result.status);
+    }
+    if args.get_flag("dump") {
+        println!("{}", serde_json::to_string_pretty(&post)?);
+    }
+    Ok(())
+}
+
+/// Runs the code as `args` describe, returning the result and the state it left.
+fn execute(args: &ArgMatches) -> anyhow::Result<(ExecutionResult, Alloc)> {
+    let code = match (
+        args.get_one::<String>("code"),
+        args.get_one::<String>("codefile"),
+    ) {
+        (Some(code), _) => parse_hex(code)?,
+        (None, Some(path)) => {
+            parse_hex(&fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?)?
+        }
+        (None, None) => unreachable!("clap requires code or a code file"),
+    };
+    let input = args
+        .get_one::<String>("input")
+        .map(|input| parse_hex(input))
+        .transpose()?
+        .unwrap_or_default();
+    let gas_limit = *args.get_one::<u64>("gas").expect("gas has a default");
+    let gas_price = parse_u256(
+        args.get_one::<String>("price")
+            .expect("price has a default"),
+    )?;
+    let value = parse_u256(
+        args.get_one::<String>("value")
+            .expect("value has a default"),
+    )?;
+    let sender = parse_address(args.get_one::<String>("sender"), "sender")?;
+    let receiver = parse_address(args.get_one::<String>("receiver"), "receiver")?;
+    let fork: Fork = args
+        .get_one::<String>("fork")
+        .expect("fork has a default")
+        .parse()?;
+
+    let mut alloc = match args.get_one::<String>("prestate") {
+        Some(path) => {
+            let genesis =
+                fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+            let mut genesis: Value = serde_json::from_str(&genesis)
+                .with_context(|| format!("failed to parse {path}"))?;
+            serde_json::from_value(genesis["alloc"].take())
+                .with_context(|| format!("invalid alloc in {path}"))?
+        }
+        None => Alloc::default(),
+    };
+    let action = if args.get_flag("create") {
+        Action::Create(Box::new(CreateInputs {
+            scheme: CreateScheme::Create,
+            caller: sender,
+            value,
+            init_code: code,
+            input,
+            gas_limit,
+            depth: 0,
+        }))
+    } else {
+        alloc.0.entry(receiver).or_default().code = code;
+        Action::Call(Box::new(CallInputs {
+            scheme: CallScheme::Call,
+            target: receiver,
+            code_address: receiver,
+            caller: sender,
+            value,
+            transfer: true,
+            input,
+            gas_limit,
+            is_static: false,
+            depth: 0,
+        }))
+    };
+
+    let block = BlockEnv {
+        gas_limit,
+        ..Default::default()
+    };
+    let tx = TxEnv {
+        origin: sender,
+        gas_price,
+        ..Default::default()
+    };
+    let mut state = JournaledState::new(
+        alloc.to_db(),
+        Env {
+            fork,
+            chain_id: 1,
+            block,
+            tx,
+        },
+    );
+    let result = if args.get_flag("json") {
+        let mut tracer = Eip3155Tracer::new(io::stderr());
+        let result = execute_message_with_tracer(&mut state, action, &mut tracer)?;
+        tracer.finish()?;
+        result
+    } else {
+        execute_message_with_tracer(&mut state, action, &mut NoopTracer)?
+    };
+    alloc.apply(&state.state_diff());
+    Ok((result, alloc))
+}
+
+fn parse_hex(value: &str) -> anyhow::Result<Bytes> {
+    let value = value.trim();
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    Ok(hex::decode(digits)
+        .with_context(|| format!("invalid hex {value}"))?
+        .into())
+}
+
+fn parse_u256(value: &str) -> anyhow::Result<U256> {
+    let number = match value.strip_prefix("0x") {
+        Some(digits) => U256::from_str_radix(digits, 16).ok(),
+        None => U256::from_dec_str(value).ok(),
+    };
+    number.ok_or_else(|| anyhow!("invalid number {value}"))
+}
+
+/// The given address, or by default the one whose bytes spell `name`, as geth picks.
+fn parse_address(value: Option<&String>, name: &str) -> anyhow::Result<Address> {
+    let Some(value) = value else {
+        let mut address = Address::zero();
+        address.0[20 - name.len()..].copy_from_slice(name.as_bytes());
+        return Ok(address);
+    };
+    value
+        .parse()
+        .map_err(|_| anyhow!("invalid address {value}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli;
+
+    fn execute_with(args: &[&str]) -> (ExecutionResult, Alloc) {
+        let matches = cli::cli().get_matches_from(["reec", "evm", "run"].iter().chain(args));
+        let (_, evm) = matches.subcommand().unwrap();
+        execute(evm.subcommand_matches("run").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn runs_code_as_the_receiver() {
+        // SSTORE(0, CALLDATALOAD(0)) and returns 32 bytes of memory holding CALLER
+        let (result, post) = execute_with(&["0x5f355f55335f5260205ff3", "--input", "0x2a"]);
+        assert!(result.is_success());
+        let sender = parse_address(None, "sender").unwrap();
+        assert_eq!(&result.output[12..], sender.as_bytes());
+        let receiver = &post.0[&parse_address(None, "receiver").unwrap()];
+        assert_eq!(
+            receiver.storage.values().next(),
+            Some(&(U256::from(0x2a) << 248))
+        );
+        // A cold SSTORE setting a zero slot, and 22 gas for the rest with the memory word
+        assert_eq!(result.gas_used, 22_122);
+    }
+
+    #[test]
+    fn creates_contracts() {
+        // Returns one byte of zeroed memory as the code to deploy
+        let (result, post) = execute_with(&["0x60015ff3", "--create", "--fork", "Cancun"]);
+        let address = result.contract_address.unwrap();
+        assert_eq!(post.0[&address].code, Bytes::from_static(&[0]));
+        assert_eq!(post.0[&parse_address(None, "sender").unwrap()].nonce, 1);
+    }
+}
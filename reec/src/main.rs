@@ -1,17 +1,161 @@
+use std::path::Path;
+
+use ef_tests::Genesis;
+use evm::ChainConfig;
+use storage::{
+    era::EpochRoots,
+    pruner::{PruneMode, Pruner},
+    Store,
+};
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 
+mod chain_file;
 mod cli;
+mod db;
+mod evm_run;
+mod t8n;
 
 fn main() {
-    let subscriber = FmtSubscriber::builder().with_max_level(Level::DEBUG).finish();
+    let subscriber = FmtSubscriber::builder()
+        .with_max_level(Level::DEBUG)
+        .finish();
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 
     let matches = cli::cli().get_matches();
-    let http_addr = matches.get_one::<String>("http.addr").expect("http.addr is required");
-    let http_port = matches.get_one::<String>("http.port").expect("http.port is required");
-    let authrpc_addr = matches.get_one::<String>("authrpc.addr").expect("authrpc.addr is required");
-    let authrpc_port = matches.get_one::<String>("authrpc.port").expect("authrpc.port is required");
+    let datadir = matches
+        .get_one::<String>("datadir")
+        .expect("datadir is required");
+
+    match matches.subcommand() {
+        Some(("export", args)) => {
+            let store = Store::new(datadir).expect("failed to open the database");
+            let file = args.get_one::<String>("file").expect("file is required");
+            let from = *args.get_one::<u64>("from").expect("from has a default");
+            let to = match args.get_one::<u64>("to") {
+                Some(to) => *to,
+                None => store
+                    .get_latest_block_number()
+                    .expect("failed to read the latest block")
+                    .expect("the database holds no blocks"),
+            };
+            if let Err(err) = chain_file::export_blocks(&store, Path::new(file), from, to) {
+                tracing::error!("Export failed: {err:#}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(("import", args)) => {
+            let store = Store::new(datadir).expect("failed to open the database");
+            let file = args.get_one::<String>("file").expect("file is required");
+            let result = chain_config(&matches, &store)
+                .and_then(|config| chain_file::import_blocks(&store, &config, Path::new(file)));
+            if let Err(err) = result {
+                tracing::error!("Import failed: {err:#}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(("import-era", args)) => {
+            let store = Store::new(datadir).expect("failed to open the database");
+            let roots = args
+                .get_one::<String>("epoch-roots")
+                .expect("epoch-roots is required");
+            let trusted = match EpochRoots::from_file(Path::new(roots)) {
+                Ok(trusted) => trusted,
+                Err(err) => {
+                    tracing::error!("{err:#}");
+                    std::process::exit(1);
+                }
+            };
+            let mut files: Vec<&String> = args
+                .get_many::<String>("files")
+                .expect("files are required")
+                .collect();
+            files.sort();
+            for file in files {
+                if let Err(err) = store.import_era1(Path::new(file), &trusted) {
+                    tracing::error!("Import of {file} failed: {err:#}");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(("evm", args)) => {
+            let Some(("run", args)) = args.subcommand() else {
+                unreachable!("clap requires the evm subcommand");
+            };
+            if let Err(err) = evm_run::run(args) {
+                eprintln!("{err:#}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(("t8n", args)) => {
+            if let Err(err) = t8n::run(args) {
+                eprintln!("{err:#}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(("db", args)) => {
+            let store = Store::new(datadir).expect("failed to open the database");
+            if let Err(err) = db::run(&store, args) {
+                tracing::error!("{err:#}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    let http_addr = matches
+        .get_one::<String>("http.addr")
+        .expect("http.addr is required");
+    let http_port = matches
+        .get_one::<String>("http.port")
+        .expect("http.port is required");
+    let authrpc_addr = matches
+        .get_one::<String>("authrpc.addr")
+        .expect("authrpc.addr is required");
+    let authrpc_port = matches
+        .get_one::<String>("authrpc.port")
+        .expect("authrpc.port is required");
+
+    let prune_mode: PruneMode = matches
+        .get_one::<String>("prune")
+        .expect("prune is required")
+        .parse()
+        .expect("prune mode is validated by clap");
+
+    let store = Store::new(datadir).expect("failed to open the database");
+    store
+        .set_prune_mode(prune_mode)
+        .expect("incompatible prune mode");
+    let chain_config = match chain_config(&matches, &store) {
+        Ok(config) => config,
+        Err(err) => {
+            tracing::error!("{err:#}");
+            std::process::exit(1);
+        }
+    };
+    let pruner = Pruner::new(store.clone(), prune_mode).spawn();
+
+    let context = rpc::RpcApiContext {
+        store,
+        chain_config,
+    };
+    rpc::start_api(http_addr, http_port, authrpc_addr, authrpc_port, context);
+    pruner.stop();
+}
 
-    rpc::start_api(http_addr, http_port, authrpc_addr, authrpc_port);
+/// The chain of the `--genesis` file, whose genesis block the datadir is initialized
+/// with or checked to hold, or mainnet without one.
+fn chain_config(matches: &clap::ArgMatches, store: &Store) -> anyhow::Result<ChainConfig> {
+    let Some(file) = matches.get_one::<String>("genesis") else {
+        return Ok(ChainConfig::mainnet());
+    };
+    let genesis = Genesis::from_file(Path::new(file))?;
+    chain_file::init_genesis(store, &genesis)?;
+    genesis.chain_config()
 }
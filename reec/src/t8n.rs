@@ -0,0 +1,185 @@
+//! `reec t8n`, the transition tool of execution-spec-tests, with the flags and files of
+//! geth's `evm t8n` so the fillers can drive it like geth.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader},
+    path::Path,
+};
+
+use anyhow::Context;
+use clap::ArgMatches;
+use ef_tests::t8n::{transition, HexBytes, T8nInput, T8nOutput};
+use ethereum_types::U256;
+use evm::Fork;
+use serde_json::{Map, Value};
+
+pub fn run(args: &ArgMatches) -> anyhow::Result<()> {
+    let fork: Fork = args
+        .get_one::<String>("state.fork")
+        .expect("state.fork has a default")
+        .parse()?;
+    let chain_id = *args
+        .get_one::<u64>("state.chainid")
+        .expect("state.chainid has a default");
+    let reward = match *args
+        .get_one::<i64>("state.reward")
+        .expect("state.reward has a default")
+    {
+        reward if reward < 0 => None,
+        reward => Some(U256::from(reward)),
+    };
+    let output = transition(&read_input(args)?, fork, chain_id, reward)?;
+    write_output(args, output)
+}
+
+/// Reads the inputs from their files, or from the single object on stdin holding the
+/// ones given as `stdin`.
+fn read_input(args: &ArgMatches) -> anyhow::Result<T8nInput> {
+    let mut stdin: Option<Value> = None;
+    let mut input = Map::new();
+    for name in ["alloc", "env", "txs"] {
+        let source = args
+            .get_one::<String>(&format!("input.{name}"))
+            .expect("inputs have defaults");
+        let value = match source.as_str() {
+            "stdin" => {
+                if stdin.is_none() {
+                    let value = serde_json::from_reader(io::stdin().lock())
+                        .context("failed to parse stdin")?;
+                    stdin = Some(value);
+                }
+                stdin
+                    .as_mut()
+                    .and_then(|stdin| stdin.get_mut(name))
+                    .map(Value::take)
+                    .unwrap_or_default()
+            }
+            path => {
+                let file = File::open(path).with_context(|| format!("failed to open {path}"))?;
+                serde_json::from_reader(BufReader::new(file))
+                    .with_context(|| format!("failed to parse {path}"))?
+            }
+        };
+        if !value.is_null() {
+            input.insert(name.to_string(), value);
+        }
+    }
+    serde_json::from_value(Value::Object(input)).context("invalid input")
+}
+
+/// Writes each output to its file under the base directory, or into the object written
+/// to stdout or stderr.
+fn write_output(args: &ArgMatches, output: T8nOutput) -> anyhow::Result<()> {
+    let basedir = Path::new(
+        args.get_one::<String>("output.basedir")
+            .expect("output.basedir has a default"),
+    );
+    let outputs = [
+        ("result", serde_json::to_value(&output.result)?),
+        ("alloc", serde_json::to_value(&output.alloc)?),
+        ("body", serde_json::to_value(HexBytes(output.body))?),
+    ];
+    let mut streams: BTreeMap<&str, Map<String, Value>> = BTreeMap::new();
+    for (name, value) in outputs {
+        let Some(destination) = args.get_one::<String>(&format!("output.{name}")) else {
+            continue;
+        };
+        match destination.as_str() {
+            stream @ ("stdout" | "stderr") => {
+                streams
+                    .entry(stream)
+                    .or_default()
+                    .insert(name.to_string(), value);
+            }
+            file => {
+                let path = basedir.join(file);
+                fs::write(&path, serde_json::to_string_pretty(&value)?)
+                    .with_context(|| format!("failed to write {}", path.display()))?;
+            }
+        }
+    }
+    if let Some(object) = streams.get("stdout") {
+        println!("{}", serde_json::to_string_pretty(object)?);
+    }
+    if let Some(object) = streams.get("stderr") {
+        eprintln!("{}", serde_json::to_string_pretty(object)?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli;
+
+    #[test]
+    fn writes_outputs_to_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        let sender = "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b";
+        let alloc = serde_json::json!({ sender: { "balance": "0x3b9aca00" } });
+        let env = serde_json::json!({
+            "currentCoinbase": "0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba",
+            "currentGasLimit": "0x1c9c380",
+            "currentNumber": "0x1",
+            "currentTimestamp": "0x3e8",
+            "currentBaseFee": "0x7",
+            "currentRandom": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "withdrawals": [],
+        });
+        let txs = serde_json::json!([{
+            "type": "0x2",
+            "chainId": "0x1",
+            "nonce": "0x0",
+            "gas": "0x5208",
+            "maxFeePerGas": "0xa",
+            "maxPriorityFeePerGas": "0x1",
+            "to": "0x0000000000000000000000000000000000001000",
+            "value": "0x1",
+            "input": "0x",
+            "secretKey": "0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8",
+        }]);
+        fs::write(path("alloc.json"), alloc.to_string()).unwrap();
+        fs::write(path("env.json"), env.to_string()).unwrap();
+        fs::write(path("txs.json"), txs.to_string()).unwrap();
+
+        let matches = cli::cli().get_matches_from([
+            "reec",
+            "t8n",
+            "--input.alloc",
+            &path("alloc.json"),
+            "--input.env",
+            &path("env.json"),
+            "--input.txs",
+            &path("txs.json"),
+            "--output.basedir",
+            &path(""),
+            "--output.alloc",
+            "post.json",
+            "--output.body",
+            "body.json",
+            "--state.fork",
+            "Shanghai",
+            "--state.reward",
+            "-1",
+        ]);
+        run(matches.subcommand_matches("t8n").unwrap()).unwrap();
+
+        let read = |name: &str| -> Value {
+            serde_json::from_str(&fs::read_to_string(path(name)).unwrap()).unwrap()
+        };
+        let result = read("result.json");
+        assert_eq!(result["gasUsed"], "0x5208");
+        assert_eq!(result["receipts"][0]["status"], "0x1");
+        assert!(result["withdrawalsRoot"].is_string());
+        let post = read("post.json");
+        assert_eq!(
+            post["0x0000000000000000000000000000000000001000"]["balance"],
+            "0x1"
+        );
+        assert_eq!(post[sender]["nonce"], "0x1");
+        assert!(read("body.json").as_str().unwrap().starts_with("0xf8"));
+    }
+}